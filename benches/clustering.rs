@@ -0,0 +1,34 @@
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use multi_exchange_l3_est::clustering::{ClusterInput, ClusterParams, Clusterer, MiniBatchKMeans};
+use std::hint::black_box;
+
+mod fixture;
+
+fn kmeans(params: &ClusterParams) -> MiniBatchKMeans {
+    MiniBatchKMeans::new(params.num_clusters, params.batch_size, params.max_iter, params.features, params.reseed_empty)
+}
+
+// Cold fits start from fresh centroids; warm ones reuse the previous fit's, as the chart does
+// when it refits every frame
+fn kmeans_fit(c: &mut Criterion) {
+    let book = fixture::load().final_book();
+    let params = ClusterParams::default();
+    let mut group = c.benchmark_group("kmeans_fit");
+    for count in [1_000, 10_000] {
+        let mut side = ClusterInput::new();
+        side.fill(&fixture::orders(&book, count));
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_with_input(BenchmarkId::new("cold", count), &side, |b, side| {
+            b.iter_batched(|| kmeans(&params), |mut kmeans| kmeans.fit(black_box(side)).len(), BatchSize::SmallInput)
+        });
+        let mut warm = kmeans(&params);
+        warm.fit(&side);
+        group.bench_with_input(BenchmarkId::new("warm", count), &side, |b, side| {
+            b.iter(|| warm.fit(black_box(side)).len())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, kmeans_fit);
+criterion_main!(benches);
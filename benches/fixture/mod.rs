@@ -0,0 +1,95 @@
+// Shared by every bench, each of which uses a subset
+#![allow(dead_code)]
+
+use multi_exchange_l3_est::exchanges::{DepthUpdate, OrderBookSnapshot};
+use multi_exchange_l3_est::orderbook::{EstimatedOrder, OrderBook, OrderQueue};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+// Recording used unless L3_BENCH_FIXTURE names another one. It is the output of the order-flow
+// simulator (seed 7, 200 ticks deep, 4000 resting orders) rather than a venue capture, so
+// results are comparable between machines and runs.
+const DEFAULT_FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/benches/fixtures/depth.jsonl");
+
+// One line of a recording
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Recorded {
+    Snapshot(OrderBookSnapshot),
+    Update(DepthUpdate),
+}
+
+// A snapshot and the depth updates that followed it
+pub struct Recording {
+    pub snapshot: OrderBookSnapshot,
+    pub updates: Vec<DepthUpdate>,
+}
+
+impl Recording {
+    // Book right after the snapshot
+    pub fn initial_book(&self) -> OrderBook {
+        let mut book = OrderBook::new();
+        book.apply_snapshot(&self.snapshot);
+        book
+    }
+
+    // Book after the whole recording, with the queues the estimator built along the way
+    pub fn final_book(&self) -> OrderBook {
+        let mut book = self.initial_book();
+        for update in &self.updates {
+            book.apply_update(update);
+        }
+        book
+    }
+}
+
+pub fn load() -> Recording {
+    match std::env::var("L3_BENCH_FIXTURE") {
+        Ok(path) => load_from(Path::new(&path)),
+        Err(_) => load_from(Path::new(DEFAULT_FIXTURE)),
+    }
+}
+
+// Reads a JSON-lines recording: `{"snapshot": {...}}` and `{"update": {...}}` messages in the
+// internal format the connectors produce. Updates before the first snapshot are dropped, as the
+// book would skip them, and a later snapshot starts the recording over.
+pub fn load_from(path: &Path) -> Recording {
+    let text = std::fs::read_to_string(path).unwrap_or_else(|e| panic!("cannot read {}: {e}", path.display()));
+    let mut recording: Option<Recording> = None;
+    for (i, line) in text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+        let message: Recorded = serde_json::from_str(line)
+            .unwrap_or_else(|e| panic!("{}:{}: not a recorded message: {e}", path.display(), i + 1));
+        match message {
+            Recorded::Snapshot(snapshot) => {
+                recording = Some(Recording {
+                    snapshot,
+                    updates: vec![],
+                })
+            }
+            Recorded::Update(update) => {
+                if let Some(recording) = &mut recording {
+                    recording.updates.push(update);
+                }
+            }
+        }
+    }
+    recording.unwrap_or_else(|| panic!("{} has no snapshot", path.display()))
+}
+
+// One book side holding `count` orders: the recorded book's levels refilled round-robin with its
+// estimated orders, so clustering sees a realistic size distribution at any scale
+pub fn orders(book: &OrderBook, count: usize) -> BTreeMap<Decimal, OrderQueue> {
+    let prices: Vec<Decimal> = book.bids.keys().chain(book.asks.keys()).copied().collect();
+    let orders: Vec<&EstimatedOrder> = book.bids.values().chain(book.asks.values()).flatten().collect();
+    assert!(!orders.is_empty(), "the recorded book is empty");
+    let mut levels: BTreeMap<Decimal, Vec<EstimatedOrder>> = BTreeMap::new();
+    for (i, &&order) in orders.iter().cycle().take(count).enumerate() {
+        let mut order = order;
+        order.id = i as u64;
+        order.priority = i as u64;
+        levels.entry(prices[i % prices.len()]).or_default().push(order);
+    }
+    levels.into_iter().map(|(price, orders)| (price, orders.into_iter().collect())).collect()
+}
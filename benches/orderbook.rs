@@ -0,0 +1,44 @@
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use rust_decimal::Decimal;
+use std::hint::black_box;
+
+mod fixture;
+
+fn snapshot(c: &mut Criterion) {
+    let recording = fixture::load();
+    let levels = recording.snapshot.bids.len() + recording.snapshot.asks.len();
+    let mut group = c.benchmark_group("snapshot");
+    group.throughput(Throughput::Elements(levels as u64));
+    let mut book = recording.initial_book();
+    group.bench_function("apply_snapshot", |b| b.iter(|| book.apply_snapshot(black_box(&recording.snapshot))));
+    group.finish();
+}
+
+// Replays every recorded update onto a fresh copy of the snapshot, reported per update
+fn apply_update(c: &mut Criterion) {
+    let recording = fixture::load();
+    let mut group = c.benchmark_group("apply_update");
+    group.throughput(Throughput::Elements(recording.updates.len() as u64));
+    for (name, grouping) in [("raw", None), ("grouped", Some(Decimal::new(5, 2)))] {
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                || {
+                    let mut book = recording.initial_book();
+                    book.set_grouping(grouping);
+                    book
+                },
+                |mut book| {
+                    for update in &recording.updates {
+                        book.apply_update(black_box(update));
+                    }
+                    book
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, snapshot, apply_update);
+criterion_main!(benches);
@@ -0,0 +1,12 @@
+fn main() {
+    // Generates the gRPC service and messages from proto/l3book.proto
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto/l3book.proto");
+        if std::env::var_os("PROTOC").is_none() {
+            let protoc = protoc_bin_vendored::protoc_bin_path().expect("vendored protoc");
+            std::env::set_var("PROTOC", protoc);
+        }
+        tonic_prost_build::compile_protos("proto/l3book.proto").expect("compile proto/l3book.proto");
+    }
+}
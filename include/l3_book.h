@@ -0,0 +1,87 @@
+/* C ABI of the multi_exchange_l3_est book engine (src/ffi.rs).
+ *
+ * Build the shared library with
+ *     cargo build --release --features ffi
+ * and link target/release/libmulti_exchange_l3_est.{so,dylib} or multi_exchange_l3_est.dll.
+ *
+ * A book is not thread safe: use each one from one thread at a time. Prices and quantities
+ * are read as their shortest decimal form, so 0.1 is taken as exactly 0.1. */
+#ifndef L3_BOOK_H
+#define L3_BOOK_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define L3_BID 0
+#define L3_ASK 1
+
+/* Results of l3_book_apply_snapshot and l3_book_apply_update */
+#define L3_APPLIED 0
+#define L3_BUFFERED 1 /* held until the first snapshot */
+#define L3_SKIPPED 2  /* already contained in the book */
+#define L3_INVALID (-1) /* null book or array, or a price that is not finite */
+
+/* Actions of an inferred event */
+#define L3_JOINED 0
+#define L3_REMOVED 1
+#define L3_REDUCED 2
+#define L3_FILLED 3
+
+typedef struct L3Book L3Book;
+
+/* New quantity at a price; zero removes the level */
+typedef struct {
+    double price;
+    double quantity;
+} L3Level;
+
+typedef struct {
+    double price;
+    double quantity;
+    uint32_t orders; /* estimated orders resting at the level */
+} L3LevelInfo;
+
+typedef struct {
+    uint64_t time; /* milliseconds since the Unix epoch */
+    int32_t side;
+    double price;
+    uint64_t order_id;
+    int32_t action;
+    double size;   /* size after the change, zero once removed */
+    double change; /* positive when the order joined, negative otherwise */
+} L3Event;
+
+typedef void (*L3EventCallback)(const L3Event *event, void *user_data);
+
+L3Book *l3_book_new(void);
+void l3_book_free(L3Book *book);
+
+/* Called during l3_book_apply_update for every order the estimator infers joining, leaving
+ * or shrinking. The event is only valid for the call. NULL stops the events. */
+void l3_book_set_event_callback(L3Book *book, L3EventCallback callback, void *user_data);
+
+int32_t l3_book_apply_snapshot(L3Book *book, uint64_t last_update_id,
+                               const L3Level *bids, size_t bids_len,
+                               const L3Level *asks, size_t asks_len);
+
+/* Changed levels of update first_update_id..=last_update_id */
+int32_t l3_book_apply_update(L3Book *book, uint64_t first_update_id, uint64_t last_update_id,
+                             const L3Level *bids, size_t bids_len,
+                             const L3Level *asks, size_t asks_len);
+
+/* Writes up to capacity levels of side, best first; returns how many were written */
+size_t l3_book_levels(const L3Book *book, int32_t side, L3LevelInfo *out, size_t capacity);
+
+/* Writes up to capacity estimated order sizes at price, oldest first; returns how many were
+ * written, 0 if the level is not in the book */
+size_t l3_book_orders(const L3Book *book, int32_t side, double price, double *out, size_t capacity);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* L3_BOOK_H */
@@ -0,0 +1,344 @@
+use crate::anomaly::Anomaly;
+use crate::exchanges::Side;
+use crate::orderbook::{OrderBook, OrderQueue};
+use rust_decimal::prelude::*;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+// Alerts kept in the log; older ones are dropped
+const LOG_CAPACITY: usize = 200;
+
+// Size an inferred order must reach to raise an alert
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertThreshold {
+    // Fixed size in base units (contracts on COIN-M)
+    Absolute(Decimal),
+    // Multiple of the median level quantity across both sides
+    MedianMultiple(f64),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AlertSettings {
+    pub enabled: bool,
+    pub threshold: AlertThreshold,
+    // Also raise a desktop notification (needs the `notify` feature)
+    pub desktop: bool,
+}
+
+impl Default for AlertSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold: AlertThreshold::MedianMultiple(20.0),
+            desktop: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AlertKind {
+    Appeared,
+    // Left the book; fills and cancels look the same in L2 data
+    Pulled,
+}
+
+impl AlertKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AlertKind::Appeared => "appeared",
+            AlertKind::Pulled => "pulled",
+        }
+    }
+}
+
+fn side_name(side: Side) -> &'static str {
+    match side {
+        Side::Bid => "bid",
+        Side::Ask => "ask",
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WhaleAlert {
+    // Milliseconds since the Unix epoch
+    pub time: u64,
+    pub kind: AlertKind,
+    pub side: Side,
+    pub price: Decimal,
+    pub size: Decimal,
+}
+
+impl WhaleAlert {
+    pub fn describe(&self) -> String {
+        format!(
+            "Large {} of {} at {} {}",
+            side_name(self.side),
+            self.size.normalize(),
+            self.price.normalize(),
+            self.kind.name()
+        )
+    }
+}
+
+// Watches a book for large inferred orders. Whether an order is large is decided once, when it
+// first appears, so a moving median threshold does not make old orders flap in and out.
+// Orders loaded from a snapshot are whole levels, not inferred orders, and never alert.
+#[derive(Debug, Default)]
+pub struct WhaleWatch {
+    pub settings: AlertSettings,
+    // Large orders currently in the book, by order id
+    tracked: HashMap<u64, WhaleAlert>,
+    // Highest order id seen by the last scan; newer ids are new orders
+    last_id: u64,
+    log: VecDeque<WhaleAlert>,
+}
+
+impl WhaleWatch {
+    pub fn new(settings: AlertSettings) -> Self {
+        Self {
+            settings,
+            ..Self::default()
+        }
+    }
+
+    // Forgets tracked orders without alerting, e.g. when a snapshot replaced the book
+    pub fn reset(&mut self) {
+        self.tracked.clear();
+    }
+
+    // Most recent last
+    pub fn log(&self) -> &VecDeque<WhaleAlert> {
+        &self.log
+    }
+
+    pub fn clear_log(&mut self) {
+        self.log.clear();
+    }
+
+    // Size at or above which a new order alerts, given the current book
+    pub fn min_size(&self, book: &OrderBook) -> Option<Decimal> {
+        match self.settings.threshold {
+            AlertThreshold::Absolute(size) => Some(size),
+            AlertThreshold::MedianMultiple(multiple) => {
+                let mut totals: Vec<Decimal> = book.bids.values().chain(book.asks.values()).map(OrderQueue::total).collect();
+                if totals.is_empty() {
+                    return None;
+                }
+                let mid = totals.len() / 2;
+                let (_, &mut median, _) = totals.select_nth_unstable(mid);
+                Some(median * Decimal::from_f64(multiple)?)
+            }
+        }
+    }
+
+    // Compares the book with the previous scan and returns the new alerts, which are also
+    // added to the log. Does nothing while alerts are disabled.
+    pub fn scan(&mut self, book: &OrderBook) -> Vec<WhaleAlert> {
+        if !self.settings.enabled {
+            return Vec::new();
+        }
+        let min_size = self.min_size(book).filter(|size| *size > Decimal::ZERO);
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        let mut alerts = Vec::new();
+        let mut present = HashMap::with_capacity(self.tracked.len());
+        let mut last_id = self.last_id;
+
+        let mut visit = |side: Side, levels: &BTreeMap<Decimal, OrderQueue>| {
+            for (&price, queue) in levels {
+                for order in queue {
+                    last_id = last_id.max(order.id);
+                    if let Some(tracked) = self.tracked.get(&order.id) {
+                        present.insert(order.id, WhaleAlert { price, size: order.size(), ..*tracked });
+                    } else if order.id > self.last_id
+                        && !order.from_snapshot
+                        && min_size.is_some_and(|min| order.size() >= min)
+                    {
+                        let alert = WhaleAlert {
+                            time: now,
+                            kind: AlertKind::Appeared,
+                            side,
+                            price,
+                            size: order.size(),
+                        };
+                        present.insert(order.id, alert);
+                        alerts.push(alert);
+                    }
+                }
+            }
+        };
+        visit(Side::Bid, &book.bids);
+        visit(Side::Ask, &book.asks);
+
+        for (id, order) in &self.tracked {
+            if !present.contains_key(id) {
+                alerts.push(WhaleAlert {
+                    time: now,
+                    kind: AlertKind::Pulled,
+                    ..*order
+                });
+            }
+        }
+        self.tracked = present;
+        self.last_id = last_id;
+
+        for &alert in &alerts {
+            if self.log.len() == LOG_CAPACITY {
+                self.log.pop_front();
+            }
+            self.log.push_back(alert);
+        }
+        if self.settings.desktop && !alerts.is_empty() {
+            notify_desktop(&alerts);
+        }
+        alerts
+    }
+}
+
+// Outbound channel for alerts, for watching books unattended
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AlertTarget {
+    // POSTs an `AlertPayload` as JSON
+    Webhook { url: String },
+    // Sends a text message through the Telegram bot API
+    Telegram { bot_token: String, chat_id: String },
+}
+
+// JSON body posted to webhooks
+#[derive(Serialize, Debug)]
+pub struct AlertPayload<'a> {
+    pub exchange: &'static str,
+    pub symbol: &'a str,
+    pub alerts: &'a [AlertEntry],
+}
+
+// Large order alerts fill in the order's side, price and size; anomalies their measured value
+// and its baseline
+#[derive(Serialize, Debug)]
+pub struct AlertEntry {
+    // Milliseconds since the Unix epoch
+    pub time: u64,
+    // appeared or pulled, or an `AnomalyKind` name
+    pub kind: &'static str,
+    // bid or ask
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub side: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price: Option<Decimal>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<Decimal>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub baseline: Option<f64>,
+    pub message: String,
+}
+
+impl From<&WhaleAlert> for AlertEntry {
+    fn from(alert: &WhaleAlert) -> Self {
+        Self {
+            time: alert.time,
+            kind: alert.kind.name(),
+            side: Some(side_name(alert.side)),
+            price: Some(alert.price),
+            size: Some(alert.size),
+            value: None,
+            baseline: None,
+            message: alert.describe(),
+        }
+    }
+}
+
+impl From<&Anomaly> for AlertEntry {
+    fn from(anomaly: &Anomaly) -> Self {
+        Self {
+            time: anomaly.time,
+            kind: anomaly.kind.name(),
+            side: None,
+            price: None,
+            size: None,
+            value: Some(anomaly.value),
+            baseline: Some(anomaly.baseline),
+            message: anomaly.describe(),
+        }
+    }
+}
+
+// Delivers alerts to every target in the background; failures are logged, not retried.
+// Each scan's alerts go out as one request per target to stay within chat rate limits.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct AlertNotifier {
+    runtime: tokio::runtime::Handle,
+    client: reqwest::Client,
+    targets: Vec<AlertTarget>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl AlertNotifier {
+    pub fn new(runtime: tokio::runtime::Handle, targets: Vec<AlertTarget>) -> Self {
+        Self {
+            runtime,
+            client: reqwest::Client::new(),
+            targets,
+        }
+    }
+
+    pub fn send(&self, exchange: &'static str, symbol: &str, alerts: &[WhaleAlert]) {
+        self.deliver(exchange, symbol, alerts.iter().map(AlertEntry::from).collect());
+    }
+
+    pub fn send_anomalies(&self, exchange: &'static str, symbol: &str, anomalies: &[Anomaly]) {
+        self.deliver(exchange, symbol, anomalies.iter().map(AlertEntry::from).collect());
+    }
+
+    fn deliver(&self, exchange: &'static str, symbol: &str, alerts: Vec<AlertEntry>) {
+        if alerts.is_empty() {
+            return;
+        }
+        for target in &self.targets {
+            let request = match target {
+                AlertTarget::Webhook { url } => self.client.post(url).json(&AlertPayload {
+                    exchange,
+                    symbol,
+                    alerts: &alerts,
+                }),
+                AlertTarget::Telegram { bot_token, chat_id } => {
+                    let text = alerts
+                        .iter()
+                        .map(|alert| format!("{exchange} {}: {}", symbol.to_uppercase(), alert.message))
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    self.client
+                        .post(format!("https://api.telegram.org/bot{bot_token}/sendMessage"))
+                        .json(&serde_json::json!({ "chat_id": chat_id, "text": text }))
+                }
+            };
+            let name = match target {
+                AlertTarget::Webhook { .. } => "webhook",
+                AlertTarget::Telegram { .. } => "Telegram",
+            };
+            self.runtime.spawn(async move {
+                match request.send().await.and_then(|response| response.error_for_status()) {
+                    Ok(_) => {}
+                    // The error carries the URL, which for Telegram includes the bot token
+                    Err(e) => tracing::warn!("Alert {name} delivery failed: {}", e.without_url()),
+                }
+            });
+        }
+    }
+}
+
+// Shows the alerts as one desktop notification, off the calling thread
+#[cfg(feature = "notify")]
+fn notify_desktop(alerts: &[WhaleAlert]) {
+    let body = alerts.iter().map(WhaleAlert::describe).collect::<Vec<_>>().join("\n");
+    std::thread::spawn(move || {
+        if let Err(e) = notify_rust::Notification::new().summary("Large order alert").body(&body).show() {
+            tracing::warn!("Desktop notification failed: {e}");
+        }
+    });
+}
+
+#[cfg(not(feature = "notify"))]
+fn notify_desktop(_alerts: &[WhaleAlert]) {}
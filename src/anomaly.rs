@@ -0,0 +1,270 @@
+// Flags abnormal market conditions against rolling baselines of the feed's recent past: message
+// rate spikes and drops, spread blowouts and the top of the book emptying out. The monitor closes
+// one sample per second; each sample is compared with the window of samples before it.
+use crate::feed::FeedMessage;
+use crate::orderbook::{OrderBook, OrderQueue};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+// Anomalies kept in the log; older ones are dropped
+const LOG_CAPACITY: usize = 200;
+
+// Price levels per side summed into the depth sample
+pub const DEPTH_LEVELS: usize = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AnomalySettings {
+    pub enabled: bool,
+    // Seconds of samples the baselines are taken over
+    pub window_secs: usize,
+    // Samples a baseline needs before it flags anything
+    pub warmup_secs: usize,
+    // Standard deviations from the mean message rate that make a spike or a drop. The deviation
+    // is at least that of a Poisson stream, so quiet feeds do not flag every burst.
+    pub rate_sigmas: f64,
+    // Spread at this multiple of its baseline is a widening
+    pub spread_multiple: f64,
+    // Depth at or below this fraction of its baseline has evaporated
+    pub depth_fraction: f64,
+    // Seconds before the same kind of anomaly is raised again
+    pub cooldown_secs: u64,
+}
+
+impl Default for AnomalySettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            window_secs: 300,
+            warmup_secs: 30,
+            rate_sigmas: 3.0,
+            spread_multiple: 4.0,
+            depth_fraction: 0.3,
+            cooldown_secs: 30,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AnomalyKind {
+    RateSpike,
+    RateDrop,
+    SpreadWidening,
+    DepthEvaporation,
+}
+
+impl AnomalyKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AnomalyKind::RateSpike => "rate_spike",
+            AnomalyKind::RateDrop => "rate_drop",
+            AnomalyKind::SpreadWidening => "spread_widening",
+            AnomalyKind::DepthEvaporation => "depth_evaporation",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Anomaly {
+    // Milliseconds since the Unix epoch
+    pub time: u64,
+    pub kind: AnomalyKind,
+    // The sample that was flagged and the baseline mean it was compared with: messages per
+    // second, spread in bps, or base units resting in the top `DEPTH_LEVELS` levels
+    pub value: f64,
+    pub baseline: f64,
+}
+
+impl Anomaly {
+    pub fn describe(&self) -> String {
+        let (value, baseline) = (self.value, self.baseline);
+        match self.kind {
+            AnomalyKind::RateSpike => format!("Message rate spiked to {value:.0}/s (baseline {baseline:.1}/s)"),
+            AnomalyKind::RateDrop => format!("Message rate dropped to {value:.0}/s (baseline {baseline:.1}/s)"),
+            AnomalyKind::SpreadWidening => format!("Spread widened to {value:.2} bps (baseline {baseline:.2} bps)"),
+            AnomalyKind::DepthEvaporation => {
+                format!("Depth of the top {DEPTH_LEVELS} levels fell to {value:.4} (baseline {baseline:.4})")
+            }
+        }
+    }
+}
+
+// The most recent samples of one measure
+#[derive(Debug, Default)]
+struct Baseline {
+    samples: VecDeque<f64>,
+}
+
+impl Baseline {
+    fn push(&mut self, value: f64, window: usize) {
+        while self.samples.len() >= window.max(1) {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(value);
+    }
+
+    // Mean and standard deviation, once there are enough samples
+    fn stats(&self, warmup: usize) -> Option<(f64, f64)> {
+        let n = self.samples.len();
+        if n == 0 || n < warmup {
+            return None;
+        }
+        let mean = self.samples.iter().sum::<f64>() / n as f64;
+        let variance = self.samples.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n as f64;
+        Some((mean, variance.sqrt()))
+    }
+}
+
+// Counts a feed's messages and samples its book once a second, raising anomalies against the
+// baselines. Spread and depth are only sampled while the book is synced and has both sides.
+#[derive(Debug, Default)]
+pub struct AnomalyMonitor {
+    pub settings: AnomalySettings,
+    // Second (since the Unix epoch) of the last sample, 0 before the first
+    second: u64,
+    messages: u64,
+    rate: Baseline,
+    spread: Baseline,
+    depth: Baseline,
+    // When each kind was last raised
+    raised: HashMap<AnomalyKind, u64>,
+    log: VecDeque<Anomaly>,
+}
+
+impl AnomalyMonitor {
+    pub fn new(settings: AnomalySettings) -> Self {
+        Self {
+            settings,
+            ..Self::default()
+        }
+    }
+
+    // Counts book, order, trade and instrument messages, as `LatencyTracker` does
+    pub fn record(&mut self, message: &FeedMessage) {
+        if matches!(
+            message,
+            FeedMessage::Update(_)
+                | FeedMessage::Snapshot(_)
+                | FeedMessage::FullState(_)
+                | FeedMessage::Order(_)
+                | FeedMessage::Trade(_)
+                | FeedMessage::InstrumentStats(_)
+        ) {
+            self.messages += 1;
+        }
+    }
+
+    // Forgets the baselines, e.g. when the monitor was off for a while
+    pub fn reset(&mut self) {
+        self.second = 0;
+        self.messages = 0;
+        self.rate = Baseline::default();
+        self.spread = Baseline::default();
+        self.depth = Baseline::default();
+    }
+
+    // Most recent last
+    pub fn log(&self) -> &VecDeque<Anomaly> {
+        &self.log
+    }
+
+    pub fn clear_log(&mut self) {
+        self.log.clear();
+    }
+
+    // Closes a sample once `now` (milliseconds since the Unix epoch) reaches a new second, and
+    // returns the anomalies it shows, which are also added to the log. Does nothing while the
+    // monitor is disabled.
+    pub fn sample(&mut self, book: &OrderBook, now: u64) -> Vec<Anomaly> {
+        if !self.settings.enabled {
+            if self.second != 0 {
+                self.reset();
+            }
+            return Vec::new();
+        }
+        let second = now / 1000;
+        if self.second == 0 {
+            // Messages counted so far arrived over an unknown span, so the first second starts here
+            self.second = second;
+            self.messages = 0;
+            return Vec::new();
+        }
+        if second <= self.second {
+            return Vec::new();
+        }
+        // A pass that took longer than a second averages its messages over the whole gap
+        let rate = self.messages as f64 / (second - self.second) as f64;
+        self.second = second;
+        self.messages = 0;
+
+        let settings = self.settings;
+        let mut found = Vec::new();
+        let mut flag = |kind: AnomalyKind, value: f64, baseline: f64| {
+            found.push(Anomaly { time: now, kind, value, baseline });
+        };
+        if let Some((mean, std)) = self.rate.stats(settings.warmup_secs) {
+            let band = std.max(mean.sqrt()).max(1.0) * settings.rate_sigmas;
+            if rate > mean + band {
+                flag(AnomalyKind::RateSpike, rate, mean);
+            } else if rate < mean - band {
+                flag(AnomalyKind::RateDrop, rate, mean);
+            }
+        }
+        self.rate.push(rate, settings.window_secs);
+
+        if let Some((spread, depth)) = book_sample(book) {
+            if let Some((mean, _)) = self.spread.stats(settings.warmup_secs) {
+                if mean > 0.0 && spread >= mean * settings.spread_multiple {
+                    flag(AnomalyKind::SpreadWidening, spread, mean);
+                }
+            }
+            if let Some((mean, _)) = self.depth.stats(settings.warmup_secs) {
+                if mean > 0.0 && depth <= mean * settings.depth_fraction {
+                    flag(AnomalyKind::DepthEvaporation, depth, mean);
+                }
+            }
+            self.spread.push(spread, settings.window_secs);
+            self.depth.push(depth, settings.window_secs);
+        }
+
+        let cooldown = settings.cooldown_secs * 1000;
+        found.retain(|anomaly| {
+            let quiet = self.raised.get(&anomaly.kind).is_none_or(|&last| now.saturating_sub(last) >= cooldown);
+            if quiet {
+                self.raised.insert(anomaly.kind, now);
+            }
+            quiet
+        });
+        for &anomaly in &found {
+            if self.log.len() == LOG_CAPACITY {
+                self.log.pop_front();
+            }
+            self.log.push_back(anomaly);
+        }
+        found
+    }
+}
+
+// Spread in bps of the mid, and the size resting in the top levels of both sides
+fn book_sample(book: &OrderBook) -> Option<(f64, f64)> {
+    if !book.is_synced() {
+        return None;
+    }
+    let (bid, ask) = (book.best_bid()?, book.best_ask()?);
+    let mid = (bid + ask) / Decimal::TWO;
+    if mid <= Decimal::ZERO {
+        return None;
+    }
+    let spread = ((ask - bid) / mid * Decimal::from(10_000)).to_f64()?;
+    let depth: Decimal = book
+        .bids
+        .values()
+        .rev()
+        .take(DEPTH_LEVELS)
+        .chain(book.asks.values().take(DEPTH_LEVELS))
+        .map(OrderQueue::total)
+        .sum();
+    Some((spread, depth.to_f64()?))
+}
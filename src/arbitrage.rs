@@ -0,0 +1,86 @@
+use crate::exchanges::Side;
+use crate::orderbook::OrderBook;
+use rust_decimal::Decimal;
+use std::collections::VecDeque;
+
+// Buying `size` on one venue and selling it on another at the same moment
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ArbSpread {
+    // Average price paid walking the buy venue's asks
+    pub buy_price: Decimal,
+    // Average price received walking the sell venue's bids
+    pub sell_price: Decimal,
+    // (sell - buy) / buy in basis points; positive is a profit before fees
+    pub bps: Decimal,
+}
+
+// Executable spread for `size` in base units. None if either book is out of sync or too thin
+// to fill the whole size.
+pub fn executable_spread(buy_on: &OrderBook, sell_on: &OrderBook, size: Decimal) -> Option<ArbSpread> {
+    if !buy_on.is_synced() || !sell_on.is_synced() {
+        return None;
+    }
+    let buy = buy_on.estimate_fill(Side::Bid, size)?;
+    let sell = sell_on.estimate_fill(Side::Ask, size)?;
+    if buy.filled < size || sell.filled < size {
+        return None;
+    }
+    Some(ArbSpread {
+        buy_price: buy.avg_price,
+        sell_price: sell.avg_price,
+        bps: (sell.avg_price - buy.avg_price) / buy.avg_price * Decimal::from(10_000),
+    })
+}
+
+// Spread in both directions between venues A and B at one moment
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ArbSample {
+    // Milliseconds since the Unix epoch
+    pub timestamp: u64,
+    // Buy on A, sell on B
+    pub a_to_b: Option<ArbSpread>,
+    // Buy on B, sell on A
+    pub b_to_a: Option<ArbSpread>,
+}
+
+// Rolling record of the executable spread between two books
+#[derive(Debug)]
+pub struct ArbMonitor {
+    samples: VecDeque<ArbSample>,
+    capacity: usize,
+}
+
+impl ArbMonitor {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn sample(&mut self, a: &OrderBook, b: &OrderBook, size: Decimal, timestamp: u64) -> ArbSample {
+        let sample = ArbSample {
+            timestamp,
+            a_to_b: executable_spread(a, b, size),
+            b_to_a: executable_spread(b, a, size),
+        };
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+        sample
+    }
+
+    // Oldest first
+    pub fn samples(&self) -> &VecDeque<ArbSample> {
+        &self.samples
+    }
+
+    pub fn latest(&self) -> Option<&ArbSample> {
+        self.samples.back()
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+}
@@ -0,0 +1,351 @@
+// Archives the full book with its estimated orders at a fixed cadence, one timestamped file per
+// snapshot, so days of running build a dataset. Files are laid out as
+// `<dir>/<exchange>_<symbol>/<YYYY-MM-DD>/<HH-MM-SS.mmm>.<json|parquet>` (UTC), and the oldest
+// are deleted to stay within the file count and disk usage caps, including files left by
+// earlier runs. Writing happens on a background thread, so the feed loop never waits on disk.
+use crate::exchanges::ExchangeType;
+use crate::orderbook::{OrderBook, OrderQueue};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+// Bumped whenever a field changes meaning or goes away
+pub const ARCHIVE_VERSION: u32 = 1;
+
+pub const DEFAULT_ARCHIVE_INTERVAL: Duration = Duration::from_secs(60);
+
+// Snapshots waiting for the writer before new ones are dropped
+const QUEUE_CAPACITY: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    // One JSON document per snapshot, exact decimals as strings
+    Json,
+    // One row per estimated order (`parquet` feature)
+    #[cfg(feature = "parquet")]
+    Parquet,
+}
+
+impl ArchiveFormat {
+    pub const ALL: &'static [ArchiveFormat] = &[
+        ArchiveFormat::Json,
+        #[cfg(feature = "parquet")]
+        ArchiveFormat::Parquet,
+    ];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ArchiveFormat::Json => "json",
+            #[cfg(feature = "parquet")]
+            ArchiveFormat::Parquet => "parquet",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|f| f.extension().eq_ignore_ascii_case(name))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArchiveOptions {
+    pub dir: PathBuf,
+    pub interval: Duration,
+    pub format: ArchiveFormat,
+    // Oldest files are deleted beyond this many for the instrument
+    pub max_files: Option<usize>,
+    // ...or beyond this many bytes; the newest file is always kept
+    pub max_bytes: Option<u64>,
+}
+
+impl ArchiveOptions {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir, interval: DEFAULT_ARCHIVE_INTERVAL, format: ArchiveFormat::Json, max_files: None, max_bytes: None }
+    }
+}
+
+// One archived book
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ArchivedBook {
+    pub version: u32,
+    pub exchange: String,
+    pub symbol: String,
+    // Milliseconds since the Unix epoch
+    pub timestamp: u64,
+    pub last_update_id: u64,
+    // Best first on both sides
+    pub bids: Vec<ArchivedLevel>,
+    pub asks: Vec<ArchivedLevel>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ArchivedLevel {
+    pub price: Decimal,
+    pub quantity: Decimal,
+    // Estimated order sizes in time priority, oldest first
+    pub orders: Vec<Decimal>,
+}
+
+impl ArchivedBook {
+    pub fn from_book(book: &OrderBook, exchange: ExchangeType, symbol: &str, timestamp: u64) -> Self {
+        let level = |(&price, queue): (&Decimal, &OrderQueue)| ArchivedLevel {
+            price,
+            quantity: queue.total(),
+            orders: queue.sizes().collect(),
+        };
+        Self {
+            version: ARCHIVE_VERSION,
+            exchange: exchange.name().to_string(),
+            symbol: symbol.to_string(),
+            timestamp,
+            last_update_id: book.last_update_id(),
+            bids: book.bids.iter().rev().map(level).collect(),
+            asks: book.asks.iter().map(level).collect(),
+        }
+    }
+
+    pub fn read(path: &Path) -> io::Result<Self> {
+        serde_json::from_slice(&fs::read(path)?).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+// Hands snapshots of one feed's book to the writer thread at the configured cadence
+pub struct Archiver {
+    options: ArchiveOptions,
+    exchange: ExchangeType,
+    symbol: String,
+    tx: Option<SyncSender<ArchivedBook>>,
+    thread: Option<JoinHandle<()>>,
+    // Time of the last snapshot in milliseconds since the Unix epoch
+    last: Option<u64>,
+    written: Arc<AtomicU64>,
+    dropped: u64,
+}
+
+impl Archiver {
+    // Creates the instrument's directory and picks up the files already in it for rotation
+    pub fn start(options: ArchiveOptions, exchange: ExchangeType, symbol: &str) -> io::Result<Self> {
+        let root = options.dir.join(format!("{}_{}", exchange.name().to_lowercase(), sanitize(symbol)));
+        fs::create_dir_all(&root)?;
+        let mut rotation = Rotation::scan(&root, options.format.extension())?;
+        let (tx, rx) = mpsc::sync_channel::<ArchivedBook>(QUEUE_CAPACITY);
+        let written = Arc::new(AtomicU64::new(0));
+        let counter = written.clone();
+        let writer = options.clone();
+        let thread = std::thread::Builder::new().name("archive".to_string()).spawn(move || {
+            for book in rx {
+                match write(&root, &writer, &book) {
+                    Ok((path, bytes)) => {
+                        counter.fetch_add(1, Ordering::Relaxed);
+                        rotation.push(path, bytes);
+                        rotation.enforce(&root, writer.max_files, writer.max_bytes);
+                    }
+                    Err(e) => {
+                        tracing::error!("Archiving the book to {} failed: {e}", root.display())
+                    }
+                }
+            }
+        })?;
+        tracing::info!(
+            "Archiving {} {} every {:?} to {}",
+            exchange.name(),
+            symbol,
+            options.interval,
+            options.dir.display()
+        );
+        Ok(Self {
+            options,
+            exchange,
+            symbol: symbol.to_string(),
+            tx: Some(tx),
+            thread: Some(thread),
+            last: None,
+            written,
+            dropped: 0,
+        })
+    }
+
+    // Queues a snapshot if the interval has passed since the last one; unsynced books are skipped
+    pub fn sample(&mut self, book: &OrderBook) {
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        let interval = self.options.interval.as_millis() as u64;
+        if self.last.is_some_and(|last| now.saturating_sub(last) < interval) || !book.is_synced() {
+            return;
+        }
+        // Strictly increasing, as the time names the file
+        let timestamp = self.last.map_or(now, |last| now.max(last + 1));
+        self.last = Some(timestamp);
+        let snapshot = ArchivedBook::from_book(book, self.exchange, &self.symbol, timestamp);
+        if let Some(tx) = &self.tx {
+            if let Err(TrySendError::Full(_)) = tx.try_send(snapshot) {
+                self.dropped += 1;
+            }
+        }
+    }
+
+    // Files written so far
+    pub fn written(&self) -> u64 {
+        self.written.load(Ordering::Relaxed)
+    }
+
+    // Snapshots skipped because the disk could not keep up
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    // Writes what is still queued
+    pub fn close(&mut self) {
+        self.tx = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for Archiver {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+// Symbols such as `BTC/USD` or `ETH-PERP` as one path component
+pub(crate) fn sanitize(symbol: &str) -> String {
+    symbol
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect::<String>()
+        .to_lowercase()
+}
+
+// Writes through a temporary name, so a crash never leaves a partial file under the final one
+fn write(root: &Path, options: &ArchiveOptions, book: &ArchivedBook) -> io::Result<(PathBuf, u64)> {
+    let time = chrono::DateTime::from_timestamp_millis(book.timestamp as i64).unwrap_or_default();
+    let dir = root.join(time.format("%Y-%m-%d").to_string());
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("{}.{}", time.format("%H-%M-%S%.3f"), options.format.extension()));
+    let partial = path.with_extension("partial");
+    match options.format {
+        ArchiveFormat::Json => fs::write(&partial, serde_json::to_vec(book).map_err(io::Error::other)?)?,
+        #[cfg(feature = "parquet")]
+        ArchiveFormat::Parquet => parquet_archive::write(&partial, book)?,
+    }
+    fs::rename(&partial, &path)?;
+    let bytes = fs::metadata(&path)?.len();
+    Ok((path, bytes))
+}
+
+// Archive files of one instrument, oldest first
+struct Rotation {
+    files: VecDeque<(PathBuf, u64)>,
+    bytes: u64,
+}
+
+impl Rotation {
+    fn scan(root: &Path, extension: &str) -> io::Result<Self> {
+        let mut files = Vec::new();
+        for day in fs::read_dir(root)? {
+            let day = day?.path();
+            if !day.is_dir() {
+                continue;
+            }
+            for file in fs::read_dir(&day)? {
+                let file = file?;
+                let path = file.path();
+                if path.extension().is_some_and(|ext| ext == extension) {
+                    files.push((path, file.metadata()?.len()));
+                }
+            }
+        }
+        // Day and time names sort chronologically
+        files.sort();
+        Ok(Self { bytes: files.iter().map(|(_, bytes)| bytes).sum(), files: files.into() })
+    }
+
+    fn push(&mut self, path: PathBuf, bytes: u64) {
+        self.bytes += bytes;
+        self.files.push_back((path, bytes));
+    }
+
+    fn enforce(&mut self, root: &Path, max_files: Option<usize>, max_bytes: Option<u64>) {
+        while self.files.len() > 1
+            && (max_files.is_some_and(|max| self.files.len() > max) || max_bytes.is_some_and(|max| self.bytes > max))
+        {
+            let (path, bytes) = self.files.pop_front().expect("more than one file");
+            self.bytes -= bytes;
+            if let Err(e) = fs::remove_file(&path) {
+                tracing::warn!("Could not delete archived {}: {e}", path.display());
+            }
+            // The day's directory goes with its last file
+            if let Some(day) = path.parent().filter(|day| *day != root) {
+                let _ = fs::remove_dir(day);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "parquet")]
+mod parquet_archive {
+    use super::ArchivedBook;
+    use arrow_array::{ArrayRef, Float64Array, Int64Array, RecordBatch, StringArray, UInt32Array};
+    use arrow_schema::{DataType, Field, Schema};
+    use parquet::arrow::ArrowWriter;
+    use rust_decimal::prelude::ToPrimitive;
+    use std::fs::File;
+    use std::io;
+    use std::path::Path;
+    use std::sync::Arc;
+
+    // Long format: timestamp, exchange, symbol, side, level (1 = best), price, quantity, order
+    // (0 = oldest) and the order's estimated size
+    pub fn write(path: &Path, book: &ArchivedBook) -> io::Result<()> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("timestamp", DataType::Int64, false),
+            Field::new("exchange", DataType::Utf8, false),
+            Field::new("symbol", DataType::Utf8, false),
+            Field::new("side", DataType::Utf8, false),
+            Field::new("level", DataType::UInt32, false),
+            Field::new("price", DataType::Float64, false),
+            Field::new("quantity", DataType::Float64, false),
+            Field::new("order", DataType::UInt32, false),
+            Field::new("size", DataType::Float64, false),
+        ]));
+        let (mut sides, mut levels, mut prices, mut quantities, mut orders, mut sizes) =
+            (Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new());
+        for (side, book_side) in [("bid", &book.bids), ("ask", &book.asks)] {
+            for (i, level) in book_side.iter().enumerate() {
+                for (j, size) in level.orders.iter().enumerate() {
+                    sides.push(side);
+                    levels.push(i as u32 + 1);
+                    prices.push(level.price.to_f64().unwrap_or(f64::NAN));
+                    quantities.push(level.quantity.to_f64().unwrap_or(f64::NAN));
+                    orders.push(j as u32);
+                    sizes.push(size.to_f64().unwrap_or(f64::NAN));
+                }
+            }
+        }
+        let rows = sides.len();
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(Int64Array::from(vec![book.timestamp as i64; rows])),
+            Arc::new(StringArray::from(vec![book.exchange.as_str(); rows])),
+            Arc::new(StringArray::from(vec![book.symbol.as_str(); rows])),
+            Arc::new(StringArray::from(sides)),
+            Arc::new(UInt32Array::from(levels)),
+            Arc::new(Float64Array::from(prices)),
+            Arc::new(Float64Array::from(quantities)),
+            Arc::new(UInt32Array::from(orders)),
+            Arc::new(Float64Array::from(sizes)),
+        ];
+        let batch = RecordBatch::try_new(schema.clone(), columns).map_err(io::Error::other)?;
+        let mut writer = ArrowWriter::try_new(File::create(path)?, schema, None).map_err(io::Error::other)?;
+        writer.write(&batch).map_err(io::Error::other)?;
+        writer.close().map_err(io::Error::other)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,150 @@
+// Snapshot reconciliation: the locally maintained book diffed against a REST snapshot pulled in
+// the background, to measure how far diff-stream maintenance drifts over a long session.
+//
+// The snapshot is taken a little before it is compared, so a fast book shows some drift even
+// when nothing was missed; the trend over a session is what matters.
+use crate::exchanges::{OrderBookSnapshot, PriceLevel};
+use crate::orderbook::{OrderBook, OrderQueue};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+
+// One comparison, over the price range the snapshot covers on each side
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BookDrift {
+    // Local time of the comparison, ms since the Unix epoch
+    pub time: u64,
+    // Distinct levels in the snapshot or the book within that range
+    pub levels: usize,
+    // In both with different sizes
+    pub mismatched: usize,
+    // In the snapshot but not the book
+    pub missing: usize,
+    // In the book but not the snapshot
+    pub extra: usize,
+    // Sum of |book size - snapshot size| over the levels
+    pub size_error: Decimal,
+    pub snapshot_size: Decimal,
+}
+
+impl BookDrift {
+    pub fn compare(book: &OrderBook, snapshot: &OrderBookSnapshot) -> Self {
+        let mut drift = BookDrift {
+            time: chrono::Utc::now().timestamp_millis() as u64,
+            ..Default::default()
+        };
+        let lowest_bid = snapshot.bids.iter().map(|level| level.price).min();
+        if let Some(lowest) = lowest_bid {
+            drift.add_side(book.bids.range(lowest..), &snapshot.bids);
+        }
+        let highest_ask = snapshot.asks.iter().map(|level| level.price).max();
+        if let Some(highest) = highest_ask {
+            drift.add_side(book.asks.range(..=highest), &snapshot.asks);
+        }
+        drift
+    }
+
+    fn add_side<'a>(&mut self, book: impl Iterator<Item = (&'a Decimal, &'a OrderQueue)>, snapshot: &[PriceLevel]) {
+        let mut expected: BTreeMap<Decimal, Decimal> = snapshot
+            .iter()
+            .filter(|level| level.qty > Decimal::ZERO)
+            .map(|level| (level.price, level.qty))
+            .collect();
+        self.snapshot_size += expected.values().sum::<Decimal>();
+        for (price, queue) in book {
+            let size = queue.total();
+            self.levels += 1;
+            match expected.remove(price) {
+                Some(qty) if qty == size => {}
+                Some(qty) => {
+                    self.mismatched += 1;
+                    self.size_error += (size - qty).abs();
+                }
+                None => {
+                    self.extra += 1;
+                    self.size_error += size;
+                }
+            }
+        }
+        self.levels += expected.len();
+        self.missing += expected.len();
+        self.size_error += expected.values().sum::<Decimal>();
+    }
+
+    pub fn drifted(&self) -> usize {
+        self.mismatched + self.missing + self.extra
+    }
+
+    // Share of the compared levels that differ
+    pub fn level_error(&self) -> f64 {
+        if self.levels == 0 {
+            return 0.0;
+        }
+        self.drifted() as f64 / self.levels as f64
+    }
+
+    // Size error relative to the snapshot's total size
+    pub fn size_error_ratio(&self) -> f64 {
+        if self.snapshot_size.is_zero() {
+            return 0.0;
+        }
+        (self.size_error / self.snapshot_size).to_f64().unwrap_or(0.0)
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "{}/{} levels off ({} mismatched, {} missing, {} extra), size error {} ({:.2}%)",
+            self.drifted(),
+            self.levels,
+            self.mismatched,
+            self.missing,
+            self.extra,
+            self.size_error.normalize(),
+            self.size_error_ratio() * 100.0
+        )
+    }
+}
+
+// Drift over a session
+#[derive(Clone, Debug, Default)]
+pub struct AuditStats {
+    pub audits: u64,
+    pub last: Option<BookDrift>,
+    // Audits that found no drift at all
+    pub clean: u64,
+    pub worst_level_error: f64,
+    level_error_sum: f64,
+    size_error_sum: f64,
+}
+
+impl AuditStats {
+    pub fn record(&mut self, drift: BookDrift) {
+        self.audits += 1;
+        if drift.drifted() == 0 {
+            self.clean += 1;
+        }
+        self.level_error_sum += drift.level_error();
+        self.size_error_sum += drift.size_error_ratio();
+        self.worst_level_error = self.worst_level_error.max(drift.level_error());
+        self.last = Some(drift);
+    }
+
+    pub fn mean_level_error(&self) -> f64 {
+        self.level_error_sum / self.audits.max(1) as f64
+    }
+
+    pub fn mean_size_error(&self) -> f64 {
+        self.size_error_sum / self.audits.max(1) as f64
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "{} audits, {} clean; levels off {:.2}% on average (worst {:.2}%), size error {:.2}% on average",
+            self.audits,
+            self.clean,
+            self.mean_level_error() * 100.0,
+            self.worst_level_error * 100.0,
+            self.mean_size_error() * 100.0
+        )
+    }
+}
@@ -0,0 +1,53 @@
+use multi_exchange_l3_est::orderbook::{Decomposition, EstimatorSettings, OrderBook};
+use multi_exchange_l3_est::simulator::{Accuracy, OrderFlowSimulator, SimulatorConfig};
+use std::env;
+
+// Runs the L3 estimator on simulated L2 diffs and scores its queues against the simulated orders.
+// Usage: estimator_accuracy [events] [--seed <n>] [--depth <ticks>] [--decomposition <single|power_law>]
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut events: u64 = 100_000;
+    let mut config = SimulatorConfig::default();
+    let mut estimator = EstimatorSettings::default();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => config.seed = args.next().ok_or("--seed requires a value")?.parse()?,
+            "--depth" => config.depth = args.next().ok_or("--depth requires a value")?.parse()?,
+            "--decomposition" => {
+                let name = args.next().ok_or("--decomposition requires a value")?;
+                estimator.decomposition =
+                    Decomposition::from_name(&name).ok_or(format!("unknown decomposition: {name}"))?;
+            }
+            _ => events = arg.parse()?,
+        }
+    }
+
+    let mut sim = OrderFlowSimulator::new(config.clone());
+    let mut book = OrderBook::new();
+    book.set_estimator(estimator);
+    book.apply_snapshot(&sim.snapshot());
+    println!("Seed {}, {} resting orders at the start", config.seed, sim.order_count());
+    let report_every = (events / 10).max(1);
+    for i in 1..=events {
+        book.handle_update(sim.step().update);
+        if i % report_every == 0 || i == events {
+            report(i, sim.time_ms(), &sim.accuracy(&book));
+        }
+    }
+    Ok(())
+}
+
+fn report(events: u64, time_ms: u64, accuracy: &Accuracy) {
+    println!(
+        "{events:>9} events ({:>7.1}s simulated): {}/{} levels exact ({:.1}%), recall {:.1}%, precision {:.1}% \
+         ({} true, {} estimated orders)",
+        time_ms as f64 / 1000.0,
+        accuracy.exact_levels,
+        accuracy.levels,
+        accuracy.exact_level_ratio() * 100.0,
+        accuracy.recall() * 100.0,
+        accuracy.precision() * 100.0,
+        accuracy.true_orders,
+        accuracy.estimated_orders,
+    );
+}
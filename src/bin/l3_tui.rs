@@ -0,0 +1,59 @@
+#[cfg(not(target_arch = "wasm32"))]
+use multi_exchange_l3_est::config::Config;
+#[cfg(not(target_arch = "wasm32"))]
+use multi_exchange_l3_est::exchanges::{net, ExchangeType};
+#[cfg(not(target_arch = "wasm32"))]
+use multi_exchange_l3_est::logging;
+#[cfg(not(target_arch = "wasm32"))]
+use multi_exchange_l3_est::tui;
+#[cfg(not(target_arch = "wasm32"))]
+use std::env;
+
+// A terminal has no browser counterpart; the web build is the GUI
+#[cfg(target_arch = "wasm32")]
+fn main() {}
+
+// Terminal frontend for hosts where the GUI cannot run, e.g. over SSH.
+// Usage: l3_tui [symbol] [--config <path>] [--exchange <name>] [--testnet] [--proxy <url>]
+//        [--log-level <filter>] [--log-file <path>]
+#[cfg(not(target_arch = "wasm32"))]
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let config = match args.iter().position(|a| a == "--config") {
+        Some(i) => Config::load(args.get(i + 1).ok_or("--config requires a value")?)?,
+        None => Config::default(),
+    };
+    let mut symbol = config.symbol.as_ref().map(|s| s.to_ascii_lowercase());
+    let mut exchange = config.exchange_type()?;
+    let mut settings = config.exchange_settings()?;
+    let mut network = config.network_settings()?;
+    // Console output would tear through the terminal UI, so records only go to the file
+    let mut logging = logging::LogSettings {
+        console: false,
+        ..config.logging.settings()
+    };
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--config" => {
+                args.next();
+            }
+            "--exchange" => {
+                let name = args.next().ok_or("--exchange requires a value")?;
+                exchange = Some(ExchangeType::from_name(&name).ok_or(format!("unknown or disabled exchange: {name}"))?);
+            }
+            "--testnet" => settings.testnet = true,
+            "--proxy" => network.proxy = Some(args.next().ok_or("--proxy requires a URL")?),
+            "--log-level" => logging.level = args.next().ok_or("--log-level requires a filter, e.g. debug")?,
+            "--log-file" => logging.file = Some(args.next().ok_or("--log-file requires a file path")?.into()),
+            _ => symbol = Some(arg.to_ascii_lowercase()),
+        }
+    }
+    logging::init(&logging)?;
+    net::configure(network)?;
+    let exchange = exchange.unwrap_or(ExchangeType::ALL[0]);
+    let symbol = exchange.adapt_symbol_with(&symbol.unwrap_or_else(|| "dogeusdt".to_string()), &settings);
+    tui::run(symbol, exchange, settings)?;
+    Ok(())
+}
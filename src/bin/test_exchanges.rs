@@ -1,6 +1,13 @@
-use multi_exchange_l3_est::exchanges::{ExchangeType, Exchange};
+#[cfg(not(target_arch = "wasm32"))]
+use multi_exchange_l3_est::exchanges::ExchangeType;
+#[cfg(not(target_arch = "wasm32"))]
 use tokio::time::{timeout, Duration};
 
+// Connects to the live venues from a terminal; there is nothing to run in a browser
+#[cfg(target_arch = "wasm32")]
+fn main() {}
+
+#[cfg(not(target_arch = "wasm32"))]
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Testing Multi-Exchange L3 Order Book Estimator...\n");
@@ -9,8 +16,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("🔄 Testing Binance Exchange...");
     let binance = ExchangeType::Binance.create_exchange();
     println!("✅ Exchange name: {}", binance.get_name());
-    println!("✅ Symbol formatting: {} -> {}", "DOGEUSDT", binance.format_symbol("DOGEUSDT"));
-    let (price_prec, qty_prec) = binance.get_precision("DOGEUSDT");
+    println!("✅ Symbol formatting: DOGEUSDT -> {}", binance.format_symbol("DOGEUSDT"));
+    let (price_prec, qty_prec) = binance.get_precision("DOGEUSDT").await;
     println!("✅ Precision: price={}, quantity={}", price_prec, qty_prec);
     
     // Test connection (with timeout)
@@ -24,8 +31,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("\n🔄 Testing Hyperliquid Exchange...");
     let hyperliquid = ExchangeType::Hyperliquid.create_exchange();
     println!("✅ Exchange name: {}", hyperliquid.get_name());
-    println!("✅ Symbol formatting: {} -> {}", "SOL", hyperliquid.format_symbol("SOL"));
-    let (price_prec, qty_prec) = hyperliquid.get_precision("SOL");
+    println!("✅ Symbol formatting: SOL -> {}", hyperliquid.format_symbol("SOL"));
+    let (price_prec, qty_prec) = hyperliquid.get_precision("SOL").await;
     println!("✅ Precision: price={}, quantity={}", price_prec, qty_prec);
 
     // Test connection (with timeout)
@@ -35,8 +42,143 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         Err(_) => println!("⚠️  WebSocket connection timeout (expected in some environments)"),
     }
 
+    // Test Bitstamp
+    println!("\n🔄 Testing Bitstamp Exchange...");
+    let bitstamp = ExchangeType::Bitstamp.create_exchange();
+    println!("✅ Exchange name: {}", bitstamp.get_name());
+    println!("✅ Symbol formatting: BTCUSD -> {}", bitstamp.format_symbol("BTCUSD"));
+    let (price_prec, qty_prec) = bitstamp.get_precision("btcusd").await;
+    println!("✅ Precision: price={}, quantity={}", price_prec, qty_prec);
+
+    // Test connection (with timeout)
+    match timeout(Duration::from_secs(5), bitstamp.connect(&bitstamp.format_symbol("BTCUSD"))).await {
+        Ok(Ok(_)) => println!("✅ WebSocket connection successful"),
+        Ok(Err(e)) => println!("⚠️  WebSocket connection failed: {}", e),
+        Err(_) => println!("⚠️  WebSocket connection timeout (expected in some environments)"),
+    }
+
+    // Test Gate.io
+    println!("\n🔄 Testing Gate.io Exchange...");
+    let gateio = ExchangeType::Gateio.create_exchange();
+    println!("✅ Exchange name: {}", gateio.get_name());
+    println!("✅ Symbol formatting: doge_usdt -> {}", gateio.format_symbol("doge_usdt"));
+    let (price_prec, qty_prec) = gateio.get_precision("DOGE_USDT").await;
+    println!("✅ Precision: price={}, quantity={}", price_prec, qty_prec);
+
+    // Test connection (with timeout)
+    match timeout(Duration::from_secs(5), gateio.connect(&gateio.format_symbol("DOGE_USDT"))).await {
+        Ok(Ok(_)) => println!("✅ WebSocket connection successful"),
+        Ok(Err(e)) => println!("⚠️  WebSocket connection failed: {}", e),
+        Err(_) => println!("⚠️  WebSocket connection timeout (expected in some environments)"),
+    }
+
+    // Test Bitget
+    println!("\n🔄 Testing Bitget Exchange...");
+    let bitget = ExchangeType::Bitget.create_exchange();
+    println!("✅ Exchange name: {}", bitget.get_name());
+    println!("✅ Symbol formatting: dogeusdt -> {}", bitget.format_symbol("dogeusdt"));
+    let (price_prec, qty_prec) = bitget.get_precision("DOGEUSDT").await;
+    println!("✅ Precision: price={}, quantity={}", price_prec, qty_prec);
+
+    // Test connection (with timeout)
+    match timeout(Duration::from_secs(5), bitget.connect(&bitget.format_symbol("DOGEUSDT"))).await {
+        Ok(Ok(_)) => println!("✅ WebSocket connection successful"),
+        Ok(Err(e)) => println!("⚠️  WebSocket connection failed: {}", e),
+        Err(_) => println!("⚠️  WebSocket connection timeout (expected in some environments)"),
+    }
+
+    // Test KuCoin
+    println!("\n🔄 Testing KuCoin Exchange...");
+    let kucoin = ExchangeType::Kucoin.create_exchange();
+    println!("✅ Exchange name: {}", kucoin.get_name());
+    println!("✅ Symbol formatting: dogeusdtm -> {}", kucoin.format_symbol("dogeusdtm"));
+    let (price_prec, qty_prec) = kucoin.get_precision("DOGEUSDTM").await;
+    println!("✅ Precision: price={}, quantity={}", price_prec, qty_prec);
+
+    // Test connection (with timeout)
+    match timeout(Duration::from_secs(5), kucoin.connect(&kucoin.format_symbol("DOGEUSDTM"))).await {
+        Ok(Ok(_)) => println!("✅ WebSocket connection successful"),
+        Ok(Err(e)) => println!("⚠️  WebSocket connection failed: {}", e),
+        Err(_) => println!("⚠️  WebSocket connection timeout (expected in some environments)"),
+    }
+
+    // Test MEXC
+    println!("\n🔄 Testing MEXC Exchange...");
+    let mexc = ExchangeType::Mexc.create_exchange();
+    println!("✅ Exchange name: {}", mexc.get_name());
+    println!("✅ Symbol formatting: doge_usdt -> {}", mexc.format_symbol("doge_usdt"));
+    let (price_prec, qty_prec) = mexc.get_precision("DOGE_USDT").await;
+    println!("✅ Precision: price={}, quantity={}", price_prec, qty_prec);
+
+    // Test connection (with timeout)
+    match timeout(Duration::from_secs(5), mexc.connect(&mexc.format_symbol("DOGE_USDT"))).await {
+        Ok(Ok(_)) => println!("✅ WebSocket connection successful"),
+        Ok(Err(e)) => println!("⚠️  WebSocket connection failed: {}", e),
+        Err(_) => println!("⚠️  WebSocket connection timeout (expected in some environments)"),
+    }
+
+    // Test BitMEX
+    println!("\n🔄 Testing BitMEX Exchange...");
+    let bitmex = ExchangeType::Bitmex.create_exchange();
+    println!("✅ Exchange name: {}", bitmex.get_name());
+    println!("✅ Symbol formatting: xbtusd -> {}", bitmex.format_symbol("xbtusd"));
+    let (price_prec, qty_prec) = bitmex.get_precision("XBTUSD").await;
+    println!("✅ Precision: price={}, quantity={}", price_prec, qty_prec);
+
+    // Test connection (with timeout)
+    match timeout(Duration::from_secs(5), bitmex.connect(&bitmex.format_symbol("XBTUSD"))).await {
+        Ok(Ok(_)) => println!("✅ WebSocket connection successful"),
+        Ok(Err(e)) => println!("⚠️  WebSocket connection failed: {}", e),
+        Err(_) => println!("⚠️  WebSocket connection timeout (expected in some environments)"),
+    }
+
+    // Test Bitfinex
+    println!("\n🔄 Testing Bitfinex Exchange...");
+    let bitfinex = ExchangeType::Bitfinex.create_exchange();
+    println!("✅ Exchange name: {}", bitfinex.get_name());
+    println!("✅ Symbol formatting: btcusd -> {}", bitfinex.format_symbol("btcusd"));
+    let (price_prec, qty_prec) = bitfinex.get_precision("tBTCUSD").await;
+    println!("✅ Precision: price={}, quantity={}", price_prec, qty_prec);
+
+    // Test connection (with timeout)
+    match timeout(Duration::from_secs(5), bitfinex.connect(&bitfinex.format_symbol("tBTCUSD"))).await {
+        Ok(Ok(_)) => println!("✅ WebSocket connection successful"),
+        Ok(Err(e)) => println!("⚠️  WebSocket connection failed: {}", e),
+        Err(_) => println!("⚠️  WebSocket connection timeout (expected in some environments)"),
+    }
+
+    // Test Aevo
+    println!("\n🔄 Testing Aevo Exchange...");
+    let aevo = ExchangeType::Aevo.create_exchange();
+    println!("✅ Exchange name: {}", aevo.get_name());
+    println!("✅ Symbol formatting: eth-perp -> {}", aevo.format_symbol("eth-perp"));
+    let (price_prec, qty_prec) = aevo.get_precision("ETH-PERP").await;
+    println!("✅ Precision: price={}, quantity={}", price_prec, qty_prec);
+
+    // Test connection (with timeout)
+    match timeout(Duration::from_secs(5), aevo.connect(&aevo.format_symbol("ETH-PERP"))).await {
+        Ok(Ok(_)) => println!("✅ WebSocket connection successful"),
+        Ok(Err(e)) => println!("⚠️  WebSocket connection failed: {}", e),
+        Err(_) => println!("⚠️  WebSocket connection timeout (expected in some environments)"),
+    }
+
+    // Test Drift
+    println!("\n🔄 Testing Drift Exchange...");
+    let drift = ExchangeType::Drift.create_exchange();
+    println!("✅ Exchange name: {}", drift.get_name());
+    println!("✅ Symbol formatting: sol-perp -> {}", drift.format_symbol("sol-perp"));
+    let (price_prec, qty_prec) = drift.get_precision("SOL-PERP").await;
+    println!("✅ Precision: price={}, quantity={}", price_prec, qty_prec);
+
+    // Test connection (with timeout)
+    match timeout(Duration::from_secs(5), drift.connect(&drift.format_symbol("SOL-PERP"))).await {
+        Ok(Ok(_)) => println!("✅ WebSocket connection successful"),
+        Ok(Err(e)) => println!("⚠️  WebSocket connection failed: {}", e),
+        Err(_) => println!("⚠️  WebSocket connection timeout (expected in some environments)"),
+    }
+
     println!("\n🎉 All exchange modules loaded successfully!");
-    println!("📊 The GUI application is ready to visualize order books from both exchanges.");
+    println!("📊 The GUI application is ready to visualize order books from all exchanges.");
     println!("🚀 Run 'cargo run --release dogeusdt' on a machine with a display to see the visualization.");
 
     Ok(())
@@ -0,0 +1,147 @@
+use super::{
+    collect_points, euclidean_distance, normalize, relabel_by_center, ClusterFeatures, ClusterInput, Clusterer, Point,
+};
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+
+const UNVISITED: usize = usize::MAX;
+const NOISE: usize = usize::MAX - 1;
+
+// Density-based clustering. The number of clusters follows the data; points in sparse
+// regions (typically the few very large orders) are grouped into a separate outlier class
+// instead of dragging a centroid away from the bulk of the book.
+pub struct Dbscan {
+    eps: f64,
+    min_points: usize,
+    features: ClusterFeatures,
+    num_labels: usize,
+    // Buffers reused by every fit
+    points: Vec<Point>,
+    labels: Vec<usize>,
+}
+
+impl Dbscan {
+    pub fn new(eps: f64, min_points: usize, features: ClusterFeatures) -> Self {
+        Self {
+            eps,
+            min_points: min_points.max(1),
+            features,
+            num_labels: 0,
+            points: vec![],
+            labels: vec![],
+        }
+    }
+
+    // Neighbours of `idx` within eps. `order` holds point indices sorted by qty, and
+    // `rank[idx]` is the position of `idx` in it; since |dq| <= distance, only the window of
+    // points with qty within eps has to be scanned.
+    fn region_query(&self, points: &[Point], order: &[usize], rank: &[usize], idx: usize) -> Vec<usize> {
+        let p = &points[idx];
+        let mut neighbours = vec![];
+        for &j in order[..rank[idx]].iter().rev() {
+            if p.qty - points[j].qty > self.eps {
+                break;
+            }
+            if euclidean_distance(p, &points[j]) <= self.eps {
+                neighbours.push(j);
+            }
+        }
+        for &j in order[rank[idx]..].iter() {
+            if points[j].qty - p.qty > self.eps {
+                break;
+            }
+            if euclidean_distance(p, &points[j]) <= self.eps {
+                neighbours.push(j);
+            }
+        }
+        neighbours
+    }
+}
+
+impl Clusterer for Dbscan {
+    fn fit(&mut self, input: &ClusterInput) -> &[usize] {
+        let mut points = std::mem::take(&mut self.points);
+        let mut labels = std::mem::take(&mut self.labels);
+        collect_points(input, self.features, &mut points);
+        labels.clear();
+        if points.is_empty() {
+            self.num_labels = 0;
+            self.points = points;
+            self.labels = labels;
+            return &self.labels;
+        }
+        normalize(&mut points);
+
+        let mut order: Vec<usize> = (0..points.len()).collect();
+        order.sort_by(|&a, &b| points[a].qty.partial_cmp(&points[b].qty).unwrap_or(Ordering::Equal));
+        let mut rank = vec![0; points.len()];
+        for (r, &i) in order.iter().enumerate() {
+            rank[i] = r;
+        }
+
+        labels.resize(points.len(), UNVISITED);
+        let mut cluster = 0;
+        for i in 0..points.len() {
+            if labels[i] != UNVISITED {
+                continue;
+            }
+            let neighbours = self.region_query(&points, &order, &rank, i);
+            if neighbours.len() < self.min_points {
+                labels[i] = NOISE;
+                continue;
+            }
+
+            // Expand the cluster from this core point
+            labels[i] = cluster;
+            let mut frontier: VecDeque<usize> = neighbours.into();
+            while let Some(j) = frontier.pop_front() {
+                if labels[j] == NOISE {
+                    labels[j] = cluster; // Border point
+                }
+                if labels[j] != UNVISITED {
+                    continue;
+                }
+                labels[j] = cluster;
+                let next = self.region_query(&points, &order, &rank, j);
+                if next.len() >= self.min_points {
+                    frontier.extend(next);
+                }
+            }
+            cluster += 1;
+        }
+
+        // Outliers get their own label so they can be colored distinctly
+        let has_noise = labels.contains(&NOISE);
+        let num_labels = cluster + usize::from(has_noise);
+        for label in labels.iter_mut() {
+            if *label == NOISE {
+                *label = cluster;
+            }
+        }
+
+        let mut sums = vec![0.0; num_labels];
+        let mut counts = vec![0usize; num_labels];
+        for (p, &label) in points.iter().zip(labels.iter()) {
+            sums[label] += p.qty;
+            counts[label] += 1;
+        }
+        let centers: Vec<f64> = sums
+            .iter()
+            .zip(counts.iter())
+            .map(|(&s, &c)| if c > 0 { s / c as f64 } else { 0.0 })
+            .collect();
+
+        self.num_labels = relabel_by_center(&mut labels, &centers);
+        self.points = points;
+        self.labels = labels;
+        &self.labels
+    }
+
+    fn labels(&self) -> &[usize] {
+        &self.labels
+    }
+
+    fn num_clusters(&self) -> usize {
+        self.num_labels
+    }
+}
@@ -0,0 +1,165 @@
+use super::{collect_points, normalize, relabel_by_center, ClusterFeatures, ClusterInput, Clusterer, Point};
+use std::cmp::Ordering;
+
+// Keeps components from collapsing onto a single point
+const MIN_VARIANCE: f64 = 1e-6;
+const TOLERANCE: f64 = 1e-6;
+
+const DIMS: usize = 2;
+
+// Gaussian mixture with diagonal covariance fitted with EM. Unlike k-means each component has
+// its own spread, so a wide component can absorb the heavy tail of large orders while narrow
+// ones resolve the dense region of small orders.
+pub struct GaussianMixture {
+    num_components: usize,
+    max_iter: usize,
+    features: ClusterFeatures,
+    means: Vec<[f64; DIMS]>,
+    variances: Vec<[f64; DIMS]>,
+    weights: Vec<f64>,
+    // Buffers reused by every fit
+    points: Vec<Point>,
+    labels: Vec<usize>,
+}
+
+impl GaussianMixture {
+    pub fn new(num_components: usize, max_iter: usize, features: ClusterFeatures) -> Self {
+        Self {
+            num_components: num_components.max(1),
+            max_iter,
+            features,
+            means: vec![],
+            variances: vec![],
+            weights: vec![],
+            points: vec![],
+            labels: vec![],
+        }
+    }
+
+    // Means at points with evenly spaced qty quantiles, a shared variance scaled down from the
+    // global one (otherwise a single large order inflates it and every component merges),
+    // uniform weights
+    fn initialize(&mut self, points: &[Point]) {
+        let mut sorted: Vec<[f64; DIMS]> = points.iter().map(Point::coords).collect();
+        sorted.sort_by(|a, b| a[0].partial_cmp(&b[0]).unwrap_or(Ordering::Equal));
+
+        let k = self.num_components;
+        let n = sorted.len() as f64;
+        let mut variance = [0.0; DIMS];
+        for (d, v) in variance.iter_mut().enumerate() {
+            let mean = sorted.iter().map(|x| x[d]).sum::<f64>() / n;
+            *v = (sorted.iter().map(|x| (x[d] - mean).powi(2)).sum::<f64>() / n / (k * k) as f64).max(MIN_VARIANCE);
+        }
+
+        self.means = (0..k)
+            .map(|i| sorted[((i as f64 + 0.5) / k as f64 * (sorted.len() - 1) as f64) as usize])
+            .collect();
+        self.variances = vec![variance; k];
+        self.weights = vec![1.0 / k as f64; k];
+    }
+
+    fn log_density(&self, x: &[f64; DIMS], c: usize) -> f64 {
+        let mut log_p = self.weights[c].max(f64::MIN_POSITIVE).ln();
+        for ((&xd, &mean), &var) in x.iter().zip(&self.means[c]).zip(&self.variances[c]) {
+            log_p -= 0.5 * (2.0 * std::f64::consts::PI * var).ln() + (xd - mean).powi(2) / (2.0 * var);
+        }
+        log_p
+    }
+
+    // Responsibilities of each component for `x`, normalized with log-sum-exp
+    fn responsibilities(&self, x: &[f64; DIMS], out: &mut [f64]) {
+        let mut max_log = f64::NEG_INFINITY;
+        for (c, r) in out.iter_mut().enumerate() {
+            *r = self.log_density(x, c);
+            max_log = max_log.max(*r);
+        }
+        let mut total = 0.0;
+        for r in out.iter_mut() {
+            *r = (*r - max_log).exp();
+            total += *r;
+        }
+        for r in out.iter_mut() {
+            *r /= total;
+        }
+    }
+}
+
+impl Clusterer for GaussianMixture {
+    fn fit(&mut self, input: &ClusterInput) -> &[usize] {
+        let mut points = std::mem::take(&mut self.points);
+        let mut labels = std::mem::take(&mut self.labels);
+        collect_points(input, self.features, &mut points);
+        labels.clear();
+        if points.is_empty() {
+            self.points = points;
+            self.labels = labels;
+            return &self.labels;
+        }
+        normalize(&mut points);
+
+        let k = self.num_components;
+        if self.means.len() != k {
+            self.initialize(&points);
+        }
+
+        let mut resp = vec![0.0; k];
+        for _ in 0..self.max_iter {
+            // E-step accumulates the sufficient statistics directly
+            let mut nk = vec![0.0; k];
+            let mut sum_x = vec![[0.0; DIMS]; k];
+            let mut sum_x2 = vec![[0.0; DIMS]; k];
+            for p in &points {
+                let x = p.coords();
+                self.responsibilities(&x, &mut resp);
+                for c in 0..k {
+                    nk[c] += resp[c];
+                    for d in 0..DIMS {
+                        sum_x[c][d] += resp[c] * x[d];
+                        sum_x2[c][d] += resp[c] * x[d] * x[d];
+                    }
+                }
+            }
+
+            // M-step
+            let mut shift: f64 = 0.0;
+            for c in 0..k {
+                if nk[c] <= f64::EPSILON {
+                    continue;
+                }
+                for d in 0..DIMS {
+                    let mean = sum_x[c][d] / nk[c];
+                    shift = shift.max((mean - self.means[c][d]).abs());
+                    self.means[c][d] = mean;
+                    self.variances[c][d] = (sum_x2[c][d] / nk[c] - mean * mean).max(MIN_VARIANCE);
+                }
+                self.weights[c] = nk[c] / points.len() as f64;
+            }
+            if shift < TOLERANCE {
+                break;
+            }
+        }
+
+        labels.extend(points.iter().map(|p| {
+            self.responsibilities(&p.coords(), &mut resp);
+            resp.iter()
+                .enumerate()
+                .max_by(|a, b| a.1.partial_cmp(b.1).unwrap_or(Ordering::Equal))
+                .map(|(c, _)| c)
+                .unwrap_or(0)
+        }));
+
+        let centers: Vec<f64> = self.means.iter().map(|m| m[0]).collect();
+        relabel_by_center(&mut labels, &centers);
+        self.points = points;
+        self.labels = labels;
+        &self.labels
+    }
+
+    fn labels(&self) -> &[usize] {
+        &self.labels
+    }
+
+    fn num_clusters(&self) -> usize {
+        self.num_components
+    }
+}
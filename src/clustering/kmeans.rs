@@ -0,0 +1,396 @@
+use super::{
+    collect_points, euclidean_distance, normalize, relabel_by_center, ClusterFeatures, ClusterInput, Clusterer, Point,
+};
+use crate::orderbook::OrderQueue;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, VecDeque};
+use std::ops::RangeInclusive;
+
+// Cluster counts the automatic choice of k picks from
+pub const AUTO_K_RANGE: RangeInclusive<usize> = 3..=15;
+
+// Orders the silhouette of each candidate k is computed on, evenly strided through the input,
+// since the score is quadratic in the points
+const SILHOUETTE_SAMPLE: usize = 512;
+
+// Fits between two choices of k, about five seconds of a chart refitting every frame
+const AUTO_K_INTERVAL: usize = 300;
+
+// Full k-means passes per candidate k, stopping earlier once no label changes
+const SELECTION_ITERATIONS: usize = 50;
+
+// Mini-batch iterations a centroid may go without a sampled point before it is re-seeded
+const EMPTY_PATIENCE: usize = 8;
+
+// How the centroids of a fresh fit are placed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KMeansInit {
+    // At evenly spaced size ranks: deterministic, and each fit starts where the last one would
+    #[default]
+    Spaced,
+    // k-means++: each centroid drawn with probability proportional to the squared distance to
+    // the nearest one already placed, which spreads them over the tail of a heavily skewed
+    // size distribution instead of stacking them on the mass of small orders
+    #[serde(alias = "kmeans++")]
+    PlusPlus,
+}
+
+impl KMeansInit {
+    pub const ALL: [KMeansInit; 2] = [KMeansInit::Spaced, KMeansInit::PlusPlus];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            KMeansInit::Spaced => "Spaced",
+            KMeansInit::PlusPlus => "K-Means++",
+        }
+    }
+}
+
+// Mini-batch K-means with stability: uses previous centroids if provided, deterministic init if not, and label sorting
+pub struct MiniBatchKMeans {
+    num_clusters: usize,
+    batch_size: usize,
+    max_iter: usize,
+    features: ClusterFeatures,
+    // Picks `num_clusters` by silhouette every `AUTO_K_INTERVAL` fits
+    auto_k: bool,
+    // Fits left until k is chosen again
+    next_selection: usize,
+    init: KMeansInit,
+    // Moves centroids that stop capturing points onto the worst-served point
+    reseed_empty: bool,
+    // Draws the mini-batches
+    rng: StdRng,
+    centroids: Vec<Point>,
+    // Buffers reused by every fit
+    points: Vec<Point>,
+    labels: Vec<usize>,
+}
+
+impl MiniBatchKMeans {
+    // `reseed_empty`: a centroid that captures no point of the mini-batches for `EMPTY_PATIENCE`
+    // iterations in a row is moved to the point farthest from every centroid, as k-means++ would
+    // seed it. Without it such a centroid stays where it is, leaving a dead cluster that shares
+    // its color with a neighbour.
+    pub fn new(
+        num_clusters: usize,
+        batch_size: usize,
+        max_iter: usize,
+        features: ClusterFeatures,
+        reseed_empty: bool,
+    ) -> Self {
+        Self {
+            num_clusters,
+            batch_size,
+            max_iter,
+            features,
+            auto_k: false,
+            next_selection: 0,
+            init: KMeansInit::Spaced,
+            reseed_empty,
+            rng: StdRng::from_os_rng(),
+            centroids: vec![],
+            points: vec![],
+            labels: vec![],
+        }
+    }
+
+    // Chooses the number of clusters itself from `AUTO_K_RANGE`, by the silhouette of a full
+    // k-means on a subsample, and again every few hundred fits as the book changes. The count
+    // given to `new` is kept until the input has enough distinct orders to choose.
+    pub fn auto_k(mut self, enabled: bool) -> Self {
+        self.auto_k = enabled;
+        self
+    }
+
+    // Draws the mini-batches from `seed`, so the same inputs fitted in the same order give the
+    // same labels on every run
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+
+    // Placement of the centroids when a fit has none to start from; k-means++ draws from the
+    // same generator as the mini-batches, so `seed` makes it repeat too
+    pub fn init(mut self, init: KMeansInit) -> Self {
+        self.init = init;
+        self
+    }
+
+    fn closest_centroid(&self, p: &Point) -> usize {
+        closest(&self.centroids, p)
+    }
+
+    fn plus_plus_centroids(&mut self, points: &[Point]) -> Vec<Point> {
+        let mut centroids = vec![points[self.rng.random_range(0..points.len())]];
+        // Squared distance of every point to its closest centroid so far
+        let mut weights: Vec<f64> = points.iter().map(|p| euclidean_distance(p, &centroids[0]).powi(2)).collect();
+        while centroids.len() < self.num_clusters {
+            let total: f64 = weights.iter().sum();
+            let next = if total > 0.0 {
+                let mut target = self.rng.random::<f64>() * total;
+                let index = weights.iter().position(|&w| {
+                    target -= w;
+                    target < 0.0
+                });
+                // Rounding can leave the target just past the last weight
+                points[index.unwrap_or_else(|| weights.iter().rposition(|&w| w > 0.0).unwrap_or(0))]
+            } else {
+                // Fewer distinct points than clusters; the extra centroids get re-seeded or stay empty
+                centroids[0]
+            };
+            for (w, p) in weights.iter_mut().zip(points) {
+                *w = w.min(euclidean_distance(p, &next).powi(2));
+            }
+            centroids.push(next);
+        }
+        centroids
+    }
+
+    // The point farthest from its closest centroid, unless every point sits on one
+    fn farthest_point(&self, points: &[Point]) -> Option<Point> {
+        let distance = |p: &Point| euclidean_distance(p, &self.centroids[self.closest_centroid(p)]);
+        points
+            .iter()
+            .map(|p| (distance(p), p))
+            .filter(|&(dist, _)| dist > 0.0)
+            .max_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, &p)| p)
+    }
+}
+
+fn closest(centroids: &[Point], p: &Point) -> usize {
+    let mut min_dist = f64::INFINITY;
+    let mut min_idx = 0;
+    for (i, c) in centroids.iter().enumerate() {
+        let dist = euclidean_distance(p, c);
+        if dist < min_dist {
+            min_dist = dist;
+            min_idx = i;
+        }
+    }
+    min_idx
+}
+
+fn initialize_centroids(points: &[Point], num_clusters: usize) -> Vec<Point> {
+    let mut centroids = vec![];
+
+    // Deterministic initialization: sort by qty and pick evenly spaced points
+    let mut sorted: Vec<Point> = points.to_vec();
+    sorted.sort_by(|a, b| a.qty.partial_cmp(&b.qty).unwrap_or(Ordering::Equal));
+
+    let step = (sorted.len() - 1) / (num_clusters.max(1) - 1).max(1);
+    for i in 0..num_clusters {
+        let idx = (i * step).min(sorted.len() - 1);
+        centroids.push(sorted[idx]);
+    }
+
+    while centroids.len() < num_clusters && !sorted.is_empty() {
+        centroids.push(sorted[0]); // Fill remaining with first point if needed
+    }
+
+    centroids
+}
+
+// The k in `AUTO_K_RANGE` whose clustering of a subsample of `points` has the highest mean
+// silhouette; None when the subsample has fewer distinct points than the smallest k
+fn choose_k(points: &[Point]) -> Option<usize> {
+    let stride = points.len().div_ceil(SILHOUETTE_SAMPLE).max(1);
+    let sample: Vec<Point> = points.iter().step_by(stride).copied().collect();
+    let mut distinct: Vec<[f64; 2]> = sample.iter().map(Point::coords).collect();
+    distinct.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    distinct.dedup();
+
+    let mut labels = vec![];
+    let mut best: Option<(f64, usize)> = None;
+    for k in AUTO_K_RANGE.take_while(|&k| k <= distinct.len()) {
+        lloyd(&sample, k, &mut labels);
+        let score = silhouette(&sample, &labels, k);
+        if best.is_none_or(|(best_score, _)| score > best_score) {
+            best = Some((score, k));
+        }
+    }
+    best.map(|(_, k)| k)
+}
+
+// Batch k-means from the deterministic seeds until the labels settle
+fn lloyd(points: &[Point], k: usize, labels: &mut Vec<usize>) {
+    let mut centroids = initialize_centroids(points, k);
+    labels.clear();
+    labels.resize(points.len(), usize::MAX);
+    let mut sums = vec![(0.0, 0.0, 0usize); k];
+    for _ in 0..SELECTION_ITERATIONS {
+        let mut changed = false;
+        for (label, p) in labels.iter_mut().zip(points) {
+            let c = closest(&centroids, p);
+            changed |= *label != c;
+            *label = c;
+        }
+        if !changed {
+            break;
+        }
+        sums.fill((0.0, 0.0, 0));
+        for (&label, p) in labels.iter().zip(points) {
+            let sum = &mut sums[label];
+            *sum = (sum.0 + p.qty, sum.1 + p.dist, sum.2 + 1);
+        }
+        for (c, &(qty, dist, n)) in centroids.iter_mut().zip(&sums) {
+            if n > 0 {
+                *c = Point {
+                    qty: qty / n as f64,
+                    dist: dist / n as f64,
+                };
+            }
+        }
+    }
+}
+
+// Mean silhouette of a labelling: per point, how much closer it is on average to its own
+// cluster than to the nearest other one, from -1 to 1. Points alone in their cluster score 0.
+fn silhouette(points: &[Point], labels: &[usize], k: usize) -> f64 {
+    let mut sizes = vec![0usize; k];
+    for &label in labels {
+        sizes[label] += 1;
+    }
+    let mut sums = vec![0.0; k];
+    let mut total = 0.0;
+    for (p, &own) in points.iter().zip(labels) {
+        if sizes[own] <= 1 {
+            continue;
+        }
+        sums.fill(0.0);
+        for (q, &label) in points.iter().zip(labels) {
+            sums[label] += euclidean_distance(p, q);
+        }
+        let a = sums[own] / (sizes[own] - 1) as f64;
+        let b = (0..k)
+            .filter(|&c| c != own && sizes[c] > 0)
+            .map(|c| sums[c] / sizes[c] as f64)
+            .fold(f64::INFINITY, f64::min);
+        let scale = a.max(b);
+        if b.is_finite() && scale > 0.0 {
+            total += (b - a) / scale;
+        }
+    }
+    total / points.len() as f64
+}
+
+impl Clusterer for MiniBatchKMeans {
+    // Fit on data, using previous centroids if available
+    fn fit(&mut self, input: &ClusterInput) -> &[usize] {
+        let mut points = std::mem::take(&mut self.points);
+        let mut labels = std::mem::take(&mut self.labels);
+        collect_points(input, self.features, &mut points);
+        labels.clear();
+
+        if points.is_empty() {
+            self.points = points;
+            self.labels = labels;
+            return &self.labels;
+        }
+
+        normalize(&mut points);
+
+        if self.auto_k {
+            if self.next_selection == 0 {
+                if let Some(k) = choose_k(&points) {
+                    self.num_clusters = k;
+                    self.next_selection = AUTO_K_INTERVAL;
+                }
+            }
+            self.next_selection = self.next_selection.saturating_sub(1);
+        }
+
+        // Initialize centroids if not already set
+        if self.centroids.is_empty() || self.centroids.len() != self.num_clusters {
+            self.centroids = match self.init {
+                KMeansInit::Spaced => initialize_centroids(&points, self.num_clusters),
+                KMeansInit::PlusPlus => self.plus_plus_centroids(&points),
+            };
+        }
+
+        // Mini-batch updates
+        let mut counts = vec![0; self.num_clusters];
+        let mut sums = vec![(0.0, 0.0); self.num_clusters];
+        // Consecutive iterations each centroid captured nothing
+        let mut idle = vec![0; self.num_clusters];
+        for _ in 0..self.max_iter {
+            counts.fill(0);
+            sums.fill((0.0, 0.0));
+
+            // Mini-batch sampled with replacement
+            for _ in 0..self.batch_size.min(points.len()) {
+                let p = points[self.rng.random_range(0..points.len())];
+                let closest = self.closest_centroid(&p);
+                sums[closest].0 += p.qty;
+                sums[closest].1 += p.dist;
+                counts[closest] += 1;
+            }
+
+            for i in 0..self.num_clusters {
+                if counts[i] > 0 {
+                    let lr = 1.0 / counts[i] as f64; // Learning rate
+                    let n = counts[i] as f64;
+                    let c = &mut self.centroids[i];
+                    c.qty = (1.0 - lr) * c.qty + lr * (sums[i].0 / n);
+                    c.dist = (1.0 - lr) * c.dist + lr * (sums[i].1 / n);
+                }
+            }
+
+            if self.reseed_empty {
+                for i in 0..self.num_clusters {
+                    idle[i] = if counts[i] > 0 { 0 } else { idle[i] + 1 };
+                    if idle[i] >= EMPTY_PATIENCE {
+                        idle[i] = 0;
+                        if let Some(p) = self.farthest_point(&points) {
+                            self.centroids[i] = p;
+                        }
+                    }
+                }
+            }
+        }
+
+        // Assign labels
+        labels.extend(points.iter().map(|p| self.closest_centroid(p)));
+
+        // Stabilize labels by sorting based on centroid qty
+        let centers: Vec<f64> = self.centroids.iter().map(|c| c.qty).collect();
+        relabel_by_center(&mut labels, &centers);
+
+        self.points = points;
+        self.labels = labels;
+        &self.labels
+    }
+
+    fn labels(&self) -> &[usize] {
+        &self.labels
+    }
+
+    fn num_clusters(&self) -> usize {
+        self.num_clusters
+    }
+}
+
+// Usage in cluster_order_book
+#[allow(dead_code)]
+pub fn cluster_order_book(
+    order_book: &BTreeMap<Decimal, OrderQueue>,
+    num_classes: usize,
+    batch_size: usize,
+    max_iter: usize,
+) -> BTreeMap<Decimal, VecDeque<(Decimal, usize)>> {
+    let mut kmeans = MiniBatchKMeans::new(num_classes, batch_size, max_iter, ClusterFeatures::default(), true);
+
+    let mut input = ClusterInput::new();
+    input.fill(order_book);
+    let labels = kmeans.fit(&input);
+    input
+        .labelled(labels)
+        .map(|(price, sizes, labels)| (price, sizes.iter().copied().zip(labels.iter().copied()).collect()))
+        .collect()
+}
@@ -0,0 +1,386 @@
+pub mod dbscan;
+pub mod gmm;
+pub mod kmeans;
+pub mod quantile;
+
+use crate::orderbook::OrderQueue;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+pub use dbscan::Dbscan;
+pub use gmm::GaussianMixture;
+pub use kmeans::{KMeansInit, MiniBatchKMeans, AUTO_K_RANGE};
+pub use quantile::QuantileBands;
+
+// Point structure for clustering: order size plus an optional price dimension, which stays
+// at zero unless `ClusterFeatures::price_distance` is enabled
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Point {
+    pub(crate) qty: f64,
+    pub(crate) dist: f64,
+}
+
+impl Point {
+    pub(crate) fn coords(&self) -> [f64; 2] {
+        [self.qty, self.dist]
+    }
+}
+
+pub(crate) fn euclidean_distance(a: &Point, b: &Point) -> f64 {
+    ((a.qty - b.qty).powi(2) + (a.dist - b.dist).powi(2)).sqrt()
+}
+
+// Which features the clustering sees
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClusterFeatures {
+    // Cluster on ln(qty) so a few huge orders don't squash everything else into one cluster
+    pub log_qty: bool,
+    // Add each order's price distance from the touch as a second dimension
+    pub price_distance: bool,
+}
+
+impl Default for ClusterFeatures {
+    fn default() -> Self {
+        Self {
+            log_qty: true,
+            price_distance: false,
+        }
+    }
+}
+
+pub(crate) fn normalize(points: &mut [Point]) {
+    if points.is_empty() {
+        return;
+    }
+
+    let mut min_q = f64::MAX;
+    let mut max_q = f64::MIN;
+    let mut min_d = f64::MAX;
+    let mut max_d = f64::MIN;
+
+    for p in points.iter() {
+        min_q = min_q.min(p.qty);
+        max_q = max_q.max(p.qty);
+        min_d = min_d.min(p.dist);
+        max_d = max_d.max(p.dist);
+    }
+
+    let range_q = max_q - min_q;
+    let range_d = max_d - min_d;
+
+    for p in points.iter_mut() {
+        if range_q > 0.0 {
+            p.qty = (p.qty - min_q) / range_q;
+        }
+        if range_d > 0.0 {
+            p.dist = (p.dist - min_d) / range_d;
+        }
+    }
+}
+
+// Levels to cluster as (price, order sizes), flattened into buffers that keep their capacity
+// when refilled, so clustering the top of the book every frame neither clones the book nor
+// allocates
+#[derive(Clone, Debug, Default)]
+pub struct ClusterInput {
+    prices: Vec<Decimal>,
+    // End of each level's sizes in `sizes`
+    ends: Vec<usize>,
+    // Positive order sizes in level then queue order
+    sizes: Vec<Decimal>,
+    // Level at which each appended side after the first starts
+    segments: Vec<usize>,
+}
+
+impl ClusterInput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn clear(&mut self) {
+        self.prices.clear();
+        self.ends.clear();
+        self.sizes.clear();
+        self.segments.clear();
+    }
+
+    // Appends a level; orders that are not positive are left out
+    pub fn push_level(&mut self, price: Decimal, sizes: impl IntoIterator<Item = Decimal>) {
+        self.prices.push(price);
+        self.sizes.extend(sizes.into_iter().filter(|&qty| qty > Decimal::ZERO));
+        self.ends.push(self.sizes.len());
+    }
+
+    // Appends the levels of `other` as a side of their own, whose price distances are measured
+    // from its own first level, so both sides of a book can be clustered in one fit
+    pub fn append(&mut self, other: &ClusterInput) {
+        let (levels, orders) = (self.prices.len(), self.sizes.len());
+        if levels > 0 && !other.prices.is_empty() {
+            self.segments.push(levels);
+        }
+        self.segments.extend(other.segments.iter().map(|&level| level + levels));
+        self.prices.extend(&other.prices);
+        self.ends.extend(other.ends.iter().map(|&end| end + orders));
+        self.sizes.extend(&other.sizes);
+    }
+
+    // Replaces the input with `levels`, in the order given, e.g. the top of a book side
+    pub fn fill<'a>(&mut self, levels: impl IntoIterator<Item = (&'a Decimal, &'a OrderQueue)>) {
+        self.fill_with(levels, |_, qty| qty);
+    }
+
+    // As `fill`, with every order's size mapped by `size(price, qty)`, e.g. to its notional
+    pub fn fill_with<'a>(
+        &mut self,
+        levels: impl IntoIterator<Item = (&'a Decimal, &'a OrderQueue)>,
+        size: impl Fn(Decimal, Decimal) -> Decimal,
+    ) {
+        self.clear();
+        for (&price, queue) in levels {
+            self.push_level(price, queue.sizes().map(|qty| size(price, qty)));
+        }
+    }
+
+    // Number of orders, i.e. of labels a fit returns
+    pub fn len(&self) -> usize {
+        self.sizes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sizes.is_empty()
+    }
+
+    pub fn levels(&self) -> impl DoubleEndedIterator<Item = (Decimal, &[Decimal])> + '_ {
+        self.prices.iter().enumerate().map(|(i, &price)| (price, &self.sizes[self.start(i)..self.ends[i]]))
+    }
+
+    // Each level with its orders' labels, given the labels of a fit on this input
+    pub fn labelled<'a>(
+        &'a self,
+        labels: &'a [usize],
+    ) -> impl DoubleEndedIterator<Item = (Decimal, &'a [Decimal], &'a [usize])> {
+        self.prices.iter().enumerate().map(move |(i, &price)| {
+            let range = self.start(i)..self.ends[i];
+            (price, &self.sizes[range.clone()], labels.get(range).unwrap_or_default())
+        })
+    }
+
+    // What each cluster of a fit on this input holds, indexed by label. Clusters the fit left
+    // empty are listed with no orders.
+    pub fn summarize(&self, labels: &[usize], num_clusters: usize) -> Vec<ClusterSummary> {
+        self.summarize_with(labels, num_clusters, |price, qty| price * qty)
+    }
+
+    // As `summarize`, valuing each order at `notional(price, qty)`, for sizes that are not in
+    // the base asset
+    pub fn summarize_with(
+        &self,
+        labels: &[usize],
+        num_clusters: usize,
+        notional: impl Fn(Decimal, Decimal) -> Decimal,
+    ) -> Vec<ClusterSummary> {
+        let num_clusters = labels.iter().map(|&label| label + 1).max().unwrap_or(0).max(num_clusters);
+        let mut summaries = vec![ClusterSummary::default(); num_clusters];
+        for (price, sizes, labels) in self.labelled(labels) {
+            for (&qty, &label) in sizes.iter().zip(labels) {
+                let summary = &mut summaries[label];
+                summary.orders += 1;
+                summary.total_qty += qty;
+                summary.notional += notional(price, qty);
+            }
+        }
+        summaries
+    }
+
+    fn start(&self, level: usize) -> usize {
+        if level == 0 {
+            0
+        } else {
+            self.ends[level - 1]
+        }
+    }
+}
+
+// The orders one label of a fit covers, in the input's size units whatever features the fit saw
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ClusterSummary {
+    pub orders: usize,
+    pub total_qty: Decimal,
+    // Quote currency value of the orders, price × size unless summarized otherwise
+    pub notional: Decimal,
+}
+
+impl ClusterSummary {
+    // Mean order size, the cluster's centroid in quantity units; None for an empty cluster
+    pub fn centroid_size(&self) -> Option<Decimal> {
+        (self.orders > 0).then(|| self.total_qty / Decimal::from(self.orders))
+    }
+}
+
+// Refills `points` with every order of `input`, in the order labels are returned by
+// `Clusterer::fit`.
+//
+// Each side of the input lies entirely on one side of the mid, so the distance from the mid
+// differs from the distance to the side's first level only by a constant, which does not
+// survive `normalize`; no mid price is needed.
+pub(crate) fn collect_points(input: &ClusterInput, features: ClusterFeatures, points: &mut Vec<Point>) {
+    points.clear();
+    let mut base = input.prices.first().copied().unwrap_or(Decimal::ZERO);
+    let mut segments = input.segments.iter().peekable();
+    for (level, (price, sizes)) in input.levels().enumerate() {
+        if segments.next_if_eq(&&level).is_some() {
+            base = price;
+        }
+        let dist = if features.price_distance {
+            (price - base).abs().to_f64().unwrap_or(0.0)
+        } else {
+            0.0
+        };
+        points.extend(sizes.iter().map(|qty| {
+            let qty = qty.to_f64().unwrap_or(0.0);
+            let qty = if features.log_qty { qty.ln() } else { qty };
+            Point { qty, dist }
+        }));
+    }
+}
+
+// Renumbers labels so that label 0 is the cluster with the smallest center, keeping
+// colors stable between fits. Returns the number of distinct labels.
+pub(crate) fn relabel_by_center(labels: &mut [usize], centers: &[f64]) -> usize {
+    let mut center_indices: Vec<usize> = (0..centers.len()).collect();
+    center_indices.sort_by(|&a, &b| centers[a].partial_cmp(&centers[b]).unwrap_or(Ordering::Equal));
+
+    let mut label_map = HashMap::new();
+    for (new_label, &old_label) in center_indices.iter().enumerate() {
+        label_map.insert(old_label, new_label);
+    }
+
+    for label in labels.iter_mut() {
+        *label = *label_map.get(label).unwrap_or(&0);
+    }
+    centers.len()
+}
+
+// Common interface for the order-size clustering algorithms. Clusterers keep their working
+// buffers between fits, so a long-lived one allocates only when the input grows.
+pub trait Clusterer {
+    // Assigns a label to every order of `input`, in level then queue order. Labels are sorted
+    // by cluster size so that higher labels mean larger orders.
+    fn fit(&mut self, input: &ClusterInput) -> &[usize];
+
+    // Labels returned by the last `fit`
+    fn labels(&self) -> &[usize];
+
+    // Number of distinct labels produced by the last `fit`
+    fn num_clusters(&self) -> usize;
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClusteringAlgorithm {
+    #[default]
+    KMeans,
+    Dbscan,
+    #[serde(alias = "gmm")]
+    GaussianMixture,
+    Quantile,
+}
+
+impl ClusteringAlgorithm {
+    pub const ALL: [ClusteringAlgorithm; 4] = [
+        ClusteringAlgorithm::KMeans,
+        ClusteringAlgorithm::Dbscan,
+        ClusteringAlgorithm::GaussianMixture,
+        ClusteringAlgorithm::Quantile,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ClusteringAlgorithm::KMeans => "K-Means",
+            ClusteringAlgorithm::Dbscan => "DBSCAN",
+            ClusteringAlgorithm::GaussianMixture => "Gaussian Mixture",
+            ClusteringAlgorithm::Quantile => "Quantile Bands",
+        }
+    }
+
+    pub fn build(&self, params: &ClusterParams) -> Box<dyn Clusterer> {
+        match self {
+            ClusteringAlgorithm::KMeans => {
+                let kmeans = MiniBatchKMeans::new(
+                    params.num_clusters,
+                    params.batch_size,
+                    params.max_iter,
+                    params.features,
+                    params.reseed_empty,
+                )
+                .auto_k(params.auto_k)
+                .init(params.init);
+                Box::new(match params.seed {
+                    Some(seed) => kmeans.seed(seed),
+                    None => kmeans,
+                })
+            }
+            ClusteringAlgorithm::Dbscan => Box::new(Dbscan::new(params.eps, params.min_points, params.features)),
+            ClusteringAlgorithm::GaussianMixture => Box::new(GaussianMixture::new(
+                params.num_clusters,
+                params.max_iter,
+                params.features,
+            )),
+            ClusteringAlgorithm::Quantile => Box::new(QuantileBands::new(params.num_clusters)),
+        }
+    }
+}
+
+// Tunables shared by the clustering algorithms; each one uses the subset it needs
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ClusterParams {
+    pub num_clusters: usize,
+    pub batch_size: usize,
+    pub max_iter: usize,
+    // DBSCAN neighbourhood radius, in normalized quantity units
+    pub eps: f64,
+    pub min_points: usize,
+    pub features: ClusterFeatures,
+    // K-means picks its own cluster count, `num_clusters` being where it starts
+    #[serde(default)]
+    pub auto_k: bool,
+    // Where k-means places the centroids of a fresh fit
+    #[serde(default)]
+    pub init: KMeansInit,
+    // One fit over the orders of both sides, so a label means the same sizes on bids and asks
+    #[serde(default)]
+    pub shared_sides: bool,
+    // K-means moves centroids that stop capturing orders onto the worst-served order. On by
+    // default, including for sessions saved before it was a setting
+    #[serde(default = "default_reseed_empty")]
+    pub reseed_empty: bool,
+    // Seeds the algorithms that sample (k-means), so fits repeat across runs; None draws a seed
+    // from the OS. Only ever set from the config file, so not saved with the session.
+    #[serde(skip)]
+    pub seed: Option<u64>,
+}
+
+impl Default for ClusterParams {
+    fn default() -> Self {
+        Self {
+            num_clusters: 10,
+            batch_size: 1024,
+            max_iter: 1024,
+            eps: 0.02,
+            min_points: 4,
+            features: ClusterFeatures::default(),
+            auto_k: false,
+            init: KMeansInit::default(),
+            shared_sides: false,
+            reseed_empty: true,
+            seed: None,
+        }
+    }
+}
+
+fn default_reseed_empty() -> bool {
+    ClusterParams::default().reseed_empty
+}
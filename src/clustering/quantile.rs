@@ -0,0 +1,81 @@
+use super::{ClusterInput, Clusterer};
+use rust_decimal::prelude::ToPrimitive;
+use std::collections::VecDeque;
+
+// Order sizes the bands are drawn from: every fit adds the orders it sees, so a book refitted
+// every frame covers its last few seconds, long-lived orders weighing more than fleeting ones
+pub const QUANTILE_WINDOW: usize = 20_000;
+
+// Sizes split into bands at evenly spaced quantiles of the recently observed sizes. There is
+// nothing to iterate: a fit sorts the window once and looks every order up among the band
+// edges, and since the window moves slowly an order keeps its band from one frame to the next.
+// The features are ignored; quantiles do not change under the log and price plays no part.
+pub struct QuantileBands {
+    num_bands: usize,
+    window: VecDeque<f64>,
+    // Lower edge of every band but the first, ascending
+    edges: Vec<f64>,
+    // Buffers reused by every fit
+    sorted: Vec<f64>,
+    labels: Vec<usize>,
+}
+
+impl QuantileBands {
+    pub fn new(num_bands: usize) -> Self {
+        Self {
+            num_bands: num_bands.max(1),
+            window: VecDeque::new(),
+            edges: vec![],
+            sorted: vec![],
+            labels: vec![],
+        }
+    }
+
+    // Band edges from the last fit
+    pub fn edges(&self) -> &[f64] {
+        &self.edges
+    }
+
+    fn observe(&mut self, input: &ClusterInput) {
+        for (_, sizes) in input.levels() {
+            for qty in sizes {
+                if self.window.len() == QUANTILE_WINDOW {
+                    self.window.pop_front();
+                }
+                self.window.push_back(qty.to_f64().unwrap_or(0.0));
+            }
+        }
+        self.sorted.clear();
+        self.sorted.extend(&self.window);
+        self.sorted.sort_by(f64::total_cmp);
+        self.edges.clear();
+        if self.sorted.is_empty() {
+            return;
+        }
+        let n = self.sorted.len();
+        self.edges.extend((1..self.num_bands).map(|i| self.sorted[i * n / self.num_bands]));
+    }
+}
+
+impl Clusterer for QuantileBands {
+    fn fit(&mut self, input: &ClusterInput) -> &[usize] {
+        self.observe(input);
+        self.labels.clear();
+        for (_, sizes) in input.levels() {
+            for qty in sizes {
+                let qty = qty.to_f64().unwrap_or(0.0);
+                // Sizes at an edge belong to the band above it
+                self.labels.push(self.edges.partition_point(|&edge| edge <= qty));
+            }
+        }
+        &self.labels
+    }
+
+    fn labels(&self) -> &[usize] {
+        &self.labels
+    }
+
+    fn num_clusters(&self) -> usize {
+        self.num_bands
+    }
+}
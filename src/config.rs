@@ -0,0 +1,499 @@
+use crate::alerts::{AlertSettings, AlertTarget, AlertThreshold};
+use crate::anomaly::AnomalySettings;
+use crate::archive::{ArchiveFormat, ArchiveOptions};
+use crate::clustering::{ClusterFeatures, ClusterParams, ClusteringAlgorithm, KMeansInit};
+use crate::exchanges::net::{EndpointOverride, NetworkSettings};
+use crate::exchanges::{DepthSettings, ExchangeSettings, ExchangeType, SizeUnit};
+use crate::history::HistorySettings;
+use crate::logging::LogSettings;
+use crate::orderbook::{CountSplit, Decomposition};
+use crate::palette::{Palette, PalettePreset, Rgb};
+use crate::resume::ResumeOptions;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+// Startup settings from a TOML file (`--config <path>`). Every key is optional; command
+// line flags override what the file sets. See `config.example.toml`.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub exchange: Option<String>,
+    pub symbol: Option<String>,
+    // Binance market: spot, usdm or coinm
+    pub market: Option<String>,
+    // Binance and Hyperliquid connect to their testnets
+    pub testnet: bool,
+    pub display: DisplayConfig,
+    pub history: HistoryConfig,
+    pub cluster: ClusterConfig,
+    pub reconnect: ReconnectConfig,
+    pub alerts: AlertConfig,
+    // Message rate, spread and depth anomalies
+    pub anomalies: AnomalySettings,
+    pub network: NetworkConfig,
+    pub audit: AuditConfig,
+    pub metrics: MetricsConfig,
+    pub estimator: EstimatorConfig,
+    pub logging: LoggingConfig,
+    pub archive: ArchiveConfig,
+    pub resume: ResumeConfig,
+    // REST/WebSocket base URL overrides by venue, e.g. `[endpoints.gateio]`
+    pub endpoints: HashMap<String, EndpointOverride>,
+    // Snapshot, estimation and display depth by venue, e.g. `[depth.binance]`
+    pub depth: HashMap<String, DepthSettings>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DisplayConfig {
+    // Price levels per side in the order book table
+    pub book_rows: usize,
+    // Price levels per side in the order bar and cluster charts
+    pub chart_levels: usize,
+    pub theme: Theme,
+    pub palette: PaletteConfig,
+    // Units the book table, order bars and clustering show sizes in
+    pub size_unit: SizeUnit,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self {
+            book_rows: 20,
+            chart_levels: 100,
+            theme: Theme::Dark,
+            palette: PaletteConfig::default(),
+            size_unit: SizeUnit::Native,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+}
+
+// Chart colors as "#rrggbb": a preset, with any of its colors overridden
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PaletteConfig {
+    pub preset: PalettePreset,
+    pub bid: Option<Rgb>,
+    pub ask: Option<Rgb>,
+    pub bid_gradient: Option<Vec<Rgb>>,
+    pub ask_gradient: Option<Vec<Rgb>>,
+    pub largest: Option<Rgb>,
+    pub second_largest: Option<Rgb>,
+    pub background: Option<Rgb>,
+}
+
+impl PaletteConfig {
+    pub fn palette(&self) -> Result<Palette, ConfigError> {
+        let preset = Palette::preset(self.preset);
+        let palette = Palette {
+            bid: self.bid.unwrap_or(preset.bid),
+            ask: self.ask.unwrap_or(preset.ask),
+            bid_gradient: self.bid_gradient.clone().unwrap_or(preset.bid_gradient),
+            ask_gradient: self.ask_gradient.clone().unwrap_or(preset.ask_gradient),
+            largest: self.largest.unwrap_or(preset.largest),
+            second_largest: self.second_largest.unwrap_or(preset.second_largest),
+            background: self.background.or(preset.background),
+        };
+        palette.validate().map_err(|e| ConfigError::Invalid(format!("display.palette: {e}")))?;
+        Ok(palette)
+    }
+}
+
+// Book samples kept per tab for the heatmap and the mid price chart
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HistoryConfig {
+    pub retention_secs: u64,
+    pub sample_ms: u64,
+    // Levels per side in each sample
+    pub depth: usize,
+    // Memory budget per tab; the oldest samples are dropped to stay under it
+    pub max_memory_mb: usize,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        let defaults = HistorySettings::default();
+        Self {
+            retention_secs: defaults.retention.as_secs(),
+            sample_ms: defaults.sample_interval.as_millis() as u64,
+            depth: defaults.depth,
+            max_memory_mb: defaults.max_bytes >> 20,
+        }
+    }
+}
+
+impl HistoryConfig {
+    pub fn settings(&self) -> HistorySettings {
+        HistorySettings {
+            retention: Duration::from_secs(self.retention_secs),
+            sample_interval: Duration::from_millis(self.sample_ms.max(1)),
+            depth: self.depth.max(1),
+            max_bytes: self.max_memory_mb << 20,
+        }
+    }
+}
+
+// Initial clustering controls; unset parameters keep the algorithm defaults
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClusterConfig {
+    pub enabled: bool,
+    // kmeans, dbscan, gmm or quantile
+    pub algorithm: ClusteringAlgorithm,
+    pub num_clusters: Option<usize>,
+    // K-means chooses the cluster count by silhouette
+    pub auto_k: Option<bool>,
+    // spaced or plusplus
+    pub init: Option<KMeansInit>,
+    // Fit one model over both sides so cluster colors compare across them
+    pub shared_sides: Option<bool>,
+    // K-means moves centroids that capture no orders onto the worst-served one
+    pub reseed_empty: Option<bool>,
+    pub batch_size: Option<usize>,
+    pub max_iter: Option<usize>,
+    pub eps: Option<f64>,
+    pub min_points: Option<usize>,
+    pub log_qty: Option<bool>,
+    pub price_distance: Option<bool>,
+    // Makes the clustering reproducible across runs
+    pub seed: Option<u64>,
+}
+
+impl ClusterConfig {
+    pub fn params(&self) -> ClusterParams {
+        let defaults = ClusterParams::default();
+        let features = ClusterFeatures {
+            log_qty: self.log_qty.unwrap_or(defaults.features.log_qty),
+            price_distance: self.price_distance.unwrap_or(defaults.features.price_distance),
+        };
+        ClusterParams {
+            num_clusters: self.num_clusters.unwrap_or(defaults.num_clusters),
+            batch_size: self.batch_size.unwrap_or(defaults.batch_size),
+            max_iter: self.max_iter.unwrap_or(defaults.max_iter),
+            eps: self.eps.unwrap_or(defaults.eps),
+            min_points: self.min_points.unwrap_or(defaults.min_points),
+            features,
+            auto_k: self.auto_k.unwrap_or(defaults.auto_k),
+            init: self.init.unwrap_or(defaults.init),
+            shared_sides: self.shared_sides.unwrap_or(defaults.shared_sides),
+            reseed_empty: self.reseed_empty.unwrap_or(defaults.reseed_empty),
+            seed: self.seed,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReconnectConfig {
+    // Seconds without data before the stream is reconnected; 0 disables the watchdog
+    pub stale_timeout_secs: Option<f64>,
+    pub backoff_base_ms: Option<u64>,
+    pub backoff_max_ms: Option<u64>,
+}
+
+// Snapshot reconciliation audits
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuditConfig {
+    // Seconds between REST snapshots compared against the book; unset or 0 disables audits
+    pub interval_secs: Option<f64>,
+}
+
+// L3 estimator tunables
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EstimatorConfig {
+    // Reshaping of queues to venue-reported order counts: max_entropy or halving
+    pub count_split: CountSplit,
+    // Snapshot levels too large for one order: single or power_law
+    pub decomposition: Decomposition,
+}
+
+// Book metrics shown in the stats strip, published and exported
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetricsConfig {
+    // Quote notional of the buy/sell VWAPs; unset keeps the built-in default
+    pub vwap_notional: Option<Decimal>,
+}
+
+// Diagnostics written through `tracing`
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfig {
+    // Filter such as `debug` or `info,estimator=trace`; unset keeps `info`
+    pub level: Option<String>,
+    // JSON lines file the records are appended to
+    pub file: Option<PathBuf>,
+}
+
+impl LoggingConfig {
+    pub fn settings(&self) -> LogSettings {
+        let defaults = LogSettings::default();
+        LogSettings {
+            level: self.level.clone().unwrap_or(defaults.level),
+            file: self.file.clone(),
+            console: defaults.console,
+        }
+    }
+}
+
+// Periodic snapshots of the estimated book to disk, in headless mode
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ArchiveConfig {
+    // Directory the snapshots go to; unset disables the archive
+    pub dir: Option<PathBuf>,
+    // Seconds between snapshots; unset keeps 60
+    pub interval_secs: Option<f64>,
+    // json, or parquet with the `parquet` feature
+    pub format: Option<String>,
+    // Oldest snapshots are deleted beyond this many files or megabytes per instrument
+    pub max_files: Option<usize>,
+    pub max_mb: Option<u64>,
+}
+
+impl ArchiveConfig {
+    pub fn options(&self) -> Result<Option<ArchiveOptions>, ConfigError> {
+        let Some(dir) = &self.dir else {
+            return Ok(None);
+        };
+        let mut options = ArchiveOptions::new(dir.clone());
+        if let Some(secs) = self.interval_secs {
+            options.interval = Duration::try_from_secs_f64(secs)
+                .ok()
+                .filter(|interval| !interval.is_zero())
+                .ok_or_else(|| ConfigError::Invalid(format!("archive interval_secs must be positive, got {secs}")))?;
+        }
+        if let Some(name) = &self.format {
+            options.format = ArchiveFormat::from_name(name)
+                .ok_or_else(|| ConfigError::Invalid(format!("unsupported archive format: {name}")))?;
+        }
+        options.max_files = self.max_files;
+        options.max_bytes = self.max_mb.map(|mb| mb * 1024 * 1024);
+        Ok(Some(options))
+    }
+}
+
+// Saving the book and session statistics for the next start, in headless mode
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ResumeConfig {
+    // Directory of the saved states; unset disables resuming
+    pub dir: Option<PathBuf>,
+    // Seconds between saves while running; unset keeps 30
+    pub save_interval_secs: Option<f64>,
+}
+
+impl ResumeConfig {
+    pub fn options(&self) -> Result<Option<ResumeOptions>, ConfigError> {
+        let Some(dir) = &self.dir else {
+            return Ok(None);
+        };
+        let mut options = ResumeOptions::new(dir.clone());
+        if let Some(secs) = self.save_interval_secs {
+            options.interval = Duration::try_from_secs_f64(secs)
+                .ok()
+                .filter(|interval| !interval.is_zero())
+                .ok_or_else(|| ConfigError::Invalid(format!("resume save_interval_secs must be positive, got {secs}")))?;
+        }
+        Ok(Some(options))
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NetworkConfig {
+    // http://, socks5:// or socks5h:// proxy for all exchange traffic
+    pub proxy: Option<String>,
+}
+
+// Large order alerts
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AlertConfig {
+    pub enabled: bool,
+    // Fixed size threshold in base units; takes precedence over `median_multiple`
+    pub min_size: Option<Decimal>,
+    // Threshold as a multiple of the median level size
+    pub median_multiple: Option<f64>,
+    // Desktop notifications, with the notify feature
+    pub desktop: bool,
+    // Receives each batch of alerts as a JSON POST
+    pub webhook_url: Option<String>,
+    pub telegram: Option<TelegramConfig>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TelegramConfig {
+    pub bot_token: String,
+    pub chat_id: String,
+}
+
+impl AlertConfig {
+    pub fn settings(&self) -> AlertSettings {
+        let defaults = AlertSettings::default();
+        let threshold = match (self.min_size, self.median_multiple) {
+            (Some(size), _) => AlertThreshold::Absolute(size),
+            (None, Some(multiple)) => AlertThreshold::MedianMultiple(multiple),
+            (None, None) => defaults.threshold,
+        };
+        AlertSettings {
+            enabled: self.enabled,
+            threshold,
+            desktop: self.desktop,
+        }
+    }
+
+    pub fn targets(&self) -> Vec<AlertTarget> {
+        let webhook = self.webhook_url.iter().map(|url| AlertTarget::Webhook { url: url.clone() });
+        let telegram = self.telegram.iter().map(|t| AlertTarget::Telegram {
+            bot_token: t.bot_token.clone(),
+            chat_id: t.chat_id.clone(),
+        });
+        webhook.chain(telegram).collect()
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "cannot read config: {e}"),
+            ConfigError::Parse(e) => write!(f, "invalid config: {e}"),
+            ConfigError::Invalid(msg) => write!(f, "invalid config: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<std::io::Error> for ConfigError {
+    fn from(e: std::io::Error) -> Self {
+        ConfigError::Io(e)
+    }
+}
+
+impl From<toml::de::Error> for ConfigError {
+    fn from(e: toml::de::Error) -> Self {
+        ConfigError::Parse(e)
+    }
+}
+
+impl Config {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let text = std::fs::read_to_string(path)?;
+        let config: Config = toml::from_str(&text)?;
+        config.display.palette.palette()?;
+        Ok(config)
+    }
+
+    pub fn exchange_type(&self) -> Result<Option<ExchangeType>, ConfigError> {
+        self.exchange
+            .as_deref()
+            .map(|name| {
+                ExchangeType::from_name(name)
+                    .ok_or_else(|| ConfigError::Invalid(format!("unknown or disabled exchange: {name}")))
+            })
+            .transpose()
+    }
+
+    // Connector settings with the file's values applied over the defaults
+    pub fn exchange_settings(&self) -> Result<ExchangeSettings, ConfigError> {
+        let mut settings = ExchangeSettings::default();
+        let reconnect = &self.reconnect;
+        if let Some(secs) = reconnect.stale_timeout_secs {
+            settings.stale_timeout = Duration::try_from_secs_f64(secs)
+                .map_err(|e| ConfigError::Invalid(format!("stale_timeout_secs: {e}")))?;
+        }
+        if let Some(ms) = reconnect.backoff_base_ms {
+            settings.backoff_base = Duration::from_millis(ms);
+        }
+        if let Some(ms) = reconnect.backoff_max_ms {
+            settings.backoff_max = Duration::from_millis(ms);
+        }
+        if let Some(name) = &self.market {
+            #[cfg(feature = "binance")]
+            {
+                settings.binance_market = crate::exchanges::binance::BinanceMarket::from_name(name)
+                    .ok_or_else(|| ConfigError::Invalid(format!("unknown Binance market: {name}")))?;
+            }
+            #[cfg(not(feature = "binance"))]
+            return Err(ConfigError::Invalid(format!("market {name} requires the binance feature")));
+        }
+        settings.testnet = self.testnet;
+        if let Some(secs) = self.audit.interval_secs {
+            let interval = Duration::try_from_secs_f64(secs)
+                .map_err(|e| ConfigError::Invalid(format!("audit interval_secs: {e}")))?;
+            settings.audit_interval = Some(interval).filter(|interval| !interval.is_zero());
+        }
+        if let Some(notional) = self.metrics.vwap_notional {
+            if notional <= Decimal::ZERO {
+                return Err(ConfigError::Invalid("metrics vwap_notional must be positive".to_string()));
+            }
+            settings.vwap_notional = notional;
+        }
+        settings.estimator.count_split = self.estimator.count_split;
+        settings.estimator.decomposition = self.estimator.decomposition;
+        for (venue, depth) in &self.depth {
+            let exchange = ExchangeType::from_name(venue)
+                .ok_or_else(|| ConfigError::Invalid(format!("unknown or disabled exchange in [depth]: {venue}")))?;
+            if [depth.snapshot, depth.estimation, depth.display].contains(&Some(0)) {
+                return Err(ConfigError::Invalid(format!("depth.{venue}: depths must be at least 1")));
+            }
+            settings.depths.insert(exchange, *depth);
+        }
+        Ok(settings)
+    }
+
+    // Proxy and endpoint overrides; every venue key must name a compiled-in exchange, with a
+    // `_testnet` suffix for the testnet endpoints of those that have one
+    pub fn network_settings(&self) -> Result<NetworkSettings, ConfigError> {
+        let mut endpoints = HashMap::new();
+        for (venue, endpoint) in &self.endpoints {
+            let venue = venue.to_lowercase();
+            let (base, testnet) = match venue.strip_suffix("_testnet") {
+                Some(base) => (base, true),
+                None => (venue.as_str(), false),
+            };
+            let known = match base.strip_prefix("binance_") {
+                Some(market) => cfg!(feature = "binance") && ["spot", "usdm", "coinm"].contains(&market),
+                None => {
+                    base != "binance"
+                        && ExchangeType::from_name(base).is_some_and(|exchange| !testnet || exchange.has_testnet())
+                }
+            };
+            if !known {
+                return Err(ConfigError::Invalid(format!(
+                    "unknown endpoint key {venue}: use an exchange name, or binance_spot, binance_usdm or \
+                     binance_coinm, with _testnet appended for Binance and Hyperliquid testnet endpoints"
+                )));
+            }
+            endpoints.insert(venue, endpoint.clone());
+        }
+        Ok(NetworkSettings {
+            proxy: self.network.proxy.clone(),
+            endpoints,
+        })
+    }
+}
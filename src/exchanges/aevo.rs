@@ -0,0 +1,250 @@
+use super::error::check_status;
+use super::net::{self, WsMessage};
+use super::ratelimit::RateLimiter;
+use super::{DepthUpdate, Exchange, ExchangeError, ExchangeMessage, OrderBookSnapshot, PriceLevel, WsEndpoint};
+use futures_util::{SinkExt, StreamExt};
+use once_cell::sync::Lazy;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::mpsc::{self, Receiver};
+
+const REST_BASE: &str = "https://api.aevo.xyz";
+const WS_URL: &str = "wss://ws.aevo.xyz";
+// Aevo closes idle connections; any ping keeps them open
+const PING_INTERVAL: Duration = Duration::from_secs(20);
+
+#[derive(Serialize)]
+struct AevoRequest {
+    op: &'static str,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    data: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct AevoEnvelope {
+    #[serde(default)]
+    channel: String,
+    #[serde(default)]
+    error: Option<String>,
+    #[serde(default)]
+    data: serde_json::Value,
+}
+
+// Levels are [price, amount, implied volatility]; the volatility, only meaningful for options,
+// is dropped when they are read
+#[derive(Deserialize)]
+struct AevoBook {
+    // "snapshot" or "update" on the WebSocket, absent over REST
+    #[serde(default, rename = "type")]
+    kind: String,
+    #[serde(default)]
+    bids: Vec<PriceLevel>,
+    #[serde(default)]
+    asks: Vec<PriceLevel>,
+    // Nanoseconds since the Unix epoch
+    last_updated: String,
+}
+
+impl AevoBook {
+    fn event_time(&self) -> u64 {
+        self.last_updated.parse::<u64>().map_or(0, |ns| ns / 1_000_000)
+    }
+}
+
+#[derive(Deserialize)]
+struct AevoInstrument {
+    price_step: Decimal,
+    amount_step: Decimal,
+}
+
+// Public REST budget, well under the venue's per-IP limit
+static RATE_LIMIT: Lazy<RateLimiter> = Lazy::new(|| RateLimiter::new("Aevo", 50, Duration::from_secs(10)));
+
+// (price, qty) precision per instrument, looked up on first use
+static PRECISION_CACHE: Lazy<Mutex<HashMap<String, (usize, usize)>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+// Aevo perpetuals (`ETH-PERP`-style), an off-chain order book settling on its own rollup. The
+// `orderbook` channel sends a snapshot on subscription and diffs after it; messages carry
+// timestamps but no sequence numbers, so the connector numbers them itself.
+#[derive(Default)]
+pub struct AevoExchange {}
+
+impl AevoExchange {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    fn request(op: &'static str, data: Vec<String>) -> Option<WsMessage> {
+        let request = AevoRequest { op, data };
+        serde_json::to_string(&request).ok().map(|text| WsMessage::Text(text.into()))
+    }
+
+    async fn fetch_precision(symbol: &str) -> Result<(usize, usize), ExchangeError> {
+        let url = format!("{}/instrument/{symbol}", net::rest_base("aevo", REST_BASE));
+        let instrument: AevoInstrument = check_status(RATE_LIMIT.get(&url, 1).await?, symbol)?.json().await?;
+        Ok((
+            instrument.price_step.normalize().scale() as usize,
+            instrument.amount_step.normalize().scale() as usize,
+        ))
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Exchange for AevoExchange {
+    // Emits the channel's snapshot followed by its diffs
+    async fn connect(&self, symbol: &str) -> Result<Receiver<ExchangeMessage>, ExchangeError> {
+        let (tx, rx) = mpsc::channel(1000);
+        let endpoint = self.ws_endpoint(symbol).await?;
+        let ws_stream = net::connect_ws(&endpoint.url).await?;
+        let symbol = symbol.to_uppercase();
+
+        super::spawn(async move {
+            let (mut write, mut read) = ws_stream.split();
+            if let Some(sub_msg) = Self::request("subscribe", vec![format!("orderbook:{symbol}")]) {
+                let _ = write.send(sub_msg).await;
+            }
+
+            // Diffs are ignored until the subscription's snapshot arrives
+            let mut synced = false;
+            let mut seq = 0;
+            let mut ping = tokio::time::interval(PING_INTERVAL);
+
+            loop {
+                let message = tokio::select! {
+                    message = read.next() => message,
+                    // The feed dropped this connection (reconnect, symbol change or shutdown)
+                    _ = tx.closed() => break,
+                    _ = ping.tick() => {
+                        if let Some(ping_msg) = Self::request("ping", vec![]) {
+                            let _ = write.send(ping_msg).await;
+                        }
+                        continue;
+                    }
+                };
+                match message {
+                    Some(Ok(WsMessage::Text(text))) => {
+                        let recv_time = chrono::Utc::now().timestamp_millis() as u64;
+                        let envelope = match serde_json::from_str::<AevoEnvelope>(&text) {
+                            Ok(envelope) => envelope,
+                            Err(e) => {
+                                let _ = tx.send(ExchangeMessage::Error(e.into())).await;
+                                continue;
+                            }
+                        };
+                        if let Some(error) = envelope.error {
+                            // Subscribing to an unknown instrument is the usual cause
+                            tracing::warn!("Aevo error: {error}");
+                            let error = ExchangeError::InvalidSymbol(symbol.clone());
+                            let _ = tx.send(ExchangeMessage::Error(error)).await;
+                            break;
+                        }
+                        if !envelope.channel.starts_with("orderbook:") {
+                            continue;
+                        }
+                        let book = match serde_json::from_value::<AevoBook>(envelope.data) {
+                            Ok(book) => book,
+                            Err(e) => {
+                                let _ = tx.send(ExchangeMessage::Error(e.into())).await;
+                                continue;
+                            }
+                        };
+                        let snapshot = book.kind == "snapshot";
+                        if !(snapshot || synced) {
+                            continue;
+                        }
+                        seq += 1;
+                        let event_time = book.event_time();
+                        let message = if snapshot {
+                            synced = true;
+                            ExchangeMessage::Snapshot(OrderBookSnapshot {
+                                last_update_id: seq,
+                                event_time,
+                                recv_time,
+                                bids: book.bids,
+                                asks: book.asks,
+                            })
+                        } else {
+                            ExchangeMessage::Update(DepthUpdate {
+                                event_time,
+                                transaction_time: event_time,
+                                symbol: symbol.clone(),
+                                capital_u: seq,
+                                small_u: seq,
+                                pu: -1,
+                                bids: book.bids,
+                                asks: book.asks,
+                                recv_time,
+                            })
+                        };
+                        let _ = tx.send(message).await;
+                    }
+                    Some(Ok(WsMessage::Ping(payload))) => {
+                        let _ = write.send(WsMessage::Pong(payload)).await;
+                    }
+                    Some(Ok(WsMessage::Close(_))) | None => break,
+                    Some(Err(e)) => {
+                        tracing::warn!("Aevo WebSocket error: {e}");
+                        let _ = tx.send(ExchangeMessage::Error(e)).await;
+                        break;
+                    }
+                    Some(Ok(_)) => {}
+                }
+            }
+            let _ = write.close().await;
+        });
+
+        Ok(rx)
+    }
+
+    async fn ws_endpoint(&self, _symbol: &str) -> Result<WsEndpoint, ExchangeError> {
+        Ok(WsEndpoint::new(net::ws_url("aevo", WS_URL)))
+    }
+
+    async fn get_snapshot(&self, symbol: &str) -> Result<OrderBookSnapshot, ExchangeError> {
+        let symbol = symbol.to_uppercase();
+        let url = format!("{}/orderbook?instrument_name={symbol}", net::rest_base("aevo", REST_BASE));
+        let response = check_status(RATE_LIMIT.get(&url, 1).await?, &symbol)?;
+        let book: AevoBook = response.json().await?;
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        Ok(OrderBookSnapshot {
+            last_update_id: now,
+            event_time: 0,
+            recv_time: now,
+            bids: book.bids,
+            asks: book.asks,
+        })
+    }
+
+    async fn get_precision(&self, symbol: &str) -> (usize, usize) {
+        let symbol = symbol.to_uppercase();
+        if let Some(&precision) = PRECISION_CACHE.lock().unwrap().get(&symbol) {
+            return precision;
+        }
+        match Self::fetch_precision(&symbol).await {
+            Ok(precision) => {
+                PRECISION_CACHE.lock().unwrap().insert(symbol, precision);
+                precision
+            }
+            Err(e) => {
+                tracing::warn!("Aevo instrument request error: {e}");
+                (2, 2)
+            }
+        }
+    }
+
+    fn snapshot_in_stream(&self) -> bool {
+        true
+    }
+
+    fn format_symbol(&self, symbol: &str) -> String {
+        symbol.to_uppercase()
+    }
+
+    fn get_name(&self) -> &'static str {
+        "Aevo"
+    }
+}
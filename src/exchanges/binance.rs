@@ -1,11 +1,32 @@
-use super::{DepthUpdate, Exchange, ExchangeMessage, OrderBookSnapshot};
-use futures_util::{SinkExt, StreamExt};
-use reqwest::blocking;
+use super::error::check_status;
+use super::net::{self, WsMessage};
+use super::ratelimit::RateLimiter;
+use super::subscription::{
+    StreamFrame, StreamKind, StreamProtocol, Subscription, SubscriptionHandle, SubscriptionManager,
+};
+use super::sync::{Chaining, DepthSync};
+use super::{
+    DepthLimits, DepthUpdate, Exchange, ExchangeError, ExchangeMessage, InstrumentInfo, InstrumentStats, JoinHandle,
+    OrderBookSnapshot, PriceLevel, Side, Trade, WsEndpoint,
+};
+use once_cell::sync::Lazy;
 use rust_decimal::Decimal;
-use serde::Deserialize;
-use std::collections::VecDeque;
-use tokio::sync::mpsc::{self, Receiver, Sender};
-use tokio_tungstenite::{connect_async, tungstenite::protocol::Message as WsMessage};
+use serde::de::{DeserializeSeed, Deserializer, SeqAccess, Visitor};
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::mpsc::{self, Receiver};
+use tokio_util::sync::CancellationToken;
+
+// Consecutive failed resyncs before the stream is closed and the feed reconnects with backoff
+const MAX_RESYNCS: u32 = 3;
+// Open interest is only available over REST
+const OPEN_INTEREST_POLL: Duration = Duration::from_secs(10);
+// Binance disconnects every connection after 24 hours; streams move to a fresh one well before
+const MAX_CONNECTION_AGE: Duration = Duration::from_secs(23 * 60 * 60);
 
 #[derive(Deserialize)]
 struct BinanceExchangeInfo {
@@ -16,6 +37,9 @@ struct BinanceExchangeInfo {
 struct BinanceSymbolInfo {
     symbol: String,
     filters: Vec<BinanceFilter>,
+    // USD per contract on COIN-M
+    #[serde(rename = "contractSize", default)]
+    contract_size: Option<Decimal>,
 }
 
 #[derive(Deserialize)]
@@ -28,131 +52,679 @@ struct BinanceFilter {
     step_size: Option<String>,
 }
 
+// (price, qty) precision and contract size per symbol
+type Symbols = HashMap<String, ((usize, usize), InstrumentInfo)>;
+
+// REST weight per minute and IP of each market; Binance reports the weight used so far
+static SPOT_RATE_LIMIT: Lazy<RateLimiter> = Lazy::new(|| binance_limiter(6000));
+static USDM_RATE_LIMIT: Lazy<RateLimiter> = Lazy::new(|| binance_limiter(2400));
+static COINM_RATE_LIMIT: Lazy<RateLimiter> = Lazy::new(|| binance_limiter(2400));
+
+fn binance_limiter(weight_per_minute: u32) -> RateLimiter {
+    RateLimiter::new("Binance", weight_per_minute, Duration::from_secs(60))
+        .with_used_weight_header("x-mbx-used-weight-1m")
+}
+
+// Symbols from exchangeInfo, fetched once per market, network (testnet or not) and session
+static SYMBOL_CACHE: Lazy<Mutex<HashMap<(BinanceMarket, bool), Symbols>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+// Binance market whose book is streamed; each has its own REST and WebSocket hosts
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum BinanceMarket {
+    Spot,
+    #[default]
+    UsdM,
+    CoinM,
+}
+
+impl BinanceMarket {
+    pub const ALL: [BinanceMarket; 3] = [BinanceMarket::Spot, BinanceMarket::UsdM, BinanceMarket::CoinM];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            BinanceMarket::Spot => "Spot",
+            BinanceMarket::UsdM => "USD-M Futures",
+            BinanceMarket::CoinM => "COIN-M Futures",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "spot" => Some(BinanceMarket::Spot),
+            "usdm" | "usd-m" | "futures" => Some(BinanceMarket::UsdM),
+            "coinm" | "coin-m" => Some(BinanceMarket::CoinM),
+            _ => None,
+        }
+    }
+
+    // Keeps `symbol` if it fits the market's naming (COIN-M contracts are `<pair>_perp`)
+    pub fn adapt_symbol(&self, symbol: &str) -> String {
+        let lower = symbol.to_lowercase();
+        match self {
+            BinanceMarket::CoinM if !lower.contains('_') => "dogeusd_perp".to_string(),
+            BinanceMarket::Spot | BinanceMarket::UsdM if lower.contains('_') => "dogeusdt".to_string(),
+            _ => symbol.to_string(),
+        }
+    }
+
+    // Spot events chain by `U = u + 1`, futures events by `pu`
+    fn chaining(&self) -> Chaining {
+        match self {
+            BinanceMarket::Spot => Chaining::Contiguous,
+            BinanceMarket::UsdM | BinanceMarket::CoinM => Chaining::PreviousId,
+        }
+    }
+
+    // Label of the market on the testnet, kept distinct so production and testnet feeds of a
+    // symbol can run side by side
+    pub fn testnet_label(&self) -> &'static str {
+        match self {
+            BinanceMarket::Spot => "Spot Testnet",
+            BinanceMarket::UsdM => "USD-M Futures Testnet",
+            BinanceMarket::CoinM => "COIN-M Futures Testnet",
+        }
+    }
+
+    // Key of the market's endpoint overrides in the config
+    fn venue(&self, testnet: bool) -> &'static str {
+        match (self, testnet) {
+            (BinanceMarket::Spot, false) => "binance_spot",
+            (BinanceMarket::UsdM, false) => "binance_usdm",
+            (BinanceMarket::CoinM, false) => "binance_coinm",
+            (BinanceMarket::Spot, true) => "binance_spot_testnet",
+            (BinanceMarket::UsdM, true) => "binance_usdm_testnet",
+            (BinanceMarket::CoinM, true) => "binance_coinm_testnet",
+        }
+    }
+
+    fn rest_base(&self, testnet: bool) -> String {
+        let default = match (self, testnet) {
+            (BinanceMarket::Spot, false) => "https://api.binance.com/api/v3",
+            (BinanceMarket::UsdM, false) => "https://fapi.binance.com/fapi/v1",
+            (BinanceMarket::CoinM, false) => "https://dapi.binance.com/dapi/v1",
+            (BinanceMarket::Spot, true) => "https://testnet.binance.vision/api/v3",
+            (BinanceMarket::UsdM, true) => "https://testnet.binancefuture.com/fapi/v1",
+            (BinanceMarket::CoinM, true) => "https://testnet.binancefuture.com/dapi/v1",
+        };
+        net::rest_base(self.venue(testnet), default)
+    }
+
+    fn rate_limit(&self) -> &'static RateLimiter {
+        match self {
+            BinanceMarket::Spot => &SPOT_RATE_LIMIT,
+            BinanceMarket::UsdM => &USDM_RATE_LIMIT,
+            BinanceMarket::CoinM => &COINM_RATE_LIMIT,
+        }
+    }
+
+    // Request weight of exchangeInfo
+    fn info_weight(&self) -> u32 {
+        match self {
+            BinanceMarket::Spot => 20,
+            BinanceMarket::UsdM | BinanceMarket::CoinM => 1,
+        }
+    }
+
+    // Request weight of a depth snapshot of `depth` levels, which grows with the limit
+    fn depth_weight(&self, depth: usize) -> u32 {
+        // (deepest limit, weight) brackets, and the weight beyond the last one
+        let (brackets, deepest): (&[(usize, u32)], u32) = match self {
+            BinanceMarket::Spot => (&[(100, 5), (500, 25), (1000, 50)], 250),
+            BinanceMarket::UsdM | BinanceMarket::CoinM => (&[(50, 2), (100, 5), (500, 10)], 20),
+        };
+        brackets.iter().find(|(max, _)| depth <= *max).map_or(deepest, |&(_, weight)| weight)
+    }
+
+    // Snapshot limits the depth endpoint accepts
+    fn depth_limits(&self) -> DepthLimits {
+        match self {
+            BinanceMarket::Spot => DepthLimits::steps(&[5, 10, 20, 50, 100, 500, 1000, 5000], 1000),
+            BinanceMarket::UsdM | BinanceMarket::CoinM => DepthLimits::steps(&[5, 10, 20, 50, 100, 500, 1000], 1000),
+        }
+    }
+
+    // Combined-stream endpoint; streams are added with SUBSCRIBE requests
+    fn ws_url(&self, testnet: bool) -> String {
+        let default = match (self, testnet) {
+            (BinanceMarket::Spot, false) => "wss://stream.binance.com:9443/stream",
+            (BinanceMarket::UsdM, false) => "wss://fstream.binance.com/stream",
+            (BinanceMarket::CoinM, false) => "wss://dstream.binance.com/stream",
+            (BinanceMarket::Spot, true) => "wss://stream.testnet.binance.vision/stream",
+            (BinanceMarket::UsdM, true) => "wss://fstream.binancefuture.com/stream",
+            (BinanceMarket::CoinM, true) => "wss://dstream.binancefuture.com/stream",
+        };
+        net::ws_url(self.venue(testnet), default)
+    }
+}
+
+#[derive(Serialize)]
+struct BinanceRequest<'a> {
+    method: &'a str,
+    params: &'a [String],
+    id: u64,
+}
+
+// Stream names and SUBSCRIBE/UNSUBSCRIBE requests of a market's combined-stream endpoint
+struct BinanceProtocol {
+    market: BinanceMarket,
+}
+
+impl StreamProtocol for BinanceProtocol {
+    fn stream_name(&self, subscription: &Subscription) -> String {
+        let symbol = subscription.symbol.to_lowercase();
+        match (subscription.kind, self.market) {
+            (StreamKind::Depth, BinanceMarket::Spot) => format!("{symbol}@depth@100ms"),
+            (StreamKind::Depth, _) => format!("{symbol}@depth@0ms"),
+            (StreamKind::Trades, _) => format!("{symbol}@aggTrade"),
+            (StreamKind::MarkPrice, _) => format!("{symbol}@markPrice@1s"),
+        }
+    }
+
+    fn request(&self, subscribe: bool, streams: &[String], id: u64) -> WsMessage {
+        let request = BinanceRequest {
+            method: if subscribe { "SUBSCRIBE" } else { "UNSUBSCRIBE" },
+            params: streams,
+            id,
+        };
+        WsMessage::Text(serde_json::to_string(&request).unwrap_or_default().into())
+    }
+
+    fn route<'a>(&self, text: &'a str) -> Option<(&'a str, &'a RawValue)> {
+        let envelope = serde_json::from_str::<BinanceStreamEnvelope>(text).ok()?;
+        Some((envelope.stream, envelope.data))
+    }
+
+    fn max_age(&self) -> Option<Duration> {
+        Some(MAX_CONNECTION_AGE)
+    }
+}
+
 #[derive(Deserialize)]
 struct BinanceOrderBookSnapshot {
     #[serde(rename = "lastUpdateId")]
     last_update_id: u64,
-    bids: Vec<Vec<Decimal>>,
-    asks: Vec<Vec<Decimal>>,
+    bids: Vec<PriceLevel>,
+    asks: Vec<PriceLevel>,
 }
 
-#[derive(Deserialize, Clone)]
-struct BinanceDepthUpdate {
-    e: String,
+// Borrows from the frame; the levels are read separately by `parse_depth`
+#[derive(Deserialize)]
+struct BinanceDepthUpdate<'a> {
     #[serde(rename = "E")]
     event_time: u64,
+    // Futures only
     #[serde(rename = "T")]
-    transaction_time: u64,
-    s: String,
+    transaction_time: Option<u64>,
+    s: &'a str,
     #[serde(rename = "U")]
     capital_u: u64,
     #[serde(rename = "u")]
     small_u: u64,
-    pu: i64,
-    b: Vec<Vec<Decimal>>,
-    a: Vec<Vec<Decimal>>,
+    // Futures only
+    pu: Option<i64>,
+    #[serde(borrow)]
+    b: &'a RawValue,
+    #[serde(borrow)]
+    a: &'a RawValue,
+}
+
+#[derive(Deserialize)]
+struct BinanceStreamEnvelope<'a> {
+    stream: &'a str,
+    #[serde(borrow)]
+    data: &'a RawValue,
 }
 
-pub struct BinanceExchange {}
+// Appends a `[["price", "qty"], ...]` array to a buffer
+struct LevelsSeed<'s>(&'s mut Vec<PriceLevel>);
 
-impl BinanceExchange {
-    pub fn new() -> Self {
-        Self {}
+impl<'de> DeserializeSeed<'de> for LevelsSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
     }
 }
 
-#[async_trait::async_trait]
-impl Exchange for BinanceExchange {
-    async fn connect(&self, symbol: &str) -> Result<Receiver<ExchangeMessage>, Box<dyn std::error::Error>> {
-        let (tx, rx) = mpsc::channel(1000);
-        let ws_url = format!("wss://fstream.binance.com/ws/{}@depth@0ms", symbol.to_lowercase());
-        let symbol = symbol.to_string();
+impl<'de> Visitor<'de> for LevelsSeed<'_> {
+    type Value = ();
 
-        tokio::spawn(async move {
-            if let Ok((ws_stream, _)) = connect_async(&ws_url).await {
-                let (_, mut read) = ws_stream.split();
-                
-                while let Some(message) = read.next().await {
-                    match message {
-                        Ok(WsMessage::Text(text)) => {
-                            if let Ok(update) = serde_json::from_str::<BinanceDepthUpdate>(&text) {
-                                let depth_update = DepthUpdate {
-                                    event_time: update.event_time,
-                                    transaction_time: update.transaction_time,
-                                    symbol: update.s,
-                                    capital_u: update.capital_u,
-                                    small_u: update.small_u,
-                                    pu: update.pu,
-                                    bids: update.b,
-                                    asks: update.a,
-                                };
-                                let _ = tx.send(ExchangeMessage::Update(depth_update)).await;
-                            }
-                        }
-                        Ok(WsMessage::Ping(payload)) => {
-                            // Handle ping if needed
-                        }
-                        Ok(WsMessage::Close(_)) => break,
-                        Err(e) => {
-                            println!("Binance WebSocket error: {:?}", e);
-                            break;
-                        }
-                        _ => {}
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an array of [price, qty] levels")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(level) = seq.next_element::<PriceLevel>()? {
+            self.0.push(level);
+        }
+        Ok(())
+    }
+}
+
+// Diffs arrive thousands of times a second on liquid symbols. Nothing of the frame is copied but
+// the symbol, and levels are read into `scratch`, which keeps its capacity between diffs, so each
+// side costs one allocation of exactly its size rather than a growing Vec of Vecs.
+fn parse_depth(
+    data: &RawValue,
+    recv_time: u64,
+    scratch: &mut Vec<PriceLevel>,
+) -> Result<DepthUpdate, serde_json::Error> {
+    let update: BinanceDepthUpdate = serde_json::from_str(data.get())?;
+    let mut levels = |raw: &RawValue| {
+        scratch.clear();
+        LevelsSeed(scratch).deserialize(&mut serde_json::Deserializer::from_str(raw.get()))?;
+        Ok::<_, serde_json::Error>(scratch.to_vec())
+    };
+    Ok(DepthUpdate {
+        event_time: update.event_time,
+        transaction_time: update.transaction_time.unwrap_or(update.event_time),
+        symbol: update.s.to_string(),
+        capital_u: update.capital_u,
+        small_u: update.small_u,
+        pu: update.pu.unwrap_or(-1),
+        bids: levels(update.b)?,
+        asks: levels(update.a)?,
+        recv_time,
+    })
+}
+
+#[derive(Deserialize)]
+struct BinanceMarkPrice {
+    #[serde(rename = "p")]
+    mark_price: Decimal,
+    #[serde(rename = "i")]
+    index_price: Decimal,
+    #[serde(rename = "r")]
+    funding_rate: Decimal,
+    #[serde(rename = "T")]
+    next_funding_time: u64,
+}
+
+// One `aggTrade` event: the fills of a single taker order at one price
+#[derive(Deserialize)]
+struct BinanceAggTrade {
+    #[serde(rename = "a")]
+    trade_id: u64,
+    #[serde(rename = "p")]
+    price: Decimal,
+    #[serde(rename = "q")]
+    qty: Decimal,
+    #[serde(rename = "T")]
+    trade_time: u64,
+    // The buyer was the resting order, i.e. the taker sold
+    #[serde(rename = "m")]
+    buyer_is_maker: bool,
+}
+
+impl From<BinanceAggTrade> for Trade {
+    fn from(trade: BinanceAggTrade) -> Self {
+        Trade {
+            trade_id: trade.trade_id,
+            price: trade.price,
+            qty: trade.qty,
+            aggressor: if trade.buyer_is_maker { Side::Ask } else { Side::Bid },
+            timestamp: trade.trade_time,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct BinanceOpenInterest {
+    #[serde(rename = "openInterest")]
+    open_interest: Decimal,
+}
+
+impl From<BinanceMarkPrice> for InstrumentStats {
+    fn from(mark: BinanceMarkPrice) -> Self {
+        InstrumentStats {
+            mark_price: Some(mark.mark_price),
+            index_price: Some(mark.index_price),
+            funding_rate: Some(mark.funding_rate),
+            next_funding_time: Some(mark.next_funding_time),
+            open_interest: None,
+        }
+    }
+}
+
+enum StreamEvent {
+    Depth(StreamFrame),
+    Trade(StreamFrame),
+    MarkPrice(StreamFrame),
+    Snapshot(Result<OrderBookSnapshot, ExchangeError>),
+    // A subscription ended with the connection, with the error if there was one
+    Closed(Option<ExchangeError>),
+    // The connection is due to be replaced ahead of the forced disconnect
+    Expired,
+}
+
+// A connector's subscriptions on one connection: depth, trades and, on futures, mark price
+struct Streams {
+    depth: SubscriptionHandle,
+    trades: SubscriptionHandle,
+    mark: Option<SubscriptionHandle>,
+    expires_at: tokio::time::Instant,
+}
+
+impl Streams {
+    fn add(manager: &SubscriptionManager, market: BinanceMarket, symbol: &str) -> Self {
+        Self {
+            depth: manager.add(&Subscription::new(symbol, StreamKind::Depth)),
+            trades: manager.add(&Subscription::new(symbol, StreamKind::Trades)),
+            mark: (market != BinanceMarket::Spot)
+                .then(|| manager.add(&Subscription::new(symbol, StreamKind::MarkPrice))),
+            expires_at: manager.expires_at().unwrap_or_else(|| tokio::time::Instant::now() + MAX_CONNECTION_AGE),
+        }
+    }
+}
+
+impl StreamEvent {
+    fn from_frame(frame: Option<Result<StreamFrame, ExchangeError>>, wrap: fn(StreamFrame) -> Self) -> Self {
+        match frame {
+            Some(Ok(frame)) => wrap(frame),
+            Some(Err(e)) => StreamEvent::Closed(Some(e)),
+            None => StreamEvent::Closed(None),
+        }
+    }
+}
+
+pub struct BinanceExchange {
+    market: BinanceMarket,
+    // Streams the market's testnet, whose books are thin and synthetic but free to hammer
+    testnet: bool,
+    // Snapshot levels per side, one of the market's accepted limits
+    depth: usize,
+}
+
+impl Default for BinanceExchange {
+    fn default() -> Self {
+        Self::new(BinanceMarket::default(), false, None)
+    }
+}
+
+impl BinanceExchange {
+    // `depth` is rounded to a limit the market accepts
+    pub fn new(market: BinanceMarket, testnet: bool, depth: Option<usize>) -> Self {
+        let depth = market.depth_limits().negotiate(depth).unwrap_or(1000);
+        Self { market, testnet, depth }
+    }
+
+    // Decimals implied by the PRICE_FILTER tick size and LOT_SIZE step size
+    fn symbol_precision(sym_info: &BinanceSymbolInfo) -> (usize, usize) {
+        let mut price_prec = 2;
+        let mut qty_prec = 2;
+        for filter in &sym_info.filters {
+            if filter.filter_type == "PRICE_FILTER" {
+                if let Some(ts) = &filter.tick_size {
+                    let tick_size = ts.parse::<f64>().unwrap_or(1.0);
+                    if tick_size > 0.0 {
+                        price_prec = (-tick_size.log10()).ceil() as usize;
+                    }
+                }
+            } else if filter.filter_type == "LOT_SIZE" {
+                if let Some(ss) = &filter.step_size {
+                    let step_size = ss.parse::<f64>().unwrap_or(1.0);
+                    if step_size > 0.0 {
+                        qty_prec = (-step_size.log10()).ceil() as usize;
                     }
                 }
             }
-        });
+        }
+        (price_prec, qty_prec)
+    }
 
-        Ok(rx)
+    async fn fetch_symbols(market: BinanceMarket, testnet: bool) -> Result<Symbols, ExchangeError> {
+        let url = format!("{}/exchangeInfo", market.rest_base(testnet));
+        let response = market.rate_limit().get(&url, market.info_weight()).await?;
+        let info: BinanceExchangeInfo = check_status(response, "")?.json().await?;
+        Ok(info
+            .symbols
+            .iter()
+            .map(|s| {
+                // COIN-M sizes are contracts worth a fixed amount of USD; the others are in the base asset
+                let instrument = match (market, s.contract_size) {
+                    (BinanceMarket::CoinM, Some(size)) => InstrumentInfo::inverse(size),
+                    _ => InstrumentInfo::BASE,
+                };
+                (s.symbol.clone(), (Self::symbol_precision(s), instrument))
+            })
+            .collect())
     }
 
-    async fn get_snapshot(&self, symbol: &str) -> Result<OrderBookSnapshot, Box<dyn std::error::Error>> {
-        let url = format!(
-            "https://fapi.binance.com/fapi/v1/depth?symbol={}&limit=1000",
-            symbol.to_uppercase()
-        );
-        
-        let client = reqwest::Client::new();
-        let response = client.get(&url).send().await?;
+    async fn symbol_info(&self, symbol: &str) -> Option<((usize, usize), InstrumentInfo)> {
+        let symbol = symbol.to_uppercase();
+        let key = (self.market, self.testnet);
+        if !SYMBOL_CACHE.lock().unwrap().contains_key(&key) {
+            match Self::fetch_symbols(self.market, self.testnet).await {
+                Ok(symbols) => {
+                    SYMBOL_CACHE.lock().unwrap().insert(key, symbols);
+                }
+                Err(e) => tracing::warn!("Binance exchangeInfo request error: {e}"),
+            }
+        }
+        SYMBOL_CACHE.lock().unwrap().get(&key).and_then(|symbols| symbols.get(&symbol)).copied()
+    }
+
+    async fn fetch_snapshot(
+        market: BinanceMarket,
+        testnet: bool,
+        depth: usize,
+        symbol: String,
+    ) -> Result<OrderBookSnapshot, ExchangeError> {
+        let url = format!("{}/depth?symbol={}&limit={depth}", market.rest_base(testnet), symbol.to_uppercase());
+        let response = check_status(market.rate_limit().get(&url, market.depth_weight(depth)).await?, &symbol)?;
         let snapshot: BinanceOrderBookSnapshot = response.json().await?;
-        
+
         Ok(OrderBookSnapshot {
             last_update_id: snapshot.last_update_id,
+            event_time: 0,
+            recv_time: chrono::Utc::now().timestamp_millis() as u64,
             bids: snapshot.bids,
             asks: snapshot.asks,
         })
     }
 
-    fn get_precision(&self, symbol: &str) -> (usize, usize) {
-        let mut price_prec = 2;
-        let mut qty_prec = 2;
-        
-        let url = "https://fapi.binance.com/fapi/v1/exchangeInfo".to_string();
-        if let Ok(resp) = blocking::get(&url) {
-            if let Ok(info) = resp.json::<BinanceExchangeInfo>() {
-                if let Some(sym_info) = info.symbols.into_iter().find(|s| s.symbol == symbol.to_uppercase()) {
-                    for filter in sym_info.filters {
-                        if filter.filter_type == "PRICE_FILTER" {
-                            if let Some(ts) = filter.tick_size {
-                                let tick_size = ts.parse::<f64>().unwrap_or(1.0);
-                                if tick_size > 0.0 {
-                                    price_prec = (-tick_size.log10()).ceil() as usize;
-                                }
+    async fn fetch_open_interest(market: BinanceMarket, testnet: bool, symbol: &str) -> Result<Decimal, ExchangeError> {
+        let url = format!("{}/openInterest?symbol={}", market.rest_base(testnet), symbol.to_uppercase());
+        let response = check_status(market.rate_limit().get(&url, 1).await?, symbol)?;
+        let oi: BinanceOpenInterest = response.json().await?;
+        Ok(oi.open_interest)
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Exchange for BinanceExchange {
+    // Emits a snapshot followed by an already sequenced update stream, plus trades; a sequence
+    // break is reported as a Desync error and followed by a fresh snapshot. Connections for the
+    // same market share one socket, each adding its streams to it.
+    async fn connect(&self, symbol: &str) -> Result<Receiver<ExchangeMessage>, ExchangeError> {
+        let (tx, rx) = mpsc::channel(1000);
+        let (market, testnet, depth) = (self.market, self.testnet, self.depth);
+        let endpoint = self.ws_endpoint(symbol).await?;
+        let manager = SubscriptionManager::shared(&endpoint.url, BinanceProtocol { market }).await?;
+        let url = endpoint.url;
+        let symbol = symbol.to_string();
+
+        // Polls open interest for as long as the stream task runs; holding only a weak sender lets
+        // the channel close as soon as the stream ends, and the stream task cancels the poller on exit
+        let poller = CancellationToken::new();
+        if market != BinanceMarket::Spot {
+            let weak_tx = tx.downgrade();
+            let symbol = symbol.clone();
+            let cancelled = poller.clone();
+            super::spawn(async move {
+                let mut interval = tokio::time::interval(OPEN_INTEREST_POLL);
+                loop {
+                    tokio::select! {
+                        _ = interval.tick() => {}
+                        _ = cancelled.cancelled() => break,
+                    }
+                    let result = Self::fetch_open_interest(market, testnet, &symbol).await;
+                    let Some(tx) = weak_tx.upgrade() else {
+                        break;
+                    };
+                    let message = match result {
+                        Ok(oi) => ExchangeMessage::InstrumentStats(InstrumentStats {
+                            open_interest: Some(oi),
+                            ..Default::default()
+                        }),
+                        Err(e) => ExchangeMessage::Error(e),
+                    };
+                    if tx.send(message).await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+
+        super::spawn(async move {
+            let _poller = poller.drop_guard();
+            let mut streams = Streams::add(&manager, market, &symbol);
+            // The handles hold the connection open; the manager must not outlive a renewal
+            drop(manager);
+            let mut sync = DepthSync::new(market.chaining());
+            // Fetched once the first diff is buffered, so the snapshot cannot predate the stream
+            let mut pending: Option<JoinHandle<Result<OrderBookSnapshot, ExchangeError>>> = None;
+            let mut awaiting_snapshot = true;
+            let mut failed_resyncs = 0;
+            let mut scratch: Vec<PriceLevel> = Vec::new();
+
+            loop {
+                let event = tokio::select! {
+                    // The feed dropped this connection (reconnect, symbol change or shutdown)
+                    _ = tx.closed() => break,
+                    frame = streams.depth.recv() => StreamEvent::from_frame(frame, StreamEvent::Depth),
+                    frame = streams.trades.recv() => StreamEvent::from_frame(frame, StreamEvent::Trade),
+                    frame = async { streams.mark.as_mut().unwrap().recv().await }, if streams.mark.is_some() => {
+                        StreamEvent::from_frame(frame, StreamEvent::MarkPrice)
+                    }
+                    _ = tokio::time::sleep_until(streams.expires_at) => StreamEvent::Expired,
+                    snapshot = async { pending.as_mut().unwrap().await }, if pending.is_some() => {
+                        StreamEvent::Snapshot(snapshot.unwrap_or_else(|e| Err(ExchangeError::Network(e.to_string()))))
+                    }
+                };
+
+                let synced = match event {
+                    StreamEvent::Snapshot(Ok(snapshot)) => {
+                        pending = None;
+                        awaiting_snapshot = false;
+                        sync.on_snapshot(snapshot.last_update_id).map(|ready| (Some(snapshot), ready))
+                    }
+                    StreamEvent::Snapshot(Err(e)) => {
+                        tracing::warn!("Binance snapshot request error: {e}");
+                        let _ = tx.send(ExchangeMessage::Error(e)).await;
+                        break;
+                    }
+                    StreamEvent::Depth(frame) => match parse_depth(&frame.data, frame.recv_time, &mut scratch) {
+                        Ok(update) => {
+                            if awaiting_snapshot && pending.is_none() {
+                                let snapshot = Self::fetch_snapshot(market, testnet, depth, symbol.clone());
+                                pending = Some(super::spawn(snapshot));
                             }
-                        } else if filter.filter_type == "LOT_SIZE" {
-                            if let Some(ss) = filter.step_size {
-                                let step_size = ss.parse::<f64>().unwrap_or(1.0);
-                                if step_size > 0.0 {
-                                    qty_prec = (-step_size.log10()).ceil() as usize;
-                                }
+                            sync.on_event(update).map(|ready| (None, ready.into_iter().collect()))
+                        }
+                        Err(e) => {
+                            let _ = tx.send(ExchangeMessage::Error(e.into())).await;
+                            continue;
+                        }
+                    },
+                    StreamEvent::Trade(frame) => {
+                        let message = match serde_json::from_str::<BinanceAggTrade>(frame.data.get()) {
+                            Ok(trade) => ExchangeMessage::Trade(trade.into()),
+                            Err(e) => ExchangeMessage::Error(e.into()),
+                        };
+                        let _ = tx.send(message).await;
+                        continue;
+                    }
+                    StreamEvent::MarkPrice(frame) => {
+                        let message = match serde_json::from_str::<BinanceMarkPrice>(frame.data.get()) {
+                            Ok(mark) => ExchangeMessage::InstrumentStats(mark.into()),
+                            Err(e) => ExchangeMessage::Error(e.into()),
+                        };
+                        let _ = tx.send(message).await;
+                        continue;
+                    }
+                    StreamEvent::Closed(error) => {
+                        if let Some(e) = error {
+                            let _ = tx.send(ExchangeMessage::Error(e)).await;
+                        }
+                        break;
+                    }
+                    StreamEvent::Expired => {
+                        // Subscribe on a fresh connection before leaving the old one, then resync
+                        // from a snapshot taken on the new stream; the book keeps its state until then
+                        match SubscriptionManager::shared(&url, BinanceProtocol { market }).await {
+                            Ok(manager) => {
+                                tracing::info!("Binance connection due for renewal, moving {symbol} to a new one");
+                                streams = Streams::add(&manager, market, &symbol);
+                                sync = DepthSync::new(market.chaining());
+                                awaiting_snapshot = true;
+                                pending = None;
+                            }
+                            Err(e) => {
+                                // Leave it to the feed's reconnect before Binance cuts the old one
+                                let _ = tx.send(ExchangeMessage::Error(e)).await;
+                                break;
                             }
                         }
+                        continue;
+                    }
+                };
+
+                match synced {
+                    Ok((snapshot, ready)) => {
+                        if let Some(snapshot) = snapshot {
+                            let _ = tx.send(ExchangeMessage::Snapshot(snapshot)).await;
+                        }
+                        if !ready.is_empty() {
+                            failed_resyncs = 0;
+                        }
+                        for update in ready {
+                            let _ = tx.send(ExchangeMessage::Update(update)).await;
+                        }
+                    }
+                    Err(e) => {
+                        // Start over from a fresh snapshot, or give up and let the feed reconnect
+                        tracing::warn!("Binance depth sync lost: {e}");
+                        let _ = tx.send(ExchangeMessage::Error(e)).await;
+                        failed_resyncs += 1;
+                        if failed_resyncs > MAX_RESYNCS {
+                            break;
+                        }
+                        sync = DepthSync::new(market.chaining());
+                        awaiting_snapshot = true;
+                        pending = Some(super::spawn(Self::fetch_snapshot(market, testnet, depth, symbol.clone())));
                     }
                 }
             }
-        }
-        
-        (price_prec, qty_prec)
+        });
+
+        Ok(rx)
+    }
+
+    async fn ws_endpoint(&self, _symbol: &str) -> Result<WsEndpoint, ExchangeError> {
+        Ok(WsEndpoint::new(self.market.ws_url(self.testnet)))
+    }
+
+    async fn get_snapshot(&self, symbol: &str) -> Result<OrderBookSnapshot, ExchangeError> {
+        Self::fetch_snapshot(self.market, self.testnet, self.depth, symbol.to_string()).await
+    }
+
+    async fn get_precision(&self, symbol: &str) -> (usize, usize) {
+        self.symbol_info(symbol).await.map_or((2, 2), |(precision, _)| precision)
+    }
+
+    async fn get_instrument(&self, symbol: &str) -> InstrumentInfo {
+        self.symbol_info(symbol).await.map(|(_, instrument)| instrument).unwrap_or_default()
+    }
+
+    fn snapshot_in_stream(&self) -> bool {
+        true
+    }
+
+    fn depth_limits(&self) -> DepthLimits {
+        self.market.depth_limits()
     }
 
     fn format_symbol(&self, symbol: &str) -> String {
@@ -0,0 +1,386 @@
+use super::error::check_status;
+use super::net::{self, WsMessage};
+use super::ratelimit::RateLimiter;
+use super::{
+    DepthLimits, DepthUpdate, Exchange, ExchangeError, ExchangeMessage, OrderBookSnapshot, OrderEvent, OrderEventKind,
+    PriceLevel, Side, WsEndpoint,
+};
+use futures_util::{SinkExt, StreamExt};
+use once_cell::sync::Lazy;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::mpsc::{self, Receiver};
+
+const REST_BASE: &str = "https://api-pub.bitfinex.com/v2";
+const WS_URL: &str = "wss://api-pub.bitfinex.com/ws/2";
+// Orders per side the raw book subscription and REST snapshot can be requested at; the
+// stream also takes 250, which the REST endpoint does not
+const DEPTH_LIMITS: DepthLimits = DepthLimits::steps(&[25, 100], 100);
+// Prices are quoted to five significant figures
+const PRICE_SIG_FIGS: i32 = 5;
+const QTY_DECIMALS: usize = 8;
+// Info code asking clients to reconnect (server restart)
+const RECONNECT_CODE: u32 = 20051;
+
+#[derive(Serialize)]
+struct BitfinexSubscription<'a> {
+    event: &'a str,
+    channel: &'a str,
+    symbol: &'a str,
+    prec: &'a str,
+    len: &'a str,
+}
+
+// Control messages are objects, channel data arrays led by the channel id. Heartbeats
+// (`[id, "hb"]`) match neither and are skipped with the other unknown frames.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BitfinexFrame {
+    Event(BitfinexEvent),
+    Data(u64, BitfinexPayload),
+}
+
+#[derive(Deserialize)]
+struct BitfinexEvent {
+    event: String,
+    #[serde(default, rename = "chanId")]
+    chan_id: Option<u64>,
+    #[serde(default)]
+    code: Option<u32>,
+    #[serde(default)]
+    msg: String,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BitfinexPayload {
+    Snapshot(Vec<BitfinexOrder>),
+    Order(BitfinexOrder),
+}
+
+// [order id, price, amount]: a positive amount is a bid, a negative one an ask, and a zero price
+// removes the order
+type BitfinexOrder = (u64, Decimal, Decimal);
+
+// The most restrictive public endpoint (ticker) allows 30 requests per minute
+static RATE_LIMIT: Lazy<RateLimiter> = Lazy::new(|| RateLimiter::new("Bitfinex", 30, Duration::from_secs(60)));
+
+// (price, qty) precision per pair, looked up on first use
+static PRECISION_CACHE: Lazy<Mutex<HashMap<String, (usize, usize)>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+// Price-keyed bid and ask levels
+type Levels = (Vec<PriceLevel>, Vec<PriceLevel>);
+
+// The raw book: every visible order and the level totals they add up to. Hidden orders never
+// appear in the feed, so the totals are the displayed depth only.
+#[derive(Default)]
+struct RawBook {
+    orders: HashMap<u64, (Side, Decimal, Decimal)>,
+    bids: BTreeMap<Decimal, Decimal>,
+    asks: BTreeMap<Decimal, Decimal>,
+}
+
+impl RawBook {
+    fn load(&mut self, orders: &[BitfinexOrder]) -> Levels {
+        *self = Self::default();
+        for &(id, price, amount) in orders {
+            let side = if amount > Decimal::ZERO { Side::Bid } else { Side::Ask };
+            self.orders.insert(id, (side, price, amount.abs()));
+            self.adjust(side, price, amount.abs());
+        }
+        let levels = |side: &BTreeMap<Decimal, Decimal>| side.iter().map(|(&p, &q)| PriceLevel::new(p, q)).collect();
+        (levels(&self.bids), levels(&self.asks))
+    }
+
+    // The order event for a change, if it concerns a known or new order, and the new totals of
+    // the levels it touched
+    fn apply(&mut self, (id, price, amount): BitfinexOrder, timestamp: u64) -> Option<(OrderEvent, Levels)> {
+        let mut touched = vec![];
+        let previous = self.orders.remove(&id);
+        if let Some((side, old_price, qty)) = previous {
+            self.adjust(side, old_price, -qty);
+            touched.push((side, old_price));
+        }
+        let event = if price.is_zero() {
+            let (side, price, qty) = previous?;
+            OrderEvent {
+                kind: OrderEventKind::Deleted,
+                order_id: id,
+                side,
+                price,
+                qty,
+                timestamp,
+            }
+        } else {
+            let side = if amount > Decimal::ZERO { Side::Bid } else { Side::Ask };
+            let qty = amount.abs();
+            self.orders.insert(id, (side, price, qty));
+            self.adjust(side, price, qty);
+            touched.push((side, price));
+            OrderEvent {
+                kind: if previous.is_some() { OrderEventKind::Changed } else { OrderEventKind::Created },
+                order_id: id,
+                side,
+                price,
+                qty,
+                timestamp,
+            }
+        };
+        let (mut bids, mut asks) = (vec![], vec![]);
+        for (side, price) in touched {
+            let (totals, levels) = match side {
+                Side::Bid => (&self.bids, &mut bids),
+                Side::Ask => (&self.asks, &mut asks),
+            };
+            let total = totals.get(&price).copied().unwrap_or_default();
+            levels.push(PriceLevel::new(price, total));
+        }
+        Some((event, (bids, asks)))
+    }
+
+    fn adjust(&mut self, side: Side, price: Decimal, delta: Decimal) {
+        let totals = match side {
+            Side::Bid => &mut self.bids,
+            Side::Ask => &mut self.asks,
+        };
+        let total = totals.entry(price).or_default();
+        *total += delta;
+        if *total <= Decimal::ZERO {
+            totals.remove(&price);
+        }
+    }
+
+}
+
+// One Created event per order of a snapshot, in the payload's order: the venue lists each level's
+// orders front first, and the receiving queues rank orders by arrival
+fn order_events(orders: &[BitfinexOrder], timestamp: u64) -> impl Iterator<Item = OrderEvent> + '_ {
+    orders.iter().map(move |&(order_id, price, amount)| OrderEvent {
+        kind: OrderEventKind::Created,
+        order_id,
+        side: if amount > Decimal::ZERO { Side::Bid } else { Side::Ask },
+        price,
+        qty: amount.abs(),
+        timestamp,
+    })
+}
+
+// Bitfinex spot pairs (`tBTCUSD`-style). The `R0` book precision is the raw book: one entry
+// per visible order, so the connector forwards every order as a ground-truth order event and
+// aggregates them into price levels for the book engine. Messages carry no sequence numbers
+// or timestamps; the connector numbers them itself.
+pub struct BitfinexExchange {
+    // Orders per side in the subscription and snapshot, one of `DEPTH_LIMITS`
+    depth: usize,
+}
+
+impl Default for BitfinexExchange {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl BitfinexExchange {
+    pub fn new(depth: Option<usize>) -> Self {
+        Self {
+            depth: DEPTH_LIMITS.negotiate(depth).unwrap_or(100),
+        }
+    }
+
+    fn price_decimals(last_price: f64) -> usize {
+        if last_price <= 0.0 {
+            return QTY_DECIMALS;
+        }
+        (PRICE_SIG_FIGS - (last_price.log10().floor() as i32 + 1)).clamp(0, QTY_DECIMALS as i32) as usize
+    }
+
+    async fn fetch_precision(symbol: &str) -> Result<(usize, usize), ExchangeError> {
+        let url = format!("{}/ticker/{symbol}", net::rest_base("bitfinex", REST_BASE));
+        // [bid, bid size, ask, ask size, daily change, daily change %, last price, ...]
+        let ticker: Vec<f64> = check_status(RATE_LIMIT.get(&url, 1).await?, symbol)?.json().await?;
+        let last_price = ticker.get(6).copied().ok_or_else(|| ExchangeError::InvalidSymbol(symbol.to_string()))?;
+        Ok((Self::price_decimals(last_price), QTY_DECIMALS))
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Exchange for BitfinexExchange {
+    // Emits the raw book as a snapshot followed by one order event and one level update per
+    // order change
+    async fn connect(&self, symbol: &str) -> Result<Receiver<ExchangeMessage>, ExchangeError> {
+        let (tx, rx) = mpsc::channel(1000);
+        let endpoint = self.ws_endpoint(symbol).await?;
+        let ws_stream = net::connect_ws(&endpoint.url).await?;
+        let symbol = self.format_symbol(symbol);
+        let len = self.depth.to_string();
+
+        super::spawn(async move {
+            let (mut write, mut read) = ws_stream.split();
+            let subscription = BitfinexSubscription {
+                event: "subscribe",
+                channel: "book",
+                symbol: &symbol,
+                prec: "R0",
+                len: &len,
+            };
+            if let Ok(sub_msg) = serde_json::to_string(&subscription) {
+                let _ = write.send(WsMessage::Text(sub_msg.into())).await;
+            }
+
+            let mut book = RawBook::default();
+            // Changes are ignored until the subscription's snapshot arrives
+            let mut synced = false;
+            let mut seq = 0;
+            // Set once the subscription is confirmed
+            let mut book_channel = None;
+
+            // Ends when the stream closes or the feed drops this connection (reconnect, symbol
+            // change or shutdown)
+            while let Some(message) = tokio::select! {
+                message = read.next() => message,
+                _ = tx.closed() => None,
+            } {
+                match message {
+                    Ok(WsMessage::Text(text)) => {
+                        let recv_time = chrono::Utc::now().timestamp_millis() as u64;
+                        let Ok(frame) = serde_json::from_str::<BitfinexFrame>(&text) else {
+                            continue;
+                        };
+                        let payload = match frame {
+                            BitfinexFrame::Event(event) => match event.event.as_str() {
+                                "error" => {
+                                    // Subscribing to an unknown pair is the usual cause
+                                    tracing::warn!("Bitfinex error: {}", event.msg);
+                                    let error = ExchangeError::InvalidSymbol(symbol.clone());
+                                    let _ = tx.send(ExchangeMessage::Error(error)).await;
+                                    break;
+                                }
+                                "info" if event.code == Some(RECONNECT_CODE) => break,
+                                "subscribed" => {
+                                    book_channel = event.chan_id;
+                                    continue;
+                                }
+                                _ => continue,
+                            },
+                            BitfinexFrame::Data(chan_id, payload) if Some(chan_id) == book_channel => payload,
+                            BitfinexFrame::Data(..) => continue,
+                        };
+                        match payload {
+                            BitfinexPayload::Snapshot(orders) => {
+                                seq += 1;
+                                synced = true;
+                                let (bids, asks) = book.load(&orders);
+                                let snapshot = OrderBookSnapshot {
+                                    last_update_id: seq,
+                                    event_time: 0,
+                                    recv_time,
+                                    bids,
+                                    asks,
+                                };
+                                let _ = tx.send(ExchangeMessage::Snapshot(snapshot)).await;
+                                for event in order_events(&orders, recv_time) {
+                                    let _ = tx.send(ExchangeMessage::Order(event)).await;
+                                }
+                            }
+                            BitfinexPayload::Order(order) if synced => {
+                                let Some((event, (bids, asks))) = book.apply(order, recv_time) else {
+                                    continue;
+                                };
+                                seq += 1;
+                                let update = DepthUpdate {
+                                    event_time: 0,
+                                    transaction_time: 0,
+                                    symbol: symbol.clone(),
+                                    capital_u: seq,
+                                    small_u: seq,
+                                    pu: -1,
+                                    bids,
+                                    asks,
+                                    recv_time,
+                                };
+                                let _ = tx.send(ExchangeMessage::Order(event)).await;
+                                let _ = tx.send(ExchangeMessage::Update(update)).await;
+                            }
+                            BitfinexPayload::Order(_) => {}
+                        }
+                    }
+                    Ok(WsMessage::Ping(payload)) => {
+                        let _ = write.send(WsMessage::Pong(payload)).await;
+                    }
+                    Ok(WsMessage::Close(_)) => break,
+                    Err(e) => {
+                        tracing::warn!("Bitfinex WebSocket error: {e}");
+                        let _ = tx.send(ExchangeMessage::Error(e)).await;
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+            let _ = write.close().await;
+        });
+
+        Ok(rx)
+    }
+
+    async fn ws_endpoint(&self, _symbol: &str) -> Result<WsEndpoint, ExchangeError> {
+        Ok(WsEndpoint::new(net::ws_url("bitfinex", WS_URL)))
+    }
+
+    async fn get_snapshot(&self, symbol: &str) -> Result<OrderBookSnapshot, ExchangeError> {
+        let symbol = self.format_symbol(symbol);
+        let base = net::rest_base("bitfinex", REST_BASE);
+        let url = format!("{base}/book/{symbol}/R0?len={}", self.depth);
+        let response = check_status(RATE_LIMIT.get(&url, 1).await?, &symbol)?;
+        let orders: Vec<BitfinexOrder> = response.json().await?;
+        let (bids, asks) = RawBook::default().load(&orders);
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        Ok(OrderBookSnapshot {
+            last_update_id: now,
+            event_time: 0,
+            recv_time: now,
+            bids,
+            asks,
+        })
+    }
+
+    async fn get_precision(&self, symbol: &str) -> (usize, usize) {
+        let symbol = self.format_symbol(symbol);
+        if let Some(&precision) = PRECISION_CACHE.lock().unwrap().get(&symbol) {
+            return precision;
+        }
+        match Self::fetch_precision(&symbol).await {
+            Ok(precision) => {
+                PRECISION_CACHE.lock().unwrap().insert(symbol, precision);
+                precision
+            }
+            Err(e) => {
+                tracing::warn!("Bitfinex ticker request error: {e}");
+                (2, QTY_DECIMALS)
+            }
+        }
+    }
+
+    fn snapshot_in_stream(&self) -> bool {
+        true
+    }
+
+    fn depth_limits(&self) -> DepthLimits {
+        DEPTH_LIMITS
+    }
+
+    // `btcusd` and `tBTCUSD` both become `tBTCUSD`
+    fn format_symbol(&self, symbol: &str) -> String {
+        match symbol.strip_prefix('t') {
+            Some(pair) if !pair.is_empty() && pair == pair.to_uppercase() => symbol.to_string(),
+            _ => format!("t{}", symbol.to_uppercase()),
+        }
+    }
+
+    fn get_name(&self) -> &'static str {
+        "Bitfinex"
+    }
+}
@@ -0,0 +1,341 @@
+use super::error::check_status;
+use super::net::{self, WsMessage};
+use super::ratelimit::RateLimiter;
+use super::{DepthUpdate, Exchange, ExchangeError, ExchangeMessage, OrderBookSnapshot, PriceLevel, WsEndpoint};
+use futures_util::{SinkExt, StreamExt};
+use once_cell::sync::Lazy;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::mpsc::{self, Receiver};
+
+const REST_BASE: &str = "https://api.bitget.com/api/v2/mix/market";
+const WS_URL: &str = "wss://ws.bitget.com/v2/ws/public";
+const PRODUCT_TYPE: &str = "USDT-FUTURES";
+// Bitget drops connections without a text "ping" for two minutes
+const PING_INTERVAL: Duration = Duration::from_secs(25);
+// Levels per side covered by the `books` checksum
+const CHECKSUM_DEPTH: usize = 25;
+// Consecutive checksum failures before the stream is closed and the feed reconnects
+const MAX_RESYNCS: u32 = 3;
+
+#[derive(Serialize)]
+struct BitgetRequest<'a> {
+    op: &'a str,
+    args: [BitgetArg<'a>; 1],
+}
+
+#[derive(Serialize)]
+struct BitgetArg<'a> {
+    #[serde(rename = "instType")]
+    inst_type: &'a str,
+    channel: &'a str,
+    #[serde(rename = "instId")]
+    inst_id: &'a str,
+}
+
+#[derive(Deserialize)]
+struct BitgetEnvelope {
+    // "snapshot" or "update" on data pushes
+    #[serde(default)]
+    action: String,
+    // "subscribe", "unsubscribe" or "error" on replies
+    #[serde(default)]
+    event: String,
+    #[serde(default)]
+    msg: String,
+    #[serde(default)]
+    data: Vec<BitgetBook>,
+}
+
+// Prices and sizes keep the venue's string formatting, which the checksum is computed over
+#[derive(Deserialize)]
+struct BitgetBook {
+    asks: Vec<PriceLevel>,
+    bids: Vec<PriceLevel>,
+    checksum: i64,
+    seq: u64,
+    ts: String,
+}
+
+#[derive(Deserialize)]
+struct BitgetResponse<T> {
+    data: T,
+}
+
+#[derive(Deserialize)]
+struct BitgetDepth {
+    asks: Vec<PriceLevel>,
+    bids: Vec<PriceLevel>,
+}
+
+#[derive(Deserialize)]
+struct BitgetContract {
+    symbol: String,
+    #[serde(rename = "pricePlace")]
+    price_place: String,
+    #[serde(rename = "volumePlace")]
+    volume_place: String,
+}
+
+// Market data endpoints allow 20 requests per second and IP
+static RATE_LIMIT: Lazy<RateLimiter> = Lazy::new(|| RateLimiter::new("Bitget", 20, Duration::from_secs(1)));
+
+// (price, qty) precision per contract, fetched once per session
+static PRECISION_CACHE: Lazy<Mutex<HashMap<String, (usize, usize)>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+// The connector's own copy of the book, kept only to verify the checksum of every push
+#[derive(Default)]
+struct ChecksumBook {
+    bids: BTreeMap<Decimal, Decimal>,
+    asks: BTreeMap<Decimal, Decimal>,
+}
+
+impl ChecksumBook {
+    fn load(&mut self, book: &BitgetBook) {
+        self.bids.clear();
+        self.asks.clear();
+        self.apply(book);
+    }
+
+    fn apply(&mut self, book: &BitgetBook) {
+        for (side, levels) in [(&mut self.bids, &book.bids), (&mut self.asks, &book.asks)] {
+            for level in levels {
+                if level.qty.is_zero() {
+                    side.remove(&level.price);
+                } else {
+                    side.insert(level.price, level.qty);
+                }
+            }
+        }
+    }
+
+    // CRC32 of "bid1:size1:ask1:size1:bid2:..." over the top levels, as a signed 32-bit integer;
+    // a side that runs out first is simply skipped
+    fn checksum(&self) -> i32 {
+        let mut bids = self.bids.iter().rev();
+        let mut asks = self.asks.iter();
+        let mut parts = Vec::with_capacity(CHECKSUM_DEPTH * 4);
+        for _ in 0..CHECKSUM_DEPTH {
+            for level in [bids.next(), asks.next()].into_iter().flatten() {
+                parts.push(level.0.to_string());
+                parts.push(level.1.to_string());
+            }
+        }
+        crc32fast::hash(parts.join(":").as_bytes()) as i32
+    }
+}
+
+// Bitget USDT-M perpetuals. The `books` channel sends a full snapshot on subscription and
+// incremental updates after it, each with a checksum of the top 25 levels; on a mismatch the
+// channel is resubscribed for a fresh snapshot.
+#[derive(Default)]
+pub struct BitgetExchange {}
+
+impl BitgetExchange {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    fn request<'a>(op: &'a str, symbol: &'a str) -> BitgetRequest<'a> {
+        BitgetRequest {
+            op,
+            args: [BitgetArg {
+                inst_type: PRODUCT_TYPE,
+                channel: "books",
+                inst_id: symbol,
+            }],
+        }
+    }
+
+    async fn fetch_precisions() -> Result<HashMap<String, (usize, usize)>, ExchangeError> {
+        let url = format!("{}/contracts?productType={PRODUCT_TYPE}", net::rest_base("bitget", REST_BASE));
+        let response: BitgetResponse<Vec<BitgetContract>> =
+            check_status(RATE_LIMIT.get(&url, 1).await?, "")?.json().await?;
+        Ok(response
+            .data
+            .into_iter()
+            .map(|c| {
+                let precision = (c.price_place.parse().unwrap_or(2), c.volume_place.parse().unwrap_or(2));
+                (c.symbol, precision)
+            })
+            .collect())
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Exchange for BitgetExchange {
+    // Emits the channel's snapshot followed by its updates, checksum-verified; a mismatch is
+    // reported as a Desync error and followed by a fresh snapshot
+    async fn connect(&self, symbol: &str) -> Result<Receiver<ExchangeMessage>, ExchangeError> {
+        let (tx, rx) = mpsc::channel(1000);
+        let endpoint = self.ws_endpoint(symbol).await?;
+        let ws_stream = net::connect_ws(&endpoint.url).await?;
+        let symbol = symbol.to_uppercase();
+
+        super::spawn(async move {
+            let (mut write, mut read) = ws_stream.split();
+            if let Ok(sub_msg) = serde_json::to_string(&Self::request("subscribe", &symbol)) {
+                let _ = write.send(WsMessage::Text(sub_msg.into())).await;
+            }
+
+            let mut book = ChecksumBook::default();
+            // Updates are ignored until the (re)subscription's snapshot arrives
+            let mut synced = false;
+            let mut failed_resyncs = 0;
+            let mut ping = tokio::time::interval(PING_INTERVAL);
+
+            loop {
+                let message = tokio::select! {
+                    message = read.next() => message,
+                    // The feed dropped this connection (reconnect, symbol change or shutdown)
+                    _ = tx.closed() => break,
+                    _ = ping.tick() => {
+                        let _ = write.send(WsMessage::Text("ping".into())).await;
+                        continue;
+                    }
+                };
+                match message {
+                    Some(Ok(WsMessage::Text(text))) => {
+                        if text.as_str() == "pong" {
+                            continue;
+                        }
+                        let recv_time = chrono::Utc::now().timestamp_millis() as u64;
+                        let envelope = match serde_json::from_str::<BitgetEnvelope>(&text) {
+                            Ok(envelope) => envelope,
+                            Err(e) => {
+                                let _ = tx.send(ExchangeMessage::Error(e.into())).await;
+                                continue;
+                            }
+                        };
+                        if envelope.event == "error" {
+                            // Subscribing to an unknown contract is the usual cause
+                            tracing::warn!("Bitget error: {}", envelope.msg);
+                            let error = ExchangeError::InvalidSymbol(symbol.clone());
+                            let _ = tx.send(ExchangeMessage::Error(error)).await;
+                            break;
+                        }
+                        let snapshot = match envelope.action.as_str() {
+                            "snapshot" => true,
+                            "update" if synced => false,
+                            _ => continue,
+                        };
+                        for data in envelope.data {
+                            if snapshot {
+                                book.load(&data);
+                            } else {
+                                book.apply(&data);
+                            }
+                            let checksum = book.checksum();
+                            if checksum != data.checksum as i32 {
+                                let e = ExchangeError::Desync(format!(
+                                    "checksum {} does not match the book ({checksum})",
+                                    data.checksum
+                                ));
+                                tracing::warn!("Bitget depth sync lost: {e}");
+                                let _ = tx.send(ExchangeMessage::Error(e)).await;
+                                failed_resyncs += 1;
+                                synced = false;
+                                break;
+                            }
+                            failed_resyncs = 0;
+                            synced = true;
+                            let event_time = data.ts.parse::<u64>().unwrap_or(0);
+                            let message = if snapshot {
+                                ExchangeMessage::Snapshot(OrderBookSnapshot {
+                                    last_update_id: data.seq,
+                                    event_time,
+                                    recv_time,
+                                    bids: data.bids,
+                                    asks: data.asks,
+                                })
+                            } else {
+                                ExchangeMessage::Update(DepthUpdate {
+                                    event_time,
+                                    transaction_time: event_time,
+                                    symbol: symbol.clone(),
+                                    capital_u: data.seq,
+                                    small_u: data.seq,
+                                    pu: -1,
+                                    bids: data.bids,
+                                    asks: data.asks,
+                                    recv_time,
+                                })
+                            };
+                            let _ = tx.send(message).await;
+                        }
+                        if !synced {
+                            if failed_resyncs > MAX_RESYNCS {
+                                break;
+                            }
+                            // Resubscribing makes Bitget send a fresh snapshot
+                            for op in ["unsubscribe", "subscribe"] {
+                                if let Ok(msg) = serde_json::to_string(&Self::request(op, &symbol)) {
+                                    let _ = write.send(WsMessage::Text(msg.into())).await;
+                                }
+                            }
+                        }
+                    }
+                    Some(Ok(WsMessage::Ping(payload))) => {
+                        let _ = write.send(WsMessage::Pong(payload)).await;
+                    }
+                    Some(Ok(WsMessage::Close(_))) | None => break,
+                    Some(Err(e)) => {
+                        tracing::warn!("Bitget WebSocket error: {e}");
+                        let _ = tx.send(ExchangeMessage::Error(e)).await;
+                        break;
+                    }
+                    Some(Ok(_)) => {}
+                }
+            }
+            let _ = write.close().await;
+        });
+
+        Ok(rx)
+    }
+
+    async fn ws_endpoint(&self, _symbol: &str) -> Result<WsEndpoint, ExchangeError> {
+        Ok(WsEndpoint::new(net::ws_url("bitget", WS_URL)))
+    }
+
+    async fn get_snapshot(&self, symbol: &str) -> Result<OrderBookSnapshot, ExchangeError> {
+        let symbol = symbol.to_uppercase();
+        let base = net::rest_base("bitget", REST_BASE);
+        let url = format!("{base}/merge-depth?symbol={symbol}&productType={PRODUCT_TYPE}&limit=max");
+        let response = check_status(RATE_LIMIT.get(&url, 1).await?, &symbol)?;
+        let depth: BitgetResponse<BitgetDepth> = response.json().await?;
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        Ok(OrderBookSnapshot {
+            last_update_id: now,
+            event_time: 0,
+            recv_time: now,
+            bids: depth.data.bids,
+            asks: depth.data.asks,
+        })
+    }
+
+    async fn get_precision(&self, symbol: &str) -> (usize, usize) {
+        if PRECISION_CACHE.lock().unwrap().is_empty() {
+            match Self::fetch_precisions().await {
+                Ok(precisions) => *PRECISION_CACHE.lock().unwrap() = precisions,
+                Err(e) => tracing::warn!("Bitget contracts request error: {e}"),
+            }
+        }
+        PRECISION_CACHE.lock().unwrap().get(&symbol.to_uppercase()).copied().unwrap_or((2, 2))
+    }
+
+    fn snapshot_in_stream(&self) -> bool {
+        true
+    }
+
+    fn format_symbol(&self, symbol: &str) -> String {
+        symbol.to_uppercase()
+    }
+
+    fn get_name(&self) -> &'static str {
+        "Bitget"
+    }
+}
@@ -0,0 +1,362 @@
+use super::error::check_status;
+use super::net::{self, WsMessage};
+use super::ratelimit::RateLimiter;
+use super::{
+    DepthUpdate, Exchange, ExchangeError, ExchangeMessage, InstrumentInfo, OrderBookSnapshot, PriceLevel, WsEndpoint,
+};
+use chrono::{DateTime, Utc};
+use futures_util::{SinkExt, StreamExt};
+use once_cell::sync::Lazy;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::mpsc::{self, Receiver};
+
+const REST_BASE: &str = "https://www.bitmex.com/api/v1";
+const WS_URL: &str = "wss://ws.bitmex.com/realtime";
+// BitMEX expects a text "ping" when the connection has been quiet for a while
+const PING_INTERVAL: Duration = Duration::from_secs(20);
+// Consecutive failed resyncs before the stream is closed and the feed reconnects
+const MAX_RESYNCS: u32 = 3;
+
+#[derive(Serialize)]
+struct BitmexRequest<'a> {
+    op: &'a str,
+    args: [String; 1],
+}
+
+#[derive(Deserialize)]
+struct BitmexEnvelope {
+    // Set on table pushes, with the action being "partial", "insert", "update" or "delete"
+    #[serde(default)]
+    table: String,
+    #[serde(default)]
+    action: String,
+    #[serde(default)]
+    error: Option<String>,
+    #[serde(default)]
+    data: Vec<BitmexEntry>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+enum BitmexSide {
+    Buy,
+    Sell,
+}
+
+// One `orderBookL2` level. Deletes carry only the id and side, and updates cannot be relied on
+// to repeat the price, so the connector remembers the price of every live id.
+#[derive(Deserialize)]
+struct BitmexEntry {
+    id: u64,
+    side: BitmexSide,
+    #[serde(default)]
+    size: Option<Decimal>,
+    #[serde(default)]
+    price: Option<Decimal>,
+    #[serde(default)]
+    timestamp: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BitmexInstrument {
+    symbol: String,
+    tick_size: Decimal,
+    #[serde(default)]
+    is_inverse: bool,
+    // Settlement currency units per contract (per 1/price on inverse contracts), negative there
+    #[serde(default)]
+    multiplier: Option<Decimal>,
+    #[serde(default)]
+    quote_to_settle_multiplier: Option<Decimal>,
+    // Contracts per unit of the base asset on linear contracts
+    #[serde(default)]
+    underlying_to_position_multiplier: Option<Decimal>,
+}
+
+impl BitmexInstrument {
+    // Inverse contracts are worth |multiplier| / quoteToSettleMultiplier USD (1 on XBTUSD), linear
+    // ones 1 / underlyingToPositionMultiplier of the base asset. Quanto contracts, worth a fixed
+    // amount of XBT per USD, have neither and are left as they are.
+    fn info(&self) -> InstrumentInfo {
+        let ratio = |numerator: Decimal, denominator: Option<Decimal>| {
+            denominator.filter(|d| !d.is_zero()).map_or(Decimal::ONE, |d| numerator / d)
+        };
+        if self.is_inverse {
+            let multiplier = self.multiplier.unwrap_or(Decimal::ONE).abs();
+            InstrumentInfo::inverse(ratio(multiplier, self.quote_to_settle_multiplier))
+        } else {
+            InstrumentInfo::linear(ratio(Decimal::ONE, self.underlying_to_position_multiplier))
+        }
+    }
+}
+
+// Unauthenticated REST allows 30 requests per minute
+static RATE_LIMIT: Lazy<RateLimiter> = Lazy::new(|| RateLimiter::new("BitMEX", 30, Duration::from_secs(60)));
+
+// (price, qty) precision and contract size per instrument, fetched once per session
+type Instruments = HashMap<String, ((usize, usize), InstrumentInfo)>;
+static INSTRUMENT_CACHE: Lazy<Mutex<Instruments>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+// Price-keyed bid and ask deltas
+type Levels = (Vec<PriceLevel>, Vec<PriceLevel>);
+
+// Side and price of every live level id, used to translate id-keyed actions into price levels
+#[derive(Default)]
+struct IdBook {
+    levels: HashMap<u64, (BitmexSide, Decimal)>,
+}
+
+impl IdBook {
+    // A "partial" replaces the whole book; an id the book does not know means a missed message
+    fn apply(&mut self, action: &str, entries: Vec<BitmexEntry>) -> Result<Levels, ExchangeError> {
+        if action == "partial" {
+            self.levels.clear();
+        }
+        let unknown = |id| ExchangeError::Desync(format!("{action} for unknown level id {id}"));
+        let (mut bids, mut asks) = (vec![], vec![]);
+        for entry in entries {
+            let (side, price, size) = match action {
+                "partial" | "insert" => {
+                    let price = entry
+                        .price
+                        .ok_or_else(|| ExchangeError::Parse(format!("level {} has no price", entry.id)))?;
+                    self.levels.insert(entry.id, (entry.side, price));
+                    (entry.side, price, entry.size.unwrap_or_default())
+                }
+                "update" => {
+                    let &(side, price) = self.levels.get(&entry.id).ok_or_else(|| unknown(entry.id))?;
+                    (side, price, entry.size.unwrap_or_default())
+                }
+                "delete" => {
+                    let (side, price) = self.levels.remove(&entry.id).ok_or_else(|| unknown(entry.id))?;
+                    (side, price, Decimal::ZERO)
+                }
+                _ => continue,
+            };
+            match side {
+                BitmexSide::Buy => bids.push(PriceLevel::new(price, size)),
+                BitmexSide::Sell => asks.push(PriceLevel::new(price, size)),
+            }
+        }
+        Ok((bids, asks))
+    }
+}
+
+// BitMEX derivatives (`XBTUSD`, `ETHUSDT`, ..., sizes in contracts). `orderBookL2` keys levels
+// by id rather than price and has no sequence numbers: a "partial" starts the book and
+// insert/update/delete actions follow in order, so the connector numbers the messages itself.
+#[derive(Default)]
+pub struct BitmexExchange {}
+
+impl BitmexExchange {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    fn request(op: &str, symbol: &str) -> Option<WsMessage> {
+        let request = BitmexRequest {
+            op,
+            args: [format!("orderBookL2:{symbol}")],
+        };
+        serde_json::to_string(&request).ok().map(|text| WsMessage::Text(text.into()))
+    }
+
+    async fn fetch_instruments() -> Result<Instruments, ExchangeError> {
+        let url = format!("{}/instrument/active", net::rest_base("bitmex", REST_BASE));
+        let instruments: Vec<BitmexInstrument> = check_status(RATE_LIMIT.get(&url, 1).await?, "")?.json().await?;
+        Ok(instruments
+            .into_iter()
+            .map(|i| {
+                let info = i.info();
+                (i.symbol, ((i.tick_size.normalize().scale() as usize, 0), info))
+            })
+            .collect())
+    }
+
+    async fn instrument(symbol: &str) -> Option<((usize, usize), InstrumentInfo)> {
+        if INSTRUMENT_CACHE.lock().unwrap().is_empty() {
+            match Self::fetch_instruments().await {
+                Ok(instruments) => *INSTRUMENT_CACHE.lock().unwrap() = instruments,
+                Err(e) => tracing::warn!("BitMEX instrument request error: {e}"),
+            }
+        }
+        INSTRUMENT_CACHE.lock().unwrap().get(&symbol.to_uppercase()).copied()
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Exchange for BitmexExchange {
+    // Emits the partial as a snapshot followed by price-keyed updates; an action for an unknown
+    // id is reported as a Desync error and followed by a fresh partial
+    async fn connect(&self, symbol: &str) -> Result<Receiver<ExchangeMessage>, ExchangeError> {
+        let (tx, rx) = mpsc::channel(1000);
+        let endpoint = self.ws_endpoint(symbol).await?;
+        let ws_stream = net::connect_ws(&endpoint.url).await?;
+        let symbol = symbol.to_uppercase();
+
+        super::spawn(async move {
+            let (mut write, mut read) = ws_stream.split();
+            if let Some(sub_msg) = Self::request("subscribe", &symbol) {
+                let _ = write.send(sub_msg).await;
+            }
+
+            let mut book = IdBook::default();
+            // Actions are ignored until the (re)subscription's partial arrives
+            let mut synced = false;
+            let mut seq = 0;
+            let mut failed_resyncs = 0;
+            let mut ping = tokio::time::interval(PING_INTERVAL);
+
+            loop {
+                let message = tokio::select! {
+                    message = read.next() => message,
+                    // The feed dropped this connection (reconnect, symbol change or shutdown)
+                    _ = tx.closed() => break,
+                    _ = ping.tick() => {
+                        let _ = write.send(WsMessage::Text("ping".into())).await;
+                        continue;
+                    }
+                };
+                match message {
+                    Some(Ok(WsMessage::Text(text))) => {
+                        if text.as_str() == "pong" {
+                            continue;
+                        }
+                        let recv_time = chrono::Utc::now().timestamp_millis() as u64;
+                        let envelope = match serde_json::from_str::<BitmexEnvelope>(&text) {
+                            Ok(envelope) => envelope,
+                            Err(e) => {
+                                let _ = tx.send(ExchangeMessage::Error(e.into())).await;
+                                continue;
+                            }
+                        };
+                        if let Some(error) = envelope.error {
+                            // Subscribing to an unknown instrument is the usual cause
+                            tracing::warn!("BitMEX error: {error}");
+                            let error = ExchangeError::InvalidSymbol(symbol.clone());
+                            let _ = tx.send(ExchangeMessage::Error(error)).await;
+                            break;
+                        }
+                        let partial = envelope.action == "partial";
+                        if envelope.table != "orderBookL2" || !(partial || synced) {
+                            continue;
+                        }
+                        let event_time = envelope
+                            .data
+                            .iter()
+                            .filter_map(|entry| entry.timestamp)
+                            .max()
+                            .map_or(0, |t| t.timestamp_millis() as u64);
+                        let (bids, asks) = match book.apply(&envelope.action, envelope.data) {
+                            Ok(levels) => levels,
+                            Err(e) => {
+                                tracing::warn!("BitMEX depth sync lost: {e}");
+                                let _ = tx.send(ExchangeMessage::Error(e)).await;
+                                failed_resyncs += 1;
+                                if failed_resyncs > MAX_RESYNCS {
+                                    break;
+                                }
+                                // Resubscribing makes BitMEX send a fresh partial
+                                synced = false;
+                                for op in ["unsubscribe", "subscribe"] {
+                                    if let Some(msg) = Self::request(op, &symbol) {
+                                        let _ = write.send(msg).await;
+                                    }
+                                }
+                                continue;
+                            }
+                        };
+                        seq += 1;
+                        let message = if partial {
+                            synced = true;
+                            failed_resyncs = 0;
+                            ExchangeMessage::Snapshot(OrderBookSnapshot {
+                                last_update_id: seq,
+                                event_time,
+                                recv_time,
+                                bids,
+                                asks,
+                            })
+                        } else {
+                            ExchangeMessage::Update(DepthUpdate {
+                                event_time,
+                                transaction_time: event_time,
+                                symbol: symbol.clone(),
+                                capital_u: seq,
+                                small_u: seq,
+                                pu: -1,
+                                bids,
+                                asks,
+                                recv_time,
+                            })
+                        };
+                        let _ = tx.send(message).await;
+                    }
+                    Some(Ok(WsMessage::Ping(payload))) => {
+                        let _ = write.send(WsMessage::Pong(payload)).await;
+                    }
+                    Some(Ok(WsMessage::Close(_))) | None => break,
+                    Some(Err(e)) => {
+                        tracing::warn!("BitMEX WebSocket error: {e}");
+                        let _ = tx.send(ExchangeMessage::Error(e)).await;
+                        break;
+                    }
+                    Some(Ok(_)) => {}
+                }
+            }
+            let _ = write.close().await;
+        });
+
+        Ok(rx)
+    }
+
+    async fn ws_endpoint(&self, _symbol: &str) -> Result<WsEndpoint, ExchangeError> {
+        Ok(WsEndpoint::new(net::ws_url("bitmex", WS_URL)))
+    }
+
+    async fn get_snapshot(&self, symbol: &str) -> Result<OrderBookSnapshot, ExchangeError> {
+        let symbol = symbol.to_uppercase();
+        let url = format!("{}/orderBook/L2?symbol={symbol}&depth=0", net::rest_base("bitmex", REST_BASE));
+        let response = check_status(RATE_LIMIT.get(&url, 1).await?, &symbol)?;
+        let entries: Vec<BitmexEntry> = response.json().await?;
+        // Unknown instruments come back as an empty book
+        if entries.is_empty() {
+            return Err(ExchangeError::InvalidSymbol(symbol));
+        }
+        let (bids, asks) = IdBook::default().apply("partial", entries)?;
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        Ok(OrderBookSnapshot {
+            last_update_id: now,
+            event_time: 0,
+            recv_time: now,
+            bids,
+            asks,
+        })
+    }
+
+    async fn get_precision(&self, symbol: &str) -> (usize, usize) {
+        Self::instrument(symbol).await.map_or((1, 0), |(precision, _)| precision)
+    }
+
+    // Sizes are contracts: USD on the inverse XBTUSD, fractions of the base asset on linear ones
+    async fn get_instrument(&self, symbol: &str) -> InstrumentInfo {
+        Self::instrument(symbol).await.map(|(_, info)| info).unwrap_or_default()
+    }
+
+    fn snapshot_in_stream(&self) -> bool {
+        true
+    }
+
+    fn format_symbol(&self, symbol: &str) -> String {
+        symbol.to_uppercase()
+    }
+
+    fn get_name(&self) -> &'static str {
+        "BitMEX"
+    }
+}
@@ -0,0 +1,246 @@
+use super::error::check_status;
+use super::net::{self, WsMessage};
+use super::ratelimit::RateLimiter;
+use super::{
+    DepthLimits, Exchange, ExchangeError, ExchangeMessage, OrderBookSnapshot, OrderEvent, OrderEventKind, PriceLevel,
+    Side, WsEndpoint,
+};
+use futures_util::{SinkExt, StreamExt};
+use once_cell::sync::Lazy;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::mpsc::{self, Receiver};
+
+const REST_BASE: &str = "https://www.bitstamp.net/api/v2";
+const WS_URL: &str = "wss://ws.bitstamp.net";
+
+#[derive(Serialize)]
+struct BitstampSubscription {
+    event: String,
+    data: BitstampSubscriptionData,
+}
+
+#[derive(Serialize)]
+struct BitstampSubscriptionData {
+    channel: String,
+}
+
+#[derive(Deserialize)]
+struct BitstampEnvelope {
+    event: String,
+    channel: String,
+    #[serde(default)]
+    data: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct BitstampBook {
+    microtimestamp: String,
+    bids: Vec<[String; 2]>,
+    asks: Vec<[String; 2]>,
+}
+
+#[derive(Deserialize)]
+struct BitstampLiveOrder {
+    id: u64,
+    order_type: u8, // 0 = buy, 1 = sell
+    microtimestamp: String,
+    amount_str: String,
+    price_str: String,
+}
+
+#[derive(Deserialize)]
+struct BitstampPairInfo {
+    url_symbol: String,
+    base_decimals: usize,
+    counter_decimals: usize,
+}
+
+// Public REST allows 400 requests per second
+static RATE_LIMIT: Lazy<RateLimiter> = Lazy::new(|| RateLimiter::new("Bitstamp", 400, Duration::from_secs(1)));
+
+// (price, qty) precision per pair from trading-pairs-info, fetched once per session
+static PRECISION_CACHE: Lazy<Mutex<HashMap<String, (usize, usize)>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Default)]
+pub struct BitstampExchange {}
+
+impl BitstampExchange {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    fn parse_levels(levels: &[[String; 2]]) -> Vec<PriceLevel> {
+        levels
+            .iter()
+            .filter_map(|[px, sz]| match (Decimal::from_str(px), Decimal::from_str(sz)) {
+                (Ok(price), Ok(size)) => Some(PriceLevel::new(price, size)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    async fn fetch_precisions() -> Result<HashMap<String, (usize, usize)>, ExchangeError> {
+        let url = format!("{}/trading-pairs-info/", net::rest_base("bitstamp", REST_BASE));
+        let pairs: Vec<BitstampPairInfo> = check_status(RATE_LIMIT.get(&url, 1).await?, "")?.json().await?;
+        Ok(pairs
+            .into_iter()
+            .map(|p| (p.url_symbol, (p.counter_decimals, p.base_decimals)))
+            .collect())
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Exchange for BitstampExchange {
+    async fn connect(&self, symbol: &str) -> Result<Receiver<ExchangeMessage>, ExchangeError> {
+        let (tx, rx) = mpsc::channel(1000);
+        let endpoint = self.ws_endpoint(symbol).await?;
+        let symbol = symbol.to_lowercase();
+
+        let ws_stream = net::connect_ws(&endpoint.url).await?;
+
+        super::spawn(async move {
+            let (mut write, mut read) = ws_stream.split();
+
+            // Subscribe to the aggregated book and the per-order event stream
+            for channel in [format!("order_book_{symbol}"), format!("live_orders_{symbol}")] {
+                let subscription = BitstampSubscription {
+                    event: "bts:subscribe".to_string(),
+                    data: BitstampSubscriptionData { channel },
+                };
+                if let Ok(sub_msg) = serde_json::to_string(&subscription) {
+                    let _ = write.send(WsMessage::Text(sub_msg.into())).await;
+                }
+            }
+
+            let mut prev_time = 0u64;
+            // Ends when the stream closes or the feed drops this connection (reconnect, symbol
+            // change or shutdown)
+            while let Some(message) = tokio::select! {
+                message = read.next() => message,
+                _ = tx.closed() => None,
+            } {
+                match message {
+                    Ok(WsMessage::Text(text)) => {
+                        let recv_time = chrono::Utc::now().timestamp_millis() as u64;
+                        let Ok(envelope) = serde_json::from_str::<BitstampEnvelope>(&text) else {
+                            continue;
+                        };
+                        if envelope.event == "bts:request_reconnect" {
+                            break;
+                        }
+
+                        if envelope.channel.starts_with("order_book_") && envelope.event == "data" {
+                            let Ok(book) = serde_json::from_value::<BitstampBook>(envelope.data) else {
+                                continue;
+                            };
+                            // Full top-100 state; the book engine diffs consecutive states
+                            let time = book.microtimestamp.parse::<u64>().unwrap_or(prev_time);
+                            let state = OrderBookSnapshot {
+                                last_update_id: time,
+                                // Microseconds on the wire
+                                event_time: time / 1000,
+                                recv_time,
+                                bids: Self::parse_levels(&book.bids),
+                                asks: Self::parse_levels(&book.asks),
+                            };
+                            let _ = tx.send(ExchangeMessage::FullState(state)).await;
+                            prev_time = time;
+                        } else if envelope.channel.starts_with("live_orders_") {
+                            let kind = match envelope.event.as_str() {
+                                "order_created" => OrderEventKind::Created,
+                                "order_changed" => OrderEventKind::Changed,
+                                "order_deleted" => OrderEventKind::Deleted,
+                                _ => continue,
+                            };
+                            let Ok(order) = serde_json::from_value::<BitstampLiveOrder>(envelope.data) else {
+                                continue;
+                            };
+                            if let (Ok(price), Ok(qty)) = (
+                                Decimal::from_str(&order.price_str),
+                                Decimal::from_str(&order.amount_str),
+                            ) {
+                                let event = OrderEvent {
+                                    kind,
+                                    order_id: order.id,
+                                    side: if order.order_type == 0 { Side::Bid } else { Side::Ask },
+                                    price,
+                                    qty,
+                                    timestamp: order.microtimestamp.parse::<u64>().unwrap_or(0) / 1000,
+                                };
+                                let _ = tx.send(ExchangeMessage::Order(event)).await;
+                            }
+                        }
+                    }
+                    Ok(WsMessage::Ping(payload)) => {
+                        let _ = write.send(WsMessage::Pong(payload)).await;
+                    }
+                    Ok(WsMessage::Close(_)) => break,
+                    Err(e) => {
+                        tracing::warn!("Bitstamp WebSocket error: {e}");
+                        let _ = tx.send(ExchangeMessage::Error(e)).await;
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+            let _ = write.close().await;
+        });
+
+        Ok(rx)
+    }
+
+    async fn ws_endpoint(&self, _symbol: &str) -> Result<WsEndpoint, ExchangeError> {
+        Ok(WsEndpoint::new(net::ws_url("bitstamp", WS_URL)))
+    }
+
+    async fn get_snapshot(&self, symbol: &str) -> Result<OrderBookSnapshot, ExchangeError> {
+        let url = format!(
+            "{}/order_book/{}/",
+            net::rest_base("bitstamp", REST_BASE),
+            symbol.to_lowercase()
+        );
+        let response = check_status(RATE_LIMIT.get(&url, 1).await?, symbol)?;
+        let book: BitstampBook = response.json().await?;
+
+        Ok(OrderBookSnapshot {
+            last_update_id: book.microtimestamp.parse::<u64>()?,
+            event_time: 0,
+            recv_time: chrono::Utc::now().timestamp_millis() as u64,
+            bids: Self::parse_levels(&book.bids),
+            asks: Self::parse_levels(&book.asks),
+        })
+    }
+
+    async fn get_precision(&self, symbol: &str) -> (usize, usize) {
+        if PRECISION_CACHE.lock().unwrap().is_empty() {
+            match Self::fetch_precisions().await {
+                Ok(precisions) => *PRECISION_CACHE.lock().unwrap() = precisions,
+                Err(e) => tracing::warn!("Bitstamp trading-pairs-info request error: {e}"),
+            }
+        }
+        PRECISION_CACHE.lock().unwrap().get(&symbol.to_lowercase()).copied().unwrap_or((2, 8))
+    }
+
+    fn snapshot_in_stream(&self) -> bool {
+        true
+    }
+
+    // The order_book channel carries the top 100 levels
+    fn depth_limits(&self) -> DepthLimits {
+        DepthLimits::fixed(100)
+    }
+
+    fn format_symbol(&self, symbol: &str) -> String {
+        symbol.to_lowercase()
+    }
+
+    fn get_name(&self) -> &'static str {
+        "Bitstamp"
+    }
+}
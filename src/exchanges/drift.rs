@@ -0,0 +1,338 @@
+use super::error::check_status;
+use super::net::{self, WsMessage};
+use super::ratelimit::RateLimiter;
+use super::{
+    DepthLimits, Exchange, ExchangeError, ExchangeMessage, OrderBookSnapshot, OrderEvent, OrderEventKind, PriceLevel,
+    Side, WsEndpoint,
+};
+use futures_util::{SinkExt, StreamExt};
+use once_cell::sync::Lazy;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::mpsc::{self, Receiver};
+
+const REST_BASE: &str = "https://dlob.drift.trade";
+const WS_URL: &str = "wss://dlob.drift.trade/ws";
+// Levels per side REST snapshots can be requested at
+const DEPTH_LIMITS: DepthLimits = DepthLimits::steps(&[10, 20, 50, 100], 100);
+// The DLOB server has no order-level stream, so resting orders are polled
+const L3_POLL_INTERVAL: Duration = Duration::from_secs(2);
+// Prices and sizes are fixed-point integers with these scales
+const PRICE_SCALE: u32 = 6;
+const BASE_SCALE: u32 = 9;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DriftSubscription<'a> {
+    #[serde(rename = "type")]
+    kind: &'a str,
+    market_type: &'a str,
+    channel: &'a str,
+    market: &'a str,
+}
+
+#[derive(Deserialize)]
+struct DriftEnvelope {
+    #[serde(default)]
+    channel: String,
+    #[serde(default)]
+    error: Option<String>,
+    // The book arrives as a JSON document inside a string
+    #[serde(default)]
+    data: String,
+}
+
+#[derive(Deserialize)]
+struct DriftLevel {
+    price: String,
+    size: String,
+}
+
+// Aggregated DLOB, including the vAMM's liquidity
+#[derive(Deserialize)]
+struct DriftL2Book {
+    bids: Vec<DriftLevel>,
+    asks: Vec<DriftLevel>,
+    slot: u64,
+    #[serde(default)]
+    ts: u64,
+}
+
+// Resting orders by maker account; the vAMM is not included
+#[derive(Deserialize)]
+struct DriftL3Book {
+    bids: Vec<DriftL3Order>,
+    asks: Vec<DriftL3Order>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DriftL3Order {
+    price: String,
+    size: String,
+    maker: String,
+    order_id: u64,
+}
+
+// Fixed-point integer string to decimal
+fn scaled(raw: &str, scale: u32) -> Option<Decimal> {
+    let mut value = Decimal::from_str(raw).ok()?;
+    value.set_scale(value.scale() + scale).ok()?;
+    Some(value.normalize())
+}
+
+fn parse_levels(levels: &[DriftLevel]) -> Vec<PriceLevel> {
+    levels
+        .iter()
+        .filter_map(|level| Some(PriceLevel::new(scaled(&level.price, PRICE_SCALE)?, scaled(&level.size, BASE_SCALE)?)))
+        .collect()
+}
+
+impl DriftL2Book {
+    fn state(&self, recv_time: u64) -> OrderBookSnapshot {
+        OrderBookSnapshot {
+            last_update_id: self.slot,
+            event_time: self.ts,
+            recv_time,
+            bids: parse_levels(&self.bids),
+            asks: parse_levels(&self.asks),
+        }
+    }
+}
+
+// The DLOB server is shared infrastructure; keep well under its per-IP limit
+static RATE_LIMIT: Lazy<RateLimiter> = Lazy::new(|| RateLimiter::new("Drift", 10, Duration::from_secs(1)));
+
+// (price, qty) precision per market, inferred from its book on first use
+static PRECISION_CACHE: Lazy<Mutex<HashMap<String, (usize, usize)>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+// Orders from the previous L3 poll, keyed by a hash of maker and per-maker order id
+#[derive(Default)]
+struct OrderTracker {
+    orders: HashMap<u64, OrderEvent>,
+}
+
+impl OrderTracker {
+    // Order events turning the previous poll into this one
+    fn diff(&mut self, book: DriftL3Book, timestamp: u64) -> Vec<OrderEvent> {
+        let mut next = HashMap::new();
+        let mut events = vec![];
+        let sides = [(Side::Bid, book.bids), (Side::Ask, book.asks)];
+        for (side, order) in sides.into_iter().flat_map(|(side, orders)| orders.into_iter().map(move |o| (side, o))) {
+            let (Some(price), Some(qty)) = (scaled(&order.price, PRICE_SCALE), scaled(&order.size, BASE_SCALE)) else {
+                continue;
+            };
+            let mut hasher = DefaultHasher::new();
+            (&order.maker, order.order_id).hash(&mut hasher);
+            let order_id = hasher.finish();
+            let kind = match self.orders.get(&order_id) {
+                None => OrderEventKind::Created,
+                Some(prev) if prev.price != price || prev.qty != qty => OrderEventKind::Changed,
+                // Unchanged since the last poll
+                Some(prev) => {
+                    next.insert(order_id, prev.clone());
+                    continue;
+                }
+            };
+            let event = OrderEvent { kind, order_id, side, price, qty, timestamp };
+            events.push(event.clone());
+            next.insert(order_id, event);
+        }
+        for (id, order) in self.orders.drain() {
+            if !next.contains_key(&id) {
+                events.push(OrderEvent {
+                    kind: OrderEventKind::Deleted,
+                    timestamp,
+                    ..order
+                });
+            }
+        }
+        self.orders = next;
+        events
+    }
+}
+
+// Drift perpetuals on Solana (`SOL-PERP`-style). The DLOB server streams the aggregated book,
+// vAMM liquidity included, as full states per Solana slot; individual resting orders are
+// only available over REST and are polled and diffed into order events.
+pub struct DriftExchange {
+    // Levels per side in REST snapshots, one of `DEPTH_LIMITS`
+    depth: usize,
+}
+
+impl Default for DriftExchange {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl DriftExchange {
+    pub fn new(depth: Option<usize>) -> Self {
+        Self {
+            depth: DEPTH_LIMITS.negotiate(depth).unwrap_or(100),
+        }
+    }
+
+    async fn fetch_l2(&self, symbol: &str) -> Result<DriftL2Book, ExchangeError> {
+        let base = net::rest_base("drift", REST_BASE);
+        let url = format!("{base}/l2?marketName={symbol}&depth={}", self.depth);
+        Ok(check_status(RATE_LIMIT.get(&url, 1).await?, symbol)?.json().await?)
+    }
+
+    async fn fetch_l3(symbol: String) -> Result<DriftL3Book, ExchangeError> {
+        let url = format!("{}/l3?marketName={symbol}", net::rest_base("drift", REST_BASE));
+        Ok(check_status(RATE_LIMIT.get(&url, 1).await?, &symbol)?.json().await?)
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Exchange for DriftExchange {
+    // Emits a full state per slot and the order events found by each L3 poll
+    async fn connect(&self, symbol: &str) -> Result<Receiver<ExchangeMessage>, ExchangeError> {
+        let (tx, rx) = mpsc::channel(1000);
+        let endpoint = self.ws_endpoint(symbol).await?;
+        let ws_stream = net::connect_ws(&endpoint.url).await?;
+        let symbol = symbol.to_uppercase();
+
+        super::spawn(async move {
+            let (mut write, mut read) = ws_stream.split();
+            let subscription = DriftSubscription {
+                kind: "subscribe",
+                market_type: "perp",
+                channel: "orderbook",
+                market: &symbol,
+            };
+            if let Ok(sub_msg) = serde_json::to_string(&subscription) {
+                let _ = write.send(WsMessage::Text(sub_msg.into())).await;
+            }
+
+            let mut tracker = OrderTracker::default();
+            let mut poll = tokio::time::interval(L3_POLL_INTERVAL);
+            let mut pending = None;
+
+            loop {
+                let message = tokio::select! {
+                    message = read.next() => message,
+                    // The feed dropped this connection (reconnect, symbol change or shutdown)
+                    _ = tx.closed() => break,
+                    _ = poll.tick(), if pending.is_none() => {
+                        pending = Some(super::spawn(Self::fetch_l3(symbol.clone())));
+                        continue;
+                    }
+                    l3 = async { pending.as_mut().unwrap().await }, if pending.is_some() => {
+                        pending = None;
+                        let now = chrono::Utc::now().timestamp_millis() as u64;
+                        match l3.unwrap_or_else(|e| Err(ExchangeError::Network(e.to_string()))) {
+                            Ok(book) => {
+                                for event in tracker.diff(book, now) {
+                                    let _ = tx.send(ExchangeMessage::Order(event)).await;
+                                }
+                            }
+                            Err(e) => {
+                                tracing::warn!("Drift L3 request error: {e}");
+                                let _ = tx.send(ExchangeMessage::Error(e)).await;
+                            }
+                        }
+                        continue;
+                    }
+                };
+                match message {
+                    Some(Ok(WsMessage::Text(text))) => {
+                        let recv_time = chrono::Utc::now().timestamp_millis() as u64;
+                        let Ok(envelope) = serde_json::from_str::<DriftEnvelope>(&text) else {
+                            continue;
+                        };
+                        if let Some(error) = envelope.error {
+                            // Subscribing to an unknown market is the usual cause
+                            tracing::warn!("Drift error: {error}");
+                            let error = ExchangeError::InvalidSymbol(symbol.clone());
+                            let _ = tx.send(ExchangeMessage::Error(error)).await;
+                            break;
+                        }
+                        if !envelope.channel.starts_with("orderbook") {
+                            continue;
+                        }
+                        // Every orderbook message is a full state; the book engine diffs them
+                        match serde_json::from_str::<DriftL2Book>(&envelope.data) {
+                            Ok(book) => {
+                                let _ = tx.send(ExchangeMessage::FullState(book.state(recv_time))).await;
+                            }
+                            Err(e) => {
+                                let _ = tx.send(ExchangeMessage::Error(e.into())).await;
+                            }
+                        }
+                    }
+                    Some(Ok(WsMessage::Ping(payload))) => {
+                        let _ = write.send(WsMessage::Pong(payload)).await;
+                    }
+                    Some(Ok(WsMessage::Close(_))) | None => break,
+                    Some(Err(e)) => {
+                        tracing::warn!("Drift WebSocket error: {e}");
+                        let _ = tx.send(ExchangeMessage::Error(e)).await;
+                        break;
+                    }
+                    Some(Ok(_)) => {}
+                }
+            }
+            let _ = write.close().await;
+        });
+
+        Ok(rx)
+    }
+
+    async fn ws_endpoint(&self, _symbol: &str) -> Result<WsEndpoint, ExchangeError> {
+        Ok(WsEndpoint::new(net::ws_url("drift", WS_URL)))
+    }
+
+    async fn get_snapshot(&self, symbol: &str) -> Result<OrderBookSnapshot, ExchangeError> {
+        let book = self.fetch_l2(&symbol.to_uppercase()).await?;
+        let mut snapshot = book.state(chrono::Utc::now().timestamp_millis() as u64);
+        snapshot.event_time = 0;
+        Ok(snapshot)
+    }
+
+    // The DLOB server publishes no market metadata, so the precision is the finest one seen in
+    // a snapshot of the book
+    async fn get_precision(&self, symbol: &str) -> (usize, usize) {
+        let symbol = symbol.to_uppercase();
+        if let Some(&precision) = PRECISION_CACHE.lock().unwrap().get(&symbol) {
+            return precision;
+        }
+        match self.get_snapshot(&symbol).await {
+            Ok(snapshot) => {
+                let levels = || snapshot.bids.iter().chain(&snapshot.asks);
+                let price_prec = levels().map(|level| level.price.scale() as usize).max().unwrap_or(4);
+                let qty_prec = levels().map(|level| level.qty.scale() as usize).max().unwrap_or(2);
+                let precision = (price_prec, qty_prec);
+                PRECISION_CACHE.lock().unwrap().insert(symbol, precision);
+                precision
+            }
+            Err(e) => {
+                tracing::warn!("Drift L2 request error: {e}");
+                (4, 2)
+            }
+        }
+    }
+
+    fn snapshot_in_stream(&self) -> bool {
+        true
+    }
+
+    fn depth_limits(&self) -> DepthLimits {
+        DEPTH_LIMITS
+    }
+
+    fn format_symbol(&self, symbol: &str) -> String {
+        symbol.to_uppercase()
+    }
+
+    fn get_name(&self) -> &'static str {
+        "Drift"
+    }
+}
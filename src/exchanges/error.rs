@@ -0,0 +1,102 @@
+use std::fmt;
+
+// Errors surfaced by the exchange connectors. Variants carry a description rather than the
+// source error so they can be cloned into feed messages for the UI.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExchangeError {
+    // Connection, TLS or HTTP transport failure
+    Network(String),
+    // Unexpected payload from the venue
+    Parse(String),
+    // The venue does not know the requested symbol
+    InvalidSymbol(String),
+    // HTTP 429/418 or an equivalent venue response
+    RateLimited(String),
+    // Sequence gap between the snapshot and the update stream
+    Desync(String),
+}
+
+impl ExchangeError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ExchangeError::Network(_) => "network",
+            ExchangeError::Parse(_) => "parse",
+            ExchangeError::InvalidSymbol(_) => "invalid symbol",
+            ExchangeError::RateLimited(_) => "rate limited",
+            ExchangeError::Desync(_) => "desync",
+        }
+    }
+}
+
+impl fmt::Display for ExchangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExchangeError::Network(msg)
+            | ExchangeError::Parse(msg)
+            | ExchangeError::InvalidSymbol(msg)
+            | ExchangeError::RateLimited(msg)
+            | ExchangeError::Desync(msg) => write!(f, "{}: {msg}", self.kind()),
+        }
+    }
+}
+
+impl std::error::Error for ExchangeError {}
+
+impl From<reqwest::Error> for ExchangeError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_decode() {
+            ExchangeError::Parse(e.to_string())
+        } else {
+            ExchangeError::Network(e.to_string())
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl From<tungstenite::Error> for ExchangeError {
+    fn from(e: tungstenite::Error) -> Self {
+        ExchangeError::Network(e.to_string())
+    }
+}
+
+impl From<std::io::Error> for ExchangeError {
+    fn from(e: std::io::Error) -> Self {
+        ExchangeError::Network(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for ExchangeError {
+    fn from(e: serde_json::Error) -> Self {
+        ExchangeError::Parse(e.to_string())
+    }
+}
+
+impl From<std::num::ParseIntError> for ExchangeError {
+    fn from(e: std::num::ParseIntError) -> Self {
+        ExchangeError::Parse(e.to_string())
+    }
+}
+
+// Maps HTTP error statuses of REST calls for `symbol` onto the error variants
+#[cfg(any(
+    feature = "binance",
+    feature = "hyperliquid",
+    feature = "bitstamp",
+    feature = "gateio",
+    feature = "bitget",
+    feature = "kucoin",
+    feature = "mexc",
+    feature = "bitmex",
+    feature = "bitfinex",
+    feature = "aevo",
+    feature = "drift"
+))]
+pub(crate) fn check_status(response: reqwest::Response, symbol: &str) -> Result<reqwest::Response, ExchangeError> {
+    let status = response.status();
+    match status.as_u16() {
+        429 | 418 => Err(ExchangeError::RateLimited(format!("HTTP {status}"))),
+        400 | 404 => Err(ExchangeError::InvalidSymbol(symbol.to_string())),
+        _ if !status.is_success() => Err(ExchangeError::Network(format!("HTTP {status}"))),
+        _ => Ok(response),
+    }
+}
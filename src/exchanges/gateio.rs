@@ -0,0 +1,393 @@
+use super::error::check_status;
+use super::net::{self, WsMessage};
+use super::ratelimit::RateLimiter;
+use super::sync::{Chaining, DepthSync};
+use super::{
+    DepthLimits, DepthUpdate, Exchange, ExchangeError, ExchangeMessage, InstrumentInfo, InstrumentStats,
+    OrderBookSnapshot, PriceLevel, WsEndpoint,
+};
+use futures_util::{SinkExt, StreamExt};
+use once_cell::sync::Lazy;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::mpsc::{self, Receiver};
+
+// USDT-settled perpetuals
+const REST_BASE: &str = "https://api.gateio.ws/api/v4/futures/usdt";
+const WS_URL: &str = "wss://fx-ws.gateio.ws/v4/ws/usdt";
+// Levels the diff stream can be subscribed at. The REST snapshot is requested at the same
+// depth, so levels leaving the window are reported by the stream.
+const DEPTH_LIMITS: DepthLimits = DepthLimits::steps(&[20, 50, 100], 100);
+// Consecutive failed resyncs before the stream is closed and the feed reconnects with backoff
+const MAX_RESYNCS: u32 = 3;
+// Gate closes connections it has not heard from; an application-level ping keeps it open
+const PING_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Serialize)]
+struct GateioRequest<'a> {
+    time: i64,
+    channel: &'a str,
+    #[serde(skip_serializing_if = "str::is_empty")]
+    event: &'a str,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    payload: Vec<&'a str>,
+}
+
+impl<'a> GateioRequest<'a> {
+    fn new(channel: &'a str, event: &'a str, payload: Vec<&'a str>) -> Self {
+        Self {
+            time: chrono::Utc::now().timestamp(),
+            channel,
+            event,
+            payload,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct GateioEnvelope {
+    channel: String,
+    #[serde(default)]
+    event: String,
+    #[serde(default)]
+    error: Option<GateioError>,
+    #[serde(default)]
+    result: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct GateioError {
+    message: String,
+}
+
+// Sizes are in contracts; Gate sends them as numbers or strings depending on the endpoint
+#[derive(Deserialize)]
+struct GateioLevel {
+    p: Decimal,
+    s: Decimal,
+}
+
+#[derive(Deserialize)]
+struct GateioOrderBook {
+    id: u64,
+    // Seconds since the Unix epoch, with milliseconds as the fraction
+    current: f64,
+    bids: Vec<GateioLevel>,
+    asks: Vec<GateioLevel>,
+}
+
+#[derive(Deserialize)]
+struct GateioBookUpdate {
+    t: u64,
+    s: String,
+    #[serde(rename = "U")]
+    capital_u: u64,
+    u: u64,
+    #[serde(default)]
+    b: Vec<GateioLevel>,
+    #[serde(default)]
+    a: Vec<GateioLevel>,
+}
+
+#[derive(Deserialize)]
+struct GateioTicker {
+    mark_price: Option<Decimal>,
+    index_price: Option<Decimal>,
+    funding_rate: Option<Decimal>,
+    // Open interest in contracts
+    total_size: Option<Decimal>,
+}
+
+#[derive(Deserialize)]
+struct GateioContract {
+    name: String,
+    order_price_round: String,
+    // Base asset per contract, e.g. "0.0001" on BTC_USDT
+    #[serde(default)]
+    quanto_multiplier: Option<Decimal>,
+}
+
+impl From<GateioTicker> for InstrumentStats {
+    fn from(ticker: GateioTicker) -> Self {
+        InstrumentStats {
+            mark_price: ticker.mark_price,
+            index_price: ticker.index_price,
+            funding_rate: ticker.funding_rate,
+            next_funding_time: None,
+            open_interest: ticker.total_size,
+        }
+    }
+}
+
+fn levels(levels: Vec<GateioLevel>) -> Vec<PriceLevel> {
+    levels.into_iter().map(|level| PriceLevel::new(level.p, level.s)).collect()
+}
+
+// Public endpoints allow 200 requests per 10 seconds
+static RATE_LIMIT: Lazy<RateLimiter> = Lazy::new(|| RateLimiter::new("Gate.io", 200, Duration::from_secs(10)));
+
+// (price, qty) precision and size per contract from the contract list, fetched once per session
+type Contracts = HashMap<String, ((usize, usize), InstrumentInfo)>;
+static CONTRACT_CACHE: Lazy<Mutex<Contracts>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+enum StreamEvent {
+    Ws(Option<Result<WsMessage, ExchangeError>>),
+    Snapshot(Result<OrderBookSnapshot, ExchangeError>),
+    Ping,
+}
+
+// Gate.io USDT perpetuals. The `futures.order_book_update` diffs chain by `U = u + 1` onto a
+// REST snapshot taken with its id, so the connector synchronizes them like Binance spot.
+pub struct GateioExchange {
+    // Levels per side in the snapshot and the stream, one of `DEPTH_LIMITS`
+    depth: usize,
+}
+
+impl Default for GateioExchange {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl GateioExchange {
+    pub fn new(depth: Option<usize>) -> Self {
+        Self {
+            depth: DEPTH_LIMITS.negotiate(depth).unwrap_or(100),
+        }
+    }
+
+    async fn fetch_snapshot(contract: String, depth: usize) -> Result<OrderBookSnapshot, ExchangeError> {
+        let base = net::rest_base("gateio", REST_BASE);
+        let url = format!("{base}/order_book?contract={contract}&limit={depth}&with_id=true");
+        let response = check_status(RATE_LIMIT.get(&url, 1).await?, &contract)?;
+        let book: GateioOrderBook = response.json().await?;
+        Ok(OrderBookSnapshot {
+            last_update_id: book.id,
+            event_time: (book.current * 1000.0) as u64,
+            recv_time: chrono::Utc::now().timestamp_millis() as u64,
+            bids: levels(book.bids),
+            asks: levels(book.asks),
+        })
+    }
+
+    async fn fetch_contracts() -> Result<Contracts, ExchangeError> {
+        let url = format!("{}/contracts", net::rest_base("gateio", REST_BASE));
+        let contracts: Vec<GateioContract> = check_status(RATE_LIMIT.get(&url, 1).await?, "")?.json().await?;
+        Ok(contracts
+            .into_iter()
+            .map(|c| {
+                // Decimals of the tick, e.g. "0.00001" -> 5; sizes are whole contracts
+                let price_decimals = c
+                    .order_price_round
+                    .split_once('.')
+                    .map_or(0, |(_, frac)| frac.trim_end_matches('0').len());
+                let multiplier = c.quanto_multiplier.filter(|m| !m.is_zero()).unwrap_or(Decimal::ONE);
+                (c.name, ((price_decimals, 0), InstrumentInfo::linear(multiplier)))
+            })
+            .collect())
+    }
+
+    async fn contract(symbol: &str) -> Option<((usize, usize), InstrumentInfo)> {
+        if CONTRACT_CACHE.lock().unwrap().is_empty() {
+            match Self::fetch_contracts().await {
+                Ok(contracts) => *CONTRACT_CACHE.lock().unwrap() = contracts,
+                Err(e) => tracing::warn!("Gate.io contracts request error: {e}"),
+            }
+        }
+        CONTRACT_CACHE.lock().unwrap().get(&symbol.to_uppercase()).copied()
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Exchange for GateioExchange {
+    // Emits a snapshot followed by an already sequenced update stream; a sequence break is
+    // reported as a Desync error and followed by a fresh snapshot
+    async fn connect(&self, symbol: &str) -> Result<Receiver<ExchangeMessage>, ExchangeError> {
+        let (tx, rx) = mpsc::channel(1000);
+        let endpoint = self.ws_endpoint(symbol).await?;
+        let ws_stream = net::connect_ws(&endpoint.url).await?;
+        let contract = symbol.to_uppercase();
+        let depth = self.depth;
+
+        super::spawn(async move {
+            let (mut write, mut read) = ws_stream.split();
+            let window = depth.to_string();
+            let subscriptions = [
+                GateioRequest::new("futures.order_book_update", "subscribe", vec![&contract, "100ms", &window]),
+                GateioRequest::new("futures.tickers", "subscribe", vec![&contract]),
+            ];
+            for subscription in &subscriptions {
+                if let Ok(sub_msg) = serde_json::to_string(subscription) {
+                    let _ = write.send(WsMessage::Text(sub_msg.into())).await;
+                }
+            }
+
+            let mut sync = DepthSync::new(Chaining::Contiguous);
+            // Fetched while the stream is already being buffered
+            let mut pending = Some(super::spawn(Self::fetch_snapshot(contract.clone(), depth)));
+            let mut failed_resyncs = 0;
+            let mut ping = tokio::time::interval(PING_INTERVAL);
+
+            loop {
+                let event = tokio::select! {
+                    message = read.next() => StreamEvent::Ws(message),
+                    // The feed dropped this connection (reconnect, symbol change or shutdown)
+                    _ = tx.closed() => break,
+                    snapshot = async { pending.as_mut().unwrap().await }, if pending.is_some() => {
+                        StreamEvent::Snapshot(snapshot.unwrap_or_else(|e| Err(ExchangeError::Network(e.to_string()))))
+                    }
+                    _ = ping.tick() => StreamEvent::Ping,
+                };
+
+                let synced = match event {
+                    StreamEvent::Snapshot(Ok(snapshot)) => {
+                        pending = None;
+                        sync.on_snapshot(snapshot.last_update_id).map(|ready| (Some(snapshot), ready))
+                    }
+                    StreamEvent::Snapshot(Err(e)) => {
+                        tracing::warn!("Gate.io snapshot request error: {e}");
+                        let _ = tx.send(ExchangeMessage::Error(e)).await;
+                        break;
+                    }
+                    StreamEvent::Ping => {
+                        if let Ok(ping_msg) = serde_json::to_string(&GateioRequest::new("futures.ping", "", vec![])) {
+                            let _ = write.send(WsMessage::Text(ping_msg.into())).await;
+                        }
+                        continue;
+                    }
+                    StreamEvent::Ws(Some(Ok(WsMessage::Text(text)))) => {
+                        let recv_time = chrono::Utc::now().timestamp_millis() as u64;
+                        let envelope = match serde_json::from_str::<GateioEnvelope>(&text) {
+                            Ok(envelope) => envelope,
+                            Err(e) => {
+                                let _ = tx.send(ExchangeMessage::Error(e.into())).await;
+                                continue;
+                            }
+                        };
+                        if let Some(error) = envelope.error {
+                            // Subscribing to an unknown contract is the usual cause
+                            tracing::warn!("Gate.io {} error: {}", envelope.channel, error.message);
+                            let error = ExchangeError::InvalidSymbol(contract.clone());
+                            let _ = tx.send(ExchangeMessage::Error(error)).await;
+                            break;
+                        }
+                        if envelope.event != "update" {
+                            continue;
+                        }
+                        match envelope.channel.as_str() {
+                            "futures.tickers" => {
+                                let message = match serde_json::from_value::<Vec<GateioTicker>>(envelope.result) {
+                                    Ok(tickers) => match tickers.into_iter().next() {
+                                        Some(ticker) => ExchangeMessage::InstrumentStats(ticker.into()),
+                                        None => continue,
+                                    },
+                                    Err(e) => ExchangeMessage::Error(e.into()),
+                                };
+                                let _ = tx.send(message).await;
+                                continue;
+                            }
+                            "futures.order_book_update" => {}
+                            _ => continue,
+                        }
+                        match serde_json::from_value::<GateioBookUpdate>(envelope.result) {
+                            Ok(update) => {
+                                let update = DepthUpdate {
+                                    event_time: update.t,
+                                    transaction_time: update.t,
+                                    symbol: update.s,
+                                    capital_u: update.capital_u,
+                                    small_u: update.u,
+                                    pu: -1,
+                                    bids: levels(update.b),
+                                    asks: levels(update.a),
+                                    recv_time,
+                                };
+                                sync.on_event(update).map(|ready| (None, ready.into_iter().collect()))
+                            }
+                            Err(e) => {
+                                let _ = tx.send(ExchangeMessage::Error(e.into())).await;
+                                continue;
+                            }
+                        }
+                    }
+                    StreamEvent::Ws(Some(Ok(WsMessage::Ping(payload)))) => {
+                        let _ = write.send(WsMessage::Pong(payload)).await;
+                        continue;
+                    }
+                    StreamEvent::Ws(Some(Ok(WsMessage::Close(_)))) | StreamEvent::Ws(None) => break,
+                    StreamEvent::Ws(Some(Err(e))) => {
+                        tracing::warn!("Gate.io WebSocket error: {e}");
+                        let _ = tx.send(ExchangeMessage::Error(e)).await;
+                        break;
+                    }
+                    StreamEvent::Ws(Some(Ok(_))) => continue,
+                };
+
+                match synced {
+                    Ok((snapshot, ready)) => {
+                        if let Some(snapshot) = snapshot {
+                            let _ = tx.send(ExchangeMessage::Snapshot(snapshot)).await;
+                        }
+                        if !ready.is_empty() {
+                            failed_resyncs = 0;
+                        }
+                        for update in ready {
+                            let _ = tx.send(ExchangeMessage::Update(update)).await;
+                        }
+                    }
+                    Err(e) => {
+                        // Start over from a fresh snapshot, or give up and let the feed reconnect
+                        tracing::warn!("Gate.io depth sync lost: {e}");
+                        let _ = tx.send(ExchangeMessage::Error(e)).await;
+                        failed_resyncs += 1;
+                        if failed_resyncs > MAX_RESYNCS {
+                            break;
+                        }
+                        sync = DepthSync::new(Chaining::Contiguous);
+                        pending = Some(super::spawn(Self::fetch_snapshot(contract.clone(), depth)));
+                    }
+                }
+            }
+            let _ = write.close().await;
+        });
+
+        Ok(rx)
+    }
+
+    async fn ws_endpoint(&self, _symbol: &str) -> Result<WsEndpoint, ExchangeError> {
+        Ok(WsEndpoint::new(net::ws_url("gateio", WS_URL)))
+    }
+
+    async fn get_snapshot(&self, symbol: &str) -> Result<OrderBookSnapshot, ExchangeError> {
+        Self::fetch_snapshot(symbol.to_uppercase(), self.depth).await
+    }
+
+    async fn get_precision(&self, symbol: &str) -> (usize, usize) {
+        Self::contract(symbol).await.map_or((4, 0), |(precision, _)| precision)
+    }
+
+    // Sizes are contracts of the contract's quanto multiplier
+    async fn get_instrument(&self, symbol: &str) -> InstrumentInfo {
+        Self::contract(symbol).await.map(|(_, instrument)| instrument).unwrap_or_default()
+    }
+
+    fn snapshot_in_stream(&self) -> bool {
+        true
+    }
+
+    fn depth_limits(&self) -> DepthLimits {
+        DEPTH_LIMITS
+    }
+
+    fn format_symbol(&self, symbol: &str) -> String {
+        symbol.to_uppercase()
+    }
+
+    fn get_name(&self) -> &'static str {
+        "Gate.io"
+    }
+}
@@ -1,10 +1,18 @@
-use super::{DepthUpdate, Exchange, ExchangeMessage, OrderBookSnapshot};
+use super::error::check_status;
+use super::net::{self, WsMessage};
+use super::ratelimit::RateLimiter;
+use super::{
+    DepthLimits, Exchange, ExchangeError, ExchangeMessage, InstrumentStats, OrderBookSnapshot, PriceLevel, WsEndpoint,
+};
 use futures_util::{SinkExt, StreamExt};
+use once_cell::sync::Lazy;
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
 use tokio::sync::mpsc::{self, Receiver};
-use tokio_tungstenite::{connect_async, tungstenite::protocol::Message as WsMessage};
 
 #[derive(Serialize)]
 struct HyperliquidSubscription {
@@ -19,9 +27,15 @@ struct HyperliquidSubscriptionData {
     coin: String,
 }
 
+#[derive(Deserialize)]
+struct HyperliquidWsEnvelope {
+    channel: String,
+    #[serde(default)]
+    data: serde_json::Value,
+}
+
 #[derive(Deserialize)]
 struct HyperliquidWsBook {
-    coin: String,
     levels: [Vec<HyperliquidWsLevel>; 2], // [bids, asks]
     time: u64,
 }
@@ -30,7 +44,8 @@ struct HyperliquidWsBook {
 struct HyperliquidWsLevel {
     px: String,  // price
     sz: String,  // size
-    n: u32,      // number of orders
+    #[serde(default)]
+    n: Option<u32>, // number of orders
 }
 
 #[derive(Serialize)]
@@ -40,6 +55,73 @@ struct HyperliquidInfoRequest {
     coin: String,
 }
 
+#[derive(Serialize)]
+struct HyperliquidMetaRequest {
+    #[serde(rename = "type")]
+    req_type: String,
+}
+
+#[derive(Deserialize)]
+struct HyperliquidMeta {
+    universe: Vec<HyperliquidAssetMeta>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HyperliquidAssetMeta {
+    name: String,
+    sz_decimals: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HyperliquidAssetCtx {
+    mark_px: Option<String>,
+    oracle_px: Option<String>,
+    funding: Option<String>,
+    open_interest: Option<String>,
+}
+
+impl HyperliquidAssetCtx {
+    fn stats(&self) -> InstrumentStats {
+        let parse = |value: &Option<String>| value.as_deref().and_then(|v| Decimal::from_str(v).ok());
+        // Funding is paid every hour on the hour
+        let hour = 3_600_000;
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        InstrumentStats {
+            mark_price: parse(&self.mark_px),
+            index_price: parse(&self.oracle_px),
+            funding_rate: parse(&self.funding),
+            next_funding_time: Some((now / hour + 1) * hour),
+            open_interest: parse(&self.open_interest),
+        }
+    }
+}
+
+// `activeAssetCtx` channel payload
+#[derive(Deserialize)]
+struct HyperliquidActiveAssetCtx {
+    ctx: HyperliquidAssetCtx,
+}
+
+const REST_BASE: &str = "https://api.hyperliquid.xyz";
+const WS_URL: &str = "wss://api.hyperliquid.xyz/ws";
+const TESTNET_REST_BASE: &str = "https://api.hyperliquid-testnet.xyz";
+const TESTNET_WS_URL: &str = "wss://api.hyperliquid-testnet.xyz/ws";
+
+// Perp prices may have at most 6 - szDecimals decimals and 5 significant figures
+const MAX_PRICE_DECIMALS: u32 = 6;
+const MAX_SIG_FIGS: i32 = 5;
+
+// Info requests share 1200 weight per minute and IP
+static RATE_LIMIT: Lazy<RateLimiter> = Lazy::new(|| RateLimiter::new("Hyperliquid", 1200, Duration::from_secs(60)));
+
+// (price, qty) precision per coin
+type Precisions = HashMap<String, (usize, usize)>;
+
+// Precisions per network (testnet or not), filled for every listed coin on the network's first lookup
+static PRECISION_CACHE: Lazy<Mutex<HashMap<bool, Precisions>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
 #[derive(Deserialize)]
 struct HyperliquidL2Book {
     levels: [Vec<HyperliquidLevel>; 2], // [bids, asks]
@@ -49,198 +131,193 @@ struct HyperliquidL2Book {
 struct HyperliquidLevel {
     px: String,
     sz: String,
-    n: u32,
+    #[serde(default)]
+    n: Option<u32>,
 }
 
-pub struct HyperliquidExchange {}
+#[derive(Default)]
+pub struct HyperliquidExchange {
+    // Streams the testnet, which lists its own coins and is free to hammer
+    testnet: bool,
+}
 
 impl HyperliquidExchange {
-    pub fn new() -> Self {
-        Self {}
+    pub fn new(testnet: bool) -> Self {
+        Self { testnet }
     }
 
-    fn convert_ws_book_to_snapshot(&self, book: HyperliquidWsBook) -> OrderBookSnapshot {
-        let mut bids = Vec::new();
-        let mut asks = Vec::new();
+    // REST queries all go to the one info endpoint
+    fn info_request(&self, request: &impl Serialize) -> reqwest::RequestBuilder {
+        let base = match self.testnet {
+            true => net::rest_base("hyperliquid_testnet", TESTNET_REST_BASE),
+            false => net::rest_base("hyperliquid", REST_BASE),
+        };
+        net::client().post(format!("{base}/info")).json(request)
+    }
 
-        // Convert bids (index 0)
-        for level in &book.levels[0] {
-            if let (Ok(price), Ok(size)) = (
-                Decimal::from_str(&level.px),
-                Decimal::from_str(&level.sz),
-            ) {
-                bids.push(vec![price, size]);
-            }
-        }
+    fn price_decimals(sz_decimals: u32, mark_px: Option<f64>) -> usize {
+        let max_decimals = MAX_PRICE_DECIMALS.saturating_sub(sz_decimals) as i32;
+        let sig_fig_decimals = match mark_px {
+            Some(px) if px > 0.0 => MAX_SIG_FIGS - (px.log10().floor() as i32 + 1),
+            _ => max_decimals,
+        };
+        sig_fig_decimals.clamp(0, max_decimals) as usize
+    }
 
-        // Convert asks (index 1)
-        for level in &book.levels[1] {
-            if let (Ok(price), Ok(size)) = (
-                Decimal::from_str(&level.px),
-                Decimal::from_str(&level.sz),
-            ) {
-                asks.push(vec![price, size]);
-            }
-        }
+    async fn fetch_precisions(&self) -> Result<Precisions, ExchangeError> {
+        let request = HyperliquidMetaRequest {
+            req_type: "metaAndAssetCtxs".to_string(),
+        };
+        let response = RATE_LIMIT.send(self.info_request(&request), 20).await?;
+        let (meta, ctxs): (HyperliquidMeta, Vec<HyperliquidAssetCtx>) = response.json().await?;
 
-        OrderBookSnapshot {
-            last_update_id: book.time,
-            bids,
-            asks,
-        }
+        Ok(meta
+            .universe
+            .into_iter()
+            .zip(ctxs)
+            .map(|(asset, ctx)| {
+                let mark_px = ctx.mark_px.and_then(|px| px.parse::<f64>().ok());
+                let prec = (Self::price_decimals(asset.sz_decimals, mark_px), asset.sz_decimals as usize);
+                (asset.name.to_uppercase(), prec)
+            })
+            .collect())
     }
 
-    fn convert_ws_book_to_update(&self, book: HyperliquidWsBook) -> DepthUpdate {
+    fn convert_ws_book(book: &HyperliquidWsBook) -> OrderBookSnapshot {
         let mut bids = Vec::new();
         let mut asks = Vec::new();
 
-        // Convert bids
+        // Convert bids (index 0)
         for level in &book.levels[0] {
             if let (Ok(price), Ok(size)) = (
                 Decimal::from_str(&level.px),
                 Decimal::from_str(&level.sz),
             ) {
-                bids.push(vec![price, size]);
+                bids.push(PriceLevel {
+                    order_count: level.n,
+                    ..PriceLevel::new(price, size)
+                });
             }
         }
 
-        // Convert asks
+        // Convert asks (index 1)
         for level in &book.levels[1] {
             if let (Ok(price), Ok(size)) = (
                 Decimal::from_str(&level.px),
                 Decimal::from_str(&level.sz),
             ) {
-                asks.push(vec![price, size]);
+                asks.push(PriceLevel {
+                    order_count: level.n,
+                    ..PriceLevel::new(price, size)
+                });
             }
         }
 
-        DepthUpdate {
+        OrderBookSnapshot {
+            last_update_id: book.time,
             event_time: book.time,
-            transaction_time: book.time,
-            symbol: book.coin.clone(),
-            capital_u: book.time,
-            small_u: book.time,
-            pu: (book.time - 1) as i64,
+            recv_time: chrono::Utc::now().timestamp_millis() as u64,
             bids,
             asks,
         }
     }
 }
 
-#[async_trait::async_trait]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
 impl Exchange for HyperliquidExchange {
-    async fn connect(&self, symbol: &str) -> Result<Receiver<ExchangeMessage>, Box<dyn std::error::Error>> {
+    async fn connect(&self, symbol: &str) -> Result<Receiver<ExchangeMessage>, ExchangeError> {
         let (tx, rx) = mpsc::channel(1000);
-        let ws_url = "wss://api.hyperliquid.xyz/ws";
+        let endpoint = self.ws_endpoint(symbol).await?;
         let symbol = symbol.to_uppercase();
+        let ws_stream = net::connect_ws(&endpoint.url).await?;
 
-        tokio::spawn(async move {
-            if let Ok((ws_stream, _)) = connect_async(ws_url).await {
-                let (mut write, mut read) = ws_stream.split();
-                
-                // Subscribe to order book
+        super::spawn(async move {
+            let (mut write, mut read) = ws_stream.split();
+
+            // Subscribe to the order book and the asset context (mark, funding, open interest)
+            for sub_type in ["l2Book", "activeAssetCtx"] {
                 let subscription = HyperliquidSubscription {
                     method: "subscribe".to_string(),
                     subscription: HyperliquidSubscriptionData {
-                        sub_type: "l2Book".to_string(),
+                        sub_type: sub_type.to_string(),
                         coin: symbol.clone(),
                     },
                 };
-
                 if let Ok(sub_msg) = serde_json::to_string(&subscription) {
                     let _ = write.send(WsMessage::Text(sub_msg.into())).await;
                 }
+            }
 
-                let mut first_message = true;
-                while let Some(message) = read.next().await {
-                    match message {
-                        Ok(WsMessage::Text(text)) => {
-                            if let Ok(book) = serde_json::from_str::<HyperliquidWsBook>(&text) {
-                                if first_message {
-                                    // Send first message as snapshot
-                                    let snapshot = OrderBookSnapshot {
-                                        last_update_id: book.time,
-                                        bids: book.levels[0]
-                                            .iter()
-                                            .filter_map(|level| {
-                                                match (Decimal::from_str(&level.px), Decimal::from_str(&level.sz)) {
-                                                    (Ok(price), Ok(size)) => Some(vec![price, size]),
-                                                    _ => None,
-                                                }
-                                            })
-                                            .collect(),
-                                        asks: book.levels[1]
-                                            .iter()
-                                            .filter_map(|level| {
-                                                match (Decimal::from_str(&level.px), Decimal::from_str(&level.sz)) {
-                                                    (Ok(price), Ok(size)) => Some(vec![price, size]),
-                                                    _ => None,
-                                                }
-                                            })
-                                            .collect(),
-                                    };
-                                    let _ = tx.send(ExchangeMessage::Snapshot(snapshot)).await;
-                                    first_message = false;
-                                } else {
-                                    // Send subsequent messages as updates
-                                    let update = DepthUpdate {
-                                        event_time: book.time,
-                                        transaction_time: book.time,
-                                        symbol: book.coin.clone(),
-                                        capital_u: book.time,
-                                        small_u: book.time,
-                                        pu: (book.time - 1) as i64,
-                                        bids: book.levels[0]
-                                            .iter()
-                                            .filter_map(|level| {
-                                                match (Decimal::from_str(&level.px), Decimal::from_str(&level.sz)) {
-                                                    (Ok(price), Ok(size)) => Some(vec![price, size]),
-                                                    _ => None,
-                                                }
-                                            })
-                                            .collect(),
-                                        asks: book.levels[1]
-                                            .iter()
-                                            .filter_map(|level| {
-                                                match (Decimal::from_str(&level.px), Decimal::from_str(&level.sz)) {
-                                                    (Ok(price), Ok(size)) => Some(vec![price, size]),
-                                                    _ => None,
-                                                }
-                                            })
-                                            .collect(),
-                                    };
-                                    let _ = tx.send(ExchangeMessage::Update(update)).await;
-                                }
-                            }
+            // Ends when the stream closes or the feed drops this connection (reconnect, symbol
+            // change or shutdown)
+            while let Some(message) = tokio::select! {
+                message = read.next() => message,
+                _ = tx.closed() => None,
+            } {
+                match message {
+                    Ok(WsMessage::Text(text)) => {
+                        let Ok(envelope) = serde_json::from_str::<HyperliquidWsEnvelope>(&text) else {
+                            continue;
+                        };
+                        if envelope.channel == "activeAssetCtx" {
+                            let message = match serde_json::from_value::<HyperliquidActiveAssetCtx>(envelope.data) {
+                                Ok(active) => ExchangeMessage::InstrumentStats(active.ctx.stats()),
+                                Err(e) => ExchangeMessage::Error(e.into()),
+                            };
+                            let _ = tx.send(message).await;
+                            continue;
                         }
-                        Ok(WsMessage::Ping(payload)) => {
-                            let _ = write.send(WsMessage::Pong(payload)).await;
+                        if envelope.channel != "l2Book" {
+                            continue;
                         }
-                        Ok(WsMessage::Close(_)) => break,
-                        Err(e) => {
-                            println!("Hyperliquid WebSocket error: {:?}", e);
-                            break;
+                        // Every l2Book message is a full state; the book engine diffs them
+                        match serde_json::from_value::<HyperliquidWsBook>(envelope.data) {
+                            Ok(book) => {
+                                let state = Self::convert_ws_book(&book);
+                                let _ = tx.send(ExchangeMessage::FullState(state)).await;
+                            }
+                            Err(e) => {
+                                let _ = tx.send(ExchangeMessage::Error(e.into())).await;
+                            }
                         }
-                        _ => {}
                     }
+                    Ok(WsMessage::Ping(payload)) => {
+                        let _ = write.send(WsMessage::Pong(payload)).await;
+                    }
+                    Ok(WsMessage::Close(_)) => break,
+                    Err(e) => {
+                        tracing::warn!("Hyperliquid WebSocket error: {e}");
+                        let _ = tx.send(ExchangeMessage::Error(e)).await;
+                        break;
+                    }
+                    _ => {}
                 }
             }
+            let _ = write.close().await;
         });
 
         Ok(rx)
     }
 
-    async fn get_snapshot(&self, symbol: &str) -> Result<OrderBookSnapshot, Box<dyn std::error::Error>> {
-        let client = reqwest::Client::new();
-        let url = "https://api.hyperliquid.xyz/info";
+    async fn ws_endpoint(&self, _symbol: &str) -> Result<WsEndpoint, ExchangeError> {
+        Ok(WsEndpoint::new(match self.testnet {
+            true => net::ws_url("hyperliquid_testnet", TESTNET_WS_URL),
+            false => net::ws_url("hyperliquid", WS_URL),
+        }))
+    }
+
+    async fn get_snapshot(&self, symbol: &str) -> Result<OrderBookSnapshot, ExchangeError> {
         
         let request = HyperliquidInfoRequest {
             req_type: "l2Book".to_string(),
             coin: symbol.to_uppercase(),
         };
 
-        let response = client.post(url).json(&request).send().await?;
-        let l2_book: HyperliquidL2Book = response.json().await?;
+        let response = check_status(RATE_LIMIT.send(self.info_request(&request), 2).await?, symbol)?;
+        // Unknown coins come back as `null`
+        let l2_book: Option<HyperliquidL2Book> = response.json().await?;
+        let l2_book = l2_book.ok_or_else(|| ExchangeError::InvalidSymbol(symbol.to_string()))?;
 
         let mut bids = Vec::new();
         let mut asks = Vec::new();
@@ -251,7 +328,10 @@ impl Exchange for HyperliquidExchange {
                 Decimal::from_str(&level.px),
                 Decimal::from_str(&level.sz),
             ) {
-                bids.push(vec![price, size]);
+                bids.push(PriceLevel {
+                    order_count: level.n,
+                    ..PriceLevel::new(price, size)
+                });
             }
         }
 
@@ -261,21 +341,48 @@ impl Exchange for HyperliquidExchange {
                 Decimal::from_str(&level.px),
                 Decimal::from_str(&level.sz),
             ) {
-                asks.push(vec![price, size]);
+                asks.push(PriceLevel {
+                    order_count: level.n,
+                    ..PriceLevel::new(price, size)
+                });
             }
         }
 
         Ok(OrderBookSnapshot {
             last_update_id: chrono::Utc::now().timestamp_millis() as u64,
+            event_time: 0,
+            recv_time: chrono::Utc::now().timestamp_millis() as u64,
             bids,
             asks,
         })
     }
 
-    fn get_precision(&self, _symbol: &str) -> (usize, usize) {
-        // Hyperliquid typically uses higher precision
-        // This could be made dynamic by fetching from API
-        (4, 4)
+    async fn get_precision(&self, symbol: &str) -> (usize, usize) {
+        let coin = symbol.to_uppercase();
+        if !PRECISION_CACHE.lock().unwrap().contains_key(&self.testnet) {
+            match self.fetch_precisions().await {
+                Ok(precisions) => {
+                    PRECISION_CACHE.lock().unwrap().insert(self.testnet, precisions);
+                }
+                Err(e) => tracing::warn!("Hyperliquid meta request error: {e}"),
+            }
+        }
+        PRECISION_CACHE
+            .lock()
+            .unwrap()
+            .get(&self.testnet)
+            .and_then(|precisions| precisions.get(&coin))
+            .copied()
+            .unwrap_or((4, 4))
+    }
+
+    fn snapshot_in_stream(&self) -> bool {
+        true
+    }
+
+    // l2Book states carry the top 20 levels per side
+    fn depth_limits(&self) -> DepthLimits {
+        DepthLimits::fixed(20)
     }
 
     fn format_symbol(&self, symbol: &str) -> String {
@@ -0,0 +1,436 @@
+use super::error::check_status;
+use super::net::{self, WsMessage};
+use super::ratelimit::RateLimiter;
+use super::sync::{Chaining, DepthSync};
+use super::{
+    DepthUpdate, Exchange, ExchangeError, ExchangeMessage, InstrumentInfo, InstrumentStats, OrderBookSnapshot,
+    PriceLevel, WsEndpoint,
+};
+use futures_util::{SinkExt, StreamExt};
+use once_cell::sync::Lazy;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::mpsc::{self, Receiver};
+
+const REST_BASE: &str = "https://api-futures.kucoin.com/api/v1";
+// Consecutive failed resyncs before the stream is closed and the feed reconnects with backoff
+const MAX_RESYNCS: u32 = 3;
+// Used if the bullet response does not say
+const DEFAULT_PING_INTERVAL: Duration = Duration::from_secs(18);
+
+#[derive(Deserialize)]
+struct KucoinResponse<T> {
+    data: T,
+}
+
+#[derive(Deserialize)]
+struct KucoinBullet {
+    token: String,
+    #[serde(rename = "instanceServers")]
+    instance_servers: Vec<KucoinServer>,
+}
+
+#[derive(Deserialize)]
+struct KucoinServer {
+    endpoint: String,
+    // Milliseconds
+    #[serde(rename = "pingInterval")]
+    ping_interval: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct KucoinRequest {
+    id: String,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    topic: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response: Option<bool>,
+}
+
+impl KucoinRequest {
+    fn subscribe(topic: String) -> Self {
+        Self {
+            id: chrono::Utc::now().timestamp_millis().to_string(),
+            kind: "subscribe",
+            topic: Some(topic),
+            response: Some(true),
+        }
+    }
+
+    fn ping() -> Self {
+        Self {
+            id: chrono::Utc::now().timestamp_millis().to_string(),
+            kind: "ping",
+            topic: None,
+            response: None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct KucoinEnvelope {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    subject: String,
+    // A string describing the problem on errors
+    #[serde(default)]
+    data: serde_json::Value,
+}
+
+// One level change per message; sequences are contiguous
+#[derive(Deserialize)]
+struct KucoinLevelChange {
+    sequence: u64,
+    // "price,side,size" with side "buy" or "sell" and size in lots
+    change: String,
+    timestamp: u64,
+}
+
+#[derive(Deserialize)]
+struct KucoinSnapshot {
+    sequence: u64,
+    bids: Vec<PriceLevel>,
+    asks: Vec<PriceLevel>,
+    // Nanoseconds since the Unix epoch
+    ts: u64,
+}
+
+#[derive(Deserialize)]
+struct KucoinMarkPrice {
+    #[serde(rename = "markPrice")]
+    mark_price: Decimal,
+    #[serde(rename = "indexPrice")]
+    index_price: Decimal,
+}
+
+#[derive(Deserialize)]
+struct KucoinFundingRate {
+    #[serde(rename = "fundingRate")]
+    funding_rate: Decimal,
+}
+
+impl From<KucoinMarkPrice> for InstrumentStats {
+    fn from(mark: KucoinMarkPrice) -> Self {
+        InstrumentStats {
+            mark_price: Some(mark.mark_price),
+            index_price: Some(mark.index_price),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<KucoinFundingRate> for InstrumentStats {
+    fn from(funding: KucoinFundingRate) -> Self {
+        InstrumentStats {
+            funding_rate: Some(funding.funding_rate),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct KucoinContract {
+    symbol: String,
+    #[serde(rename = "tickSize")]
+    tick_size: f64,
+    // Base asset per lot, or USD per lot (negative) on inverse contracts
+    #[serde(default)]
+    multiplier: Option<Decimal>,
+    #[serde(rename = "isInverse", default)]
+    is_inverse: bool,
+}
+
+// Public futures endpoints share 2000 weight per 30 seconds
+static RATE_LIMIT: Lazy<RateLimiter> = Lazy::new(|| RateLimiter::new("KuCoin", 2000, Duration::from_secs(30)));
+
+// (price, qty) precision and lot size per contract, fetched once per session
+type Contracts = HashMap<String, ((usize, usize), InstrumentInfo)>;
+static CONTRACT_CACHE: Lazy<Mutex<Contracts>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+enum StreamEvent {
+    Ws(Option<Result<WsMessage, ExchangeError>>),
+    Snapshot(Result<OrderBookSnapshot, ExchangeError>),
+    Ping,
+}
+
+// KuCoin Futures (USDT- and coin-margined perpetuals, e.g. XBTUSDTM). The WebSocket endpoint
+// and a connection token come from a REST "bullet" call made before every connection; the
+// level 2 stream then sends one level change per message, sequenced onto a REST snapshot.
+#[derive(Default)]
+pub struct KucoinExchange {}
+
+impl KucoinExchange {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    // "price,side,size" into a one-level update
+    fn parse_change(change: &KucoinLevelChange, symbol: &str, recv_time: u64) -> Result<DepthUpdate, ExchangeError> {
+        let mut parts = change.change.split(',');
+        let (Some(price), Some(side), Some(size)) = (parts.next(), parts.next(), parts.next()) else {
+            return Err(ExchangeError::Parse(format!("malformed level change {:?}", change.change)));
+        };
+        let level = PriceLevel::new(
+            Decimal::from_str(price).map_err(|e| ExchangeError::Parse(e.to_string()))?,
+            Decimal::from_str(size).map_err(|e| ExchangeError::Parse(e.to_string()))?,
+        );
+        let (bids, asks) = match side {
+            "buy" => (vec![level], vec![]),
+            "sell" => (vec![], vec![level]),
+            _ => return Err(ExchangeError::Parse(format!("unknown side {side:?}"))),
+        };
+        Ok(DepthUpdate {
+            event_time: change.timestamp,
+            transaction_time: change.timestamp,
+            symbol: symbol.to_string(),
+            capital_u: change.sequence,
+            small_u: change.sequence,
+            pu: -1,
+            bids,
+            asks,
+            recv_time,
+        })
+    }
+
+    async fn fetch_snapshot(symbol: String) -> Result<OrderBookSnapshot, ExchangeError> {
+        let url = format!("{}/level2/snapshot?symbol={symbol}", net::rest_base("kucoin", REST_BASE));
+        let response = check_status(RATE_LIMIT.get(&url, 1).await?, &symbol)?;
+        let snapshot: KucoinResponse<KucoinSnapshot> = response.json().await?;
+        let snapshot = snapshot.data;
+        Ok(OrderBookSnapshot {
+            last_update_id: snapshot.sequence,
+            event_time: snapshot.ts / 1_000_000,
+            recv_time: chrono::Utc::now().timestamp_millis() as u64,
+            bids: snapshot.bids,
+            asks: snapshot.asks,
+        })
+    }
+
+    async fn fetch_contracts() -> Result<Contracts, ExchangeError> {
+        let url = format!("{}/contracts/active", net::rest_base("kucoin", REST_BASE));
+        let response: KucoinResponse<Vec<KucoinContract>> =
+            check_status(RATE_LIMIT.get(&url, 1).await?, "")?.json().await?;
+        Ok(response
+            .data
+            .into_iter()
+            .map(|c| {
+                // Sizes are whole lots
+                let price_decimals = if c.tick_size > 0.0 {
+                    (-c.tick_size.log10()).ceil().max(0.0) as usize
+                } else {
+                    2
+                };
+                let multiplier = c.multiplier.map_or(Decimal::ONE, |m| m.abs());
+                let instrument = match c.is_inverse {
+                    true => InstrumentInfo::inverse(multiplier),
+                    false => InstrumentInfo::linear(multiplier),
+                };
+                (c.symbol, ((price_decimals, 0), instrument))
+            })
+            .collect())
+    }
+
+    async fn contract(symbol: &str) -> Option<((usize, usize), InstrumentInfo)> {
+        if CONTRACT_CACHE.lock().unwrap().is_empty() {
+            match Self::fetch_contracts().await {
+                Ok(contracts) => *CONTRACT_CACHE.lock().unwrap() = contracts,
+                Err(e) => tracing::warn!("KuCoin contracts request error: {e}"),
+            }
+        }
+        CONTRACT_CACHE.lock().unwrap().get(&symbol.to_uppercase()).copied()
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Exchange for KucoinExchange {
+    // Emits a snapshot followed by an already sequenced update stream; a sequence break is
+    // reported as a Desync error and followed by a fresh snapshot
+    async fn connect(&self, symbol: &str) -> Result<Receiver<ExchangeMessage>, ExchangeError> {
+        let (tx, rx) = mpsc::channel(1000);
+        let endpoint = self.ws_endpoint(symbol).await?;
+        let ws_stream = net::connect_ws(&endpoint.url).await?;
+        let symbol = symbol.to_uppercase();
+        let ping_interval = endpoint.ping_interval.unwrap_or(DEFAULT_PING_INTERVAL);
+
+        super::spawn(async move {
+            let (mut write, mut read) = ws_stream.split();
+            for topic in [format!("/contractMarket/level2:{symbol}"), format!("/contract/instrument:{symbol}")] {
+                if let Ok(sub_msg) = serde_json::to_string(&KucoinRequest::subscribe(topic)) {
+                    let _ = write.send(WsMessage::Text(sub_msg.into())).await;
+                }
+            }
+
+            let mut sync = DepthSync::new(Chaining::Contiguous);
+            // Fetched while the stream is already being buffered
+            let mut pending = Some(super::spawn(Self::fetch_snapshot(symbol.clone())));
+            let mut failed_resyncs = 0;
+            let mut ping = tokio::time::interval(ping_interval);
+
+            loop {
+                let event = tokio::select! {
+                    message = read.next() => StreamEvent::Ws(message),
+                    // The feed dropped this connection (reconnect, symbol change or shutdown)
+                    _ = tx.closed() => break,
+                    snapshot = async { pending.as_mut().unwrap().await }, if pending.is_some() => {
+                        StreamEvent::Snapshot(snapshot.unwrap_or_else(|e| Err(ExchangeError::Network(e.to_string()))))
+                    }
+                    _ = ping.tick() => StreamEvent::Ping,
+                };
+
+                let synced = match event {
+                    StreamEvent::Snapshot(Ok(snapshot)) => {
+                        pending = None;
+                        sync.on_snapshot(snapshot.last_update_id).map(|ready| (Some(snapshot), ready))
+                    }
+                    StreamEvent::Snapshot(Err(e)) => {
+                        tracing::warn!("KuCoin snapshot request error: {e}");
+                        let _ = tx.send(ExchangeMessage::Error(e)).await;
+                        break;
+                    }
+                    StreamEvent::Ping => {
+                        if let Ok(ping_msg) = serde_json::to_string(&KucoinRequest::ping()) {
+                            let _ = write.send(WsMessage::Text(ping_msg.into())).await;
+                        }
+                        continue;
+                    }
+                    StreamEvent::Ws(Some(Ok(WsMessage::Text(text)))) => {
+                        let recv_time = chrono::Utc::now().timestamp_millis() as u64;
+                        let envelope = match serde_json::from_str::<KucoinEnvelope>(&text) {
+                            Ok(envelope) => envelope,
+                            Err(e) => {
+                                let _ = tx.send(ExchangeMessage::Error(e.into())).await;
+                                continue;
+                            }
+                        };
+                        match envelope.kind.as_str() {
+                            "message" => {}
+                            "error" => {
+                                // Subscribing to an unknown contract is the usual cause
+                                tracing::warn!("KuCoin error: {}", envelope.data);
+                                let error = ExchangeError::InvalidSymbol(symbol.clone());
+                                let _ = tx.send(ExchangeMessage::Error(error)).await;
+                                break;
+                            }
+                            _ => continue,
+                        }
+                        if envelope.subject != "level2" {
+                            let stats: Result<InstrumentStats, _> = match envelope.subject.as_str() {
+                                "mark.index.price" => {
+                                    serde_json::from_value::<KucoinMarkPrice>(envelope.data).map(Into::into)
+                                }
+                                "funding.rate" => {
+                                    serde_json::from_value::<KucoinFundingRate>(envelope.data).map(Into::into)
+                                }
+                                _ => continue,
+                            };
+                            let message = match stats {
+                                Ok(stats) => ExchangeMessage::InstrumentStats(stats),
+                                Err(e) => ExchangeMessage::Error(e.into()),
+                            };
+                            let _ = tx.send(message).await;
+                            continue;
+                        }
+                        let update = serde_json::from_value::<KucoinLevelChange>(envelope.data)
+                            .map_err(ExchangeError::from)
+                            .and_then(|change| Self::parse_change(&change, &symbol, recv_time));
+                        match update {
+                            Ok(update) => sync.on_event(update).map(|ready| (None, ready.into_iter().collect())),
+                            Err(e) => {
+                                let _ = tx.send(ExchangeMessage::Error(e)).await;
+                                continue;
+                            }
+                        }
+                    }
+                    StreamEvent::Ws(Some(Ok(WsMessage::Ping(payload)))) => {
+                        let _ = write.send(WsMessage::Pong(payload)).await;
+                        continue;
+                    }
+                    StreamEvent::Ws(Some(Ok(WsMessage::Close(_)))) | StreamEvent::Ws(None) => break,
+                    StreamEvent::Ws(Some(Err(e))) => {
+                        tracing::warn!("KuCoin WebSocket error: {e}");
+                        let _ = tx.send(ExchangeMessage::Error(e)).await;
+                        break;
+                    }
+                    StreamEvent::Ws(Some(Ok(_))) => continue,
+                };
+
+                match synced {
+                    Ok((snapshot, ready)) => {
+                        if let Some(snapshot) = snapshot {
+                            let _ = tx.send(ExchangeMessage::Snapshot(snapshot)).await;
+                        }
+                        if !ready.is_empty() {
+                            failed_resyncs = 0;
+                        }
+                        for update in ready {
+                            let _ = tx.send(ExchangeMessage::Update(update)).await;
+                        }
+                    }
+                    Err(e) => {
+                        // Start over from a fresh snapshot, or give up and let the feed reconnect
+                        tracing::warn!("KuCoin depth sync lost: {e}");
+                        let _ = tx.send(ExchangeMessage::Error(e)).await;
+                        failed_resyncs += 1;
+                        if failed_resyncs > MAX_RESYNCS {
+                            break;
+                        }
+                        sync = DepthSync::new(Chaining::Contiguous);
+                        pending = Some(super::spawn(Self::fetch_snapshot(symbol.clone())));
+                    }
+                }
+            }
+            let _ = write.close().await;
+        });
+
+        Ok(rx)
+    }
+
+    // Public connections need a token from the bullet endpoint, valid for one connection
+    async fn ws_endpoint(&self, _symbol: &str) -> Result<WsEndpoint, ExchangeError> {
+        let request = net::client().post(format!("{}/bullet-public", net::rest_base("kucoin", REST_BASE)));
+        let response = check_status(RATE_LIMIT.send(request, 10).await?, "")?;
+        let bullet: KucoinResponse<KucoinBullet> = response.json().await?;
+        let bullet = bullet.data;
+        let Some(server) = bullet.instance_servers.into_iter().next() else {
+            return Err(ExchangeError::Parse("bullet response lists no servers".to_string()));
+        };
+        let connect_id = chrono::Utc::now().timestamp_millis();
+        Ok(WsEndpoint {
+            url: format!("{}?token={}&connectId={connect_id}", server.endpoint, bullet.token),
+            ping_interval: server.ping_interval.map(Duration::from_millis),
+        })
+    }
+
+    async fn get_snapshot(&self, symbol: &str) -> Result<OrderBookSnapshot, ExchangeError> {
+        Self::fetch_snapshot(symbol.to_uppercase()).await
+    }
+
+    async fn get_precision(&self, symbol: &str) -> (usize, usize) {
+        Self::contract(symbol).await.map_or((4, 0), |(precision, _)| precision)
+    }
+
+    // Sizes are lots of the contract's multiplier
+    async fn get_instrument(&self, symbol: &str) -> InstrumentInfo {
+        Self::contract(symbol).await.map(|(_, instrument)| instrument).unwrap_or_default()
+    }
+
+    fn snapshot_in_stream(&self) -> bool {
+        true
+    }
+
+    fn format_symbol(&self, symbol: &str) -> String {
+        symbol.to_uppercase()
+    }
+
+    fn get_name(&self) -> &'static str {
+        "KuCoin"
+    }
+}
@@ -0,0 +1,321 @@
+use super::error::check_status;
+use super::net::{self, WsMessage};
+use super::ratelimit::RateLimiter;
+use super::sync::{Chaining, DepthSync};
+use super::{
+    DepthUpdate, Exchange, ExchangeError, ExchangeMessage, InstrumentInfo, OrderBookSnapshot, PriceLevel, WsEndpoint,
+};
+use futures_util::{SinkExt, StreamExt};
+use once_cell::sync::Lazy;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::mpsc::{self, Receiver};
+
+const REST_BASE: &str = "https://contract.mexc.com/api/v1/contract";
+const WS_URL: &str = "wss://contract.mexc.com/edge";
+// Consecutive failed resyncs before the stream is closed and the feed reconnects with backoff
+const MAX_RESYNCS: u32 = 3;
+// MEXC closes connections without a ping for a minute
+const PING_INTERVAL: Duration = Duration::from_secs(15);
+
+#[derive(Serialize)]
+struct MexcRequest<'a> {
+    method: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    param: Option<MexcParam<'a>>,
+}
+
+#[derive(Serialize)]
+struct MexcParam<'a> {
+    symbol: &'a str,
+}
+
+#[derive(Deserialize)]
+struct MexcEnvelope {
+    channel: String,
+    #[serde(default)]
+    data: serde_json::Value,
+    #[serde(default)]
+    ts: u64,
+}
+
+// Levels are [price, contracts, order count]
+#[derive(Deserialize)]
+struct MexcDepth {
+    #[serde(default)]
+    asks: Vec<Vec<Decimal>>,
+    #[serde(default)]
+    bids: Vec<Vec<Decimal>>,
+    version: u64,
+    // Only in REST snapshots
+    #[serde(default)]
+    timestamp: u64,
+}
+
+#[derive(Deserialize)]
+struct MexcResponse<T> {
+    data: T,
+}
+
+#[derive(Deserialize)]
+struct MexcContract {
+    symbol: String,
+    #[serde(rename = "priceScale")]
+    price_scale: usize,
+    #[serde(rename = "volScale")]
+    vol_scale: usize,
+    // Base asset per contract, e.g. 0.0001 on BTC_USDT
+    #[serde(rename = "contractSize", default)]
+    contract_size: Option<Decimal>,
+}
+
+// Keeps the order count alongside the quantity
+fn levels(levels: Vec<Vec<Decimal>>) -> Vec<PriceLevel> {
+    levels
+        .iter()
+        .filter_map(|level| {
+            Some(PriceLevel {
+                order_count: level.get(2).and_then(|count| count.to_u32()),
+                ..PriceLevel::new(*level.first()?, *level.get(1)?)
+            })
+        })
+        .collect()
+}
+
+// Contract market endpoints allow 20 requests per 2 seconds
+static RATE_LIMIT: Lazy<RateLimiter> = Lazy::new(|| RateLimiter::new("MEXC", 20, Duration::from_secs(2)));
+
+// (price, qty) precision and size per contract, fetched once per session
+type Contracts = HashMap<String, ((usize, usize), InstrumentInfo)>;
+static CONTRACT_CACHE: Lazy<Mutex<Contracts>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+enum StreamEvent {
+    Ws(Option<Result<WsMessage, ExchangeError>>),
+    Snapshot(Result<OrderBookSnapshot, ExchangeError>),
+    Ping,
+}
+
+// MEXC USDT perpetuals (`BTC_USDT`-style contracts, sizes in contracts). `push.depth` diffs
+// carry a version that increments by one per message and continues from the REST snapshot's.
+#[derive(Default)]
+pub struct MexcExchange {}
+
+impl MexcExchange {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    async fn fetch_snapshot(symbol: String) -> Result<OrderBookSnapshot, ExchangeError> {
+        let url = format!("{}/depth/{symbol}", net::rest_base("mexc", REST_BASE));
+        let response = check_status(RATE_LIMIT.get(&url, 1).await?, &symbol)?;
+        let depth: MexcResponse<MexcDepth> = response.json().await?;
+        let depth = depth.data;
+        Ok(OrderBookSnapshot {
+            last_update_id: depth.version,
+            event_time: depth.timestamp,
+            recv_time: chrono::Utc::now().timestamp_millis() as u64,
+            bids: levels(depth.bids),
+            asks: levels(depth.asks),
+        })
+    }
+
+    async fn fetch_contracts() -> Result<Contracts, ExchangeError> {
+        let url = format!("{}/detail", net::rest_base("mexc", REST_BASE));
+        let response: MexcResponse<Vec<MexcContract>> = check_status(RATE_LIMIT.get(&url, 1).await?, "")?.json().await?;
+        Ok(response
+            .data
+            .into_iter()
+            .map(|c| {
+                let size = c.contract_size.filter(|size| !size.is_zero()).unwrap_or(Decimal::ONE);
+                (c.symbol, ((c.price_scale, c.vol_scale), InstrumentInfo::linear(size)))
+            })
+            .collect())
+    }
+
+    async fn contract(symbol: &str) -> Option<((usize, usize), InstrumentInfo)> {
+        if CONTRACT_CACHE.lock().unwrap().is_empty() {
+            match Self::fetch_contracts().await {
+                Ok(contracts) => *CONTRACT_CACHE.lock().unwrap() = contracts,
+                Err(e) => tracing::warn!("MEXC contract detail request error: {e}"),
+            }
+        }
+        CONTRACT_CACHE.lock().unwrap().get(&symbol.to_uppercase()).copied()
+    }
+
+    fn send_text(request: &MexcRequest) -> Option<WsMessage> {
+        serde_json::to_string(request).ok().map(|text| WsMessage::Text(text.into()))
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Exchange for MexcExchange {
+    // Emits a snapshot followed by an already sequenced update stream; a sequence break is
+    // reported as a Desync error and followed by a fresh snapshot
+    async fn connect(&self, symbol: &str) -> Result<Receiver<ExchangeMessage>, ExchangeError> {
+        let (tx, rx) = mpsc::channel(1000);
+        let endpoint = self.ws_endpoint(symbol).await?;
+        let ws_stream = net::connect_ws(&endpoint.url).await?;
+        let symbol = symbol.to_uppercase();
+
+        super::spawn(async move {
+            let (mut write, mut read) = ws_stream.split();
+            let subscription = MexcRequest {
+                method: "sub.depth",
+                param: Some(MexcParam { symbol: &symbol }),
+            };
+            if let Some(sub_msg) = Self::send_text(&subscription) {
+                let _ = write.send(sub_msg).await;
+            }
+
+            let mut sync = DepthSync::new(Chaining::Contiguous);
+            // Fetched while the stream is already being buffered
+            let mut pending = Some(super::spawn(Self::fetch_snapshot(symbol.clone())));
+            let mut failed_resyncs = 0;
+            let mut ping = tokio::time::interval(PING_INTERVAL);
+
+            loop {
+                let event = tokio::select! {
+                    message = read.next() => StreamEvent::Ws(message),
+                    // The feed dropped this connection (reconnect, symbol change or shutdown)
+                    _ = tx.closed() => break,
+                    snapshot = async { pending.as_mut().unwrap().await }, if pending.is_some() => {
+                        StreamEvent::Snapshot(snapshot.unwrap_or_else(|e| Err(ExchangeError::Network(e.to_string()))))
+                    }
+                    _ = ping.tick() => StreamEvent::Ping,
+                };
+
+                let synced = match event {
+                    StreamEvent::Snapshot(Ok(snapshot)) => {
+                        pending = None;
+                        sync.on_snapshot(snapshot.last_update_id).map(|ready| (Some(snapshot), ready))
+                    }
+                    StreamEvent::Snapshot(Err(e)) => {
+                        tracing::warn!("MEXC snapshot request error: {e}");
+                        let _ = tx.send(ExchangeMessage::Error(e)).await;
+                        break;
+                    }
+                    StreamEvent::Ping => {
+                        if let Some(ping_msg) = Self::send_text(&MexcRequest { method: "ping", param: None }) {
+                            let _ = write.send(ping_msg).await;
+                        }
+                        continue;
+                    }
+                    StreamEvent::Ws(Some(Ok(WsMessage::Text(text)))) => {
+                        let recv_time = chrono::Utc::now().timestamp_millis() as u64;
+                        let envelope = match serde_json::from_str::<MexcEnvelope>(&text) {
+                            Ok(envelope) => envelope,
+                            Err(e) => {
+                                let _ = tx.send(ExchangeMessage::Error(e.into())).await;
+                                continue;
+                            }
+                        };
+                        match envelope.channel.as_str() {
+                            "push.depth" => {}
+                            "rs.error" => {
+                                // Subscribing to an unknown contract is the usual cause
+                                tracing::warn!("MEXC error: {}", envelope.data);
+                                let error = ExchangeError::InvalidSymbol(symbol.clone());
+                                let _ = tx.send(ExchangeMessage::Error(error)).await;
+                                break;
+                            }
+                            _ => continue,
+                        }
+                        match serde_json::from_value::<MexcDepth>(envelope.data) {
+                            Ok(depth) => {
+                                let update = DepthUpdate {
+                                    event_time: envelope.ts,
+                                    transaction_time: envelope.ts,
+                                    symbol: symbol.clone(),
+                                    capital_u: depth.version,
+                                    small_u: depth.version,
+                                    pu: -1,
+                                    bids: levels(depth.bids),
+                                    asks: levels(depth.asks),
+                                    recv_time,
+                                };
+                                sync.on_event(update).map(|ready| (None, ready.into_iter().collect()))
+                            }
+                            Err(e) => {
+                                let _ = tx.send(ExchangeMessage::Error(e.into())).await;
+                                continue;
+                            }
+                        }
+                    }
+                    StreamEvent::Ws(Some(Ok(WsMessage::Ping(payload)))) => {
+                        let _ = write.send(WsMessage::Pong(payload)).await;
+                        continue;
+                    }
+                    StreamEvent::Ws(Some(Ok(WsMessage::Close(_)))) | StreamEvent::Ws(None) => break,
+                    StreamEvent::Ws(Some(Err(e))) => {
+                        tracing::warn!("MEXC WebSocket error: {e}");
+                        let _ = tx.send(ExchangeMessage::Error(e)).await;
+                        break;
+                    }
+                    StreamEvent::Ws(Some(Ok(_))) => continue,
+                };
+
+                match synced {
+                    Ok((snapshot, ready)) => {
+                        if let Some(snapshot) = snapshot {
+                            let _ = tx.send(ExchangeMessage::Snapshot(snapshot)).await;
+                        }
+                        if !ready.is_empty() {
+                            failed_resyncs = 0;
+                        }
+                        for update in ready {
+                            let _ = tx.send(ExchangeMessage::Update(update)).await;
+                        }
+                    }
+                    Err(e) => {
+                        // Start over from a fresh snapshot, or give up and let the feed reconnect
+                        tracing::warn!("MEXC depth sync lost: {e}");
+                        let _ = tx.send(ExchangeMessage::Error(e)).await;
+                        failed_resyncs += 1;
+                        if failed_resyncs > MAX_RESYNCS {
+                            break;
+                        }
+                        sync = DepthSync::new(Chaining::Contiguous);
+                        pending = Some(super::spawn(Self::fetch_snapshot(symbol.clone())));
+                    }
+                }
+            }
+            let _ = write.close().await;
+        });
+
+        Ok(rx)
+    }
+
+    async fn ws_endpoint(&self, _symbol: &str) -> Result<WsEndpoint, ExchangeError> {
+        Ok(WsEndpoint::new(net::ws_url("mexc", WS_URL)))
+    }
+
+    async fn get_snapshot(&self, symbol: &str) -> Result<OrderBookSnapshot, ExchangeError> {
+        Self::fetch_snapshot(symbol.to_uppercase()).await
+    }
+
+    async fn get_precision(&self, symbol: &str) -> (usize, usize) {
+        Self::contract(symbol).await.map_or((4, 0), |(precision, _)| precision)
+    }
+
+    // Sizes are contracts of the contract size
+    async fn get_instrument(&self, symbol: &str) -> InstrumentInfo {
+        Self::contract(symbol).await.map(|(_, instrument)| instrument).unwrap_or_default()
+    }
+
+    fn snapshot_in_stream(&self) -> bool {
+        true
+    }
+
+    fn format_symbol(&self, symbol: &str) -> String {
+        symbol.to_uppercase()
+    }
+
+    fn get_name(&self) -> &'static str {
+        "MEXC"
+    }
+}
@@ -0,0 +1,259 @@
+use super::{
+    DepthUpdate, Exchange, ExchangeError, ExchangeMessage, InstrumentInfo, OrderBookSnapshot, PriceLevel, Trade,
+};
+use rust_decimal::Decimal;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc::{self, Receiver};
+
+// (price, qty) levels of a scripted message
+pub type Levels<'a> = &'a [(Decimal, Decimal)];
+
+// One step of a scripted connection
+#[derive(Clone, Debug)]
+pub enum MockStep {
+    Message(ExchangeMessage),
+    // Venue frame decoded as a depth update the way connectors decode theirs; one that does
+    // not decode is delivered as a Parse error
+    Raw(String),
+    Delay(Duration),
+    // Keeps the connection open without sending anything more, e.g. to trip the stale watchdog
+    Stall,
+}
+
+// Messages one connection delivers, in order; the stream closes after the last step unless
+// the script stalls
+#[derive(Clone, Debug, Default)]
+pub struct MockConnection {
+    steps: Vec<MockStep>,
+    // Served by `get_snapshot`; without one the script carries its own snapshots in the stream
+    rest_snapshot: Option<OrderBookSnapshot>,
+    refused: bool,
+}
+
+impl MockConnection {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // A connection attempt that fails, as when the venue is unreachable
+    pub fn refused() -> Self {
+        Self {
+            refused: true,
+            ..Self::default()
+        }
+    }
+
+    pub fn message(mut self, message: ExchangeMessage) -> Self {
+        self.steps.push(MockStep::Message(message));
+        self
+    }
+
+    pub fn snapshot(self, last_update_id: u64, bids: Levels, asks: Levels) -> Self {
+        self.message(ExchangeMessage::Snapshot(snapshot(last_update_id, bids, asks)))
+    }
+
+    // Update `first..=last` chained Binance futures style (`pu` = `first` - 1); leaving ids out
+    // between two updates scripts a sequence gap
+    pub fn update(self, first: u64, last: u64, bids: Levels, asks: Levels) -> Self {
+        self.message(ExchangeMessage::Update(DepthUpdate {
+            event_time: 0,
+            transaction_time: 0,
+            symbol: String::new(),
+            capital_u: first,
+            small_u: last,
+            pu: first as i64 - 1,
+            bids: levels(bids),
+            asks: levels(asks),
+            recv_time: 0,
+        }))
+    }
+
+    pub fn full_state(self, id: u64, bids: Levels, asks: Levels) -> Self {
+        self.message(ExchangeMessage::FullState(snapshot(id, bids, asks)))
+    }
+
+    pub fn trade(self, trade: Trade) -> Self {
+        self.message(ExchangeMessage::Trade(trade))
+    }
+
+    pub fn error(self, error: ExchangeError) -> Self {
+        self.message(ExchangeMessage::Error(error))
+    }
+
+    pub fn raw(mut self, frame: impl Into<String>) -> Self {
+        self.steps.push(MockStep::Raw(frame.into()));
+        self
+    }
+
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.steps.push(MockStep::Delay(delay));
+        self
+    }
+
+    pub fn stall(mut self) -> Self {
+        self.steps.push(MockStep::Stall);
+        self
+    }
+
+    pub fn rest_snapshot(mut self, last_update_id: u64, bids: Levels, asks: Levels) -> Self {
+        self.rest_snapshot = Some(snapshot(last_update_id, bids, asks));
+        self
+    }
+}
+
+fn levels(levels: Levels) -> Vec<PriceLevel> {
+    levels.iter().map(|&level| level.into()).collect()
+}
+
+fn snapshot(last_update_id: u64, bids: Levels, asks: Levels) -> OrderBookSnapshot {
+    OrderBookSnapshot {
+        last_update_id,
+        event_time: 0,
+        recv_time: 0,
+        bids: levels(bids),
+        asks: levels(asks),
+    }
+}
+
+// Connections handed out by `MockExchange`, one per connect; connects after the last one fail.
+// Clones share the queue, so the feed's reconnects work through it even though the feed
+// creates a new exchange for every attempt.
+#[derive(Clone, Debug)]
+pub struct MockScript {
+    connections: Arc<Mutex<VecDeque<MockConnection>>>,
+    precision: (usize, usize),
+    instrument: InstrumentInfo,
+}
+
+impl Default for MockScript {
+    fn default() -> Self {
+        Self {
+            connections: Arc::default(),
+            precision: (2, 2),
+            instrument: InstrumentInfo::BASE,
+        }
+    }
+}
+
+// Scripts are compared by identity; settings holding the same script are equal
+impl PartialEq for MockScript {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.connections, &other.connections)
+            && self.precision == other.precision
+            && self.instrument == other.instrument
+    }
+}
+
+impl MockScript {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn connection(self, connection: MockConnection) -> Self {
+        self.connections.lock().unwrap().push_back(connection);
+        self
+    }
+
+    pub fn precision(mut self, price_decimals: usize, qty_decimals: usize) -> Self {
+        self.precision = (price_decimals, qty_decimals);
+        self
+    }
+
+    pub fn instrument(mut self, instrument: InstrumentInfo) -> Self {
+        self.instrument = instrument;
+        self
+    }
+
+    // Connections not yet taken by a connect
+    pub fn remaining(&self) -> usize {
+        self.connections.lock().unwrap().len()
+    }
+}
+
+// Replays a `MockScript` instead of talking to a venue, so the feed, book and estimator can be
+// exercised offline with exact control over timing, sequence gaps and malformed frames
+pub struct MockExchange {
+    script: MockScript,
+    rest_snapshot: Mutex<Option<OrderBookSnapshot>>,
+}
+
+impl MockExchange {
+    pub fn new(script: MockScript) -> Self {
+        Self {
+            script,
+            rest_snapshot: Mutex::new(None),
+        }
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Exchange for MockExchange {
+    async fn connect(&self, symbol: &str) -> Result<Receiver<ExchangeMessage>, ExchangeError> {
+        let connection = self.script.connections.lock().unwrap().pop_front();
+        let connection = match connection {
+            Some(connection) if !connection.refused => connection,
+            Some(_) => return Err(ExchangeError::Network(format!("mock connection for {symbol} refused"))),
+            None => return Err(ExchangeError::Network("mock script has no connections left".to_string())),
+        };
+        *self.rest_snapshot.lock().unwrap() = connection.rest_snapshot;
+        let (tx, rx) = mpsc::channel(1000);
+
+        super::spawn(async move {
+            for step in connection.steps {
+                let message = match step {
+                    MockStep::Message(message) => message,
+                    MockStep::Raw(frame) => match serde_json::from_str::<DepthUpdate>(&frame) {
+                        Ok(update) => ExchangeMessage::Update(update),
+                        Err(e) => ExchangeMessage::Error(e.into()),
+                    },
+                    MockStep::Delay(delay) => {
+                        tokio::select! {
+                            _ = tokio::time::sleep(delay) => continue,
+                            _ = tx.closed() => return,
+                        }
+                    }
+                    MockStep::Stall => {
+                        tx.closed().await;
+                        return;
+                    }
+                };
+                if tx.send(message).await.is_err() {
+                    return;
+                }
+            }
+        });
+
+        Ok(rx)
+    }
+
+    async fn get_snapshot(&self, symbol: &str) -> Result<OrderBookSnapshot, ExchangeError> {
+        self.rest_snapshot
+            .lock()
+            .unwrap()
+            .clone()
+            .ok_or_else(|| ExchangeError::Network(format!("no mock REST snapshot for {symbol}")))
+    }
+
+    async fn get_precision(&self, _symbol: &str) -> (usize, usize) {
+        self.script.precision
+    }
+
+    async fn get_instrument(&self, _symbol: &str) -> InstrumentInfo {
+        self.script.instrument
+    }
+
+    fn snapshot_in_stream(&self) -> bool {
+        self.rest_snapshot.lock().unwrap().is_none()
+    }
+
+    fn format_symbol(&self, symbol: &str) -> String {
+        symbol.to_uppercase()
+    }
+
+    fn get_name(&self) -> &'static str {
+        "Mock"
+    }
+}
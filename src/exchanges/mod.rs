@@ -1,22 +1,228 @@
+#[cfg(feature = "binance")]
 pub mod binance;
+mod error;
+#[cfg(feature = "bitget")]
+pub mod bitget;
+#[cfg(feature = "bitstamp")]
+pub mod bitstamp;
+#[cfg(feature = "gateio")]
+pub mod gateio;
+#[cfg(feature = "hyperliquid")]
 pub mod hyperliquid;
+#[cfg(feature = "kucoin")]
+pub mod kucoin;
+#[cfg(feature = "mexc")]
+pub mod mexc;
+#[cfg(feature = "bitmex")]
+pub mod bitmex;
+#[cfg(feature = "bitfinex")]
+pub mod bitfinex;
+#[cfg(feature = "aevo")]
+pub mod aevo;
+#[cfg(feature = "drift")]
+pub mod drift;
+#[cfg(feature = "mock")]
+pub mod mock;
+#[cfg(any(
+    feature = "binance",
+    feature = "hyperliquid",
+    feature = "bitstamp",
+    feature = "gateio",
+    feature = "bitget",
+    feature = "kucoin",
+    feature = "mexc",
+    feature = "bitmex",
+    feature = "bitfinex",
+    feature = "aevo",
+    feature = "drift"
+))]
+mod ratelimit;
+pub mod net;
+mod transport;
+pub mod subscription;
+#[cfg(feature = "synthetic")]
+pub mod synthetic;
+#[cfg(any(feature = "binance", feature = "gateio", feature = "kucoin", feature = "mexc"))]
+mod sync;
 
+use once_cell::sync::Lazy;
 use rust_decimal::Decimal;
-use serde::Deserialize;
-use std::collections::{BTreeMap, VecDeque};
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::time::Duration;
 use tokio::sync::mpsc::Receiver;
+use tokio_util::task::TaskTracker;
+use tracing::Instrument;
+
+pub use error::ExchangeError;
+
+// Longest `shutdown` waits for sockets to close
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(2);
+
+// Every task the connectors spawn, so shutdown can wait for sockets to close
+static TASKS: Lazy<TaskTracker> = Lazy::new(TaskTracker::new);
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) use tokio::task::JoinHandle;
+// Awaiting a task in the browser yields its output like tokio's handle, or an error if it was dropped
+#[cfg(target_arch = "wasm32")]
+pub(crate) type JoinHandle<T> = tokio::sync::oneshot::Receiver<T>;
+
+// What `spawn_on` starts tasks on from outside an async context: a tokio runtime natively, the
+// browser's event loop on wasm32, which needs no handle
+#[cfg(not(target_arch = "wasm32"))]
+pub type SpawnHandle = tokio::runtime::Handle;
+#[cfg(target_arch = "wasm32")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SpawnHandle;
+
+// Spawns a connector task. Stream tasks end once their channel's receiver is dropped, closing
+// their socket on the way out; helpers end with the stream task that owns them. The task logs
+// under the caller's span, i.e. its feed's exchange and symbol.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn spawn<F>(task: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    TASKS.spawn(task.in_current_span())
+}
+
+// There's no tokio runtime in the browser; tasks run on its event loop and hand back their output
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn spawn<F>(task: F) -> JoinHandle<F::Output>
+where
+    F: Future + 'static,
+    F::Output: 'static,
+{
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let task = TASKS.track_future(task.in_current_span());
+    wasm_bindgen_futures::spawn_local(async move {
+        let _ = tx.send(task.await);
+    });
+    rx
+}
+
+// `spawn` from outside the runtime, e.g. a feed started by the GUI thread
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn spawn_on<F>(task: F, runtime: &SpawnHandle) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    TASKS.spawn_on(task.in_current_span(), runtime)
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn spawn_on<F>(task: F, _runtime: &SpawnHandle) -> JoinHandle<F::Output>
+where
+    F: Future + 'static,
+    F::Output: 'static,
+{
+    spawn(task)
+}
+
+// Waits for connector tasks to finish once their feeds were dropped, so sockets get a close
+// frame on exit. Tasks still running after SHUTDOWN_GRACE are left to the runtime's shutdown.
+pub async fn shutdown() {
+    TASKS.close();
+    let _ = tokio::time::timeout(SHUTDOWN_GRACE, TASKS.wait()).await;
+}
+
+#[cfg(not(any(
+    feature = "binance",
+    feature = "hyperliquid",
+    feature = "bitstamp",
+    feature = "gateio",
+    feature = "bitget",
+    feature = "kucoin",
+    feature = "mexc",
+    feature = "bitmex",
+    feature = "bitfinex",
+    feature = "aevo",
+    feature = "drift",
+    feature = "synthetic"
+)))]
+compile_error!("at least one exchange feature must be enabled");
 
 #[derive(Clone, Debug)]
 pub enum ExchangeMessage {
     Snapshot(OrderBookSnapshot),
     Update(DepthUpdate),
+    // Complete book state from venues that publish whole books rather than diffs; the book
+    // engine diffs consecutive states
+    FullState(OrderBookSnapshot),
+    Order(OrderEvent),
+    Trade(Trade),
+    InstrumentStats(InstrumentStats),
+    // Non-fatal stream error; the connector keeps running unless the channel closes
+    Error(ExchangeError),
+}
+
+// One aggregated price level; in updates a zero qty removes the level
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PriceLevel {
+    pub price: Decimal,
+    pub qty: Decimal,
+    // Orders resting at the level, from venues that report it
+    pub order_count: Option<u32>,
+}
+
+impl PriceLevel {
+    pub fn new(price: Decimal, qty: Decimal) -> Self {
+        Self {
+            price,
+            qty,
+            order_count: None,
+        }
+    }
+}
+
+impl From<(Decimal, Decimal)> for PriceLevel {
+    fn from((price, qty): (Decimal, Decimal)) -> Self {
+        Self::new(price, qty)
+    }
+}
+
+// Levels arrive as `[price, qty, ...]` arrays, as most venues send them; whatever follows the
+// quantity means something different per venue, so connectors that want it parse it themselves
+impl<'de> Deserialize<'de> for PriceLevel {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct LevelVisitor;
+
+        impl<'de> Visitor<'de> for LevelVisitor {
+            type Value = PriceLevel;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a [price, qty] array")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<PriceLevel, A::Error> {
+                let price = seq.next_element()?.ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                let qty = seq.next_element()?.ok_or_else(|| de::Error::invalid_length(1, &self))?;
+                while seq.next_element::<de::IgnoredAny>()?.is_some() {}
+                Ok(PriceLevel::new(price, qty))
+            }
+        }
+
+        deserializer.deserialize_seq(LevelVisitor)
+    }
 }
 
 #[derive(Deserialize, Clone, Debug)]
 pub struct OrderBookSnapshot {
     pub last_update_id: u64,
-    pub bids: Vec<Vec<Decimal>>,
-    pub asks: Vec<Vec<Decimal>>,
+    // Venue time of the state in milliseconds since the Unix epoch, 0 if the venue gives none
+    #[serde(default)]
+    pub event_time: u64,
+    // Local time the message arrived, in milliseconds since the Unix epoch (0 if unknown)
+    #[serde(default)]
+    pub recv_time: u64,
+    pub bids: Vec<PriceLevel>,
+    pub asks: Vec<PriceLevel>,
 }
 
 #[derive(Deserialize, Clone, Debug)]
@@ -26,31 +232,615 @@ pub struct DepthUpdate {
     pub symbol: String,
     pub capital_u: u64,
     pub small_u: u64,
+    // Previous update's `small_u`, or -1 for venues without sequence numbers
     pub pu: i64,
-    pub bids: Vec<Vec<Decimal>>,
-    pub asks: Vec<Vec<Decimal>>,
+    pub bids: Vec<PriceLevel>,
+    pub asks: Vec<PriceLevel>,
+    // Local time the message arrived, in milliseconds since the Unix epoch (0 if unknown)
+    #[serde(default)]
+    pub recv_time: u64,
+}
+
+impl DepthUpdate {
+    // Folds a later update into this one. Levels from `newer` replace those at the same price,
+    // and the result spans both sequence ranges: the first `capital_u` and `pu`, the last `small_u`.
+    pub fn merge(&mut self, newer: DepthUpdate) {
+        merge_levels(&mut self.bids, newer.bids);
+        merge_levels(&mut self.asks, newer.asks);
+        self.small_u = newer.small_u;
+        self.event_time = newer.event_time;
+        self.transaction_time = newer.transaction_time;
+        self.recv_time = newer.recv_time;
+    }
+}
+
+fn merge_levels(levels: &mut Vec<PriceLevel>, newer: Vec<PriceLevel>) {
+    let mut index: HashMap<Decimal, usize> = levels.iter().enumerate().map(|(i, level)| (level.price, i)).collect();
+    for level in newer {
+        let price = level.price;
+        match index.get(&price) {
+            Some(&i) => levels[i] = level,
+            None => {
+                index.insert(price, levels.len());
+                levels.push(level);
+            }
+        }
+    }
+}
+
+// Derivatives statistics for the streamed instrument. Venues fill what each message carries
+// and leave the rest None, so consumers merge consecutive messages field by field.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InstrumentStats {
+    pub mark_price: Option<Decimal>,
+    // Index (or oracle) price the mark is anchored to
+    pub index_price: Option<Decimal>,
+    // Rate for the current funding interval as a fraction (0.0001 = 0.01%)
+    pub funding_rate: Option<Decimal>,
+    // Milliseconds since the Unix epoch
+    pub next_funding_time: Option<u64>,
+    // In base units, or contracts on COIN-M
+    pub open_interest: Option<Decimal>,
+}
+
+impl InstrumentStats {
+    // Overwrites the fields `newer` carries
+    pub fn merge(&mut self, newer: &InstrumentStats) {
+        self.mark_price = newer.mark_price.or(self.mark_price);
+        self.index_price = newer.index_price.or(self.index_price);
+        self.funding_rate = newer.funding_rate.or(self.funding_rate);
+        self.next_funding_time = newer.next_funding_time.or(self.next_funding_time);
+        self.open_interest = newer.open_interest.or(self.open_interest);
+    }
+}
+
+// What one unit of a venue's book size is worth. Spot markets and most perpetuals quote sizes in
+// the base asset; contract venues quote them in contracts of a fixed size, either of the base
+// asset (linear) or of the quote currency (inverse: COIN-M, BitMEX XBTUSD).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InstrumentInfo {
+    // Base asset per contract, or quote currency per contract when inverse
+    pub multiplier: Decimal,
+    pub inverse: bool,
+}
+
+impl Default for InstrumentInfo {
+    fn default() -> Self {
+        Self::BASE
+    }
+}
+
+impl InstrumentInfo {
+    // Sizes already in the base asset
+    pub const BASE: InstrumentInfo = InstrumentInfo {
+        multiplier: Decimal::ONE,
+        inverse: false,
+    };
+
+    pub fn linear(multiplier: Decimal) -> Self {
+        Self {
+            multiplier,
+            inverse: false,
+        }
+    }
+
+    pub fn inverse(multiplier: Decimal) -> Self {
+        Self {
+            multiplier,
+            inverse: true,
+        }
+    }
+
+    // One unit of the venue's size at `price`, expressed in `unit`
+    fn per_unit(&self, unit: SizeUnit, price: Decimal) -> Decimal {
+        match (unit, self.inverse) {
+            (SizeUnit::Native, _) => Decimal::ONE,
+            (SizeUnit::Base, false) => self.multiplier,
+            (SizeUnit::Base, true) if price > Decimal::ZERO => self.multiplier / price,
+            (SizeUnit::Base, true) => Decimal::ZERO,
+            (SizeUnit::Notional, false) => self.multiplier * price,
+            (SizeUnit::Notional, true) => self.multiplier,
+        }
+    }
+
+    // `qty` in the venue's units at `price`, expressed in `unit`
+    pub fn size_in(&self, unit: SizeUnit, qty: Decimal, price: Decimal) -> Decimal {
+        qty * self.per_unit(unit, price)
+    }
+
+    // `qty` in `from` at `price`, expressed in `to`; zero where `from` values nothing, e.g. the
+    // base asset at a zero price
+    pub fn convert(&self, qty: Decimal, price: Decimal, from: SizeUnit, to: SizeUnit) -> Decimal {
+        let from = self.per_unit(from, price);
+        if from.is_zero() {
+            return Decimal::ZERO;
+        }
+        qty * self.per_unit(to, price) / from
+    }
+
+    // Decimals to show sizes in `unit` with, given the venue's quantity decimals
+    pub fn decimals(&self, unit: SizeUnit, qty_decimals: usize) -> usize {
+        match (unit, self.inverse) {
+            (SizeUnit::Native, _) => qty_decimals,
+            (SizeUnit::Base, false) => qty_decimals + self.multiplier.normalize().scale() as usize,
+            // Contracts worth a fixed quote amount come to fractions of a coin
+            (SizeUnit::Base, true) => 8,
+            (SizeUnit::Notional, _) => 2,
+        }
+    }
+}
+
+// Unit order sizes are shown and clustered in
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SizeUnit {
+    // As the venue quotes them: base asset, or contracts on contract venues
+    #[default]
+    Native,
+    // Base asset, contracts converted by their multiplier, so venues compare
+    Base,
+    // Quote currency value (quantity × price)
+    Notional,
+}
+
+impl SizeUnit {
+    pub const ALL: [SizeUnit; 3] = [SizeUnit::Native, SizeUnit::Base, SizeUnit::Notional];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SizeUnit::Native => "Native",
+            SizeUnit::Base => "Base",
+            SizeUnit::Notional => "Notional",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    Bid,
+    Ask,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OrderEventKind {
+    Created,
+    Changed,
+    Deleted,
 }
 
-#[async_trait::async_trait]
+// Order-level (true L3) event from venues that publish individual orders
+#[derive(Clone, Debug)]
+pub struct OrderEvent {
+    pub kind: OrderEventKind,
+    pub order_id: u64,
+    pub side: Side,
+    pub price: Decimal,
+    pub qty: Decimal,
+    pub timestamp: u64,
+}
+
+// Executed trade (aggregated per taker order on venues that aggregate)
+#[derive(Clone, Debug)]
+pub struct Trade {
+    pub trade_id: u64,
+    pub price: Decimal,
+    pub qty: Decimal,
+    // Side of the taker: Bid for a buy that lifted an ask
+    pub aggressor: Side,
+    // Milliseconds since the Unix epoch
+    pub timestamp: u64,
+}
+
+// Where a connector opens its WebSocket. Most venues have a fixed URL; some hand out a
+// per-connection endpoint and token over REST first (KuCoin).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WsEndpoint {
+    pub url: String,
+    // Keepalive interval handed out with the endpoint; connectors with a fixed interval keep
+    // their own
+    pub ping_interval: Option<Duration>,
+}
+
+impl WsEndpoint {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            ping_interval: None,
+        }
+    }
+}
+
+// reqwest's futures aren't Send in the browser, where everything runs on one thread
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
 pub trait Exchange: Send + Sync {
-    async fn connect(&self, symbol: &str) -> Result<Receiver<ExchangeMessage>, Box<dyn std::error::Error>>;
-    async fn get_snapshot(&self, symbol: &str) -> Result<OrderBookSnapshot, Box<dyn std::error::Error>>;
-    fn get_precision(&self, symbol: &str) -> (usize, usize);
+    async fn connect(&self, symbol: &str) -> Result<Receiver<ExchangeMessage>, ExchangeError>;
+    // WebSocket for `symbol`, bootstrapped over REST where the venue requires it. `connect`
+    // resolves it on every (re)connect since bootstrap tokens may be single-use.
+    async fn ws_endpoint(&self, _symbol: &str) -> Result<WsEndpoint, ExchangeError> {
+        Err(ExchangeError::Network(format!("{} has no WebSocket endpoint", self.get_name())))
+    }
+    async fn get_snapshot(&self, symbol: &str) -> Result<OrderBookSnapshot, ExchangeError>;
+    // (price decimals, quantity decimals); implementations cache venue metadata per session
+    async fn get_precision(&self, symbol: &str) -> (usize, usize);
+    // Contract size of `symbol` on venues that quote sizes in contracts
+    async fn get_instrument(&self, _symbol: &str) -> InstrumentInfo {
+        InstrumentInfo::BASE
+    }
+    // True if the connector synchronizes a snapshot into its own stream, so the feed must
+    // not fetch one separately
+    fn snapshot_in_stream(&self) -> bool {
+        false
+    }
+    // Book depths the venue serves; most send the whole book
+    fn depth_limits(&self) -> DepthLimits {
+        DepthLimits::FULL
+    }
     fn format_symbol(&self, symbol: &str) -> String;
     fn get_name(&self) -> &'static str;
 }
 
-#[derive(Clone, Copy, Debug)]
+// Capability descriptor for the depth of a venue's book
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DepthLimits {
+    // Depths the connector can request, ascending; empty when the venue's depth is fixed
+    pub steps: &'static [usize],
+    // Levels per side delivered, None for the whole book; where there are steps, the depth
+    // requested when none is configured
+    pub levels: Option<usize>,
+}
+
+impl DepthLimits {
+    pub const FULL: DepthLimits = DepthLimits {
+        steps: &[],
+        levels: None,
+    };
+
+    pub const fn fixed(levels: usize) -> Self {
+        Self {
+            steps: &[],
+            levels: Some(levels),
+        }
+    }
+
+    pub const fn steps(steps: &'static [usize], default: usize) -> Self {
+        Self {
+            steps,
+            levels: Some(default),
+        }
+    }
+
+    // Depth to request for `wanted` levels per side: the shallowest step that covers it, or the
+    // deepest one when none does
+    pub fn negotiate(&self, wanted: Option<usize>) -> Option<usize> {
+        match (wanted, self.steps.last()) {
+            (Some(wanted), Some(&deepest)) => {
+                Some(self.steps.iter().copied().find(|&step| step >= wanted).unwrap_or(deepest))
+            }
+            _ => self.levels,
+        }
+    }
+}
+
+// Per-venue depth overrides (`[depth.<venue>]`); unset values keep the defaults
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DepthSettings {
+    // Levels per side requested in snapshots and depth-limited streams, rounded to one the
+    // venue accepts
+    pub snapshot: Option<usize>,
+    // Levels per side the book estimates queues for; deeper levels are dropped
+    pub estimation: Option<usize>,
+    // Levels per side shown in the book table and charts, at most the `[display]` values
+    pub display: Option<usize>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum ExchangeType {
+    #[cfg(feature = "binance")]
     Binance,
+    #[cfg(feature = "hyperliquid")]
     Hyperliquid,
+    #[cfg(feature = "bitstamp")]
+    Bitstamp,
+    #[cfg(feature = "gateio")]
+    Gateio,
+    #[cfg(feature = "bitget")]
+    Bitget,
+    #[cfg(feature = "kucoin")]
+    Kucoin,
+    #[cfg(feature = "mexc")]
+    Mexc,
+    #[cfg(feature = "bitmex")]
+    Bitmex,
+    #[cfg(feature = "bitfinex")]
+    Bitfinex,
+    #[cfg(feature = "aevo")]
+    Aevo,
+    #[cfg(feature = "drift")]
+    Drift,
+    #[cfg(feature = "synthetic")]
+    Synthetic,
+    // Scripted replay for tests; not listed in ALL, so it cannot be picked by name
+    #[cfg(feature = "mock")]
+    Mock,
+}
+
+// Connector options that are chosen at runtime rather than per call
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExchangeSettings {
+    // A connection without any message for this long is considered stalled and reconnected;
+    // zero disables the watchdog
+    pub stale_timeout: Duration,
+    // Reconnect delay after the first failure, doubled per consecutive failure up to the max
+    pub backoff_base: Duration,
+    pub backoff_max: Duration,
+    #[cfg(feature = "binance")]
+    pub binance_market: binance::BinanceMarket,
+    // Connects to the venue's testnet instead of production, where the venue has one
+    pub testnet: bool,
+    pub depths: HashMap<ExchangeType, DepthSettings>,
+    // Pulls a REST snapshot this often to audit the book against; None disables audit mode
+    pub audit_interval: Option<Duration>,
+    // Quote notional the book's buy/sell VWAP metrics are taken over
+    pub vwap_notional: Decimal,
+    pub estimator: crate::orderbook::EstimatorSettings,
+    #[cfg(feature = "synthetic")]
+    pub synthetic: synthetic::SyntheticConfig,
+    #[cfg(feature = "mock")]
+    pub mock: mock::MockScript,
+}
+
+impl Default for ExchangeSettings {
+    fn default() -> Self {
+        Self {
+            stale_timeout: Duration::from_secs(15),
+            backoff_base: Duration::from_millis(500),
+            backoff_max: Duration::from_secs(30),
+            #[cfg(feature = "binance")]
+            binance_market: binance::BinanceMarket::default(),
+            testnet: false,
+            depths: HashMap::new(),
+            audit_interval: None,
+            vwap_notional: crate::orderbook::DEFAULT_VWAP_NOTIONAL,
+            estimator: crate::orderbook::EstimatorSettings::default(),
+            #[cfg(feature = "synthetic")]
+            synthetic: synthetic::SyntheticConfig::default(),
+            #[cfg(feature = "mock")]
+            mock: mock::MockScript::default(),
+        }
+    }
+}
+
+impl ExchangeSettings {
+    pub fn depth(&self, exchange: ExchangeType) -> DepthSettings {
+        self.depths.get(&exchange).copied().unwrap_or_default()
+    }
 }
 
 impl ExchangeType {
+    // Exchanges compiled into this build, in display order
+    pub const ALL: &'static [ExchangeType] = &[
+        #[cfg(feature = "binance")]
+        ExchangeType::Binance,
+        #[cfg(feature = "hyperliquid")]
+        ExchangeType::Hyperliquid,
+        #[cfg(feature = "bitstamp")]
+        ExchangeType::Bitstamp,
+        #[cfg(feature = "gateio")]
+        ExchangeType::Gateio,
+        #[cfg(feature = "bitget")]
+        ExchangeType::Bitget,
+        #[cfg(feature = "kucoin")]
+        ExchangeType::Kucoin,
+        #[cfg(feature = "mexc")]
+        ExchangeType::Mexc,
+        #[cfg(feature = "bitmex")]
+        ExchangeType::Bitmex,
+        #[cfg(feature = "bitfinex")]
+        ExchangeType::Bitfinex,
+        #[cfg(feature = "aevo")]
+        ExchangeType::Aevo,
+        #[cfg(feature = "drift")]
+        ExchangeType::Drift,
+        #[cfg(feature = "synthetic")]
+        ExchangeType::Synthetic,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            #[cfg(feature = "binance")]
+            ExchangeType::Binance => "Binance",
+            #[cfg(feature = "hyperliquid")]
+            ExchangeType::Hyperliquid => "Hyperliquid",
+            #[cfg(feature = "bitstamp")]
+            ExchangeType::Bitstamp => "Bitstamp",
+            #[cfg(feature = "gateio")]
+            ExchangeType::Gateio => "Gate.io",
+            #[cfg(feature = "bitget")]
+            ExchangeType::Bitget => "Bitget",
+            #[cfg(feature = "kucoin")]
+            ExchangeType::Kucoin => "KuCoin",
+            #[cfg(feature = "mexc")]
+            ExchangeType::Mexc => "MEXC",
+            #[cfg(feature = "bitmex")]
+            ExchangeType::Bitmex => "BitMEX",
+            #[cfg(feature = "bitfinex")]
+            ExchangeType::Bitfinex => "Bitfinex",
+            #[cfg(feature = "aevo")]
+            ExchangeType::Aevo => "Aevo",
+            #[cfg(feature = "drift")]
+            ExchangeType::Drift => "Drift",
+            #[cfg(feature = "synthetic")]
+            ExchangeType::Synthetic => "Synthetic",
+            #[cfg(feature = "mock")]
+            ExchangeType::Mock => "Mock",
+        }
+    }
+
+    // Case-insensitive, ignoring punctuation so "gateio" finds "Gate.io"
+    pub fn from_name(name: &str) -> Option<Self> {
+        let key = |s: &str| s.chars().filter(char::is_ascii_alphanumeric).collect::<String>().to_lowercase();
+        Self::ALL.iter().copied().find(|e| key(e.name()) == key(name))
+    }
+
+    // Keeps `symbol` if it plausibly belongs to this venue, otherwise returns a default
+    // symbol in the venue's format (e.g. `dogeusdt` means nothing on Hyperliquid)
+    pub fn adapt_symbol(&self, symbol: &str) -> String {
+        let lower = symbol.to_lowercase();
+        match self {
+            #[cfg(feature = "binance")]
+            ExchangeType::Binance if !lower.contains("usd") => "dogeusdt".to_string(),
+            #[cfg(feature = "hyperliquid")]
+            ExchangeType::Hyperliquid if lower.contains("usdt") => "SOL".to_string(),
+            #[cfg(feature = "bitstamp")]
+            ExchangeType::Bitstamp if !lower.contains("usd") => "btcusd".to_string(),
+            #[cfg(feature = "gateio")]
+            ExchangeType::Gateio if !lower.ends_with("_usdt") => "DOGE_USDT".to_string(),
+            #[cfg(feature = "bitget")]
+            ExchangeType::Bitget if !lower.ends_with("usdt") || lower.contains('_') => "DOGEUSDT".to_string(),
+            #[cfg(feature = "kucoin")]
+            ExchangeType::Kucoin if !lower.ends_with("usdtm") && !lower.ends_with("usdm") => "DOGEUSDTM".to_string(),
+            #[cfg(feature = "mexc")]
+            ExchangeType::Mexc if !lower.ends_with("_usdt") => "DOGE_USDT".to_string(),
+            #[cfg(feature = "bitmex")]
+            ExchangeType::Bitmex if !(lower.ends_with("usd") || lower.ends_with("usdt")) || lower.contains('_') => {
+                "XBTUSD".to_string()
+            }
+            #[cfg(feature = "bitfinex")]
+            ExchangeType::Bitfinex if !lower.ends_with("usd") && !lower.ends_with("ust") => "tBTCUSD".to_string(),
+            #[cfg(feature = "aevo")]
+            ExchangeType::Aevo if !lower.ends_with("-perp") => "ETH-PERP".to_string(),
+            #[cfg(feature = "drift")]
+            ExchangeType::Drift if !lower.ends_with("-perp") => "SOL-PERP".to_string(),
+            #[cfg(feature = "synthetic")]
+            ExchangeType::Synthetic if lower != "synth" => "synth".to_string(),
+            _ => symbol.to_string(),
+        }
+    }
+
+    // Keeps `symbol` if it fits both the venue and the market chosen in `settings`
+    #[cfg_attr(not(feature = "binance"), allow(unused_variables))]
+    pub fn adapt_symbol_with(&self, symbol: &str, settings: &ExchangeSettings) -> String {
+        let symbol = self.adapt_symbol(symbol);
+        match self {
+            #[cfg(feature = "binance")]
+            ExchangeType::Binance => settings.binance_market.adapt_symbol(&symbol),
+            #[allow(unreachable_patterns)]
+            _ => symbol,
+        }
+    }
+
+    // Market shown in the heading, e.g. "Perpetual" or "Spot", marked when it is a testnet
+    #[cfg_attr(not(any(feature = "binance", feature = "hyperliquid")), allow(unused_variables))]
+    pub fn market_label(&self, settings: &ExchangeSettings) -> &'static str {
+        match self {
+            #[cfg(feature = "binance")]
+            ExchangeType::Binance if settings.testnet => settings.binance_market.testnet_label(),
+            #[cfg(feature = "binance")]
+            ExchangeType::Binance => settings.binance_market.label(),
+            #[cfg(feature = "hyperliquid")]
+            ExchangeType::Hyperliquid if settings.testnet => "Perpetual Testnet",
+            #[cfg(feature = "hyperliquid")]
+            ExchangeType::Hyperliquid => "Perpetual",
+            #[cfg(feature = "bitstamp")]
+            ExchangeType::Bitstamp => "Spot",
+            #[cfg(feature = "gateio")]
+            ExchangeType::Gateio => "Perpetual",
+            #[cfg(feature = "bitget")]
+            ExchangeType::Bitget => "Perpetual",
+            #[cfg(feature = "kucoin")]
+            ExchangeType::Kucoin => "Perpetual",
+            #[cfg(feature = "mexc")]
+            ExchangeType::Mexc => "Perpetual",
+            #[cfg(feature = "bitmex")]
+            ExchangeType::Bitmex => "Perpetual",
+            #[cfg(feature = "bitfinex")]
+            ExchangeType::Bitfinex => "Spot",
+            #[cfg(feature = "aevo")]
+            ExchangeType::Aevo => "Perpetual",
+            #[cfg(feature = "drift")]
+            ExchangeType::Drift => "Perpetual",
+            #[cfg(feature = "synthetic")]
+            ExchangeType::Synthetic => "Simulated",
+            #[cfg(feature = "mock")]
+            ExchangeType::Mock => "Scripted",
+        }
+    }
+
+    // Whether `ExchangeSettings::testnet` has any effect
+    pub fn has_testnet(&self) -> bool {
+        match self {
+            #[cfg(feature = "binance")]
+            ExchangeType::Binance => true,
+            #[cfg(feature = "hyperliquid")]
+            ExchangeType::Hyperliquid => true,
+            #[allow(unreachable_patterns)]
+            _ => false,
+        }
+    }
+
+    // Whether the venue publishes individual orders alongside the aggregated book
+    pub fn has_order_feed(&self) -> bool {
+        match self {
+            #[cfg(feature = "bitstamp")]
+            ExchangeType::Bitstamp => true,
+            #[cfg(feature = "bitfinex")]
+            ExchangeType::Bitfinex => true,
+            #[cfg(feature = "drift")]
+            ExchangeType::Drift => true,
+            #[allow(unreachable_patterns)]
+            _ => false,
+        }
+    }
+
     pub fn create_exchange(&self) -> Box<dyn Exchange> {
+        self.create_exchange_with(&ExchangeSettings::default())
+    }
+
+    #[cfg_attr(
+        not(any(
+            feature = "binance",
+            feature = "gateio",
+            feature = "bitfinex",
+            feature = "drift",
+            feature = "synthetic",
+            feature = "mock"
+        )),
+        allow(unused_variables)
+    )]
+    pub fn create_exchange_with(&self, settings: &ExchangeSettings) -> Box<dyn Exchange> {
         match self {
-            ExchangeType::Binance => Box::new(binance::BinanceExchange::new()),
-            ExchangeType::Hyperliquid => Box::new(hyperliquid::HyperliquidExchange::new()),
+            #[cfg(feature = "binance")]
+            ExchangeType::Binance => Box::new(binance::BinanceExchange::new(
+                settings.binance_market,
+                settings.testnet,
+                settings.depth(*self).snapshot,
+            )),
+            #[cfg(feature = "hyperliquid")]
+            ExchangeType::Hyperliquid => Box::new(hyperliquid::HyperliquidExchange::new(settings.testnet)),
+            #[cfg(feature = "bitstamp")]
+            ExchangeType::Bitstamp => Box::new(bitstamp::BitstampExchange::new()),
+            #[cfg(feature = "gateio")]
+            ExchangeType::Gateio => Box::new(gateio::GateioExchange::new(settings.depth(*self).snapshot)),
+            #[cfg(feature = "bitget")]
+            ExchangeType::Bitget => Box::new(bitget::BitgetExchange::new()),
+            #[cfg(feature = "kucoin")]
+            ExchangeType::Kucoin => Box::new(kucoin::KucoinExchange::new()),
+            #[cfg(feature = "mexc")]
+            ExchangeType::Mexc => Box::new(mexc::MexcExchange::new()),
+            #[cfg(feature = "bitmex")]
+            ExchangeType::Bitmex => Box::new(bitmex::BitmexExchange::new()),
+            #[cfg(feature = "bitfinex")]
+            ExchangeType::Bitfinex => Box::new(bitfinex::BitfinexExchange::new(settings.depth(*self).snapshot)),
+            #[cfg(feature = "aevo")]
+            ExchangeType::Aevo => Box::new(aevo::AevoExchange::new()),
+            #[cfg(feature = "drift")]
+            ExchangeType::Drift => Box::new(drift::DriftExchange::new(settings.depth(*self).snapshot)),
+            #[cfg(feature = "synthetic")]
+            ExchangeType::Synthetic => Box::new(synthetic::SyntheticExchange::new(settings.synthetic.clone())),
+            #[cfg(feature = "mock")]
+            ExchangeType::Mock => Box::new(mock::MockExchange::new(settings.mock.clone())),
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,259 @@
+use super::ExchangeError;
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::RwLock;
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::net::TcpStream;
+#[cfg(not(target_arch = "wasm32"))]
+use tokio_tungstenite::{client_async_tls, connect_async, MaybeTlsStream, WebSocketStream};
+
+pub use super::transport::{Transport, WsMessage, WsStream};
+
+// Longest proxy response head read before giving up on a CONNECT
+#[cfg(not(target_arch = "wasm32"))]
+const MAX_PROXY_RESPONSE: usize = 8192;
+
+// Base URLs replacing a connector's built-in ones, e.g. a regional host or a relay
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EndpointOverride {
+    pub rest: Option<String>,
+    pub ws: Option<String>,
+}
+
+// Proxy and endpoint configuration shared by every connector
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NetworkSettings {
+    // http://, socks5:// or socks5h:// (names resolved by the proxy) URL, with optional
+    // user:password; REST and WebSocket traffic both go through it
+    pub proxy: Option<String>,
+    // Overrides by venue key: the exchange name in lowercase (`gateio`, `bitmex`, ...), or
+    // `binance_spot`, `binance_usdm` and `binance_coinm` for the Binance markets
+    pub endpoints: HashMap<String, EndpointOverride>,
+}
+
+struct Network {
+    settings: NetworkSettings,
+    // Tunnels WebSockets natively; the browser's WebSocket can't be proxied
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    proxy: Option<reqwest::Url>,
+    client: reqwest::Client,
+}
+
+static NETWORK: Lazy<RwLock<Network>> = Lazy::new(|| {
+    RwLock::new(Network {
+        settings: NetworkSettings::default(),
+        proxy: None,
+        client: reqwest::Client::new(),
+    })
+});
+
+// Applies `settings` to every connection opened from now on; call it at startup
+pub fn configure(settings: NetworkSettings) -> Result<(), ExchangeError> {
+    #[cfg_attr(target_arch = "wasm32", allow(unused_mut))]
+    let mut builder = reqwest::Client::builder();
+    let proxy = match &settings.proxy {
+        Some(url) => {
+            let parsed = reqwest::Url::parse(url)
+                .map_err(|e| ExchangeError::Network(format!("invalid proxy URL {url}: {e}")))?;
+            if !matches!(parsed.scheme(), "http" | "socks5" | "socks5h") || parsed.host_str().is_none() {
+                return Err(ExchangeError::Network(format!(
+                    "unsupported proxy {url}: expected http://, socks5:// or socks5h://host:port"
+                )));
+            }
+            // The browser routes requests itself, so a web build only validates the URL
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                builder = builder.proxy(reqwest::Proxy::all(url.as_str())?);
+            }
+            Some(parsed)
+        }
+        None => None,
+    };
+    let client = builder.build()?;
+    *NETWORK.write().unwrap() = Network {
+        settings,
+        proxy,
+        client,
+    };
+    Ok(())
+}
+
+// HTTP client for REST calls, routed through the proxy if one is configured. Clones share
+// one connection pool.
+pub fn client() -> reqwest::Client {
+    NETWORK.read().unwrap().client.clone()
+}
+
+// REST base URL of `venue`, `default` unless the configuration overrides it
+pub fn rest_base(venue: &str, default: &str) -> String {
+    endpoint(venue, default, |e| e.rest.as_deref())
+}
+
+// WebSocket URL of `venue`, `default` unless the configuration overrides it
+pub fn ws_url(venue: &str, default: &str) -> String {
+    endpoint(venue, default, |e| e.ws.as_deref())
+}
+
+fn endpoint(venue: &str, default: &str, pick: impl Fn(&EndpointOverride) -> Option<&str>) -> String {
+    let network = NETWORK.read().unwrap();
+    let url = network.settings.endpoints.get(venue).and_then(pick).unwrap_or(default);
+    url.trim_end_matches('/').to_string()
+}
+
+// Opens a WebSocket to `url` over the target's transport
+pub async fn connect_ws(url: &str) -> Result<WsStream, ExchangeError> {
+    WsStream::connect(url).await
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+// Opens a tokio-tungstenite WebSocket to `url`, tunnelled through the configured proxy if
+// there is one
+pub(super) async fn connect_tungstenite(url: &str) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, ExchangeError> {
+    let proxy = NETWORK.read().unwrap().proxy.clone();
+    let Some(proxy) = proxy else {
+        return Ok(connect_async(url).await?.0);
+    };
+    let target =
+        reqwest::Url::parse(url).map_err(|e| ExchangeError::Network(format!("invalid WebSocket URL {url}: {e}")))?;
+    let host = target.host_str().ok_or_else(|| ExchangeError::Network(format!("no host in {url}")))?;
+    let port = target.port_or_known_default().unwrap_or(443);
+    let stream = tunnel(&proxy, host, port).await?;
+    Ok(client_async_tls(url, stream).await?.0)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn tunnel(proxy: &reqwest::Url, host: &str, port: u16) -> Result<TcpStream, ExchangeError> {
+    let proxy_host = proxy.host_str().unwrap_or_default();
+    let proxy_port = proxy.port_or_known_default().unwrap_or(1080);
+    let mut stream = TcpStream::connect((proxy_host, proxy_port)).await?;
+    match proxy.scheme() {
+        "http" => http_connect(&mut stream, proxy, host, port).await?,
+        _ => socks5_connect(&mut stream, proxy, host, port).await?,
+    }
+    Ok(stream)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn http_connect(
+    stream: &mut TcpStream,
+    proxy: &reqwest::Url,
+    host: &str,
+    port: u16,
+) -> Result<(), ExchangeError> {
+    let mut request = format!("CONNECT {host}:{port} HTTP/1.1\r\nHost: {host}:{port}\r\n");
+    if !proxy.username().is_empty() {
+        let credentials = format!("{}:{}", proxy.username(), proxy.password().unwrap_or_default());
+        request += &format!("Proxy-Authorization: Basic {}\r\n", base64(credentials.as_bytes()));
+    }
+    request += "\r\n";
+    stream.write_all(request.as_bytes()).await?;
+
+    // Read exactly the response head; whatever follows belongs to the tunnelled connection
+    let mut head = Vec::new();
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() >= MAX_PROXY_RESPONSE {
+            return Err(ExchangeError::Network("proxy response too long".to_string()));
+        }
+        head.push(stream.read_u8().await?);
+    }
+    let head = String::from_utf8_lossy(&head);
+    let status_line = head.lines().next().unwrap_or_default();
+    if status_line.split_whitespace().nth(1) != Some("200") {
+        return Err(ExchangeError::Network(format!("proxy refused CONNECT: {status_line}")));
+    }
+    Ok(())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn socks5_connect(
+    stream: &mut TcpStream,
+    proxy: &reqwest::Url,
+    host: &str,
+    port: u16,
+) -> Result<(), ExchangeError> {
+    let refused = |step: &str| ExchangeError::Network(format!("SOCKS5 proxy refused {step}"));
+
+    // Greeting: offer username/password only when the URL carries credentials
+    let auth = !proxy.username().is_empty();
+    stream.write_all(if auth { &[5, 2, 0, 2] } else { &[5, 1, 0] }).await?;
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply).await?;
+    match reply[1] {
+        0 => {}
+        2 if auth => {
+            let (user, pass) = (proxy.username().as_bytes(), proxy.password().unwrap_or_default().as_bytes());
+            let mut request = vec![1, user.len() as u8];
+            request.extend_from_slice(user);
+            request.push(pass.len() as u8);
+            request.extend_from_slice(pass);
+            stream.write_all(&request).await?;
+            stream.read_exact(&mut reply).await?;
+            if reply[1] != 0 {
+                return Err(refused("the credentials"));
+            }
+        }
+        _ => return Err(refused("every authentication method")),
+    }
+
+    // socks5:// resolves the target locally, socks5h:// leaves it to the proxy
+    let mut request = vec![5, 1, 0];
+    if proxy.scheme() == "socks5" {
+        let addr = tokio::net::lookup_host((host, port))
+            .await?
+            .next()
+            .ok_or_else(|| ExchangeError::Network(format!("cannot resolve {host}")))?;
+        match addr.ip() {
+            std::net::IpAddr::V4(ip) => {
+                request.push(1);
+                request.extend_from_slice(&ip.octets());
+            }
+            std::net::IpAddr::V6(ip) => {
+                request.push(4);
+                request.extend_from_slice(&ip.octets());
+            }
+        }
+    } else {
+        request.push(3);
+        request.push(host.len() as u8);
+        request.extend_from_slice(host.as_bytes());
+    }
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request).await?;
+
+    let mut header = [0u8; 4];
+    stream.read_exact(&mut header).await?;
+    if header[1] != 0 {
+        return Err(refused(&format!("CONNECT to {host}:{port} (code {})", header[1])));
+    }
+    // Skip the bound address and port
+    let bound = match header[3] {
+        1 => 4,
+        4 => 16,
+        _ => stream.read_u8().await? as usize,
+    };
+    let mut skip = vec![0u8; bound + 2];
+    stream.read_exact(&mut skip).await?;
+    Ok(())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn base64(input: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(input.len().div_ceil(3) * 4);
+    for chunk in input.chunks(3) {
+        let byte = |i: usize| *chunk.get(i).unwrap_or(&0) as u32;
+        let n = byte(0) << 16 | byte(1) << 8 | byte(2);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
@@ -0,0 +1,134 @@
+use super::{net, ExchangeError};
+use std::sync::Mutex;
+use std::time::Duration;
+use web_time::Instant;
+
+struct Bucket {
+    tokens: f64,
+    refilled: Instant,
+    // Set from Retry-After when the venue answers 429/418; nothing is sent before it
+    blocked_until: Option<Instant>,
+}
+
+// Token bucket over a venue's REST request weight. Each request waits for its weight, so bursts
+// such as repeated refetches on a flaky connection are spread out instead of tripping the venue's
+// limit; a 429/418 blocks the venue for the Retry-After the response asks for.
+pub(crate) struct RateLimiter {
+    name: &'static str,
+    capacity: f64,
+    // Tokens regained per second
+    rate: f64,
+    // Response header reporting the weight the venue has counted so far in its window
+    used_weight_header: Option<&'static str>,
+    bucket: Mutex<Bucket>,
+}
+
+impl RateLimiter {
+    // `capacity` weight per `window`, e.g. Binance USD-M's 2400 per minute
+    pub(crate) fn new(name: &'static str, capacity: u32, window: Duration) -> Self {
+        Self {
+            name,
+            capacity: capacity as f64,
+            rate: capacity as f64 / window.as_secs_f64(),
+            used_weight_header: None,
+            bucket: Mutex::new(Bucket {
+                tokens: capacity as f64,
+                refilled: Instant::now(),
+                blocked_until: None,
+            }),
+        }
+    }
+
+    // Trusts the venue's own count over the local estimate when it reports one
+    #[cfg_attr(not(feature = "binance"), allow(dead_code))]
+    pub(crate) fn with_used_weight_header(mut self, header: &'static str) -> Self {
+        self.used_weight_header = Some(header);
+        self
+    }
+
+    #[cfg_attr(
+        not(any(
+            feature = "binance",
+            feature = "bitstamp",
+            feature = "gateio",
+            feature = "bitget",
+            feature = "kucoin",
+            feature = "mexc",
+            feature = "bitmex",
+            feature = "bitfinex",
+            feature = "aevo",
+            feature = "drift"
+        )),
+        allow(dead_code)
+    )]
+    pub(crate) async fn get(&self, url: &str, weight: u32) -> Result<reqwest::Response, ExchangeError> {
+        self.send(net::client().get(url), weight).await
+    }
+
+    // Sends once `weight` is available; requests other than plain GETs are built with
+    // `net::client`. Rate limit responses become RateLimited errors, other statuses are left to
+    // `check_status`.
+    pub(crate) async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+        weight: u32,
+    ) -> Result<reqwest::Response, ExchangeError> {
+        self.acquire(weight).await;
+        let response = request.send().await?;
+        self.account(&response);
+        let status = response.status();
+        if matches!(status.as_u16(), 429 | 418) {
+            // Without a Retry-After, wait for the bucket to refill completely
+            let retry_after = response
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|value| value.to_str().ok()?.parse().ok())
+                .map_or(Duration::from_secs_f64(self.capacity / self.rate), Duration::from_secs);
+            tracing::warn!("{} REST rate limit hit (HTTP {status}), pausing requests for {retry_after:?}", self.name);
+            let mut bucket = self.bucket.lock().unwrap();
+            bucket.blocked_until = Some(Instant::now() + retry_after);
+            bucket.tokens = 0.0;
+            return Err(ExchangeError::RateLimited(format!("HTTP {status}, retry after {retry_after:?}")));
+        }
+        Ok(response)
+    }
+
+    async fn acquire(&self, weight: u32) {
+        // A request heavier than the whole bucket only waits for a full one
+        let weight = (weight as f64).min(self.capacity);
+        let mut logged = false;
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().unwrap();
+                let now = Instant::now();
+                let elapsed = now.duration_since(bucket.refilled).as_secs_f64();
+                bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.capacity);
+                bucket.refilled = now;
+                match bucket.blocked_until {
+                    Some(until) if until > now => until - now,
+                    _ if bucket.tokens >= weight => {
+                        bucket.tokens -= weight;
+                        return;
+                    }
+                    _ => Duration::from_secs_f64((weight - bucket.tokens) / self.rate),
+                }
+            };
+            if !logged && wait >= Duration::from_secs(1) {
+                tracing::warn!("{} REST request weight exhausted, waiting {wait:?}", self.name);
+                logged = true;
+            }
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    fn account(&self, response: &reqwest::Response) {
+        let used = self
+            .used_weight_header
+            .and_then(|header| response.headers().get(header))
+            .and_then(|value| value.to_str().ok()?.parse::<f64>().ok());
+        if let Some(used) = used {
+            let mut bucket = self.bucket.lock().unwrap();
+            bucket.tokens = bucket.tokens.min((self.capacity - used).max(0.0));
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use super::net::WsMessage;
+use super::ExchangeError;
+use futures_util::{SinkExt, StreamExt};
+use once_cell::sync::Lazy;
+use serde_json::value::RawValue;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+use tokio::sync::mpsc::{self, Receiver, Sender, UnboundedReceiver, UnboundedSender};
+use tokio::time::Instant;
+
+// Frames a subscriber may fall behind before the connection waits for it
+const SUBSCRIBER_CAPACITY: usize = 1000;
+
+// Live connections by URL, shared by every connector streaming from the same host
+static CONNECTIONS: Lazy<Mutex<HashMap<String, Weak<Connection>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+// What a subscription streams for its symbol
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StreamKind {
+    Depth,
+    Trades,
+    // Mark/index price and funding on perpetuals
+    MarkPrice,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Subscription {
+    pub symbol: String,
+    pub kind: StreamKind,
+}
+
+impl Subscription {
+    pub fn new(symbol: impl Into<String>, kind: StreamKind) -> Self {
+        Self {
+            symbol: symbol.into(),
+            kind,
+        }
+    }
+}
+
+// Delivers a subscription's frames, or the error that ended its connection
+type FrameSender = Sender<Result<StreamFrame, ExchangeError>>;
+
+// Payload of one data frame routed to a subscription, left unparsed for the connector to read
+// into its own types
+#[derive(Clone, Debug)]
+pub struct StreamFrame {
+    pub data: Box<RawValue>,
+    // Local time the frame arrived, in milliseconds since the Unix epoch
+    pub recv_time: u64,
+}
+
+// How a venue multiplexes streams over one connection
+pub trait StreamProtocol: Send + Sync + 'static {
+    // Venue name of the stream carrying `subscription`, e.g. `dogeusdt@aggTrade`
+    fn stream_name(&self, subscription: &Subscription) -> String;
+    // Request (un)subscribing `streams`; `id` increases per request on the connection
+    fn request(&self, subscribe: bool, streams: &[String], id: u64) -> WsMessage;
+    // Stream name and payload of a data frame, None for replies and other control frames
+    fn route<'a>(&self, text: &'a str) -> Option<(&'a str, &'a RawValue)>;
+    // Age at which connectors should move to a fresh connection, ahead of a venue's forced
+    // disconnect; None if connections may stay open indefinitely
+    fn max_age(&self) -> Option<Duration> {
+        None
+    }
+}
+
+enum Command {
+    Add {
+        key: u64,
+        stream: String,
+        frames: FrameSender,
+    },
+    Remove {
+        key: u64,
+    },
+}
+
+struct Connection {
+    protocol: Arc<dyn StreamProtocol>,
+    commands: UnboundedSender<Command>,
+    next_key: AtomicU64,
+    opened: Instant,
+}
+
+impl Connection {
+    fn expires_at(&self) -> Option<Instant> {
+        self.protocol.max_age().map(|age| self.opened + age)
+    }
+
+    // Still open and not yet due to be replaced
+    fn usable(&self) -> bool {
+        !self.commands.is_closed() && self.expires_at().is_none_or(|at| Instant::now() < at)
+    }
+}
+
+// One WebSocket connection carrying any number of subscriptions, for one or several symbols.
+// Subscriptions are added and removed at runtime; the venue is only asked to (un)subscribe a
+// stream when its first subscriber arrives or its last one leaves. The socket closes once the
+// manager and every handle are dropped, and a lost socket ends all its subscriptions with the
+// error, so each connector's reconnect logic still applies.
+#[derive(Clone)]
+pub struct SubscriptionManager {
+    connection: Arc<Connection>,
+}
+
+// Frames of one subscription; dropping the handle removes the subscription
+pub struct SubscriptionHandle {
+    key: u64,
+    frames: Receiver<Result<StreamFrame, ExchangeError>>,
+    connection: Arc<Connection>,
+}
+
+impl SubscriptionHandle {
+    // Next frame, or None once the connection is gone
+    pub async fn recv(&mut self) -> Option<Result<StreamFrame, ExchangeError>> {
+        self.frames.recv().await
+    }
+}
+
+impl Drop for SubscriptionHandle {
+    fn drop(&mut self) {
+        let _ = self.connection.commands.send(Command::Remove { key: self.key });
+    }
+}
+
+impl SubscriptionManager {
+    // Opens a dedicated connection
+    pub async fn connect(url: &str, protocol: impl StreamProtocol) -> Result<Self, ExchangeError> {
+        let ws_stream = super::net::connect_ws(url).await?;
+        let protocol: Arc<dyn StreamProtocol> = Arc::new(protocol);
+        let (commands, command_rx) = mpsc::unbounded_channel();
+        super::spawn(run(ws_stream, protocol.clone(), command_rx));
+        Ok(Self {
+            connection: Arc::new(Connection {
+                protocol,
+                commands,
+                next_key: AtomicU64::new(0),
+                opened: Instant::now(),
+            }),
+        })
+    }
+
+    // Joins the live connection to `url` if there is one, otherwise opens it. A connection past
+    // its protocol's max age is not joined; the next caller opens its replacement.
+    pub async fn shared(url: &str, protocol: impl StreamProtocol) -> Result<Self, ExchangeError> {
+        let live = CONNECTIONS.lock().unwrap().get(url).and_then(Weak::upgrade);
+        if let Some(connection) = live.filter(|c| c.usable()) {
+            return Ok(Self { connection });
+        }
+        let manager = Self::connect(url, protocol).await?;
+        let mut connections = CONNECTIONS.lock().unwrap();
+        connections.retain(|_, c| c.strong_count() > 0);
+        connections.insert(url.to_string(), Arc::downgrade(&manager.connection));
+        Ok(manager)
+    }
+
+    pub fn add(&self, subscription: &Subscription) -> SubscriptionHandle {
+        let key = self.connection.next_key.fetch_add(1, Ordering::Relaxed);
+        let (frames, rx) = mpsc::channel(SUBSCRIBER_CAPACITY);
+        let stream = self.connection.protocol.stream_name(subscription);
+        // If the connection is already gone the handle simply yields None
+        let _ = self.connection.commands.send(Command::Add { key, stream, frames });
+        SubscriptionHandle {
+            key,
+            frames: rx,
+            connection: self.connection.clone(),
+        }
+    }
+
+    pub fn remove(&self, handle: SubscriptionHandle) {
+        drop(handle);
+    }
+
+    // When subscribers should move to a fresh connection, per the protocol's max age
+    pub fn expires_at(&self) -> Option<Instant> {
+        self.connection.expires_at()
+    }
+
+    // The socket has closed; handles will not receive anything more
+    pub fn is_closed(&self) -> bool {
+        self.connection.commands.is_closed()
+    }
+}
+
+// Drives the socket: applies subscription changes and routes data frames to subscribers
+async fn run(
+    ws_stream: super::net::WsStream,
+    protocol: Arc<dyn StreamProtocol>,
+    mut commands: UnboundedReceiver<Command>,
+) {
+    let (mut write, mut read) = ws_stream.split();
+    // Subscribers per stream name
+    let mut routes: HashMap<String, Vec<(u64, FrameSender)>> = HashMap::new();
+    let mut request_id = 0;
+
+    loop {
+        tokio::select! {
+            command = commands.recv() => match command {
+                // The manager and every handle are gone
+                None => break,
+                Some(Command::Add { key, stream, frames }) => {
+                    let subscribers = routes.entry(stream.clone()).or_default();
+                    if subscribers.is_empty() {
+                        request_id += 1;
+                        let _ = write.send(protocol.request(true, &[stream], request_id)).await;
+                    }
+                    subscribers.push((key, frames));
+                }
+                Some(Command::Remove { key }) => {
+                    let mut unused = vec![];
+                    for (stream, subscribers) in routes.iter_mut() {
+                        subscribers.retain(|(k, _)| *k != key);
+                        if subscribers.is_empty() {
+                            unused.push(stream.clone());
+                        }
+                    }
+                    if !unused.is_empty() {
+                        for stream in &unused {
+                            routes.remove(stream);
+                        }
+                        request_id += 1;
+                        let _ = write.send(protocol.request(false, &unused, request_id)).await;
+                    }
+                }
+            },
+            message = read.next() => match message {
+                Some(Ok(WsMessage::Text(text))) => {
+                    let recv_time = chrono::Utc::now().timestamp_millis() as u64;
+                    let Some((stream, data)) = protocol.route(&text) else {
+                        continue;
+                    };
+                    for (_, frames) in routes.get(stream).into_iter().flatten() {
+                        let frame = StreamFrame { data: data.to_owned(), recv_time };
+                        let _ = frames.send(Ok(frame)).await;
+                    }
+                }
+                Some(Ok(WsMessage::Ping(payload))) => {
+                    let _ = write.send(WsMessage::Pong(payload)).await;
+                }
+                Some(Ok(WsMessage::Close(_))) | None => break,
+                Some(Err(e)) => {
+                    tracing::warn!("Multiplexed WebSocket error: {e}");
+                    for (_, frames) in routes.values().flatten() {
+                        let _ = frames.send(Err(e.clone())).await;
+                    }
+                    break;
+                }
+                Some(Ok(_)) => {}
+            }
+        }
+    }
+    let _ = write.close().await;
+}
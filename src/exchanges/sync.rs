@@ -0,0 +1,86 @@
+use super::{DepthUpdate, ExchangeError};
+use std::collections::VecDeque;
+
+// How a venue's diff events chain onto the previous one
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Chaining {
+    // `U` is the previous `u` + 1, and the snapshot already contains its own id (Binance spot,
+    // Gate.io, KuCoin, MEXC)
+    Contiguous,
+    // `pu` is the previous `u`, and the first event straddles the snapshot id (Binance futures)
+    #[cfg_attr(not(feature = "binance"), allow(dead_code))]
+    PreviousId,
+}
+
+// Diff-depth synchronization per the usual "manage a local order book" procedure: events are
+// buffered until a REST snapshot is loaded, events the snapshot already contains are dropped,
+// the first remaining event must straddle the snapshot's id and every later event must chain
+// onto the previous one.
+#[derive(Debug)]
+pub(crate) struct DepthSync {
+    chaining: Chaining,
+    // `u` of the last event forwarded (or the snapshot id); None while waiting for a snapshot
+    last_u: Option<u64>,
+    // No event has been bridged onto the snapshot yet
+    bridging: bool,
+    buffer: VecDeque<DepthUpdate>,
+}
+
+impl DepthSync {
+    pub(crate) fn new(chaining: Chaining) -> Self {
+        Self {
+            chaining,
+            last_u: None,
+            bridging: false,
+            buffer: VecDeque::new(),
+        }
+    }
+
+    // Next stream event: Some if it is ready to forward, Err if the sequence broke
+    pub(crate) fn on_event(&mut self, update: DepthUpdate) -> Result<Option<DepthUpdate>, ExchangeError> {
+        let Some(last_u) = self.last_u else {
+            self.buffer.push_back(update);
+            return Ok(None);
+        };
+        // First id not yet contained in the book
+        let next = match self.chaining {
+            Chaining::Contiguous => last_u + 1,
+            Chaining::PreviousId => last_u,
+        };
+        if update.small_u < next {
+            return Ok(None);
+        }
+        if self.bridging {
+            if update.capital_u > next {
+                return Err(ExchangeError::Desync(format!(
+                    "first event U {} is past snapshot {last_u}",
+                    update.capital_u
+                )));
+            }
+            self.bridging = false;
+        } else {
+            match self.chaining {
+                Chaining::Contiguous if update.capital_u != next => {
+                    return Err(ExchangeError::Desync(format!("U {} does not follow u {last_u}", update.capital_u)));
+                }
+                Chaining::PreviousId if update.pu != last_u as i64 => {
+                    return Err(ExchangeError::Desync(format!("pu {} does not follow u {last_u}", update.pu)));
+                }
+                _ => {}
+            }
+        }
+        self.last_u = Some(update.small_u);
+        Ok(Some(update))
+    }
+
+    // Snapshot loaded: replays the buffered events and returns those to forward after it
+    pub(crate) fn on_snapshot(&mut self, last_update_id: u64) -> Result<Vec<DepthUpdate>, ExchangeError> {
+        self.last_u = Some(last_update_id);
+        self.bridging = true;
+        let mut ready = vec![];
+        for update in std::mem::take(&mut self.buffer) {
+            ready.extend(self.on_event(update)?);
+        }
+        Ok(ready)
+    }
+}
@@ -0,0 +1,354 @@
+use super::{DepthLimits, DepthUpdate, Exchange, ExchangeError, ExchangeMessage, OrderBookSnapshot, PriceLevel};
+use crate::simulator::{OrderFlowSimulator, SimulatorConfig};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::{self, Receiver};
+use tokio::time::{interval, Duration, Instant};
+
+// Parameters of the generated market
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyntheticConfig {
+    // Depth updates per second
+    pub update_rate: u32,
+    // Price levels maintained on each side
+    pub depth: usize,
+    // Probability that an update adds a whale order
+    pub whale_probability: f64,
+    // Pull whale orders again after this many updates (spoofing); 0 keeps them resting
+    pub whale_lifetime: u64,
+    // Simulate individual orders (`simulator::OrderFlowSimulator`) instead of the random walk,
+    // so the estimate can be compared with a known order flow; the whale settings do not apply
+    pub order_flow: bool,
+    // Seed of the order flow; the same seed replays the same market
+    pub seed: u64,
+}
+
+impl Default for SyntheticConfig {
+    fn default() -> Self {
+        Self {
+            update_rate: 100,
+            depth: 200,
+            whale_probability: 0.005,
+            whale_lifetime: 0,
+            order_flow: false,
+            seed: 1,
+        }
+    }
+}
+
+const TICK_SIZE: Decimal = Decimal::from_parts(1, 0, 0, false, 2); // 0.01
+const BASE_QTY: f64 = 10.0;
+const WHALE_MULTIPLIER: f64 = 50.0;
+
+struct Whale {
+    is_bid: bool,
+    tick: i64,
+    qty: Decimal,
+    expires_at: u64,
+}
+
+// Generated book in integer ticks; `seq` follows Binance futures U/u/pu semantics
+struct SimState {
+    bids: BTreeMap<i64, Decimal>,
+    asks: BTreeMap<i64, Decimal>,
+    whales: Vec<Whale>,
+    seq: u64,
+    symbol: String,
+    rng: StdRng,
+}
+
+impl SimState {
+    fn new(symbol: &str, depth: usize) -> Self {
+        let mut state = Self {
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+            whales: vec![],
+            seq: 1,
+            symbol: symbol.to_string(),
+            rng: StdRng::from_os_rng(),
+        };
+        let mid = 10_000i64;
+        for i in 0..depth as i64 {
+            let bid_qty = state.random_qty();
+            let ask_qty = state.random_qty();
+            state.bids.insert(mid - 1 - i, bid_qty);
+            state.asks.insert(mid + 1 + i, ask_qty);
+        }
+        state
+    }
+
+    fn random_qty(&mut self) -> Decimal {
+        // Heavy-tailed order sizes: Pareto with shape 1.5
+        let u: f64 = self.rng.random_range(0.01..1.0);
+        let qty = BASE_QTY * u.powf(-1.0 / 1.5);
+        Decimal::from_f64_retain(qty).unwrap_or(Decimal::ONE).round_dp(3)
+    }
+
+    fn price(tick: i64) -> Decimal {
+        Decimal::from(tick) * TICK_SIZE
+    }
+
+    fn snapshot(&self) -> OrderBookSnapshot {
+        OrderBookSnapshot {
+            last_update_id: self.seq,
+            event_time: 0,
+            recv_time: 0,
+            bids: self.bids.iter().map(|(&t, &q)| PriceLevel::new(Self::price(t), q)).collect(),
+            asks: self.asks.iter().map(|(&t, &q)| PriceLevel::new(Self::price(t), q)).collect(),
+        }
+    }
+
+    fn step(&mut self, config: &SyntheticConfig) -> DepthUpdate {
+        let mut changed_bids: BTreeMap<i64, Decimal> = BTreeMap::new();
+        let mut changed_asks: BTreeMap<i64, Decimal> = BTreeMap::new();
+
+        let is_bid = self.rng.random_bool(0.5);
+        let (touch, sign) = if is_bid {
+            (self.bids.keys().next_back().copied().unwrap_or(9_999), -1)
+        } else {
+            (self.asks.keys().next().copied().unwrap_or(10_001), 1)
+        };
+
+        if self.rng.random_bool(0.02) {
+            // Price move: the touch level trades away and the other side steps in
+            let (side, other, changed, other_changed) = if is_bid {
+                (&mut self.bids, &mut self.asks, &mut changed_bids, &mut changed_asks)
+            } else {
+                (&mut self.asks, &mut self.bids, &mut changed_asks, &mut changed_bids)
+            };
+            side.remove(&touch);
+            changed.insert(touch, Decimal::ZERO);
+            let qty = BASE_QTY * self.rng.random_range(0.5..2.0);
+            let qty = Decimal::from_f64_retain(qty).unwrap_or(Decimal::ONE).round_dp(3);
+            other.insert(touch, qty);
+            other_changed.insert(touch, qty);
+        } else {
+            // Add or cancel near the touch, biased towards the inside
+            let u: f64 = self.rng.random();
+            let tick = touch + sign * (u * u * config.depth as f64) as i64;
+            let add = self.rng.random_bool(0.55);
+            let new_order = self.random_qty();
+            let side = if is_bid { &mut self.bids } else { &mut self.asks };
+            let level = side.entry(tick).or_insert(Decimal::ZERO);
+            if add {
+                *level += new_order;
+            } else {
+                let cancel = (*level * Decimal::from_f64_retain(self.rng.random_range(0.1..1.0)).unwrap_or(Decimal::ONE)).round_dp(3);
+                *level = (*level - cancel).max(Decimal::ZERO);
+            }
+            let qty = *level;
+            if qty == Decimal::ZERO {
+                side.remove(&tick);
+            }
+            if is_bid {
+                changed_bids.insert(tick, qty);
+            } else {
+                changed_asks.insert(tick, qty);
+            }
+        }
+
+        if config.whale_probability > 0.0 && self.rng.random_bool(config.whale_probability.min(1.0)) {
+            let whale_bid = self.rng.random_bool(0.5);
+            let offset = self.rng.random_range(0..(config.depth as i64 / 4).max(1));
+            let qty = Decimal::from_f64_retain(BASE_QTY * WHALE_MULTIPLIER * self.rng.random_range(1.0..4.0))
+                .unwrap_or(Decimal::ONE)
+                .round_dp(3);
+            let (side, changed, tick) = if whale_bid {
+                let best = self.bids.keys().next_back().copied().unwrap_or(9_999);
+                (&mut self.bids, &mut changed_bids, best - offset)
+            } else {
+                let best = self.asks.keys().next().copied().unwrap_or(10_001);
+                (&mut self.asks, &mut changed_asks, best + offset)
+            };
+            let level = side.entry(tick).or_insert(Decimal::ZERO);
+            *level += qty;
+            changed.insert(tick, *level);
+            if config.whale_lifetime > 0 {
+                self.whales.push(Whale {
+                    is_bid: whale_bid,
+                    tick,
+                    qty,
+                    expires_at: self.seq + config.whale_lifetime,
+                });
+            }
+        }
+
+        // Pull expired whales
+        let seq = self.seq;
+        let (expired, alive): (Vec<Whale>, Vec<Whale>) = self.whales.drain(..).partition(|w| w.expires_at <= seq);
+        self.whales = alive;
+        for whale in expired {
+            let (side, changed) = if whale.is_bid {
+                (&mut self.bids, &mut changed_bids)
+            } else {
+                (&mut self.asks, &mut changed_asks)
+            };
+            if let Some(level) = side.get_mut(&whale.tick) {
+                *level = (*level - whale.qty).max(Decimal::ZERO);
+                let qty = *level;
+                if qty == Decimal::ZERO {
+                    side.remove(&whale.tick);
+                }
+                changed.insert(whale.tick, qty);
+            }
+        }
+
+        // Keep the configured depth
+        while self.bids.len() > config.depth {
+            if let Some((tick, _)) = self.bids.pop_first() {
+                changed_bids.insert(tick, Decimal::ZERO);
+            }
+        }
+        while self.asks.len() > config.depth {
+            if let Some((tick, _)) = self.asks.pop_last() {
+                changed_asks.insert(tick, Decimal::ZERO);
+            }
+        }
+
+        let pu = self.seq;
+        self.seq += 1;
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        DepthUpdate {
+            event_time: now,
+            transaction_time: now,
+            symbol: self.symbol.clone(),
+            capital_u: pu,
+            small_u: self.seq,
+            pu: pu as i64,
+            bids: changed_bids.into_iter().map(|(t, q)| PriceLevel::new(Self::price(t), q)).collect(),
+            asks: changed_asks.into_iter().map(|(t, q)| PriceLevel::new(Self::price(t), q)).collect(),
+            recv_time: now,
+        }
+    }
+}
+
+enum Generator {
+    RandomWalk(Box<SimState>),
+    OrderFlow { sim: Box<OrderFlowSimulator>, symbol: String },
+}
+
+impl Generator {
+    fn new(symbol: &str, config: &SyntheticConfig) -> Self {
+        if !config.order_flow {
+            return Generator::RandomWalk(Box::new(SimState::new(symbol, config.depth)));
+        }
+        let sim = OrderFlowSimulator::new(SimulatorConfig {
+            seed: config.seed,
+            depth: config.depth,
+            ..SimulatorConfig::default()
+        });
+        Generator::OrderFlow {
+            sim: Box::new(sim),
+            symbol: symbol.to_string(),
+        }
+    }
+
+    fn snapshot(&self) -> OrderBookSnapshot {
+        match self {
+            Generator::RandomWalk(state) => state.snapshot(),
+            Generator::OrderFlow { sim, .. } => sim.snapshot(),
+        }
+    }
+
+    fn step(&mut self, config: &SyntheticConfig) -> DepthUpdate {
+        match self {
+            Generator::RandomWalk(state) => state.step(config),
+            // Emitted at the configured rate rather than in simulated time, stamped like a live feed
+            Generator::OrderFlow { sim, symbol } => {
+                let now = chrono::Utc::now().timestamp_millis() as u64;
+                DepthUpdate {
+                    symbol: symbol.clone(),
+                    event_time: now,
+                    transaction_time: now,
+                    recv_time: now,
+                    ..sim.step().update
+                }
+            }
+        }
+    }
+}
+
+// Generates a random-walk market, or a simulated order flow, at a configurable update rate, for
+// load testing and demos without network access
+pub struct SyntheticExchange {
+    config: SyntheticConfig,
+    state: Arc<Mutex<Option<Generator>>>,
+}
+
+impl SyntheticExchange {
+    pub fn new(config: SyntheticConfig) -> Self {
+        Self {
+            config,
+            state: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Exchange for SyntheticExchange {
+    async fn connect(&self, symbol: &str) -> Result<Receiver<ExchangeMessage>, ExchangeError> {
+        let (tx, rx) = mpsc::channel(1000);
+        let config = self.config.clone();
+        let state = self.state.clone();
+        *state.lock().unwrap() = Some(Generator::new(symbol, &config));
+
+        super::spawn(async move {
+            let start = Instant::now();
+            let mut emitted: u64 = 0;
+            let mut ticker = interval(Duration::from_millis(1));
+            loop {
+                // A zero rate never sends, so also stop once the feed drops the channel
+                tokio::select! {
+                    _ = ticker.tick() => {}
+                    _ = tx.closed() => break,
+                }
+                // Catch up to the configured rate; high rates emit several updates per tick
+                let due = (start.elapsed().as_secs_f64() * config.update_rate as f64) as u64;
+                let mut updates = Vec::new();
+                {
+                    let mut guard = state.lock().unwrap();
+                    let Some(sim) = guard.as_mut() else { break };
+                    while emitted < due {
+                        updates.push(sim.step(&config));
+                        emitted += 1;
+                    }
+                }
+                for update in updates {
+                    if tx.send(ExchangeMessage::Update(update)).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+
+        Ok(rx)
+    }
+
+    async fn get_snapshot(&self, _symbol: &str) -> Result<OrderBookSnapshot, ExchangeError> {
+        let guard = self.state.lock().unwrap();
+        match guard.as_ref() {
+            Some(sim) => Ok(sim.snapshot()),
+            None => Err(ExchangeError::Desync("synthetic market not started".to_string())),
+        }
+    }
+
+    async fn get_precision(&self, _symbol: &str) -> (usize, usize) {
+        (2, 3)
+    }
+
+    fn depth_limits(&self) -> DepthLimits {
+        DepthLimits::fixed(self.config.depth)
+    }
+
+    fn format_symbol(&self, symbol: &str) -> String {
+        symbol.to_uppercase()
+    }
+
+    fn get_name(&self) -> &'static str {
+        "Synthetic"
+    }
+}
@@ -0,0 +1,269 @@
+use super::ExchangeError;
+use futures_util::{Sink, Stream};
+use std::future::Future;
+
+// The WebSocket under the connectors: tokio-tungstenite natively, the browser's WebSocket on
+// wasm32. Connectors only see `WsMessage` frames through the `Transport` stream and sink, so
+// they build for both.
+#[cfg(not(target_arch = "wasm32"))]
+pub type WsStream = native::NativeWs;
+#[cfg(target_arch = "wasm32")]
+pub type WsStream = browser::BrowserWs;
+
+// Frame payloads, shared rather than copied where the transport allows it
+#[cfg(not(target_arch = "wasm32"))]
+pub use tungstenite::{Bytes as Payload, Utf8Bytes as Text};
+#[cfg(target_arch = "wasm32")]
+pub type Text = String;
+#[cfg(target_arch = "wasm32")]
+pub type Payload = Vec<u8>;
+
+// One WebSocket frame, whichever transport carried it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WsMessage {
+    Text(Text),
+    Binary(Payload),
+    // Browsers answer pings themselves, so the browser transport never yields these
+    Ping(Payload),
+    Pong(Payload),
+    // Close with the peer's reason, if it gave one
+    Close(Option<String>),
+}
+
+// A connected WebSocket: frames in as a stream, frames out as a sink
+pub trait Transport:
+    Stream<Item = Result<WsMessage, ExchangeError>> + Sink<WsMessage, Error = ExchangeError> + Unpin + Send + Sized + 'static
+{
+    fn connect(url: &str) -> impl Future<Output = Result<Self, ExchangeError>> + Send;
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod native {
+    use super::{ExchangeError, Transport, WsMessage};
+    use futures_util::{Sink, SinkExt, Stream, StreamExt};
+    use std::pin::Pin;
+    use std::task::{ready, Context, Poll};
+    use tokio::net::TcpStream;
+    use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+    use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+    use tokio_tungstenite::tungstenite::Message;
+    use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+    pub struct NativeWs(WebSocketStream<MaybeTlsStream<TcpStream>>);
+
+    impl Transport for NativeWs {
+        async fn connect(url: &str) -> Result<Self, ExchangeError> {
+            super::super::net::connect_tungstenite(url).await.map(NativeWs)
+        }
+    }
+
+    impl Stream for NativeWs {
+        type Item = Result<WsMessage, ExchangeError>;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            loop {
+                let message = match ready!(self.0.poll_next_unpin(cx)) {
+                    Some(Ok(message)) => message,
+                    Some(Err(e)) => return Poll::Ready(Some(Err(e.into()))),
+                    None => return Poll::Ready(None),
+                };
+                let message = match message {
+                    Message::Text(text) => WsMessage::Text(text),
+                    Message::Binary(data) => WsMessage::Binary(data),
+                    Message::Ping(payload) => WsMessage::Ping(payload),
+                    Message::Pong(payload) => WsMessage::Pong(payload),
+                    Message::Close(frame) => WsMessage::Close(frame.map(|frame| frame.reason.to_string())),
+                    // Raw frames only show up when writing, never when reading
+                    Message::Frame(_) => continue,
+                };
+                return Poll::Ready(Some(Ok(message)));
+            }
+        }
+    }
+
+    impl Sink<WsMessage> for NativeWs {
+        type Error = ExchangeError;
+
+        fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), ExchangeError>> {
+            self.0.poll_ready_unpin(cx).map_err(Into::into)
+        }
+
+        fn start_send(mut self: Pin<&mut Self>, message: WsMessage) -> Result<(), ExchangeError> {
+            let message = match message {
+                WsMessage::Text(text) => Message::Text(text),
+                WsMessage::Binary(data) => Message::Binary(data),
+                WsMessage::Ping(payload) => Message::Ping(payload),
+                WsMessage::Pong(payload) => Message::Pong(payload),
+                WsMessage::Close(reason) => Message::Close(reason.map(|reason| CloseFrame {
+                    code: CloseCode::Normal,
+                    reason: reason.into(),
+                })),
+            };
+            self.0.start_send_unpin(message).map_err(Into::into)
+        }
+
+        fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), ExchangeError>> {
+            self.0.poll_flush_unpin(cx).map_err(Into::into)
+        }
+
+        fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), ExchangeError>> {
+            self.0.poll_close_unpin(cx).map_err(Into::into)
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod browser {
+    use super::{ExchangeError, Transport, WsMessage};
+    use futures_util::{Sink, Stream};
+    use std::cell::Cell;
+    use std::pin::Pin;
+    use std::rc::Rc;
+    use std::task::{Context, Poll};
+    use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+    use tokio::sync::oneshot;
+    use wasm_bindgen::prelude::*;
+    use wasm_bindgen::JsCast;
+    use web_sys::{BinaryType, CloseEvent, Event, MessageEvent, WebSocket};
+
+    type Incoming = UnboundedSender<Result<WsMessage, ExchangeError>>;
+
+    // The browser's socket and its callbacks are not Send, so they live in a local task and
+    // the connector holds the channels to it
+    pub struct BrowserWs {
+        incoming: UnboundedReceiver<Result<WsMessage, ExchangeError>>,
+        outgoing: UnboundedSender<WsMessage>,
+    }
+
+    impl Transport for BrowserWs {
+        async fn connect(url: &str) -> Result<Self, ExchangeError> {
+            let (incoming_tx, incoming) = mpsc::unbounded_channel();
+            let (outgoing, outgoing_rx) = mpsc::unbounded_channel();
+            let (opened_tx, opened) = oneshot::channel();
+            wasm_bindgen_futures::spawn_local(run(url.to_string(), incoming_tx, outgoing_rx, opened_tx));
+            opened
+                .await
+                .map_err(|_| ExchangeError::Network(format!("WebSocket to {url} dropped while connecting")))??;
+            Ok(Self { incoming, outgoing })
+        }
+    }
+
+    // Opens the socket, reports the outcome on `opened`, then forwards frames both ways until
+    // the connector drops its end
+    async fn run(
+        url: String,
+        incoming: Incoming,
+        mut outgoing: UnboundedReceiver<WsMessage>,
+        opened: oneshot::Sender<Result<(), ExchangeError>>,
+    ) {
+        let socket = match WebSocket::new(&url) {
+            Ok(socket) => socket,
+            Err(e) => {
+                let _ = opened.send(Err(js_error(e)));
+                return;
+            }
+        };
+        socket.set_binary_type(BinaryType::Arraybuffer);
+        let opened = Rc::new(Cell::new(Some(opened)));
+
+        let on_open = {
+            let opened = opened.clone();
+            Closure::<dyn FnMut()>::new(move || {
+                if let Some(opened) = opened.take() {
+                    let _ = opened.send(Ok(()));
+                }
+            })
+        };
+        let on_message = {
+            let incoming = incoming.clone();
+            Closure::<dyn FnMut(MessageEvent)>::new(move |event: MessageEvent| {
+                let data = event.data();
+                let message = match data.as_string() {
+                    Some(text) => WsMessage::Text(text),
+                    None => WsMessage::Binary(js_sys::Uint8Array::new(&data).to_vec()),
+                };
+                let _ = incoming.send(Ok(message));
+            })
+        };
+        // The browser gives no detail on errors; a failed open is reported to `connect`, a
+        // later failure to the stream
+        let on_error = {
+            let (opened, incoming, url) = (opened.clone(), incoming.clone(), url.clone());
+            Closure::<dyn FnMut(Event)>::new(move |_: Event| {
+                let error = ExchangeError::Network(format!("WebSocket error on {url}"));
+                match opened.take() {
+                    Some(opened) => {
+                        let _ = opened.send(Err(error));
+                    }
+                    None => {
+                        let _ = incoming.send(Err(error));
+                    }
+                }
+            })
+        };
+        let on_close = {
+            let incoming = incoming.clone();
+            Closure::<dyn FnMut(CloseEvent)>::new(move |event: CloseEvent| {
+                let reason = event.reason();
+                let _ = incoming.send(Ok(WsMessage::Close((!reason.is_empty()).then_some(reason))));
+            })
+        };
+        socket.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+        socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        socket.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+        socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+
+        while let Some(message) = outgoing.recv().await {
+            let sent = match message {
+                WsMessage::Text(text) => socket.send_with_str(&text),
+                WsMessage::Binary(data) => socket.send_with_u8_array(&data),
+                WsMessage::Close(_) => break,
+                // The browser handles control frames
+                WsMessage::Ping(_) | WsMessage::Pong(_) => Ok(()),
+            };
+            if let Err(e) = sent {
+                let _ = incoming.send(Err(js_error(e)));
+            }
+        }
+        socket.set_onopen(None);
+        socket.set_onmessage(None);
+        socket.set_onerror(None);
+        socket.set_onclose(None);
+        let _ = socket.close();
+    }
+
+    fn js_error(e: JsValue) -> ExchangeError {
+        ExchangeError::Network(e.as_string().unwrap_or_else(|| format!("{e:?}")))
+    }
+
+    impl Stream for BrowserWs {
+        type Item = Result<WsMessage, ExchangeError>;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            self.incoming.poll_recv(cx)
+        }
+    }
+
+    impl Sink<WsMessage> for BrowserWs {
+        type Error = ExchangeError;
+
+        fn poll_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), ExchangeError>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn start_send(self: Pin<&mut Self>, message: WsMessage) -> Result<(), ExchangeError> {
+            self.outgoing
+                .send(message)
+                .map_err(|_| ExchangeError::Network("WebSocket closed".to_string()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), ExchangeError>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), ExchangeError>> {
+            let _ = self.outgoing.send(WsMessage::Close(None));
+            Poll::Ready(Ok(()))
+        }
+    }
+}
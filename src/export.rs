@@ -0,0 +1,387 @@
+use crate::exchanges::ExchangeType;
+use crate::orderbook::{LiquidityBand, OrderBook, OrderQueue, LIQUIDITY_BANDS_BPS};
+use rust_decimal::Decimal;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    #[cfg(feature = "parquet")]
+    Parquet,
+}
+
+impl ExportFormat {
+    // Formats compiled into this build
+    pub const ALL: &'static [ExportFormat] = &[
+        ExportFormat::Csv,
+        #[cfg(feature = "parquet")]
+        ExportFormat::Parquet,
+    ];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            #[cfg(feature = "parquet")]
+            ExportFormat::Parquet => "parquet",
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?;
+        Self::ALL.iter().copied().find(|f| f.extension().eq_ignore_ascii_case(ext))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ExportOptions {
+    pub path: PathBuf,
+    pub format: ExportFormat,
+    // Time between rows
+    pub interval: Duration,
+    // Price levels per side in each row
+    pub levels: usize,
+}
+
+impl ExportOptions {
+    // Timestamped file in the working directory, e.g. `binance_dogeusdt_20250101_120000.csv`
+    pub fn with_default_path(exchange: ExchangeType, symbol: &str, format: ExportFormat) -> Self {
+        let stamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+        let name = format!(
+            "{}_{}_{stamp}.{}",
+            exchange.name().to_lowercase(),
+            symbol.to_lowercase(),
+            format.extension()
+        );
+        Self {
+            path: PathBuf::from(name),
+            format,
+            interval: Duration::from_secs(1),
+            levels: 10,
+        }
+    }
+}
+
+// One sampled book state: top-of-book metrics plus the top levels with their estimated
+// order counts
+struct ExportRow {
+    timestamp: u64,
+    best_bid: Option<Decimal>,
+    best_ask: Option<Decimal>,
+    spread: Option<Decimal>,
+    mid: Option<Decimal>,
+    microprice: Option<Decimal>,
+    imbalance: Option<Decimal>,
+    liquidity: Option<[LiquidityBand; LIQUIDITY_BANDS_BPS.len()]>,
+    buy_vwap: Option<Decimal>,
+    sell_vwap: Option<Decimal>,
+    order_count: usize,
+    // (price, quantity, estimated orders), best first; padded with None up to `levels`
+    bids: Vec<Option<(Decimal, Decimal, usize)>>,
+    asks: Vec<Option<(Decimal, Decimal, usize)>>,
+}
+
+impl ExportRow {
+    fn from_book(book: &OrderBook, levels: usize, timestamp: u64) -> Self {
+        let side = |iter: &mut dyn Iterator<Item = (&Decimal, &OrderQueue)>| {
+            let mut out: Vec<_> = iter
+                .take(levels)
+                .map(|(&price, orders)| Some((price, orders.total(), orders.len())))
+                .collect();
+            out.resize(levels, None);
+            out
+        };
+        let (best_bid, best_ask) = (book.best_bid(), book.best_ask());
+        let metrics = book.metrics();
+        Self {
+            timestamp,
+            best_bid,
+            best_ask,
+            spread: best_bid.zip(best_ask).map(|(bid, ask)| ask - bid),
+            mid: metrics.map(|m| m.mid),
+            microprice: metrics.map(|m| m.microprice),
+            imbalance: metrics.map(|m| m.imbalance),
+            liquidity: metrics.map(|m| m.liquidity),
+            buy_vwap: metrics.and_then(|m| m.buy_vwap),
+            sell_vwap: metrics.and_then(|m| m.sell_vwap),
+            order_count: book.order_count(),
+            bids: side(&mut book.bids.iter().rev()),
+            asks: side(&mut book.asks.iter()),
+        }
+    }
+
+    // Values of the metric columns between "best_bid" and "order_count", in column order
+    fn metrics(&self) -> Vec<Option<Decimal>> {
+        let mut values = vec![self.best_bid, self.best_ask, self.spread, self.mid, self.microprice, self.imbalance];
+        for i in 0..LIQUIDITY_BANDS_BPS.len() {
+            let band = self.liquidity.map(|bands| bands[i]);
+            values.push(band.map(|b| b.bid));
+            values.push(band.map(|b| b.ask));
+        }
+        values.push(self.buy_vwap);
+        values.push(self.sell_vwap);
+        values
+    }
+}
+
+// Column names shared by both formats: metrics first (see `ExportRow::metrics`), then per level
+// `bid_price_1, bid_qty_1, bid_orders_1, ..., ask_price_1, ...`
+fn column_names(levels: usize) -> Vec<String> {
+    let mut names: Vec<String> = [
+        "timestamp", "exchange", "symbol", "best_bid", "best_ask", "spread", "mid", "microprice", "imbalance",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect();
+    for bps in LIQUIDITY_BANDS_BPS {
+        names.push(format!("bid_liquidity_{bps}bps"));
+        names.push(format!("ask_liquidity_{bps}bps"));
+    }
+    names.extend(["buy_vwap", "sell_vwap", "order_count"].map(String::from));
+    for side in ["bid", "ask"] {
+        for i in 1..=levels {
+            names.push(format!("{side}_price_{i}"));
+            names.push(format!("{side}_qty_{i}"));
+            names.push(format!("{side}_orders_{i}"));
+        }
+    }
+    names
+}
+
+enum Writer {
+    Csv(BufWriter<File>),
+    #[cfg(feature = "parquet")]
+    Parquet(Box<parquet_writer::ParquetWriter>),
+}
+
+// Samples a book at a fixed interval into a CSV or Parquet file. The file is complete once the
+// exporter is closed or dropped (Parquet writes its footer then; CSV rows are flushed as written).
+pub struct Exporter {
+    options: ExportOptions,
+    exchange: ExchangeType,
+    symbol: String,
+    writer: Option<Writer>,
+    // Time of the last row in milliseconds since the Unix epoch
+    last_row: Option<u64>,
+    rows: u64,
+}
+
+impl Exporter {
+    pub fn create(options: ExportOptions, exchange: ExchangeType, symbol: &str) -> io::Result<Self> {
+        let file = File::create(&options.path)?;
+        let writer = match options.format {
+            ExportFormat::Csv => {
+                let mut out = BufWriter::new(file);
+                writeln!(out, "{}", column_names(options.levels).join(","))?;
+                out.flush()?;
+                Writer::Csv(out)
+            }
+            #[cfg(feature = "parquet")]
+            ExportFormat::Parquet => Writer::Parquet(Box::new(parquet_writer::ParquetWriter::new(file, options.levels)?)),
+        };
+        tracing::info!("Exporting {} {} to {}", exchange.name(), symbol, options.path.display());
+        Ok(Self {
+            options,
+            exchange,
+            symbol: symbol.to_string(),
+            writer: Some(writer),
+            last_row: None,
+            rows: 0,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.options.path
+    }
+
+    pub fn rows(&self) -> u64 {
+        self.rows
+    }
+
+    // Writes a row if the interval has passed since the last one; books without both sides
+    // (not yet synced) are skipped
+    pub fn sample(&mut self, book: &OrderBook) -> io::Result<()> {
+        self.sample_at(book, chrono::Utc::now().timestamp_millis() as u64)
+    }
+
+    // Like `sample`, at `time` rather than now, for replays of historical data
+    pub fn sample_at(&mut self, book: &OrderBook, time: u64) -> io::Result<()> {
+        let interval = self.options.interval.as_millis() as u64;
+        if self.last_row.is_some_and(|last| time < last.saturating_add(interval)) {
+            return Ok(());
+        }
+        if book.best_bid().is_none() || book.best_ask().is_none() {
+            return Ok(());
+        }
+        self.last_row = Some(time);
+        let row = ExportRow::from_book(book, self.options.levels, time);
+        match &mut self.writer {
+            Some(Writer::Csv(out)) => {
+                write_csv_row(out, &row, self.exchange.name(), &self.symbol)?;
+                out.flush()?;
+            }
+            #[cfg(feature = "parquet")]
+            Some(Writer::Parquet(out)) => out.push(&row, self.exchange.name(), &self.symbol)?,
+            None => return Ok(()),
+        }
+        self.rows += 1;
+        Ok(())
+    }
+
+    pub fn close(&mut self) -> io::Result<()> {
+        match self.writer.take() {
+            Some(Writer::Csv(mut out)) => out.flush(),
+            #[cfg(feature = "parquet")]
+            Some(Writer::Parquet(out)) => out.close(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Exporter {
+    fn drop(&mut self) {
+        if let Err(e) = self.close() {
+            tracing::error!("Failed to finish export {}: {e}", self.options.path.display());
+        }
+    }
+}
+
+fn write_csv_row(out: &mut impl Write, row: &ExportRow, exchange: &str, symbol: &str) -> io::Result<()> {
+    let opt = |v: Option<Decimal>| v.map_or(String::new(), |v| v.to_string());
+    write!(out, "{},{},{}", row.timestamp, exchange, symbol)?;
+    for value in row.metrics() {
+        write!(out, ",{}", opt(value))?;
+    }
+    write!(out, ",{}", row.order_count)?;
+    for level in row.bids.iter().chain(&row.asks) {
+        match level {
+            Some((price, qty, orders)) => write!(out, ",{price},{qty},{orders}")?,
+            None => write!(out, ",,,")?,
+        }
+    }
+    writeln!(out)
+}
+
+#[cfg(feature = "parquet")]
+mod parquet_writer {
+    use super::{column_names, ExportRow, LIQUIDITY_BANDS_BPS};
+    use arrow_array::{ArrayRef, Float64Array, Int64Array, RecordBatch, StringArray, UInt64Array};
+    use arrow_schema::{DataType, Field, Schema, SchemaRef};
+    use parquet::arrow::ArrowWriter;
+    use rust_decimal::prelude::ToPrimitive;
+    use rust_decimal::Decimal;
+    use std::fs::File;
+    use std::io;
+    use std::sync::Arc;
+
+    // best_bid, best_ask, spread, mid, microprice, imbalance, bid/ask liquidity per band and the two VWAPs
+    const METRIC_COLUMNS: usize = 8 + 2 * LIQUIDITY_BANDS_BPS.len();
+
+    // Rows are buffered column-wise and written as one record batch per this many rows
+    const BATCH_ROWS: usize = 256;
+
+    // Prices and quantities are stored as f64 for pandas/polars; CSV keeps exact decimals
+    pub struct ParquetWriter {
+        writer: ArrowWriter<File>,
+        schema: SchemaRef,
+        levels: usize,
+        timestamps: Vec<i64>,
+        labels: Vec<(String, String)>,
+        // One column per float field, in schema order after the label columns
+        floats: Vec<Vec<Option<f64>>>,
+        order_counts: Vec<Vec<Option<u64>>>,
+    }
+
+    fn to_f64(v: Option<Decimal>) -> Option<f64> {
+        v.and_then(|v| v.to_f64())
+    }
+
+    fn parquet_err(e: impl std::error::Error + Send + Sync + 'static) -> io::Error {
+        io::Error::other(e)
+    }
+
+    impl ParquetWriter {
+        pub fn new(file: File, levels: usize) -> io::Result<Self> {
+            let fields: Vec<Field> = column_names(levels)
+                .into_iter()
+                .map(|name| {
+                    let data_type = match name.as_str() {
+                        "timestamp" => DataType::Int64,
+                        "exchange" | "symbol" => DataType::Utf8,
+                        "order_count" => DataType::UInt64,
+                        n if n.contains("_orders_") => DataType::UInt64,
+                        _ => DataType::Float64,
+                    };
+                    let nullable = !matches!(name.as_str(), "timestamp" | "exchange" | "symbol" | "order_count");
+                    Field::new(name, data_type, nullable)
+                })
+                .collect();
+            let schema = Arc::new(Schema::new(fields));
+            let writer = ArrowWriter::try_new(file, schema.clone(), None).map_err(parquet_err)?;
+            Ok(Self {
+                writer,
+                schema,
+                levels,
+                timestamps: Vec::new(),
+                labels: Vec::new(),
+                // Metrics + price/qty per level
+                floats: vec![Vec::new(); METRIC_COLUMNS + 4 * levels],
+                // total + one per level
+                order_counts: vec![Vec::new(); 1 + 2 * levels],
+            })
+        }
+
+        pub fn push(&mut self, row: &ExportRow, exchange: &str, symbol: &str) -> io::Result<()> {
+            self.timestamps.push(row.timestamp as i64);
+            self.labels.push((exchange.to_string(), symbol.to_string()));
+            for (column, value) in self.floats.iter_mut().zip(row.metrics()) {
+                column.push(to_f64(value));
+            }
+            self.order_counts[0].push(Some(row.order_count as u64));
+            for (i, level) in row.bids.iter().chain(&row.asks).enumerate() {
+                self.floats[METRIC_COLUMNS + 2 * i].push(to_f64(level.map(|l| l.0)));
+                self.floats[METRIC_COLUMNS + 1 + 2 * i].push(to_f64(level.map(|l| l.1)));
+                self.order_counts[1 + i].push(level.map(|l| l.2 as u64));
+            }
+            if self.timestamps.len() >= BATCH_ROWS {
+                self.flush()?;
+            }
+            Ok(())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            if self.timestamps.is_empty() {
+                return Ok(());
+            }
+            let mut columns: Vec<ArrayRef> = vec![
+                Arc::new(Int64Array::from(std::mem::take(&mut self.timestamps))),
+                Arc::new(StringArray::from_iter_values(self.labels.iter().map(|l| l.0.as_str()))),
+                Arc::new(StringArray::from_iter_values(self.labels.iter().map(|l| l.1.as_str()))),
+            ];
+            self.labels.clear();
+            let mut floats = self.floats.iter_mut().map(|c| Arc::new(Float64Array::from(std::mem::take(c))) as ArrayRef);
+            let mut counts = self
+                .order_counts
+                .iter_mut()
+                .map(|c| Arc::new(UInt64Array::from(std::mem::take(c))) as ArrayRef);
+            // Same order as `column_names`
+            columns.extend(floats.by_ref().take(METRIC_COLUMNS));
+            columns.extend(counts.next());
+            for _ in 0..2 * self.levels {
+                columns.extend(floats.next());
+                columns.extend(floats.next());
+                columns.extend(counts.next());
+            }
+            let batch = RecordBatch::try_new(self.schema.clone(), columns).map_err(parquet_err)?;
+            self.writer.write(&batch).map_err(parquet_err)
+        }
+
+        pub fn close(mut self) -> io::Result<()> {
+            self.flush()?;
+            self.writer.close().map_err(parquet_err)?;
+            Ok(())
+        }
+    }
+}
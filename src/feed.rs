@@ -0,0 +1,445 @@
+use crate::exchanges::{
+    self, DepthUpdate, ExchangeError, ExchangeMessage, ExchangeSettings, ExchangeType, InstrumentInfo, InstrumentStats,
+    OrderBookSnapshot, OrderEvent, Trade,
+};
+use crate::latency::{LatencyStats, LatencyTracker};
+use rand::Rng;
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
+use web_time::Instant;
+
+// Messages delivered from the feed task to a frontend (GUI, headless, ...)
+pub enum FeedMessage {
+    Snapshot(OrderBookSnapshot),
+    Update(DepthUpdate),
+    FullState(OrderBookSnapshot),
+    Order(OrderEvent),
+    Trade(Trade),
+    InstrumentStats(InstrumentStats),
+    // (price decimals, quantity decimals) for the current symbol
+    Precision(usize, usize),
+    // What one unit of the current symbol's quantities is worth, sent right after `Precision`
+    Instrument(InstrumentInfo),
+    Status(ConnectionStatus),
+    Error(ExchangeError),
+    // The stream was re-established; the book must drop its sync state and wait for the
+    // snapshot that follows
+    Resync,
+    // The frontend fell so far behind that `FeedQueue` dropped this many book messages; the
+    // book must resync and the frontend should ask the feed for a `Control::Refetch`
+    Overflow(usize),
+    // REST snapshot pulled in audit mode, to compare against the book rather than load
+    Audit(OrderBookSnapshot),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConnectionStatus {
+    Connecting,
+    Connected,
+    // No message within the stale timeout; a reconnect follows
+    Stale,
+    Disconnected,
+}
+
+impl ConnectionStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ConnectionStatus::Connecting => "Connecting",
+            ConnectionStatus::Connected => "Connected",
+            ConnectionStatus::Stale => "Stale",
+            ConnectionStatus::Disconnected => "Disconnected",
+        }
+    }
+}
+
+pub enum Control {
+    Refetch,
+    ChangeSymbol(String),
+    ChangeExchange(ExchangeType),
+    UpdateSettings(ExchangeSettings),
+}
+
+// Callback invoked for every feed message; frontends decide how to hand it over
+// to their own thread (channel send, repaint request, ...)
+pub type FeedSink = Arc<dyn Fn(FeedMessage) + Send + Sync>;
+
+// Bounded hand-off from a feed task to a frontend thread. While the consumer keeps up,
+// messages pass through unchanged. Once the queue is half full, consecutive depth updates are
+// merged into one (and a full book state replaces the previous one); if it fills up anyway,
+// all queued book messages are dropped, an `Overflow` is queued instead and further updates
+// are discarded until the next snapshot, full state or resync.
+pub struct FeedQueue {
+    state: Mutex<QueueState>,
+    ready: Condvar,
+    capacity: usize,
+}
+
+#[derive(Default)]
+struct QueueState {
+    messages: VecDeque<FeedMessage>,
+    discarding: bool,
+    coalesced: u64,
+    overflows: u64,
+}
+
+// Counters since the queue was created
+#[derive(Clone, Copy, Debug, Default)]
+pub struct QueueStats {
+    // Messages waiting when the stats were taken
+    pub pending: usize,
+    // Depth updates and book states merged into a queued one
+    pub coalesced: u64,
+    pub overflows: u64,
+}
+
+impl FeedQueue {
+    pub fn new(capacity: usize) -> Arc<Self> {
+        Arc::new(Self {
+            state: Mutex::new(QueueState::default()),
+            ready: Condvar::new(),
+            capacity: capacity.max(2),
+        })
+    }
+
+    pub fn push(&self, message: FeedMessage) {
+        let mut state = self.state.lock().unwrap();
+        match &message {
+            FeedMessage::Update(_) | FeedMessage::Order(_) | FeedMessage::Trade(_) if state.discarding => return,
+            FeedMessage::Snapshot(_) | FeedMessage::FullState(_) | FeedMessage::Resync => state.discarding = false,
+            _ => {}
+        }
+        let message = if state.messages.len() >= self.capacity / 2 {
+            match Self::coalesce(state.messages.back_mut(), message) {
+                Some(message) => message,
+                None => {
+                    state.coalesced += 1;
+                    return;
+                }
+            }
+        } else {
+            message
+        };
+        if state.messages.len() >= self.capacity {
+            let is_book = |m: &FeedMessage| {
+                matches!(
+                    m,
+                    FeedMessage::Snapshot(_)
+                        | FeedMessage::Update(_)
+                        | FeedMessage::FullState(_)
+                        | FeedMessage::Order(_)
+                        | FeedMessage::Trade(_)
+                )
+            };
+            let before = state.messages.len();
+            state.messages.retain(|m| !is_book(m));
+            let dropped = before - state.messages.len() + usize::from(is_book(&message));
+            // Status and metadata messages are rare, so only book messages count against the bound
+            if dropped > 0 {
+                state.messages.push_back(FeedMessage::Overflow(dropped));
+                state.discarding = true;
+                state.overflows += 1;
+            }
+            if !is_book(&message) {
+                state.messages.push_back(message);
+            }
+        } else {
+            state.messages.push_back(message);
+        }
+        drop(state);
+        self.ready.notify_one();
+    }
+
+    // Merges `message` into the last queued one if both are depth updates or both are full
+    // states; returns the message if it has to be queued on its own
+    fn coalesce(last: Option<&mut FeedMessage>, message: FeedMessage) -> Option<FeedMessage> {
+        match (last, message) {
+            (Some(FeedMessage::Update(queued)), FeedMessage::Update(update)) => {
+                queued.merge(update);
+                None
+            }
+            (Some(FeedMessage::FullState(queued)), FeedMessage::FullState(state)) => {
+                *queued = state;
+                None
+            }
+            (_, message) => Some(message),
+        }
+    }
+
+    // Everything queued so far, without waiting
+    pub fn drain(&self) -> VecDeque<FeedMessage> {
+        std::mem::take(&mut self.state.lock().unwrap().messages)
+    }
+
+    // Like `drain`, but waits up to `timeout` for the first message
+    pub fn drain_timeout(&self, timeout: Duration) -> VecDeque<FeedMessage> {
+        let state = self.state.lock().unwrap();
+        let (mut state, _) = self
+            .ready
+            .wait_timeout_while(state, timeout, |s| s.messages.is_empty())
+            .unwrap();
+        std::mem::take(&mut state.messages)
+    }
+
+    pub fn stats(&self) -> QueueStats {
+        let state = self.state.lock().unwrap();
+        QueueStats {
+            pending: state.messages.len(),
+            coalesced: state.coalesced,
+            overflows: state.overflows,
+        }
+    }
+}
+
+// Handle to a feed task; dropping it cancels the task wherever it is waiting (connecting,
+// fetching a snapshot, backing off), which drops the connector's channel and closes its socket
+pub struct FeedWorker {
+    control_tx: Sender<Control>,
+    latency: Arc<Mutex<LatencyTracker>>,
+    cancel: CancellationToken,
+}
+
+impl FeedWorker {
+    pub fn spawn(
+        runtime: &exchanges::SpawnHandle,
+        sink: FeedSink,
+        symbol: String,
+        exchange: ExchangeType,
+        settings: ExchangeSettings,
+    ) -> Self {
+        let (control_tx, control_rx) = mpsc::channel(1);
+        // Measured as messages leave the feed, before the frontend's queue can coalesce them
+        let latency = Arc::new(Mutex::new(LatencyTracker::new()));
+        let tracker = latency.clone();
+        let sink: FeedSink = Arc::new(move |msg| {
+            tracker.lock().unwrap().record(&msg);
+            sink(msg)
+        });
+        let cancel = CancellationToken::new();
+        let span = tracing::info_span!("feed", exchange = exchange.name(), symbol = %symbol);
+        let stream = fetch_and_stream_loop(sink, control_rx, symbol, exchange, settings).instrument(span);
+        exchanges::spawn_on(cancel.clone().run_until_cancelled_owned(stream), runtime);
+        Self {
+            control_tx,
+            latency,
+            cancel,
+        }
+    }
+
+    pub fn latency(&self) -> LatencyStats {
+        self.latency.lock().unwrap().stats()
+    }
+
+    // False if the previous control message has not been picked up yet
+    pub fn send(&self, control: Control) -> bool {
+        self.control_tx.try_send(control).is_ok()
+    }
+}
+
+impl Drop for FeedWorker {
+    fn drop(&mut self) {
+        self.cancel.cancel();
+    }
+}
+
+// Reconnect delay after a lost stream: doubles per consecutive failure up to the cap, with
+// jitter so many clients do not reconnect in lockstep
+fn backoff_delay(attempt: u32, settings: &ExchangeSettings) -> Duration {
+    let cap = settings
+        .backoff_base
+        .saturating_mul(1 << attempt.min(16))
+        .min(settings.backoff_max)
+        .as_millis() as u64;
+    Duration::from_millis(rand::rng().random_range(cap / 2..=cap))
+}
+
+// Why a connection ended
+enum Interrupt {
+    // Stream closed, connect failed or the snapshot could not be fetched
+    Lost,
+    // Boxed, as exchange settings make it far larger than the other variants
+    Control(Box<Control>),
+    // Control channel closed: the frontend is gone
+    Shutdown,
+}
+
+fn forward(sink: &FeedSink, message: ExchangeMessage) {
+    sink(match message {
+        ExchangeMessage::Snapshot(snapshot) => FeedMessage::Snapshot(snapshot),
+        ExchangeMessage::Update(update) => FeedMessage::Update(update),
+        ExchangeMessage::FullState(state) => FeedMessage::FullState(state),
+        ExchangeMessage::Order(event) => FeedMessage::Order(event),
+        ExchangeMessage::Trade(trade) => FeedMessage::Trade(trade),
+        ExchangeMessage::InstrumentStats(stats) => FeedMessage::InstrumentStats(stats),
+        ExchangeMessage::Error(e) => FeedMessage::Error(e),
+    })
+}
+
+// Connects to the selected exchange and streams snapshot/updates into `sink` until the
+// control channel closes. Every control message restarts the connection; a lost stream is
+// reconnected with backoff, resubscribed and resynced from a fresh snapshot.
+pub async fn fetch_and_stream_loop(
+    sink: FeedSink,
+    mut control_rx: Receiver<Control>,
+    mut symbol: String,
+    mut exchange_type: ExchangeType,
+    mut settings: ExchangeSettings,
+) {
+    let mut attempt = 0;
+    let mut first_connection = true;
+    loop {
+        let exchange = exchange_type.create_exchange_with(&settings);
+        let formatted_symbol = exchange.format_symbol(&symbol);
+        if let Some(wanted) = settings.depth(exchange_type).snapshot.filter(|_| first_connection) {
+            match exchange.depth_limits().negotiate(Some(wanted)) {
+                Some(depth) if depth == wanted => {}
+                Some(depth) => tracing::warn!("{} serves {depth} levels per side, not {wanted}", exchange.get_name()),
+                None => tracing::warn!("{} always sends the whole book; snapshot depth ignored", exchange.get_name()),
+            }
+        }
+
+        // Connect to exchange WebSocket
+        sink(FeedMessage::Status(ConnectionStatus::Connecting));
+        let interrupt = match exchange.connect(&formatted_symbol).await {
+            Ok(mut rx) => {
+                tracing::info!("Connected to {} WebSocket for {}", exchange.get_name(), formatted_symbol);
+                sink(FeedMessage::Status(ConnectionStatus::Connected));
+                if !first_connection {
+                    sink(FeedMessage::Resync);
+                }
+                first_connection = false;
+
+                // Fetch precision and contract size (cached per session) and, unless the connector
+                // synchronizes one into its stream, the initial snapshot
+                let (precision, instrument, snapshot) = tokio::join!(
+                    exchange.get_precision(&formatted_symbol),
+                    exchange.get_instrument(&formatted_symbol),
+                    async {
+                        if exchange.snapshot_in_stream() {
+                            Ok(None)
+                        } else {
+                            exchange.get_snapshot(&formatted_symbol).await.map(Some)
+                        }
+                    }
+                );
+                sink(FeedMessage::Precision(precision.0, precision.1));
+                sink(FeedMessage::Instrument(instrument));
+                match snapshot {
+                    Ok(snapshot) => {
+                        if let Some(snapshot) = snapshot {
+                            tracing::debug!("Snapshot fetched successfully from {}", exchange.get_name());
+                            sink(FeedMessage::Snapshot(snapshot));
+                            attempt = 0;
+                        }
+
+                        // Process WebSocket messages until the stream ends or stalls, or the
+                        // frontend asks for something else
+                        let timeout = settings.stale_timeout;
+                        let mut watchdog = tokio::time::interval((timeout / 4).max(Duration::from_millis(100)));
+                        let mut last_message = Instant::now();
+                        let mut audit_timer = settings.audit_interval.filter(|every| !every.is_zero()).map(|every| {
+                            tokio::time::interval_at(tokio::time::Instant::now() + every, every)
+                        });
+                        let mut audit = None;
+                        loop {
+                            tokio::select! {
+                                message = rx.recv() => match message {
+                                    Some(message) => {
+                                        // Side channels keep flowing when the book stalls
+                                        if !matches!(message, ExchangeMessage::InstrumentStats(_) | ExchangeMessage::Error(_)) {
+                                            last_message = Instant::now();
+                                        }
+                                        // A synced book resets the backoff
+                                        if matches!(message, ExchangeMessage::Snapshot(_) | ExchangeMessage::FullState(_)) {
+                                            attempt = 0;
+                                        }
+                                        forward(&sink, message);
+                                    }
+                                    None => break Interrupt::Lost,
+                                },
+                                _ = watchdog.tick(), if !timeout.is_zero() => {
+                                    if last_message.elapsed() >= timeout {
+                                        tracing::warn!("No data from {} for {timeout:?}, reconnecting", exchange.get_name());
+                                        sink(FeedMessage::Status(ConnectionStatus::Stale));
+                                        break Interrupt::Lost;
+                                    }
+                                }
+                                // One audit snapshot at a time; a slow venue delays the next
+                                _ = async { audit_timer.as_mut().unwrap().tick().await },
+                                    if audit_timer.is_some() && audit.is_none() =>
+                                {
+                                    audit = Some(exchange.get_snapshot(&formatted_symbol));
+                                }
+                                result = async { audit.as_mut().unwrap().await }, if audit.is_some() => {
+                                    audit = None;
+                                    match result {
+                                        Ok(snapshot) => sink(FeedMessage::Audit(snapshot)),
+                                        Err(e) => tracing::warn!("Audit snapshot error: {e}"),
+                                    }
+                                }
+                                ctrl = control_rx.recv() => match ctrl {
+                                    Some(ctrl) => break Interrupt::Control(Box::new(ctrl)),
+                                    None => break Interrupt::Shutdown,
+                                },
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        tracing::warn!("Snapshot request error: {e}");
+                        sink(FeedMessage::Error(e));
+                        Interrupt::Lost
+                    }
+                }
+            }
+            Err(e) => {
+                tracing::warn!("Failed to connect to {} WebSocket: {e}", exchange.get_name());
+                sink(FeedMessage::Error(e));
+                Interrupt::Lost
+            }
+        };
+
+        let ctrl = match interrupt {
+            Interrupt::Shutdown => break,
+            Interrupt::Control(ctrl) => *ctrl,
+            Interrupt::Lost => {
+                sink(FeedMessage::Status(ConnectionStatus::Disconnected));
+                let delay = backoff_delay(attempt, &settings);
+                attempt += 1;
+                tracing::info!("{} stream lost, reconnecting in {delay:?} (attempt {attempt})", exchange.get_name());
+                // A control message cuts the wait short
+                tokio::select! {
+                    _ = tokio::time::sleep(delay) => continue,
+                    ctrl = control_rx.recv() => match ctrl {
+                        Some(ctrl) => ctrl,
+                        None => break,
+                    },
+                }
+            }
+        };
+
+        attempt = 0;
+        match ctrl {
+            Control::Refetch => {
+                tracing::info!("Refetch triggered, restarting connection.");
+            }
+            Control::ChangeSymbol(new_symbol) => {
+                symbol = new_symbol;
+                tracing::info!("Changing symbol to {symbol}, restarting connection.");
+            }
+            Control::ChangeExchange(new_exchange) => {
+                exchange_type = new_exchange;
+                symbol = exchange_type.adapt_symbol_with(&symbol, &settings);
+                tracing::info!("Changing exchange to {:?}, restarting connection.", exchange_type);
+            }
+            Control::UpdateSettings(new_settings) => {
+                settings = new_settings;
+                // e.g. a Binance spot pair is not a valid COIN-M contract
+                symbol = exchange_type.adapt_symbol_with(&symbol, &settings);
+                tracing::info!("Connector settings changed, restarting connection.");
+            }
+        }
+    }
+}
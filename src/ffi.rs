@@ -0,0 +1,277 @@
+// C ABI for the book engine, declared in include/l3_book.h, so the estimator can be embedded
+// in C and C++ tools. Build with `--features ffi` and link the cdylib.
+//
+// Every pointer argument must be valid for the call: books come from `l3_book_new` and are
+// not used after `l3_book_free`; level arrays hold the given number of elements. A book must
+// not be used from two threads at once. Null books are rejected rather than dereferenced.
+#![allow(clippy::missing_safety_doc)]
+
+use crate::exchanges::{DepthUpdate, OrderBookSnapshot, PriceLevel, Side};
+use crate::orderbook::{InferredAction, InferredEvent, OrderBook, UpdateOutcome};
+use rust_decimal::prelude::*;
+use std::ffi::c_void;
+use std::slice;
+
+pub const L3_BID: i32 = 0;
+pub const L3_ASK: i32 = 1;
+
+pub const L3_APPLIED: i32 = 0;
+pub const L3_BUFFERED: i32 = 1;
+pub const L3_SKIPPED: i32 = 2;
+// A null book, a null array with a non-zero length, or a price that is not finite
+pub const L3_INVALID: i32 = -1;
+
+pub const L3_JOINED: i32 = 0;
+pub const L3_REMOVED: i32 = 1;
+pub const L3_REDUCED: i32 = 2;
+pub const L3_FILLED: i32 = 3;
+
+// Input level: the new quantity at a price, zero removing the level
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct L3Level {
+    pub price: f64,
+    pub quantity: f64,
+}
+
+// Output level, best first
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct L3LevelInfo {
+    pub price: f64,
+    pub quantity: f64,
+    // Estimated orders resting at the level
+    pub orders: u32,
+}
+
+// One decision of the estimator, passed to the event callback
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct L3Event {
+    // Milliseconds since the Unix epoch
+    pub time: u64,
+    pub side: i32,
+    pub price: f64,
+    pub order_id: u64,
+    pub action: i32,
+    // Size after the change, zero once removed
+    pub size: f64,
+    // Positive when the order joined, negative otherwise
+    pub change: f64,
+}
+
+pub type L3EventCallback = Option<unsafe extern "C" fn(event: *const L3Event, user_data: *mut c_void)>;
+
+pub struct L3Book {
+    book: OrderBook,
+    callback: L3EventCallback,
+    user_data: *mut c_void,
+}
+
+impl L3Book {
+    // Hands the events of the last change to the callback
+    fn emit(&mut self) {
+        let Some(callback) = self.callback else {
+            return;
+        };
+        for event in self.book.drain_events() {
+            let event = L3Event::from(event);
+            unsafe { callback(&event, self.user_data) };
+        }
+    }
+}
+
+impl From<InferredEvent> for L3Event {
+    fn from(event: InferredEvent) -> Self {
+        Self {
+            time: event.time,
+            side: match event.side {
+                Side::Bid => L3_BID,
+                Side::Ask => L3_ASK,
+            },
+            price: event.price.to_f64().unwrap_or(0.0),
+            order_id: event.order_id,
+            action: match event.action {
+                InferredAction::Joined => L3_JOINED,
+                InferredAction::Removed => L3_REMOVED,
+                InferredAction::Reduced => L3_REDUCED,
+                InferredAction::Filled => L3_FILLED,
+            },
+            size: event.size.to_f64().unwrap_or(0.0),
+            change: event.change.to_f64().unwrap_or(0.0),
+        }
+    }
+}
+
+fn outcome_code(outcome: UpdateOutcome) -> i32 {
+    match outcome {
+        UpdateOutcome::Applied => L3_APPLIED,
+        UpdateOutcome::Buffered => L3_BUFFERED,
+        UpdateOutcome::Skipped => L3_SKIPPED,
+    }
+}
+
+// Doubles are read as their shortest decimal form, so 0.1 is 0.1 rather than its binary value
+unsafe fn levels(levels: *const L3Level, len: usize) -> Option<Vec<PriceLevel>> {
+    if len == 0 {
+        return Some(Vec::new());
+    }
+    if levels.is_null() {
+        return None;
+    }
+    slice::from_raw_parts(levels, len)
+        .iter()
+        .map(|level| Some(PriceLevel::new(Decimal::from_f64(level.price)?, Decimal::from_f64(level.quantity)?)))
+        .collect()
+}
+
+#[no_mangle]
+pub extern "C" fn l3_book_new() -> *mut L3Book {
+    Box::into_raw(Box::new(L3Book {
+        book: OrderBook::new(),
+        callback: None,
+        user_data: std::ptr::null_mut(),
+    }))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn l3_book_free(book: *mut L3Book) {
+    if !book.is_null() {
+        drop(Box::from_raw(book));
+    }
+}
+
+// Called for every order the estimator infers joining, leaving or shrinking, during the
+// `l3_book_apply_update` that caused it; `user_data` is passed back unchanged. A null
+// callback stops the events.
+#[no_mangle]
+pub unsafe extern "C" fn l3_book_set_event_callback(
+    book: *mut L3Book,
+    callback: L3EventCallback,
+    user_data: *mut c_void,
+) {
+    let Some(book) = book.as_mut() else {
+        return;
+    };
+    book.callback = callback;
+    book.user_data = user_data;
+    book.book.set_event_log(callback.is_some());
+}
+
+// Replaces the book with a full snapshot
+#[no_mangle]
+pub unsafe extern "C" fn l3_book_apply_snapshot(
+    book: *mut L3Book,
+    last_update_id: u64,
+    bids: *const L3Level,
+    bids_len: usize,
+    asks: *const L3Level,
+    asks_len: usize,
+) -> i32 {
+    let (Some(book), Some(bids), Some(asks)) = (book.as_mut(), levels(bids, bids_len), levels(asks, asks_len)) else {
+        return L3_INVALID;
+    };
+    let snapshot = OrderBookSnapshot {
+        last_update_id,
+        event_time: 0,
+        recv_time: 0,
+        bids,
+        asks,
+    };
+    let outcome = book.book.apply_snapshot(&snapshot);
+    book.emit();
+    outcome_code(outcome)
+}
+
+// Applies the changed levels of update `first_update_id..=last_update_id`. Updates arriving
+// before the first snapshot are held until it comes; ones older than the book are skipped.
+#[no_mangle]
+pub unsafe extern "C" fn l3_book_apply_update(
+    book: *mut L3Book,
+    first_update_id: u64,
+    last_update_id: u64,
+    bids: *const L3Level,
+    bids_len: usize,
+    asks: *const L3Level,
+    asks_len: usize,
+) -> i32 {
+    let (Some(book), Some(bids), Some(asks)) = (book.as_mut(), levels(bids, bids_len), levels(asks, asks_len)) else {
+        return L3_INVALID;
+    };
+    let update = DepthUpdate {
+        event_time: 0,
+        transaction_time: 0,
+        symbol: String::new(),
+        capital_u: first_update_id,
+        small_u: last_update_id,
+        // No chaining: gaps are the caller's to detect
+        pu: -1,
+        bids,
+        asks,
+        recv_time: 0,
+    };
+    let outcome = book.book.handle_update(update);
+    book.emit();
+    outcome_code(outcome)
+}
+
+// Writes up to `capacity` levels of `side`, best first, and returns how many were written
+#[no_mangle]
+pub unsafe extern "C" fn l3_book_levels(book: *const L3Book, side: i32, out: *mut L3LevelInfo, capacity: usize) -> usize {
+    let Some(book) = book.as_ref() else {
+        return 0;
+    };
+    if out.is_null() || capacity == 0 {
+        return 0;
+    }
+    let out = slice::from_raw_parts_mut(out, capacity);
+    let info = |(price, queue): (&Decimal, &crate::orderbook::OrderQueue)| L3LevelInfo {
+        price: price.to_f64().unwrap_or(0.0),
+        quantity: queue.total().to_f64().unwrap_or(0.0),
+        orders: queue.len() as u32,
+    };
+    let levels: Box<dyn Iterator<Item = L3LevelInfo>> = match side {
+        L3_BID => Box::new(book.book.bids.iter().rev().map(info)),
+        L3_ASK => Box::new(book.book.asks.iter().map(info)),
+        _ => return 0,
+    };
+    let mut written = 0;
+    for (slot, level) in out.iter_mut().zip(levels) {
+        *slot = level;
+        written += 1;
+    }
+    written
+}
+
+// Writes up to `capacity` estimated order sizes at `price` on `side`, oldest first, and
+// returns how many were written; 0 if the level is not in the book
+#[no_mangle]
+pub unsafe extern "C" fn l3_book_orders(
+    book: *const L3Book,
+    side: i32,
+    price: f64,
+    out: *mut f64,
+    capacity: usize,
+) -> usize {
+    let (Some(book), Some(price)) = (book.as_ref(), Decimal::from_f64(price)) else {
+        return 0;
+    };
+    if out.is_null() || capacity == 0 {
+        return 0;
+    }
+    let queue = match side {
+        L3_BID => book.book.bids.get(&price),
+        L3_ASK => book.book.asks.get(&price),
+        _ => None,
+    };
+    let Some(queue) = queue else {
+        return 0;
+    };
+    let out = slice::from_raw_parts_mut(out, capacity);
+    let mut written = 0;
+    for (slot, size) in out.iter_mut().zip(queue.sizes()) {
+        *slot = size.to_f64().unwrap_or(0.0);
+        written += 1;
+    }
+    written
+}
@@ -0,0 +1,89 @@
+// Fixed-point arithmetic for the book engine. Quantities are held as integer lots of
+// 10^-decimals and prices are bucketed as integer ticks, so the hot path adds and compares
+// integers; `Decimal` is only built at the API boundary.
+use rust_decimal::Decimal;
+
+// Finest scale the engine switches to; Decimal itself allows 28
+pub const MAX_DECIMALS: u32 = 18;
+
+fn pow10(exp: u32) -> Option<i128> {
+    10i128.checked_pow(exp)
+}
+
+// `value` as a whole number of 10^-decimals units, None if it has finer digits or does not fit
+pub fn to_lots(value: Decimal, decimals: u32) -> Option<i64> {
+    let scale = value.scale();
+    let mantissa = value.mantissa();
+    let units = if scale <= decimals {
+        mantissa.checked_mul(pow10(decimals - scale)?)?
+    } else {
+        // Trailing zeros, e.g. "1.500" at 2 decimals
+        let divisor = pow10(scale - decimals)?;
+        if mantissa % divisor != 0 {
+            return None;
+        }
+        mantissa / divisor
+    };
+    i64::try_from(units).ok()
+}
+
+pub fn from_lots(lots: i128, decimals: u32) -> Decimal {
+    Decimal::from_i128_with_scale(lots, decimals)
+}
+
+// Fewest decimals that represent `value` exactly
+pub fn decimals_of(value: Decimal) -> u32 {
+    value.normalize().scale()
+}
+
+// Multiplies lots held at `from` decimals to `to` (>= from) decimals
+pub fn rescale(lots: i64, from: u32, to: u32) -> Option<i64> {
+    i64::try_from(i128::from(lots).checked_mul(pow10(to - from)?)?).ok()
+}
+
+// Both values as integers of a common scale, and that scale
+fn common_units(a: Decimal, b: Decimal) -> Option<(i128, i128, u32)> {
+    let scale = a.scale().max(b.scale());
+    let a_units = a.mantissa().checked_mul(pow10(scale - a.scale())?)?;
+    let b_units = b.mantissa().checked_mul(pow10(scale - b.scale())?)?;
+    Some((a_units, b_units, scale))
+}
+
+// `price` rounded down (or up) to a multiple of `bucket`, in integer ticks; None when the
+// scaled values overflow and the caller has to fall back to Decimal division
+pub fn round_to_bucket(price: Decimal, bucket: Decimal, up: bool) -> Option<Decimal> {
+    let (price, bucket, scale) = common_units(price, bucket)?;
+    if bucket <= 0 {
+        return None;
+    }
+    let buckets = if up { -(-price).div_euclid(bucket) } else { price.div_euclid(bucket) };
+    Decimal::try_from_i128_with_scale(buckets.checked_mul(bucket)?, scale).ok()
+}
+
+// Decimals of `ratio` results, beyond what any display or export uses
+pub const RATIO_DECIMALS: u32 = 16;
+
+// (num / 10^num_scale) / (den / 10^den_scale) as units of 10^-RATIO_DECIMALS, rounded half away
+// from zero; None on overflow or a zero denominator
+pub fn ratio(num: i128, num_scale: u32, den: i128, den_scale: u32) -> Option<i128> {
+    if den == 0 {
+        return None;
+    }
+    let exp = (den_scale + RATIO_DECIMALS) as i64 - num_scale as i64;
+    let (num, den) = if exp >= 0 {
+        (num.checked_mul(pow10(exp as u32)?)?, den)
+    } else {
+        (num, den.checked_mul(pow10((-exp) as u32)?)?)
+    };
+    let (quotient, remainder) = (num / den, num % den);
+    if remainder.unsigned_abs() >= den.unsigned_abs() - remainder.unsigned_abs() {
+        Some(quotient + num.signum() * den.signum())
+    } else {
+        Some(quotient)
+    }
+}
+
+// Units of 10^-scale as a Decimal without trailing zeros; None if it does not fit a Decimal
+pub fn to_decimal(units: i128, scale: u32) -> Option<Decimal> {
+    Some(Decimal::try_from_i128_with_scale(units, scale).ok()?.normalize())
+}
@@ -0,0 +1,278 @@
+// Realized order-flow statistics: the estimator's inferred events aggregated over a rolling
+// window, so a book feed yields add/cancel activity without a venue order stream.
+//
+// Removals and reductions count as cancels unless a trade was attributed to them; on venues
+// without a trade stream fills therefore show up as cancels.
+use crate::exchanges::Side;
+use crate::orderbook::{InferredAction, InferredEvent, OrderBook, LIQUIDITY_BANDS_BPS};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::ops::Bound;
+
+// Seconds of events the statistics cover unless `OrderFlow::new` says otherwise
+pub const DEFAULT_FLOW_WINDOW_SECS: u64 = 60;
+
+// Cancels are bucketed by distance from the mid: within each of `LIQUIDITY_BANDS_BPS`, then beyond
+pub const DISTANCE_BUCKETS: usize = LIQUIDITY_BANDS_BPS.len() + 1;
+
+// Seconds over which `queue_value` takes the chance of a fill
+pub const QUEUE_VALUE_HORIZON_SECS: f64 = 60.0;
+
+// Events of one second
+#[derive(Clone, Debug, Default)]
+struct FlowSecond {
+    second: u64,
+    // Indexed by `side_index`
+    adds: [u64; 2],
+    add_size: [Decimal; 2],
+    cancels: [u64; 2],
+    fills: [u64; 2],
+    cancels_by_distance: [u64; DISTANCE_BUCKETS],
+    // Size leaving the best levels through trades (per side) and through cancels
+    touch_filled: [Decimal; 2],
+    touch_cancelled: Decimal,
+}
+
+// Statistics over the seconds currently in the window
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub struct FlowStats {
+    // Seconds with at least one event that the window spans
+    pub seconds: u64,
+    pub adds: u64,
+    pub cancels: u64,
+    pub fills: u64,
+    // Orders joining per order cancelled; None before the first cancel
+    pub add_cancel_ratio: Option<f64>,
+    // Cancels per second within each band of the mid (see `DISTANCE_BUCKETS`), narrowest first
+    pub cancel_rate_by_distance: [f64; DISTANCE_BUCKETS],
+    // Mean size of the orders joining each side; None when none joined
+    pub mean_bid_size: Option<Decimal>,
+    pub mean_ask_size: Option<Decimal>,
+    // Share of the size leaving the best bid and ask that traded rather than cancelled
+    pub touch_fill_rate: Option<f64>,
+    // Size traded per second at the best bid and at the best ask
+    pub bid_traded_per_sec: f64,
+    pub ask_traded_per_sec: f64,
+}
+
+impl FlowStats {
+    pub fn describe(&self) -> String {
+        let ratio = |v: Option<f64>, scale: f64| v.map_or("-".to_string(), |v| format!("{:.2}", v * scale));
+        let size = |v: Option<Decimal>| v.map_or("-".to_string(), |v| v.round_dp(8).normalize().to_string());
+        let rates: Vec<String> = self.cancel_rate_by_distance.iter().map(|rate| format!("{rate:.2}")).collect();
+        format!(
+            "{} adds, {} cancels, {} fills over {}s; add/cancel {}; cancels/s by distance {}; \
+             mean size bid {} ask {}; touch fill rate {}%",
+            self.adds,
+            self.cancels,
+            self.fills,
+            self.seconds,
+            ratio(self.add_cancel_ratio, 1.0),
+            rates.join("/"),
+            size(self.mean_bid_size),
+            size(self.mean_ask_size),
+            ratio(self.touch_fill_rate, 100.0)
+        )
+    }
+}
+
+// Label of each distance bucket, e.g. "≤5bps" and ">50bps"
+pub fn distance_label(bucket: usize) -> String {
+    match LIQUIDITY_BANDS_BPS.get(bucket) {
+        Some(bps) => format!("≤{bps}bps"),
+        None => format!(">{}bps", LIQUIDITY_BANDS_BPS[LIQUIDITY_BANDS_BPS.len() - 1]),
+    }
+}
+
+fn side_index(side: Side) -> usize {
+    match side {
+        Side::Bid => 0,
+        Side::Ask => 1,
+    }
+}
+
+// Rolling aggregation of inferred events, one bucket per second
+#[derive(Clone, Debug)]
+pub struct OrderFlow {
+    window_secs: u64,
+    seconds: VecDeque<FlowSecond>,
+}
+
+impl Default for OrderFlow {
+    fn default() -> Self {
+        Self::new(DEFAULT_FLOW_WINDOW_SECS)
+    }
+}
+
+impl OrderFlow {
+    pub fn new(window_secs: u64) -> Self {
+        Self {
+            window_secs: window_secs.max(1),
+            seconds: VecDeque::new(),
+        }
+    }
+
+    pub fn window_secs(&self) -> u64 {
+        self.window_secs
+    }
+
+    pub fn clear(&mut self) {
+        self.seconds.clear();
+    }
+
+    // Adds one event, classified against the book's touch when it was drained
+    pub fn record(&mut self, event: &InferredEvent, best_bid: Option<Decimal>, best_ask: Option<Decimal>) {
+        let second = event.time / 1000;
+        if self.seconds.back().is_none_or(|last| last.second < second) {
+            self.seconds.push_back(FlowSecond {
+                second,
+                ..FlowSecond::default()
+            });
+            while self.seconds.front().is_some_and(|first| first.second + self.window_secs <= second) {
+                self.seconds.pop_front();
+            }
+        }
+        // Events are recorded in time order; a late one joins the newest second
+        let bucket = self.seconds.back_mut().expect("a second was just pushed");
+        let side = side_index(event.side);
+        // The touch may have moved away from a level that just emptied, so anything at or
+        // inside it counts
+        let at_touch = match event.side {
+            Side::Bid => best_bid.is_none_or(|best| event.price >= best),
+            Side::Ask => best_ask.is_none_or(|best| event.price <= best),
+        };
+        match event.action {
+            InferredAction::Joined => {
+                bucket.adds[side] += 1;
+                bucket.add_size[side] += event.change;
+            }
+            InferredAction::Filled => {
+                bucket.fills[side] += 1;
+                if at_touch {
+                    bucket.touch_filled[side] -= event.change;
+                }
+            }
+            InferredAction::Removed | InferredAction::Reduced => {
+                bucket.cancels[side] += 1;
+                if at_touch {
+                    bucket.touch_cancelled -= event.change;
+                }
+                if let Some(mid) = best_bid.zip(best_ask).map(|(bid, ask)| (bid + ask) / Decimal::TWO) {
+                    bucket.cancels_by_distance[distance_bucket(event.price, mid)] += 1;
+                }
+            }
+        }
+    }
+
+    // Statistics over the window ending at the newest recorded second
+    pub fn stats(&self) -> FlowStats {
+        let mut stats = FlowStats::default();
+        let (Some(first), Some(last)) = (self.seconds.front(), self.seconds.back()) else {
+            return stats;
+        };
+        stats.seconds = last.second - first.second + 1;
+        let mut add_size = [Decimal::ZERO; 2];
+        let mut adds = [0u64; 2];
+        let mut cancels_by_distance = [0u64; DISTANCE_BUCKETS];
+        let (mut touch_filled, mut touch_cancelled) = ([Decimal::ZERO; 2], Decimal::ZERO);
+        for second in &self.seconds {
+            for side in 0..2 {
+                adds[side] += second.adds[side];
+                add_size[side] += second.add_size[side];
+                stats.cancels += second.cancels[side];
+                stats.fills += second.fills[side];
+                touch_filled[side] += second.touch_filled[side];
+            }
+            for (total, count) in cancels_by_distance.iter_mut().zip(second.cancels_by_distance) {
+                *total += count;
+            }
+            touch_cancelled += second.touch_cancelled;
+        }
+        stats.adds = adds[0] + adds[1];
+        stats.add_cancel_ratio = (stats.cancels > 0).then(|| stats.adds as f64 / stats.cancels as f64);
+        stats.cancel_rate_by_distance = cancels_by_distance.map(|count| count as f64 / stats.seconds as f64);
+        let mean = |size: Decimal, count: u64| (count > 0).then(|| size / Decimal::from(count));
+        stats.mean_bid_size = mean(add_size[0], adds[0]);
+        stats.mean_ask_size = mean(add_size[1], adds[1]);
+        let touch_total = touch_filled[0] + touch_filled[1] + touch_cancelled;
+        stats.touch_fill_rate = (touch_total > Decimal::ZERO)
+            .then(|| ((touch_filled[0] + touch_filled[1]) / touch_total).to_f64())
+            .flatten();
+        let per_sec = |size: Decimal| size.to_f64().unwrap_or(0.0) / stats.seconds as f64;
+        stats.bid_traded_per_sec = per_sec(touch_filled[0]);
+        stats.ask_traded_per_sec = per_sec(touch_filled[1]);
+        stats
+    }
+}
+
+// Expected edge of a hypothetical order joining the back of a level now. Adverse selection is
+// not modelled: the edge is the distance from the mid when the order rests, not after the price
+// has moved against a fill, so this is an upper bound.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QueueValue {
+    // Size that has to trade first: the better levels and the whole queue at the level
+    pub qty_ahead: Decimal,
+    // Seconds for that size to trade at the touch's traded rate; None when nothing traded
+    pub time_to_fill: Option<f64>,
+    // Chance of a fill within `QUEUE_VALUE_HORIZON_SECS`
+    pub fill_probability: f64,
+    // Distance from the mid in the order's favour, bps
+    pub edge_bps: f64,
+}
+
+impl QueueValue {
+    // Fill probability times the edge, bps
+    pub fn value_bps(&self) -> f64 {
+        self.fill_probability * self.edge_bps
+    }
+}
+
+// Queue value of an order joining `side` at `price` now, from the estimated queue position
+// (`OrderBook::queue_position`) and the size traded per second at that side's touch. Trades are
+// taken as a Poisson stream draining the size ahead, so the order fills within the horizon with
+// probability 1 - exp(-horizon / time to fill). Cancels ahead of the order are ignored, which
+// makes the fill chance conservative. None without both sides of the book or any flow.
+pub fn queue_value(book: &OrderBook, side: Side, price: Decimal, stats: &FlowStats) -> Option<QueueValue> {
+    let (bid, ask) = book.best_bid().zip(book.best_ask())?;
+    if stats.seconds == 0 {
+        return None;
+    }
+    let mid = (bid + ask) / Decimal::TWO;
+    // Every resting order joined before this one
+    let position = book.queue_position(side, price, u64::MAX);
+    let (better, edge, traded_per_sec): (Decimal, _, _) = match side {
+        Side::Bid => (
+            book.bids.range((Bound::Excluded(price), Bound::Unbounded)).map(|(_, queue)| queue.total()).sum(),
+            mid - price,
+            stats.bid_traded_per_sec,
+        ),
+        Side::Ask => (
+            book.asks.range(..price).map(|(_, queue)| queue.total()).sum(),
+            price - mid,
+            stats.ask_traded_per_sec,
+        ),
+    };
+    let qty_ahead = better + position.qty_ahead;
+    let time_to_fill = (traded_per_sec > 0.0).then(|| qty_ahead.to_f64().unwrap_or(0.0) / traded_per_sec);
+    let fill_probability = time_to_fill.map_or(0.0, |secs| 1.0 - (-QUEUE_VALUE_HORIZON_SECS / secs).exp());
+    Some(QueueValue {
+        qty_ahead,
+        time_to_fill,
+        fill_probability,
+        edge_bps: (edge / mid * Decimal::from(10_000)).to_f64().unwrap_or(0.0),
+    })
+}
+
+// Index into `cancel_rate_by_distance` of a price's distance from `mid`
+fn distance_bucket(price: Decimal, mid: Decimal) -> usize {
+    if mid <= Decimal::ZERO {
+        return DISTANCE_BUCKETS - 1;
+    }
+    let distance = (price - mid).abs() * Decimal::from(10_000);
+    LIQUIDITY_BANDS_BPS
+        .iter()
+        .position(|&bps| distance <= mid * Decimal::from(bps))
+        .unwrap_or(DISTANCE_BUCKETS - 1)
+}
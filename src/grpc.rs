@@ -0,0 +1,180 @@
+// gRPC counterpart of the WebSocket book server, for clients that want a typed schema: the
+// service and messages are generated from proto/l3book.proto, which ships with the crate.
+use crate::exchanges::{ExchangeType, Side};
+use crate::orderbook::{InferredAction, InferredEvent};
+use crate::server::{BookMessage, LevelMessage};
+use futures_util::stream::{self, BoxStream, StreamExt};
+use proto::l3_book_server::{L3Book, L3BookServer};
+use proto::{inferred_order, BookState, InferredOrder, Level};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::{broadcast, watch};
+use tonic::transport::server::TcpIncoming;
+use tonic::{Request, Response, Status};
+
+pub mod proto {
+    tonic::include_proto!("l3book.v1");
+}
+
+// Inferred orders buffered per client; a client further behind skips the oldest
+const ORDER_BUFFER: usize = 4096;
+
+// Serves the latest book state and the stream of inferred orders of one feed. Like the
+// WebSocket server, book subscribers only ever receive the newest state.
+pub struct GrpcServer {
+    exchange: ExchangeType,
+    symbol: String,
+    book: watch::Sender<Option<Arc<BookState>>>,
+    orders: broadcast::Sender<InferredOrder>,
+}
+
+impl GrpcServer {
+    pub fn start(
+        runtime: &tokio::runtime::Handle,
+        addr: SocketAddr,
+        exchange: ExchangeType,
+        symbol: &str,
+    ) -> std::io::Result<Self> {
+        let listener = runtime.block_on(TcpListener::bind(addr))?;
+        tracing::info!("Serving the estimated book over gRPC on {}", listener.local_addr()?);
+        let (book, _) = watch::channel(None);
+        let (orders, _) = broadcast::channel(ORDER_BUFFER);
+        let service = Service {
+            book: book.subscribe(),
+            orders: orders.clone(),
+        };
+        runtime.spawn(async move {
+            let result = tonic::transport::Server::builder()
+                .add_service(L3BookServer::new(service))
+                .serve_with_incoming(TcpIncoming::from(listener))
+                .await;
+            if let Err(e) = result {
+                tracing::error!("gRPC server stopped: {e}");
+            }
+        });
+        Ok(Self {
+            exchange,
+            symbol: symbol.to_string(),
+            book,
+            orders,
+        })
+    }
+
+    pub fn publish(&self, message: &BookMessage) {
+        self.book.send_replace(Some(Arc::new(BookState::from(message))));
+    }
+
+    pub fn publish_order(&self, event: &InferredEvent) {
+        // Fails only while nobody is subscribed
+        let _ = self.orders.send(InferredOrder {
+            exchange: self.exchange.name().to_string(),
+            symbol: self.symbol.clone(),
+            time: event.time,
+            side: match event.side {
+                Side::Bid => proto::Side::Bid,
+                Side::Ask => proto::Side::Ask,
+            } as i32,
+            price: event.price.to_string(),
+            order_id: event.order_id,
+            action: match event.action {
+                InferredAction::Joined => inferred_order::Action::Joined,
+                InferredAction::Removed => inferred_order::Action::Removed,
+                InferredAction::Reduced => inferred_order::Action::Reduced,
+                InferredAction::Filled => inferred_order::Action::Filled,
+            } as i32,
+            size: event.size.to_string(),
+            change: event.change.to_string(),
+        });
+    }
+}
+
+impl From<&BookMessage<'_>> for BookState {
+    fn from(message: &BookMessage) -> Self {
+        let level = |level: &LevelMessage| Level {
+            price: level.price.to_string(),
+            quantity: level.quantity.to_string(),
+            orders: level.orders.iter().map(ToString::to_string).collect(),
+            confidence: level.confidence.clone(),
+        };
+        BookState {
+            exchange: message.exchange.to_string(),
+            symbol: message.symbol.to_string(),
+            timestamp: message.timestamp,
+            last_update_id: message.last_update_id,
+            synced: message.synced,
+            mid: message.mid.map(|v| v.to_string()),
+            microprice: message.microprice.map(|v| v.to_string()),
+            imbalance: message.imbalance.map(|v| v.to_string()),
+            bids: message.bids.iter().map(level).collect(),
+            asks: message.asks.iter().map(level).collect(),
+        }
+    }
+}
+
+// `depth` levels per side of `state`, all of them for 0
+fn truncated(state: &BookState, depth: u32) -> BookState {
+    let depth = if depth == 0 { usize::MAX } else { depth as usize };
+    BookState {
+        bids: state.bids.iter().take(depth).cloned().collect(),
+        asks: state.asks.iter().take(depth).cloned().collect(),
+        ..state.clone()
+    }
+}
+
+struct Service {
+    book: watch::Receiver<Option<Arc<BookState>>>,
+    orders: broadcast::Sender<InferredOrder>,
+}
+
+#[tonic::async_trait]
+impl L3Book for Service {
+    type SubscribeBookStream = BoxStream<'static, Result<BookState, Status>>;
+    type SubscribeInferredOrdersStream = BoxStream<'static, Result<InferredOrder, Status>>;
+
+    async fn subscribe_book(
+        &self,
+        request: Request<proto::SubscribeBookRequest>,
+    ) -> Result<Response<Self::SubscribeBookStream>, Status> {
+        let depth = request.into_inner().depth;
+        let mut rx = self.book.clone();
+        // Send the current state right away instead of waiting for the next change
+        rx.mark_changed();
+        let states = stream::unfold(rx, move |mut rx| async move {
+            loop {
+                rx.changed().await.ok()?;
+                let state = rx.borrow_and_update().clone();
+                if let Some(state) = state {
+                    return Some((Ok(truncated(&state, depth)), rx));
+                }
+            }
+        });
+        Ok(Response::new(states.boxed()))
+    }
+
+    async fn get_snapshot(&self, request: Request<proto::GetSnapshotRequest>) -> Result<Response<BookState>, Status> {
+        let state = self.book.borrow().clone();
+        match state {
+            Some(state) => Ok(Response::new(truncated(&state, request.into_inner().depth))),
+            None => Err(Status::unavailable("no book state yet")),
+        }
+    }
+
+    async fn subscribe_inferred_orders(
+        &self,
+        _request: Request<proto::SubscribeInferredOrdersRequest>,
+    ) -> Result<Response<Self::SubscribeInferredOrdersStream>, Status> {
+        let orders = stream::unfold(self.orders.subscribe(), |mut rx| async move {
+            loop {
+                match rx.recv().await {
+                    Ok(order) => return Some((Ok(order), rx)),
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!("gRPC order subscriber fell behind, skipped {skipped} orders");
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        });
+        Ok(Response::new(orders.boxed()))
+    }
+}
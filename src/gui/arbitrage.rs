@@ -0,0 +1,203 @@
+use super::tab::{BookTab, FeedKey};
+use crate::arbitrage::{ArbMonitor, ArbSample, ArbSpread};
+use eframe::egui;
+use egui::Color32;
+use egui_plot::{HLine, Legend, Line, Plot, PlotPoints};
+use rust_decimal::prelude::*;
+use rust_decimal::Decimal;
+use std::collections::VecDeque;
+use std::time::Duration;
+use web_time::Instant;
+
+// 5 minutes of spread history at 250ms
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(250);
+const CAPACITY: usize = 1200;
+const ALERT_LOG_CAPACITY: usize = 50;
+
+// Executable spread between two tabs streaming the same asset, plotted over time, with an
+// alert whenever either direction rises above the threshold
+pub struct ArbView {
+    leg_a: Option<FeedKey>,
+    leg_b: Option<FeedKey>,
+    // Base units bought on one venue and sold on the other
+    size: f64,
+    threshold_bps: f64,
+    monitor: ArbMonitor,
+    last_sample: Instant,
+    above_threshold: bool,
+    // (timestamp, direction, bps) each time the spread crossed the threshold
+    alerts: VecDeque<(u64, String, f64)>,
+}
+
+impl Default for ArbView {
+    fn default() -> Self {
+        Self {
+            leg_a: None,
+            leg_b: None,
+            size: 1000.0,
+            threshold_bps: 10.0,
+            monitor: ArbMonitor::new(CAPACITY),
+            last_sample: Instant::now(),
+            above_threshold: false,
+            alerts: VecDeque::new(),
+        }
+    }
+}
+
+impl ArbView {
+    // Samples the spread between the selected tabs; runs every frame whatever the view
+    pub fn update(&mut self, tabs: &[BookTab]) {
+        if self.last_sample.elapsed() < SAMPLE_INTERVAL {
+            return;
+        }
+        self.last_sample = Instant::now();
+        if self.leg_a.is_none() && self.leg_b.is_none() && tabs.len() >= 2 {
+            self.leg_a = Some(tabs[0].key());
+            self.leg_b = Some(tabs[1].key());
+        }
+        let find = |key: &Option<FeedKey>| tabs.iter().find(|tab| Some(tab.key()) == *key);
+        let (Some(a), Some(b)) = (find(&self.leg_a), find(&self.leg_b)) else {
+            return;
+        };
+        let Some(size) = Decimal::from_f64(self.size).filter(|s| *s > Decimal::ZERO) else {
+            return;
+        };
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        let sample = self.monitor.sample(&a.book, &b.book, size, now);
+
+        // The more profitable direction
+        let best = [(sample.a_to_b, a, b), (sample.b_to_a, b, a)]
+            .into_iter()
+            .filter_map(|(spread, buy, sell)| spread.map(|s| (s.bps, buy, sell)))
+            .max_by_key(|(bps, ..)| *bps);
+        let threshold = Decimal::from_f64(self.threshold_bps).unwrap_or(Decimal::MAX);
+        let above = best.filter(|(bps, ..)| *bps >= threshold);
+        if let (Some((bps, buy, sell)), false) = (above, self.above_threshold) {
+            if self.alerts.len() == ALERT_LOG_CAPACITY {
+                self.alerts.pop_front();
+            }
+            let direction = format!("buy {} → sell {}", buy.title(), sell.title());
+            self.alerts.push_back((now, direction, bps.to_f64().unwrap_or(0.0)));
+        }
+        self.above_threshold = above.is_some();
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, tabs: &[(FeedKey, String)]) {
+        if tabs.len() < 2 {
+            ui.label("Open the same asset on two exchanges to monitor the arbitrage spread.");
+            return;
+        }
+        ui.horizontal(|ui| {
+            let mut changed = Self::leg_selector(ui, "arb_leg_a", "A:", &mut self.leg_a, tabs);
+            changed |= Self::leg_selector(ui, "arb_leg_b", "B:", &mut self.leg_b, tabs);
+            ui.label("Size:");
+            changed |= ui
+                .add(egui::DragValue::new(&mut self.size).range(0.0..=f64::MAX).speed(10.0))
+                .on_hover_text("Base units walked through each book; prices assume the same quote currency")
+                .changed();
+            ui.label("Alert above (bps):");
+            ui.add(egui::DragValue::new(&mut self.threshold_bps).speed(0.5));
+            if changed {
+                self.monitor.clear();
+                self.above_threshold = false;
+            }
+        });
+
+        let latest = self.monitor.latest().copied();
+        ui.horizontal(|ui| {
+            let show = |ui: &mut egui::Ui, label: &str, spread: Option<ArbSpread>| match spread {
+                Some(s) => {
+                    let bps = s.bps.to_f64().unwrap_or(0.0);
+                    let color = if bps >= self.threshold_bps {
+                        Color32::GOLD
+                    } else if bps >= 0.0 {
+                        Color32::GREEN
+                    } else {
+                        Color32::GRAY
+                    };
+                    ui.colored_label(
+                        color,
+                        format!("{label}: {bps:+.2} bps (buy {} / sell {})", s.buy_price.normalize(), s.sell_price.normalize()),
+                    );
+                }
+                None => {
+                    ui.label(format!("{label}: -"));
+                }
+            };
+            show(ui, "Buy A → sell B", latest.and_then(|s| s.a_to_b));
+            ui.separator();
+            show(ui, "Buy B → sell A", latest.and_then(|s| s.b_to_a));
+        });
+
+        let Some(newest) = latest.map(|s| s.timestamp) else {
+            ui.label("Waiting for both books...");
+            return;
+        };
+        let series = |leg: fn(&ArbSample) -> Option<ArbSpread>| -> PlotPoints {
+            self.monitor
+                .samples()
+                .iter()
+                .filter_map(|s| {
+                    let bps = leg(s)?.bps.to_f64()?;
+                    Some([(s.timestamp as f64 - newest as f64) / 1000.0, bps])
+                })
+                .collect()
+        };
+        let a_to_b = series(|s| s.a_to_b);
+        let b_to_a = series(|s| s.b_to_a);
+
+        ui.columns(2, |columns| {
+            Plot::new("arb_spread_chart")
+                .legend(Legend::default())
+                .x_axis_label("seconds")
+                .y_axis_label("bps")
+                .height(columns[0].available_height().max(200.0))
+                .show(&mut columns[0], |plot_ui| {
+                    plot_ui.line(Line::new("buy A → sell B", a_to_b).color(Color32::LIGHT_GREEN));
+                    plot_ui.line(Line::new("buy B → sell A", b_to_a).color(Color32::LIGHT_BLUE));
+                    plot_ui.hline(HLine::new("alert", self.threshold_bps).color(Color32::GOLD));
+                    plot_ui.hline(HLine::new("zero", 0.0).color(Color32::DARK_GRAY));
+                });
+            let ui = &mut columns[1];
+            ui.horizontal(|ui| {
+                ui.label("Threshold crossings");
+                if ui.small_button("Clear").clicked() {
+                    self.alerts.clear();
+                }
+            });
+            egui::ScrollArea::vertical().id_salt("arb_alerts").show(ui, |ui| {
+                for (timestamp, direction, bps) in self.alerts.iter().rev() {
+                    let time = chrono::DateTime::from_timestamp_millis(*timestamp as i64)
+                        .map(|t| t.with_timezone(&chrono::Local).format("%H:%M:%S").to_string())
+                        .unwrap_or_default();
+                    ui.colored_label(Color32::GOLD, format!("{time} {bps:+.2} bps, {direction}"));
+                }
+            });
+        });
+    }
+
+    // Returns whether the selection changed
+    fn leg_selector(
+        ui: &mut egui::Ui,
+        id: &str,
+        label: &str,
+        leg: &mut Option<FeedKey>,
+        tabs: &[(FeedKey, String)],
+    ) -> bool {
+        ui.label(label);
+        let selected = tabs
+            .iter()
+            .find(|(key, _)| Some(key) == leg.as_ref())
+            .map_or("-", |(_, title)| title.as_str());
+        let mut changed = false;
+        egui::ComboBox::from_id_salt(id).selected_text(selected).show_ui(ui, |ui| {
+            for (key, title) in tabs {
+                if ui.selectable_label(Some(key) == leg.as_ref(), title).clicked() {
+                    *leg = Some(key.clone());
+                    changed = true;
+                }
+            }
+        });
+        changed
+    }
+}
@@ -0,0 +1,80 @@
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+// Quantity axis and navigation of the order bar chart
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct BarAxes {
+    // Quantities drawn on a log scale, so one whale order does not flatten every other bar
+    pub log_scale: bool,
+    // Top of the quantity axis; None fits the largest level
+    pub fixed_max: Option<f64>,
+    // Zoom and pan with the mouse (double-click or "Reset view" to go back)
+    pub interactive: bool,
+}
+
+impl BarAxes {
+    // Axis settings; true when the view should be reset, either because they changed or
+    // because "Reset view" was clicked
+    pub fn controls(&mut self, ui: &mut egui::Ui, largest_level: f64) -> bool {
+        let before = *self;
+        let mut reset = false;
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.log_scale, "Log scale")
+                .on_hover_text("Quantity axis on a log scale, so small orders stay visible next to a whale");
+            let mut fixed = self.fixed_max.is_some();
+            ui.checkbox(&mut fixed, "Fixed max").on_hover_text("Hold the quantity axis at a set height");
+            match (fixed, &mut self.fixed_max) {
+                (true, Some(max)) => {
+                    let speed = (*max * 0.01).max(1e-6);
+                    ui.add(egui::DragValue::new(max).range(1e-9..=f64::MAX).speed(speed));
+                }
+                (true, None) => self.fixed_max = Some(if largest_level > 0.0 { largest_level } else { 1.0 }),
+                (false, _) => self.fixed_max = None,
+            }
+            ui.checkbox(&mut self.interactive, "Zoom/pan");
+            reset = ui.button("Reset view").clicked();
+        });
+        reset || *self != before
+    }
+
+    // Axis mapping for this frame; `unit` is the smallest order shown, which the log scale
+    // places one decade above zero
+    pub fn scale(&self, unit: f64) -> QtyScale {
+        QtyScale {
+            log: self.log_scale,
+            unit: if unit > 0.0 { unit } else { 1.0 },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct QtyScale {
+    log: bool,
+    unit: f64,
+}
+
+impl QtyScale {
+    // Height on the axis of a quantity stacked from zero
+    pub fn y(&self, qty: f64) -> f64 {
+        if self.log {
+            (qty.max(0.0) / self.unit * 9.0).ln_1p() / std::f64::consts::LN_10
+        } else {
+            qty
+        }
+    }
+
+    // Quantity at a height on the axis
+    pub fn qty(&self, y: f64) -> f64 {
+        if self.log {
+            (10f64.powf(y) - 1.0) * self.unit / 9.0
+        } else {
+            y
+        }
+    }
+
+    // A bar segment stacked from `base` to `base + qty`, as (base, height) on the axis
+    pub fn segment(&self, base: f64, qty: f64) -> (f64, f64) {
+        let (low, high) = (self.y(base), self.y(base + qty));
+        (low, high - low)
+    }
+}
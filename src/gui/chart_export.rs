@@ -0,0 +1,267 @@
+use crate::exchanges::{ExchangeType, Side};
+use crate::orderbook::OrderQueue;
+use eframe::egui;
+use egui::{Color32, Event, Rect, ViewportCommand};
+use egui_plot::{Bar, PlotPoint, PlotTransform};
+use rust_decimal::prelude::*;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+// Price labels along the bottom of an SVG export
+const SVG_PRICE_TICKS: usize = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChartFormat {
+    // Screenshot of the plot as drawn, axes included
+    Png,
+    // The bars redrawn as vector shapes
+    Svg,
+}
+
+impl ChartFormat {
+    pub const ALL: [ChartFormat; 2] = [ChartFormat::Png, ChartFormat::Svg];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ChartFormat::Png => "png",
+            ChartFormat::Svg => "svg",
+        }
+    }
+}
+
+// The order bar chart of one frame, as needed to save it
+pub struct Chart<'a> {
+    pub exchange: ExchangeType,
+    pub symbol: &'a str,
+    pub bars: &'a [Bar],
+    pub transform: &'a PlotTransform,
+    // The whole plot widget, axes included, which a PNG is cropped to
+    pub rect: Rect,
+    pub background: Color32,
+    pub text: Color32,
+    pub price_prec: usize,
+}
+
+// A level shown in the chart, for the CSV written next to the image
+pub struct ChartLevel<'a> {
+    pub side: Side,
+    pub price: Decimal,
+    pub queue: &'a OrderQueue,
+    // Cluster of each order, front of the queue first, in clustering mode
+    pub clusters: Option<&'a [usize]>,
+}
+
+// "Export chart": saves the order bar chart as PNG or SVG, optionally with the levels behind
+// it as CSV, to a timestamped file in the working directory
+pub struct ChartExport {
+    format: ChartFormat,
+    with_levels: bool,
+    // PNG waiting for the screenshot requested from the viewport, with the rect to crop it to
+    pending: Option<(PathBuf, Rect)>,
+    // What the last export wrote, or why it failed
+    status: Option<Result<String, String>>,
+}
+
+impl Default for ChartExport {
+    fn default() -> Self {
+        Self {
+            format: ChartFormat::Png,
+            with_levels: true,
+            pending: None,
+            status: None,
+        }
+    }
+}
+
+impl ChartExport {
+    // Format and CSV toggle; true when "Export chart" was clicked
+    pub fn controls(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut clicked = false;
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("chart_export_format")
+                .selected_text(self.format.extension())
+                .show_ui(ui, |ui| {
+                    for format in ChartFormat::ALL {
+                        ui.selectable_value(&mut self.format, format, format.extension());
+                    }
+                });
+            ui.checkbox(&mut self.with_levels, "Level data")
+                .on_hover_text("Also write the shown levels and their estimated orders as CSV");
+            clicked = ui
+                .add_enabled(self.pending.is_none(), egui::Button::new("Export chart"))
+                .on_hover_text("Save the chart to a file in the working directory")
+                .clicked();
+            match &self.status {
+                Some(Ok(written)) => {
+                    ui.weak(written);
+                }
+                Some(Err(e)) => {
+                    ui.colored_label(Color32::LIGHT_RED, e);
+                }
+                None => {}
+            }
+        });
+        clicked
+    }
+
+    // Writes the CSV and the SVG right away; a PNG is written by `receive` once the viewport
+    // delivers the screenshot, at the earliest next frame
+    pub fn export<'a>(&mut self, ctx: &egui::Context, chart: &Chart, levels: impl IntoIterator<Item = ChartLevel<'a>>) {
+        let stem = default_stem(chart.exchange, chart.symbol);
+        let mut written = Vec::new();
+        if self.with_levels {
+            let path = stem.with_extension("csv");
+            if let Err(e) = write_levels(&path, levels) {
+                self.status = Some(Err(format!("Failed to write {}: {e}", path.display())));
+                return;
+            }
+            written.push(path);
+        }
+        let path = stem.with_extension(self.format.extension());
+        match self.format {
+            ChartFormat::Png => {
+                self.pending = Some((path, chart.rect));
+                ctx.send_viewport_cmd(ViewportCommand::Screenshot(Default::default()));
+            }
+            ChartFormat::Svg => {
+                if let Err(e) = std::fs::write(&path, svg(chart)) {
+                    self.status = Some(Err(format!("Failed to write {}: {e}", path.display())));
+                    return;
+                }
+                written.push(path);
+            }
+        }
+        self.status = (!written.is_empty()).then(|| Ok(saved(&written)));
+    }
+
+    // Crops and saves the screenshot of a pending PNG export, if it arrived this frame
+    pub fn receive(&mut self, ctx: &egui::Context) {
+        if self.pending.is_none() {
+            return;
+        }
+        let image = ctx.input(|input| {
+            input.events.iter().find_map(|event| match event {
+                Event::Screenshot { image, .. } => Some(image.clone()),
+                _ => None,
+            })
+        });
+        let (Some(image), Some((path, rect))) = (image, self.pending.take()) else {
+            return;
+        };
+        let chart = image.region(&rect, Some(ctx.pixels_per_point()));
+        let [width, height] = chart.size;
+        let result = image::save_buffer(
+            &path,
+            chart.as_raw(),
+            width as u32,
+            height as u32,
+            image::ExtendedColorType::Rgba8,
+        );
+        self.status = Some(match result {
+            Ok(()) => match &self.status {
+                // The CSV went out with the request
+                Some(Ok(written)) if !written.is_empty() => Ok(format!("{written}, {}", path.display())),
+                _ => Ok(saved(&[path])),
+            },
+            Err(e) => Err(format!("Failed to write {}: {e}", path.display())),
+        });
+    }
+}
+
+fn saved(paths: &[PathBuf]) -> String {
+    paths.iter().map(|path| path.display().to_string()).collect::<Vec<_>>().join(", ")
+}
+
+// e.g. `binance_dogeusdt_chart_20250101_120000`, the extension added per file
+fn default_stem(exchange: ExchangeType, symbol: &str) -> PathBuf {
+    let stamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+    PathBuf::from(format!("{}_{}_chart_{stamp}", exchange.name().to_lowercase(), symbol.to_lowercase()))
+}
+
+// One row per estimated order, front of each queue first
+fn write_levels<'a>(path: &Path, levels: impl IntoIterator<Item = ChartLevel<'a>>) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(out, "side,price,level_qty,reported_orders,position,size,confidence,inference,cluster")?;
+    for level in levels {
+        let side = match level.side {
+            Side::Bid => "bid",
+            Side::Ask => "ask",
+        };
+        let reported = level.queue.reported_count().map(|count| count.to_string()).unwrap_or_default();
+        for (i, order) in level.queue.iter().enumerate() {
+            let cluster = level.clusters.and_then(|labels| labels.get(i)).map(|c| c.to_string()).unwrap_or_default();
+            writeln!(
+                out,
+                "{side},{},{},{reported},{i},{},{},{},{cluster}",
+                level.price,
+                level.queue.total(),
+                order.size(),
+                order.confidence(),
+                order.inference.label(),
+            )?;
+        }
+    }
+    out.flush()
+}
+
+// The bars in the plot's current view as rects in an SVG the size of the plot area, with the
+// title on top and a few price labels along the bottom
+fn svg(chart: &Chart) -> String {
+    let frame = *chart.transform.frame();
+    let (width, height) = (frame.width(), frame.height());
+    let color = |c: Color32| {
+        let [r, g, b, a] = c.to_srgba_unmultiplied();
+        format!("fill=\"rgb({r},{g},{b})\" fill-opacity=\"{:.3}\"", a as f32 / 255.0)
+    };
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width:.0}\" height=\"{height:.0}\" viewBox=\"0 0 {width:.1} {height:.1}\">"
+    );
+    let _ = writeln!(out, "<rect width=\"100%\" height=\"100%\" {}/>", color(chart.background));
+    for bar in chart.bars {
+        let lower = bar.base_offset.unwrap_or(0.0);
+        let half = bar.bar_width / 2.0;
+        let corner = |x: f64, y: f64| chart.transform.position_from_point(&PlotPoint::new(x, y)) - frame.min;
+        let rect = Rect::from_two_pos(
+            corner(bar.argument - half, lower + bar.value).to_pos2(),
+            corner(bar.argument + half, lower).to_pos2(),
+        )
+        .intersect(Rect::from_min_size(egui::Pos2::ZERO, frame.size()));
+        if !rect.is_positive() {
+            continue;
+        }
+        let _ = writeln!(
+            out,
+            "<rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\" {}/>",
+            rect.min.x,
+            rect.min.y,
+            rect.width(),
+            rect.height(),
+            color(bar.fill)
+        );
+    }
+    let text = color(chart.text);
+    let bounds = chart.transform.bounds();
+    let (min, max) = (bounds.min()[0], bounds.max()[0]);
+    for i in 0..SVG_PRICE_TICKS {
+        let x = (i as f32 + 0.5) / SVG_PRICE_TICKS as f32 * width;
+        let price = min + (max - min) * (x / width) as f64;
+        let _ = writeln!(
+            out,
+            "<text x=\"{x:.1}\" y=\"{:.1}\" font-family=\"sans-serif\" font-size=\"11\" text-anchor=\"middle\" {text}>{price:.prec$}</text>",
+            height - 4.0,
+            prec = chart.price_prec
+        );
+    }
+    let _ = writeln!(
+        out,
+        "<text x=\"8\" y=\"16\" font-family=\"sans-serif\" font-size=\"13\" {text}>{} {}</text>",
+        chart.exchange.name(),
+        chart.symbol.to_uppercase()
+    );
+    out.push_str("</svg>\n");
+    out
+}
@@ -0,0 +1,73 @@
+use super::cluster_color;
+use crate::clustering::ClusterSummary;
+use crate::exchanges::Side;
+use eframe::egui;
+use egui::{Color32, Vec2};
+use rust_decimal::prelude::*;
+
+// Legend rows shown before the table scrolls
+const LEGEND_HEIGHT: f32 = 160.0;
+
+// Share of its color a bar outside the highlighted cluster keeps
+pub const DIMMED: f32 = 0.15;
+
+// One side's clusters as the chart colors them
+pub struct SideLegend<'a> {
+    pub side: Side,
+    pub summaries: &'a [ClusterSummary],
+    pub gradient: &'a [Color32],
+}
+
+// Each cluster's color, member count, centroid size, total size and notional (in the quote
+// currency) per side. Clicking a cluster highlights only its members in the chart; clicking it
+// again shows every cluster.
+pub fn show(ui: &mut egui::Ui, sides: [SideLegend; 2], highlight: &mut Option<(Side, usize)>, qty_prec: usize) {
+    // A highlighted cluster that no longer exists, e.g. after lowering the cluster count
+    if let Some((side, label)) = *highlight {
+        if sides.iter().any(|legend| legend.side == side && label >= legend.summaries.len()) {
+            *highlight = None;
+        }
+    }
+    let qty = |qty: Decimal| format!("{:.1$}", qty.to_f64().unwrap_or(0.0), qty_prec);
+    egui::CollapsingHeader::new("Cluster legend").default_open(true).show(ui, |ui| {
+        egui::ScrollArea::vertical().max_height(LEGEND_HEIGHT).show(ui, |ui| {
+            ui.horizontal_top(|ui| {
+                for legend in &sides {
+                    let name = match legend.side {
+                        Side::Bid => "Bids",
+                        Side::Ask => "Asks",
+                    };
+                    egui::Grid::new(("cluster_legend", name)).striped(true).show(ui, |ui| {
+                        ui.strong(name);
+                        ui.label("Orders");
+                        ui.label("Centroid");
+                        ui.label("Total");
+                        ui.label("Notional");
+                        ui.end_row();
+                        let count = legend.summaries.len();
+                        for (label, summary) in legend.summaries.iter().enumerate() {
+                            let key = (legend.side, label);
+                            ui.horizontal(|ui| {
+                                let color = cluster_color(legend.gradient, label, count);
+                                egui::widgets::color_picker::show_color(ui, color, Vec2::splat(12.0));
+                                let selected = *highlight == Some(key);
+                                let response = ui
+                                    .selectable_label(selected, label.to_string())
+                                    .on_hover_text("Highlight only this cluster's orders");
+                                if response.clicked() {
+                                    *highlight = if selected { None } else { Some(key) };
+                                }
+                            });
+                            ui.label(summary.orders.to_string());
+                            ui.label(summary.centroid_size().map_or("-".to_string(), qty));
+                            ui.label(qty(summary.total_qty));
+                            ui.label(format!("{:.2}", summary.notional.to_f64().unwrap_or(0.0)));
+                            ui.end_row();
+                        }
+                    });
+                    ui.add_space(16.0);
+                }
+            });
+        });
+    });
+}
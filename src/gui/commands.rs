@@ -0,0 +1,190 @@
+use super::ViewMode;
+use crate::exchanges::ExchangeType;
+use eframe::egui;
+use egui::{Key, Modifiers};
+
+// Id of the watch row's symbol box, which `Command::FocusSymbol` focuses
+pub const SYMBOL_BOX: &str = "watch_symbol";
+
+// Entries listed in the command palette at once
+const MAX_ENTRIES: usize = 12;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    TogglePause,
+    ToggleClustering,
+    // The exchange in the watch row, cycled through `ExchangeType::ALL`
+    NextExchange,
+    FocusSymbol,
+    NextTab,
+    PreviousTab,
+    OpenPalette,
+    FocusTab(usize),
+    Open(ExchangeType, String),
+    SetView(ViewMode),
+    ToggleEventLog,
+    ToggleFlowStats,
+    ToggleSessionStats,
+    ToggleVolumeProfile,
+    ToggleLog,
+    ToggleThemeEditor,
+}
+
+// Commands for the shortcuts pressed this frame. Single keys are ignored while a text field has
+// the keyboard, so they can still be typed.
+pub fn shortcuts(ctx: &egui::Context) -> Vec<Command> {
+    let typing = ctx.wants_keyboard_input();
+    ctx.input_mut(|input| {
+        let mut commands = Vec::new();
+        if input.consume_key(Modifiers::COMMAND, Key::K) {
+            commands.push(Command::OpenPalette);
+        }
+        if typing {
+            return commands;
+        }
+        let keys = [
+            (Key::Space, Command::TogglePause),
+            (Key::C, Command::ToggleClustering),
+            (Key::E, Command::NextExchange),
+            (Key::Slash, Command::FocusSymbol),
+            (Key::CloseBracket, Command::NextTab),
+            (Key::OpenBracket, Command::PreviousTab),
+        ];
+        for (key, command) in keys {
+            if input.consume_key(Modifiers::NONE, key) {
+                commands.push(command);
+            }
+        }
+        commands
+    })
+}
+
+// A palette entry: what it shows and what it runs
+pub struct Entry {
+    pub label: String,
+    // Shortcut shown next to the label
+    pub hint: &'static str,
+    pub command: Command,
+}
+
+impl Entry {
+    pub fn new(label: impl Into<String>, hint: &'static str, command: Command) -> Self {
+        Self {
+            label: label.into(),
+            hint,
+            command,
+        }
+    }
+}
+
+// Ctrl+K search over the open tabs, toggles and views, plus "Open <symbol> on <exchange>" for
+// whatever was typed
+#[derive(Default)]
+pub struct CommandPalette {
+    open: bool,
+    query: String,
+    selected: usize,
+}
+
+impl CommandPalette {
+    pub fn open(&mut self) {
+        self.open = true;
+        self.query.clear();
+        self.selected = 0;
+    }
+
+    // The command picked this frame, if any; `watched` is the exchange the watch row is on,
+    // listed first for new subscriptions
+    pub fn show(&mut self, ctx: &egui::Context, entries: Vec<Entry>, watched: ExchangeType) -> Option<Command> {
+        if !self.open {
+            return None;
+        }
+        let words: Vec<String> = self.query.split_whitespace().map(str::to_lowercase).collect();
+        let mut matches: Vec<Entry> = entries
+            .into_iter()
+            .filter(|entry| {
+                let label = entry.label.to_lowercase();
+                words.iter().all(|word| label.contains(word.as_str()))
+            })
+            .collect();
+        matches.extend(open_entries(&words, watched));
+        matches.truncate(MAX_ENTRIES);
+
+        let (up, down, enter, escape) = ctx.input(|input| {
+            (
+                input.key_pressed(Key::ArrowUp),
+                input.key_pressed(Key::ArrowDown),
+                input.key_pressed(Key::Enter),
+                input.key_pressed(Key::Escape),
+            )
+        });
+        if down {
+            self.selected += 1;
+        }
+        if up {
+            self.selected = self.selected.saturating_sub(1);
+        }
+        self.selected = self.selected.min(matches.len().saturating_sub(1));
+
+        let mut picked = enter.then(|| matches.get(self.selected).map(|entry| entry.command.clone())).flatten();
+        egui::Window::new("Command palette")
+            .title_bar(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, [0.0, 60.0])
+            .show(ctx, |ui| {
+                let edit = ui.add(
+                    egui::TextEdit::singleline(&mut self.query)
+                        .hint_text("Tab, command, or a symbol to open")
+                        .desired_width(360.0),
+                );
+                edit.request_focus();
+                if edit.changed() {
+                    self.selected = 0;
+                }
+                ui.separator();
+                for (i, entry) in matches.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        if ui.selectable_label(i == self.selected, &entry.label).clicked() {
+                            picked = Some(entry.command.clone());
+                        }
+                        if !entry.hint.is_empty() {
+                            ui.weak(entry.hint);
+                        }
+                    });
+                }
+                if matches.is_empty() {
+                    ui.weak("No match");
+                }
+            });
+        if picked.is_some() || escape {
+            self.open = false;
+        }
+        picked
+    }
+}
+
+// "Open <symbol> on <exchange>" for the typed words: words naming an exchange pick the venues,
+// the rest make up the symbol; with no venue named, every one is offered, the watched first
+fn open_entries(words: &[String], watched: ExchangeType) -> Vec<Entry> {
+    // Three letters at least, so a short symbol is not taken for a venue
+    let names = |exchange: ExchangeType, word: &str| word.len() >= 3 && exchange.name().to_lowercase().starts_with(word);
+    let (venues, symbol): (Vec<&String>, Vec<&String>) =
+        words.iter().partition(|word| ExchangeType::ALL.iter().any(|&e| names(e, word)));
+    let symbol = symbol.iter().map(|word| word.as_str()).collect::<String>();
+    if symbol.is_empty() {
+        return Vec::new();
+    }
+    let mut exchanges: Vec<ExchangeType> = ExchangeType::ALL
+        .iter()
+        .copied()
+        .filter(|&e| venues.is_empty() || venues.iter().any(|venue| names(e, venue)))
+        .collect();
+    exchanges.sort_by_key(|&exchange| exchange != watched);
+    exchanges
+        .into_iter()
+        .map(|exchange| {
+            let label = format!("Open {} on {}", symbol.to_uppercase(), exchange.name());
+            Entry::new(label, "", Command::Open(exchange, symbol.clone()))
+        })
+        .collect()
+}
@@ -0,0 +1,55 @@
+use super::price_step;
+use crate::history::BookHistory;
+use eframe::egui;
+use egui::Color32;
+use egui_plot::{Bar, BarChart, Plot, VLine};
+use rust_decimal::prelude::*;
+use std::time::Duration;
+
+const ADDED: Color32 = Color32::from_rgb(40, 180, 70);
+const PULLED: Color32 = Color32::from_rgb(210, 50, 50);
+
+// Net change in resting size per level over the last `lookback_secs` of the book history (up to
+// `until` while paused): size added above the axis in green, size pulled or traded away below it
+// in red. Shows where liquidity moved, which the absolute bars hide.
+pub fn show(ui: &mut egui::Ui, history: &BookHistory, until: Option<u64>, lookback_secs: &mut u64, price_prec: usize) {
+    let max_lookback = history.settings().retention.as_secs().max(1);
+    let deltas = history.deltas(until, Duration::from_secs(*lookback_secs));
+    ui.horizontal(|ui| {
+        ui.label("Change over (s):");
+        ui.add(egui::Slider::new(lookback_secs, 1..=max_lookback));
+        if let Some((span, _)) = &deltas {
+            ui.label(format!("{:.1}s of history", *span as f64 / 1000.0));
+        }
+    });
+    let Some((_, deltas)) = deltas else {
+        ui.label("Collecting book history...");
+        return;
+    };
+
+    let step = price_step(deltas.iter().map(|delta| delta.price));
+    let bars: Vec<Bar> = deltas
+        .iter()
+        .filter_map(|delta| {
+            let change = delta.change.to_f64()?;
+            let color = if change > 0.0 { ADDED } else { PULLED };
+            Some(Bar::new(delta.price.to_f64()?, change).width(step * 0.9).fill(color))
+        })
+        .collect();
+    let mid = match until {
+        Some(until) => history.frame_at(until).and_then(|frame| frame.mid),
+        None => history.latest().and_then(|frame| frame.mid),
+    };
+    let mid = mid.and_then(|mid| mid.to_f64());
+
+    Plot::new("delta_chart")
+        .y_axis_label("net size change")
+        .show_x(false)
+        .x_axis_formatter(move |mark, _| format!("{:.1$}", mark.value, price_prec))
+        .show(ui, |plot_ui| {
+            plot_ui.bar_chart(BarChart::new("delta", bars));
+            if let Some(mid) = mid {
+                plot_ui.vline(VLine::new("mid", mid).color(Color32::GRAY).width(1.0));
+            }
+        });
+}
@@ -0,0 +1,89 @@
+use crate::exchanges::Side;
+use crate::orderbook::{FillEstimate, OrderBook, OrderQueue};
+use eframe::egui;
+use egui::Color32;
+use egui_plot::{Line, Plot, PlotPoints, VLine};
+use rust_decimal::prelude::*;
+
+pub const DEPTH_LEVELS: usize = 100;
+
+// Cumulative resting size as a step line, best price outwards
+fn cumulative_steps<'a>(levels: impl Iterator<Item = (&'a Decimal, &'a OrderQueue)>) -> Vec<[f64; 2]> {
+    let mut points = vec![];
+    let mut total = 0.0;
+    for (price, queue) in levels.take(DEPTH_LEVELS) {
+        let price = price.to_f64().unwrap_or(0.0);
+        points.push([price, total]);
+        total += queue.total().to_f64().unwrap_or(0.0);
+        points.push([price, total]);
+    }
+    points
+}
+
+fn describe(label: &str, fill: Option<FillEstimate>, size: Decimal, price_prec: usize) -> String {
+    let Some(f) = fill else {
+        return format!("{label}: -");
+    };
+    let partial = if f.filled < size {
+        format!(", only {} fillable", f.filled)
+    } else {
+        String::new()
+    };
+    format!(
+        "{label}: avg {:.prec$} through {:.prec$} ({} levels, {:.2} bps){partial}",
+        f.avg_price.to_f64().unwrap_or(0.0),
+        f.worst_price.to_f64().unwrap_or(0.0),
+        f.levels,
+        f.slippage_bps.to_f64().unwrap_or(0.0),
+        prec = price_prec,
+    )
+}
+
+// Classic depth chart with a slippage calculator for an order of `order_size`, bids and asks
+// drawn in the palette's (bid, ask) colors. `linked` shares the price axis with the volume profile.
+pub fn show(
+    ui: &mut egui::Ui,
+    book: &OrderBook,
+    order_size: &mut f64,
+    price_prec: usize,
+    colors: (Color32, Color32),
+    linked: bool,
+) {
+    let size = Decimal::from_f64(*order_size).unwrap_or(Decimal::ZERO);
+    let buy = book.estimate_fill(Side::Bid, size);
+    let sell = book.estimate_fill(Side::Ask, size);
+
+    ui.horizontal(|ui| {
+        ui.label("Order size:");
+        ui.add(egui::DragValue::new(order_size).range(0.0..=f64::MAX).speed(1.0));
+    });
+    ui.label(describe("Buy", buy, size, price_prec));
+    ui.label(describe("Sell", sell, size, price_prec));
+
+    let bids = cumulative_steps(book.view_bids().iter().rev());
+    let asks = cumulative_steps(book.view_asks().iter());
+
+    let mut plot = Plot::new("depth_chart").x_axis_label("price").y_axis_label("cumulative size");
+    if linked {
+        plot = plot.link_axis(super::volume_profile::PRICE_AXIS, [true, false]);
+    }
+    plot.show(ui, |plot_ui| {
+        plot_ui.line(
+            Line::new("bids", PlotPoints::from(bids))
+                .color(colors.0)
+                .fill(0.0),
+        );
+        plot_ui.line(
+            Line::new("asks", PlotPoints::from(asks))
+                .color(colors.1)
+                .fill(0.0),
+        );
+        // How far the order walks the book on each side
+        if let Some(f) = buy {
+            plot_ui.vline(VLine::new("buy", f.worst_price.to_f64().unwrap_or(0.0)).color(Color32::GOLD));
+        }
+        if let Some(f) = sell {
+            plot_ui.vline(VLine::new("sell", f.worst_price.to_f64().unwrap_or(0.0)).color(Color32::GOLD));
+        }
+    });
+}
@@ -0,0 +1,114 @@
+use super::gradient_color;
+use crate::history::{BookFrame, BookHistory};
+use eframe::egui;
+use egui::{Color32, ColorImage, TextureHandle, TextureOptions};
+use egui_plot::{Line, Plot, PlotImage, PlotPoint, PlotPoints};
+use rust_decimal::prelude::*;
+
+// Vertical resolution of the heatmap, in price buckets
+const ROWS: usize = 200;
+
+// Dark background through blue and orange to white for the largest resting size
+const HEAT_GRADIENT: [Color32; 4] = [
+    Color32::from_rgb(10, 10, 30),
+    Color32::from_rgb(30, 90, 200),
+    Color32::from_rgb(250, 150, 30),
+    Color32::from_rgb(255, 255, 230),
+];
+
+// Price x time rendering of the book history: each column is one sampled frame, each row a
+// price bucket, and the color is the resting size in that bucket.
+#[derive(Default)]
+pub struct DepthHeatmap {
+    texture: Option<TextureHandle>,
+    // Timestamp of the newest frame in the current texture, to skip redundant rebuilds
+    rendered_at: Option<u64>,
+    price_range: (f64, f64),
+    // Seconds from the newest frame in the texture back to the oldest
+    span: f64,
+}
+
+impl DepthHeatmap {
+    pub fn clear(&mut self) {
+        self.texture = None;
+        self.rendered_at = None;
+    }
+
+    // Frames up to `until` (all of them when None), so a paused view stays put as history grows
+    pub fn show(&mut self, ui: &mut egui::Ui, history: &BookHistory, until: Option<u64>) {
+        let shown = || history.mids().take_while(move |&(ts, _)| until.is_none_or(|until| ts <= until));
+        let Some((end, _)) = shown().last() else {
+            ui.label("Collecting book history...");
+            return;
+        };
+        if self.rendered_at != Some(end) {
+            if let Some(latest) = history.frame_at(end) {
+                self.rebuild(ui.ctx(), history, &latest);
+            }
+        }
+        let Some(texture) = &self.texture else {
+            return;
+        };
+
+        let seconds = |timestamp: u64| (timestamp as f64 - end as f64) / 1000.0;
+        let start = -self.span;
+        let (lo, hi) = self.price_range;
+        let mid_line: PlotPoints = shown()
+            .filter(|&(timestamp, _)| seconds(timestamp) >= start)
+            .filter_map(|(timestamp, mid)| Some([seconds(timestamp), mid?.to_f64()?]))
+            .collect();
+
+        Plot::new("depth_heatmap")
+            .x_axis_label("seconds")
+            .y_axis_label("price")
+            .show(ui, |plot_ui| {
+                plot_ui.image(PlotImage::new(
+                    "depth",
+                    texture.id(),
+                    PlotPoint::new(start / 2.0, (lo + hi) / 2.0),
+                    [(-start).max(1e-3) as f32, (hi - lo) as f32],
+                ));
+                plot_ui.line(Line::new("mid", mid_line).color(Color32::WHITE));
+            });
+    }
+
+    fn rebuild(&mut self, ctx: &egui::Context, history: &BookHistory, latest: &BookFrame) {
+        // Price window spanned by the latest frame; older frames are clipped to it
+        let lo = latest.bids.last().or(latest.asks.first()).map(|l| l.price);
+        let hi = latest.asks.last().or(latest.bids.first()).map(|l| l.price);
+        let (Some(lo), Some(hi)) = (lo.and_then(|p| p.to_f64()), hi.and_then(|p| p.to_f64())) else {
+            return;
+        };
+        if hi <= lo {
+            return;
+        }
+
+        let width = history.mids().take_while(|&(timestamp, _)| timestamp <= latest.timestamp).count();
+        let mut cells = vec![0.0f64; width * ROWS];
+        for (x, frame) in history.frames().take(width).enumerate() {
+            for level in frame.bids.iter().chain(frame.asks.iter()) {
+                let price = level.price.to_f64().unwrap_or(0.0);
+                if price < lo || price > hi {
+                    continue;
+                }
+                let row = (((price - lo) / (hi - lo)) * (ROWS - 1) as f64).round() as usize;
+                // Row 0 is the top of the image
+                cells[(ROWS - 1 - row) * width + x] += level.qty.to_f64().unwrap_or(0.0);
+            }
+        }
+
+        // Log scale so a single wall doesn't wash out the rest of the book
+        let max = cells.iter().cloned().fold(0.0, f64::max).ln_1p().max(f64::EPSILON);
+        let pixels = cells.iter().map(|&q| gradient_color(&HEAT_GRADIENT, (q.ln_1p() / max) as f32)).collect();
+        let image = ColorImage::new([width, ROWS], pixels);
+
+        match &mut self.texture {
+            Some(texture) => texture.set(image, TextureOptions::NEAREST),
+            None => self.texture = Some(ctx.load_texture("depth_heatmap", image, TextureOptions::NEAREST)),
+        }
+        self.rendered_at = Some(latest.timestamp);
+        self.price_range = (lo, hi);
+        let oldest = history.oldest_timestamp().unwrap_or(latest.timestamp);
+        self.span = latest.timestamp.saturating_sub(oldest) as f64 / 1000.0;
+    }
+}
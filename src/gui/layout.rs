@@ -0,0 +1,215 @@
+use eframe::egui;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+#[cfg(not(target_arch = "wasm32"))]
+use winit::{application::ApplicationHandler, event_loop::ActiveEventLoop};
+
+pub const STORAGE_KEY: &str = "monitor_layouts";
+
+// Resizable panels whose width or height is saved with the layout
+const PANEL_IDS: [&str; 5] = ["alert_log", "event_log", "flow_stats", "session_stats", "log_viewer"];
+
+// Physical width and height, and scale in hundredths, of every connected monitor, sorted; filled in
+// by `MonitorWatch` and left empty where the platform can't list monitors (the browser)
+static CONNECTED: Lazy<Mutex<Vec<(u32, u32, u32)>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+// Which of the optional panels are open
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct OpenPanels {
+    pub event_log: bool,
+    pub flow_stats: bool,
+    pub session_stats: bool,
+    pub log_viewer: bool,
+    pub volume_profile: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WindowLayout {
+    pub pos: [f32; 2],
+    pub size: [f32; 2],
+    pub maximized: bool,
+    // Layouts saved before panels were tracked restore only the window geometry
+    #[serde(default)]
+    pub panels: Option<OpenPanels>,
+    #[serde(default)]
+    pub panel_sizes: BTreeMap<String, [f32; 2]>,
+}
+
+// Window and panel layouts keyed by the display configuration they were saved on, so a layout
+// tuned for a large external monitor doesn't get restored onto a laptop panel.
+//
+// The configuration is the set of connected monitors, sorted by size and scale, so a laptop panel
+// on its own and the same panel next to an external display get separate layouts. The saved layout
+// is restored at startup and when a monitor is plugged in or removed, never when the window is
+// only dragged to another display.
+#[derive(Serialize, Deserialize, Default)]
+pub struct MonitorLayouts {
+    layouts: HashMap<String, WindowLayout>,
+    #[serde(skip)]
+    current_key: Option<String>,
+    #[serde(skip)]
+    current: Option<WindowLayout>,
+}
+
+impl MonitorLayouts {
+    pub fn load(storage: Option<&dyn eframe::Storage>) -> Self {
+        storage
+            .and_then(|s| eframe::get_value(s, STORAGE_KEY))
+            .unwrap_or_default()
+    }
+
+    pub fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        if let (Some(key), Some(layout)) = (self.current_key.clone(), self.current.clone()) {
+            self.layouts.insert(key, layout);
+        }
+        eframe::set_value(storage, STORAGE_KEY, self);
+    }
+
+    // Keeps track of the live window geometry and `panels`, and restores the saved layout when the
+    // set of connected monitors changes, returning the panels it had open for the caller to apply
+    pub fn track(&mut self, ctx: &egui::Context, panels: OpenPanels) -> Option<OpenPanels> {
+        let key = {
+            let connected = CONNECTED.lock().unwrap();
+            if connected.is_empty() {
+                return None;
+            }
+            connected
+                .iter()
+                .map(|&(width, height, scale)| format!("{width}x{height}@{:.2}", scale as f32 / 100.0))
+                .collect::<Vec<_>>()
+                .join("+")
+        };
+
+        if self.current_key.as_deref() != Some(key.as_str()) {
+            if let (Some(old_key), Some(layout)) = (self.current_key.take(), self.current.take()) {
+                self.layouts.insert(old_key, layout);
+            }
+            self.current_key = Some(key.clone());
+            let layout = self.layouts.get(&key).cloned()?;
+            Self::restore(ctx, &layout);
+            let restored = layout.panels;
+            self.current = Some(layout);
+            return restored;
+        }
+
+        let (outer_rect, inner_rect, maximized) = ctx.input(|i| {
+            let vp = i.viewport();
+            (vp.outer_rect, vp.inner_rect, vp.maximized.unwrap_or(false))
+        });
+        if let (Some(outer), Some(inner)) = (outer_rect, inner_rect) {
+            let prev = self.current.take();
+            // Keep the last un-maximized geometry so restoring doesn't lose it
+            let (pos, size) = match prev {
+                Some(prev) if maximized => (prev.pos, prev.size),
+                _ => ([outer.min.x, outer.min.y], [inner.width(), inner.height()]),
+            };
+            let panel_sizes = PANEL_IDS
+                .iter()
+                .filter_map(|&id| {
+                    let state = egui::containers::panel::PanelState::load(ctx, egui::Id::new(id))?;
+                    Some((id.to_string(), [state.rect.width(), state.rect.height()]))
+                })
+                .collect();
+            self.current = Some(WindowLayout { pos, size, maximized, panels: Some(panels), panel_sizes });
+        }
+        None
+    }
+
+    fn restore(ctx: &egui::Context, layout: &WindowLayout) {
+        ctx.send_viewport_cmd(egui::ViewportCommand::Maximized(false));
+        ctx.send_viewport_cmd(egui::ViewportCommand::OuterPosition(egui::pos2(layout.pos[0], layout.pos[1])));
+        ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(egui::vec2(layout.size[0], layout.size[1])));
+        if layout.maximized {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Maximized(true));
+        }
+        // Panels read their size back from memory, so only the width or height matters here
+        for (id, &[width, height]) in &layout.panel_sizes {
+            let rect = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(width, height));
+            let state = egui::containers::panel::PanelState { rect };
+            ctx.data_mut(|d| d.insert_persisted(egui::Id::new(id.as_str()), state));
+        }
+    }
+}
+
+// Runs eframe's application on an event loop of our own, so the connected monitors can be listed;
+// eframe's viewport info only covers the monitor the window is on
+#[cfg(not(target_arch = "wasm32"))]
+pub struct MonitorWatch<A> {
+    app: A,
+    polled: Option<std::time::Instant>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<A> MonitorWatch<A> {
+    pub fn new(app: A) -> Self {
+        Self { app, polled: None }
+    }
+
+    // Listing monitors is a round trip to the display server, so at most once a second
+    fn poll(&mut self, event_loop: &ActiveEventLoop) {
+        if self.polled.is_some_and(|at| at.elapsed() < std::time::Duration::from_secs(1)) {
+            return;
+        }
+        self.polled = Some(std::time::Instant::now());
+        let mut monitors: Vec<_> = event_loop
+            .available_monitors()
+            .map(|m| (m.size().width, m.size().height, (m.scale_factor() * 100.0).round() as u32))
+            .collect();
+        monitors.sort_unstable();
+        *CONNECTED.lock().unwrap() = monitors;
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<A: ApplicationHandler<eframe::UserEvent>> ApplicationHandler<eframe::UserEvent> for MonitorWatch<A> {
+    fn new_events(&mut self, event_loop: &ActiveEventLoop, cause: winit::event::StartCause) {
+        self.app.new_events(event_loop, cause);
+    }
+
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        // Listed before the window is created so its first frame already has them
+        self.poll(event_loop);
+        self.app.resumed(event_loop);
+    }
+
+    fn user_event(&mut self, event_loop: &ActiveEventLoop, event: eframe::UserEvent) {
+        self.app.user_event(event_loop, event);
+    }
+
+    fn window_event(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        window_id: winit::window::WindowId,
+        event: winit::event::WindowEvent,
+    ) {
+        self.app.window_event(event_loop, window_id, event);
+    }
+
+    fn device_event(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        device_id: winit::event::DeviceId,
+        event: winit::event::DeviceEvent,
+    ) {
+        self.app.device_event(event_loop, device_id, event);
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        self.poll(event_loop);
+        self.app.about_to_wait(event_loop);
+    }
+
+    fn suspended(&mut self, event_loop: &ActiveEventLoop) {
+        self.app.suspended(event_loop);
+    }
+
+    fn exiting(&mut self, event_loop: &ActiveEventLoop) {
+        self.app.exiting(event_loop);
+    }
+
+    fn memory_warning(&mut self, event_loop: &ActiveEventLoop) {
+        self.app.memory_warning(event_loop);
+    }
+}
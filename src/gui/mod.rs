@@ -0,0 +1,1606 @@
+mod arbitrage;
+mod bar_axes;
+mod chart_export;
+mod cluster_legend;
+mod commands;
+mod delta_chart;
+mod depth_chart;
+mod heatmap;
+mod layout;
+mod playback;
+mod price_chart;
+mod session;
+mod tab;
+mod theme;
+mod tooltip;
+mod volume_profile;
+
+#[cfg(feature = "binance")]
+use crate::exchanges::binance::BinanceMarket;
+use crate::exchanges::{self, ExchangeSettings, ExchangeType, InstrumentInfo, Side, SizeUnit};
+use crate::alerts::{AlertKind, AlertSettings, AlertThreshold};
+use crate::anomaly::AnomalySettings;
+use crate::clustering::{ClusterInput, ClusterParams, Clusterer, ClusteringAlgorithm, KMeansInit, AUTO_K_RANGE};
+use crate::config::{Config, DisplayConfig, Theme};
+use crate::flow;
+use crate::history::HistorySettings;
+use crate::logging::{self, LogRecord};
+use crate::orderbook::{InferredAction, OrderBook, OrderQueue};
+use crate::palette::Palette;
+use crate::session::duration_label;
+use crate::volume_profile::DEFAULT_PROFILE_WINDOW_SECS;
+use eframe::egui;
+use egui::{Align2, Color32};
+use egui_plot::{Bar, BarChart, Line, Plot, PlotPoint, Text};
+use rust_decimal::prelude::*;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use chart_export::{Chart, ChartExport, ChartLevel};
+use commands::{Command, CommandPalette, Entry};
+use session::{SavedTab, UiSession};
+use tab::BookTab;
+
+// Color for `cluster` out of `num_clusters`, interpolated linearly along the palette's gradient
+// stops, so any number of clusters maps onto them
+fn cluster_color(stops: &[Color32], cluster: usize, num_clusters: usize) -> Color32 {
+    if num_clusters <= 1 {
+        return gradient_color(stops, 0.0);
+    }
+    gradient_color(stops, cluster.min(num_clusters - 1) as f32 / (num_clusters - 1) as f32)
+}
+
+// Fades `color` for estimated orders the estimator is less sure of
+fn confidence_color(color: Color32, confidence: f64) -> Color32 {
+    color.gamma_multiply((0.3 + 0.7 * confidence) as f32)
+}
+
+// Color at `t` in [0, 1] along the gradient stops
+fn gradient_color(stops: &[Color32], t: f32) -> Color32 {
+    if stops.is_empty() {
+        return Color32::GRAY;
+    }
+    if stops.len() == 1 {
+        return stops[0];
+    }
+    let scaled = t.clamp(0.0, 1.0) * (stops.len() - 1) as f32;
+    let idx = (scaled.floor() as usize).min(stops.len() - 2);
+    let frac = scaled - idx as f32;
+    let (a, b) = (stops[idx], stops[idx + 1]);
+    let lerp = |x: u8, y: u8| (x as f32 + (y as f32 - x as f32) * frac).round() as u8;
+    Color32::from_rgb(lerp(a.r(), b.r()), lerp(a.g(), b.g()), lerp(a.b(), b.b()))
+}
+
+// Smallest gap between two of the prices, the width a level gets in the order bar chart
+fn price_step(prices: impl Iterator<Item = Decimal>) -> f64 {
+    let mut prices: Vec<Decimal> = prices.collect();
+    prices.sort_unstable();
+    prices
+        .windows(2)
+        .map(|pair| pair[1] - pair[0])
+        .filter(|gap| *gap > Decimal::ZERO)
+        .min()
+        .and_then(|gap| gap.to_f64())
+        .unwrap_or(1.0)
+}
+
+const GROUP_TICK_OPTIONS: [u32; 7] = [1, 2, 5, 10, 25, 50, 100];
+
+const ORDERS_HINT: &str = "Orders at the level: the venue's count where it reports one, otherwise ~ the estimate";
+
+// The venue's order count for a level where known, else the estimated queue length
+fn orders_label(queue: &OrderQueue) -> String {
+    match queue.reported_count() {
+        Some(count) => count.to_string(),
+        None => format!("~{}", queue.len()),
+    }
+}
+
+const QUEUE_VALUE_HINT: &str = "Expected edge, bps from the mid, of an order joining the back of the level now: \
+     the chance the size ahead trades within a minute at the touch's traded rate times the distance from the mid. \
+     Adverse selection is not modelled, so this is an upper bound";
+
+// Queue value of joining a level, with its inputs on hover
+fn queue_value_cell(
+    ui: &mut egui::Ui,
+    book: &OrderBook,
+    side: Side,
+    price: Decimal,
+    stats: &flow::FlowStats,
+    prec: usize,
+) {
+    let Some(value) = flow::queue_value(book, side, price, stats) else {
+        ui.label("-");
+        return;
+    };
+    let time_to_fill = value
+        .time_to_fill
+        .map_or("never at the current rate".to_string(), |secs| format!("~{}", duration_label(secs as u64)));
+    ui.label(format!("{:+.2}", value.value_bps())).on_hover_text(format!(
+        "{:.prec$} ahead, fills in {time_to_fill}\nFill chance within {}s: {:.1}%\nEdge if filled: {:+.2} bps",
+        value.qty_ahead.to_f64().unwrap_or(0.0),
+        flow::QUEUE_VALUE_HORIZON_SECS,
+        value.fill_probability * 100.0,
+        value.edge_bps
+    ));
+}
+
+// Clusterers and input buffers kept between frames, so refitting the chart every frame neither
+// clones the book nor allocates, and k-means and the mixture start from the previous fit
+struct ClusterState {
+    algorithm: ClusteringAlgorithm,
+    params: ClusterParams,
+    // Units of the inputs' sizes
+    unit: SizeUnit,
+    bids: Box<dyn Clusterer>,
+    asks: Box<dyn Clusterer>,
+    // Fitted over both sides instead with `ClusterParams::shared_sides`, asks first
+    shared: Box<dyn Clusterer>,
+    bid_input: ClusterInput,
+    ask_input: ClusterInput,
+    shared_input: ClusterInput,
+}
+
+impl ClusterState {
+    fn new(algorithm: ClusteringAlgorithm, params: ClusterParams) -> Self {
+        Self {
+            algorithm,
+            params,
+            unit: SizeUnit::Native,
+            bids: algorithm.build(&params),
+            asks: algorithm.build(&params),
+            shared: algorithm.build(&params),
+            bid_input: ClusterInput::new(),
+            ask_input: ClusterInput::new(),
+            shared_input: ClusterInput::new(),
+        }
+    }
+
+    // Starts over with fresh clusterers when the algorithm, its settings or the size units
+    // changed, since centroids and band edges in one unit mean nothing in another
+    fn configure(&mut self, algorithm: ClusteringAlgorithm, params: ClusterParams, unit: SizeUnit) {
+        if self.algorithm != algorithm || self.params != params || self.unit != unit {
+            self.algorithm = algorithm;
+            self.params = params;
+            self.unit = unit;
+            self.bids = algorithm.build(&params);
+            self.asks = algorithm.build(&params);
+            self.shared = algorithm.build(&params);
+        }
+    }
+
+    // Refits to the top `levels` of each side of `book`, sizes converted from `contract` units
+    fn fit(&mut self, book: &OrderBook, levels: usize, contract: InstrumentInfo) {
+        let size = |price, qty| contract.size_in(self.unit, qty, price);
+        self.ask_input.fill_with(book.view_asks().iter().take(levels), size);
+        self.bid_input.fill_with(book.view_bids().iter().rev().take(levels), size);
+        if self.params.shared_sides {
+            self.shared_input.clear();
+            self.shared_input.append(&self.ask_input);
+            self.shared_input.append(&self.bid_input);
+            self.shared.fit(&self.shared_input);
+        } else {
+            self.asks.fit(&self.ask_input);
+            self.bids.fit(&self.bid_input);
+        }
+    }
+
+    // The shown levels of `side`, best first
+    fn input(&self, side: Side) -> &ClusterInput {
+        match side {
+            Side::Bid => &self.bid_input,
+            Side::Ask => &self.ask_input,
+        }
+    }
+
+    // Labels of `side`'s orders from the last fit, in the order of its input
+    fn labels(&self, side: Side) -> &[usize] {
+        if !self.params.shared_sides {
+            return match side {
+                Side::Bid => self.bids.labels(),
+                Side::Ask => self.asks.labels(),
+            };
+        }
+        let labels = self.shared.labels();
+        let asks = self.ask_input.len().min(labels.len());
+        match side {
+            Side::Bid => &labels[asks..],
+            Side::Ask => &labels[..asks],
+        }
+    }
+
+    fn num_clusters(&self, side: Side) -> usize {
+        match (self.params.shared_sides, side) {
+            (true, _) => self.shared.num_clusters(),
+            (false, Side::Bid) => self.bids.num_clusters(),
+            (false, Side::Ask) => self.asks.num_clusters(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+enum ViewMode {
+    Bars,
+    Heatmap,
+    Depth,
+    Arbitrage,
+    Delta,
+}
+
+// The previous session's tabs and UI settings are restored; the startup subscription is
+// opened next to them if `explicit` (given on the command line or in the config file),
+// otherwise only when there is nothing to restore
+#[cfg(not(target_arch = "wasm32"))]
+pub fn run(
+    symbol: String,
+    exchange: ExchangeType,
+    settings: ExchangeSettings,
+    config: Config,
+    alerts: AlertSettings,
+    explicit: bool,
+) -> eframe::Result {
+    let options = eframe::NativeOptions {
+        // Window geometry is persisted per monitor configuration by `layout::MonitorLayouts`
+        persist_window: false,
+        ..Default::default()
+    };
+    let event_loop = winit::event_loop::EventLoop::<eframe::UserEvent>::with_user_event().build()?;
+    let app = eframe::create_native(
+        "Multi-Exchange Order Book Visualizer",
+        options,
+        Box::new(move |cc| Ok(Box::new(MyApp::new(cc, symbol, exchange, settings, config, alerts, explicit)))),
+        &event_loop,
+    );
+    event_loop.run_app(&mut layout::MonitorWatch::new(app))?;
+    Ok(())
+}
+
+// Starts the visualizer in the browser, drawing into the canvas with id `canvas_id`
+#[cfg(target_arch = "wasm32")]
+pub async fn run_web(
+    canvas_id: &str,
+    symbol: String,
+    exchange: ExchangeType,
+    settings: ExchangeSettings,
+    config: Config,
+    alerts: AlertSettings,
+) -> Result<(), wasm_bindgen::JsValue> {
+    use wasm_bindgen::JsCast;
+    let canvas = web_sys::window()
+        .and_then(|window| window.document())
+        .and_then(|document| document.get_element_by_id(canvas_id))
+        .and_then(|element| element.dyn_into::<web_sys::HtmlCanvasElement>().ok())
+        .ok_or_else(|| wasm_bindgen::JsValue::from_str(&format!("no canvas with id {canvas_id}")))?;
+    eframe::WebRunner::new()
+        .start(
+            canvas,
+            eframe::WebOptions::default(),
+            Box::new(move |cc| Ok(Box::new(MyApp::new(cc, symbol, exchange, settings, config, alerts, false)))),
+        )
+        .await
+}
+
+struct MyApp {
+    // Feed workers of all tabs run on this runtime; in the browser they run on its event loop
+    #[cfg(not(target_arch = "wasm32"))]
+    runtime: tokio::runtime::Runtime,
+    ctx: egui::Context,
+    // One tab per (exchange, market, symbol) subscription, in tab bar order
+    tabs: Vec<BookTab>,
+    active: usize,
+    // Subscription to open from the watch row
+    watch_exchange: ExchangeType,
+    watch_symbol: String,
+    watch_settings: ExchangeSettings,
+    cluster_mode: bool,
+    view_mode: ViewMode,
+    bar_axes: bar_axes::BarAxes,
+    chart_export: ChartExport,
+    mid_lookback_secs: u64,
+    // Seconds the level delta view compares the book across
+    delta_secs: u64,
+    slippage_size: f64,
+    cluster_algorithm: ClusteringAlgorithm,
+    cluster_params: ClusterParams,
+    clusters: ClusterState,
+    // Cluster picked in the legend, whose orders alone keep their colors in the chart
+    cluster_highlight: Option<(Side, usize)>,
+    arb: arbitrage::ArbView,
+    // Large order alert settings, applied to every tab
+    alerts: AlertSettings,
+    // Message rate, spread and depth anomaly settings, applied to every tab
+    anomalies: AnomalySettings,
+    // Panel listing the estimator's inferred events for the active tab
+    event_log: bool,
+    // Panel with rolling order-flow statistics for the active tab
+    flow_stats: bool,
+    // Panel with the active tab's uptime, resyncs and update counts
+    session_stats: bool,
+    // Traded volume by price under the order bar and depth charts, and the window it covers
+    volume_profile: bool,
+    profile_window_secs: u64,
+    // Panel with the application's recent log records, and the most verbose level it shows
+    log_viewer: bool,
+    log_level: tracing::Level,
+    // Book table rows and chart levels per side
+    display: DisplayConfig,
+    theme: Theme,
+    // Units sizes are shown and clustered in
+    size_unit: SizeUnit,
+    palette: Palette,
+    // The config file's palette, which the theme editor resets to
+    configured_palette: Palette,
+    theme_editor: bool,
+    command_palette: CommandPalette,
+    // Book sampling for the heatmap and the mid price chart, the same for every tab
+    history: HistorySettings,
+    layouts: layout::MonitorLayouts,
+}
+
+impl MyApp {
+    fn new(
+        cc: &eframe::CreationContext<'_>,
+        symbol: String,
+        initial_exchange: ExchangeType,
+        settings: ExchangeSettings,
+        config: Config,
+        alerts: AlertSettings,
+        explicit: bool,
+    ) -> Self {
+        let palette = config.display.palette.palette().unwrap_or_default();
+        let mut app = Self {
+            #[cfg(not(target_arch = "wasm32"))]
+            runtime: tokio::runtime::Runtime::new().unwrap(),
+            ctx: cc.egui_ctx.clone(),
+            tabs: Vec::new(),
+            active: 0,
+            watch_exchange: initial_exchange,
+            watch_symbol: symbol,
+            watch_settings: settings,
+            cluster_mode: config.cluster.enabled,
+            view_mode: ViewMode::Bars,
+            bar_axes: bar_axes::BarAxes::default(),
+            chart_export: ChartExport::default(),
+            mid_lookback_secs: 60,
+            delta_secs: 10,
+            slippage_size: 1000.0,
+            cluster_algorithm: config.cluster.algorithm,
+            cluster_params: config.cluster.params(),
+            clusters: ClusterState::new(config.cluster.algorithm, config.cluster.params()),
+            cluster_highlight: None,
+            arb: arbitrage::ArbView::default(),
+            alerts,
+            anomalies: config.anomalies,
+            event_log: false,
+            flow_stats: false,
+            session_stats: false,
+            volume_profile: false,
+            profile_window_secs: DEFAULT_PROFILE_WINDOW_SECS,
+            log_viewer: false,
+            log_level: tracing::Level::INFO,
+            theme: config.display.theme,
+            size_unit: config.display.size_unit,
+            palette: palette.clone(),
+            configured_palette: palette,
+            theme_editor: false,
+            command_palette: CommandPalette::default(),
+            display: config.display,
+            history: config.history.settings(),
+            layouts: layout::MonitorLayouts::load(cc.storage),
+        };
+        if let Some(session) = UiSession::load(cc.storage) {
+            app.restore(&session);
+        }
+        theme::apply(&cc.egui_ctx, app.theme, &app.palette);
+        if app.tabs.is_empty() || explicit {
+            app.open_watched();
+        }
+        app
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn spawner(&self) -> &exchanges::SpawnHandle {
+        self.runtime.handle()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn spawner(&self) -> &exchanges::SpawnHandle {
+        &exchanges::SpawnHandle
+    }
+
+    fn open_panels(&self) -> layout::OpenPanels {
+        layout::OpenPanels {
+            event_log: self.event_log,
+            flow_stats: self.flow_stats,
+            session_stats: self.session_stats,
+            log_viewer: self.log_viewer,
+            volume_profile: self.volume_profile,
+        }
+    }
+
+    fn restore(&mut self, session: &UiSession) {
+        self.cluster_mode = session.cluster_mode;
+        self.cluster_algorithm = session.cluster_algorithm;
+        // The seed is not saved with the session; the config file's stands
+        self.cluster_params = ClusterParams {
+            seed: self.cluster_params.seed,
+            ..session.cluster_params
+        };
+        self.view_mode = session.view_mode;
+        self.bar_axes = session.bar_axes;
+        self.mid_lookback_secs = session.mid_lookback_secs;
+        if let Some(secs) = session.delta_secs {
+            self.delta_secs = secs;
+        }
+        self.slippage_size = session.slippage_size;
+        self.alerts = session.alerts;
+        if let Some(anomalies) = session.anomalies {
+            self.anomalies = anomalies;
+        }
+        self.event_log = session.event_log;
+        self.flow_stats = session.flow_stats;
+        self.session_stats = session.session_stats;
+        self.volume_profile = session.volume_profile;
+        if let Some(secs) = session.profile_window_secs {
+            self.profile_window_secs = secs;
+        }
+        self.log_viewer = session.log_viewer;
+        if let Some(theme) = session.theme {
+            self.theme = theme;
+        }
+        if let Some(unit) = session.size_unit {
+            self.size_unit = unit;
+        }
+        if let Some(palette) = session.palette.clone().filter(|palette| palette.validate().is_ok()) {
+            self.palette = palette;
+        }
+        for saved in &session.tabs {
+            let Some(exchange) = saved.exchange_type() else {
+                continue;
+            };
+            let settings = saved.settings(&self.watch_settings);
+            let i = self.open_tab(exchange, saved.symbol.clone(), settings);
+            let tab = &mut self.tabs[i];
+            tab.group_ticks = saved.group_ticks;
+            tab.apply_grouping();
+        }
+        self.active = session.active.min(self.tabs.len().saturating_sub(1));
+    }
+
+    fn session(&self) -> UiSession {
+        let tabs = self
+            .tabs
+            .iter()
+            .map(|tab| SavedTab {
+                exchange: tab.exchange.name().to_string(),
+                symbol: tab.symbol.clone(),
+                #[cfg(feature = "binance")]
+                binance_market: tab.settings.binance_market,
+                testnet: tab.settings.testnet,
+                group_ticks: tab.group_ticks,
+            })
+            .collect();
+        UiSession {
+            tabs,
+            active: self.active,
+            cluster_mode: self.cluster_mode,
+            cluster_algorithm: self.cluster_algorithm,
+            cluster_params: self.cluster_params,
+            view_mode: self.view_mode,
+            bar_axes: self.bar_axes,
+            mid_lookback_secs: self.mid_lookback_secs,
+            delta_secs: Some(self.delta_secs),
+            slippage_size: self.slippage_size,
+            alerts: self.alerts,
+            anomalies: Some(self.anomalies),
+            event_log: self.event_log,
+            flow_stats: self.flow_stats,
+            session_stats: self.session_stats,
+            volume_profile: self.volume_profile,
+            profile_window_secs: Some(self.profile_window_secs),
+            log_viewer: self.log_viewer,
+            theme: Some(self.theme),
+            palette: Some(self.palette.clone()),
+            size_unit: Some(self.size_unit),
+        }
+    }
+}
+
+impl MyApp {
+    // Threshold and notification settings for large order alerts
+    fn alert_controls(ui: &mut egui::Ui, alerts: &mut AlertSettings) {
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut alerts.enabled, "Large order alerts");
+            if !alerts.enabled {
+                return;
+            }
+            let mut relative = matches!(alerts.threshold, AlertThreshold::MedianMultiple(_));
+            ui.radio_value(&mut relative, false, "Size ≥");
+            ui.radio_value(&mut relative, true, "Median level ×");
+            match (relative, &mut alerts.threshold) {
+                (true, AlertThreshold::MedianMultiple(multiple)) => {
+                    ui.add(egui::DragValue::new(multiple).range(1.0..=1000.0).speed(0.5));
+                }
+                (false, AlertThreshold::Absolute(size)) => {
+                    let mut value = size.to_f64().unwrap_or(0.0);
+                    if ui.add(egui::DragValue::new(&mut value).range(0.0..=f64::MAX)).changed() {
+                        *size = Decimal::from_f64(value).unwrap_or(*size);
+                    }
+                }
+                (true, _) => alerts.threshold = AlertSettings::default().threshold,
+                (false, _) => alerts.threshold = AlertThreshold::Absolute(Decimal::from(1000)),
+            }
+            if cfg!(feature = "notify") {
+                ui.checkbox(&mut alerts.desktop, "Desktop notifications");
+            }
+        });
+    }
+
+    // Large order alerts and anomalies raised for the tab, newest first
+    fn alert_log(ui: &mut egui::Ui, tab: &mut BookTab) {
+        ui.horizontal(|ui| {
+            ui.heading("Alerts");
+            if ui.small_button("Clear").clicked() {
+                tab.whales.clear_log();
+                tab.anomalies.clear_log();
+            }
+        });
+        if let Some(min) = tab.whales.min_size(&tab.book).filter(|_| tab.whales.settings.enabled) {
+            ui.label(format!("Threshold: {:.1$}", min.to_f64().unwrap_or(0.0), tab.qty_prec));
+        }
+        ui.separator();
+        let whales = tab.whales.log().iter().map(|alert| {
+            let color = match alert.kind {
+                AlertKind::Appeared => Color32::GOLD,
+                AlertKind::Pulled => Color32::GRAY,
+            };
+            (alert.time, color, alert.describe())
+        });
+        let anomalies = tab.anomalies.log().iter().map(|a| (a.time, price_chart::anomaly_color(a.kind), a.describe()));
+        let mut entries: Vec<_> = whales.chain(anomalies).collect();
+        entries.sort_by_key(|&(time, ..)| std::cmp::Reverse(time));
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for (time, color, text) in entries {
+                let time = chrono::DateTime::from_timestamp_millis(time as i64)
+                    .map(|t| t.with_timezone(&chrono::Local).format("%H:%M:%S").to_string())
+                    .unwrap_or_default();
+                ui.colored_label(color, format!("{time} {text}"));
+            }
+        });
+    }
+
+    // The estimator's decisions for the tab, newest first
+    fn event_log(ui: &mut egui::Ui, tab: &mut BookTab) {
+        ui.horizontal(|ui| {
+            ui.heading("Inferred events");
+            if ui.small_button("Clear").clicked() {
+                tab.events.clear();
+            }
+        });
+        ui.label("Fills and cancels look the same to the estimator");
+        ui.separator();
+        let row_height = ui.text_style_height(&egui::TextStyle::Body);
+        let events = &tab.events;
+        egui::ScrollArea::vertical().show_rows(ui, row_height, events.len(), |ui, rows| {
+            for event in rows.filter_map(|i| events.get(events.len() - 1 - i)) {
+                let time = chrono::DateTime::from_timestamp_millis(event.time as i64)
+                    .map(|t| t.with_timezone(&chrono::Local).format("%H:%M:%S%.3f").to_string())
+                    .unwrap_or_default();
+                let color = match event.action {
+                    InferredAction::Joined => Color32::GREEN,
+                    InferredAction::Removed => Color32::GRAY,
+                    InferredAction::Reduced => Color32::ORANGE,
+                    InferredAction::Filled => Color32::LIGHT_BLUE,
+                };
+                let side = match event.side {
+                    Side::Bid => "bid",
+                    Side::Ask => "ask",
+                };
+                ui.colored_label(
+                    color,
+                    format!(
+                        "{time} #{} {} {side} {:.4$} {:+.5$}",
+                        event.order_id,
+                        event.action.label(),
+                        event.price.to_f64().unwrap_or(0.0),
+                        event.change.to_f64().unwrap_or(0.0),
+                        tab.price_prec,
+                        tab.qty_prec
+                    ),
+                );
+            }
+        });
+    }
+
+    // Rolling statistics of the tab's inferred events
+    fn flow_stats(ui: &mut egui::Ui, tab: &mut BookTab) {
+        let flow = &mut tab.flow;
+        ui.horizontal(|ui| {
+            ui.heading("Order flow");
+            if ui.small_button("Reset").clicked() {
+                flow.clear();
+            }
+        });
+        ui.label(format!("Last {}s of inferred events", flow.window_secs()));
+        ui.separator();
+        let stats = flow.stats();
+        let qty_prec = tab.qty_prec;
+        let size = |v: Option<Decimal>| {
+            v.map_or("-".to_string(), |v| format!("{:.1$}", v.to_f64().unwrap_or(0.0), qty_prec))
+        };
+        egui::Grid::new("flow_stats").num_columns(2).striped(true).show(ui, |ui| {
+            ui.label("Adds / cancels / fills");
+            ui.label(format!("{} / {} / {}", stats.adds, stats.cancels, stats.fills));
+            ui.end_row();
+            ui.label("Add/cancel ratio");
+            ui.label(stats.add_cancel_ratio.map_or("-".to_string(), |r| format!("{r:.2}")));
+            ui.end_row();
+            ui.label("Mean order size bid");
+            ui.colored_label(Color32::GREEN, size(stats.mean_bid_size));
+            ui.end_row();
+            ui.label("Mean order size ask");
+            ui.colored_label(Color32::RED, size(stats.mean_ask_size));
+            ui.end_row();
+            ui.label("Fill rate at touch")
+                .on_hover_text("Share of the size leaving the best bid and ask that traded rather than cancelled");
+            ui.label(stats.touch_fill_rate.map_or("-".to_string(), |r| format!("{:.1}%", r * 100.0)));
+            ui.end_row();
+        });
+        ui.separator();
+        ui.label("Cancels per second by distance from mid");
+        egui::Grid::new("flow_cancels").num_columns(2).striped(true).show(ui, |ui| {
+            for (bucket, rate) in stats.cancel_rate_by_distance.iter().enumerate() {
+                ui.label(flow::distance_label(bucket));
+                ui.label(format!("{rate:.2}"));
+                ui.end_row();
+            }
+        });
+        if stats.fills == 0 && stats.cancels > 0 {
+            ui.separator();
+            ui.label("No trades attributed: without a trade stream, fills count as cancels");
+        }
+    }
+
+    // Connection and book engine counters of the tab since it was opened
+    fn session_stats(ui: &mut egui::Ui, tab: &BookTab) {
+        let stats = tab.session_stats();
+        ui.heading("Session");
+        ui.label(format!("{} since the tab was opened", duration_label(stats.session_secs)));
+        ui.separator();
+        egui::Grid::new("session_stats").num_columns(2).striped(true).show(ui, |ui| {
+            ui.label("Uptime");
+            ui.label(duration_label(stats.uptime_secs))
+                .on_hover_text("Time since the current connection was established");
+            ui.end_row();
+            ui.label("Connected");
+            ui.label(format!("{:.1}%", stats.connected_ratio * 100.0));
+            ui.end_row();
+            ui.label("Reconnects");
+            ui.label(stats.reconnects().to_string());
+            ui.end_row();
+            ui.label("Resyncs")
+                .on_hover_text("Times the book waited for a fresh snapshot: reconnects, gaps, crossed books, overflows");
+            ui.label(stats.resyncs.to_string());
+            ui.end_row();
+            ui.label("Messages");
+            ui.label(stats.messages.to_string());
+            ui.end_row();
+            ui.label("Updates applied");
+            ui.label(stats.updates_applied.to_string())
+                .on_hover_text(format!("Plus {} merged into the bursts applied per frame", stats.updates_coalesced));
+            ui.end_row();
+            ui.label("Updates dropped")
+                .on_hover_text("Updates older than the book, and messages dropped because the UI fell behind");
+            ui.label(stats.updates_dropped.to_string());
+            ui.end_row();
+            ui.label("Mean apply time");
+            ui.label(format!("{:.1} µs", stats.mean_apply_us));
+            ui.end_row();
+        });
+    }
+
+    // Records from every feed, not just the active tab's, oldest at the top
+    fn log_viewer(ui: &mut egui::Ui, level: &mut tracing::Level) {
+        ui.horizontal(|ui| {
+            ui.heading("Log");
+            egui::ComboBox::from_id_salt("log_level").selected_text(level.as_str()).show_ui(ui, |ui| {
+                for option in [
+                    tracing::Level::ERROR,
+                    tracing::Level::WARN,
+                    tracing::Level::INFO,
+                    tracing::Level::DEBUG,
+                    tracing::Level::TRACE,
+                ] {
+                    ui.selectable_value(level, option, option.as_str());
+                }
+            })
+            .response
+            .on_hover_text("Most verbose level shown; the log level filter decides what is recorded at all");
+            if ui.small_button("Clear").clicked() {
+                logging::clear_recent();
+            }
+        });
+        ui.separator();
+        // Higher levels are the more verbose ones
+        let records: Vec<LogRecord> = logging::recent().into_iter().filter(|r| r.level <= *level).collect();
+        let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+        egui::ScrollArea::vertical().stick_to_bottom(true).auto_shrink(false).show_rows(
+            ui,
+            row_height,
+            records.len(),
+            |ui, rows| {
+                for record in &records[rows] {
+                    let time = chrono::DateTime::from_timestamp_millis(record.time as i64)
+                        .map(|t| t.with_timezone(&chrono::Local).format("%H:%M:%S%.3f").to_string())
+                        .unwrap_or_default();
+                    let color = match record.level {
+                        tracing::Level::ERROR => Color32::RED,
+                        tracing::Level::WARN => Color32::GOLD,
+                        tracing::Level::INFO => ui.visuals().text_color(),
+                        _ => Color32::GRAY,
+                    };
+                    let spans = if record.spans.is_empty() { String::new() } else { format!("{} ", record.spans) };
+                    ui.label(
+                        egui::RichText::new(format!(
+                            "{time} {:>5} {spans}{}: {}",
+                            record.level, record.target, record.message
+                        ))
+                        .monospace()
+                        .color(color),
+                    );
+                }
+            },
+        );
+    }
+
+    fn grouping_label(ticks: u32) -> String {
+        if ticks <= 1 {
+            "Off".to_string()
+        } else {
+            format!("{ticks} ticks")
+        }
+    }
+
+    // Focuses the tab subscribed to (exchange, market, symbol), opening it if needed
+    fn open_tab(&mut self, exchange: ExchangeType, symbol: String, settings: ExchangeSettings) -> usize {
+        let symbol = exchange.adapt_symbol_with(&symbol, &settings);
+        let key = (exchange, exchange.market_label(&settings), symbol.to_lowercase());
+        self.active = match self.tabs.iter().position(|t| t.key() == key) {
+            Some(i) => i,
+            None => {
+                let tab = BookTab::open(self.spawner(), &self.ctx, exchange, symbol, settings, self.history);
+                self.tabs.push(tab);
+                self.tabs.len() - 1
+            }
+        };
+        self.active
+    }
+
+    fn open_watched(&mut self) {
+        self.open_tab(self.watch_exchange, self.watch_symbol.clone(), self.watch_settings.clone());
+    }
+
+    // Moves the watch row to `exchange`, adapting the symbol to its format
+    fn watch(&mut self, exchange: ExchangeType) {
+        if exchange != self.watch_exchange {
+            self.watch_exchange = exchange;
+            self.watch_symbol = exchange.adapt_symbol_with(&self.watch_symbol, &self.watch_settings);
+        }
+    }
+
+    // What the command palette offers besides opening the typed symbol
+    fn palette_entries(&self) -> Vec<Entry> {
+        let mut entries: Vec<Entry> = self
+            .tabs
+            .iter()
+            .enumerate()
+            .map(|(i, tab)| Entry::new(format!("Tab: {}", tab.title()), "", Command::FocusTab(i)))
+            .collect();
+        entries.extend([
+            Entry::new("Pause / resume", "Space", Command::TogglePause),
+            Entry::new("Toggle clustering", "C", Command::ToggleClustering),
+            Entry::new("Next exchange", "E", Command::NextExchange),
+            Entry::new("Focus symbol box", "/", Command::FocusSymbol),
+            Entry::new("Next tab", "]", Command::NextTab),
+            Entry::new("Previous tab", "[", Command::PreviousTab),
+            Entry::new("Toggle event log", "", Command::ToggleEventLog),
+            Entry::new("Toggle flow stats", "", Command::ToggleFlowStats),
+            Entry::new("Toggle session stats", "", Command::ToggleSessionStats),
+            Entry::new("Toggle volume profile", "", Command::ToggleVolumeProfile),
+            Entry::new("Toggle log", "", Command::ToggleLog),
+            Entry::new("Theme editor", "", Command::ToggleThemeEditor),
+            Entry::new("View: Order Bars", "", Command::SetView(ViewMode::Bars)),
+            Entry::new("View: Depth Heatmap", "", Command::SetView(ViewMode::Heatmap)),
+            Entry::new("View: Cumulative Depth", "", Command::SetView(ViewMode::Depth)),
+            Entry::new("View: Arb Spread", "", Command::SetView(ViewMode::Arbitrage)),
+            Entry::new("View: Level Delta", "", Command::SetView(ViewMode::Delta)),
+        ]);
+        entries
+    }
+
+    fn run(&mut self, ctx: &egui::Context, command: Command) {
+        let count = self.tabs.len();
+        match command {
+            Command::TogglePause => {
+                let tab = &mut self.tabs[self.active];
+                tab.playback.toggle(&tab.book);
+            }
+            Command::ToggleClustering => self.cluster_mode = !self.cluster_mode,
+            Command::NextExchange => {
+                let all = ExchangeType::ALL;
+                let next = all.iter().position(|&e| e == self.watch_exchange).map_or(0, |i| (i + 1) % all.len());
+                self.watch(all[next]);
+            }
+            Command::FocusSymbol => ctx.memory_mut(|memory| memory.request_focus(egui::Id::new(commands::SYMBOL_BOX))),
+            Command::NextTab => self.active = (self.active + 1) % count,
+            Command::PreviousTab => self.active = (self.active + count - 1) % count,
+            Command::OpenPalette => self.command_palette.open(),
+            Command::FocusTab(i) => self.active = i.min(count - 1),
+            Command::Open(exchange, symbol) => {
+                self.watch(exchange);
+                self.watch_symbol = exchange.adapt_symbol_with(&symbol, &self.watch_settings);
+                self.open_watched();
+            }
+            Command::SetView(view) => self.view_mode = view,
+            Command::ToggleEventLog => self.event_log = !self.event_log,
+            Command::ToggleFlowStats => self.flow_stats = !self.flow_stats,
+            Command::ToggleSessionStats => self.session_stats = !self.session_stats,
+            Command::ToggleVolumeProfile => self.volume_profile = !self.volume_profile,
+            Command::ToggleLog => self.log_viewer = !self.log_viewer,
+            Command::ToggleThemeEditor => self.theme_editor = !self.theme_editor,
+        }
+    }
+
+    // One entry per subscription; closing a tab drops its worker, which stops the feed
+    fn tab_bar(&mut self, ui: &mut egui::Ui) {
+        let mut close = None;
+        ui.horizontal_wrapped(|ui| {
+            for (i, tab) in self.tabs.iter().enumerate() {
+                ui.colored_label(tab::status_color(tab.connection()), "●");
+                let title = if tab.playback.is_paused() { format!("{} ⏸", tab.title()) } else { tab.title() };
+                ui.selectable_value(&mut self.active, i, title);
+                if self.tabs.len() > 1 && ui.small_button("×").on_hover_text("Close tab").clicked() {
+                    close = Some(i);
+                }
+                ui.separator();
+            }
+        });
+        if let Some(i) = close {
+            self.tabs.remove(i);
+            if self.active > i || self.active == self.tabs.len() {
+                self.active -= 1;
+            }
+        }
+    }
+
+    // Exchange, market and symbol for a new tab
+    fn watch_row(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Exchange:");
+            let mut selected = self.watch_exchange;
+            egui::ComboBox::from_id_salt("watch_exchange")
+                .selected_text(selected.name())
+                .show_ui(ui, |ui| {
+                    for &exchange in ExchangeType::ALL {
+                        ui.selectable_value(&mut selected, exchange, exchange.name());
+                    }
+                });
+            self.watch(selected);
+            #[cfg(feature = "binance")]
+            if self.watch_exchange == ExchangeType::Binance {
+                self.binance_market_selector(ui);
+            }
+            if self.watch_exchange.has_testnet() {
+                ui.checkbox(&mut self.watch_settings.testnet, "Testnet")
+                    .on_hover_text("Connect to the exchange's testnet instead of production");
+            }
+            ui.label("Symbol:");
+            let edit = ui.add(egui::TextEdit::singleline(&mut self.watch_symbol).id(egui::Id::new(commands::SYMBOL_BOX)));
+            let submitted = edit.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if ui.button("Open").on_hover_text("Open in a new tab, or focus it if already open").clicked() || submitted {
+                self.open_watched();
+            }
+        });
+    }
+
+    // Spot / USD-M / COIN-M; each market is a separate subscription
+    #[cfg(feature = "binance")]
+    fn binance_market_selector(&mut self, ui: &mut egui::Ui) {
+        ui.label("Market:");
+        let mut market = self.watch_settings.binance_market;
+        egui::ComboBox::from_id_salt("binance_market")
+            .selected_text(market.label())
+            .show_ui(ui, |ui| {
+                for m in BinanceMarket::ALL {
+                    ui.selectable_value(&mut market, m, m.label());
+                }
+            });
+        if market != self.watch_settings.binance_market {
+            self.watch_settings.binance_market = market;
+            self.watch_symbol = self.watch_exchange.adapt_symbol_with(&self.watch_symbol, &self.watch_settings);
+        }
+    }
+}
+
+impl eframe::App for MyApp {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        self.layouts.save(storage);
+        self.session().save(storage);
+    }
+
+    // Stops every feed and gives the connectors a moment to close their sockets; the browser
+    // can't block, and closes them itself when the page goes away
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.tabs.clear();
+        #[cfg(not(target_arch = "wasm32"))]
+        self.runtime.block_on(exchanges::shutdown());
+    }
+
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if let Some(panels) = self.layouts.track(ctx, self.open_panels()) {
+            self.event_log = panels.event_log;
+            self.flow_stats = panels.flow_stats;
+            self.session_stats = panels.session_stats;
+            self.log_viewer = panels.log_viewer;
+            self.volume_profile = panels.volume_profile;
+        }
+
+        // Background tabs are drained too so their books and histories stay current
+        for (i, tab) in self.tabs.iter_mut().enumerate() {
+            tab.set_visible(i == self.active);
+            tab.whales.settings = self.alerts;
+            tab.anomalies.settings = self.anomalies;
+            tab.profile.set_window(self.profile_window_secs);
+            tab.set_event_log(self.event_log);
+            tab.drain();
+        }
+        self.arb.update(&self.tabs);
+        self.chart_export.receive(ctx);
+        let mut commands = commands::shortcuts(ctx);
+        let entries = self.palette_entries();
+        commands.extend(self.command_palette.show(ctx, entries, self.watch_exchange));
+        for command in commands {
+            self.run(ctx, command);
+        }
+        let tab_names: Vec<_> = self.tabs.iter().map(|tab| (tab.key(), tab.title())).collect();
+
+        egui::TopBottomPanel::top("tab_bar").show(ctx, |ui| {
+            self.tab_bar(ui);
+            self.watch_row(ui);
+        });
+
+        let tab = &mut self.tabs[self.active];
+        if self.alerts.enabled || self.anomalies.enabled {
+            egui::SidePanel::right("alert_log").default_width(260.0).show(ctx, |ui| {
+                Self::alert_log(ui, tab);
+            });
+        }
+        if self.event_log {
+            egui::SidePanel::left("event_log").default_width(300.0).show(ctx, |ui| {
+                Self::event_log(ui, tab);
+            });
+        }
+        if self.theme_editor {
+            let mut open = true;
+            egui::Window::new("Theme").open(&mut open).resizable(false).show(ctx, |ui| {
+                if theme::editor(ui, &mut self.theme, &mut self.palette, &self.configured_palette) {
+                    theme::apply(ctx, self.theme, &self.palette);
+                }
+            });
+            self.theme_editor = open;
+        }
+        if self.flow_stats {
+            egui::SidePanel::left("flow_stats").default_width(240.0).show(ctx, |ui| {
+                Self::flow_stats(ui, tab);
+            });
+        }
+        if self.session_stats {
+            egui::SidePanel::left("session_stats").default_width(220.0).show(ctx, |ui| {
+                Self::session_stats(ui, tab);
+            });
+        }
+        if self.log_viewer {
+            egui::TopBottomPanel::bottom("log_viewer").resizable(true).default_height(160.0).show(ctx, |ui| {
+                Self::log_viewer(ui, &mut self.log_level);
+            });
+        }
+        egui::TopBottomPanel::bottom("mid_price_panel")
+            .resizable(true)
+            .default_height(180.0)
+            .show(ctx, |ui| {
+                price_chart::show(
+                    ui,
+                    &tab.history,
+                    &tab.trades,
+                    tab.anomalies.log(),
+                    tab.playback.until(),
+                    &mut self.mid_lookback_secs,
+                    tab.price_prec,
+                );
+            });
+
+        let cap = tab.settings.depth(tab.exchange).display.unwrap_or(usize::MAX);
+        let (rows, levels) = (self.display.book_rows.min(cap), self.display.chart_levels.min(cap));
+        egui::CentralPanel::default().show(ctx, |ui| {
+            tab.header(ui);
+            ui.horizontal(|ui| {
+                if ui.button("Toggle Clustering Mode").clicked() {
+                    self.cluster_mode = !self.cluster_mode;
+                }
+                ui.checkbox(&mut self.event_log, "Event log")
+                    .on_hover_text("Show the orders the estimator infers joining, leaving and shrinking");
+                ui.checkbox(&mut self.flow_stats, "Flow stats")
+                    .on_hover_text("Add/cancel ratio, cancel rate by distance, order sizes and touch fill rate");
+                ui.checkbox(&mut self.anomalies.enabled, "Anomalies")
+                    .on_hover_text("Message rate spikes and drops, spread blowouts and vanishing depth");
+                ui.checkbox(&mut self.volume_profile, "Volume profile")
+                    .on_hover_text("Traded volume by price under the order bar and depth charts");
+                ui.checkbox(&mut self.session_stats, "Session")
+                    .on_hover_text("Uptime, reconnects, resyncs, messages and updates applied or dropped");
+                ui.checkbox(&mut self.log_viewer, "Log")
+                    .on_hover_text("Recent connector, feed and estimator log records");
+                ui.toggle_value(&mut self.theme_editor, "Theme")
+                    .on_hover_text("Dark or light theme, chart colors and a colorblind-safe preset");
+                ui.label("View:");
+                ui.selectable_value(&mut self.view_mode, ViewMode::Bars, "Order Bars");
+                ui.selectable_value(&mut self.view_mode, ViewMode::Heatmap, "Depth Heatmap");
+                ui.selectable_value(&mut self.view_mode, ViewMode::Depth, "Cumulative Depth");
+                ui.selectable_value(&mut self.view_mode, ViewMode::Arbitrage, "Arb Spread");
+                ui.selectable_value(&mut self.view_mode, ViewMode::Delta, "Level Delta")
+                    .on_hover_text("Net change in resting size per level over the last seconds of history");
+                ui.label("Group:");
+                let mut group_ticks = tab.group_ticks;
+                egui::ComboBox::from_id_salt("group_ticks")
+                    .selected_text(Self::grouping_label(group_ticks))
+                    .show_ui(ui, |ui| {
+                        for ticks in GROUP_TICK_OPTIONS {
+                            ui.selectable_value(&mut group_ticks, ticks, Self::grouping_label(ticks));
+                        }
+                    });
+                if group_ticks != tab.group_ticks {
+                    tab.group_ticks = group_ticks;
+                    tab.apply_grouping();
+                }
+                ui.label("Sizes:");
+                egui::ComboBox::from_id_salt("size_unit")
+                    .selected_text(self.size_unit.label())
+                    .show_ui(ui, |ui| {
+                        for unit in SizeUnit::ALL {
+                            ui.selectable_value(&mut self.size_unit, unit, unit.label());
+                        }
+                    })
+                    .response
+                    .on_hover_text(
+                        "Native as the venue quotes them, base asset with contracts converted, or notional in the \
+                         quote currency",
+                    );
+            });
+            Self::alert_controls(ui, &mut self.alerts);
+
+            if self.cluster_mode {
+                ui.horizontal(|ui| {
+                    ui.label("Algorithm:");
+                    egui::ComboBox::from_id_salt("cluster_algorithm")
+                        .selected_text(self.cluster_algorithm.name())
+                        .show_ui(ui, |ui| {
+                            for algorithm in ClusteringAlgorithm::ALL {
+                                ui.selectable_value(&mut self.cluster_algorithm, algorithm, algorithm.name());
+                            }
+                        });
+                    ui.label("Features:");
+                    let features = &mut self.cluster_params.features;
+                    ui.checkbox(&mut features.log_qty, "Log quantity");
+                    ui.checkbox(&mut features.price_distance, "Price distance");
+                    ui.checkbox(&mut self.cluster_params.shared_sides, "Shared sides")
+                        .on_hover_text("One fit over both sides, so a shade means the same sizes on bids and asks");
+                });
+                let params = &mut self.cluster_params;
+                match self.cluster_algorithm {
+                    ClusteringAlgorithm::KMeans => {
+                        ui.horizontal(|ui| {
+                            ui.label("Clusters:");
+                            ui.add_enabled(!params.auto_k, egui::Slider::new(&mut params.num_clusters, 2..=20));
+                            let (low, high) = (AUTO_K_RANGE.start(), AUTO_K_RANGE.end());
+                            ui.checkbox(&mut params.auto_k, "Auto").on_hover_text(format!(
+                                "Pick {low} to {high} clusters by silhouette on a sample of the orders, \
+                                 every few seconds"
+                            ));
+                            let clusters = &self.clusters;
+                            if params.auto_k && clusters.algorithm == ClusteringAlgorithm::KMeans {
+                                let (bids, asks) = (clusters.num_clusters(Side::Bid), clusters.num_clusters(Side::Ask));
+                                ui.label(format!("bids {bids}, asks {asks}"));
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Init:");
+                            egui::ComboBox::from_id_salt("kmeans_init")
+                                .selected_text(params.init.name())
+                                .show_ui(ui, |ui| {
+                                    for init in KMeansInit::ALL {
+                                        ui.selectable_value(&mut params.init, init, init.name());
+                                    }
+                                });
+                            ui.checkbox(&mut params.reseed_empty, "Reseed empty")
+                                .on_hover_text("Move a centroid that stops capturing orders onto the worst-served one");
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Batch Size:");
+                            ui.add(egui::Slider::new(&mut params.batch_size, 32..=2048));
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Max Iter:");
+                            ui.add(egui::Slider::new(&mut params.max_iter, 64..=2048));
+                        });
+                    }
+                    ClusteringAlgorithm::Dbscan => {
+                        ui.horizontal(|ui| {
+                            ui.label("Eps:");
+                            ui.add(egui::Slider::new(&mut params.eps, 0.001..=0.2).logarithmic(true));
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Min Points:");
+                            ui.add(egui::Slider::new(&mut params.min_points, 1..=32));
+                        });
+                    }
+                    ClusteringAlgorithm::GaussianMixture => {
+                        ui.horizontal(|ui| {
+                            ui.label("Components:");
+                            ui.add(egui::Slider::new(&mut params.num_clusters, 2..=20));
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Max Iter:");
+                            ui.add(egui::Slider::new(&mut params.max_iter, 8..=512));
+                        });
+                    }
+                    ClusteringAlgorithm::Quantile => {
+                        ui.horizontal(|ui| {
+                            ui.label("Bands:");
+                            ui.add(egui::Slider::new(&mut params.num_clusters, 2..=20));
+                        });
+                    }
+                }
+            }
+
+            // Sizes as the unit picked, from the venue's, and the decimals they are shown with
+            let (unit, contract) = (self.size_unit, tab.contract);
+            let size = move |price: Decimal, qty: Decimal| contract.size_in(unit, qty, price);
+            let size_prec = contract.decimals(unit, tab.qty_prec);
+            let size_header = if unit == SizeUnit::Native { "Quantity" } else { unit.label() };
+
+            ui.horizontal(|ui| {
+                ui.vertical(|ui| {
+                    let book = tab.playback.book(&tab.book);
+                    // Queues are valued on the raw levels, a bucket as joining at its price
+                    let flow_stats = tab.flow.stats();
+                    egui::Grid::new("order_book_grid")
+                        .striped(true)
+                        .show(ui, |ui| {
+                            ui.label("Asks");
+                            ui.label("Price");
+                            ui.label(size_header);
+                            ui.label("Orders").on_hover_text(ORDERS_HINT);
+                            ui.label("Value").on_hover_text(QUEUE_VALUE_HINT);
+                            ui.end_row();
+
+                            for (price, qty) in book.view_asks().iter().take(rows).rev() {
+                                ui.label("");
+                                ui.label(format!(
+                                    "{:.1$}",
+                                    price.to_f64().unwrap_or(0.0),
+                                    tab.price_prec
+                                ));
+                                ui.label(format!(
+                                    "{:.1$}",
+                                    size(*price, qty.total()).to_f64().unwrap_or(0.0),
+                                    size_prec
+                                ));
+                                ui.label(orders_label(qty));
+                                queue_value_cell(ui, book, Side::Ask, *price, &flow_stats, tab.qty_prec);
+                                ui.end_row();
+                            }
+
+                            ui.label("Bids");
+                            ui.label("Price");
+                            ui.label(size_header);
+                            ui.label("Orders").on_hover_text(ORDERS_HINT);
+                            ui.label("Value").on_hover_text(QUEUE_VALUE_HINT);
+                            ui.end_row();
+
+                            for (price, qty) in book.view_bids().iter().rev().take(rows) {
+                                ui.label("");
+                                ui.label(format!(
+                                    "{:.1$}",
+                                    price.to_f64().unwrap_or(0.0),
+                                    tab.price_prec
+                                ));
+                                ui.label(format!(
+                                    "{:.1$}",
+                                    size(*price, qty.total()).to_f64().unwrap_or(0.0),
+                                    size_prec
+                                ));
+                                ui.label(orders_label(qty));
+                                queue_value_cell(ui, book, Side::Bid, *price, &flow_stats, tab.qty_prec);
+                                ui.end_row();
+                            }
+                        });
+                });
+
+                ui.vertical(|ui| {
+                    let book = tab.playback.book(&tab.book);
+                    // The profile spans the prices the chart above shows
+                    let profile = self.volume_profile && matches!(self.view_mode, ViewMode::Bars | ViewMode::Depth);
+                    if profile {
+                        let shown = if self.view_mode == ViewMode::Depth { depth_chart::DEPTH_LEVELS } else { levels };
+                        let low = book.view_bids().keys().rev().take(shown).next_back().and_then(|p| p.to_f64());
+                        let high = book.view_asks().keys().take(shown).next_back().and_then(|p| p.to_f64());
+                        let colors = (theme::color(self.palette.bid), theme::color(self.palette.ask));
+                        egui::TopBottomPanel::bottom("volume_profile_panel")
+                            .resizable(true)
+                            .default_height(volume_profile::PROFILE_HEIGHT)
+                            .show_inside(ui, |ui| {
+                                let (window, range) = (&mut self.profile_window_secs, low.zip(high));
+                                let precision = (tab.price_prec, tab.qty_prec);
+                                volume_profile::show(ui, &tab.profile, window, range, precision, colors);
+                            });
+                    }
+                    match self.view_mode {
+                        ViewMode::Bars => {}
+                        ViewMode::Heatmap => {
+                            tab.heatmap.show(ui, &tab.history, tab.playback.until());
+                            return;
+                        }
+                        ViewMode::Depth => {
+                            let colors = (theme::color(self.palette.bid), theme::color(self.palette.ask));
+                            depth_chart::show(ui, book, &mut self.slippage_size, tab.price_prec, colors, profile);
+                            return;
+                        }
+                        ViewMode::Arbitrage => {
+                            self.arb.show(ui, &tab_names);
+                            return;
+                        }
+                        ViewMode::Delta => {
+                            let until = tab.playback.until();
+                            delta_chart::show(ui, &tab.history, until, &mut self.delta_secs, tab.price_prec);
+                            return;
+                        }
+                    }
+                    let bid_levels: Vec<(&Decimal, Decimal)> = book
+                        .view_bids()
+                        .iter()
+                        .rev()
+                        .take(levels)
+                        .map(|(key, queue)| (key, size(*key, queue.total())))
+                        .collect();
+                    let ask_levels: Vec<(&Decimal, Decimal)> = book
+                        .view_asks()
+                        .iter()
+                        .take(levels)
+                        .map(|(key, queue)| (key, size(*key, queue.total())))
+                        .collect();
+                    let mut max_qty: f64 = 0.0;
+                    for (_, qty) in &bid_levels {
+                        max_qty = max_qty.max(qty.to_f64().unwrap_or(0.0));
+                    }
+                    for (_, qty) in &ask_levels {
+                        max_qty = max_qty.max(qty.to_f64().unwrap_or(0.0));
+                    }
+                    let reset_view = self.bar_axes.controls(ui, max_qty);
+                    let export_chart = self.chart_export.controls(ui);
+                    let bid_gradient = theme::colors(&self.palette.bid_gradient);
+                    let ask_gradient = theme::colors(&self.palette.ask_gradient);
+                    if self.cluster_mode {
+                        let clusters = &mut self.clusters;
+                        clusters.configure(self.cluster_algorithm, self.cluster_params, unit);
+                        clusters.fit(book, levels, contract);
+                        let notional = |price, qty| contract.convert(qty, price, unit, SizeUnit::Notional);
+                        let [bids, asks] = [Side::Bid, Side::Ask].map(|side| {
+                            let (labels, num_clusters) = (clusters.labels(side), clusters.num_clusters(side));
+                            clusters.input(side).summarize_with(labels, num_clusters, notional)
+                        });
+                        let sides = [
+                            cluster_legend::SideLegend { side: Side::Bid, summaries: &bids, gradient: &bid_gradient },
+                            cluster_legend::SideLegend { side: Side::Ask, summaries: &asks, gradient: &ask_gradient },
+                        ];
+                        cluster_legend::show(ui, sides, &mut self.cluster_highlight, size_prec);
+                    }
+                    // Every order shown, per side, in the units shown
+                    let bid_orders = || {
+                        let levels = book.view_bids().iter().rev().take(levels);
+                        levels.flat_map(move |(&price, queue)| queue.sizes().map(move |qty| size(price, qty)))
+                    };
+                    let ask_orders = || {
+                        let levels = book.view_asks().iter().take(levels);
+                        levels.flat_map(move |(&price, queue)| queue.sizes().map(move |qty| size(price, qty)))
+                    };
+                    let smallest_order = bid_orders()
+                        .chain(ask_orders())
+                        .filter(|size| *size > Decimal::ZERO)
+                        .min()
+                        .and_then(|size| size.to_f64())
+                        .unwrap_or(1.0);
+                    let scale = self.bar_axes.scale(smallest_order);
+                    let top = scale.y(self.bar_axes.fixed_max.unwrap_or(max_qty));
+
+                    // Bars stand at their prices, as wide as the closest two levels are apart, so
+                    // gaps in the book show as gaps in the chart
+                    let step = price_step(bid_levels.iter().chain(&ask_levels).map(|(price, _)| **price));
+                    let mut bars: Vec<Bar> = Vec::new();
+                    let palette = &self.palette;
+                    let largest = theme::color(palette.largest);
+                    let second_largest = theme::color(palette.second_largest);
+
+                    let max_bid_order: Decimal = bid_orders().max().unwrap_or(Decimal::ZERO);
+                    let max_ask_order: Decimal = ask_orders().max().unwrap_or(Decimal::ZERO);
+                    let second_max_bid_order = {
+                        let mut orders: Vec<_> = bid_orders().collect();
+                        orders.sort_by(|a, b| b.cmp(a)); // Sort in descending order
+                        orders.get(1).cloned().unwrap_or(Decimal::ZERO)
+                    };
+                    let second_max_ask_order = {
+                        let mut orders: Vec<_> = ask_orders().collect();
+                        orders.sort_by(|a, b| b.cmp(a)); // Sort in descending order
+                        orders.get(1).cloned().unwrap_or(Decimal::ZERO)
+                    };
+
+                    if !self.cluster_mode {
+                        for (price, qty_deq) in book.view_asks().iter().take(levels) {
+                            let x = price.to_f64().unwrap_or(0.0);
+                            let mut offset = 0.0;
+
+                            for (j, order) in qty_deq.iter().enumerate() {
+                                let qty = size(*price, order.size());
+                                if qty <= dec!(0.0) {
+                                    continue;
+                                }
+                                let color = if qty == max_ask_order {
+                                    largest
+                                } else if qty == second_max_ask_order {
+                                    second_largest
+                                } else {
+                                    Self::get_order_color(j, theme::color(palette.ask))
+                                };
+                                let (base, height) = scale.segment(offset, qty.to_f64().unwrap_or(0.0));
+                                let bar = Bar::new(x, height)
+                                    .fill(confidence_color(color, order.confidence()))
+                                    .base_offset(base)
+                                    .width(step * 0.9);
+                                bars.push(bar);
+                                offset += qty.to_f64().unwrap_or(0.0);
+                            }
+                        }
+
+                        // Color Mapping for Bids
+                        for (price, qty_deq) in book.view_bids().iter().rev().take(levels) {
+                            let x = price.to_f64().unwrap_or(0.0);
+                            let mut offset = 0.0;
+
+                            for (j, order) in qty_deq.iter().enumerate() {
+                                let qty = size(*price, order.size());
+                                if qty <= dec!(0.0) {
+                                    continue;
+                                }
+                                let color = if qty == max_bid_order {
+                                    largest
+                                } else if qty == second_max_bid_order {
+                                    second_largest
+                                } else {
+                                    Self::get_order_color(j, theme::color(palette.bid))
+                                };
+                                let (base, height) = scale.segment(offset, qty.to_f64().unwrap_or(0.0));
+                                let bar = Bar::new(x, height)
+                                    .fill(confidence_color(color, order.confidence()))
+                                    .base_offset(base)
+                                    .width(step * 0.9);
+                                bars.push(bar);
+                                offset += qty.to_f64().unwrap_or(0.0);
+                            }
+                        }
+                    } else {
+                        let clusters = &self.clusters;
+                        // Outside the cluster picked in the legend, orders fade into the background
+                        let highlight = self.cluster_highlight;
+                        let dim = |side: Side, cluster: usize, color: Color32| match highlight {
+                            Some(picked) if picked != (side, cluster) => color.gamma_multiply(cluster_legend::DIMMED),
+                            _ => color,
+                        };
+                        let num_ask_clusters = clusters.num_clusters(Side::Ask);
+                        let num_bid_clusters = clusters.num_clusters(Side::Bid);
+
+                        // Asks in clustering mode
+                        let ask_labels = clusters.input(Side::Ask).labelled(clusters.labels(Side::Ask));
+                        let ask_queues = ask_labels.zip(book.view_asks().values());
+                        for ((price, sizes, labels), queue) in ask_queues {
+                            let x = price.to_f64().unwrap_or(0.0);
+                            let mut offset = 0.0;
+
+                            for ((&qty, &cluster), order) in sizes.iter().zip(labels).zip(queue) {
+                                let color = if qty == max_ask_order {
+                                    largest
+                                } else {
+                                    cluster_color(&ask_gradient, cluster, num_ask_clusters)
+                                };
+                                let color = dim(Side::Ask, cluster, color);
+                                let (base, height) = scale.segment(offset, qty.to_f64().unwrap_or(0.0));
+                                let bar = Bar::new(x, height)
+                                    .fill(confidence_color(color, order.confidence()))
+                                    .base_offset(base)
+                                    .width(step * 0.9);
+                                bars.push(bar);
+                                offset += qty.to_f64().unwrap_or(0.0);
+                            }
+                        }
+
+                        // Bids in clustering mode, best first
+                        let bid_labels = clusters.input(Side::Bid).labelled(clusters.labels(Side::Bid));
+                        let bid_queues = bid_labels.zip(book.view_bids().values().rev());
+                        for ((price, sizes, labels), queue) in bid_queues {
+                            let x = price.to_f64().unwrap_or(0.0);
+                            let mut offset = 0.0;
+
+                            for ((&qty, &cluster), order) in sizes.iter().zip(labels).zip(queue) {
+                                let color = if qty == max_bid_order {
+                                    largest
+                                } else {
+                                    cluster_color(&bid_gradient, cluster, num_bid_clusters)
+                                };
+                                let color = dim(Side::Bid, cluster, color);
+                                let (base, height) = scale.segment(offset, qty.to_f64().unwrap_or(0.0));
+                                let bar = Bar::new(x, height)
+                                    .fill(confidence_color(color, order.confidence()))
+                                    .base_offset(base)
+                                    .width(step * 0.9);
+                                bars.push(bar);
+                                offset += qty.to_f64().unwrap_or(0.0);
+                            }
+                        }
+                    }
+
+                    // The hypothetical order sits in its level's bar on top of the quantity ahead of
+                    // it; only drawn on raw levels, since a bucket mixes several queues
+                    let my_order_marker = tab
+                        .my_order
+                        .filter(|_| book.grouping().is_none())
+                        .and_then(|order| {
+                            let position = book.queue_position(order.side, order.price, order.joined_at);
+                            let shown = match order.side {
+                                Side::Bid => book.bids.keys().rev().take(levels).any(|&p| p == order.price),
+                                Side::Ask => book.asks.keys().take(levels).any(|&p| p == order.price),
+                            };
+                            let x = order.price.to_f64().filter(|_| shown)?;
+                            Some((x, scale.y(size(order.price, position.qty_ahead).to_f64().unwrap_or(0.0))))
+                        });
+
+                    let interactive = self.bar_axes.interactive;
+                    let price_prec = tab.price_prec;
+                    let mut plot = Plot::new("orderbook_chart")
+                        .allow_drag(interactive)
+                        .allow_scroll(interactive)
+                        .allow_zoom(interactive)
+                        .show_axes([true, true])
+                        .show_x(false)
+                        .show_y(false)
+                        .x_axis_formatter(move |mark, _| format!("{:.1$}", mark.value, price_prec));
+                    if self.bar_axes.log_scale {
+                        plot = plot.y_axis_formatter(move |mark, _| {
+                            if mark.value < 0.0 {
+                                return String::new();
+                            }
+                            format!("{:.1$}", scale.qty(mark.value), size_prec)
+                        });
+                    }
+                    if self.bar_axes.fixed_max.is_some() && top > 0.0 {
+                        plot = plot.default_y_bounds(0.0, top);
+                    }
+                    if profile {
+                        plot = plot.link_axis(volume_profile::PRICE_AXIS, [true, false]);
+                    }
+                    if reset_view {
+                        plot = plot.reset();
+                    }
+                    let exported_bars = export_chart.then(|| bars.clone());
+                    let response = plot.show(ui, |plot_ui| {
+                        plot_ui.bar_chart(BarChart::new("ob", bars).allow_hover(false));
+
+                        if let Some((x, y)) = my_order_marker {
+                            plot_ui.line(
+                                Line::new("my order", vec![[x - step * 0.6, y], [x + step * 0.6, y]])
+                                    .color(Color32::WHITE)
+                                    .width(2.0),
+                            );
+                            plot_ui.text(
+                                Text::new("my order", PlotPoint::new(x, y), "my order")
+                                    .color(Color32::WHITE)
+                                    .anchor(Align2::CENTER_BOTTOM),
+                            );
+                        }
+                        plot_ui.pointer_coordinate()
+                    });
+
+                    if let Some(bars) = exported_bars {
+                        let chart = Chart {
+                            exchange: tab.exchange,
+                            symbol: &tab.symbol,
+                            bars: &bars,
+                            transform: &response.transform,
+                            rect: response.response.rect,
+                            background: ui.visuals().extreme_bg_color,
+                            text: ui.visuals().text_color(),
+                            price_prec: tab.price_prec,
+                        };
+                        // The clustering input holds exactly the shown levels, in the same order
+                        let clusters = &self.clusters;
+                        let (ask_labels, bid_labels): (Vec<&[usize]>, Vec<&[usize]>) = if self.cluster_mode {
+                            let labels = |(_, _, labels)| labels;
+                            (
+                                clusters.input(Side::Ask).labelled(clusters.labels(Side::Ask)).map(labels).collect(),
+                                clusters.input(Side::Bid).labelled(clusters.labels(Side::Bid)).map(labels).collect(),
+                            )
+                        } else {
+                            Default::default()
+                        };
+                        let asks = book.view_asks().iter().take(levels).map(|(p, q)| (Side::Ask, p, q, &ask_labels));
+                        let bids = book.view_bids().iter().rev().take(levels).map(|(p, q)| (Side::Bid, p, q, &bid_labels));
+                        let levels = asks.enumerate().chain(bids.enumerate()).map(|(i, (side, &price, queue, labels))| {
+                            ChartLevel {
+                                side,
+                                price,
+                                queue,
+                                clusters: labels.get(i).copied(),
+                            }
+                        });
+                        self.chart_export.export(ui.ctx(), &chart, levels);
+                    }
+
+                    // The level whose bar is under the pointer, with its composition
+                    let hovered = response.inner.filter(|_| response.response.hovered()).and_then(|pointer| {
+                        let shown_bids = book.view_bids().iter().rev().take(levels).map(|(p, q)| (Side::Bid, p, q));
+                        let shown_asks = book.view_asks().iter().take(levels).map(|(p, q)| (Side::Ask, p, q));
+                        let distance = |price: &Decimal| (price.to_f64().unwrap_or(0.0) - pointer.x).abs();
+                        shown_bids
+                            .chain(shown_asks)
+                            .filter(|(_, price, _)| distance(price) <= step / 2.0)
+                            .min_by(|a, b| distance(a.1).total_cmp(&distance(b.1)))
+                            .map(|(side, &price, queue)| (side, price, queue, scale.qty(pointer.y)))
+                    });
+                    if let Some((side, price, queue, pointer_qty)) = hovered {
+                        let clusters = if !self.cluster_mode {
+                            None
+                        } else {
+                            let mut levels = self.clusters.input(side).labelled(self.clusters.labels(side));
+                            levels.find(|(p, _, _)| *p == price).map(|(_, _, labels)| labels)
+                        };
+                        // Venue queues are keyed by raw price, so only shown on ungrouped levels
+                        let live = match book.grouping() {
+                            None => book.live_orders().queue(side, price),
+                            Some(_) => &[],
+                        };
+                        let level = tooltip::LevelTooltip {
+                            price,
+                            queue,
+                            clusters,
+                            live,
+                            pointer_qty,
+                            unit_size: size(price, Decimal::ONE),
+                            now: tab.playback.until().unwrap_or_else(|| chrono::Utc::now().timestamp_millis() as u64),
+                            price_prec: tab.price_prec,
+                            qty_prec: size_prec,
+                        };
+                        response.response.on_hover_ui_at_pointer(|ui| level.show(ui));
+                    }
+                });
+            });
+        });
+    }
+}
+
+impl MyApp {
+    // Function to calculate color based on the order index
+    fn get_order_color(index: usize, base_color: Color32) -> Color32 {
+        // Brighten the color by 5% for each order index
+        let brightening_factor = 1.0 + 0.05 * index as f32; // 5% brighter per order
+        let r = (base_color.r() as f32 * brightening_factor).min(255.0) as u8;
+        let g = (base_color.g() as f32 * brightening_factor).min(255.0) as u8;
+        let b = (base_color.b() as f32 * brightening_factor).min(255.0) as u8;
+
+        Color32::from_rgb(r, g, b)
+    }
+}
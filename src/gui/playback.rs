@@ -0,0 +1,140 @@
+use crate::exchanges::OrderBookSnapshot;
+use crate::history::BookHistory;
+use crate::orderbook::OrderBook;
+use eframe::egui;
+use egui::Color32;
+use rust_decimal::Decimal;
+
+// Pause and scrub-back for a tab: freezes what the book, charts and heatmap show while the feed
+// keeps draining into the live book and history, until the view is resumed.
+#[derive(Default)]
+pub struct Playback {
+    paused: Option<Paused>,
+}
+
+struct Paused {
+    // The live book as it was when paused, estimated queues included
+    book: OrderBook,
+    // Milliseconds since the Unix epoch
+    paused_at: u64,
+    // Position of the scrub slider in seconds before the pause, zero for the paused book itself
+    offset: f64,
+    // History sample at the slider and a book of its aggregated levels; samples carry no queues
+    sample: Option<(u64, OrderBook)>,
+}
+
+impl Playback {
+    pub fn is_paused(&self) -> bool {
+        self.paused.is_some()
+    }
+
+    pub fn pause(&mut self, book: &OrderBook) {
+        self.paused = Some(Paused {
+            book: book.clone(),
+            paused_at: chrono::Utc::now().timestamp_millis() as u64,
+            offset: 0.0,
+            sample: None,
+        });
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = None;
+    }
+
+    pub fn toggle(&mut self, live: &OrderBook) {
+        if self.is_paused() {
+            self.resume();
+        } else {
+            self.pause(live);
+        }
+    }
+
+    // The book to display: `live`, or the frozen one while paused
+    pub fn book<'a>(&'a self, live: &'a OrderBook) -> &'a OrderBook {
+        match &self.paused {
+            Some(Paused { sample: Some((_, book)), .. }) => book,
+            Some(paused) => &paused.book,
+            None => live,
+        }
+    }
+
+    // Time the charts end at while paused, None when live
+    pub fn until(&self) -> Option<u64> {
+        let paused = self.paused.as_ref()?;
+        Some(paused.sample.as_ref().map_or(paused.paused_at, |(timestamp, _)| *timestamp))
+    }
+
+    // Keeps the frozen books' metrics over the same levels as the live book's
+    pub fn set_metrics_depth(&mut self, depth: usize) {
+        if let Some(paused) = &mut self.paused {
+            paused.book.set_metrics_depth(depth);
+            if let Some((_, book)) = &mut paused.sample {
+                book.set_metrics_depth(depth);
+            }
+        }
+    }
+
+    pub fn set_vwap_notional(&mut self, notional: Decimal) {
+        if let Some(paused) = &mut self.paused {
+            paused.book.set_vwap_notional(notional);
+            if let Some((_, book)) = &mut paused.sample {
+                book.set_vwap_notional(notional);
+            }
+        }
+    }
+
+    // Pause / Live toggle and, while paused, the slider back through the retained history
+    pub fn controls(&mut self, ui: &mut egui::Ui, live: &OrderBook, history: &BookHistory) {
+        ui.horizontal(|ui| {
+            let Some(paused) = &mut self.paused else {
+                if ui.button("⏸ Pause").on_hover_text("Freeze the display; the feed keeps running").clicked() {
+                    self.pause(live);
+                }
+                return;
+            };
+            let resume = ui.button("▶ Live").on_hover_text("Resume the live view").clicked();
+            let oldest = history.oldest_timestamp().unwrap_or(paused.paused_at).min(paused.paused_at);
+            let span = (paused.paused_at - oldest) as f64 / 1000.0;
+            paused.offset = paused.offset.clamp(-span, 0.0);
+            ui.label("Scrub (s):");
+            ui.add_enabled(span > 0.0, egui::Slider::new(&mut paused.offset, -span..=0.0).fixed_decimals(1));
+            paused.scrub(history);
+            let paused_for = (chrono::Utc::now().timestamp_millis() as u64).saturating_sub(paused.paused_at);
+            ui.colored_label(Color32::YELLOW, format!("Paused {}s ago, feed still buffering", paused_for / 1000));
+            if paused.sample.is_some() {
+                ui.label("(sampled levels, no queue estimate)");
+            }
+            if resume {
+                self.resume();
+            }
+        });
+    }
+}
+
+impl Paused {
+    // Loads the history sample at the slider position, if it moved to another one
+    fn scrub(&mut self, history: &BookHistory) {
+        if self.offset >= 0.0 {
+            self.sample = None;
+            return;
+        }
+        let at = (self.paused_at as f64 + self.offset * 1000.0) as u64;
+        let Some(frame) = history.frame_at(at) else {
+            return;
+        };
+        if self.sample.as_ref().is_some_and(|(timestamp, _)| *timestamp == frame.timestamp) {
+            return;
+        }
+        // Grouping, precision and metrics depth come from the paused book
+        let mut book = self.book.clone();
+        book.clear();
+        book.apply_snapshot(&OrderBookSnapshot {
+            last_update_id: 1,
+            event_time: frame.timestamp,
+            recv_time: frame.timestamp,
+            bids: frame.bids,
+            asks: frame.asks,
+        });
+        self.sample = Some((frame.timestamp, book));
+    }
+}
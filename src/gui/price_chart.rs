@@ -0,0 +1,75 @@
+use crate::anomaly::{Anomaly, AnomalyKind};
+use crate::exchanges::{Side, Trade};
+use crate::history::BookHistory;
+use eframe::egui;
+use egui::Color32;
+use egui_plot::{Line, Plot, PlotPoints, Points, VLine};
+use rust_decimal::prelude::*;
+use std::collections::VecDeque;
+
+// Marker color of each kind of anomaly, also used in the alert log
+pub fn anomaly_color(kind: AnomalyKind) -> Color32 {
+    match kind {
+        AnomalyKind::RateSpike | AnomalyKind::RateDrop => Color32::from_rgb(200, 120, 255),
+        AnomalyKind::SpreadWidening => Color32::ORANGE,
+        AnomalyKind::DepthEvaporation => Color32::LIGHT_RED,
+    }
+}
+
+// Mid price over the last `lookback_secs`, x in seconds relative to the newest sample (or to
+// `until` while paused), with trade prints overlaid (green for buyer-initiated, red for
+// seller-initiated) and anomalies marked as vertical lines
+pub fn show(
+    ui: &mut egui::Ui,
+    history: &BookHistory,
+    trades: &VecDeque<Trade>,
+    anomalies: &VecDeque<Anomaly>,
+    until: Option<u64>,
+    lookback_secs: &mut u64,
+    price_prec: usize,
+) {
+    ui.horizontal(|ui| {
+        ui.label("Mid Price");
+        ui.label("Lookback (s):");
+        let max_lookback = history.settings().retention.as_secs();
+        ui.add(egui::Slider::new(lookback_secs, 5..=max_lookback.max(5)));
+        let shown = history.mids().take_while(|&(timestamp, _)| until.is_none_or(|until| timestamp <= until));
+        if let Some(mid) = shown.last().and_then(|(_, mid)| mid) {
+            ui.label(format!("{:.1$}", mid.to_f64().unwrap_or(0.0), price_prec + 1));
+        }
+    });
+
+    let Some(end) = until.or(history.latest().map(|f| f.timestamp)) else {
+        return;
+    };
+    let cutoff = end.saturating_sub(*lookback_secs * 1000);
+    let window = |timestamp: u64| timestamp >= cutoff && timestamp <= end;
+    let points: PlotPoints = history
+        .mids()
+        .filter(|&(timestamp, _)| window(timestamp))
+        .filter_map(|(timestamp, mid)| Some([(timestamp as f64 - end as f64) / 1000.0, mid?.to_f64()?]))
+        .collect();
+    let prints = |aggressor: Side| -> PlotPoints {
+        trades
+            .iter()
+            .filter(|t| t.aggressor == aggressor && window(t.timestamp))
+            .filter_map(|t| Some([(t.timestamp as f64 - end as f64) / 1000.0, t.price.to_f64()?]))
+            .collect()
+    };
+    let (buys, sells) = (prints(Side::Bid), prints(Side::Ask));
+
+    Plot::new("mid_price_chart")
+        .allow_drag(false)
+        .allow_scroll(false)
+        .allow_zoom(false)
+        .x_axis_label("seconds")
+        .show(ui, |plot_ui| {
+            plot_ui.line(Line::new("mid", points).color(Color32::LIGHT_BLUE));
+            plot_ui.points(Points::new("buys", buys).color(Color32::GREEN).radius(2.0));
+            plot_ui.points(Points::new("sells", sells).color(Color32::RED).radius(2.0));
+            for anomaly in anomalies.iter().filter(|a| window(a.time)) {
+                let x = (anomaly.time as f64 - end as f64) / 1000.0;
+                plot_ui.vline(VLine::new(anomaly.describe(), x).color(anomaly_color(anomaly.kind)).width(1.5));
+            }
+        });
+}
@@ -0,0 +1,98 @@
+use super::bar_axes::BarAxes;
+use super::ViewMode;
+use crate::alerts::AlertSettings;
+use crate::anomaly::AnomalySettings;
+use crate::clustering::{ClusterParams, ClusteringAlgorithm};
+use crate::config::Theme;
+#[cfg(feature = "binance")]
+use crate::exchanges::binance::BinanceMarket;
+use crate::exchanges::{ExchangeSettings, ExchangeType, SizeUnit};
+use crate::palette::Palette;
+use serde::{Deserialize, Serialize};
+
+pub const STORAGE_KEY: &str = "ui_session";
+
+// Subscription of an open tab. The exchange is stored by name so a session saved by a build
+// with more exchange features still loads; tabs for missing exchanges are dropped.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SavedTab {
+    pub exchange: String,
+    pub symbol: String,
+    #[cfg(feature = "binance")]
+    #[serde(default)]
+    pub binance_market: BinanceMarket,
+    #[serde(default)]
+    pub testnet: bool,
+    pub group_ticks: u32,
+}
+
+impl SavedTab {
+    // Connector settings for the restored tab, starting from the ones given at startup
+    pub fn settings(&self, base: &ExchangeSettings) -> ExchangeSettings {
+        let mut settings = base.clone();
+        #[cfg(feature = "binance")]
+        {
+            settings.binance_market = self.binance_market;
+        }
+        settings.testnet = self.testnet;
+        settings
+    }
+
+    pub fn exchange_type(&self) -> Option<ExchangeType> {
+        ExchangeType::from_name(&self.exchange)
+    }
+}
+
+// UI state restored on the next launch; window geometry is saved separately by
+// `layout::MonitorLayouts`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct UiSession {
+    pub tabs: Vec<SavedTab>,
+    pub active: usize,
+    pub cluster_mode: bool,
+    pub cluster_algorithm: ClusteringAlgorithm,
+    pub cluster_params: ClusterParams,
+    pub view_mode: ViewMode,
+    #[serde(default)]
+    pub bar_axes: BarAxes,
+    pub mid_lookback_secs: u64,
+    #[serde(default)]
+    pub delta_secs: Option<u64>,
+    pub slippage_size: f64,
+    #[serde(default)]
+    pub alerts: AlertSettings,
+    // None in sessions saved before anomaly detection existed, which keep the config's settings
+    #[serde(default)]
+    pub anomalies: Option<AnomalySettings>,
+    #[serde(default)]
+    pub event_log: bool,
+    #[serde(default)]
+    pub flow_stats: bool,
+    #[serde(default)]
+    pub session_stats: bool,
+    #[serde(default)]
+    pub volume_profile: bool,
+    #[serde(default)]
+    pub profile_window_secs: Option<u64>,
+    #[serde(default)]
+    pub log_viewer: bool,
+    // Set from the theme editor; None in sessions saved before it existed
+    #[serde(default)]
+    pub theme: Option<Theme>,
+    #[serde(default)]
+    pub palette: Option<Palette>,
+    // None in sessions saved before size units existed, which keep the config's
+    #[serde(default)]
+    pub size_unit: Option<SizeUnit>,
+}
+
+impl UiSession {
+    // None on the first launch, or if the saved state no longer matches this version
+    pub fn load(storage: Option<&dyn eframe::Storage>) -> Option<Self> {
+        storage.and_then(|s| eframe::get_value(s, STORAGE_KEY))
+    }
+
+    pub fn save(&self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, STORAGE_KEY, self);
+    }
+}
@@ -0,0 +1,712 @@
+use super::heatmap;
+use super::playback::Playback;
+use crate::alerts::WhaleWatch;
+use crate::anomaly::AnomalyMonitor;
+use crate::audit::{AuditStats, BookDrift};
+use crate::exchanges::{ExchangeError, ExchangeSettings, ExchangeType, InstrumentInfo, InstrumentStats, Side, Trade};
+use crate::export::{ExportFormat, ExportOptions, Exporter};
+use crate::feed::{self, ConnectionStatus, Control, FeedMessage, FeedQueue, FeedWorker};
+use crate::flow::OrderFlow;
+use crate::history::{BookFrame, BookHistory, HistorySettings};
+use crate::live_orders::{QueueCheck, QueueCheckStats};
+use crate::orderbook::{InferredEvent, OrderBook, QueuePosition};
+use crate::session::{SessionStats, SessionTracker};
+use crate::volume_profile::VolumeProfile;
+use eframe::egui;
+use egui::Color32;
+use rust_decimal::prelude::*;
+use rust_decimal::Decimal;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use web_time::Instant;
+
+// Background tabs keep their books current but repaint at most this often
+const BACKGROUND_REPAINT: Duration = Duration::from_millis(500);
+
+// Messages the feed may queue between frames before it coalesces and then drops them
+const QUEUE_CAPACITY: usize = 10_000;
+// Backlog per frame above which the status line reports the UI as behind
+const BEHIND_WARNING: usize = 500;
+// Recent trades kept for the price chart
+const TRADE_CAPACITY: usize = 5000;
+// Inferred events kept for the event log panel
+const EVENT_LOG_CAPACITY: usize = 2000;
+// How often the estimate is checked against the venue's queues on order-level feeds
+const QUEUE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+// Levels listed in the queue check's hover text
+const QUEUE_CHECK_LEVELS: usize = 10;
+
+// Hypothetical order whose queue position is tracked against the estimated book
+#[derive(Clone, Copy, Debug)]
+pub struct MyOrder {
+    pub side: Side,
+    pub price: Decimal,
+    // Milliseconds since the Unix epoch
+    pub joined_at: u64,
+}
+
+// Identifies a subscription: exchange, market (e.g. Binance spot vs USD-M) and symbol
+pub type FeedKey = (ExchangeType, &'static str, String);
+
+// One subscription with its own feed worker, book and history
+pub struct BookTab {
+    pub exchange: ExchangeType,
+    pub symbol: String,
+    pub settings: ExchangeSettings,
+    pub book: OrderBook,
+    queue: Arc<FeedQueue>,
+    // Messages drained in the last frame
+    behind: usize,
+    // Dropping the worker (i.e. closing the tab) stops the feed
+    worker: FeedWorker,
+    // Whether the tab is on screen; the feed sink repaints immediately only if it is
+    visible: Arc<AtomicBool>,
+    pub history: BookHistory,
+    last_sample: Instant,
+    pub heatmap: heatmap::DepthHeatmap,
+    // Paused or scrubbed-back view; the book and history above stay live underneath
+    pub playback: Playback,
+    // Price grouping in multiples of the tick (1 = raw levels)
+    pub group_ticks: u32,
+    pub price_prec: usize,
+    pub qty_prec: usize,
+    // Contract size of the symbol's quantities, for showing them in base or quote units
+    pub contract: InstrumentInfo,
+    // Depth updates received in the current one-second window, and the last full window's count
+    update_window: (Instant, u32),
+    updates_per_sec: u32,
+    // The estimator's recent decisions, oldest first; only collected while the panel is open
+    pub events: VecDeque<InferredEvent>,
+    event_log: bool,
+    // Rolling statistics over the same events, always kept since the book table values its
+    // queues against them
+    pub flow: OrderFlow,
+    // Most recent trades on venues with a trade stream, oldest first
+    pub trades: VecDeque<Trade>,
+    // Traded volume by price over the app's profile window, bucketed like the book
+    pub profile: VolumeProfile,
+    connection: ConnectionStatus,
+    last_error: Option<ExchangeError>,
+    // Set when the stream stalls or drops, cleared by the next snapshot
+    book_stale: bool,
+    // The book kept crossing and a fresh snapshot was requested
+    book_crossed: bool,
+    // Mark, funding and open interest on perp venues, merged across messages
+    instrument: Option<InstrumentStats>,
+    // Drift found by the audit snapshots, in audit mode
+    audit: AuditStats,
+    // The estimate against the venue's own queues, on order-level feeds
+    queue_checks: QueueCheckStats,
+    last_queue_check: Instant,
+    // Uptime, reconnects and message counts since the tab was opened
+    session: SessionTracker,
+    // Running CSV/Parquet export of this tab's book, sampled as messages are drained
+    exporter: Option<Exporter>,
+    export_format: ExportFormat,
+    export_error: Option<String>,
+    // Large order alerts; the settings are shared by all tabs and set by the app
+    pub whales: WhaleWatch,
+    // Message rate, spread and depth anomalies; the settings are shared like the alerts'
+    pub anomalies: AnomalyMonitor,
+    pub my_order: Option<MyOrder>,
+    // Inputs for placing the next hypothetical order
+    my_order_side: Side,
+    my_order_price: String,
+}
+
+impl BookTab {
+    // Starts the tab's feed worker on `runtime`
+    pub fn open(
+        runtime: &crate::exchanges::SpawnHandle,
+        ctx: &egui::Context,
+        exchange: ExchangeType,
+        symbol: String,
+        settings: ExchangeSettings,
+        history: HistorySettings,
+    ) -> Self {
+        let queue = FeedQueue::new(QUEUE_CAPACITY);
+        let feed_queue = queue.clone();
+        let visible = Arc::new(AtomicBool::new(true));
+        let ctx = ctx.clone();
+        let on_screen = visible.clone();
+        let sink: feed::FeedSink = Arc::new(move |msg| {
+            let repaint = !matches!(msg, FeedMessage::Order(_));
+            feed_queue.push(msg);
+            if !repaint {
+                return;
+            }
+            if on_screen.load(Ordering::Relaxed) {
+                ctx.request_repaint();
+            } else {
+                ctx.request_repaint_after(BACKGROUND_REPAINT);
+            }
+        });
+        let worker = FeedWorker::spawn(runtime, sink, symbol.clone(), exchange, settings.clone());
+        let mut book = OrderBook::new();
+        book.set_max_depth(settings.depth(exchange).estimation);
+        book.set_vwap_notional(settings.vwap_notional);
+        book.set_estimator(settings.estimator);
+        // Inferred events feed the order-flow statistics
+        book.set_event_log(true);
+
+        Self {
+            exchange,
+            symbol,
+            settings,
+            book,
+            queue,
+            behind: 0,
+            worker,
+            visible,
+            history: BookHistory::new(history),
+            last_sample: Instant::now(),
+            heatmap: heatmap::DepthHeatmap::default(),
+            playback: Playback::default(),
+            group_ticks: 1,
+            // Placeholder until the feed reports the symbol's precision
+            price_prec: 2,
+            qty_prec: 2,
+            contract: InstrumentInfo::BASE,
+            update_window: (Instant::now(), 0),
+            updates_per_sec: 0,
+            events: VecDeque::new(),
+            event_log: false,
+            flow: OrderFlow::default(),
+            trades: VecDeque::new(),
+            profile: VolumeProfile::default(),
+            connection: ConnectionStatus::Connecting,
+            last_error: None,
+            book_stale: false,
+            book_crossed: false,
+            instrument: None,
+            audit: AuditStats::default(),
+            queue_checks: QueueCheckStats::default(),
+            last_queue_check: Instant::now(),
+            session: SessionTracker::new(),
+            exporter: None,
+            export_format: ExportFormat::Csv,
+            export_error: None,
+            whales: WhaleWatch::default(),
+            anomalies: AnomalyMonitor::default(),
+            my_order: None,
+            my_order_side: Side::Bid,
+            my_order_price: String::new(),
+        }
+    }
+
+    pub fn key(&self) -> FeedKey {
+        (self.exchange, self.exchange.market_label(&self.settings), self.symbol.to_lowercase())
+    }
+
+    pub fn title(&self) -> String {
+        let testnet = if self.settings.testnet && self.exchange.has_testnet() { " (testnet)" } else { "" };
+        format!("{} {}{testnet}", self.exchange.name(), self.symbol.to_uppercase())
+    }
+
+    pub fn connection(&self) -> ConnectionStatus {
+        self.connection
+    }
+
+    pub fn session_stats(&self) -> SessionStats {
+        self.session.stats(&self.book.counters())
+    }
+
+    pub fn set_visible(&self, visible: bool) {
+        self.visible.store(visible, Ordering::Relaxed);
+    }
+
+    // Starts or stops collecting the estimator's events for the event log
+    pub fn set_event_log(&mut self, enabled: bool) {
+        self.event_log = enabled;
+        if !enabled {
+            self.events.clear();
+        }
+    }
+
+    // Applies everything the feed delivered since the last frame and samples the history
+    pub fn drain(&mut self) {
+        let messages = self.queue.drain();
+        self.behind = messages.len();
+        for msg in messages {
+            self.session.record(&msg);
+            self.anomalies.record(&msg);
+            match msg {
+                FeedMessage::Snapshot(snap) => {
+                    self.book_stale = false;
+                    self.book_crossed = false;
+                    self.book.apply_snapshot(&snap);
+                    self.whales.reset();
+                }
+                FeedMessage::Status(status) => {
+                    self.connection = status;
+                    if matches!(status, ConnectionStatus::Stale | ConnectionStatus::Disconnected) {
+                        self.book_stale = true;
+                    }
+                }
+                FeedMessage::Error(e) => {
+                    self.last_error = Some(e);
+                }
+                FeedMessage::InstrumentStats(stats) => {
+                    self.instrument.get_or_insert_with(InstrumentStats::default).merge(&stats);
+                }
+                FeedMessage::Resync => self.book.resync(),
+                FeedMessage::Overflow(dropped) => {
+                    // Too far behind to catch up from the queue: start over from a fresh snapshot
+                    self.last_error = Some(ExchangeError::Desync(format!(
+                        "UI fell behind the feed, dropped {dropped} messages"
+                    )));
+                    self.book_stale = true;
+                    self.book.resync();
+                    self.worker.send(Control::Refetch);
+                }
+                FeedMessage::Audit(snapshot) => {
+                    self.book.flush_updates();
+                    if self.book.is_synced() {
+                        self.audit.record(BookDrift::compare(&self.book, &snapshot));
+                    }
+                }
+                FeedMessage::Precision(price_prec, qty_prec) => {
+                    let changed = price_prec != self.price_prec;
+                    self.price_prec = price_prec;
+                    self.qty_prec = qty_prec;
+                    self.book.set_qty_decimals(qty_prec);
+                    // The grouping bucket is a multiple of the tick implied by the precision
+                    if changed && self.group_ticks > 1 {
+                        self.apply_grouping();
+                    }
+                }
+                FeedMessage::Instrument(contract) => self.contract = contract,
+                FeedMessage::Update(update) => {
+                    self.update_window.1 += 1;
+                    self.book.queue_update(update);
+                }
+                FeedMessage::FullState(state) => {
+                    self.update_window.1 += 1;
+                    self.book_stale = false;
+                    self.book_crossed = false;
+                    self.book.apply_full_state(&state);
+                }
+                FeedMessage::Trade(trade) => {
+                    self.book.apply_trade(&trade);
+                    self.profile.record(&trade);
+                    if self.trades.len() == TRADE_CAPACITY {
+                        self.trades.pop_front();
+                    }
+                    self.trades.push_back(trade);
+                }
+                // Ground-truth orders from venues with an order-level feed
+                FeedMessage::Order(event) => self.book.apply_order_event(&event),
+            }
+        }
+        // Everything drained this frame goes out as one delta
+        self.book.flush_updates();
+        if self.book.needs_resync() {
+            self.book_crossed = true;
+            self.book.resync();
+            self.worker.send(Control::Refetch);
+        }
+        let (best_bid, best_ask) = (self.book.best_bid(), self.book.best_ask());
+        for event in self.book.drain_events() {
+            self.flow.record(&event, best_bid, best_ask);
+            if !self.event_log {
+                continue;
+            }
+            if self.events.len() == EVENT_LOG_CAPACITY {
+                self.events.pop_front();
+            }
+            self.events.push_back(event);
+        }
+        self.whales.scan(&self.book);
+        if self.exchange.has_order_feed() && self.last_queue_check.elapsed() >= QUEUE_CHECK_INTERVAL {
+            self.last_queue_check = Instant::now();
+            if self.book.is_synced() {
+                self.queue_checks.record(QueueCheck::compare(&self.book));
+            }
+        }
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        self.anomalies.sample(&self.book, now);
+        self.profile.expire(now);
+        let sampling = *self.history.settings();
+        if self.book.last_update_id() != 0 && self.last_sample.elapsed() >= sampling.sample_interval {
+            self.last_sample = Instant::now();
+            let now = chrono::Utc::now().timestamp_millis() as u64;
+            self.history.push(BookFrame::from_book(&self.book, sampling.depth, now));
+        }
+        if let Some(exporter) = &mut self.exporter {
+            if let Err(e) = exporter.sample(&self.book) {
+                self.export_error = Some(format!("Export to {} failed: {e}", exporter.path().display()));
+                self.exporter = None;
+            }
+        }
+        if self.update_window.0.elapsed() >= Duration::from_secs(1) {
+            self.updates_per_sec = self.update_window.1;
+            self.update_window = (Instant::now(), 0);
+        }
+    }
+
+    // Tick size is inferred from the price precision
+    pub fn apply_grouping(&mut self) {
+        let bucket = (self.group_ticks > 1)
+            .then(|| Decimal::new(1, self.price_prec as u32) * Decimal::from(self.group_ticks));
+        self.book.set_grouping(bucket);
+        self.profile.set_bucket(bucket);
+        self.history.clear();
+        self.heatmap.clear();
+        self.playback.resume();
+    }
+
+    // Heading, connection state, indicators and venue-specific controls
+    pub fn header(&mut self, ui: &mut egui::Ui) {
+        ui.heading(format!(
+            "{} {} {} Order Book",
+            self.exchange.name(),
+            self.symbol.to_uppercase(),
+            self.exchange.market_label(&self.settings)
+        ));
+        self.status_line(ui);
+        self.playback.controls(ui, &self.book, &self.history);
+        self.stats_strip(ui);
+        self.instrument_strip(ui);
+        if self.exchange.has_order_feed() {
+            self.queue_check_line(ui);
+        }
+        #[cfg(feature = "synthetic")]
+        if self.exchange == ExchangeType::Synthetic {
+            self.synthetic_controls(ui);
+        }
+        self.my_order_controls(ui);
+        self.export_controls(ui);
+    }
+
+    // Current estimate for the hypothetical order, if one is placed
+    pub fn my_order_position(&self) -> Option<(MyOrder, QueuePosition)> {
+        let order = self.my_order?;
+        Some((order, self.book.queue_position(order.side, order.price, order.joined_at)))
+    }
+
+    // Places a hypothetical order at a price and shows its estimated queue position
+    fn my_order_controls(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("My order:");
+            match self.my_order_position() {
+                Some((order, position)) => {
+                    let side = side_label(order.side);
+                    let now = chrono::Utc::now().timestamp_millis() as u64;
+                    ui.label(format!(
+                        "{side} {:.2$}, joined {}s ago",
+                        order.price.to_f64().unwrap_or(0.0),
+                        now.saturating_sub(order.joined_at) / 1000,
+                        self.price_prec
+                    ));
+                    ui.separator();
+                    if position.traded_through {
+                        ui.colored_label(Color32::GREEN, "Price traded through, would have filled");
+                    } else {
+                        ui.label(format!(
+                            "{} orders / {:.3$} ahead of {:.3$} at the level",
+                            position.orders_ahead,
+                            position.qty_ahead.to_f64().unwrap_or(0.0),
+                            position.level_qty.to_f64().unwrap_or(0.0),
+                            self.qty_prec
+                        ));
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.my_order = None;
+                    }
+                }
+                None => {
+                    egui::ComboBox::from_id_salt("my_order_side")
+                        .selected_text(side_label(self.my_order_side))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.my_order_side, Side::Bid, "Bid");
+                            ui.selectable_value(&mut self.my_order_side, Side::Ask, "Ask");
+                        });
+                    ui.add(egui::TextEdit::singleline(&mut self.my_order_price).hint_text("price").desired_width(90.0));
+                    let price = self.my_order_price.trim().parse::<Decimal>().ok().filter(|p| *p > Decimal::ZERO);
+                    let join = ui
+                        .add_enabled(price.is_some(), egui::Button::new("Join"))
+                        .on_hover_text("Track an order joining the back of this level's queue now");
+                    if let (true, Some(price)) = (join.clicked(), price) {
+                        self.my_order = Some(MyOrder {
+                            side: self.my_order_side,
+                            price,
+                            joined_at: chrono::Utc::now().timestamp_millis() as u64,
+                        });
+                    }
+                }
+            }
+        });
+    }
+
+    // Starts/stops writing book rows (top levels, spread, imbalance, order counts) to a file
+    fn export_controls(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Export:");
+            match &mut self.exporter {
+                Some(exporter) => {
+                    ui.label(format!("{} rows to {}", exporter.rows(), exporter.path().display()));
+                    if ui.button("Stop").clicked() {
+                        if let Err(e) = exporter.close() {
+                            self.export_error = Some(format!("Failed to finish {}: {e}", exporter.path().display()));
+                        }
+                        self.exporter = None;
+                    }
+                }
+                None => {
+                    if ExportFormat::ALL.len() > 1 {
+                        egui::ComboBox::from_id_salt("export_format")
+                            .selected_text(self.export_format.extension())
+                            .show_ui(ui, |ui| {
+                                for &format in ExportFormat::ALL {
+                                    ui.selectable_value(&mut self.export_format, format, format.extension());
+                                }
+                            });
+                    }
+                    if ui.button("Start").on_hover_text("Write a row per second to a file in the working directory").clicked() {
+                        let options = ExportOptions::with_default_path(self.exchange, &self.symbol, self.export_format);
+                        match Exporter::create(options, self.exchange, &self.symbol) {
+                            Ok(exporter) => {
+                                self.exporter = Some(exporter);
+                                self.export_error = None;
+                            }
+                            Err(e) => self.export_error = Some(format!("Cannot start export: {e}")),
+                        }
+                    }
+                }
+            }
+            if let Some(e) = &self.export_error {
+                ui.colored_label(Color32::LIGHT_RED, e);
+            }
+        });
+    }
+
+    // Connection state indicator and the most recent error, if any
+    fn status_line(&self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.colored_label(status_color(self.connection), format!("● {}", self.connection.label()));
+            if self.book_crossed {
+                ui.colored_label(Color32::LIGHT_RED, "Book crossed, resyncing");
+            } else if self.book_stale {
+                ui.colored_label(Color32::ORANGE, "Book stale, waiting for snapshot");
+            } else if self.book.crossings() > 0 {
+                ui.colored_label(Color32::ORANGE, format!("Crossed {}x, pruned", self.book.crossings()))
+                    .on_hover_text("Updates left bids at or above the best ask; the stale side's levels were dropped");
+            }
+            if self.behind >= BEHIND_WARNING {
+                ui.colored_label(Color32::ORANGE, format!("Behind by {} messages", self.behind));
+            }
+            let latency = self.worker.latency();
+            ui.separator();
+            let label = if latency.samples > 0 {
+                format!("latency p50 {} ms p99 {} ms | {} msg/s", latency.p50_ms, latency.p99_ms, latency.messages_per_sec)
+            } else {
+                format!("{} msg/s", latency.messages_per_sec)
+            };
+            ui.label(label).on_hover_text(format!(
+                "Local receive time minus the exchange's event time over the last {} messages \
+                 (min {} ms, max {} ms). Includes clock skew between the exchange and this machine.",
+                latency.samples, latency.min_ms, latency.max_ms
+            ));
+            if let Some(drift) = &self.audit.last {
+                ui.separator();
+                let color = if drift.drifted() == 0 { Color32::LIGHT_GREEN } else { Color32::ORANGE };
+                let details = format!("Last REST snapshot: {}\nSession: {}", drift.describe(), self.audit.describe());
+                ui.colored_label(color, format!("audit: {}/{} levels off", drift.drifted(), drift.levels))
+                    .on_hover_text(details);
+            }
+            let stats = self.queue.stats();
+            if stats.coalesced > 0 || stats.overflows > 0 {
+                ui.label(format!("({} coalesced, {} resyncs)", stats.coalesced, stats.overflows))
+                    .on_hover_text("Updates merged or dropped because the UI could not keep up");
+            }
+            if let Some(e) = &self.last_error {
+                ui.separator();
+                ui.colored_label(Color32::LIGHT_RED, format!("Last error: {e}"));
+            }
+        });
+    }
+
+    // Estimated against known orders, and how the estimate compares with the venue's queues
+    fn queue_check_line(&self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "Estimated orders: {} | Venue orders known: {}",
+                self.book.order_count(),
+                self.book.live_orders().len()
+            ));
+            let Some(check) = &self.queue_checks.last else {
+                return;
+            };
+            ui.separator();
+            let mut details = format!("Last check: {}\nSession: {}\n", check.describe(), self.queue_checks.describe());
+            for level in check.worst(QUEUE_CHECK_LEVELS) {
+                let price = format!("{:.1$}", level.price.to_f64().unwrap_or(0.0), self.price_prec);
+                details.push_str(&format!(
+                    "\n{:?} {price}: {} estimated, {} at the venue, size error {}",
+                    level.side,
+                    level.estimated,
+                    level.venue,
+                    level.size_error.normalize()
+                ));
+            }
+            let color = if check.exact() == check.levels.len() { Color32::LIGHT_GREEN } else { Color32::ORANGE };
+            ui.colored_label(
+                color,
+                format!("vs venue: order count exact on {}/{} levels", check.exact(), check.levels.len()),
+            )
+            .on_hover_text(details);
+        });
+    }
+
+    // Funding rate, mark/index price and open interest for perp venues
+    fn instrument_strip(&self, ui: &mut egui::Ui) {
+        let Some(stats) = &self.instrument else {
+            return;
+        };
+        let price = |p: Option<Decimal>| match p {
+            Some(p) => format!("{:.1$}", p.to_f64().unwrap_or(0.0), self.price_prec),
+            None => "-".to_string(),
+        };
+        ui.horizontal(|ui| {
+            ui.label(format!("Mark: {}", price(stats.mark_price)));
+            ui.separator();
+            ui.label(format!("Index: {}", price(stats.index_price)));
+            ui.separator();
+            match stats.funding_rate {
+                Some(rate) => {
+                    let pct = rate.to_f64().unwrap_or(0.0) * 100.0;
+                    let color = if pct >= 0.0 { Color32::GREEN } else { Color32::RED };
+                    ui.colored_label(color, format!("Funding: {pct:+.4}%"));
+                }
+                None => {
+                    ui.label("Funding: -");
+                }
+            }
+            if let Some(next) = stats.next_funding_time {
+                let now = chrono::Utc::now().timestamp_millis() as u64;
+                let mins = next.saturating_sub(now) / 60_000;
+                ui.label(format!("(next in {}h {:02}m)", mins / 60, mins % 60));
+            }
+            ui.separator();
+            match stats.open_interest {
+                Some(oi) => ui.label(format!("Open interest: {:.1$}", oi.to_f64().unwrap_or(0.0), self.qty_prec)),
+                None => ui.label("Open interest: -"),
+            };
+        });
+    }
+
+    // Derived top-of-book indicators from the book engine
+    fn stats_strip(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let prec = self.price_prec + 1;
+            match self.playback.book(&self.book).metrics() {
+                Some(m) => {
+                    ui.label(format!("Mid: {:.1$}", m.mid.to_f64().unwrap_or(0.0), prec));
+                    ui.separator();
+                    ui.label(format!("Microprice: {:.1$}", m.microprice.to_f64().unwrap_or(0.0), prec));
+                    ui.separator();
+                    ui.label(format!("Weighted mid: {:.1$}", m.weighted_mid.to_f64().unwrap_or(0.0), prec));
+                    ui.separator();
+                    let imbalance = m.imbalance.to_f64().unwrap_or(0.0);
+                    let color = if imbalance >= 0.0 { Color32::GREEN } else { Color32::RED };
+                    ui.colored_label(color, format!("Imbalance: {imbalance:+.3}"));
+                }
+                None => {
+                    ui.label("Waiting for book...");
+                }
+            }
+            ui.label("over top");
+            let mut depth = self.book.metrics_depth();
+            if ui.add(egui::DragValue::new(&mut depth).range(1..=100)).changed() {
+                self.book.set_metrics_depth(depth);
+                self.playback.set_metrics_depth(depth);
+            }
+            ui.label("levels");
+        });
+        ui.horizontal(|ui| {
+            let book = self.playback.book(&self.book);
+            let Some(m) = book.metrics() else {
+                return;
+            };
+            let qty = |v: Decimal| format!("{:.1$}", v.to_f64().unwrap_or(0.0), self.qty_prec);
+            for band in &m.liquidity {
+                ui.label(format!("±{}bps:", band.bps));
+                ui.colored_label(Color32::GREEN, qty(band.bid));
+                ui.colored_label(Color32::RED, qty(band.ask));
+                ui.separator();
+            }
+            let prec = self.price_prec + 1;
+            let vwap = |v: Option<Decimal>| {
+                v.map_or("-".to_string(), |v| format!("{:.1$}", v.to_f64().unwrap_or(0.0), prec))
+            };
+            ui.label(format!("VWAP buy {} / sell {} for", vwap(m.buy_vwap), vwap(m.sell_vwap)))
+                .on_hover_text("Average fill price of a market order worth this much quote currency");
+            let mut notional = book.vwap_notional().to_f64().unwrap_or(0.0);
+            let drag = egui::DragValue::new(&mut notional).range(1.0..=1e9).speed(100.0);
+            if ui.add(drag).changed() {
+                if let Some(notional) = Decimal::from_f64_retain(notional.round()) {
+                    self.book.set_vwap_notional(notional);
+                    self.playback.set_vwap_notional(notional);
+                }
+            }
+        });
+    }
+
+    // Load generator tunables; applying them restarts the synthetic feed
+    #[cfg(feature = "synthetic")]
+    fn synthetic_controls(&mut self, ui: &mut egui::Ui) {
+        let config = &mut self.settings.synthetic;
+        ui.horizontal(|ui| {
+            ui.label("Updates/s:");
+            ui.add(egui::Slider::new(&mut config.update_rate, 1..=50_000).logarithmic(true));
+            ui.label("Depth:");
+            ui.add(egui::Slider::new(&mut config.depth, 10..=2_000).logarithmic(true));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Whale probability:");
+            ui.add(egui::Slider::new(&mut config.whale_probability, 0.0..=0.1));
+            ui.label("Whale lifetime (updates, 0 = rest):");
+            ui.add(egui::Slider::new(&mut config.whale_lifetime, 0..=10_000));
+        });
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut config.order_flow, "Simulated order flow")
+                .on_hover_text("Poisson arrivals and cancellations of individual orders instead of the random walk");
+            ui.label("Seed:");
+            ui.add_enabled(config.order_flow, egui::DragValue::new(&mut config.seed));
+        });
+        ui.horizontal(|ui| {
+            if ui.button("Apply").clicked() {
+                let _ = self.worker.send(Control::UpdateSettings(self.settings.clone()));
+                self.book.clear();
+                self.whales.reset();
+                self.instrument = None;
+                self.history.clear();
+                self.heatmap.clear();
+                self.profile.clear();
+                self.flow.clear();
+                self.playback.resume();
+            }
+            ui.label(format!(
+                "Received {} updates/s (target {})",
+                self.updates_per_sec, self.settings.synthetic.update_rate
+            ));
+        });
+    }
+}
+
+fn side_label(side: Side) -> &'static str {
+    match side {
+        Side::Bid => "Bid",
+        Side::Ask => "Ask",
+    }
+}
+
+pub fn status_color(status: ConnectionStatus) -> Color32 {
+    match status {
+        ConnectionStatus::Connected => Color32::GREEN,
+        ConnectionStatus::Connecting | ConnectionStatus::Stale => Color32::YELLOW,
+        ConnectionStatus::Disconnected => Color32::RED,
+    }
+}
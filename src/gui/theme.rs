@@ -0,0 +1,101 @@
+use crate::config::Theme;
+use crate::palette::{Palette, PalettePreset, Rgb, MAX_GRADIENT_STOPS};
+use eframe::egui;
+use egui::Color32;
+
+pub fn color(rgb: Rgb) -> Color32 {
+    let [r, g, b] = rgb.0;
+    Color32::from_rgb(r, g, b)
+}
+
+pub fn colors(stops: &[Rgb]) -> Vec<Color32> {
+    stops.iter().map(|&stop| color(stop)).collect()
+}
+
+// Switches egui to the theme and paints panels and windows with the palette's background
+pub fn apply(ctx: &egui::Context, theme: Theme, palette: &Palette) {
+    let (egui_theme, visuals) = match theme {
+        Theme::Dark => (egui::Theme::Dark, egui::Visuals::dark()),
+        Theme::Light => (egui::Theme::Light, egui::Visuals::light()),
+    };
+    ctx.set_theme(egui_theme);
+    ctx.style_mut_of(egui_theme, |style| {
+        style.visuals.panel_fill = palette.background.map_or(visuals.panel_fill, color);
+        style.visuals.window_fill = palette.background.map_or(visuals.window_fill, color);
+    });
+}
+
+// Theme, preset and per-color controls; true when anything changed. `configured` is the
+// palette from the config file, which "Reset" goes back to.
+pub fn editor(ui: &mut egui::Ui, theme: &mut Theme, palette: &mut Palette, configured: &Palette) -> bool {
+    let before = (*theme, palette.clone());
+    ui.horizontal(|ui| {
+        ui.label("Theme:");
+        ui.radio_value(theme, Theme::Dark, "Dark");
+        ui.radio_value(theme, Theme::Light, "Light");
+    });
+    ui.horizontal(|ui| {
+        ui.label("Preset:");
+        for preset in PalettePreset::ALL {
+            if ui.button(preset.name()).clicked() {
+                *palette = Palette {
+                    background: palette.background,
+                    ..Palette::preset(preset)
+                };
+            }
+        }
+        if ui.button("Reset").on_hover_text("Back to the palette from the config file").clicked() {
+            *palette = configured.clone();
+        }
+    });
+    ui.separator();
+    egui::Grid::new("palette_grid").num_columns(2).show(ui, |ui| {
+        for (label, rgb) in [
+            ("Bid orders", &mut palette.bid),
+            ("Ask orders", &mut palette.ask),
+            ("Largest order", &mut palette.largest),
+            ("Second largest", &mut palette.second_largest),
+        ] {
+            ui.label(label);
+            ui.color_edit_button_srgb(&mut rgb.0);
+            ui.end_row();
+        }
+        for (label, stops) in [
+            ("Bid clusters", &mut palette.bid_gradient),
+            ("Ask clusters", &mut palette.ask_gradient),
+        ] {
+            ui.label(label);
+            gradient_editor(ui, stops);
+            ui.end_row();
+        }
+        ui.label("Background");
+        ui.horizontal(|ui| {
+            let mut custom = palette.background.is_some();
+            if ui.checkbox(&mut custom, "Custom").changed() {
+                palette.background = custom.then_some(Rgb::new(24, 24, 28));
+            }
+            if let Some(background) = &mut palette.background {
+                ui.color_edit_button_srgb(&mut background.0);
+            }
+        });
+        ui.end_row();
+    });
+    before != (*theme, palette.clone())
+}
+
+// Stops of a cluster gradient, with buttons to add one (a copy of the last) or drop the last
+fn gradient_editor(ui: &mut egui::Ui, stops: &mut Vec<Rgb>) {
+    ui.horizontal(|ui| {
+        for stop in stops.iter_mut() {
+            ui.color_edit_button_srgb(&mut stop.0);
+        }
+        if stops.len() < MAX_GRADIENT_STOPS && ui.small_button("+").clicked() {
+            if let Some(&last) = stops.last() {
+                stops.push(last);
+            }
+        }
+        if stops.len() > 1 && ui.small_button("−").clicked() {
+            stops.pop();
+        }
+    });
+}
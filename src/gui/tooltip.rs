@@ -0,0 +1,97 @@
+use crate::live_orders::LiveOrder;
+use crate::orderbook::OrderQueue;
+use eframe::egui;
+use rust_decimal::prelude::*;
+
+// Orders listed in the tooltip before the rest are summed up
+const MAX_LISTED_ORDERS: usize = 25;
+
+// What the order bar chart shows about the level under the pointer
+pub struct LevelTooltip<'a> {
+    pub price: Decimal,
+    pub queue: &'a OrderQueue,
+    // Cluster of each order, front of the queue first, in clustering mode
+    pub clusters: Option<&'a [usize]>,
+    // The venue's own queue at the price on order-level feeds, front first; empty elsewhere
+    pub live: &'a [LiveOrder],
+    // Quantity stacked below the pointer, which picks out the order it is on
+    pub pointer_qty: f64,
+    // What one of the venue's size units comes to in the units shown, e.g. the price for notional
+    pub unit_size: Decimal,
+    // Reference time for order ages, ms since the Unix epoch
+    pub now: u64,
+    pub price_prec: usize,
+    pub qty_prec: usize,
+}
+
+impl LevelTooltip<'_> {
+    pub fn show(&self, ui: &mut egui::Ui) {
+        let qty = |size: Decimal| format!("{:.1$}", (size * self.unit_size).to_f64().unwrap_or(0.0), self.qty_prec);
+        ui.strong(format!("{:.1$}", self.price.to_f64().unwrap_or(0.0), self.price_prec));
+        ui.label(format!("Total {}", qty(self.queue.total())));
+        let reported = self.queue.reported_count().map(|count| format!(", venue reports {count}")).unwrap_or_default();
+        ui.label(format!("{} inferred orders{reported}", self.queue.len()));
+        if !self.live.is_empty() {
+            let total: Decimal = self.live.iter().map(|order| order.qty).sum();
+            ui.label(format!("{} orders at the venue, {} in total", self.live.len(), qty(total)));
+        }
+        ui.separator();
+        let mut below = 0.0;
+        egui::Grid::new("level_tooltip").num_columns(6).show(ui, |ui| {
+            ui.label("");
+            ui.label("Size");
+            ui.label("Age");
+            ui.label("Inference");
+            if self.clusters.is_some() {
+                ui.label("Cluster");
+            }
+            if !self.live.is_empty() {
+                ui.label("Venue");
+            }
+            ui.end_row();
+            // Estimated orders with the venue's order at the same place in the queue beside them
+            let rows = self.queue.len().max(self.live.len()).min(MAX_LISTED_ORDERS);
+            for i in 0..rows {
+                let Some(order) = self.queue.get(i) else {
+                    // The venue holds more orders than were inferred
+                    let blanks = if self.clusters.is_some() { 5 } else { 4 };
+                    for _ in 0..blanks {
+                        ui.label("");
+                    }
+                    ui.label(qty(self.live[i].qty));
+                    ui.end_row();
+                    continue;
+                };
+                let size = (order.size() * self.unit_size).to_f64().unwrap_or(0.0);
+                let hovered = (below..below + size).contains(&self.pointer_qty);
+                below += size;
+                ui.label(if hovered { "▶" } else { "" });
+                ui.label(qty(order.size()));
+                ui.label(age(self.now.saturating_sub(order.first_seen)));
+                ui.label(format!("{} ({:.0}%)", order.inference.label(), order.confidence() * 100.0));
+                if let Some(clusters) = self.clusters {
+                    ui.label(clusters.get(i).map_or("-".to_string(), |cluster| cluster.to_string()));
+                }
+                if !self.live.is_empty() {
+                    ui.label(self.live.get(i).map_or("-".to_string(), |order| qty(order.qty)));
+                }
+                ui.end_row();
+            }
+        });
+        let rest = self.queue.len().saturating_sub(MAX_LISTED_ORDERS);
+        if rest > 0 {
+            let size: Decimal = self.queue.sizes().skip(MAX_LISTED_ORDERS).sum();
+            ui.label(format!("… {rest} more, {} in total", qty(size)));
+        }
+    }
+}
+
+// e.g. "42s", "3m 05s", "2h 10m"
+fn age(ms: u64) -> String {
+    let secs = ms / 1000;
+    match secs {
+        0..60 => format!("{secs}s"),
+        60..3600 => format!("{}m {:02}s", secs / 60, secs % 60),
+        _ => format!("{}h {:02}m", secs / 3600, secs % 3600 / 60),
+    }
+}
@@ -0,0 +1,82 @@
+use super::price_step;
+use crate::volume_profile::VolumeProfile;
+use eframe::egui;
+use egui::Color32;
+use egui_plot::{Bar, BarChart, Plot, VLine};
+use rust_decimal::prelude::*;
+
+// Height of the strip under the book chart
+pub const PROFILE_HEIGHT: f32 = 140.0;
+
+// Axis link group of the profile and the book charts it is drawn under
+pub const PRICE_AXIS: &str = "book_price_axis";
+
+// Volume traded at each price bucket over the window, drawn under the book chart: buys in the
+// bid color with sells stacked on top in the ask color, and the point of control marked.
+// `range` is the price span of the chart above, which the profile is drawn over so the two line
+// up, and zooming or dragging that chart moves the profile with it.
+pub fn show(
+    ui: &mut egui::Ui,
+    profile: &VolumeProfile,
+    window_secs: &mut u64,
+    range: Option<(f64, f64)>,
+    (price_prec, qty_prec): (usize, usize),
+    colors: (Color32, Color32),
+) {
+    let poc = profile.point_of_control();
+    ui.horizontal(|ui| {
+        ui.label("Volume profile");
+        ui.label("Window (s):");
+        ui.add(egui::Slider::new(window_secs, 10..=3600).logarithmic(true));
+        ui.label(format!("{} trades", profile.trades()));
+        if let Some((price, level)) = poc {
+            ui.label(format!(
+                "POC {:.2$} ({:.3$})",
+                price.to_f64().unwrap_or(0.0),
+                level.total().to_f64().unwrap_or(0.0),
+                price_prec,
+                qty_prec
+            ));
+        }
+    });
+
+    let shown = |price: f64| range.is_none_or(|(low, high)| price >= low && price <= high);
+    let levels: Vec<_> = profile
+        .levels()
+        .iter()
+        .filter_map(|(price, level)| Some((price.to_f64()?, level.buy.to_f64()?, level.sell.to_f64()?)))
+        .filter(|&(price, ..)| shown(price))
+        .collect();
+    let step = match profile.bucket() {
+        Some(bucket) => bucket.to_f64().unwrap_or(1.0),
+        None => price_step(profile.levels().keys().copied()),
+    };
+    let buys = BarChart::new(
+        "buys",
+        levels.iter().map(|&(price, buy, _)| Bar::new(price, buy).width(step * 0.9).fill(colors.0)).collect(),
+    )
+    .allow_hover(false);
+    let sells = BarChart::new(
+        "sells",
+        levels.iter().map(|&(price, _, sell)| Bar::new(price, sell).width(step * 0.9).fill(colors.1)).collect(),
+    )
+    .allow_hover(false)
+    .stack_on(&[&buys]);
+
+    let mut plot = Plot::new("volume_profile")
+        .link_axis(PRICE_AXIS, [true, false])
+        .allow_scroll(false)
+        .show_x(false)
+        .show_y(false)
+        .x_axis_formatter(move |mark, _| format!("{:.1$}", mark.value, price_prec));
+    if let Some((low, high)) = range {
+        plot = plot.include_x(low).include_x(high);
+    }
+    plot.show(ui, |plot_ui| {
+        plot_ui.bar_chart(buys);
+        plot_ui.bar_chart(sells);
+        if let Some(price) = poc.and_then(|(price, _)| price.to_f64()).filter(|&price| shown(price)) {
+            plot_ui.vline(VLine::new("point of control", price).color(Color32::GOLD).width(1.0));
+        }
+    });
+}
@@ -0,0 +1,441 @@
+use crate::alerts::{AlertNotifier, AlertSettings, AlertTarget, WhaleWatch};
+use crate::anomaly::{AnomalyMonitor, AnomalySettings};
+use crate::archive::{ArchiveOptions, Archiver};
+use crate::audit::{AuditStats, BookDrift};
+use crate::exchanges::{self, ExchangeSettings, ExchangeType, InstrumentStats};
+use crate::export::{ExportOptions, Exporter};
+use crate::feed::{self, Control, FeedMessage, FeedQueue, FeedWorker};
+use crate::flow::OrderFlow;
+#[cfg(feature = "grpc")]
+use crate::grpc::GrpcServer;
+use crate::live_orders::{QueueCheck, QueueCheckStats};
+use crate::orderbook::OrderBook;
+use crate::publish::{EventBody, PublishOptions, Publisher};
+use crate::resume::{ResumeOptions, StateSaver};
+use crate::server::{BookMessage, BookServer, ServeOptions};
+use crate::session::SessionTracker;
+#[cfg(feature = "sqlite")]
+use crate::store::EventStore;
+use rust_decimal::Decimal;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// Messages the feed may queue ahead of the loop before it coalesces and then drops them
+const QUEUE_CAPACITY: usize = 10_000;
+
+// Book states are published to WebSocket clients at most this often
+const PUBLISH_INTERVAL: Duration = Duration::from_millis(100);
+
+// Optional outputs besides the console summary
+#[derive(Clone, Debug, Default)]
+pub struct HeadlessOptions {
+    // Publish the estimated book to WebSocket clients
+    pub serve: Option<ServeOptions>,
+    // Serve the estimated book and the inferred orders over gRPC (`grpc` feature)
+    pub grpc: Option<ServeOptions>,
+    // Periodically write book rows to a CSV/Parquet file
+    pub export: Option<ExportOptions>,
+    // Log large order alerts
+    pub alerts: AlertSettings,
+    // Also deliver the alerts to webhooks or Telegram
+    pub alert_targets: Vec<AlertTarget>,
+    // Log message rate, spread and depth anomalies, and deliver them with the alerts
+    pub anomalies: AnomalySettings,
+    // Publish snapshots, diffs, inferred order events and trades to NATS or Kafka
+    pub publish: Option<PublishOptions>,
+    // Periodically write the full estimated book to timestamped files
+    pub archive: Option<ArchiveOptions>,
+    // Record the inferred order lifecycles to this SQLite database (`sqlite` feature)
+    pub events_db: Option<PathBuf>,
+    // Save the book and session statistics, and resume from them on the next start
+    pub resume: Option<ResumeOptions>,
+}
+
+// Runs the feed and estimator without a GUI, logging a book summary every second and printing a
+// JSON report of the run on exit. Ctrl-C stops it cleanly so export files are complete.
+pub fn run(
+    symbol: String,
+    exchange: ExchangeType,
+    settings: ExchangeSettings,
+    options: HeadlessOptions,
+) -> std::io::Result<()> {
+    // Everything but the final report goes to the log under the feed's labels
+    let _span = tracing::info_span!("headless", exchange = exchange.name(), symbol = %symbol).entered();
+    let queue = FeedQueue::new(QUEUE_CAPACITY);
+    let feed_queue = queue.clone();
+    let sink: feed::FeedSink = Arc::new(move |msg| feed_queue.push(msg));
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    // The feed shuts down once the worker is dropped
+    let max_depth = settings.depth(exchange).estimation;
+    let vwap_notional = settings.vwap_notional;
+    let estimator = settings.estimator;
+    let worker = FeedWorker::spawn(runtime.handle(), sink, symbol.clone(), exchange, settings);
+    let stop = Arc::new(AtomicBool::new(false));
+    let on_signal = stop.clone();
+    runtime.spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            on_signal.store(true, Ordering::Relaxed);
+        }
+    });
+    let server = options
+        .serve
+        .map(|serve| BookServer::start(runtime.handle(), serve.addr).map(|server| (server, serve.depth)))
+        .transpose()?;
+    let mut exporter = options
+        .export
+        .map(|export| Exporter::create(export, exchange, &symbol))
+        .transpose()?;
+    #[cfg(feature = "grpc")]
+    let grpc = options
+        .grpc
+        .map(|serve| GrpcServer::start(runtime.handle(), serve.addr, exchange, &symbol).map(|grpc| (grpc, serve.depth)))
+        .transpose()?;
+    #[cfg(not(feature = "grpc"))]
+    if options.grpc.is_some() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "the gRPC server needs a build with --features grpc",
+        ));
+    }
+    #[cfg(feature = "sqlite")]
+    let mut store = options
+        .events_db
+        .map(|path| EventStore::open(&path, exchange, &symbol))
+        .transpose()?;
+    #[cfg(not(feature = "sqlite"))]
+    if options.events_db.is_some() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "recording inferred orders needs a build with --features sqlite",
+        ));
+    }
+    let mut archiver = options
+        .archive
+        .map(|archive| Archiver::start(archive, exchange, &symbol))
+        .transpose()?;
+    let publisher = options
+        .publish
+        .map(|publish| Publisher::start(runtime.handle(), publish, exchange, &symbol))
+        .transpose()?;
+
+    let mut book = OrderBook::new();
+    book.set_max_depth(max_depth);
+    book.set_vwap_notional(vwap_notional);
+    book.set_estimator(estimator);
+    // Inferred events feed the order-flow statistics and the publisher
+    book.set_event_log(true);
+    let mut flow = OrderFlow::default();
+    let mut whales = WhaleWatch::new(options.alerts);
+    let mut anomalies = AnomalyMonitor::new(options.anomalies);
+    let notifier = (!options.alert_targets.is_empty())
+        .then(|| AlertNotifier::new(runtime.handle().clone(), options.alert_targets));
+    let mut last_print = Instant::now();
+    let mut instrument: Option<InstrumentStats> = None;
+    let mut audit = AuditStats::default();
+    // The estimate against the venue's own queues, on order-level feeds
+    let mut queue_checks = QueueCheckStats::default();
+    let mut session = SessionTracker::new();
+    let mut saver = options.resume.as_ref().map(|resume| StateSaver::new(resume, exchange, &symbol));
+    // Loaded in place of the first snapshot, which it is then reconciled against
+    let mut resumed = None;
+    if let Some(saver) = &saver {
+        match saver.load() {
+            Ok(Some(saved)) => {
+                let saved_at = chrono::DateTime::from_timestamp_millis(saved.saved_at as i64).unwrap_or_default();
+                tracing::info!("Resuming the state saved at {saved_at}");
+                session = SessionTracker::resumed(saved.session);
+                resumed = Some(saved.book);
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Could not load {}, starting afresh: {e}", saver.path().display()),
+        }
+    }
+    let mut last_publish = Instant::now();
+    let mut changed = false;
+    while !stop.load(Ordering::Relaxed) {
+        // Inferred events of this pass, and how many of them the event store has
+        let mut events = Vec::new();
+        #[cfg(feature = "sqlite")]
+        let mut stored = 0;
+        for message in queue.drain_timeout(Duration::from_millis(200)) {
+            session.record(&message);
+            anomalies.record(&message);
+            if matches!(
+                message,
+                FeedMessage::Snapshot(_)
+                    | FeedMessage::Update(_)
+                    | FeedMessage::FullState(_)
+                    | FeedMessage::Resync
+                    | FeedMessage::Overflow(_)
+            ) {
+                changed = true;
+            }
+            if let Some(publisher) = &publisher {
+                match &message {
+                    FeedMessage::Snapshot(snap) | FeedMessage::FullState(snap) => {
+                        publisher.publish(event_time(snap.event_time, snap.recv_time), EventBody::snapshot(snap))
+                    }
+                    FeedMessage::Update(update) => {
+                        publisher.publish(event_time(update.event_time, update.recv_time), EventBody::diff(update))
+                    }
+                    FeedMessage::Trade(trade) => publisher.publish(trade.timestamp, EventBody::trade(trade)),
+                    _ => {}
+                }
+            }
+            match message {
+                FeedMessage::Snapshot(snap) => {
+                    // The replaced book's last events are stored before the snapshot closes its orders
+                    book.flush_updates();
+                    events.extend(book.drain_events());
+                    #[cfg(feature = "sqlite")]
+                    if let Some(store) = &mut store {
+                        store.record(events[stored..].to_vec());
+                        store.reset(event_time(snap.event_time, snap.recv_time));
+                        stored = events.len();
+                    }
+                    match resumed.take() {
+                        Some(saved) => book.resume(&saved, &snap),
+                        None => book.apply_snapshot(&snap),
+                    };
+                    whales.reset();
+                }
+                FeedMessage::Update(update) => {
+                    book.queue_update(update);
+                }
+                FeedMessage::FullState(state) => {
+                    match resumed.take().filter(|_| !book.is_synced()) {
+                        Some(saved) => book.resume(&saved, &state),
+                        None => book.apply_full_state(&state),
+                    };
+                }
+                FeedMessage::Status(status) => tracing::info!("Feed status: {}", status.label()),
+                FeedMessage::Error(e) => tracing::error!("Feed error: {e}"),
+                FeedMessage::Resync => book.resync(),
+                FeedMessage::Overflow(dropped) => {
+                    tracing::warn!("Fell behind, dropped {dropped} messages; resyncing");
+                    book.resync();
+                    worker.send(Control::Refetch);
+                }
+                FeedMessage::InstrumentStats(stats) => {
+                    instrument.get_or_insert_with(InstrumentStats::default).merge(&stats);
+                }
+                FeedMessage::Precision(..) | FeedMessage::Instrument(_) => {}
+                FeedMessage::Audit(snapshot) => {
+                    book.flush_updates();
+                    if book.is_synced() {
+                        let drift = BookDrift::compare(&book, &snapshot);
+                        tracing::info!("Audit: {}", drift.describe());
+                        audit.record(drift);
+                    }
+                }
+                FeedMessage::Trade(trade) => book.apply_trade(&trade),
+                FeedMessage::Order(event) => book.apply_order_event(&event),
+            }
+        }
+        book.flush_updates();
+        if book.needs_resync() {
+            tracing::warn!("Book crossed {} times; resyncing", book.crossings());
+            book.resync();
+            worker.send(Control::Refetch);
+        }
+        let (best_bid, best_ask) = (book.best_bid(), book.best_ask());
+        events.extend(book.drain_events());
+        for event in &events {
+            flow.record(event, best_bid, best_ask);
+            if let Some(publisher) = &publisher {
+                publisher.publish(event.time, EventBody::order(event));
+            }
+            #[cfg(feature = "grpc")]
+            if let Some((grpc, _)) = &grpc {
+                grpc.publish_order(event);
+            }
+        }
+        #[cfg(feature = "sqlite")]
+        if let Some(store) = &mut store {
+            store.record(events.split_off(stored));
+        }
+        let alerts = whales.scan(&book);
+        for alert in &alerts {
+            tracing::warn!("{}", alert.describe());
+        }
+        let found = anomalies.sample(&book, chrono::Utc::now().timestamp_millis() as u64);
+        for anomaly in &found {
+            tracing::warn!("{}", anomaly.describe());
+        }
+        if let Some(notifier) = &notifier {
+            notifier.send(exchange.name(), &symbol, &alerts);
+            notifier.send_anomalies(exchange.name(), &symbol, &found);
+        }
+
+        if changed && last_publish.elapsed() >= PUBLISH_INTERVAL {
+            last_publish = Instant::now();
+            changed = false;
+            let stats = session.stats(&book.counters());
+            if let Some((server, depth)) = &server {
+                server.publish(&BookMessage::from_book(&book, exchange, &symbol, *depth, stats));
+            }
+            #[cfg(feature = "grpc")]
+            if let Some((grpc, depth)) = &grpc {
+                grpc.publish(&BookMessage::from_book(&book, exchange, &symbol, *depth, stats));
+            }
+        }
+
+        if let Some(export) = &mut exporter {
+            if let Err(e) = export.sample(&book) {
+                tracing::error!("Export to {} failed, stopping it: {e}", export.path().display());
+                exporter = None;
+            }
+        }
+
+        if let Some(archiver) = &mut archiver {
+            archiver.sample(&book);
+        }
+
+        if let Some(saver) = &mut saver {
+            if let Err(e) = saver.sample(&book, session.stats(&book.counters())) {
+                tracing::error!("Saving the state to {} failed: {e}", saver.path().display());
+            }
+        }
+
+        if last_print.elapsed() >= Duration::from_secs(1) {
+            last_print = Instant::now();
+            match (book.best_bid(), book.best_ask()) {
+                (Some(bid), Some(ask)) => {
+                    tracing::info!(
+                        "bid {} ask {} spread {} | levels {}/{} | est. orders {}",
+                        bid,
+                        ask,
+                        ask - bid,
+                        book.bids.len(),
+                        book.asks.len(),
+                        book.order_count()
+                    );
+                    if let Some(m) = book.metrics() {
+                        tracing::info!(
+                            "microprice {:.8} weighted mid {:.8} imbalance {:+.3}",
+                            m.microprice, m.weighted_mid, m.imbalance
+                        );
+                        let bands: Vec<String> = m
+                            .liquidity
+                            .iter()
+                            .map(|band| format!("{}bps {}/{}", band.bps, band.bid.normalize(), band.ask.normalize()))
+                            .collect();
+                        let vwap = |v: Option<Decimal>| v.map_or("-".to_string(), |v| format!("{v:.8}"));
+                        tracing::info!(
+                            "liquidity bid/ask {} | vwap {} buy {} sell {}",
+                            bands.join(", "),
+                            book.vwap_notional(),
+                            vwap(m.buy_vwap),
+                            vwap(m.sell_vwap)
+                        );
+                    }
+                }
+                _ => tracing::info!("Waiting for the book..."),
+            }
+            let latency = worker.latency();
+            if latency.samples > 0 {
+                tracing::info!(
+                    "latency p50 {} ms p99 {} ms (incl. clock skew) | {} msg/s",
+                    latency.p50_ms, latency.p99_ms, latency.messages_per_sec
+                );
+            }
+            tracing::info!("session {}", session.stats(&book.counters()).describe());
+            if exchange.has_order_feed() && book.is_synced() {
+                let check = QueueCheck::compare(&book);
+                if !check.levels.is_empty() {
+                    tracing::info!("vs venue queues: {}", check.describe());
+                }
+                queue_checks.record(check);
+            }
+            let flow_stats = flow.stats();
+            if flow_stats.seconds > 0 {
+                tracing::info!("flow {}", flow_stats.describe());
+            }
+            if let Some(stats) = &instrument {
+                let show = |v: Option<Decimal>| v.map_or("-".to_string(), |v| v.to_string());
+                tracing::info!(
+                    "mark {} index {} funding {} open interest {}",
+                    show(stats.mark_price),
+                    show(stats.index_price),
+                    show(stats.funding_rate),
+                    show(stats.open_interest)
+                );
+            }
+        }
+    }
+    drop(worker);
+    runtime.block_on(exchanges::shutdown());
+    let stats = session.stats(&book.counters());
+    tracing::info!("session: {}", stats.describe());
+    // The run's report, the one thing written to stdout
+    let mut report = serde_json::json!({
+        "exchange": exchange.name(),
+        "symbol": symbol,
+        "session": stats,
+    });
+    if let Some(publisher) = publisher {
+        if publisher.dropped() > 0 {
+            tracing::warn!("Publisher dropped {} events", publisher.dropped());
+        }
+        report["publisher_dropped"] = publisher.dropped().into();
+        publisher.close();
+    }
+    #[cfg(feature = "sqlite")]
+    if let Some(mut store) = store {
+        store.record(book.drain_events().collect());
+        store.close();
+        tracing::info!(
+            "Recorded {} inferred events to {} (session {}, {} batches dropped)",
+            store.written(),
+            store.path().display(),
+            store.session(),
+            store.dropped()
+        );
+        report["events_recorded"] = store.written().into();
+    }
+    if let Some(mut archiver) = archiver {
+        archiver.close();
+        tracing::info!("Archived {} snapshots ({} dropped)", archiver.written(), archiver.dropped());
+        report["snapshots_archived"] = archiver.written().into();
+    }
+    if let Some(mut saver) = saver {
+        saver.save(&book, stats)?;
+    }
+    if audit.audits > 0 {
+        tracing::info!("Audit: {}", audit.describe());
+        report["audit"] = serde_json::json!({
+            "audits": audit.audits,
+            "clean": audit.clean,
+            "mean_level_error": audit.mean_level_error(),
+            "mean_size_error": audit.mean_size_error(),
+            "worst_level_error": audit.worst_level_error,
+        });
+    }
+    if queue_checks.checks > 0 {
+        tracing::info!("vs venue queues: {}", queue_checks.describe());
+        report["queue_checks"] = serde_json::json!({
+            "checks": queue_checks.checks,
+            "exact_ratio": queue_checks.exact_ratio(),
+            "mean_count_error": queue_checks.mean_count_error(),
+            "mean_size_error": queue_checks.mean_size_error(),
+        });
+    }
+    if let Some(mut export) = exporter {
+        export.close()?;
+        tracing::info!("Wrote {} rows to {}", export.rows(), export.path().display());
+        report["export_rows"] = export.rows().into();
+    }
+    println!("{report}");
+    Ok(())
+}
+
+// Venue time where the message has one, else its arrival
+fn event_time(venue: u64, received: u64) -> u64 {
+    if venue > 0 {
+        venue
+    } else {
+        received
+    }
+}
@@ -0,0 +1,352 @@
+use crate::exchanges::{PriceLevel, Side};
+use crate::orderbook::{OrderBook, OrderQueue};
+use rust_decimal::Decimal;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, VecDeque};
+use std::time::Duration;
+
+// Aggregated book state at one point in time, top `depth` levels per side of the book's
+// (possibly grouped) view
+#[derive(Clone, Debug)]
+pub struct BookFrame {
+    // Milliseconds since the Unix epoch
+    pub timestamp: u64,
+    // Mid of the raw book, unaffected by grouping
+    pub mid: Option<Decimal>,
+    // Best first on both sides
+    pub bids: Vec<PriceLevel>,
+    pub asks: Vec<PriceLevel>,
+}
+
+impl BookFrame {
+    pub fn from_book(book: &OrderBook, depth: usize, timestamp: u64) -> Self {
+        let level = |(&price, queue): (&Decimal, &OrderQueue)| PriceLevel::new(price, queue.total());
+        let mid = match (book.best_bid(), book.best_ask()) {
+            (Some(bid), Some(ask)) => Some((bid + ask) / Decimal::TWO),
+            _ => None,
+        };
+        Self {
+            timestamp,
+            mid,
+            bids: book.view_bids().iter().rev().take(depth).map(level).collect(),
+            asks: book.view_asks().iter().take(depth).map(level).collect(),
+        }
+    }
+
+    pub fn mid(&self) -> Option<Decimal> {
+        self.mid
+    }
+
+    // Net change per level since `earlier`, bids then asks, best first. Only prices within the
+    // depth both samples reach are compared, so levels that just scrolled past the sampled depth
+    // as the price moved do not show as pulled.
+    pub fn deltas_since(&self, earlier: &BookFrame) -> Vec<LevelDelta> {
+        let mut deltas = side_deltas(Side::Bid, &earlier.bids, &self.bids);
+        deltas.extend(side_deltas(Side::Ask, &earlier.asks, &self.asks));
+        deltas
+    }
+}
+
+// Net change in resting size at one price between two samples
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LevelDelta {
+    pub side: Side,
+    pub price: Decimal,
+    // Positive where size was added, negative where it was pulled or traded away
+    pub change: Decimal,
+}
+
+fn side_deltas(side: Side, before: &[PriceLevel], after: &[PriceLevel]) -> Vec<LevelDelta> {
+    let bids = side == Side::Bid;
+    // The shallower of the two samples' deepest prices; an empty side limits nothing
+    let limit = match (before.last(), after.last()) {
+        (Some(a), Some(b)) if bids => Some(a.price.max(b.price)),
+        (Some(a), Some(b)) => Some(a.price.min(b.price)),
+        _ => None,
+    };
+    let within = |price: Decimal| limit.is_none_or(|limit| if bids { price >= limit } else { price <= limit });
+    let mut sizes: BTreeMap<Decimal, (Decimal, Decimal)> = BTreeMap::new();
+    for level in before.iter().filter(|l| within(l.price)) {
+        sizes.entry(level.price).or_default().0 = level.qty;
+    }
+    for level in after.iter().filter(|l| within(l.price)) {
+        sizes.entry(level.price).or_default().1 = level.qty;
+    }
+    let deltas = sizes
+        .into_iter()
+        .filter(|(_, (before, after))| before != after)
+        .map(|(price, (before, after))| LevelDelta { side, price, change: after - before });
+    if bids {
+        deltas.rev().collect()
+    } else {
+        deltas.collect()
+    }
+}
+
+// Every this many samples one is stored in full, bounding the replay needed to read any sample
+const KEYFRAME_INTERVAL: usize = 50;
+
+// How much history is sampled and kept
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HistorySettings {
+    // Samples older than this, relative to the newest, are dropped
+    pub retention: Duration,
+    pub sample_interval: Duration,
+    // Levels per side in each sample
+    pub depth: usize,
+    // Estimated memory the samples may take; the oldest are dropped first to stay under it
+    pub max_bytes: usize,
+}
+
+impl Default for HistorySettings {
+    fn default() -> Self {
+        Self {
+            retention: Duration::from_secs(600),
+            sample_interval: Duration::from_millis(200),
+            depth: 100,
+            max_bytes: 64 << 20,
+        }
+    }
+}
+
+// A sample as stored: keyframes hold their levels in full, the others only the levels that
+// changed since the previous sample, where a zero quantity removes the level
+struct StoredFrame {
+    timestamp: u64,
+    mid: Option<Decimal>,
+    keyframe: bool,
+    bids: Vec<PriceLevel>,
+    asks: Vec<PriceLevel>,
+}
+
+impl StoredFrame {
+    fn bytes(&self) -> usize {
+        size_of::<Self>() + (self.bids.capacity() + self.asks.capacity()) * size_of::<PriceLevel>()
+    }
+
+    // The full sample, given the one before it (unused for keyframes)
+    fn expand(&self, previous: Option<&BookFrame>) -> BookFrame {
+        let (bids, asks) = match previous {
+            Some(previous) if !self.keyframe => {
+                (patch(&previous.bids, &self.bids, true), patch(&previous.asks, &self.asks, false))
+            }
+            _ => (self.bids.clone(), self.asks.clone()),
+        };
+        BookFrame {
+            timestamp: self.timestamp,
+            mid: self.mid,
+            bids,
+            asks,
+        }
+    }
+}
+
+// Order of levels in a frame: best first, so bids descend and asks ascend
+fn level_order(a: Decimal, b: Decimal, bids: bool) -> Ordering {
+    if bids {
+        b.cmp(&a)
+    } else {
+        a.cmp(&b)
+    }
+}
+
+// Levels of `next` that differ from `previous`, and removals for those it no longer has
+fn diff(previous: &[PriceLevel], next: &[PriceLevel], bids: bool) -> Vec<PriceLevel> {
+    let mut changes = vec![];
+    let (mut old, mut new) = (previous.iter().peekable(), next.iter().peekable());
+    loop {
+        match (old.peek(), new.peek()) {
+            (Some(o), Some(n)) => match level_order(o.price, n.price, bids) {
+                Ordering::Less => changes.push(PriceLevel::new(old.next().unwrap().price, Decimal::ZERO)),
+                Ordering::Greater => changes.push(*new.next().unwrap()),
+                Ordering::Equal => {
+                    let (o, n) = (old.next().unwrap(), new.next().unwrap());
+                    if o != n {
+                        changes.push(*n);
+                    }
+                }
+            },
+            (Some(o), None) => {
+                changes.push(PriceLevel::new(o.price, Decimal::ZERO));
+                old.next();
+            }
+            (None, Some(&&n)) => {
+                changes.push(n);
+                new.next();
+            }
+            (None, None) => break,
+        }
+    }
+    changes.shrink_to_fit();
+    changes
+}
+
+// `previous` with `changes` from `diff` applied
+fn patch(previous: &[PriceLevel], changes: &[PriceLevel], bids: bool) -> Vec<PriceLevel> {
+    let mut levels = Vec::with_capacity(previous.len() + changes.len());
+    let (mut old, mut new) = (previous.iter().peekable(), changes.iter().peekable());
+    loop {
+        let next = match (old.peek(), new.peek()) {
+            (Some(o), Some(n)) => match level_order(o.price, n.price, bids) {
+                Ordering::Less => old.next(),
+                Ordering::Greater => new.next(),
+                Ordering::Equal => {
+                    old.next();
+                    new.next()
+                }
+            },
+            (Some(_), None) => old.next(),
+            (None, Some(_)) => new.next(),
+            (None, None) => break,
+        };
+        levels.extend(next.filter(|level| !level.qty.is_zero()));
+    }
+    levels
+}
+
+// Time-indexed ring buffer of sampled book states, oldest first. Samples are delta-encoded
+// against the previous one and leave once they are older than the retention or the estimated
+// memory exceeds the budget. The oldest stored sample is always a keyframe.
+pub struct BookHistory {
+    frames: VecDeque<StoredFrame>,
+    // Newest sample in full, which the next one is encoded against
+    latest: Option<BookFrame>,
+    settings: HistorySettings,
+    bytes: usize,
+    since_keyframe: usize,
+}
+
+impl BookHistory {
+    pub fn new(settings: HistorySettings) -> Self {
+        Self {
+            frames: VecDeque::new(),
+            latest: None,
+            settings,
+            bytes: 0,
+            since_keyframe: 0,
+        }
+    }
+
+    pub fn settings(&self) -> &HistorySettings {
+        &self.settings
+    }
+
+    pub fn push(&mut self, frame: BookFrame) {
+        let stored = match &self.latest {
+            Some(previous) if self.since_keyframe + 1 < KEYFRAME_INTERVAL => {
+                self.since_keyframe += 1;
+                StoredFrame {
+                    timestamp: frame.timestamp,
+                    mid: frame.mid,
+                    keyframe: false,
+                    bids: diff(&previous.bids, &frame.bids, true),
+                    asks: diff(&previous.asks, &frame.asks, false),
+                }
+            }
+            _ => {
+                self.since_keyframe = 0;
+                StoredFrame {
+                    timestamp: frame.timestamp,
+                    mid: frame.mid,
+                    keyframe: true,
+                    bids: frame.bids.clone(),
+                    asks: frame.asks.clone(),
+                }
+            }
+        };
+        self.bytes += stored.bytes();
+        self.frames.push_back(stored);
+        self.latest = Some(frame);
+        self.evict();
+    }
+
+    // Drops expired samples, then the oldest while over the memory budget; the newest stays
+    fn evict(&mut self) {
+        let Some(newest) = self.frames.back().map(|f| f.timestamp) else {
+            return;
+        };
+        let cutoff = newest.saturating_sub(self.settings.retention.as_millis() as u64);
+        while self.frames.len() > 1 {
+            let front = &self.frames[0];
+            if front.timestamp >= cutoff && self.bytes <= self.settings.max_bytes {
+                break;
+            }
+            let Some(dropped) = self.frames.pop_front() else {
+                break;
+            };
+            self.bytes -= dropped.bytes();
+            // The new oldest sample becomes a keyframe, as its base is gone
+            let next = &mut self.frames[0];
+            if !next.keyframe {
+                let full = next.expand(Some(&dropped.expand(None)));
+                self.bytes -= next.bytes();
+                (next.bids, next.asks, next.keyframe) = (full.bids, full.asks, true);
+                self.bytes += next.bytes();
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.latest = None;
+        self.bytes = 0;
+        self.since_keyframe = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    // Estimated memory taken by the stored samples
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    // Every sample in full, oldest first
+    pub fn frames(&self) -> impl Iterator<Item = BookFrame> + '_ {
+        let mut previous: Option<BookFrame> = None;
+        self.frames.iter().map(move |stored| {
+            let frame = stored.expand(previous.as_ref());
+            previous = Some(frame.clone());
+            frame
+        })
+    }
+
+    // (timestamp, mid) of every sample, oldest first, without expanding the levels
+    pub fn mids(&self) -> impl Iterator<Item = (u64, Option<Decimal>)> + '_ {
+        self.frames.iter().map(|f| (f.timestamp, f.mid))
+    }
+
+    pub fn oldest_timestamp(&self) -> Option<u64> {
+        self.frames.front().map(|f| f.timestamp)
+    }
+
+    // The last sample taken at or before `timestamp`
+    pub fn frame_at(&self, timestamp: u64) -> Option<BookFrame> {
+        let index = self.frames.partition_point(|f| f.timestamp <= timestamp).checked_sub(1)?;
+        let start = (0..=index).rev().find(|&i| self.frames[i].keyframe)?;
+        let mut frame = self.frames[start].expand(None);
+        for stored in self.frames.range(start + 1..=index) {
+            frame = stored.expand(Some(&frame));
+        }
+        Some(frame)
+    }
+
+    pub fn latest(&self) -> Option<&BookFrame> {
+        self.latest.as_ref()
+    }
+
+    // Level changes from the sample `lookback` before the one at `until` (the newest when None)
+    // to that one, with the milliseconds actually between them; with less history retained the
+    // changes run from the oldest sample
+    pub fn deltas(&self, until: Option<u64>, lookback: Duration) -> Option<(u64, Vec<LevelDelta>)> {
+        let end = self.frame_at(until.unwrap_or(u64::MAX))?;
+        let from = end.timestamp.saturating_sub(lookback.as_millis() as u64).max(self.oldest_timestamp()?);
+        let start = self.frame_at(from)?;
+        Some((end.timestamp - start.timestamp, end.deltas_since(&start)))
+    }
+}
@@ -0,0 +1,757 @@
+// Replays third-party historical L2 data through the book and estimator, so months of history
+// can be studied offline rather than only live feeds. Inputs, plain or gzipped:
+// - Tardis.dev CSV datasets: `incremental_book_L2` and `trades`
+// - Tardis normalized ndjson (`book_change` and `trade` messages, as tardis-machine emits them)
+// - crypto-lake style CSV exports: `book_delta_v2` (`side_is_bid`, `size`) and `trades`
+//
+// CSV columns are found by name from the header. Rows that arrived together (same local
+// timestamp) form one update. Consecutive `is_snapshot` rows form a snapshot; files without the
+// flag start from an empty book that their deltas build up. Several files, e.g. a book and a
+// trades file per day, are merged by arrival time, and the book's clock follows the data.
+use crate::exchanges::{
+    DepthUpdate, ExchangeMessage, ExchangeSettings, ExchangeType, OrderBookSnapshot, PriceLevel, Side, Trade,
+};
+use crate::export::{ExportOptions, Exporter};
+use crate::flow::{FlowStats, OrderFlow};
+use crate::orderbook::{InferredAction, OrderBook};
+use flate2::read::MultiGzDecoder;
+use rust_decimal::prelude::FromPrimitive;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Lines};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ImportOptions {
+    // Files, or directories whose files are all read
+    pub paths: Vec<PathBuf>,
+    // Only rows of this instrument, case-insensitive; by default the first one each file holds
+    pub symbol: Option<String>,
+}
+
+// One venue message rebuilt from the files
+#[derive(Clone, Debug)]
+pub struct HistoricalMessage {
+    // Arrival time in milliseconds since the Unix epoch, the venue's time if the file has no other
+    pub time: u64,
+    // A snapshot, an update or a trade
+    pub message: ExchangeMessage,
+}
+
+// The messages of all input files merged by arrival time. Update ids are assigned in order, so
+// the book applies every update once it has a snapshot.
+pub struct HistoricalFeed {
+    sources: Vec<Source>,
+    heads: Vec<Option<HistoricalMessage>>,
+    next_id: u64,
+    synced: bool,
+}
+
+impl HistoricalFeed {
+    pub fn open(options: &ImportOptions) -> io::Result<Self> {
+        let mut files = Vec::new();
+        for path in &options.paths {
+            if path.is_dir() {
+                let mut entries: Vec<PathBuf> = std::fs::read_dir(path)?
+                    .map(|entry| entry.map(|e| e.path()))
+                    .collect::<io::Result<_>>()?;
+                entries.retain(|p| p.is_file() && !p.file_name().is_some_and(|n| n.to_string_lossy().starts_with('.')));
+                entries.sort();
+                files.extend(entries);
+            } else {
+                files.push(path.clone());
+            }
+        }
+        if files.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "no historical data files given"));
+        }
+        let mut sources = files
+            .iter()
+            .map(|path| Source::open(path, options.symbol.as_deref()))
+            .collect::<io::Result<Vec<_>>>()?;
+        let heads = sources.iter_mut().map(Source::next).collect::<io::Result<_>>()?;
+        Ok(Self {
+            sources,
+            heads,
+            next_id: 0,
+            synced: false,
+        })
+    }
+
+    // Venue named by the first file that names one, as the file spells it (`binance-futures`)
+    pub fn venue(&self) -> Option<&str> {
+        self.sources.iter().find_map(|source| source.venue.as_deref())
+    }
+
+    // Instrument of the first file that has rows
+    pub fn symbol(&self) -> Option<&str> {
+        self.sources.iter().find_map(|source| source.symbol.as_deref())
+    }
+
+    // Rows that were malformed or belonged to another instrument
+    pub fn skipped_rows(&self) -> u64 {
+        self.sources.iter().map(|source| source.skipped).sum()
+    }
+
+    fn stamp(&mut self, mut next: HistoricalMessage) -> HistoricalMessage {
+        match &mut next.message {
+            ExchangeMessage::Snapshot(snapshot) => {
+                self.next_id += 1;
+                snapshot.last_update_id = self.next_id;
+            }
+            ExchangeMessage::Update(update) => {
+                self.next_id += 1;
+                update.capital_u = self.next_id;
+                update.small_u = self.next_id;
+                update.pu = self.next_id as i64 - 1;
+            }
+            _ => {}
+        }
+        next
+    }
+}
+
+impl Iterator for HistoricalFeed {
+    type Item = io::Result<HistoricalMessage>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Earliest head; ties go to the file given first
+        let (index, _) = self
+            .heads
+            .iter()
+            .enumerate()
+            .filter_map(|(i, head)| head.as_ref().map(|head| (i, head.time)))
+            .min_by_key(|&(i, time)| (time, i))?;
+        let head = self.heads[index].as_ref().expect("picked a present head");
+        // Deltas without a snapshot before them build on an empty book
+        if !self.synced {
+            match &head.message {
+                ExchangeMessage::Snapshot(_) => self.synced = true,
+                ExchangeMessage::Update(_) => {
+                    self.synced = true;
+                    let empty = HistoricalMessage {
+                        time: head.time,
+                        message: ExchangeMessage::Snapshot(OrderBookSnapshot {
+                            last_update_id: 0,
+                            event_time: head.time,
+                            recv_time: head.time,
+                            bids: Vec::new(),
+                            asks: Vec::new(),
+                        }),
+                    };
+                    return Some(Ok(self.stamp(empty)));
+                }
+                _ => {}
+            }
+        }
+        let next = match self.sources[index].next() {
+            Ok(next) => std::mem::replace(&mut self.heads[index], next),
+            Err(e) => {
+                self.heads[index] = None;
+                return Some(Err(e));
+            }
+        };
+        next.map(|next| Ok(self.stamp(next)))
+    }
+}
+
+// Which kind of rows a CSV file holds, from its header
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CsvKind {
+    Book,
+    Trades,
+}
+
+// Header positions of the columns the importer reads; names differ between providers
+#[derive(Clone, Debug, Default)]
+struct Columns {
+    exchange: Option<usize>,
+    symbol: Option<usize>,
+    // Venue time
+    time: Option<usize>,
+    // Arrival time at the provider's recorder
+    local_time: Option<usize>,
+    is_snapshot: Option<usize>,
+    side: Option<usize>,
+    side_is_bid: Option<usize>,
+    price: usize,
+    amount: usize,
+    trade_id: Option<usize>,
+}
+
+impl Columns {
+    fn parse(header: &str) -> Result<(CsvKind, Self), String> {
+        let names: Vec<&str> = header.split(',').map(|name| name.trim().trim_matches('"')).collect();
+        let find = |aliases: &[&str]| names.iter().position(|name| aliases.contains(name));
+        let price = find(&["price"]).ok_or("no price column")?;
+        let amount = find(&["amount", "size", "quantity", "qty"]).ok_or("no amount column")?;
+        let columns = Columns {
+            exchange: find(&["exchange"]),
+            symbol: find(&["symbol"]),
+            time: find(&["timestamp", "origin_time"]),
+            local_time: find(&["local_timestamp", "received_time"]),
+            is_snapshot: find(&["is_snapshot"]),
+            side: find(&["side"]),
+            side_is_bid: find(&["side_is_bid"]),
+            price,
+            amount,
+            trade_id: find(&["id", "trade_id"]),
+        };
+        if columns.time.is_none() && columns.local_time.is_none() {
+            return Err("no timestamp column".to_string());
+        }
+        let kind = if columns.side_is_bid.is_some() || columns.is_snapshot.is_some() {
+            CsvKind::Book
+        } else if columns.trade_id.is_some() && columns.side.is_some() {
+            CsvKind::Trades
+        } else if columns.side.is_some() {
+            CsvKind::Book
+        } else {
+            return Err("neither book nor trade columns".to_string());
+        };
+        Ok((kind, columns))
+    }
+}
+
+// One book row, before it is grouped with the others of its message
+struct BookRow {
+    time: u64,
+    local_time: u64,
+    snapshot: bool,
+    side: Side,
+    level: PriceLevel,
+}
+
+enum Format {
+    Csv { kind: CsvKind, columns: Columns },
+    Json,
+}
+
+// One input file
+struct Source {
+    path: PathBuf,
+    lines: Lines<Box<dyn BufRead>>,
+    line: u64,
+    format: Format,
+    // Instrument rows are kept for, locked to the first row's if not given
+    symbol: Option<String>,
+    venue: Option<String>,
+    // Book row read past the end of the previous message
+    pending: Option<BookRow>,
+    skipped: u64,
+}
+
+impl Source {
+    fn open(path: &Path, symbol: Option<&str>) -> io::Result<Self> {
+        let invalid = |e: String| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {e}", path.display()));
+        let mut file = BufReader::new(File::open(path)?);
+        let gzipped = file.fill_buf()?.starts_with(&[0x1f, 0x8b]);
+        let reader: Box<dyn BufRead> = if gzipped {
+            Box::new(BufReader::new(MultiGzDecoder::new(file)))
+        } else {
+            Box::new(file)
+        };
+        let mut lines = reader.lines();
+        // ndjson by its name, CSV otherwise
+        let name = path.file_name().map(|n| n.to_string_lossy().to_lowercase()).unwrap_or_default();
+        let name = name.strip_suffix(".gz").unwrap_or(&name);
+        let (format, line) = if name.ends_with(".json") || name.ends_with(".ndjson") || name.ends_with(".jsonl") {
+            (Format::Json, 0)
+        } else {
+            let header = lines.next().transpose()?.ok_or_else(|| invalid("empty file".to_string()))?;
+            let (kind, columns) = Columns::parse(&header).map_err(invalid)?;
+            (Format::Csv { kind, columns }, 1)
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            lines,
+            line,
+            format,
+            symbol: symbol.map(str::to_string),
+            venue: None,
+            pending: None,
+            skipped: 0,
+        })
+    }
+
+    // Keeps rows of the chosen instrument, noting the venue of the first one kept
+    fn accept(&mut self, venue: Option<&str>, symbol: Option<&str>) -> bool {
+        let keep = match (&self.symbol, symbol) {
+            (Some(wanted), Some(symbol)) => wanted.eq_ignore_ascii_case(symbol),
+            _ => true,
+        };
+        if !keep {
+            self.skipped += 1;
+            return false;
+        }
+        // Spelled as the file does from here on
+        if let Some(symbol) = symbol.filter(|&symbol| self.symbol.as_deref() != Some(symbol)) {
+            self.symbol = Some(symbol.to_string());
+        }
+        if self.venue.is_none() {
+            self.venue = venue.map(str::to_string);
+        }
+        true
+    }
+
+    fn next_line(&mut self) -> io::Result<Option<String>> {
+        self.line += 1;
+        self.lines
+            .next()
+            .transpose()
+            .map_err(|e| io::Error::new(e.kind(), format!("{} line {}: {e}", self.path.display(), self.line)))
+    }
+
+    fn next(&mut self) -> io::Result<Option<HistoricalMessage>> {
+        match &self.format {
+            Format::Csv { kind: CsvKind::Book, .. } => self.next_book(),
+            Format::Csv { kind: CsvKind::Trades, .. } => self.next_trade(),
+            Format::Json => self.next_json(),
+        }
+    }
+
+    fn book_row(&mut self) -> io::Result<Option<BookRow>> {
+        if let Some(row) = self.pending.take() {
+            return Ok(Some(row));
+        }
+        while let Some(line) = self.next_line()? {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let Format::Csv { columns, .. } = &self.format else {
+                unreachable!("book rows come from CSV files");
+            };
+            let fields: Vec<&str> = line.split(',').collect();
+            let field = |i: Option<usize>| i.and_then(|i| fields.get(i)).map(|f| f.trim().trim_matches('"'));
+            let side = match (field(columns.side), field(columns.side_is_bid)) {
+                (_, Some(flag)) => parse_bool(flag).map(|bid| if bid { Side::Bid } else { Side::Ask }),
+                (Some("bid" | "bids" | "buy"), None) => Some(Side::Bid),
+                (Some("ask" | "asks" | "sell"), None) => Some(Side::Ask),
+                _ => None,
+            };
+            let (time, local_time) = times(field(columns.time), field(columns.local_time));
+            let row = match (side, time, parse_decimal(field(Some(columns.price))), parse_decimal(field(Some(columns.amount)))) {
+                (Some(side), Some(time), Some(price), Some(qty)) => Some(BookRow {
+                    time,
+                    local_time: local_time.unwrap_or(time),
+                    snapshot: field(columns.is_snapshot).and_then(parse_bool).unwrap_or(false),
+                    side,
+                    level: PriceLevel::new(price, qty),
+                }),
+                _ => None,
+            };
+            let (venue, symbol) = (field(columns.exchange).map(str::to_string), field(columns.symbol).map(str::to_string));
+            match row {
+                Some(row) if self.accept(venue.as_deref(), symbol.as_deref()) => return Ok(Some(row)),
+                Some(_) => {}
+                None => self.skipped += 1,
+            }
+        }
+        Ok(None)
+    }
+
+    // Rows of one snapshot (while the flag stays set) or one update (same arrival time)
+    fn next_book(&mut self) -> io::Result<Option<HistoricalMessage>> {
+        let Some(first) = self.book_row()? else {
+            return Ok(None);
+        };
+        let (time, local_time, snapshot) = (first.time, first.local_time, first.snapshot);
+        let (mut bids, mut asks) = (Vec::new(), Vec::new());
+        let mut push = |row: BookRow| match row.side {
+            Side::Bid => bids.push(row.level),
+            Side::Ask => asks.push(row.level),
+        };
+        push(first);
+        while let Some(row) = self.book_row()? {
+            let same = if snapshot { row.snapshot } else { !row.snapshot && row.local_time == local_time };
+            if !same {
+                self.pending = Some(row);
+                break;
+            }
+            push(row);
+        }
+        let message = if snapshot {
+            // Best first, as venues send snapshots
+            bids.sort_by_key(|level| std::cmp::Reverse(level.price));
+            asks.sort_by_key(|level| level.price);
+            ExchangeMessage::Snapshot(OrderBookSnapshot {
+                last_update_id: 0,
+                event_time: time,
+                recv_time: local_time,
+                bids,
+                asks,
+            })
+        } else {
+            ExchangeMessage::Update(update(time, local_time, bids, asks))
+        };
+        Ok(Some(HistoricalMessage {
+            time: local_time,
+            message,
+        }))
+    }
+
+    fn next_trade(&mut self) -> io::Result<Option<HistoricalMessage>> {
+        while let Some(line) = self.next_line()? {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let Format::Csv { columns, .. } = &self.format else {
+                unreachable!("trade rows come from CSV files");
+            };
+            let fields: Vec<&str> = line.split(',').collect();
+            let field = |i: Option<usize>| i.and_then(|i| fields.get(i)).map(|f| f.trim().trim_matches('"'));
+            let (time, local_time) = times(field(columns.time), field(columns.local_time));
+            // Trades of unknown side cannot be attributed to a book side
+            let aggressor = match field(columns.side) {
+                Some("buy" | "b" | "bid") => Some(Side::Bid),
+                Some("sell" | "s" | "ask") => Some(Side::Ask),
+                _ => None,
+            };
+            let trade = match (aggressor, time, parse_decimal(field(Some(columns.price))), parse_decimal(field(Some(columns.amount)))) {
+                (Some(aggressor), Some(time), Some(price), Some(qty)) => Some(Trade {
+                    trade_id: field(columns.trade_id).and_then(|id| id.parse().ok()).unwrap_or(0),
+                    price,
+                    qty,
+                    aggressor,
+                    timestamp: time,
+                }),
+                _ => None,
+            };
+            let (venue, symbol) = (field(columns.exchange).map(str::to_string), field(columns.symbol).map(str::to_string));
+            match trade {
+                Some(trade) if self.accept(venue.as_deref(), symbol.as_deref()) => {
+                    return Ok(Some(HistoricalMessage {
+                        time: local_time.unwrap_or(trade.timestamp),
+                        message: ExchangeMessage::Trade(trade),
+                    }))
+                }
+                Some(_) => {}
+                None => self.skipped += 1,
+            }
+        }
+        Ok(None)
+    }
+
+    fn next_json(&mut self) -> io::Result<Option<HistoricalMessage>> {
+        while let Some(line) = self.next_line()? {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let message = match serde_json::from_str::<TardisMessage>(&line) {
+                Ok(TardisMessage::Other) => continue,
+                Ok(message) => message.into_historical(),
+                Err(_) => None,
+            };
+            let Some((message, venue, symbol)) = message else {
+                self.skipped += 1;
+                continue;
+            };
+            if self.accept(venue.as_deref(), symbol.as_deref()) {
+                return Ok(Some(message));
+            }
+        }
+        Ok(None)
+    }
+}
+
+// Normalized tardis-machine message; types other than book changes and trades are skipped
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum TardisMessage {
+    #[serde(rename_all = "camelCase")]
+    BookChange {
+        symbol: Option<String>,
+        exchange: Option<String>,
+        #[serde(default)]
+        is_snapshot: bool,
+        bids: Vec<TardisLevel>,
+        asks: Vec<TardisLevel>,
+        timestamp: Option<String>,
+        local_timestamp: Option<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Trade {
+        symbol: Option<String>,
+        exchange: Option<String>,
+        id: Option<String>,
+        price: f64,
+        amount: f64,
+        side: String,
+        timestamp: Option<String>,
+        local_timestamp: Option<String>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+struct TardisLevel {
+    price: f64,
+    amount: f64,
+}
+
+impl TardisMessage {
+    // The message with its venue and symbol, None if it is not one the book uses
+    fn into_historical(self) -> Option<(HistoricalMessage, Option<String>, Option<String>)> {
+        match self {
+            TardisMessage::BookChange {
+                symbol,
+                exchange,
+                is_snapshot,
+                bids,
+                asks,
+                timestamp,
+                local_timestamp,
+            } => {
+                let (time, local_time) = times(timestamp.as_deref(), local_timestamp.as_deref());
+                let time = time?;
+                let local_time = local_time.unwrap_or(time);
+                let levels = |levels: Vec<TardisLevel>| -> Option<Vec<PriceLevel>> {
+                    levels
+                        .into_iter()
+                        .map(|l| Some(PriceLevel::new(Decimal::from_f64(l.price)?, Decimal::from_f64(l.amount)?)))
+                        .collect()
+                };
+                let (bids, asks) = (levels(bids)?, levels(asks)?);
+                let message = if is_snapshot {
+                    ExchangeMessage::Snapshot(OrderBookSnapshot {
+                        last_update_id: 0,
+                        event_time: time,
+                        recv_time: local_time,
+                        bids,
+                        asks,
+                    })
+                } else {
+                    ExchangeMessage::Update(update(time, local_time, bids, asks))
+                };
+                let message = HistoricalMessage {
+                    time: local_time,
+                    message,
+                };
+                Some((message, exchange, symbol))
+            }
+            TardisMessage::Trade {
+                symbol,
+                exchange,
+                id,
+                price,
+                amount,
+                side,
+                timestamp,
+                local_timestamp,
+            } => {
+                let (time, local_time) = times(timestamp.as_deref(), local_timestamp.as_deref());
+                let time = time?;
+                let aggressor = match side.as_str() {
+                    "buy" => Side::Bid,
+                    "sell" => Side::Ask,
+                    _ => return None,
+                };
+                let trade = Trade {
+                    trade_id: id.and_then(|id| id.parse().ok()).unwrap_or(0),
+                    price: Decimal::from_f64(price)?,
+                    qty: Decimal::from_f64(amount)?,
+                    aggressor,
+                    timestamp: time,
+                };
+                let message = HistoricalMessage {
+                    time: local_time.unwrap_or(time),
+                    message: ExchangeMessage::Trade(trade),
+                };
+                Some((message, exchange, symbol))
+            }
+            TardisMessage::Other => None,
+        }
+    }
+}
+
+fn update(time: u64, local_time: u64, bids: Vec<PriceLevel>, asks: Vec<PriceLevel>) -> DepthUpdate {
+    DepthUpdate {
+        event_time: time,
+        transaction_time: time,
+        symbol: String::new(),
+        capital_u: 0,
+        small_u: 0,
+        pu: -1,
+        bids,
+        asks,
+        recv_time: local_time,
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" | "1" => Some(true),
+        "false" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+// Plain or scientific notation, as some exports write small sizes (`1e-05`)
+fn parse_decimal(value: Option<&str>) -> Option<Decimal> {
+    let value = value?;
+    Decimal::from_str(value).or_else(|_| Decimal::from_scientific(value)).ok()
+}
+
+// Venue and arrival time in milliseconds, each standing in for the other when missing
+fn times(time: Option<&str>, local_time: Option<&str>) -> (Option<u64>, Option<u64>) {
+    let (time, local_time) = (time.and_then(parse_time), local_time.and_then(parse_time));
+    (time.or(local_time), local_time.or(time))
+}
+
+// Epoch seconds, milliseconds, microseconds or nanoseconds (told apart by magnitude), or an
+// ISO 8601 date-time, UTC unless it says otherwise
+pub fn parse_time(value: &str) -> Option<u64> {
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+    if let Ok(n) = value.parse::<u64>() {
+        return Some(match n {
+            n if n >= 100_000_000_000_000_000 => n / 1_000_000,
+            n if n >= 100_000_000_000_000 => n / 1_000,
+            n if n >= 100_000_000_000 => n,
+            n => n * 1_000,
+        });
+    }
+    if let Ok(time) = chrono::DateTime::parse_from_rfc3339(value) {
+        return u64::try_from(time.timestamp_millis()).ok();
+    }
+    ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f"]
+        .iter()
+        .find_map(|format| chrono::NaiveDateTime::parse_from_str(value, format).ok())
+        .and_then(|time| u64::try_from(time.and_utc().timestamp_millis()).ok())
+}
+
+// Connector of a provider's venue id: `binance-futures` and `BINANCE_FUTURES` are Binance,
+// `gate-io` is Gate.io
+pub fn venue_exchange(venue: &str) -> Option<ExchangeType> {
+    let key = |s: &str| s.chars().filter(char::is_ascii_alphanumeric).collect::<String>().to_lowercase();
+    let venue = key(venue);
+    ExchangeType::ALL.iter().copied().find(|e| venue.starts_with(&key(e.name())))
+}
+
+// What a replay went through; printed as JSON when the replay ends
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct ImportSummary {
+    pub snapshots: u64,
+    pub updates: u64,
+    pub trades: u64,
+    // Inferred events by action: joined, removed, reduced, filled
+    pub events: [u64; 4],
+    // Rows that were malformed or belonged to another instrument
+    pub skipped_rows: u64,
+    // Data time covered, in milliseconds since the Unix epoch
+    pub first_time: Option<u64>,
+    pub last_time: Option<u64>,
+    // Order flow over the last minute of data
+    pub flow: FlowStats,
+    pub export_rows: u64,
+}
+
+impl ImportSummary {
+    pub fn describe(&self) -> String {
+        let date = |time: Option<u64>| {
+            time.and_then(|t| chrono::DateTime::from_timestamp_millis(t as i64))
+                .map_or("-".to_string(), |t| t.format("%Y-%m-%d %H:%M:%S%.3f").to_string())
+        };
+        format!(
+            "{} snapshots, {} updates, {} trades from {} to {}; inferred {} joins, {} removals, {} reductions, \
+             {} fills; {} rows skipped",
+            self.snapshots,
+            self.updates,
+            self.trades,
+            date(self.first_time),
+            date(self.last_time),
+            self.events[0],
+            self.events[1],
+            self.events[2],
+            self.events[3],
+            self.skipped_rows
+        )
+    }
+}
+
+// Replays the files through a book set up like a live feed's, sampling it into `export` at
+// data time. `exchange` labels the output; by default it is the files' venue.
+pub fn run(
+    options: &ImportOptions,
+    exchange: Option<ExchangeType>,
+    settings: &ExchangeSettings,
+    export: Option<ExportOptions>,
+) -> io::Result<ImportSummary> {
+    let mut feed = HistoricalFeed::open(options)?;
+    let exchange = match (exchange, feed.venue()) {
+        (Some(exchange), _) => exchange,
+        (None, Some(venue)) => venue_exchange(venue).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, format!("unknown venue {venue}, pass --exchange to label it"))
+        })?,
+        (None, None) => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the files do not name their venue, pass --exchange",
+            ))
+        }
+    };
+    let symbol = feed.symbol().or(options.symbol.as_deref()).unwrap_or("unknown").to_string();
+    let _span = tracing::info_span!("replay", exchange = exchange.name(), symbol = %symbol).entered();
+    let mut exporter = export.map(|export| Exporter::create(export, exchange, &symbol)).transpose()?;
+    let mut book = OrderBook::new();
+    book.set_max_depth(settings.depth(exchange).estimation);
+    book.set_vwap_notional(settings.vwap_notional);
+    book.set_estimator(settings.estimator);
+    book.set_event_log(true);
+    let mut flow = OrderFlow::default();
+    let mut summary = ImportSummary::default();
+    let mut last_print = Instant::now();
+    tracing::info!("Replaying {} {} from {} files", exchange.name(), symbol, feed.sources.len());
+    while let Some(next) = feed.next() {
+        let HistoricalMessage { time, message } = next?;
+        book.set_clock(Some(time));
+        summary.first_time.get_or_insert(time);
+        summary.last_time = Some(time);
+        match message {
+            ExchangeMessage::Snapshot(snapshot) => {
+                summary.snapshots += 1;
+                book.apply_snapshot(&snapshot);
+            }
+            ExchangeMessage::Update(update) => {
+                summary.updates += 1;
+                book.handle_update(update);
+            }
+            ExchangeMessage::Trade(trade) => {
+                summary.trades += 1;
+                book.apply_trade(&trade);
+            }
+            _ => {}
+        }
+        let (best_bid, best_ask) = (book.best_bid(), book.best_ask());
+        for event in book.drain_events() {
+            flow.record(&event, best_bid, best_ask);
+            summary.events[match event.action {
+                InferredAction::Joined => 0,
+                InferredAction::Removed => 1,
+                InferredAction::Reduced => 2,
+                InferredAction::Filled => 3,
+            }] += 1;
+        }
+        if let Some(export) = &mut exporter {
+            export.sample_at(&book, time)?;
+        }
+        if last_print.elapsed() >= Duration::from_secs(1) {
+            last_print = Instant::now();
+            summary.skipped_rows = feed.skipped_rows();
+            tracing::info!("Replayed so far: {}", summary.describe());
+        }
+    }
+    summary.skipped_rows = feed.skipped_rows();
+    summary.flow = flow.stats();
+    if let Some(mut export) = exporter {
+        export.close()?;
+        summary.export_rows = export.rows();
+    }
+    Ok(summary)
+}
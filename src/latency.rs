@@ -0,0 +1,95 @@
+use crate::feed::FeedMessage;
+use std::collections::VecDeque;
+
+// Delays kept for the percentiles; older ones are dropped
+const SAMPLE_CAPACITY: usize = 2000;
+
+// Latency of one feed over its most recent messages
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LatencyStats {
+    // Local receive time minus the venue's event time, in milliseconds. Includes the clock
+    // skew between the venue and this machine, so it can be negative.
+    pub p50_ms: i64,
+    pub p99_ms: i64,
+    pub min_ms: i64,
+    pub max_ms: i64,
+    // Delays the percentiles were taken over
+    pub samples: usize,
+    // Book, order, trade and instrument messages received during the last full second
+    pub messages_per_sec: u64,
+}
+
+// Collects receive delays and the message rate of a feed. Venues without an event time in
+// their messages (REST snapshots, some full states) only count towards the rate.
+#[derive(Debug, Default)]
+pub struct LatencyTracker {
+    delays: VecDeque<i64>,
+    // Second (since the Unix epoch) being counted and its message count
+    second: u64,
+    count: u64,
+    last_rate: u64,
+}
+
+impl LatencyTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, message: &FeedMessage) {
+        let (event_time, recv_time) = match message {
+            FeedMessage::Update(update) => (update.event_time, update.recv_time),
+            FeedMessage::Snapshot(snap) | FeedMessage::FullState(snap) => (snap.event_time, snap.recv_time),
+            FeedMessage::Order(_) | FeedMessage::Trade(_) | FeedMessage::InstrumentStats(_) => (0, 0),
+            _ => return,
+        };
+        let now = if recv_time > 0 {
+            recv_time
+        } else {
+            chrono::Utc::now().timestamp_millis() as u64
+        };
+        self.count_message(now);
+        if event_time > 0 && recv_time > 0 {
+            if self.delays.len() == SAMPLE_CAPACITY {
+                self.delays.pop_front();
+            }
+            self.delays.push_back(recv_time as i64 - event_time as i64);
+        }
+    }
+
+    fn count_message(&mut self, now: u64) {
+        let second = now / 1000;
+        if second != self.second {
+            // A gap of more than a second means nothing arrived in the last full one
+            self.last_rate = if second == self.second + 1 { self.count } else { 0 };
+            self.second = second;
+            self.count = 0;
+        }
+        self.count += 1;
+    }
+
+    pub fn stats(&self) -> LatencyStats {
+        let now = chrono::Utc::now().timestamp_millis() as u64 / 1000;
+        let messages_per_sec = match now.saturating_sub(self.second) {
+            0 => self.last_rate,
+            1 => self.count,
+            _ => 0,
+        };
+        if self.delays.is_empty() {
+            return LatencyStats {
+                messages_per_sec,
+                ..LatencyStats::default()
+            };
+        }
+        let mut sorted: Vec<i64> = self.delays.iter().copied().collect();
+        sorted.sort_unstable();
+        let percentile = |p: f64| sorted[((sorted.len() - 1) as f64 * p).round() as usize];
+        LatencyStats {
+            p50_ms: percentile(0.5),
+            p99_ms: percentile(0.99),
+            min_ms: sorted[0],
+            max_ms: sorted[sorted.len() - 1],
+            samples: sorted.len(),
+            messages_per_sec,
+        }
+    }
+}
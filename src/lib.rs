@@ -1,2 +1,44 @@
+pub mod alerts;
+pub mod anomaly;
+pub mod arbitrage;
+pub mod archive;
+pub mod audit;
+pub mod clustering;
+pub mod config;
 pub mod exchanges;
-pub mod kmeans;
\ No newline at end of file
+pub mod export;
+pub mod feed;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod fixed;
+pub mod flow;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+#[cfg(feature = "gui")]
+pub mod gui;
+// The headless runner and its WebSocket server need sockets, so only native builds have them
+#[cfg(not(target_arch = "wasm32"))]
+pub mod headless;
+pub mod history;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod import;
+pub mod latency;
+pub mod live_orders;
+pub mod logging;
+pub mod orderbook;
+pub mod palette;
+pub mod prior;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod publish;
+#[cfg(feature = "pyo3")]
+pub mod python;
+pub mod resume;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod server;
+pub mod session;
+pub mod simulator;
+#[cfg(feature = "sqlite")]
+pub mod store;
+#[cfg(all(feature = "tui", not(target_arch = "wasm32")))]
+pub mod tui;
+pub mod volume_profile;
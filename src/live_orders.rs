@@ -0,0 +1,278 @@
+// Ground-truth queues from venues that publish individual orders (Bitstamp `live_orders`,
+// Bitfinex `R0`, Drift's DLOB), kept next to the estimated queues rather than replacing them, so
+// the estimate can be shown and checked against what the venue actually holds.
+//
+// Bitfinex and Drift send every resting order after a (re)connect. Bitstamp only streams changes,
+// so a level is fully known once every order resting there arrived after the subscription.
+// `QueueCheck` validates the estimator against these queues where they are complete.
+use crate::exchanges::{OrderEvent, OrderEventKind, Side};
+use crate::orderbook::OrderBook;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use std::collections::{BTreeMap, HashMap};
+
+// One order as the venue reports it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LiveOrder {
+    pub order_id: u64,
+    pub qty: Decimal,
+    // Venue time the order was created or last moved, ms since the Unix epoch
+    pub timestamp: u64,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct LiveOrders {
+    // Orders per level in queue order, front first
+    bids: BTreeMap<Decimal, Vec<LiveOrder>>,
+    asks: BTreeMap<Decimal, Vec<LiveOrder>>,
+    // Where each order rests
+    index: HashMap<u64, (Side, Decimal)>,
+}
+
+impl LiveOrders {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn clear(&mut self) {
+        self.bids.clear();
+        self.asks.clear();
+        self.index.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    // A size change in place keeps the order's queue priority; a new price sends it to the back
+    // of that level, as on every price-time priority venue
+    pub fn apply(&mut self, event: &OrderEvent) {
+        let placed = self.index.get(&event.order_id).copied();
+        if let Some((side, price)) = placed {
+            if event.kind != OrderEventKind::Deleted && side == event.side && price == event.price {
+                if let Some(order) = self.level_mut(side, price).and_then(|orders| find(orders, event.order_id)) {
+                    order.qty = event.qty;
+                }
+                return;
+            }
+            self.remove(event.order_id, side, price);
+        }
+        if event.kind == OrderEventKind::Deleted || event.qty <= Decimal::ZERO {
+            return;
+        }
+        let order = LiveOrder { order_id: event.order_id, qty: event.qty, timestamp: event.timestamp };
+        self.side_mut(event.side).entry(event.price).or_default().push(order);
+        self.index.insert(event.order_id, (event.side, event.price));
+    }
+
+    // The venue's queue at `price`, front first; empty where no order is known
+    pub fn queue(&self, side: Side, price: Decimal) -> &[LiveOrder] {
+        self.side(side).get(&price).map_or(&[], Vec::as_slice)
+    }
+
+    // Levels with known orders, best first
+    pub fn levels(&self, side: Side) -> Box<dyn Iterator<Item = (Decimal, &[LiveOrder])> + '_> {
+        let levels = self.side(side).iter().map(|(&price, orders)| (price, orders.as_slice()));
+        match side {
+            Side::Bid => Box::new(levels.rev()),
+            Side::Ask => Box::new(levels),
+        }
+    }
+
+    fn side(&self, side: Side) -> &BTreeMap<Decimal, Vec<LiveOrder>> {
+        match side {
+            Side::Bid => &self.bids,
+            Side::Ask => &self.asks,
+        }
+    }
+
+    fn side_mut(&mut self, side: Side) -> &mut BTreeMap<Decimal, Vec<LiveOrder>> {
+        match side {
+            Side::Bid => &mut self.bids,
+            Side::Ask => &mut self.asks,
+        }
+    }
+
+    fn level_mut(&mut self, side: Side, price: Decimal) -> Option<&mut Vec<LiveOrder>> {
+        self.side_mut(side).get_mut(&price)
+    }
+
+    fn remove(&mut self, order_id: u64, side: Side, price: Decimal) {
+        self.index.remove(&order_id);
+        let levels = self.side_mut(side);
+        if let Some(orders) = levels.get_mut(&price) {
+            orders.retain(|order| order.order_id != order_id);
+            if orders.is_empty() {
+                levels.remove(&price);
+            }
+        }
+    }
+}
+
+fn find(orders: &mut [LiveOrder], order_id: u64) -> Option<&mut LiveOrder> {
+    orders.iter_mut().find(|order| order.order_id == order_id)
+}
+
+// The estimate at one level against the venue's queue there
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LevelCheck {
+    pub side: Side,
+    pub price: Decimal,
+    // Orders inferred, and orders the venue holds
+    pub estimated: usize,
+    pub venue: usize,
+    // Sum of |estimated size - venue size| over the orders matched by queue place, with orders
+    // only one side has counted in full
+    pub size_error: Decimal,
+    pub level_size: Decimal,
+}
+
+impl LevelCheck {
+    pub fn count_error(&self) -> usize {
+        self.estimated.abs_diff(self.venue)
+    }
+}
+
+// One comparison of the estimated queues with the venue's, over the levels whose every order
+// the venue reported, i.e. whose known orders add up to the level's size. On Bitstamp a level
+// becomes known once all the orders resting there were placed after the subscription.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct QueueCheck {
+    // Local time of the comparison, ms since the Unix epoch
+    pub time: u64,
+    pub levels: Vec<LevelCheck>,
+    // Book levels left out because the venue's orders there are not all known
+    pub unknown: usize,
+}
+
+impl QueueCheck {
+    // Raw levels only; the grouped view is not compared
+    pub fn compare(book: &OrderBook) -> Self {
+        let mut check = QueueCheck {
+            time: chrono::Utc::now().timestamp_millis() as u64,
+            ..Default::default()
+        };
+        let live = book.live_orders();
+        for (side, levels) in [(Side::Bid, &book.bids), (Side::Ask, &book.asks)] {
+            for (&price, queue) in levels {
+                let venue = live.queue(side, price);
+                let level_size = queue.total();
+                if level_size.is_zero() || venue.iter().map(|order| order.qty).sum::<Decimal>() != level_size {
+                    check.unknown += 1;
+                    continue;
+                }
+                let places = queue.len().max(venue.len());
+                let size_error = (0..places)
+                    .map(|i| {
+                        let estimated = queue.get(i).map_or(Decimal::ZERO, |order| order.size());
+                        let actual = venue.get(i).map_or(Decimal::ZERO, |order| order.qty);
+                        (estimated - actual).abs()
+                    })
+                    .sum();
+                check.levels.push(LevelCheck {
+                    side,
+                    price,
+                    estimated: queue.len(),
+                    venue: venue.len(),
+                    size_error,
+                    level_size,
+                });
+            }
+        }
+        check
+    }
+
+    // The `n` levels the estimate is furthest off on, by order count and then size
+    pub fn worst(&self, n: usize) -> Vec<LevelCheck> {
+        let mut levels = self.levels.clone();
+        levels.sort_by(|a, b| b.count_error().cmp(&a.count_error()).then(b.size_error.cmp(&a.size_error)));
+        levels.truncate(n);
+        levels
+    }
+
+    // Compared levels whose order count the estimate got right
+    pub fn exact(&self) -> usize {
+        self.levels.iter().filter(|level| level.count_error() == 0).count()
+    }
+
+    // Mean orders the estimate is off by per compared level
+    pub fn mean_count_error(&self) -> f64 {
+        if self.levels.is_empty() {
+            return 0.0;
+        }
+        self.levels.iter().map(|level| level.count_error()).sum::<usize>() as f64 / self.levels.len() as f64
+    }
+
+    // Size error relative to the compared levels' total size
+    pub fn size_error_ratio(&self) -> f64 {
+        let size: Decimal = self.levels.iter().map(|level| level.level_size).sum();
+        if size.is_zero() {
+            return 0.0;
+        }
+        let error: Decimal = self.levels.iter().map(|level| level.size_error).sum();
+        (error / size).to_f64().unwrap_or(0.0)
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "{} levels known ({} not), order count exact on {}, off by {:.2} on average, size error {:.2}%",
+            self.levels.len(),
+            self.unknown,
+            self.exact(),
+            self.mean_count_error(),
+            self.size_error_ratio() * 100.0
+        )
+    }
+}
+
+// Queue checks over a session; checks that found no known level are not counted
+#[derive(Clone, Debug, Default)]
+pub struct QueueCheckStats {
+    pub checks: u64,
+    pub last: Option<QueueCheck>,
+    levels: u64,
+    exact: u64,
+    count_error_sum: f64,
+    size_error_sum: f64,
+}
+
+impl QueueCheckStats {
+    pub fn record(&mut self, check: QueueCheck) {
+        if check.levels.is_empty() {
+            return;
+        }
+        self.checks += 1;
+        self.levels += check.levels.len() as u64;
+        self.exact += check.exact() as u64;
+        self.count_error_sum += check.mean_count_error();
+        self.size_error_sum += check.size_error_ratio();
+        self.last = Some(check);
+    }
+
+    // Share of the compared levels whose order count was right
+    pub fn exact_ratio(&self) -> f64 {
+        self.exact as f64 / self.levels.max(1) as f64
+    }
+
+    pub fn mean_count_error(&self) -> f64 {
+        self.count_error_sum / self.checks.max(1) as f64
+    }
+
+    pub fn mean_size_error(&self) -> f64 {
+        self.size_error_sum / self.checks.max(1) as f64
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "{} checks; order count exact on {:.1}% of levels, off by {:.2} on average, size error {:.2}% on average",
+            self.checks,
+            self.exact_ratio() * 100.0,
+            self.mean_count_error(),
+            self.mean_size_error() * 100.0
+        )
+    }
+}
@@ -0,0 +1,168 @@
+// Diagnostics go through `tracing`: readable lines on stderr, optionally JSON lines in a file,
+// and the most recent records in memory for the GUI's log panel. Connector and feed messages
+// carry a `feed` span with the exchange and symbol; the estimator logs its decisions at trace
+// level under the `estimator` target.
+use once_cell::sync::Lazy;
+use std::collections::VecDeque;
+use std::fmt::{self, Write as _};
+use std::fs::OpenOptions;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::fmt::layer as fmt_layer;
+use tracing_subscriber::{EnvFilter, Layer};
+
+// Records kept for the log panel; older ones are dropped
+const RECENT_CAPACITY: usize = 2000;
+
+pub const DEFAULT_LOG_LEVEL: &str = "info";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogSettings {
+    // `tracing` filter: a level (`debug`) or per-target directives (`info,estimator=trace`)
+    pub level: String,
+    // Also write every record to this file as JSON lines, appending
+    pub file: Option<PathBuf>,
+    // Print records to stderr; off where the terminal is the UI
+    pub console: bool,
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        Self {
+            level: DEFAULT_LOG_LEVEL.to_string(),
+            file: None,
+            console: true,
+        }
+    }
+}
+
+// One record as the log panel shows it
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogRecord {
+    // Milliseconds since the Unix epoch
+    pub time: u64,
+    pub level: Level,
+    pub target: String,
+    // Enclosing spans with their fields, outermost first, e.g. `feed{exchange=Binance symbol=dogeusdt}`
+    pub spans: String,
+    // The message followed by the event's other fields
+    pub message: String,
+}
+
+static RECENT: Lazy<Mutex<VecDeque<LogRecord>>> = Lazy::new(|| Mutex::new(VecDeque::new()));
+
+// Installs the subscriber; call it once at startup
+pub fn init(settings: &LogSettings) -> io::Result<()> {
+    let filter = EnvFilter::try_new(&settings.level)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, format!("invalid log level {}: {e}", settings.level)))?;
+    let console = settings.console.then(|| fmt_layer().with_writer(io::stderr));
+    let file = match &settings.file {
+        Some(path) => {
+            let file = OpenOptions::new().create(true).append(true).open(path)?;
+            Some(fmt_layer().json().with_writer(Mutex::new(file)))
+        }
+        None => None,
+    };
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(console)
+        .with(file)
+        .with(RecentLayer)
+        .try_init()
+        .map_err(|e| io::Error::other(e.to_string()))
+}
+
+// The most recent records, oldest first
+pub fn recent() -> Vec<LogRecord> {
+    RECENT.lock().unwrap().iter().cloned().collect()
+}
+
+pub fn clear_recent() {
+    RECENT.lock().unwrap().clear();
+}
+
+// Keeps formatted records in `RECENT`
+struct RecentLayer;
+
+// A span's fields, formatted when it is created
+struct SpanFields(String);
+
+impl<S> Layer<S> for RecentLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut fields = FieldWriter::default();
+        attrs.record(&mut fields);
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanFields(fields.fields));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut spans = String::new();
+        for span in ctx.event_scope(event).into_iter().flat_map(|scope| scope.from_root()) {
+            if !spans.is_empty() {
+                spans.push(':');
+            }
+            spans.push_str(span.name());
+            if let Some(fields) = span.extensions().get::<SpanFields>().filter(|f| !f.0.is_empty()) {
+                let _ = write!(spans, "{{{}}}", fields.0);
+            }
+        }
+        let mut fields = FieldWriter::default();
+        event.record(&mut fields);
+        let message = match (fields.message.is_empty(), fields.fields.is_empty()) {
+            (_, true) => fields.message,
+            (true, false) => fields.fields,
+            (false, false) => format!("{} {}", fields.message, fields.fields),
+        };
+        let record = LogRecord {
+            time: chrono::Utc::now().timestamp_millis() as u64,
+            level: *event.metadata().level(),
+            target: event.metadata().target().to_string(),
+            spans,
+            message,
+        };
+        let mut recent = RECENT.lock().unwrap();
+        if recent.len() == RECENT_CAPACITY {
+            recent.pop_front();
+        }
+        recent.push_back(record);
+    }
+}
+
+// The `message` field apart, the others as `name=value`
+#[derive(Default)]
+struct FieldWriter {
+    message: String,
+    fields: String,
+}
+
+impl Visit for FieldWriter {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = value.to_string();
+        } else {
+            self.record_debug(field, &format_args!("{value}"));
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{value:?}");
+            return;
+        }
+        if !self.fields.is_empty() {
+            self.fields.push(' ');
+        }
+        let _ = write!(self.fields, "{}={value:?}", field.name());
+    }
+}
@@ -1,738 +1,322 @@
-mod kmeans;
-mod exchanges;
-
-use eframe::egui;
-use egui::{Align2, Color32};
-use egui_plot::{Bar, BarChart, Plot, PlotPoint, Text};
-use exchanges::{Exchange, ExchangeMessage, ExchangeType};
-use once_cell::sync::Lazy;
-use rust_decimal::Decimal;
-use rust_decimal::prelude::*;
-use std::collections::{BTreeMap, VecDeque};
+#[cfg(not(target_arch = "wasm32"))]
+use multi_exchange_l3_est::alerts::{AlertTarget, AlertThreshold};
+#[cfg(not(target_arch = "wasm32"))]
+use multi_exchange_l3_est::archive::{ArchiveFormat, ArchiveOptions};
+#[cfg(not(target_arch = "wasm32"))]
+use multi_exchange_l3_est::resume::ResumeOptions;
+use multi_exchange_l3_est::config::Config;
+use multi_exchange_l3_est::exchanges::{net, ExchangeType};
+#[cfg(not(target_arch = "wasm32"))]
+use multi_exchange_l3_est::export::{ExportFormat, ExportOptions};
+#[cfg(not(target_arch = "wasm32"))]
+use multi_exchange_l3_est::headless::{self, HeadlessOptions};
+#[cfg(not(target_arch = "wasm32"))]
+use multi_exchange_l3_est::import::{self, ImportOptions};
+#[cfg(not(target_arch = "wasm32"))]
+use multi_exchange_l3_est::logging;
+#[cfg(not(target_arch = "wasm32"))]
+use multi_exchange_l3_est::publish::{self, PublishOptions, PublishTarget};
+#[cfg(not(target_arch = "wasm32"))]
+use multi_exchange_l3_est::server::ServeOptions;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
 use std::env;
-use std::sync::mpsc::{self as std_mpsc, Receiver as StdReceiver, Sender as StdSender};
-use std::thread;
-use tokio::sync::mpsc::{self, Receiver, Sender};
-
-enum AppMessage {
-    Snapshot(exchanges::OrderBookSnapshot),
-    Update(exchanges::DepthUpdate),
-}
-
-enum Control {
-    Refetch,
-    ChangeSymbol(String),
-    ChangeExchange(ExchangeType),
-}
-
-static BID_COLORS: Lazy<Vec<Color32>> = Lazy::new(|| {
-    vec![
-        Color32::from_rgb(222, 235, 247), // Light Blue
-        Color32::from_rgb(204, 227, 245), // Lighter Blue
-        Color32::from_rgb(158, 202, 225), // Blue
-        Color32::from_rgb(129, 189, 231), // Light Medium Blue
-        Color32::from_rgb(107, 174, 214), // Medium Blue
-        Color32::from_rgb(78, 157, 202),  // Medium Deep Blue
-        Color32::from_rgb(49, 130, 189),  // Deep Blue
-        Color32::from_rgb(33, 113, 181),  // Darker Deep Blue
-        Color32::from_rgb(16, 96, 168),   // Dark Blue
-        Color32::from_rgb(8, 81, 156),    // Darkest Blue
-    ]
-});
 
-static ASK_COLORS: Lazy<Vec<Color32>> = Lazy::new(|| {
-    vec![
-        Color32::from_rgb(254, 230, 206), // Light Orange
-        Color32::from_rgb(253, 216, 186), // Lighter Orange
-        Color32::from_rgb(253, 174, 107), // Orange
-        Color32::from_rgb(253, 159, 88),  // Light Deep Orange
-        Color32::from_rgb(253, 141, 60),  // Deep Orange
-        Color32::from_rgb(245, 126, 47),  // Medium Red-Orange
-        Color32::from_rgb(230, 85, 13),   // Red-Orange
-        Color32::from_rgb(204, 75, 12),   // Darker Red-Orange
-        Color32::from_rgb(179, 65, 10),   // Dark Red
-        Color32::from_rgb(166, 54, 3),    // Darkest Red
-    ]
-});
-
-fn main() -> eframe::Result {
-    // Fetch the symbol from command-line arguments or default to appropriate symbol per exchange
-    let args: Vec<String> = env::args().collect();
-    let symbol: String = if args.len() > 1 {
-        args[1].to_ascii_lowercase()
-    } else {
-        "dogeusdt".to_string() // Default for Binance, will be adjusted per exchange
+#[cfg(not(target_arch = "wasm32"))]
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Usage: multi_exchange_l3_est [symbol] [--config <path>] [--exchange <name>] [--headless]
+    //        [--stale-timeout <secs>] [--serve <addr:port>] [--serve-depth <levels>]
+    //        [--export <file.csv|file.parquet>] [--export-interval <ms>] [--export-levels <n>]
+    //        [--alert <size>|<multiple>x] [--alert-webhook <url>] [--anomalies] [--proxy <url>]
+    //        [--market spot|usdm|coinm]  (Binance)  [--testnet]  (Binance, Hyperliquid)
+    //        [--rate <updates/s>] [--depth <levels>] [--whales <probability>]
+    //        [--order-flow] [--seed <n>]  (synthetic feed)
+    //        [--log-level <filter>] [--log-file <path>]
+    //        [--grpc <addr:port>]  (grpc feature)
+    //        [--publish nats://host:port|kafka://host:port[,host:port]] [--publish-prefix <prefix>]
+    //        [--import <file|dir>]...  (replays historical data instead of a live feed)
+    //        [--archive <dir>] [--archive-interval <secs>] [--archive-format json|parquet]
+    //        [--archive-max-files <n>] [--archive-max-mb <n>]
+    //        [--events-db <file.sqlite>]  (sqlite feature)
+    //        [--resume <dir>] [--resume-interval <secs>]
+    let args: Vec<String> = env::args().skip(1).collect();
+    // The file provides the defaults, so it is loaded before any other flag is applied
+    let config = match args.iter().position(|a| a == "--config") {
+        Some(i) => Config::load(args.get(i + 1).ok_or("--config requires a value")?)?,
+        None => Config::default(),
     };
-
-    let options = eframe::NativeOptions::default();
-    eframe::run_native(
-        "Multi-Exchange Order Book Visualizer",
-        options,
-        Box::new(move |cc| Ok(Box::new(MyApp::new(cc, symbol)))),
-    )
-}
-
-struct MyApp {
-    symbol: String,
-    edited_symbol: String,
-    bids: BTreeMap<Decimal, VecDeque<Decimal>>,
-    asks: BTreeMap<Decimal, VecDeque<Decimal>>,
-    last_applied_u: u64,
-    is_synced: bool,
-    rx: StdReceiver<AppMessage>,
-    update_buffer: VecDeque<exchanges::DepthUpdate>,
-    control_tx: Sender<Control>,
-    kmeans_mode: bool,
-    price_prec: usize,
-    qty_prec: usize,
-    batch_size: usize,
-    max_iter: usize,
-    current_exchange: ExchangeType,
-    exchange_names: Vec<&'static str>,
-    selected_exchange_idx: usize,
-}
-
-impl MyApp {
-    fn new(cc: &eframe::CreationContext<'_>, symbol: String) -> Self {
-        let (tx, rx) = std_mpsc::channel();
-        let (control_tx, control_rx) = mpsc::channel(1);
-        let ctx = cc.egui_ctx.clone();
-        let s = symbol.clone();
-        let initial_exchange = ExchangeType::Binance;
-        let current_exchange = initial_exchange;
-        
-        thread::spawn(move || {
-            let rt = tokio::runtime::Runtime::new().unwrap();
-            rt.block_on(async {
-                Self::fetch_and_stream_loop(&tx, &ctx, control_rx, s, initial_exchange).await;
-            });
-        });
-
-        let exchange = current_exchange.create_exchange();
-        let (price_prec, qty_prec) = exchange.get_precision(&symbol);
-        let exchange_names = vec!["Binance", "Hyperliquid"];
-
-        Self {
-            symbol: symbol.clone(),
-            edited_symbol: symbol,
-            bids: BTreeMap::new(),
-            asks: BTreeMap::new(),
-            last_applied_u: 0,
-            is_synced: false,
-            rx,
-            update_buffer: VecDeque::new(),
-            control_tx,
-            kmeans_mode: false,
-            price_prec,
-            qty_prec,
-            batch_size: 1024,
-            max_iter: 1024,
-            current_exchange,
-            exchange_names,
-            selected_exchange_idx: 0,
-        }
-    }
-
-    async fn fetch_and_stream_loop(
-        tx: &StdSender<AppMessage>,
-        ctx: &egui::Context,
-        mut control_rx: Receiver<Control>,
-        mut symbol: String,
-        mut exchange_type: ExchangeType,
-    ) {
-        loop {
-            let exchange = exchange_type.create_exchange();
-            let formatted_symbol = exchange.format_symbol(&symbol);
-            
-            // Connect to exchange WebSocket
-            match exchange.connect(&formatted_symbol).await {
-                Ok(mut rx) => {
-                    println!("Connected to {} WebSocket for {}", exchange.get_name(), formatted_symbol);
-                    
-                    // Fetch initial snapshot
-                    match exchange.get_snapshot(&formatted_symbol).await {
-                        Ok(snapshot) => {
-                            println!("Snapshot fetched successfully from {}", exchange.get_name());
-                            tx.send(AppMessage::Snapshot(snapshot)).unwrap();
-                        }
-                        Err(e) => println!("Snapshot request error: {e:?}"),
-                    }
-                    
-                    // Process WebSocket messages
-                    let tx_clone = tx.clone();
-                    let ctx_clone = ctx.clone();
-                    let ws_handle = tokio::spawn(async move {
-                        while let Some(message) = rx.recv().await {
-                            match message {
-                                ExchangeMessage::Snapshot(snapshot) => {
-                                    tx_clone.send(AppMessage::Snapshot(snapshot)).unwrap();
-                                    ctx_clone.request_repaint();
-                                }
-                                ExchangeMessage::Update(update) => {
-                                    tx_clone.send(AppMessage::Update(update)).unwrap();
-                                    ctx_clone.request_repaint();
-                                }
-                            }
-                        }
-                    });
-
-                    if let Some(ctrl) = control_rx.recv().await {
-                        ws_handle.abort();
-                        match ctrl {
-                            Control::Refetch => {
-                                println!("Refetch triggered, restarting connection.");
-                            }
-                            Control::ChangeSymbol(new_symbol) => {
-                                symbol = new_symbol;
-                                println!("Changing symbol to {symbol}, restarting connection.");
-                            }
-                            Control::ChangeExchange(new_exchange) => {
-                                exchange_type = new_exchange;
-                                println!("Changing exchange to {:?}, restarting connection.", exchange_type);
-                            }
-                        }
-                    } else {
-                        break;
-                    }
-                }
-                Err(e) => {
-                    println!("Failed to connect to {} WebSocket: {e:?}", exchange.get_name());
-                    tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
-                }
+    let mut symbol = config.symbol.as_ref().map(|s| s.to_ascii_lowercase());
+    let mut exchange = config.exchange_type()?;
+    let mut headless_mode = !cfg!(feature = "gui");
+    let mut settings = config.exchange_settings()?;
+    let mut serve_addr: Option<std::net::SocketAddr> = None;
+    let mut serve_depth = 50;
+    let mut grpc_addr: Option<std::net::SocketAddr> = None;
+    let mut export_path: Option<PathBuf> = None;
+    let mut export_interval = Duration::from_secs(1);
+    let mut export_levels = 10;
+    let mut alerts = config.alerts.settings();
+    let mut alert_targets = config.alerts.targets();
+    let mut anomalies = config.anomalies;
+    let mut network = config.network_settings()?;
+    let mut logging = config.logging.settings();
+    let mut publish_target: Option<PublishTarget> = None;
+    let mut publish_prefix = publish::DEFAULT_PREFIX.to_string();
+    let mut import_paths: Vec<PathBuf> = Vec::new();
+    let mut events_db: Option<PathBuf> = None;
+    let mut resume = config.resume.options()?;
+    let mut resume_interval: Option<Duration> = None;
+    let mut archive = config.archive.options()?;
+    let mut archive_dir: Option<PathBuf> = None;
+    let mut archive_interval: Option<Duration> = None;
+    let mut archive_format: Option<ArchiveFormat> = None;
+    let mut archive_max_files: Option<usize> = None;
+    let mut archive_max_mb: Option<u64> = None;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--headless" => headless_mode = true,
+            "--config" => {
+                args.next();
             }
-        }
-    }
-
-    fn process_update(&mut self, update: exchanges::DepthUpdate) {
-        if update.small_u < self.last_applied_u {
-            return;
-        }
-
-        if self.is_synced {
-            if update.pu >= 0 && (update.pu as u64) != self.last_applied_u {
-                println!(
-                    "Warning: Message gap detected! pu: {}, last: {}",
-                    update.pu, self.last_applied_u
+            "--exchange" => {
+                let name = args.next().ok_or("--exchange requires a value")?;
+                exchange = Some(ExchangeType::from_name(&name).ok_or(format!("unknown or disabled exchange: {name}"))?);
+            }
+            // Publishing is driven by the headless loop, so serving implies headless mode
+            "--serve" => {
+                serve_addr = Some(args.next().ok_or("--serve requires an address, e.g. 127.0.0.1:9001")?.parse()?);
+                headless_mode = true;
+            }
+            // Like serving, publishing to a broker runs headless
+            "--publish" => {
+                let url = args.next().ok_or("--publish requires nats://host:port or kafka://host:port")?;
+                publish_target = Some(PublishTarget::parse(&url)?);
+                headless_mode = true;
+            }
+            "--publish-prefix" => publish_prefix = args.next().ok_or("--publish-prefix requires a value")?,
+            "--grpc" => {
+                grpc_addr = Some(args.next().ok_or("--grpc requires an address, e.g. 127.0.0.1:50051")?.parse()?);
+                headless_mode = true;
+            }
+            "--import" => import_paths.push(args.next().ok_or("--import requires a file or directory")?.into()),
+            // Like exporting, archiving from the command line runs headless
+            "--archive" => {
+                archive_dir = Some(args.next().ok_or("--archive requires a directory")?.into());
+                headless_mode = true;
+            }
+            "--archive-interval" => {
+                let secs: f64 = args.next().ok_or("--archive-interval requires a value")?.parse()?;
+                archive_interval = Some(
+                    Some(Duration::try_from_secs_f64(secs)?)
+                        .filter(|interval| !interval.is_zero())
+                        .ok_or("--archive-interval must be positive")?,
                 );
-                self.update_buffer.clear();
-                let _ = self.control_tx.try_send(Control::Refetch);
-                return;
             }
-            self.apply_update(&update);
-            self.last_applied_u = update.small_u;
-        } else if update.capital_u <= self.last_applied_u && self.last_applied_u <= update.small_u {
-            self.apply_update(&update);
-            self.last_applied_u = update.small_u;
-            self.is_synced = true;
-        } else {
-            println!(
-                "Initial gap detected! U: {}, u: {}, last: {}",
-                update.capital_u, update.small_u, self.last_applied_u
-            );
-            self.update_buffer.clear();
-            let _ = self.control_tx.try_send(Control::Refetch);
-        }
-    }
-}
-
-impl eframe::App for MyApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        while let Ok(msg) = self.rx.try_recv() {
-            match msg {
-                AppMessage::Snapshot(snap) => {
-                    self.bids.clear();
-                    self.asks.clear();
-                    for bid in &snap.bids {
-                        let price = bid[0];
-                        let qty = bid[1];
-                        if qty > Decimal::ZERO {
-                            self.bids.insert(price, VecDeque::from(vec![qty]));
-                        }
-                    }
-                    for ask in &snap.asks {
-                        let price = ask[0];
-                        let qty = ask[1];
-                        if qty > Decimal::ZERO {
-                            self.asks.insert(price, VecDeque::from(vec![qty]));
-                        }
-                    }
-                    self.last_applied_u = snap.last_update_id;
-                    self.is_synced = false;
-
-                    while let Some(update) = self.update_buffer.pop_front() {
-                        self.process_update(update);
-                    }
-                }
-                AppMessage::Update(update) => {
-                    if self.last_applied_u == 0 {
-                        self.update_buffer.push_back(update);
-                    } else {
-                        self.process_update(update);
-                    }
-                }
+            "--archive-format" => {
+                let name = args.next().ok_or("--archive-format requires a value")?;
+                archive_format = Some(ArchiveFormat::from_name(&name).ok_or(format!("unsupported archive format: {name}"))?);
             }
-        }
-
-        egui::CentralPanel::default().show(ctx, |ui| {
-            ui.heading(format!(
-                "{} {} Perpetual Order Book",
-                self.exchange_names[self.selected_exchange_idx],
-                self.symbol.to_uppercase()
-            ));
-            if ui.button("Toggle K-Means Mode").clicked() {
-                self.kmeans_mode = !self.kmeans_mode;
+            "--archive-max-files" => {
+                archive_max_files = Some(args.next().ok_or("--archive-max-files requires a value")?.parse()?)
             }
-
-            ui.horizontal(|ui| {
-                ui.label("Exchange:");
-                egui::ComboBox::from_label("")
-                    .selected_text(self.exchange_names[self.selected_exchange_idx])
-                    .show_ui(ui, |ui| {
-                        for (i, &name) in self.exchange_names.iter().enumerate() {
-                            if ui.selectable_value(&mut self.selected_exchange_idx, i, name).clicked() {
-                                let new_exchange = match i {
-                                    0 => ExchangeType::Binance,
-                                    1 => ExchangeType::Hyperliquid,
-                                    _ => ExchangeType::Binance,
-                                };
-                                if new_exchange as u8 != self.current_exchange as u8 {
-                                    self.current_exchange = new_exchange;
-                                    let exchange = self.current_exchange.create_exchange();
-                                    let (price_prec, qty_prec) = exchange.get_precision(&self.symbol);
-                                    self.price_prec = price_prec;
-                                    self.qty_prec = qty_prec;
-                                    
-                                    // Update symbol for exchange-specific formats
-                                    if matches!(new_exchange, ExchangeType::Hyperliquid) && self.symbol.contains("usdt") {
-                                        self.symbol = "SOL".to_string(); // Default to SOL for Hyperliquid
-                                        self.edited_symbol = self.symbol.clone();
-                                    }
-                                    
-                                    let _ = self.control_tx.try_send(Control::ChangeExchange(new_exchange));
-                                    self.bids.clear();
-                                    self.asks.clear();
-                                    self.last_applied_u = 0;
-                                    self.is_synced = false;
-                                }
-                            }
-                        }
-                    });
-            });
-            
-            ui.horizontal(|ui| {
-                ui.label("Symbol:");
-                ui.text_edit_singleline(&mut self.edited_symbol);
-                if ui.button("Change Symbol").clicked() && self.edited_symbol != self.symbol {
-                    let exchange = self.current_exchange.create_exchange();
-                    let (price_prec, qty_prec) = exchange.get_precision(&self.edited_symbol);
-                    self.price_prec = price_prec;
-                    self.qty_prec = qty_prec;
-                    
-                    let _ = self
-                        .control_tx
-                        .try_send(Control::ChangeSymbol(self.edited_symbol.clone()));
-                    self.symbol = self.edited_symbol.clone();
-                    self.bids.clear();
-                    self.asks.clear();
-                    self.last_applied_u = 0;
-                    self.is_synced = false;
+            "--archive-max-mb" => archive_max_mb = Some(args.next().ok_or("--archive-max-mb requires a value")?.parse()?),
+            // Like archiving, resuming from the command line runs headless
+            "--resume" => {
+                let dir: PathBuf = args.next().ok_or("--resume requires a directory")?.into();
+                match &mut resume {
+                    Some(resume) => resume.dir = dir,
+                    None => resume = Some(ResumeOptions::new(dir)),
                 }
-            });
-
-            if self.kmeans_mode {
-                ui.horizontal(|ui| {
-                    ui.label("Batch Size:");
-                    ui.add(egui::Slider::new(&mut self.batch_size, 32..=2048));
-                });
-                ui.horizontal(|ui| {
-                    ui.label("Max Iter:");
-                    ui.add(egui::Slider::new(&mut self.max_iter, 64..=2048));
-                });
+                headless_mode = true;
             }
-
-            ui.horizontal(|ui| {
-                ui.vertical(|ui| {
-                    egui::Grid::new("order_book_grid")
-                        .striped(true)
-                        .show(ui, |ui| {
-                            ui.label("Asks");
-                            ui.label("Price");
-                            ui.label("Quantity");
-                            ui.end_row();
-
-                            for (price, qty) in self.asks.iter().take(20).rev() {
-                                ui.label("");
-                                ui.label(format!(
-                                    "{:.1$}",
-                                    price.to_f64().unwrap_or(0.0),
-                                    self.price_prec
-                                ));
-                                ui.label(format!(
-                                    "{:.1$}",
-                                    qty.iter().sum::<Decimal>().to_f64().unwrap_or(0.0),
-                                    self.qty_prec
-                                ));
-                                ui.end_row();
-                            }
-
-                            ui.label("Bids");
-                            ui.label("Price");
-                            ui.label("Quantity");
-                            ui.end_row();
-
-                            for (price, qty) in self.bids.iter().rev().take(20) {
-                                ui.label("");
-                                ui.label(format!(
-                                    "{:.1$}",
-                                    price.to_f64().unwrap_or(0.0),
-                                    self.price_prec
-                                ));
-                                ui.label(format!(
-                                    "{:.1$}",
-                                    qty.iter().sum::<Decimal>().to_f64().unwrap_or(0.0),
-                                    self.qty_prec
-                                ));
-                                ui.end_row();
-                            }
-                        });
-                });
-
-                ui.vertical(|ui| {
-                    let bid_levels: Vec<(&Decimal, Decimal)> = self
-                        .bids
-                        .iter()
-                        .rev()
-                        .take(100)
-                        .map(|(key, deque)| {
-                            let sum = deque.iter().cloned().sum::<Decimal>(); // Sum the VecDeque<Decimal>
-                            (key, sum)
-                        })
-                        .collect();
-                    let ask_levels: Vec<(&Decimal, Decimal)> = self
-                        .asks
-                        .iter()
-                        .take(100)
-                        .map(|(key, deque)| {
-                            let sum = deque.iter().cloned().sum::<Decimal>(); // Sum the VecDeque<Decimal>
-                            (key, sum)
-                        })
-                        .collect();
-                    let mut max_qty: f64 = 0.0;
-                    for (_, qty) in &bid_levels {
-                        max_qty = max_qty.max(qty.to_f64().unwrap_or(0.0));
-                    }
-                    for (_, qty) in &ask_levels {
-                        max_qty = max_qty.max(qty.to_f64().unwrap_or(0.0));
-                    }
-
-                    let step = 1.0;
-                    let mut bars: Vec<Bar> = Vec::new();
-
-                    let max_bid_order: Decimal = self
-                        .bids
-                        .values()
-                        .rev()
-                        .take(100)
-                        .flat_map(|dq| dq.iter())
-                        .cloned()
-                        .max()
-                        .unwrap_or(Decimal::ZERO);
-                    let max_ask_order: Decimal = self
-                        .asks
-                        .values()
-                        .take(100)
-                        .flat_map(|dq| dq.iter())
-                        .cloned()
-                        .max()
-                        .unwrap_or(Decimal::ZERO);
-                    let second_max_bid_order = {
-                        let mut orders: Vec<_> = self
-                            .bids
-                            .values()
-                            .rev()
-                            .take(100)
-                            .flat_map(|dq| dq.iter())
-                            .cloned()
-                            .collect();
-                        orders.sort_by(|a, b| b.cmp(a)); // Sort in descending order
-                        orders.get(1).cloned().unwrap_or(Decimal::ZERO)
-                    };
-                    let second_max_ask_order = {
-                        let mut orders: Vec<_> = self
-                            .asks
-                            .values()
-                            .take(100)
-                            .flat_map(|dq| dq.iter())
-                            .cloned()
-                            .collect();
-                        orders.sort_by(|a, b| b.cmp(a)); // Sort in descending order
-                        orders.get(1).cloned().unwrap_or(Decimal::ZERO)
-                    };
-
-                    if !self.kmeans_mode {
-                        for (i, (_, qty_deq)) in self.asks.iter().take(100).enumerate() {
-                            let x = (i as f64 + 0.5) * step + 0.5;
-                            let mut offset = 0.0;
-
-                            for (j, &qty) in qty_deq.iter().enumerate() {
-                                if qty <= dec!(0.0) {
-                                    continue;
-                                }
-                                let color = if qty == max_ask_order {
-                                    Color32::GOLD
-                                } else if qty == second_max_ask_order {
-                                    Color32::from_rgb(184, 134, 11)
-                                } else {
-                                    self.get_order_color(j, Color32::DARK_RED)
-                                };
-                                let bar = Bar::new(x, qty.to_f64().unwrap_or(0.0))
-                                    .fill(color)
-                                    .base_offset(offset)
-                                    .width(step * 0.9);
-                                bars.push(bar);
-                                offset += qty.to_f64().unwrap_or(0.0);
-                            }
-                        }
-
-                        // Color Mapping for Bids
-                        for (i, (_, qty_deq)) in self.bids.iter().rev().take(100).enumerate() {
-                            let x = -(i as f64 + 0.5) * step - 0.5;
-                            let mut offset = 0.0;
-
-                            for (j, &qty) in qty_deq.iter().enumerate() {
-                                if qty <= dec!(0.0) {
-                                    continue;
-                                }
-                                let color = if qty == max_bid_order {
-                                    Color32::GOLD
-                                } else if qty == second_max_bid_order {
-                                    Color32::from_rgb(184, 134, 11)
-                                } else {
-                                    self.get_order_color(j, Color32::DARK_GREEN)
-                                };
-                                let bar = Bar::new(x, qty.to_f64().unwrap_or(0.0))
-                                    .fill(color)
-                                    .base_offset(offset)
-                                    .width(step * 0.9);
-                                bars.push(bar);
-                                offset += qty.to_f64().unwrap_or(0.0);
-                            }
-                        }
-                    } else {
-                        let asks_for_cluster: BTreeMap<Decimal, VecDeque<Decimal>> = self
-                            .asks
-                            .iter()
-                            .take(100)
-                            .map(|(&k, v)| (k, v.clone()))
-                            .collect();
-                        let mut kmeans_asks =
-                            kmeans::MiniBatchKMeans::new(10, self.batch_size, self.max_iter);
-                        let labels_asks = kmeans_asks.fit(&asks_for_cluster);
-                        let clustered_asks =
-                            kmeans::build_clustered_orders(&asks_for_cluster, &labels_asks);
-
-                        let bids_for_cluster: BTreeMap<Decimal, VecDeque<Decimal>> = self
-                            .bids
-                            .iter()
-                            .rev()
-                            .take(100)
-                            .map(|(&k, v)| (k, v.clone()))
-                            .collect();
-                        let mut kmeans_bids =
-                            kmeans::MiniBatchKMeans::new(10, self.batch_size, self.max_iter);
-                        let labels_bids = kmeans_bids.fit(&bids_for_cluster);
-                        let clustered_bids =
-                            kmeans::build_clustered_orders(&bids_for_cluster, &labels_bids);
-
-                        // Asks in K-Means mode
-                        for (i, (_, qty_deq)) in clustered_asks.iter().enumerate() {
-                            let x = (i as f64 + 0.5) * step + 0.5;
-                            let mut offset = 0.0;
-
-                            for &(qty, cluster) in qty_deq.iter() {
-                                if qty <= dec!(0.0) {
-                                    continue;
-                                }
-                                let color = if qty == max_ask_order {
-                                    Color32::GOLD
-                                } else {
-                                    ASK_COLORS
-                                        .get(cluster % ASK_COLORS.len())
-                                        .cloned()
-                                        .unwrap_or(Color32::GRAY)
-                                };
-                                let bar = Bar::new(x, qty.to_f64().unwrap_or(0.0))
-                                    .fill(color)
-                                    .base_offset(offset)
-                                    .width(step * 0.9);
-                                bars.push(bar);
-                                offset += qty.to_f64().unwrap_or(0.0);
-                            }
-                        }
-
-                        // Bids in K-Means mode
-                        for (i, (_, qty_deq)) in clustered_bids.iter().rev().enumerate() {
-                            let x = -(i as f64 + 0.5) * step - 0.5;
-                            let mut offset = 0.0;
-
-                            for &(qty, cluster) in qty_deq.iter() {
-                                if qty <= dec!(0.0) {
-                                    continue;
-                                }
-                                let color = if qty == max_bid_order {
-                                    Color32::GOLD
-                                } else {
-                                    BID_COLORS
-                                        .get(cluster % BID_COLORS.len())
-                                        .cloned()
-                                        .unwrap_or(Color32::GRAY)
-                                };
-                                let bar = Bar::new(x, qty.to_f64().unwrap_or(0.0))
-                                    .fill(color)
-                                    .base_offset(offset)
-                                    .width(step * 0.9);
-                                bars.push(bar);
-                                offset += qty.to_f64().unwrap_or(0.0);
-                            }
-                        }
-                    }
-
-                    Plot::new("orderbook_chart")
-                        .allow_drag(false)
-                        .allow_scroll(false)
-                        .allow_zoom(false)
-                        .show_axes([true, true])
-                        .show(ui, |plot_ui| {
-                            plot_ui.bar_chart(BarChart::new("ob", bars));
-
-                            for (i, (price, _)) in bid_levels.iter().enumerate() {
-                                if i.is_multiple_of(20) {
-                                    // Show label every 20th level
-                                    let x = -(i as f64 + 0.5) * step - 0.5;
-                                    plot_ui.text(
-                                        Text::new(
-                                            "bid",
-                                            PlotPoint::new(x, -max_qty * 0.05),
-                                            format!(
-                                                "{:.1$}",
-                                                price.to_f64().unwrap_or(0.0),
-                                                self.price_prec
-                                            ),
-                                        )
-                                        .anchor(Align2::CENTER_BOTTOM),
-                                    );
-                                }
-                            }
-
-                            for (i, (price, _)) in ask_levels.iter().enumerate() {
-                                if i.is_multiple_of(20) {
-                                    // Show label every 20th level
-                                    if i == 0 {
-                                        continue;
-                                    }
-                                    let x = (i as f64 + 0.5) * step + 0.5;
-                                    plot_ui.text(
-                                        Text::new(
-                                            "ask",
-                                            PlotPoint::new(x, -max_qty * 0.05),
-                                            format!(
-                                                "{:.1$}",
-                                                price.to_f64().unwrap_or(0.0),
-                                                self.price_prec
-                                            ),
-                                        )
-                                        .anchor(Align2::CENTER_BOTTOM),
-                                    );
-                                }
-                            }
-                        });
-                });
-            });
-        });
+            "--resume-interval" => {
+                let secs: f64 = args.next().ok_or("--resume-interval requires a value")?.parse()?;
+                resume_interval = Some(
+                    Some(Duration::try_from_secs_f64(secs)?)
+                        .filter(|interval| !interval.is_zero())
+                        .ok_or("--resume-interval must be positive")?,
+                );
+            }
+            "--events-db" => {
+                events_db = Some(args.next().ok_or("--events-db requires a file path")?.into());
+                headless_mode = true;
+            }
+            "--serve-depth" => serve_depth = args.next().ok_or("--serve-depth requires a value")?.parse()?,
+            // Exporting from the command line runs headless; the GUI has its own controls
+            "--export" => {
+                export_path = Some(args.next().ok_or("--export requires a file path")?.into());
+                headless_mode = true;
+            }
+            "--export-interval" => {
+                export_interval = Duration::from_millis(args.next().ok_or("--export-interval requires a value")?.parse()?)
+            }
+            "--export-levels" => export_levels = args.next().ok_or("--export-levels requires a value")?.parse()?,
+            // A fixed size, or a multiple of the median level size such as `20x`
+            "--alert" => {
+                let value = args.next().ok_or("--alert requires a size or a multiple such as 20x")?;
+                alerts.threshold = match value.strip_suffix('x') {
+                    Some(multiple) => AlertThreshold::MedianMultiple(multiple.parse()?),
+                    None => AlertThreshold::Absolute(value.parse()?),
+                };
+                alerts.enabled = true;
+            }
+            "--alert-webhook" => {
+                let url = args.next().ok_or("--alert-webhook requires a URL")?;
+                alert_targets.push(AlertTarget::Webhook { url });
+            }
+            "--anomalies" => anomalies.enabled = true,
+            "--testnet" => settings.testnet = true,
+            "--proxy" => network.proxy = Some(args.next().ok_or("--proxy requires a URL")?),
+            "--log-level" => logging.level = args.next().ok_or("--log-level requires a filter, e.g. debug")?,
+            "--log-file" => logging.file = Some(args.next().ok_or("--log-file requires a file path")?.into()),
+            "--vwap-notional" => {
+                settings.vwap_notional = args.next().ok_or("--vwap-notional requires a value")?.parse()?
+            }
+            "--count-split" => {
+                let name = args.next().ok_or("--count-split requires a value")?;
+                settings.estimator.count_split = multi_exchange_l3_est::orderbook::CountSplit::from_name(&name)
+                    .ok_or(format!("unknown count split: {name} (halving or max_entropy)"))?;
+            }
+            "--decomposition" => {
+                let name = args.next().ok_or("--decomposition requires a value")?;
+                settings.estimator.decomposition = multi_exchange_l3_est::orderbook::Decomposition::from_name(&name)
+                    .ok_or(format!("unknown decomposition: {name} (single or power_law)"))?;
+            }
+            "--audit" => {
+                let secs: f64 = args.next().ok_or("--audit requires an interval in seconds")?.parse()?;
+                settings.audit_interval = Some(Duration::try_from_secs_f64(secs)?).filter(|every| !every.is_zero());
+            }
+            "--stale-timeout" => {
+                let secs: f64 = args.next().ok_or("--stale-timeout requires a value")?.parse()?;
+                settings.stale_timeout = Duration::try_from_secs_f64(secs)?;
+            }
+            #[cfg(feature = "binance")]
+            "--market" => {
+                let name = args.next().ok_or("--market requires a value")?;
+                settings.binance_market = multi_exchange_l3_est::exchanges::binance::BinanceMarket::from_name(&name)
+                    .ok_or(format!("unknown Binance market: {name}"))?;
+            }
+            #[cfg(feature = "synthetic")]
+            "--rate" => settings.synthetic.update_rate = args.next().ok_or("--rate requires a value")?.parse()?,
+            #[cfg(feature = "synthetic")]
+            "--depth" => settings.synthetic.depth = args.next().ok_or("--depth requires a value")?.parse()?,
+            #[cfg(feature = "synthetic")]
+            "--order-flow" => settings.synthetic.order_flow = true,
+            #[cfg(feature = "synthetic")]
+            "--seed" => settings.synthetic.seed = args.next().ok_or("--seed requires a value")?.parse()?,
+            #[cfg(feature = "synthetic")]
+            "--whales" => {
+                settings.synthetic.whale_probability = args.next().ok_or("--whales requires a value")?.parse()?
+            }
+            _ => symbol = Some(arg.to_ascii_lowercase()),
+        }
     }
-}
-
-impl MyApp {
-    // Function to calculate color based on the order index
-    fn get_order_color(&self, index: usize, base_color: Color32) -> Color32 {
-        // Brighten the color by 5% for each order index
-        let brightening_factor = 1.0 + 0.05 * index as f32; // 5% brighter per order
-        let r = (base_color.r() as f32 * brightening_factor).min(255.0) as u8;
-        let g = (base_color.g() as f32 * brightening_factor).min(255.0) as u8;
-        let b = (base_color.b() as f32 * brightening_factor).min(255.0) as u8;
-
-        Color32::from_rgb(r, g, b)
+    // The flag's directory keeps the file's other archive settings
+    if let Some(dir) = archive_dir {
+        match &mut archive {
+            Some(archive) => archive.dir = dir,
+            None => archive = Some(ArchiveOptions::new(dir)),
+        }
     }
-}
-
-impl MyApp {
-    fn apply_update(&mut self, update: &exchanges::DepthUpdate) {
-        for bid in &update.bids {
-            let price = bid[0];
-            let qty = bid[1];
-            if qty == Decimal::ZERO {
-                self.bids.remove(&price);
-            } else {
-                let price = bid[0];
-                let qty = bid[1];
-                if qty > Decimal::ZERO {
-                    if let Some(old_qty) = self.bids.get_mut(&price) {
-                        let old_sum = old_qty.iter().sum::<Decimal>();
-                        if old_sum > qty {
-                            let change = old_sum - qty;
-                            if let Some(pos) = old_qty.iter().rposition(|&x| x == change) {
-                                old_qty.remove(pos); // Removes the last occurrence of the value
-                            } else {
-                                let largest_order = *old_qty.iter().max().unwrap();
-                                let largest_pos =
-                                    old_qty.iter().position(|&x| x == largest_order).unwrap();
-                                old_qty.remove(largest_pos);
-                                old_qty.push_back(largest_order - change);
-                            }
-                        } else if old_sum < qty {
-                            if old_sum < qty {
-                                let change = qty - old_sum;
-                                old_qty.push_back(change);
-                            }
-                        } else {
-                            // ??
-                            continue;
-                        }
-                    } else {
-                        self.bids.insert(price, VecDeque::from(vec![qty]));
-                    }
-                }
-            }
+    if let Some(archive) = &mut archive {
+        archive.interval = archive_interval.unwrap_or(archive.interval);
+        archive.format = archive_format.unwrap_or(archive.format);
+        archive.max_files = archive_max_files.or(archive.max_files);
+        archive.max_bytes = archive_max_mb.map(|mb| mb * 1024 * 1024).or(archive.max_bytes);
+    }
+    if let Some(resume) = &mut resume {
+        resume.interval = resume_interval.unwrap_or(resume.interval);
+    }
+    logging::init(&logging)?;
+    net::configure(network)?;
+    let export = match export_path {
+        Some(path) => {
+            let format = ExportFormat::from_path(&path)
+                .ok_or(format!("unsupported export format: {} (csv or parquet)", path.display()))?;
+            Some(ExportOptions {
+                path,
+                format,
+                interval: export_interval,
+                levels: export_levels,
+            })
         }
-        for ask in &update.asks {
-            let price = ask[0];
-            let qty = ask[1];
-            if qty == Decimal::ZERO {
-                self.asks.remove(&price);
-            } else if let Some(old_qty) = self.asks.get_mut(&price) {
-                let old_sum = old_qty.iter().sum::<Decimal>();
-                if old_sum > qty {
-                    let change = old_sum - qty;
-                    if let Some(pos) = old_qty.iter().rposition(|&x| x == change) {
-                        old_qty.remove(pos); // Removes the last occurrence of the value
-                    } else {
-                        let largest_order = *old_qty.iter().max().unwrap();
-                        let largest_pos = old_qty.iter().position(|&x| x == largest_order).unwrap();
-                        old_qty.remove(largest_pos);
-                        old_qty.push_back(largest_order - change);
-                    }
-                } else if old_sum < qty {
-                    if old_sum < qty {
-                        let change = qty - old_sum;
-                        old_qty.push_back(change);
-                    }
-                } else {
-                    // ??
-                    continue;
-                }
-            } else {
-                self.asks.insert(price, VecDeque::from(vec![qty]));
-            }
+        None => None,
+    };
+    // A replay of historical files runs to the end of the data and exits
+    if !import_paths.is_empty() {
+        let options = ImportOptions {
+            paths: import_paths,
+            symbol,
+        };
+        let summary = import::run(&options, exchange, &settings, export)?;
+        tracing::info!("Replayed {}", summary.describe());
+        if summary.flow.seconds > 0 {
+            tracing::info!("flow over the last minute {}", summary.flow.describe());
+        }
+        if summary.export_rows > 0 {
+            tracing::info!("Wrote {} rows", summary.export_rows);
         }
+        // The report is all that goes to stdout
+        println!("{}", serde_json::to_string(&summary)?);
+        return Ok(());
+    }
+    // Whether the subscription was chosen rather than defaulted; the GUI otherwise restores
+    // the previous session's tabs
+    #[cfg(feature = "gui")]
+    let explicit = symbol.is_some() || exchange.is_some() || settings.testnet;
+    let exchange = exchange.unwrap_or(ExchangeType::ALL[0]);
+    if settings.testnet && !exchange.has_testnet() {
+        tracing::warn!("{} has no testnet, connecting to production", exchange.name());
     }
-}
\ No newline at end of file
+    // Default for the selected exchange, will be adjusted per exchange
+    let symbol = exchange.adapt_symbol_with(&symbol.unwrap_or_else(|| "dogeusdt".to_string()), &settings);
+
+    if headless_mode {
+        let options = HeadlessOptions {
+            serve: serve_addr.map(|addr| ServeOptions { addr, depth: serve_depth }),
+            grpc: grpc_addr.map(|addr| ServeOptions { addr, depth: serve_depth }),
+            export,
+            alerts,
+            alert_targets,
+            anomalies,
+            publish: publish_target.map(|target| PublishOptions {
+                target,
+                prefix: publish_prefix,
+            }),
+            archive,
+            events_db,
+            resume,
+        };
+        headless::run(symbol, exchange, settings, options)?;
+        return Ok(());
+    }
+
+    #[cfg(feature = "gui")]
+    multi_exchange_l3_est::gui::run(symbol, exchange, settings, config, alerts, explicit)?;
+    Ok(())
+}
+
+// In the browser there are no arguments: the page loads the default configuration into the
+// `the_canvas_id` canvas, as eframe's web template names it
+#[cfg(target_arch = "wasm32")]
+fn main() {
+    let config = Config::default();
+    let settings = config.exchange_settings().expect("default settings are valid");
+    net::configure(config.network_settings().expect("default network settings are valid"))
+        .expect("default network settings apply");
+    let exchange = ExchangeType::ALL[0];
+    let symbol = exchange.adapt_symbol_with("dogeusdt", &settings);
+    let alerts = config.alerts.settings();
+    wasm_bindgen_futures::spawn_local(async move {
+        if let Err(e) =
+            multi_exchange_l3_est::gui::run_web("the_canvas_id", symbol, exchange, settings, config, alerts).await
+        {
+            web_sys::console::error_1(&e);
+        }
+    });
+}
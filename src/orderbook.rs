@@ -0,0 +1,1857 @@
+use crate::exchanges::{DepthUpdate, OrderBookSnapshot, OrderEvent, PriceLevel, Side, Trade};
+use crate::fixed;
+use crate::live_orders::LiveOrders;
+use crate::prior::SizePrior;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::time::Duration;
+use web_time::Instant;
+
+// Result of feeding a depth update into the book
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UpdateOutcome {
+    Applied,
+    // Held until the first snapshot arrives, or merged into the pending burst
+    Buffered,
+    // Older than the current book state
+    Skipped,
+}
+
+// Work the book has done since it was created, for session statistics; `clear` keeps them
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BookCounters {
+    // Depth updates (or merged bursts of them) applied to the levels
+    pub applied: u64,
+    // Updates folded into an earlier one by `queue_update`
+    pub coalesced: u64,
+    // Updates older than the book state, dropped
+    pub skipped: u64,
+    pub resyncs: u64,
+    // Time spent applying the `applied` updates, estimation included
+    pub apply_time: Duration,
+}
+
+impl BookCounters {
+    // Mean time an applied update took, in microseconds
+    pub fn mean_apply_us(&self) -> f64 {
+        if self.applied == 0 {
+            return 0.0;
+        }
+        self.apply_time.as_secs_f64() * 1e6 / self.applied as f64
+    }
+}
+
+// Derived top-of-book indicators, recomputed by the book after every change
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BookMetrics {
+    pub mid: Decimal,
+    // (bid size - ask size) / (bid size + ask size) over the top `metrics_depth` levels, in [-1, 1]
+    pub imbalance: Decimal,
+    // Best bid/ask weighted by the opposite side's size, so it leans towards the thinner side
+    pub microprice: Decimal,
+    // Midpoint of the size-weighted average prices of the top `metrics_depth` levels per side
+    pub weighted_mid: Decimal,
+    // Resting size within each of `LIQUIDITY_BANDS_BPS` of the mid
+    pub liquidity: [LiquidityBand; LIQUIDITY_BANDS_BPS.len()],
+    // Average price of a buy (walking the asks) and a sell (walking the bids) worth
+    // `vwap_notional`; None when the side holds less than that
+    pub buy_vwap: Option<Decimal>,
+    pub sell_vwap: Option<Decimal>,
+}
+
+// Distances from the mid, in basis points, that liquidity is summed within
+pub const LIQUIDITY_BANDS_BPS: [u32; 4] = [5, 10, 25, 50];
+
+// Quote notional the VWAPs in `BookMetrics` are taken over unless `set_vwap_notional` says otherwise
+pub const DEFAULT_VWAP_NOTIONAL: Decimal = Decimal::from_parts(10_000, 0, 0, false, 0);
+
+// Bid and ask size resting within `bps` basis points of the mid, in base units
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LiquidityBand {
+    pub bps: u32,
+    pub bid: Decimal,
+    pub ask: Decimal,
+}
+
+// Result of walking the book with a hypothetical market order
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FillEstimate {
+    // Quantity that could be filled; less than requested if the book ran out
+    pub filled: Decimal,
+    pub avg_price: Decimal,
+    // Price of the last level touched
+    pub worst_price: Decimal,
+    pub levels: usize,
+    // Average price relative to the best price on the side walked, in basis points (always >= 0)
+    pub slippage_bps: Decimal,
+}
+
+// How the estimator arrived at an order's current size, from most to least certain. An order
+// keeps the least certain step it went through.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Inference {
+    // Joined with a level increase of exactly its size
+    ExactDelta,
+    // Partially filled by a trade printed at its level
+    TradeAttributed,
+    // Shrunk because no order matched a decrease exactly, so the largest one took it
+    Fallback,
+    // One of the children a snapshot level too large for one order was decomposed into (see `prior`)
+    Prior,
+    // A whole snapshot level loaded as one order
+    Snapshot,
+}
+
+impl Inference {
+    // Weight in (0, 1] for consumers of the reconstruction
+    pub fn confidence(self) -> f64 {
+        match self {
+            Inference::ExactDelta => 1.0,
+            Inference::TradeAttributed => 0.8,
+            Inference::Fallback => 0.4,
+            Inference::Prior => 0.3,
+            Inference::Snapshot => 0.2,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Inference::ExactDelta => "exact delta",
+            Inference::TradeAttributed => "trade",
+            Inference::Fallback => "fallback",
+            Inference::Prior => "prior",
+            Inference::Snapshot => "snapshot",
+        }
+    }
+}
+
+// One inferred child order resting at a price level
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EstimatedOrder {
+    // Unique within the book, assigned in the order the estimator inferred the orders
+    pub id: u64,
+    // Size in lots of 10^-decimals, the book's quantity scale; `size` gives it as a Decimal
+    lots: i64,
+    decimals: u32,
+    // Milliseconds since the Unix epoch at which the estimator first saw the order
+    pub first_seen: u64,
+    // Time priority at the level, lower fills first. An order shrunk without an exact match is
+    // requeued at the back, so it gets a new priority but keeps its id and first-seen time.
+    pub priority: u64,
+    // When the order took its current place in the queue (ms since the Unix epoch); 0 for
+    // orders loaded from a snapshot, whose place is unknown
+    pub queued_at: u64,
+    // Loaded from a snapshot level rather than inferred from a change
+    pub from_snapshot: bool,
+    pub inference: Inference,
+}
+
+impl EstimatedOrder {
+    pub fn size(&self) -> Decimal {
+        fixed::from_lots(self.lots.into(), self.decimals)
+    }
+
+    pub fn confidence(&self) -> f64 {
+        self.inference.confidence()
+    }
+}
+
+// The estimated orders and estimator state of a synced book (`OrderBook::state`), kept across a
+// restart and reconciled against a fresh snapshot by `OrderBook::resume`
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SavedBook {
+    pub last_update_id: u64,
+    pub last_order_id: u64,
+    // Best first
+    pub bids: Vec<SavedLevel>,
+    pub asks: Vec<SavedLevel>,
+    pub size_prior: SizePrior,
+    pub prior_applied: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SavedLevel {
+    pub price: Decimal,
+    pub reported: Option<u32>,
+    // Time priority, oldest first
+    pub orders: Vec<SavedOrder>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SavedOrder {
+    pub id: u64,
+    pub size: Decimal,
+    pub first_seen: u64,
+    pub priority: u64,
+    pub queued_at: u64,
+    pub from_snapshot: bool,
+    pub inference: Inference,
+}
+
+impl SavedLevel {
+    fn from_queue(price: Decimal, queue: &OrderQueue) -> Self {
+        Self {
+            price,
+            reported: queue.reported,
+            orders: queue
+                .iter()
+                .map(|order| SavedOrder {
+                    id: order.id,
+                    size: order.size(),
+                    first_seen: order.first_seen,
+                    priority: order.priority,
+                    queued_at: order.queued_at,
+                    from_snapshot: order.from_snapshot,
+                    inference: order.inference,
+                })
+                .collect(),
+        }
+    }
+}
+
+// What the estimator decided a level change did to one order. It only sees level totals, so
+// a cancel and a fill look the same.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InferredAction {
+    // A new order joined the back of the queue
+    Joined,
+    // The order left the queue whole: an exact match for a decrease, part of a decrease larger
+    // than any one order, or its level was removed
+    Removed,
+    // Shrunk by a partial cancel or fill and requeued at the back, or split in place to match the
+    // venue's order count
+    Reduced,
+    // Filled by a trade at the level: partly, keeping its place in the queue, or whole (size
+    // zero), leaving it
+    Filled,
+}
+
+impl InferredAction {
+    pub fn label(self) -> &'static str {
+        match self {
+            InferredAction::Joined => "joined",
+            InferredAction::Removed => "removed",
+            InferredAction::Reduced => "reduced",
+            InferredAction::Filled => "filled",
+        }
+    }
+}
+
+// How a queue is reshaped to the order count a venue reports for its level
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CountSplit {
+    // One order at a time: the largest is split in half, or the smallest merged into its older
+    // neighbour
+    Halving,
+    // Keeps the orders inferred from exact deltas and trades and spreads the rest of the level
+    // evenly over the orders left to account for; knowing only their total and count, the equal
+    // split is the maximum-entropy one. Falls back to halving when those orders alone exceed the
+    // count or the rest is too small to spread.
+    #[default]
+    MaxEntropy,
+}
+
+impl CountSplit {
+    pub const ALL: [CountSplit; 2] = [CountSplit::Halving, CountSplit::MaxEntropy];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CountSplit::Halving => "halving",
+            CountSplit::MaxEntropy => "max_entropy",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|split| split.name() == name)
+    }
+}
+
+// What a snapshot level, which arrives with no history of how it was built, is taken to be
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Decomposition {
+    // One order of that size
+    #[default]
+    Single,
+    // Children drawn from the book's fitted order size distribution (`OrderBook::size_prior`)
+    // once it has enough samples, when the level is too large to be one plausible order. A
+    // snapshot arriving before then bootstraps the fit from its own levels; levels still loaded
+    // whole are decomposed when the fit becomes ready.
+    PowerLaw,
+}
+
+impl Decomposition {
+    pub const ALL: [Decomposition; 2] = [Decomposition::Single, Decomposition::PowerLaw];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Decomposition::Single => "single",
+            Decomposition::PowerLaw => "power_law",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|decomposition| decomposition.name() == name)
+    }
+}
+
+// Tunables of the L3 estimator
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct EstimatorSettings {
+    pub count_split: CountSplit,
+    pub decomposition: Decomposition,
+}
+
+// One decision of the L3 estimator, recorded while the book's event log is on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InferredEvent {
+    // Milliseconds since the Unix epoch
+    pub time: u64,
+    pub side: Side,
+    pub price: Decimal,
+    pub order_id: u64,
+    pub action: InferredAction,
+    // Size of the order after the change, zero once removed
+    pub size: Decimal,
+    // Change in the order's size: positive when it joined, negative otherwise
+    pub change: Decimal,
+}
+
+// Estimated orders at one price level in time priority (front = oldest)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OrderQueue {
+    orders: VecDeque<EstimatedOrder>,
+    // Sum of the orders' lots, kept up to date by every change so the level total costs nothing
+    lots: i128,
+    // Orders at the level as last reported by the venue, on venues that report it
+    reported: Option<u32>,
+}
+
+impl OrderQueue {
+    pub fn len(&self) -> usize {
+        self.orders.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.orders.is_empty()
+    }
+
+    // Level quantity, i.e. the sum of the order sizes
+    pub fn total(&self) -> Decimal {
+        match self.orders.front() {
+            Some(first) => fixed::from_lots(self.lots, first.decimals),
+            None => Decimal::ZERO,
+        }
+    }
+
+    // Size of the first `count` orders, summed as lots
+    fn sum(&self, count: usize) -> Decimal {
+        let Some(first) = self.orders.front() else {
+            return Decimal::ZERO;
+        };
+        let lots = self.orders.iter().take(count).map(|order| i128::from(order.lots)).sum();
+        fixed::from_lots(lots, first.decimals)
+    }
+
+    fn total_lots(&self) -> i128 {
+        self.lots
+    }
+
+    pub fn iter(&self) -> std::collections::vec_deque::Iter<'_, EstimatedOrder> {
+        self.orders.iter()
+    }
+
+    pub fn sizes(&self) -> impl DoubleEndedIterator<Item = Decimal> + '_ {
+        self.orders.iter().map(EstimatedOrder::size)
+    }
+
+    pub fn get(&self, index: usize) -> Option<&EstimatedOrder> {
+        self.orders.get(index)
+    }
+
+    // Quantity queued ahead of the order at `index`
+    pub fn size_ahead(&self, index: usize) -> Decimal {
+        self.sum(index)
+    }
+
+    // Orders and quantity queued no later than `joined_at` (ms since the Unix epoch), i.e.
+    // ahead of an order that joined the back of the queue then
+    pub fn ahead_of(&self, joined_at: u64) -> (usize, Decimal) {
+        let Some(first) = self.orders.front() else {
+            return (0, Decimal::ZERO);
+        };
+        let (count, lots) = self
+            .orders
+            .iter()
+            .filter(|order| order.queued_at <= joined_at)
+            .fold((0, 0i128), |(count, lots), order| (count + 1, lots + i128::from(order.lots)));
+        (count, fixed::from_lots(lots, first.decimals))
+    }
+
+    // The venue's order count for the level; the estimated queue is kept to that many orders
+    // where the sizes allow
+    pub fn reported_count(&self) -> Option<u32> {
+        self.reported
+    }
+
+    // Largest order, the oldest one on ties
+    pub fn largest(&self) -> Option<&EstimatedOrder> {
+        self.orders.iter().rev().max_by_key(|order| order.lots)
+    }
+
+    fn push_back(&mut self, order: EstimatedOrder) {
+        self.lots += i128::from(order.lots);
+        self.orders.push_back(order);
+    }
+
+    fn remove(&mut self, index: usize) -> Option<EstimatedOrder> {
+        let order = self.orders.remove(index)?;
+        self.lots -= i128::from(order.lots);
+        Some(order)
+    }
+
+    // Appends another level's orders, as a price group does; the group's reported count is
+    // known only while every level in it has one
+    fn absorb(&mut self, level: &OrderQueue) {
+        self.reported = match self.is_empty() {
+            true => level.reported,
+            false => self.reported.zip(level.reported).map(|(a, b)| a + b),
+        };
+        level.iter().for_each(|&order| self.push_back(order));
+    }
+
+    fn insert(&mut self, index: usize, order: EstimatedOrder) {
+        self.lots += i128::from(order.lots);
+        self.orders.insert(index, order);
+    }
+
+    // Moves every order from `from` to `to` decimals; the caller checked that they all fit
+    fn rescale(&mut self, from: u32, to: u32) {
+        for order in &mut self.orders {
+            order.lots = fixed::rescale(order.lots, from, to).unwrap_or(order.lots);
+            order.decimals = to;
+        }
+        self.lots = self.orders.iter().map(|order| i128::from(order.lots)).sum();
+    }
+}
+
+impl<'a> IntoIterator for &'a OrderQueue {
+    type Item = &'a EstimatedOrder;
+    type IntoIter = std::collections::vec_deque::Iter<'a, EstimatedOrder>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.orders.iter()
+    }
+}
+
+impl FromIterator<EstimatedOrder> for OrderQueue {
+    fn from_iter<I: IntoIterator<Item = EstimatedOrder>>(iter: I) -> Self {
+        let orders: VecDeque<EstimatedOrder> = iter.into_iter().collect();
+        let lots = orders.iter().map(|order| i128::from(order.lots)).sum();
+        Self {
+            orders,
+            lots,
+            reported: None,
+        }
+    }
+}
+
+// Hands out order ids and priorities, and the time and quantity scale stamped on new orders;
+// records what the estimator did to them when the event log is on
+struct OrderStamp<'a> {
+    next_id: &'a mut u64,
+    now: u64,
+    decimals: u32,
+    events: Option<&'a mut Vec<InferredEvent>>,
+    estimator: EstimatorSettings,
+    prior: &'a mut SizePrior,
+}
+
+impl OrderStamp<'_> {
+    fn next(&mut self) -> u64 {
+        *self.next_id += 1;
+        *self.next_id
+    }
+
+    fn order(&mut self, lots: i64) -> EstimatedOrder {
+        let id = self.next();
+        EstimatedOrder {
+            id,
+            lots,
+            decimals: self.decimals,
+            first_seen: self.now,
+            priority: id,
+            queued_at: self.now,
+            from_snapshot: false,
+            inference: Inference::ExactDelta,
+        }
+    }
+
+    // Children for a snapshot level of `lots` under `Decomposition::PowerLaw`, None for one order
+    fn decomposed(&self, lots: i64) -> Option<Vec<i64>> {
+        match self.estimator.decomposition {
+            Decomposition::Single => None,
+            Decomposition::PowerLaw => self.prior.decompose(lots, self.decimals),
+        }
+    }
+
+    // Queues `lots` that joined the level at once as one order, and fits the prior to its size
+    fn join(&mut self, queue: &mut OrderQueue, side: Side, price: Decimal, lots: i64) {
+        let order = self.order(lots);
+        self.record(side, price, order.id, InferredAction::Joined, 0, lots);
+        queue.push_back(order);
+        self.prior.observe(fixed::from_lots(lots.into(), self.decimals));
+    }
+
+    // A new order standing in for `order`: same size, scale, timing and provenance, fresh id
+    fn order_like(&mut self, order: &EstimatedOrder) -> EstimatedOrder {
+        let id = self.next();
+        EstimatedOrder {
+            id,
+            priority: id,
+            inference: order.inference.max(Inference::Fallback),
+            ..*order
+        }
+    }
+
+    // Order `id` at `price` went from `before` to `after` lots
+    fn record(&mut self, side: Side, price: Decimal, id: u64, action: InferredAction, before: i64, after: i64) {
+        tracing::trace!(
+            target: "estimator",
+            ?side,
+            %price,
+            order = id,
+            ?action,
+            before = %fixed::from_lots(before.into(), self.decimals),
+            after = %fixed::from_lots(after.into(), self.decimals),
+        );
+        if let Some(events) = &mut self.events {
+            events.push(InferredEvent {
+                time: self.now,
+                side,
+                price,
+                order_id: id,
+                action,
+                size: fixed::from_lots(after.into(), self.decimals),
+                change: fixed::from_lots(i128::from(after) - i128::from(before), self.decimals),
+            });
+        }
+    }
+}
+
+fn now_millis() -> u64 {
+    chrono::Utc::now().timestamp_millis() as u64
+}
+
+// Estimated place of a hypothetical order in a level's queue
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QueuePosition {
+    pub orders_ahead: usize,
+    pub qty_ahead: Decimal,
+    // Everything resting at the level, including orders that joined later
+    pub level_qty: Decimal,
+    // The opposite side has reached the price, so the order would have been filled
+    pub traded_through: bool,
+}
+
+const DEFAULT_METRICS_DEPTH: usize = 10;
+
+// How long traded volume waits for the level decrease it explains
+const TRADE_ATTRIBUTION_MS: u64 = 1000;
+
+// Crossed states repaired since the last snapshot before the book asks to be refetched
+const MAX_CROSSINGS: u64 = 3;
+
+// Volume traded against a resting level that no decrease of the level has accounted for yet
+#[derive(Clone, Copy, Debug)]
+struct TradedVolume {
+    side: Side,
+    price: Decimal,
+    qty: Decimal,
+    // Local time of the last trade, ms since the Unix epoch
+    time: u64,
+}
+
+// L2 book with a naive per-level L3 queue estimate.
+//
+// Each price level holds the estimated individual orders in FIFO order (front = oldest).
+#[derive(Clone)]
+pub struct OrderBook {
+    pub bids: BTreeMap<Decimal, OrderQueue>,
+    pub asks: BTreeMap<Decimal, OrderQueue>,
+    // Last id handed to an estimated order
+    last_order_id: u64,
+    last_applied_u: u64,
+    is_synced: bool,
+    update_buffer: VecDeque<DepthUpdate>,
+    // Burst of updates merged by `queue_update`, applied as one delta by `flush_updates`
+    pending: Option<DepthUpdate>,
+    counters: BookCounters,
+    metrics_depth: usize,
+    estimator: EstimatorSettings,
+    // Quote notional of the VWAPs in the metrics
+    vwap_notional: Decimal,
+    metrics: Option<BookMetrics>,
+    // Price bucket size for the grouped view, maintained incrementally alongside the raw levels
+    grouping: Option<Decimal>,
+    grouped_bids: BTreeMap<Decimal, OrderQueue>,
+    grouped_asks: BTreeMap<Decimal, OrderQueue>,
+    // Decimals of the instrument's quantity step, set by `set_qty_decimals`
+    step_decimals: u32,
+    // Scale every estimated order is held at: the step's, or finer once the feed sends finer
+    // quantities, until the next snapshot
+    qty_decimals: u32,
+    // Inferred events since the last `drain_events`, None while the log is off
+    events: Option<Vec<InferredEvent>>,
+    // Trades waiting for the level decrease they explain
+    traded: Vec<TradedVolume>,
+    // Levels per side estimated at most, set by `set_max_depth`
+    max_depth: Option<usize>,
+    // Crossed states repaired since the last snapshot or resync
+    crossings: u64,
+    // Order sizes fit from exact deltas and trades, for `Decomposition::PowerLaw`
+    size_prior: SizePrior,
+    // The snapshot levels were decomposed, or loaded while the prior was ready
+    prior_applied: bool,
+    // Time set by `set_clock` for replays; None follows the wall clock
+    clock: Option<u64>,
+    // The venue's own queues on order-level feeds, next to the estimate
+    live: LiveOrders,
+}
+
+impl Default for OrderBook {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OrderBook {
+    pub fn new() -> Self {
+        Self {
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+            last_order_id: 0,
+            last_applied_u: 0,
+            is_synced: false,
+            update_buffer: VecDeque::new(),
+            pending: None,
+            counters: BookCounters::default(),
+            metrics_depth: DEFAULT_METRICS_DEPTH,
+            estimator: EstimatorSettings::default(),
+            vwap_notional: DEFAULT_VWAP_NOTIONAL,
+            metrics: None,
+            grouping: None,
+            grouped_bids: BTreeMap::new(),
+            grouped_asks: BTreeMap::new(),
+            step_decimals: 0,
+            qty_decimals: 0,
+            events: None,
+            traded: Vec::new(),
+            max_depth: None,
+            crossings: 0,
+            size_prior: SizePrior::default(),
+            prior_applied: false,
+            clock: None,
+            live: LiveOrders::new(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.bids.clear();
+        self.asks.clear();
+        self.last_applied_u = 0;
+        self.is_synced = false;
+        self.update_buffer.clear();
+        self.pending = None;
+        self.metrics = None;
+        self.grouped_bids.clear();
+        self.grouped_asks.clear();
+        self.qty_decimals = self.step_decimals;
+        self.traded.clear();
+        self.crossings = 0;
+        self.size_prior = SizePrior::default();
+        self.prior_applied = false;
+        self.live.clear();
+    }
+
+    // Drops the sync state after the feed reconnected. Levels stay on display until the next
+    // snapshot replaces them; updates from the new stream are buffered until then.
+    pub fn resync(&mut self) {
+        self.flush_updates();
+        tracing::debug!(last_update_id = self.last_applied_u, "book resync, waiting for a snapshot");
+        self.last_applied_u = 0;
+        self.is_synced = false;
+        self.update_buffer.clear();
+        self.crossings = 0;
+        self.counters.resyncs += 1;
+        // Order events were missed while the stream was down; feeds that resend their resting
+        // orders do so after the next snapshot, Bitstamp's queues refill as orders turn over
+        self.live.clear();
+    }
+
+    pub fn last_update_id(&self) -> u64 {
+        self.last_applied_u
+    }
+
+    pub fn is_synced(&self) -> bool {
+        self.is_synced
+    }
+
+    // Number of updates folded into an earlier one by `queue_update`
+    pub fn coalesced_updates(&self) -> u64 {
+        self.counters.coalesced
+    }
+
+    pub fn counters(&self) -> BookCounters {
+        self.counters
+    }
+
+    pub fn best_bid(&self) -> Option<Decimal> {
+        self.bids.keys().next_back().copied()
+    }
+
+    pub fn best_ask(&self) -> Option<Decimal> {
+        self.asks.keys().next().copied()
+    }
+
+    // Quantity resting at `price` on `side` of the raw book, zero if there is no such level.
+    // Levels keep their total up to date as orders are inferred, so this is a lookup.
+    pub fn level_total(&self, side: Side, price: Decimal) -> Decimal {
+        let levels = match side {
+            Side::Bid => &self.bids,
+            Side::Ask => &self.asks,
+        };
+        levels.get(&price).map_or(Decimal::ZERO, OrderQueue::total)
+    }
+
+    // Quantity decimals of the instrument, i.e. of its step size; estimated orders are held as
+    // integer lots of that step
+    pub fn set_qty_decimals(&mut self, decimals: usize) {
+        self.step_decimals = (decimals as u32).min(fixed::MAX_DECIMALS);
+        self.rescale(self.step_decimals);
+    }
+
+    pub fn size_prior(&self) -> &SizePrior {
+        &self.size_prior
+    }
+
+    pub fn estimator(&self) -> EstimatorSettings {
+        self.estimator
+    }
+
+    // Applies from the next change on; queues already estimated are left as they are
+    pub fn set_estimator(&mut self, settings: EstimatorSettings) {
+        self.estimator = settings;
+    }
+
+    // Records the estimator's decisions for `drain_events`. Off by default; turning it off drops
+    // the events not drained yet.
+    pub fn set_event_log(&mut self, enabled: bool) {
+        if enabled != self.events.is_some() {
+            self.events = enabled.then(Vec::new);
+        }
+    }
+
+    // Replay time in milliseconds since the Unix epoch: while set, orders are stamped and trades
+    // attributed at it rather than at the wall clock, so historical data keeps its own timing
+    pub fn set_clock(&mut self, time: Option<u64>) {
+        self.clock = time;
+    }
+
+    fn now(&self) -> u64 {
+        self.clock.unwrap_or_else(now_millis)
+    }
+
+    // Orders joined, removed and reduced by the updates applied since the last call, oldest
+    // first. Snapshots and full states loaded as snapshots reset the book without events.
+    pub fn drain_events(&mut self) -> impl Iterator<Item = InferredEvent> + '_ {
+        self.events.iter_mut().flat_map(|events| events.drain(..))
+    }
+
+    // Times an update left the book crossed since the last snapshot or resync; each time the
+    // stale side's crossing levels were dropped
+    pub fn crossings(&self) -> u64 {
+        self.crossings
+    }
+
+    // The book keeps crossing, so pruning is not enough and it should be refetched
+    pub fn needs_resync(&self) -> bool {
+        self.crossings >= MAX_CROSSINGS
+    }
+
+    pub fn max_depth(&self) -> Option<usize> {
+        self.max_depth
+    }
+
+    // Keeps at most `depth` levels per side, dropping the ones furthest from the touch. A dropped
+    // level comes back with its next update, as a single order of unknown history.
+    pub fn set_max_depth(&mut self, depth: Option<usize>) {
+        self.flush_updates();
+        self.max_depth = depth.map(|d| d.max(1));
+        self.trim_depth();
+        self.refresh_metrics();
+    }
+
+    pub fn grouping(&self) -> Option<Decimal> {
+        self.grouping
+    }
+
+    // Groups levels into price buckets of `bucket` for the view accessors; None or a
+    // non-positive size shows the raw levels
+    pub fn set_grouping(&mut self, bucket: Option<Decimal>) {
+        self.grouping = bucket.filter(|b| *b > Decimal::ZERO);
+        self.rebuild_groups();
+    }
+
+    // Levels for display and clustering: the grouped book when grouping is set, otherwise
+    // the raw levels. Each bucket's queue is its levels' queues concatenated in price order.
+    pub fn view_bids(&self) -> &BTreeMap<Decimal, OrderQueue> {
+        if self.grouping.is_some() {
+            &self.grouped_bids
+        } else {
+            &self.bids
+        }
+    }
+
+    pub fn view_asks(&self) -> &BTreeMap<Decimal, OrderQueue> {
+        if self.grouping.is_some() {
+            &self.grouped_asks
+        } else {
+            &self.asks
+        }
+    }
+
+    pub fn metrics(&self) -> Option<BookMetrics> {
+        self.metrics
+    }
+
+    pub fn metrics_depth(&self) -> usize {
+        self.metrics_depth
+    }
+
+    // Number of levels per side used for imbalance and weighted mid
+    pub fn set_metrics_depth(&mut self, depth: usize) {
+        self.metrics_depth = depth.max(1);
+        self.refresh_metrics();
+    }
+
+    pub fn vwap_notional(&self) -> Decimal {
+        self.vwap_notional
+    }
+
+    // Quote notional of the buy and sell VWAPs in the metrics
+    pub fn set_vwap_notional(&mut self, notional: Decimal) {
+        self.vwap_notional = notional.max(Decimal::ZERO);
+        self.refresh_metrics();
+    }
+
+    // Fill of a market order of `size` by `taker` (Bid = buy, walks the asks; Ask = sell,
+    // walks the bids). None if the side is empty or `size` is not positive.
+    pub fn estimate_fill(&self, taker: Side, size: Decimal) -> Option<FillEstimate> {
+        if size <= Decimal::ZERO {
+            return None;
+        }
+        let levels: Box<dyn Iterator<Item = (&Decimal, &OrderQueue)>> = match taker {
+            Side::Bid => Box::new(self.asks.iter()),
+            Side::Ask => Box::new(self.bids.iter().rev()),
+        };
+
+        let mut remaining = size;
+        let mut notional = Decimal::ZERO;
+        let mut best = None;
+        let mut worst_price = Decimal::ZERO;
+        let mut count = 0;
+        for (&price, queue) in levels {
+            if remaining <= Decimal::ZERO {
+                break;
+            }
+            let take = queue.total().min(remaining);
+            best.get_or_insert(price);
+            notional += take * price;
+            remaining -= take;
+            worst_price = price;
+            count += 1;
+        }
+
+        let best = best?;
+        let filled = size - remaining;
+        if filled <= Decimal::ZERO || best <= Decimal::ZERO {
+            return None;
+        }
+        let avg_price = notional / filled;
+        Some(FillEstimate {
+            filled,
+            avg_price,
+            worst_price,
+            levels: count,
+            slippage_bps: (avg_price - best).abs() / best * Decimal::from(10_000),
+        })
+    }
+
+    // Where an order placed on `side` at `price` at `joined_at` (ms since the Unix epoch) would
+    // now be in the queue. Orders ahead of it leave as fills and cancels are inferred, so calling
+    // this after every update tracks the order. Orders loaded from a snapshot are always counted
+    // as ahead, since their place in the queue is unknown. Uses the raw levels, not the grouped view.
+    pub fn queue_position(&self, side: Side, price: Decimal, joined_at: u64) -> QueuePosition {
+        let (levels, traded_through) = match side {
+            Side::Bid => (&self.bids, self.best_ask().is_some_and(|ask| ask <= price)),
+            Side::Ask => (&self.asks, self.best_bid().is_some_and(|bid| bid >= price)),
+        };
+        let (orders_ahead, qty_ahead, level_qty) = match levels.get(&price) {
+            Some(queue) => {
+                let (orders, qty) = queue.ahead_of(joined_at);
+                (orders, qty, queue.total())
+            }
+            None => (0, Decimal::ZERO, Decimal::ZERO),
+        };
+        QueuePosition {
+            orders_ahead,
+            qty_ahead,
+            level_qty,
+            traded_through,
+        }
+    }
+
+    // Order-level (true L3) event from venues that publish individual orders. Kept as the venue's
+    // queues next to the estimated ones, which stay driven by the level updates so both can be
+    // compared (`live_orders::QueueCheck`)
+    pub fn apply_order_event(&mut self, event: &OrderEvent) {
+        self.live.apply(event);
+    }
+
+    // The venue's queues from `apply_order_event`
+    pub fn live_orders(&self) -> &LiveOrders {
+        &self.live
+    }
+
+    // Total number of estimated individual orders on both sides
+    pub fn order_count(&self) -> usize {
+        self.bids.values().chain(self.asks.values()).map(|q| q.len()).sum()
+    }
+
+    // Resets the book from a snapshot (one aggregated order per level unless decomposed) and replays any
+    // updates that were buffered while waiting for it.
+    pub fn apply_snapshot(&mut self, snap: &OrderBookSnapshot) -> UpdateOutcome {
+        self.flush_updates();
+        // The venue's queues are kept: Bitstamp's first full state arrives after order events have
+        // already been streamed, and Bitfinex and Drift resend every resting order after a
+        // reconnect, which `resync` clears them for
+        self.bids.clear();
+        self.asks.clear();
+        self.grouped_bids.clear();
+        self.grouped_asks.clear();
+        self.traded.clear();
+        self.crossings = 0;
+        self.qty_decimals = self.step_decimals;
+        self.fit_scale(snap.bids.iter().chain(&snap.asks));
+        if self.estimator.decomposition == Decomposition::PowerLaw {
+            self.size_prior.bootstrap(snap.bids.iter().chain(&snap.asks));
+        }
+        let now = self.now();
+        let mut stamp = OrderStamp {
+            next_id: &mut self.last_order_id,
+            now,
+            decimals: self.qty_decimals,
+            events: None,
+            estimator: self.estimator,
+            prior: &mut self.size_prior,
+        };
+        let sides = [(&mut self.bids, Side::Bid, &snap.bids), (&mut self.asks, Side::Ask, &snap.asks)];
+        for (levels, side, snap_levels) in sides {
+            for level in snap_levels {
+                let Some(lots) = fixed::to_lots(level.qty, stamp.decimals).filter(|&lots| lots > 0) else {
+                    continue;
+                };
+                // A reported count shapes the queue instead of the prior
+                let children = match level.order_count {
+                    Some(_) => None,
+                    None => stamp.decomposed(lots),
+                };
+                let inference = if children.is_some() { Inference::Prior } else { Inference::Snapshot };
+                let mut queue: OrderQueue = children
+                    .unwrap_or_else(|| vec![lots])
+                    .into_iter()
+                    .map(|lots| EstimatedOrder {
+                        queued_at: 0,
+                        from_snapshot: true,
+                        inference,
+                        ..stamp.order(lots)
+                    })
+                    .collect();
+                if let Some(count) = level.order_count {
+                    Self::match_count(&mut queue, side, level.price, count, &mut stamp);
+                }
+                levels.insert(level.price, queue);
+            }
+        }
+        self.prior_applied = self.size_prior.is_ready();
+        self.last_applied_u = snap.last_update_id;
+        self.is_synced = true;
+        self.trim_depth();
+        self.rebuild_groups();
+        self.refresh_metrics();
+
+        while let Some(update) = self.update_buffer.pop_front() {
+            self.process_update(update);
+        }
+        UpdateOutcome::Applied
+    }
+
+    // The estimated orders and estimator state, for `resume` after a restart
+    pub fn state(&self) -> SavedBook {
+        SavedBook {
+            last_update_id: self.last_applied_u,
+            last_order_id: self.last_order_id,
+            bids: self.bids.iter().rev().map(|(&price, queue)| SavedLevel::from_queue(price, queue)).collect(),
+            asks: self.asks.iter().map(|(&price, queue)| SavedLevel::from_queue(price, queue)).collect(),
+            size_prior: self.size_prior,
+            prior_applied: self.prior_applied,
+        }
+    }
+
+    // Loads a saved state in place of the snapshot, then applies the snapshot to it as a full
+    // state: levels it shows unchanged keep their estimated orders, ids and ages, and the
+    // difference at the others goes through the estimator like any update, as what happened
+    // while the book was not running. Buffered updates are replayed as after a snapshot.
+    pub fn resume(&mut self, state: &SavedBook, snap: &OrderBookSnapshot) -> UpdateOutcome {
+        self.flush_updates();
+        self.bids.clear();
+        self.asks.clear();
+        self.grouped_bids.clear();
+        self.grouped_asks.clear();
+        self.traded.clear();
+        self.crossings = 0;
+        let saved = state.bids.iter().chain(&state.asks).flat_map(|level| &level.orders);
+        let needed = saved.map(|order| fixed::decimals_of(order.size)).max().unwrap_or(0);
+        self.qty_decimals = self.step_decimals.max(needed.min(fixed::MAX_DECIMALS));
+        self.last_order_id = self.last_order_id.max(state.last_order_id);
+        self.size_prior = state.size_prior;
+        self.prior_applied = state.prior_applied;
+        for (levels, saved) in [(&mut self.bids, &state.bids), (&mut self.asks, &state.asks)] {
+            for level in saved {
+                let mut queue: OrderQueue = level
+                    .orders
+                    .iter()
+                    .filter_map(|order| {
+                        let lots = fixed::to_lots(order.size, self.qty_decimals).filter(|&lots| lots > 0)?;
+                        Some(EstimatedOrder {
+                            id: order.id,
+                            lots,
+                            decimals: self.qty_decimals,
+                            first_seen: order.first_seen,
+                            priority: order.priority,
+                            queued_at: order.queued_at,
+                            from_snapshot: order.from_snapshot,
+                            inference: order.inference,
+                        })
+                    })
+                    .collect();
+                if !queue.is_empty() {
+                    queue.reported = level.reported;
+                    levels.insert(level.price, queue);
+                }
+            }
+        }
+        self.fit_scale(snap.bids.iter().chain(&snap.asks));
+        self.is_synced = true;
+        self.last_applied_u = snap.last_update_id;
+        self.trim_depth();
+        self.rebuild_groups();
+        let update = DepthUpdate {
+            event_time: snap.event_time,
+            transaction_time: snap.event_time,
+            symbol: String::new(),
+            capital_u: snap.last_update_id,
+            small_u: snap.last_update_id,
+            pu: -1,
+            bids: Self::diff_levels(&self.bids, &snap.bids, Side::Bid, self.max_depth),
+            asks: Self::diff_levels(&self.asks, &snap.asks, Side::Ask, self.max_depth),
+            recv_time: snap.recv_time,
+        };
+        self.process_update(update);
+        while let Some(update) = self.update_buffer.pop_front() {
+            self.process_update(update);
+        }
+        UpdateOutcome::Applied
+    }
+
+    // Sequencing is the connector's job (see the Binance connector); the book only holds
+    // updates back until a snapshot is loaded and skips those the snapshot already contains
+    pub fn handle_update(&mut self, update: DepthUpdate) -> UpdateOutcome {
+        if self.is_synced {
+            self.process_update(update)
+        } else {
+            self.update_buffer.push_back(update);
+            UpdateOutcome::Buffered
+        }
+    }
+
+    // Like `handle_update`, but merges the update into the pending burst instead of applying it,
+    // so a frame's worth of updates costs one pass over the levels, groups and metrics. The
+    // merged delta keeps the sequence range of the whole burst. Call `flush_updates` before
+    // reading the book; the other mutating methods flush first.
+    //
+    // Until a snapshot is loaded updates are buffered one by one, so the ones it already
+    // contains are still skipped individually.
+    pub fn queue_update(&mut self, update: DepthUpdate) -> UpdateOutcome {
+        if !self.is_synced {
+            return self.handle_update(update);
+        }
+        match &mut self.pending {
+            Some(pending) if update.small_u >= pending.small_u => {
+                pending.merge(update);
+                self.counters.coalesced += 1;
+            }
+            _ => {
+                // Out of order updates are left for `process_update` to skip
+                self.flush_updates();
+                self.pending = Some(update);
+            }
+        }
+        UpdateOutcome::Buffered
+    }
+
+    // Applies the burst merged by `queue_update`, if any
+    pub fn flush_updates(&mut self) -> Option<UpdateOutcome> {
+        let update = self.pending.take()?;
+        Some(self.process_update(update))
+    }
+
+    // A trade against the book. The next decrease of the level it printed at is taken as fills
+    // from the front of that level's queue, rather than guessed from the sizes; the volume waits
+    // `TRADE_ATTRIBUTION_MS` for that decrease.
+    pub fn apply_trade(&mut self, trade: &Trade) {
+        if !self.is_synced || trade.qty <= Decimal::ZERO {
+            return;
+        }
+        let side = match trade.aggressor {
+            Side::Bid => Side::Ask,
+            Side::Ask => Side::Bid,
+        };
+        self.size_prior.observe(trade.qty);
+        let now = self.now();
+        match self.traded.iter_mut().find(|t| t.side == side && t.price == trade.price) {
+            Some(traded) => {
+                traded.qty += trade.qty;
+                traded.time = now;
+            }
+            None => self.traded.push(TradedVolume {
+                side,
+                price: trade.price,
+                qty: trade.qty,
+                time: now,
+            }),
+        }
+    }
+
+    // Full book state (Hyperliquid, Bitstamp): the first one loads like a snapshot, later ones
+    // are diffed against the current levels so the L3 inference sees true per-level deltas.
+    // Levels missing from a state, including those that fell out of a venue's depth window,
+    // are removed.
+    pub fn apply_full_state(&mut self, state: &OrderBookSnapshot) -> UpdateOutcome {
+        if !self.is_synced {
+            return self.apply_snapshot(state);
+        }
+        self.flush_updates();
+        let update = DepthUpdate {
+            event_time: state.event_time,
+            transaction_time: state.event_time,
+            symbol: String::new(),
+            capital_u: state.last_update_id,
+            small_u: state.last_update_id,
+            pu: -1,
+            bids: Self::diff_levels(&self.bids, &state.bids, Side::Bid, self.max_depth),
+            asks: Self::diff_levels(&self.asks, &state.asks, Side::Ask, self.max_depth),
+            recv_time: state.recv_time,
+        };
+        self.process_update(update)
+    }
+
+    // Levels beyond `depth` are left out of the state, so the ones the book trimmed are not
+    // re-added on every message. A level whose reported order count moved is included even if
+    // its size did not.
+    fn diff_levels(
+        current: &BTreeMap<Decimal, OrderQueue>,
+        state: &[PriceLevel],
+        side: Side,
+        depth: Option<usize>,
+    ) -> Vec<PriceLevel> {
+        let mut next: BTreeMap<Decimal, &PriceLevel> = state
+            .iter()
+            .filter(|level| level.qty > Decimal::ZERO)
+            .map(|level| (level.price, level))
+            .collect();
+        if let Some(depth) = depth {
+            next = match side {
+                Side::Bid => next.into_iter().rev().take(depth).collect(),
+                Side::Ask => next.into_iter().take(depth).collect(),
+            };
+        }
+        let mut levels: Vec<PriceLevel> = current
+            .keys()
+            .filter(|price| !next.contains_key(price))
+            .map(|&price| PriceLevel::new(price, Decimal::ZERO))
+            .collect();
+        for (price, &level) in &next {
+            let unchanged = current.get(price).is_some_and(|queue| {
+                queue.total() == level.qty && level.order_count.is_none_or(|count| queue.reported == Some(count))
+            });
+            if !unchanged {
+                levels.push(*level);
+            }
+        }
+        levels
+    }
+
+    fn process_update(&mut self, update: DepthUpdate) -> UpdateOutcome {
+        if update.small_u < self.last_applied_u {
+            self.counters.skipped += 1;
+            return UpdateOutcome::Skipped;
+        }
+        let started = Instant::now();
+        self.apply_update(&update);
+        self.last_applied_u = update.small_u;
+        self.counters.applied += 1;
+        self.counters.apply_time += started.elapsed();
+        UpdateOutcome::Applied
+    }
+
+    pub fn apply_update(&mut self, update: &DepthUpdate) {
+        self.fit_scale(update.bids.iter().chain(&update.asks));
+        let now = self.now();
+        let mut stamp = OrderStamp {
+            next_id: &mut self.last_order_id,
+            now,
+            decimals: self.qty_decimals,
+            events: self.events.as_mut(),
+            estimator: self.estimator,
+            prior: &mut self.size_prior,
+        };
+        self.traded.retain(|t| now.saturating_sub(t.time) <= TRADE_ATTRIBUTION_MS);
+        for level in &update.bids {
+            Self::apply_level(&mut self.bids, Side::Bid, level, &mut self.traded, &mut stamp);
+            if let Some(bucket) = self.grouping {
+                Self::refresh_group(&self.bids, &mut self.grouped_bids, bucket, level.price, Side::Bid);
+            }
+        }
+        for level in &update.asks {
+            Self::apply_level(&mut self.asks, Side::Ask, level, &mut self.traded, &mut stamp);
+            if let Some(bucket) = self.grouping {
+                Self::refresh_group(&self.asks, &mut self.grouped_asks, bucket, level.price, Side::Ask);
+            }
+        }
+        if let Some((side, pruned)) = Self::uncross(&mut self.bids, &mut self.asks, update, &mut stamp) {
+            self.crossings += 1;
+            tracing::debug!(?side, levels = pruned.len(), crossings = self.crossings, "crossed book, pruned the stale side");
+            if let Some(bucket) = self.grouping {
+                let (raw, grouped) = match side {
+                    Side::Bid => (&self.bids, &mut self.grouped_bids),
+                    Side::Ask => (&self.asks, &mut self.grouped_asks),
+                };
+                for price in pruned {
+                    Self::refresh_group(raw, grouped, bucket, price, side);
+                }
+            }
+        }
+        let decompose = !self.prior_applied && self.estimator.decomposition == Decomposition::PowerLaw;
+        if decompose && self.size_prior.is_ready() {
+            self.decompose_snapshot_levels();
+        }
+        self.trim_depth();
+        self.refresh_metrics();
+    }
+
+    // Splits the snapshot levels still held as one order once the prior has seen enough sizes: the
+    // order keeps the first child and the rest queue behind it
+    fn decompose_snapshot_levels(&mut self) {
+        self.prior_applied = true;
+        tracing::debug!(alpha = ?self.size_prior.alpha(), "size prior ready, decomposing snapshot levels");
+        let now = self.now();
+        let mut stamp = OrderStamp {
+            next_id: &mut self.last_order_id,
+            now,
+            decimals: self.qty_decimals,
+            events: self.events.as_mut(),
+            estimator: self.estimator,
+            prior: &mut self.size_prior,
+        };
+        for (levels, side) in [(&mut self.bids, Side::Bid), (&mut self.asks, Side::Ask)] {
+            for (&price, queue) in levels.iter_mut() {
+                if queue.len() != 1 || queue.reported.is_some() || !queue.orders[0].from_snapshot {
+                    continue;
+                }
+                let Some(children) = stamp.decomposed(queue.orders[0].lots) else {
+                    continue;
+                };
+                let order = &mut queue.orders[0];
+                let before = order.lots;
+                order.lots = children[0];
+                order.inference = Inference::Prior;
+                let (id, parent) = (order.id, *order);
+                stamp.record(side, price, id, InferredAction::Reduced, before, children[0]);
+                for &lots in &children[1..] {
+                    let child = EstimatedOrder {
+                        lots,
+                        ..stamp.order_like(&parent)
+                    };
+                    stamp.record(side, price, child.id, InferredAction::Joined, 0, lots);
+                    queue.orders.push_back(child);
+                }
+            }
+        }
+        self.rebuild_groups();
+    }
+
+    // A missed removal can leave a bid at or above the best ask. The side whose crossing levels
+    // `update` did not move is taken as stale and its crossing levels are dropped; returns that
+    // side and the prices dropped.
+    fn uncross(
+        bids: &mut BTreeMap<Decimal, OrderQueue>,
+        asks: &mut BTreeMap<Decimal, OrderQueue>,
+        update: &DepthUpdate,
+        stamp: &mut OrderStamp,
+    ) -> Option<(Side, Vec<Decimal>)> {
+        let (&bid, &ask) = (bids.keys().next_back()?, asks.keys().next()?);
+        if bid < ask {
+            return None;
+        }
+        let bid_moved = update.bids.iter().any(|level| level.price >= ask && level.qty > Decimal::ZERO);
+        let (side, stale) = if bid_moved { (Side::Ask, asks) } else { (Side::Bid, bids) };
+        // Either way the crossing levels are the ones between the best ask and the best bid
+        let pruned: Vec<Decimal> = stale.range(ask..=bid).map(|(&price, _)| price).collect();
+        for &price in &pruned {
+            for order in stale.remove(&price).iter().flatten() {
+                stamp.record(side, price, order.id, InferredAction::Removed, order.lots, 0);
+            }
+        }
+        Some((side, pruned))
+    }
+
+    // Drops the levels beyond `max_depth` from the far end of each side
+    fn trim_depth(&mut self) {
+        let Some(depth) = self.max_depth else {
+            return;
+        };
+        while self.bids.len() > depth {
+            let Some((price, _)) = self.bids.pop_first() else {
+                break;
+            };
+            if let Some(bucket) = self.grouping {
+                Self::refresh_group(&self.bids, &mut self.grouped_bids, bucket, price, Side::Bid);
+            }
+        }
+        while self.asks.len() > depth {
+            let Some((price, _)) = self.asks.pop_last() else {
+                break;
+            };
+            if let Some(bucket) = self.grouping {
+                Self::refresh_group(&self.asks, &mut self.grouped_asks, bucket, price, Side::Ask);
+            }
+        }
+    }
+
+    // Switches every order to a finer quantity scale when one of `levels` has digits the current
+    // one cannot hold
+    fn fit_scale<'a>(&mut self, levels: impl Iterator<Item = &'a PriceLevel>) {
+        let mut needed = self.qty_decimals;
+        for level in levels {
+            // The scale alone is enough unless trailing zeros pad it
+            if level.qty.scale() > needed {
+                needed = needed.max(fixed::decimals_of(level.qty));
+            }
+        }
+        if needed > self.qty_decimals {
+            self.rescale(needed.min(fixed::MAX_DECIMALS));
+        }
+    }
+
+    // Moves every order to `decimals` if that is finer than the current scale. Stays put if an
+    // order would no longer fit; quantities the scale cannot hold are then ignored.
+    fn rescale(&mut self, decimals: u32) {
+        let from = self.qty_decimals;
+        if decimals <= from {
+            return;
+        }
+        let fits = [&self.bids, &self.asks, &self.grouped_bids, &self.grouped_asks]
+            .iter()
+            .flat_map(|levels| levels.values())
+            .flatten()
+            .all(|order| fixed::rescale(order.lots, from, decimals).is_some());
+        if !fits {
+            return;
+        }
+        for levels in [&mut self.bids, &mut self.asks, &mut self.grouped_bids, &mut self.grouped_asks] {
+            levels.values_mut().for_each(|queue| queue.rescale(from, decimals));
+        }
+        self.qty_decimals = decimals;
+    }
+
+    // Bucket key for `price`: bids round down and asks round up, so grouping never makes
+    // the book look crossed. Returns the key and the raw price range it covers.
+    fn group_range(bucket: Decimal, price: Decimal, side: Side) -> (Decimal, Decimal, Decimal) {
+        match side {
+            Side::Bid => {
+                let key = fixed::round_to_bucket(price, bucket, false)
+                    .unwrap_or_else(|| (price / bucket).floor() * bucket);
+                (key, key, key + bucket)
+            }
+            Side::Ask => {
+                let key =
+                    fixed::round_to_bucket(price, bucket, true).unwrap_or_else(|| (price / bucket).ceil() * bucket);
+                (key, key - bucket, key)
+            }
+        }
+    }
+
+    fn refresh_group(
+        raw: &BTreeMap<Decimal, OrderQueue>,
+        grouped: &mut BTreeMap<Decimal, OrderQueue>,
+        bucket: Decimal,
+        price: Decimal,
+        side: Side,
+    ) {
+        let (key, lo, hi) = Self::group_range(bucket, price, side);
+        // Bids cover [lo, hi), asks (lo, hi]
+        let levels: Box<dyn Iterator<Item = &OrderQueue>> = match side {
+            Side::Bid => Box::new(raw.range(lo..hi).map(|(_, q)| q)),
+            Side::Ask => Box::new(
+                raw.range(lo..=hi)
+                    .filter(move |(&p, _)| p > lo)
+                    .map(|(_, q)| q),
+            ),
+        };
+        let mut queue = OrderQueue::default();
+        levels.for_each(|level| queue.absorb(level));
+        if queue.is_empty() {
+            grouped.remove(&key);
+        } else {
+            grouped.insert(key, queue);
+        }
+    }
+
+    fn rebuild_groups(&mut self) {
+        self.grouped_bids.clear();
+        self.grouped_asks.clear();
+        let Some(bucket) = self.grouping else {
+            return;
+        };
+        for (&price, queue) in &self.bids {
+            let (key, _, _) = Self::group_range(bucket, price, Side::Bid);
+            self.grouped_bids.entry(key).or_default().absorb(queue);
+        }
+        for (&price, queue) in &self.asks {
+            let (key, _, _) = Self::group_range(bucket, price, Side::Ask);
+            self.grouped_asks.entry(key).or_default().absorb(queue);
+        }
+    }
+
+    fn refresh_metrics(&mut self) {
+        self.metrics = self.compute_metrics();
+    }
+
+    fn compute_metrics(&self) -> Option<BookMetrics> {
+        let mut metrics = self.fixed_metrics().or_else(|| self.decimal_metrics())?;
+        metrics.liquidity = self.liquidity_bands(metrics.mid);
+        metrics.buy_vwap = Self::notional_vwap(self.asks.iter(), self.vwap_notional);
+        metrics.sell_vwap = Self::notional_vwap(self.bids.iter().rev(), self.vwap_notional);
+        Some(metrics)
+    }
+
+    // Sums each side outwards from the touch until it leaves the widest band
+    fn liquidity_bands(&self, mid: Decimal) -> [LiquidityBand; LIQUIDITY_BANDS_BPS.len()] {
+        let mut bands = LIQUIDITY_BANDS_BPS.map(|bps| LiquidityBand {
+            bps,
+            ..LiquidityBand::default()
+        });
+        let offsets = LIQUIDITY_BANDS_BPS.map(|bps| mid * Decimal::from(bps) / Decimal::from(10_000));
+        let widest = offsets[offsets.len() - 1];
+        for (&price, queue) in self.bids.range(mid - widest..).rev() {
+            let size = queue.total();
+            for (band, offset) in bands.iter_mut().zip(offsets) {
+                if price >= mid - offset {
+                    band.bid += size;
+                }
+            }
+        }
+        for (&price, queue) in self.asks.range(..=mid + widest) {
+            let size = queue.total();
+            for (band, offset) in bands.iter_mut().zip(offsets) {
+                if price <= mid + offset {
+                    band.ask += size;
+                }
+            }
+        }
+        bands
+    }
+
+    // Size-weighted average price of a market order worth `notional` walking `levels` from the
+    // touch; None if they hold less than that
+    fn notional_vwap<'a>(
+        levels: impl Iterator<Item = (&'a Decimal, &'a OrderQueue)>,
+        notional: Decimal,
+    ) -> Option<Decimal> {
+        if notional <= Decimal::ZERO {
+            return None;
+        }
+        let (mut remaining, mut size) = (notional, Decimal::ZERO);
+        for (&price, queue) in levels.filter(|(&price, _)| price > Decimal::ZERO) {
+            let qty = queue.total();
+            let level_notional = price * qty;
+            if level_notional >= remaining {
+                size += remaining / price;
+                return Some(notional / size);
+            }
+            remaining -= level_notional;
+            size += qty;
+        }
+        None
+    }
+
+    // Metrics over integer price ticks and quantity lots. None for an empty side, or if a value
+    // overflows, in which case `decimal_metrics` computes them instead.
+    fn fixed_metrics(&self) -> Option<BookMetrics> {
+        let bids = || self.bids.iter().rev().take(self.metrics_depth);
+        let asks = || self.asks.iter().take(self.metrics_depth);
+        let ps = bids().chain(asks()).map(|(price, _)| price.scale()).max()?;
+        let qs = self.qty_decimals;
+        let level = |(&price, queue): (&Decimal, &OrderQueue)| {
+            Some((i128::from(fixed::to_lots(price, ps)?), queue.total_lots()))
+        };
+        let (best_bid, best_bid_qty) = level(self.bids.iter().next_back()?)?;
+        let (best_ask, best_ask_qty) = level(self.asks.iter().next()?)?;
+
+        // Half the sum is exact one decimal further
+        let mid = fixed::to_decimal((best_bid + best_ask) * 5, ps + 1)?;
+        let top_qty = best_bid_qty + best_ask_qty;
+        let microprice = if top_qty > 0 {
+            let weighted = best_bid.checked_mul(best_ask_qty)?.checked_add(best_ask.checked_mul(best_bid_qty)?)?;
+            fixed::to_decimal(fixed::ratio(weighted, ps + qs, top_qty, qs)?, fixed::RATIO_DECIMALS)?
+        } else {
+            mid
+        };
+
+        // (size, notional) over the top levels of a side, in lots and ticks x lots
+        let totals = |levels: &mut dyn Iterator<Item = (&Decimal, &OrderQueue)>| {
+            let (mut size, mut notional) = (0i128, 0i128);
+            for entry in levels {
+                let (price, qty) = level(entry)?;
+                size += qty;
+                notional = notional.checked_add(price.checked_mul(qty)?)?;
+            }
+            Some((size, notional))
+        };
+        let (bid_size, bid_notional) = totals(&mut bids())?;
+        let (ask_size, ask_notional) = totals(&mut asks())?;
+
+        let total_size = bid_size + ask_size;
+        let imbalance = if total_size > 0 {
+            fixed::to_decimal(fixed::ratio(bid_size - ask_size, 0, total_size, 0)?, fixed::RATIO_DECIMALS)?
+        } else {
+            Decimal::ZERO
+        };
+        let weighted_mid = if bid_size > 0 && ask_size > 0 {
+            let bid_avg = fixed::ratio(bid_notional, ps + qs, bid_size, qs)?;
+            let ask_avg = fixed::ratio(ask_notional, ps + qs, ask_size, qs)?;
+            fixed::to_decimal((bid_avg + ask_avg) * 5, fixed::RATIO_DECIMALS + 1)?
+        } else {
+            mid
+        };
+
+        Some(BookMetrics {
+            mid,
+            imbalance,
+            microprice,
+            weighted_mid,
+            ..BookMetrics::default()
+        })
+    }
+
+    fn decimal_metrics(&self) -> Option<BookMetrics> {
+        let level = |(&price, queue): (&Decimal, &OrderQueue)| (price, queue.total());
+        let (best_bid, best_bid_qty) = self.bids.iter().next_back().map(level)?;
+        let (best_ask, best_ask_qty) = self.asks.iter().next().map(level)?;
+
+        let mid = (best_bid + best_ask) / Decimal::TWO;
+        let top_qty = best_bid_qty + best_ask_qty;
+        let microprice = if top_qty > Decimal::ZERO {
+            (best_bid * best_ask_qty + best_ask * best_bid_qty) / top_qty
+        } else {
+            mid
+        };
+
+        // (size, notional) over the top levels of a side
+        let totals = |levels: &mut dyn Iterator<Item = (Decimal, Decimal)>| {
+            levels.fold((Decimal::ZERO, Decimal::ZERO), |(size, notional), (price, qty)| {
+                (size + qty, notional + price * qty)
+            })
+        };
+        let (bid_size, bid_notional) = totals(&mut self.bids.iter().rev().take(self.metrics_depth).map(level));
+        let (ask_size, ask_notional) = totals(&mut self.asks.iter().take(self.metrics_depth).map(level));
+
+        let total_size = bid_size + ask_size;
+        let imbalance = if total_size > Decimal::ZERO {
+            (bid_size - ask_size) / total_size
+        } else {
+            Decimal::ZERO
+        };
+        let weighted_mid = if bid_size > Decimal::ZERO && ask_size > Decimal::ZERO {
+            (bid_notional / bid_size + ask_notional / ask_size) / Decimal::TWO
+        } else {
+            mid
+        };
+
+        Some(BookMetrics {
+            mid,
+            imbalance,
+            microprice,
+            weighted_mid,
+            ..BookMetrics::default()
+        })
+    }
+
+    // Infers the order-level change behind a new absolute quantity at `price`:
+    // - qty 0 removes the level
+    // - an increase is a new order joining the back of the queue
+    // - a decrease first fills the front of the queue with volume traded at the level, then
+    //   removes the last order matching the rest exactly, or else shrinks the largest order
+    //   by it (partial cancel/fill)
+    //
+    // A level carrying the venue's order count then has its queue split or merged to that many
+    // orders (see `match_count`).
+    fn apply_level(
+        levels: &mut BTreeMap<Decimal, OrderQueue>,
+        side: Side,
+        level: &PriceLevel,
+        traded: &mut Vec<TradedVolume>,
+        stamp: &mut OrderStamp,
+    ) {
+        Self::apply_level_size(levels, side, level, traded, stamp);
+        if let Some(count) = level.order_count {
+            if let Some(queue) = levels.get_mut(&level.price) {
+                Self::match_count(queue, side, level.price, count, stamp);
+            }
+        }
+    }
+
+    fn apply_level_size(
+        levels: &mut BTreeMap<Decimal, OrderQueue>,
+        side: Side,
+        level: &PriceLevel,
+        traded: &mut Vec<TradedVolume>,
+        stamp: &mut OrderStamp,
+    ) {
+        let (price, qty) = (level.price, level.qty);
+        if qty == Decimal::ZERO {
+            traded.retain(|t| t.side != side || t.price != price);
+            if let Some(queue) = levels.remove(&price) {
+                for order in &queue {
+                    stamp.record(side, price, order.id, InferredAction::Removed, order.lots, 0);
+                }
+            }
+            return;
+        }
+        // Negative quantities, and quantities too large for the lot scale, are ignored
+        let Some(qty) = fixed::to_lots(qty, stamp.decimals).filter(|&lots| lots > 0) else {
+            return;
+        };
+
+        let Some(queue) = levels.get_mut(&price) else {
+            let mut queue = OrderQueue::default();
+            stamp.join(&mut queue, side, price, qty);
+            levels.insert(price, queue);
+            return;
+        };
+
+        let old_sum = queue.total_lots();
+        if old_sum > i128::from(qty) {
+            let mut change = old_sum - i128::from(qty);
+            if let Some(i) = traded.iter().position(|t| t.side == side && t.price == price) {
+                let lots = fixed::to_lots(traded[i].qty, stamp.decimals).map_or(0, i128::from).min(change);
+                Self::fill_front(queue, side, price, lots, stamp);
+                traded[i].qty -= fixed::from_lots(lots, stamp.decimals);
+                if lots == 0 || traded[i].qty <= Decimal::ZERO {
+                    traded.swap_remove(i);
+                }
+                change -= lots;
+                if change == 0 {
+                    return;
+                }
+            }
+            if let Some(pos) = queue.orders.iter().rposition(|order| i128::from(order.lots) == change) {
+                // Removes the last occurrence of the value
+                if let Some(order) = queue.remove(pos) {
+                    stamp.record(side, price, order.id, InferredAction::Removed, order.lots, 0);
+                }
+                return;
+            }
+            // A decrease larger than any one order takes out the largest orders whole, then
+            // shrinks the next largest by what is left
+            while let Some(largest_pos) = queue
+                .orders
+                .iter()
+                .enumerate()
+                .max_by(|(ia, a), (ib, b)| a.lots.cmp(&b.lots).then(ib.cmp(ia)))
+                .map(|(i, _)| i)
+            {
+                let Some(mut largest_order) = queue.remove(largest_pos) else {
+                    break;
+                };
+                if i128::from(largest_order.lots) <= change {
+                    stamp.record(side, price, largest_order.id, InferredAction::Removed, largest_order.lots, 0);
+                    change -= i128::from(largest_order.lots);
+                    if change == 0 {
+                        break;
+                    }
+                    continue;
+                }
+                // Less than the order's lots, so it fits
+                let lots = largest_order.lots - change as i64;
+                stamp.record(side, price, largest_order.id, InferredAction::Reduced, largest_order.lots, lots);
+                largest_order.lots = lots;
+                largest_order.inference = largest_order.inference.max(Inference::Fallback);
+                largest_order.priority = stamp.next();
+                largest_order.queued_at = stamp.now;
+                queue.push_back(largest_order);
+                break;
+            }
+        } else if old_sum < i128::from(qty) {
+            // Between 0 and `qty`, so it fits
+            let lots = (i128::from(qty) - old_sum) as i64;
+            stamp.join(queue, side, price, lots);
+        }
+    }
+
+    // Brings the queue to the venue's `count` of orders without changing its total, spread
+    // evenly under `CountSplit::MaxEntropy` or else by halving: while too short the largest
+    // order (the oldest on ties) is split in half, the new half queued right behind it; while
+    // too long the smallest order and its older neighbour are merged into one order in the
+    // neighbour's place. A level whose lots cannot be split that far keeps fewer.
+    fn match_count(queue: &mut OrderQueue, side: Side, price: Decimal, count: u32, stamp: &mut OrderStamp) {
+        queue.reported = Some(count);
+        let target = (count as usize).max(1);
+        let max_entropy = stamp.estimator.count_split == CountSplit::MaxEntropy;
+        if queue.len() == target || max_entropy && Self::spread_evenly(queue, side, price, target, stamp) {
+            return;
+        }
+        while queue.len() < target {
+            let Some(pos) = queue
+                .orders
+                .iter()
+                .enumerate()
+                .filter(|(_, order)| order.lots >= 2)
+                .max_by(|(ia, a), (ib, b)| a.lots.cmp(&b.lots).then(ib.cmp(ia)))
+                .map(|(i, _)| i)
+            else {
+                break;
+            };
+            let order = &mut queue.orders[pos];
+            let half = order.lots / 2;
+            let before = order.lots;
+            order.lots -= half;
+            order.inference = order.inference.max(Inference::Fallback);
+            let (id, after, parent) = (order.id, order.lots, *order);
+            queue.lots -= i128::from(half);
+            stamp.record(side, price, id, InferredAction::Reduced, before, after);
+            let split = EstimatedOrder {
+                lots: half,
+                ..stamp.order_like(&parent)
+            };
+            stamp.record(side, price, split.id, InferredAction::Joined, 0, half);
+            queue.insert(pos + 1, split);
+        }
+        while queue.len() > target {
+            let Some(smallest) = queue
+                .orders
+                .iter()
+                .enumerate()
+                .min_by(|(ia, a), (ib, b)| a.lots.cmp(&b.lots).then(ib.cmp(ia)))
+                .map(|(i, _)| i)
+            else {
+                break;
+            };
+            let older = if smallest > 0 { smallest - 1 } else { 1 };
+            let (first, second) = (smallest.min(older), smallest.max(older));
+            let (Some(later), Some(earlier)) = (queue.remove(second), queue.remove(first)) else {
+                break;
+            };
+            for order in [&earlier, &later] {
+                stamp.record(side, price, order.id, InferredAction::Removed, order.lots, 0);
+            }
+            let merged = EstimatedOrder {
+                lots: earlier.lots + later.lots,
+                inference: earlier.inference.max(later.inference).max(Inference::Fallback),
+                ..stamp.order_like(&earlier)
+            };
+            stamp.record(side, price, merged.id, InferredAction::Joined, 0, merged.lots);
+            queue.insert(first, merged);
+        }
+    }
+
+    // Replaces the orders sized by fallback or snapshot with `target` minus the rest of them,
+    // splitting their lots as evenly as possible, in the place of the first one. Returns false
+    // and leaves the queue alone when there is nothing to spread or too little of it.
+    fn spread_evenly(
+        queue: &mut OrderQueue,
+        side: Side,
+        price: Decimal,
+        target: usize,
+        stamp: &mut OrderStamp,
+    ) -> bool {
+        let uncertain: Vec<usize> = (0..queue.len())
+            .filter(|&i| queue.orders[i].inference >= Inference::Fallback)
+            .collect();
+        let Some(&first) = uncertain.first() else {
+            return false;
+        };
+        let slots = match target.checked_sub(queue.len() - uncertain.len()) {
+            Some(slots) if slots > 0 => slots as i64,
+            _ => return false,
+        };
+        let mass: i64 = uncertain.iter().map(|&i| queue.orders[i].lots).sum();
+        if mass < slots {
+            return false;
+        }
+        let template = queue.orders[first];
+        for &i in uncertain.iter().rev() {
+            if let Some(order) = queue.remove(i) {
+                stamp.record(side, price, order.id, InferredAction::Removed, order.lots, 0);
+            }
+        }
+        for k in 0..slots {
+            let order = EstimatedOrder {
+                lots: mass / slots + i64::from(k < mass % slots),
+                ..stamp.order_like(&template)
+            };
+            stamp.record(side, price, order.id, InferredAction::Joined, 0, order.lots);
+            queue.insert(first + k as usize, order);
+        }
+        true
+    }
+
+    // Takes `lots` (at most the queue's total) out of the front of the queue, oldest first, as
+    // trades fill resting orders; a partially filled order keeps its place
+    fn fill_front(queue: &mut OrderQueue, side: Side, price: Decimal, lots: i128, stamp: &mut OrderStamp) {
+        let mut left = lots;
+        while left > 0 {
+            let Some(front) = queue.orders.front_mut() else {
+                break;
+            };
+            if i128::from(front.lots) > left {
+                // Less than the order's lots, so it fits
+                let after = front.lots - left as i64;
+                stamp.record(side, price, front.id, InferredAction::Filled, front.lots, after);
+                front.lots = after;
+                front.inference = front.inference.max(Inference::TradeAttributed);
+                queue.lots -= left;
+                break;
+            }
+            left -= i128::from(front.lots);
+            if let Some(order) = queue.remove(0) {
+                stamp.record(side, price, order.id, InferredAction::Filled, order.lots, 0);
+            }
+        }
+    }
+}
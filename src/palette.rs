@@ -0,0 +1,127 @@
+// Chart colors: bid and ask bars, the gradients cluster colors are drawn from, the highlights
+// for the largest orders and the panel background. Kept free of egui so `[display.palette]` in
+// the config file parses in every build; the GUI converts the colors when it draws.
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// Most stops a cluster gradient may have
+pub const MAX_GRADIENT_STOPS: usize = 10;
+
+// An sRGB color, written "#rrggbb" in the config file and the saved session
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Rgb(pub [u8; 3]);
+
+impl Rgb {
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self([r, g, b])
+    }
+}
+
+impl fmt::Display for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [r, g, b] = self.0;
+        write!(f, "#{r:02x}{g:02x}{b:02x}")
+    }
+}
+
+impl TryFrom<String> for Rgb {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        let hex = text.strip_prefix('#').unwrap_or(&text);
+        let channel = |i: usize| hex.get(i..i + 2).and_then(|c| u8::from_str_radix(c, 16).ok());
+        match (hex.len(), channel(0), channel(2), channel(4)) {
+            (6, Some(r), Some(g), Some(b)) => Ok(Self([r, g, b])),
+            _ => Err(format!("invalid color {text:?}, expected \"#rrggbb\"")),
+        }
+    }
+}
+
+impl From<Rgb> for String {
+    fn from(color: Rgb) -> Self {
+        color.to_string()
+    }
+}
+
+// Starting points for the palette; any color can then be overridden
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PalettePreset {
+    // Green bids, red asks, gold highlights
+    #[default]
+    Classic,
+    // Okabe-Ito colors that stay apart under the common forms of color blindness: blue bids,
+    // vermillion asks, yellow and reddish purple highlights
+    Colorblind,
+}
+
+impl PalettePreset {
+    pub const ALL: [PalettePreset; 2] = [PalettePreset::Classic, PalettePreset::Colorblind];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PalettePreset::Classic => "classic",
+            PalettePreset::Colorblind => "colorblind",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|preset| preset.name() == name)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Palette {
+    // Base color of the order bars; later orders in a queue are drawn brighter
+    pub bid: Rgb,
+    pub ask: Rgb,
+    // Gradient stops cluster colors are interpolated along, lightest first
+    pub bid_gradient: Vec<Rgb>,
+    pub ask_gradient: Vec<Rgb>,
+    // The largest and second largest order on each side
+    pub largest: Rgb,
+    pub second_largest: Rgb,
+    // Panel background; None keeps the theme's own
+    pub background: Option<Rgb>,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::preset(PalettePreset::Classic)
+    }
+}
+
+impl Palette {
+    pub fn preset(preset: PalettePreset) -> Self {
+        match preset {
+            PalettePreset::Classic => Self {
+                bid: Rgb::new(0, 100, 0),
+                ask: Rgb::new(139, 0, 0),
+                bid_gradient: vec![Rgb::new(222, 235, 247), Rgb::new(107, 174, 214), Rgb::new(8, 81, 156)],
+                ask_gradient: vec![Rgb::new(254, 230, 206), Rgb::new(253, 141, 60), Rgb::new(166, 54, 3)],
+                largest: Rgb::new(255, 215, 0),
+                second_largest: Rgb::new(184, 134, 11),
+                background: None,
+            },
+            PalettePreset::Colorblind => Self {
+                bid: Rgb::new(0, 90, 150),
+                ask: Rgb::new(180, 80, 0),
+                bid_gradient: vec![Rgb::new(198, 219, 239), Rgb::new(86, 180, 233), Rgb::new(0, 114, 178)],
+                ask_gradient: vec![Rgb::new(254, 227, 145), Rgb::new(230, 159, 0), Rgb::new(213, 94, 0)],
+                largest: Rgb::new(240, 228, 66),
+                second_largest: Rgb::new(204, 121, 167),
+                background: None,
+            },
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, stops) in [("bid_gradient", &self.bid_gradient), ("ask_gradient", &self.ask_gradient)] {
+            if stops.is_empty() || stops.len() > MAX_GRADIENT_STOPS {
+                return Err(format!("{name} needs 1 to {MAX_GRADIENT_STOPS} colors, got {}", stops.len()));
+            }
+        }
+        Ok(())
+    }
+}
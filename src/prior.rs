@@ -0,0 +1,138 @@
+// Order size prior: an online power-law (Pareto) fit of individual order sizes, from the level
+// increases the estimator sees whole and from trade prints. It decomposes quantities too large to
+// be one plausible order into children spread over the fitted distribution.
+//
+// Before the first updates there is nothing to fit, so the initial snapshot bootstraps the fit
+// from its own levels.
+use crate::exchanges::PriceLevel;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+// Samples before the fit is used
+pub const MIN_PRIOR_SAMPLES: u64 = 50;
+// Most children one quantity is decomposed into; the last one takes whatever is left
+pub const MAX_CHILDREN: usize = 64;
+// Most samples one level with a reported order count adds when bootstrapping
+const MAX_BOOTSTRAP_PER_LEVEL: u32 = 100;
+// Effective samples the fit remembers; older ones fade so it follows the market
+const PRIOR_MEMORY: f64 = 10_000.0;
+// Quantile of the fit above which a quantity is taken to be several orders
+const SINGLE_ORDER_QUANTILE: f64 = 0.99;
+// Quantile step between children: the golden ratio covers (0, 1) evenly without randomness, so a
+// quantity always decomposes the same way under the same fit
+const QUANTILE_STEP: f64 = 0.618_033_988_749_895;
+
+// p(x) ∝ x^-alpha for sizes x >= x_min, alpha by maximum likelihood over the samples
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SizePrior {
+    samples: u64,
+    // Faded sample count and sum of ln(size)
+    weight: f64,
+    log_sum: f64,
+    min: f64,
+}
+
+impl SizePrior {
+    pub fn observe(&mut self, size: Decimal) {
+        self.observe_n(size, 1);
+    }
+
+    // `n` orders of the same size
+    fn observe_n(&mut self, size: Decimal, n: u32) {
+        let Some(size) = size.to_f64().filter(|size| *size > 0.0 && size.is_finite()) else {
+            return;
+        };
+        if n == 0 {
+            return;
+        }
+        if self.weight >= PRIOR_MEMORY {
+            let keep = (1.0 - 1.0 / PRIOR_MEMORY).powi(n as i32);
+            self.weight *= keep;
+            self.log_sum *= keep;
+        }
+        self.weight += f64::from(n);
+        self.log_sum += f64::from(n) * size.ln();
+        self.min = if self.samples == 0 { size } else { self.min.min(size) };
+        self.samples += u64::from(n);
+    }
+
+    // Seeds the fit from snapshot levels while it is not ready. A level with a reported order
+    // count holds that many orders of its mean size; of the others, the smaller half are taken
+    // to be single orders, since a level built from several orders is rarely among the smallest.
+    pub fn bootstrap<'a>(&mut self, levels: impl IntoIterator<Item = &'a PriceLevel>) {
+        if self.is_ready() {
+            return;
+        }
+        let mut uncounted = Vec::new();
+        for level in levels {
+            if level.qty <= Decimal::ZERO {
+                continue;
+            }
+            match level.order_count.filter(|&count| count > 0) {
+                Some(count) => {
+                    self.observe_n(level.qty / Decimal::from(count), count.min(MAX_BOOTSTRAP_PER_LEVEL))
+                }
+                None => uncounted.push(level.qty),
+            }
+        }
+        uncounted.sort_unstable();
+        let smaller_half = uncounted.len().div_ceil(2);
+        for &size in &uncounted[..smaller_half] {
+            self.observe(size);
+        }
+    }
+
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.samples >= MIN_PRIOR_SAMPLES && self.alpha().is_some()
+    }
+
+    // Smallest size seen, the fit's x_min
+    pub fn min_size(&self) -> Option<f64> {
+        (self.samples > 0).then_some(self.min)
+    }
+
+    // None until the samples differ in size
+    pub fn alpha(&self) -> Option<f64> {
+        let spread = self.log_sum - self.weight * self.min_size()?.ln();
+        (spread > 1e-9).then(|| 1.0 + self.weight / spread)
+    }
+
+    // Size below which a share `q` of orders fall
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        let alpha = self.alpha()?;
+        Some(self.min * (1.0 - q.clamp(0.0, 0.999_999)).powf(-1.0 / (alpha - 1.0)))
+    }
+
+    // Child sizes, in lots of 10^-decimals, for a quantity of `lots` arriving at once; None while
+    // the fit is not ready or when the quantity is a plausible single order
+    pub fn decompose(&self, lots: i64, decimals: u32) -> Option<Vec<i64>> {
+        if !self.is_ready() {
+            return None;
+        }
+        let unit = 10f64.powi(-(decimals as i32));
+        let cap = (self.quantile(SINGLE_ORDER_QUANTILE)? / unit).floor().max(1.0) as i64;
+        if lots <= cap {
+            return None;
+        }
+        let min_lots = (self.min / unit).round().max(1.0) as i64;
+        let mut children = Vec::new();
+        let (mut left, mut q) = (lots, 0.5);
+        while left > 0 {
+            let size = (self.quantile(q)? / unit).round() as i64;
+            let mut child = size.clamp(1, cap).min(left);
+            // A remainder too small to be an order goes to the last child
+            if left - child < min_lots || children.len() + 1 == MAX_CHILDREN {
+                child = left;
+            }
+            children.push(child);
+            left -= child;
+            q = (q + QUANTILE_STEP) % 1.0;
+        }
+        Some(children)
+    }
+}
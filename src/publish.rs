@@ -0,0 +1,356 @@
+// Publishes normalized book events to a message broker so the estimator can feed a larger
+// pipeline: snapshots and diffs as the venue sent them, the estimator's inferred L3 events and
+// trades. Every message is a JSON object with the schema below, whichever venue it came from.
+//
+// NATS subjects are `<prefix>.<type>.<exchange>.<symbol>`, so subscribers can filter with
+// wildcards (`l3.order.>`). Kafka topics are `<prefix>.<type>`, keyed by `<exchange>.<symbol>`
+// so each instrument's events stay ordered within a partition.
+use crate::exchanges::{DepthUpdate, ExchangeType, OrderBookSnapshot, PriceLevel, Side, Trade};
+use crate::orderbook::InferredEvent;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+// Bumped whenever a field changes meaning or goes away; added fields keep the version
+pub const SCHEMA_VERSION: u32 = 1;
+
+pub const DEFAULT_PREFIX: &str = "l3";
+
+// Events buffered for the broker before new ones are dropped
+const QUEUE_CAPACITY: usize = 65_536;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PublishTarget {
+    // A NATS server URL, e.g. nats://127.0.0.1:4222 (`nats` feature)
+    Nats(String),
+    // Comma-separated Kafka bootstrap brokers (`kafka` feature)
+    Kafka(String),
+}
+
+impl PublishTarget {
+    // `nats://host:port` or `kafka://broker1:9092,broker2:9092`
+    pub fn parse(url: &str) -> Result<Self, String> {
+        if url.starts_with("nats://") || url.starts_with("tls://") {
+            Ok(PublishTarget::Nats(url.to_string()))
+        } else if let Some(brokers) = url.strip_prefix("kafka://").filter(|b| !b.is_empty()) {
+            Ok(PublishTarget::Kafka(brokers.to_string()))
+        } else {
+            Err(format!("unsupported publish target {url}, expected nats://host:port or kafka://host:port[,host:port]"))
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PublishOptions {
+    pub target: PublishTarget,
+    // First part of every subject or topic name
+    pub prefix: String,
+}
+
+// One published message
+#[derive(Serialize, Debug)]
+pub struct BookEvent<'a> {
+    pub version: u32,
+    pub exchange: &'static str,
+    pub symbol: &'a str,
+    // Milliseconds since the Unix epoch: the venue's time where it gives one, else arrival
+    pub time: u64,
+    #[serde(flatten)]
+    pub body: EventBody,
+}
+
+// Prices and quantities are decimal strings, as on the venues
+#[derive(Serialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EventBody {
+    // Whole book as the venue sent it, after a (re)connect or for venues that only send states
+    Snapshot {
+        last_update_id: u64,
+        bids: Vec<LevelEvent>,
+        asks: Vec<LevelEvent>,
+    },
+    // New quantity of each changed level; zero removes the level
+    Diff {
+        first_update_id: u64,
+        last_update_id: u64,
+        bids: Vec<LevelEvent>,
+        asks: Vec<LevelEvent>,
+    },
+    // What the estimator inferred a level change did to one order
+    Order {
+        side: &'static str,
+        price: Decimal,
+        order_id: u64,
+        // joined, removed, reduced or filled
+        action: &'static str,
+        size: Decimal,
+        change: Decimal,
+    },
+    Trade {
+        trade_id: u64,
+        // Side of the taker: buy lifted an ask
+        side: &'static str,
+        price: Decimal,
+        quantity: Decimal,
+    },
+}
+
+impl EventBody {
+    // Part of the subject or topic naming the event type
+    pub fn kind(&self) -> &'static str {
+        match self {
+            EventBody::Snapshot { .. } => "snapshot",
+            EventBody::Diff { .. } => "diff",
+            EventBody::Order { .. } => "order",
+            EventBody::Trade { .. } => "trade",
+        }
+    }
+
+    pub fn snapshot(snapshot: &OrderBookSnapshot) -> Self {
+        EventBody::Snapshot {
+            last_update_id: snapshot.last_update_id,
+            bids: levels(&snapshot.bids),
+            asks: levels(&snapshot.asks),
+        }
+    }
+
+    pub fn diff(update: &DepthUpdate) -> Self {
+        EventBody::Diff {
+            first_update_id: update.capital_u,
+            last_update_id: update.small_u,
+            bids: levels(&update.bids),
+            asks: levels(&update.asks),
+        }
+    }
+
+    pub fn order(event: &InferredEvent) -> Self {
+        EventBody::Order {
+            side: side_label(event.side),
+            price: event.price,
+            order_id: event.order_id,
+            action: event.action.label(),
+            size: event.size,
+            change: event.change,
+        }
+    }
+
+    pub fn trade(trade: &Trade) -> Self {
+        EventBody::Trade {
+            trade_id: trade.trade_id,
+            side: match trade.aggressor {
+                Side::Bid => "buy",
+                Side::Ask => "sell",
+            },
+            price: trade.price,
+            quantity: trade.qty,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct LevelEvent {
+    pub price: Decimal,
+    pub quantity: Decimal,
+    // Orders at the level, from venues that report it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub orders: Option<u32>,
+}
+
+fn levels(levels: &[PriceLevel]) -> Vec<LevelEvent> {
+    levels
+        .iter()
+        .map(|level| LevelEvent {
+            price: level.price,
+            quantity: level.qty,
+            orders: level.order_count,
+        })
+        .collect()
+}
+
+fn side_label(side: Side) -> &'static str {
+    match side {
+        Side::Bid => "bid",
+        Side::Ask => "ask",
+    }
+}
+
+// An encoded event and the type it is published under
+#[cfg_attr(not(any(feature = "nats", feature = "kafka")), allow(dead_code))]
+struct Outgoing {
+    kind: &'static str,
+    payload: Vec<u8>,
+}
+
+// Hands events of one feed to a background task that sends them to the broker. Publishing
+// never blocks the estimator: when the broker falls behind, events are dropped and counted.
+pub struct Publisher {
+    exchange: ExchangeType,
+    symbol: String,
+    tx: Option<mpsc::Sender<Outgoing>>,
+    dropped: AtomicU64,
+    task: Option<JoinHandle<()>>,
+    runtime: tokio::runtime::Handle,
+}
+
+impl Publisher {
+    // Connects to the broker; fails if it is unreachable or this build lacks its feature
+    pub fn start(
+        runtime: &tokio::runtime::Handle,
+        options: PublishOptions,
+        exchange: ExchangeType,
+        symbol: &str,
+    ) -> io::Result<Self> {
+        let backend = Backend::connect(runtime, &options.target)?;
+        let names = Names::new(&options.prefix, exchange, symbol);
+        let (tx, rx) = mpsc::channel(QUEUE_CAPACITY);
+        let task = runtime.spawn(backend.run(rx, names));
+        Ok(Self {
+            exchange,
+            symbol: symbol.to_string(),
+            tx: Some(tx),
+            dropped: AtomicU64::new(0),
+            task: Some(task),
+            runtime: runtime.clone(),
+        })
+    }
+
+    pub fn publish(&self, time: u64, body: EventBody) {
+        let event = BookEvent {
+            version: SCHEMA_VERSION,
+            exchange: self.exchange.name(),
+            symbol: &self.symbol,
+            time,
+            body,
+        };
+        let outgoing = Outgoing {
+            kind: event.body.kind(),
+            payload: serde_json::to_vec(&event).expect("book events serialize"),
+        };
+        if let Some(tx) = &self.tx {
+            if tx.try_send(outgoing).is_err() {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    // Events dropped because the broker could not keep up
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    // Sends what is still queued and waits for the broker to acknowledge it
+    pub fn close(mut self) {
+        self.tx = None;
+        if let Some(task) = self.task.take() {
+            let _ = self.runtime.block_on(task);
+        }
+    }
+}
+
+// Subjects and topics of one feed
+#[cfg_attr(not(any(feature = "nats", feature = "kafka")), allow(dead_code))]
+struct Names {
+    prefix: String,
+    // `<exchange>.<symbol>` with dots in either replaced, as NATS uses them as separators
+    instrument: String,
+}
+
+impl Names {
+    fn new(prefix: &str, exchange: ExchangeType, symbol: &str) -> Self {
+        let token = |s: &str| s.to_lowercase().replace(['.', ' ', '*', '>'], "_");
+        Self {
+            prefix: prefix.to_string(),
+            instrument: format!("{}.{}", token(exchange.name()), token(symbol)),
+        }
+    }
+
+    #[cfg(feature = "nats")]
+    fn subject(&self, kind: &str) -> String {
+        format!("{}.{kind}.{}", self.prefix, self.instrument)
+    }
+
+    #[cfg(feature = "kafka")]
+    fn topic(&self, kind: &str) -> String {
+        format!("{}.{kind}", self.prefix)
+    }
+}
+
+enum Backend {
+    #[cfg(feature = "nats")]
+    Nats(async_nats::Client),
+    #[cfg(feature = "kafka")]
+    Kafka(rdkafka::producer::FutureProducer),
+}
+
+impl Backend {
+    #[cfg_attr(not(feature = "nats"), allow(unused_variables))]
+    fn connect(runtime: &tokio::runtime::Handle, target: &PublishTarget) -> io::Result<Self> {
+        match target {
+            #[cfg(feature = "nats")]
+            PublishTarget::Nats(url) => runtime
+                .block_on(async_nats::connect(url.as_str()))
+                .map(Backend::Nats)
+                .map_err(|e| io::Error::new(io::ErrorKind::ConnectionRefused, format!("NATS {url}: {e}"))),
+            #[cfg(not(feature = "nats"))]
+            PublishTarget::Nats(_) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "publishing to NATS needs a build with --features nats",
+            )),
+            #[cfg(feature = "kafka")]
+            PublishTarget::Kafka(brokers) => rdkafka::ClientConfig::new()
+                .set("bootstrap.servers", brokers)
+                .set("linger.ms", "5")
+                .create()
+                .map(Backend::Kafka)
+                .map_err(|e| io::Error::other(format!("Kafka {brokers}: {e}"))),
+            #[cfg(not(feature = "kafka"))]
+            PublishTarget::Kafka(_) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "publishing to Kafka needs a build with --features kafka",
+            )),
+        }
+    }
+
+    #[cfg_attr(not(any(feature = "nats", feature = "kafka")), allow(unused_variables))]
+    async fn run(self, rx: mpsc::Receiver<Outgoing>, names: Names) {
+        match self {
+            #[cfg(feature = "nats")]
+            Backend::Nats(client) => publish_nats(client, rx, names).await,
+            #[cfg(feature = "kafka")]
+            Backend::Kafka(producer) => publish_kafka(producer, rx, names).await,
+        }
+    }
+}
+
+#[cfg(feature = "nats")]
+async fn publish_nats(client: async_nats::Client, mut rx: mpsc::Receiver<Outgoing>, names: Names) {
+    while let Some(outgoing) = rx.recv().await {
+        if let Err(e) = client.publish(names.subject(outgoing.kind), outgoing.payload.into()).await {
+            tracing::warn!("NATS publish failed: {e}");
+        }
+    }
+    if let Err(e) = client.flush().await {
+        tracing::warn!("NATS flush failed: {e}");
+    }
+}
+
+#[cfg(feature = "kafka")]
+async fn publish_kafka(producer: rdkafka::producer::FutureProducer, mut rx: mpsc::Receiver<Outgoing>, names: Names) {
+    use rdkafka::producer::{FutureRecord, Producer};
+    use rdkafka::util::Timeout;
+    while let Some(outgoing) = rx.recv().await {
+        let topic = names.topic(outgoing.kind);
+        let record = FutureRecord::to(&topic).key(&names.instrument).payload(&outgoing.payload);
+        // Delivery is confirmed by the flush on close; only a full local queue fails here
+        if let Err((e, _)) = producer.send_result(record) {
+            tracing::warn!("Kafka publish to {topic} failed: {e}");
+        }
+    }
+    let timeout = Timeout::After(std::time::Duration::from_secs(10));
+    if let Ok(Err(e)) = tokio::task::spawn_blocking(move || producer.flush(timeout)).await {
+        tracing::warn!("Kafka flush failed: {e}");
+    }
+}
@@ -0,0 +1,493 @@
+// Python bindings (`import l3est`): the book engine with its estimator, REST snapshots from the
+// connectors, and a live feed consumed with `async for`. Prices and sizes cross over as
+// `decimal.Decimal`; inputs may also be str, int or float.
+use crate::exchanges::{self, DepthUpdate, ExchangeSettings, ExchangeType, OrderBookSnapshot, PriceLevel, Side};
+use crate::feed::{self, ConnectionStatus, Control, FeedMessage, FeedQueue, FeedWorker};
+use crate::orderbook::{CountSplit, Decomposition, EstimatorSettings, InferredEvent, OrderBook, OrderQueue, UpdateOutcome};
+use pyo3::exceptions::{PyRuntimeError, PyStopAsyncIteration, PyValueError};
+use pyo3::prelude::*;
+use rust_decimal::Decimal;
+use std::sync::Arc;
+use std::time::Duration;
+
+// Messages a feed may queue ahead of Python before it coalesces and then drops them
+const QUEUE_CAPACITY: usize = 10_000;
+
+// How long one wait for feed messages blocks a runtime thread
+const DRAIN_WAIT: Duration = Duration::from_millis(100);
+
+// (price, quantity, estimated order sizes oldest first)
+type PyLevel = (Decimal, Decimal, Vec<Decimal>);
+
+fn exchange_type(name: &str) -> PyResult<ExchangeType> {
+    ExchangeType::from_name(name).ok_or_else(|| PyValueError::new_err(format!("unknown or disabled exchange: {name}")))
+}
+
+fn side_name(side: Side) -> &'static str {
+    match side {
+        Side::Bid => "bid",
+        Side::Ask => "ask",
+    }
+}
+
+fn outcome_name(outcome: UpdateOutcome) -> &'static str {
+    match outcome {
+        UpdateOutcome::Applied => "applied",
+        UpdateOutcome::Buffered => "buffered",
+        UpdateOutcome::Skipped => "skipped",
+    }
+}
+
+fn price_levels(levels: Vec<(Decimal, Decimal)>) -> Vec<PriceLevel> {
+    levels.into_iter().map(|(price, qty)| PriceLevel::new(price, qty)).collect()
+}
+
+fn level((&price, queue): (&Decimal, &OrderQueue)) -> PyLevel {
+    (price, queue.total(), queue.sizes().collect())
+}
+
+// Best first, `depth` levels at most
+fn side_levels(book: &OrderBook, side: Side, depth: Option<usize>) -> Vec<PyLevel> {
+    let depth = depth.unwrap_or(usize::MAX);
+    match side {
+        Side::Bid => book.bids.iter().rev().take(depth).map(level).collect(),
+        Side::Ask => book.asks.iter().take(depth).map(level).collect(),
+    }
+}
+
+fn parse_side(side: &str) -> PyResult<Side> {
+    match side {
+        "bid" | "bids" | "buy" => Ok(Side::Bid),
+        "ask" | "asks" | "sell" => Ok(Side::Ask),
+        _ => Err(PyValueError::new_err(format!("side must be 'bid' or 'ask', not {side:?}"))),
+    }
+}
+
+// One decision of the estimator
+#[pyclass(name = "InferredEvent", module = "l3est", frozen, get_all)]
+#[derive(Clone)]
+pub struct PyInferredEvent {
+    // Milliseconds since the Unix epoch
+    time: u64,
+    side: &'static str,
+    price: Decimal,
+    order_id: u64,
+    // joined, removed, reduced or filled
+    action: &'static str,
+    size: Decimal,
+    change: Decimal,
+}
+
+impl From<InferredEvent> for PyInferredEvent {
+    fn from(event: InferredEvent) -> Self {
+        Self {
+            time: event.time,
+            side: side_name(event.side),
+            price: event.price,
+            order_id: event.order_id,
+            action: event.action.label(),
+            size: event.size,
+            change: event.change,
+        }
+    }
+}
+
+#[pymethods]
+impl PyInferredEvent {
+    fn __repr__(&self) -> String {
+        format!(
+            "InferredEvent(#{} {} {} {} {:+})",
+            self.order_id, self.action, self.side, self.price, self.change
+        )
+    }
+}
+
+// The L3 book engine. Feed it snapshots and depth updates; it keeps the estimated queue of
+// orders at every level.
+#[pyclass(name = "OrderBook", module = "l3est")]
+pub struct PyOrderBook {
+    book: OrderBook,
+}
+
+#[pymethods]
+impl PyOrderBook {
+    // `count_split`: max_entropy or halving; `decomposition`: single or power_law
+    #[new]
+    #[pyo3(signature = (max_depth=None, count_split="max_entropy", decomposition="single", event_log=true))]
+    fn new(max_depth: Option<usize>, count_split: &str, decomposition: &str, event_log: bool) -> PyResult<Self> {
+        let estimator = EstimatorSettings {
+            count_split: CountSplit::from_name(count_split)
+                .ok_or_else(|| PyValueError::new_err(format!("unknown count_split: {count_split}")))?,
+            decomposition: Decomposition::from_name(decomposition)
+                .ok_or_else(|| PyValueError::new_err(format!("unknown decomposition: {decomposition}")))?,
+        };
+        let mut book = OrderBook::new();
+        book.set_max_depth(max_depth);
+        book.set_estimator(estimator);
+        book.set_event_log(event_log);
+        Ok(Self { book })
+    }
+
+    // Levels are (price, quantity) pairs
+    fn apply_snapshot(
+        &mut self,
+        last_update_id: u64,
+        bids: Vec<(Decimal, Decimal)>,
+        asks: Vec<(Decimal, Decimal)>,
+    ) -> &'static str {
+        let snapshot = OrderBookSnapshot {
+            last_update_id,
+            event_time: 0,
+            recv_time: 0,
+            bids: price_levels(bids),
+            asks: price_levels(asks),
+        };
+        outcome_name(self.book.apply_snapshot(&snapshot))
+    }
+
+    // New quantity of each changed level, zero removing it. `prev_update_id` is the previous
+    // update's last id on venues that chain them; a mismatch flags a gap and resyncs the book.
+    #[pyo3(signature = (first_update_id, last_update_id, bids, asks, prev_update_id=None, time=0))]
+    fn apply_update(
+        &mut self,
+        first_update_id: u64,
+        last_update_id: u64,
+        bids: Vec<(Decimal, Decimal)>,
+        asks: Vec<(Decimal, Decimal)>,
+        prev_update_id: Option<u64>,
+        time: u64,
+    ) -> &'static str {
+        let update = DepthUpdate {
+            event_time: time,
+            transaction_time: time,
+            symbol: String::new(),
+            capital_u: first_update_id,
+            small_u: last_update_id,
+            pu: prev_update_id.map_or(-1, |id| id as i64),
+            bids: price_levels(bids),
+            asks: price_levels(asks),
+            recv_time: 0,
+        };
+        outcome_name(self.book.handle_update(update))
+    }
+
+    fn resync(&mut self) {
+        self.book.resync();
+    }
+
+    fn best_bid(&self) -> Option<Decimal> {
+        self.book.best_bid()
+    }
+
+    fn best_ask(&self) -> Option<Decimal> {
+        self.book.best_ask()
+    }
+
+    // [(price, quantity, [order sizes, oldest first])], best first
+    #[pyo3(signature = (depth=None))]
+    fn bids(&self, depth: Option<usize>) -> Vec<PyLevel> {
+        side_levels(&self.book, Side::Bid, depth)
+    }
+
+    #[pyo3(signature = (depth=None))]
+    fn asks(&self, depth: Option<usize>) -> Vec<PyLevel> {
+        side_levels(&self.book, Side::Ask, depth)
+    }
+
+    // Estimated order sizes at one level, oldest first; empty if the level is not in the book
+    fn orders(&self, side: &str, price: Decimal) -> PyResult<Vec<Decimal>> {
+        let levels = match parse_side(side)? {
+            Side::Bid => &self.book.bids,
+            Side::Ask => &self.book.asks,
+        };
+        Ok(levels.get(&price).map(|queue| queue.sizes().collect()).unwrap_or_default())
+    }
+
+    // mid, microprice, weighted_mid, imbalance, buy_vwap and sell_vwap; None until both sides exist
+    fn metrics(&self) -> Option<Metrics> {
+        self.book.metrics().map(|m| Metrics {
+            mid: m.mid,
+            microprice: m.microprice,
+            weighted_mid: m.weighted_mid,
+            imbalance: m.imbalance,
+            buy_vwap: m.buy_vwap,
+            sell_vwap: m.sell_vwap,
+        })
+    }
+
+    // The estimator's decisions since the last call, oldest first
+    fn drain_events(&mut self) -> Vec<PyInferredEvent> {
+        self.book.drain_events().map(PyInferredEvent::from).collect()
+    }
+
+    #[getter]
+    fn last_update_id(&self) -> u64 {
+        self.book.last_update_id()
+    }
+
+    #[getter]
+    fn synced(&self) -> bool {
+        self.book.is_synced()
+    }
+
+    #[getter]
+    fn order_count(&self) -> usize {
+        self.book.order_count()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "OrderBook(levels {}/{}, {} orders, last update {})",
+            self.book.bids.len(),
+            self.book.asks.len(),
+            self.book.order_count(),
+            self.book.last_update_id()
+        )
+    }
+}
+
+#[pyclass(module = "l3est", frozen, get_all)]
+pub struct Metrics {
+    mid: Decimal,
+    microprice: Decimal,
+    weighted_mid: Decimal,
+    imbalance: Decimal,
+    buy_vwap: Option<Decimal>,
+    sell_vwap: Option<Decimal>,
+}
+
+// A REST snapshot as the venue returned it
+#[pyclass(name = "Snapshot", module = "l3est", frozen, get_all)]
+pub struct PySnapshot {
+    last_update_id: u64,
+    bids: Vec<(Decimal, Decimal)>,
+    asks: Vec<(Decimal, Decimal)>,
+}
+
+// The book of a live feed after one or more messages were applied
+#[pyclass(module = "l3est", frozen, get_all)]
+pub struct BookUpdate {
+    exchange: &'static str,
+    symbol: String,
+    // Connecting, Connected, Stale or Disconnected
+    status: &'static str,
+    last_update_id: u64,
+    synced: bool,
+    bids: Vec<PyLevel>,
+    asks: Vec<PyLevel>,
+    mid: Option<Decimal>,
+    microprice: Option<Decimal>,
+    imbalance: Option<Decimal>,
+    // What the estimator inferred from the messages applied since the previous update
+    events: Vec<PyInferredEvent>,
+}
+
+// Names accepted wherever an exchange is expected
+#[pyfunction]
+#[pyo3(name = "exchanges")]
+fn exchange_names() -> Vec<&'static str> {
+    ExchangeType::ALL.iter().map(|e| e.name()).collect()
+}
+
+// Awaitable REST snapshot of `symbol`, in the venue's symbol format
+#[pyfunction]
+#[pyo3(signature = (exchange, symbol, testnet=false))]
+fn fetch_snapshot<'py>(py: Python<'py>, exchange: &str, symbol: String, testnet: bool) -> PyResult<Bound<'py, PyAny>> {
+    let exchange = exchange_type(exchange)?;
+    let settings = ExchangeSettings {
+        testnet,
+        ..ExchangeSettings::default()
+    };
+    pyo3_async_runtimes::tokio::future_into_py(py, async move {
+        let connector = exchange.create_exchange_with(&settings);
+        let snapshot = connector
+            .get_snapshot(&symbol)
+            .await
+            .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+        let pairs = |levels: Vec<PriceLevel>| levels.into_iter().map(|l| (l.price, l.qty)).collect();
+        Ok(PySnapshot {
+            last_update_id: snapshot.last_update_id,
+            bids: pairs(snapshot.bids),
+            asks: pairs(snapshot.asks),
+        })
+    })
+}
+
+// A live subscription: connects, keeps the book in sync (reconnecting and resyncing as the GUI
+// does) and yields a `BookUpdate` whenever the book changed.
+//
+//     async for update in l3est.Feed("binance", "dogeusdt", depth=10):
+//         print(update.bids[0], update.events)
+#[pyclass(module = "l3est")]
+pub struct Feed {
+    exchange: ExchangeType,
+    symbol: String,
+    depth: Option<usize>,
+    queue: Arc<FeedQueue>,
+    state: Arc<tokio::sync::Mutex<FeedState>>,
+    worker: Option<Arc<FeedWorker>>,
+}
+
+struct FeedState {
+    book: OrderBook,
+    status: ConnectionStatus,
+}
+
+#[pymethods]
+impl Feed {
+    #[new]
+    #[pyo3(signature = (exchange, symbol, depth=None, testnet=false))]
+    fn new(exchange: &str, symbol: &str, depth: Option<usize>, testnet: bool) -> PyResult<Self> {
+        let exchange = exchange_type(exchange)?;
+        let settings = ExchangeSettings {
+            testnet,
+            ..ExchangeSettings::default()
+        };
+        let symbol = exchange.adapt_symbol_with(symbol, &settings);
+        let queue = FeedQueue::new(QUEUE_CAPACITY);
+        let feed_queue = queue.clone();
+        let sink: feed::FeedSink = Arc::new(move |msg| feed_queue.push(msg));
+        let mut book = OrderBook::new();
+        book.set_max_depth(settings.depth(exchange).estimation);
+        book.set_vwap_notional(settings.vwap_notional);
+        book.set_estimator(settings.estimator);
+        book.set_event_log(true);
+        let runtime = pyo3_async_runtimes::tokio::get_runtime();
+        let worker = FeedWorker::spawn(runtime.handle(), sink, symbol.clone(), exchange, settings);
+        Ok(Self {
+            exchange,
+            symbol,
+            depth,
+            queue,
+            state: Arc::new(tokio::sync::Mutex::new(FeedState {
+                book,
+                status: ConnectionStatus::Connecting,
+            })),
+            worker: Some(Arc::new(worker)),
+        })
+    }
+
+    fn __aiter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __anext__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let Some(worker) = self.worker.clone() else {
+            return Err(PyStopAsyncIteration::new_err(()));
+        };
+        let (exchange, symbol, depth) = (self.exchange, self.symbol.clone(), self.depth);
+        let queue = self.queue.clone();
+        let state = self.state.clone();
+        pyo3_async_runtimes::tokio::future_into_py(py, async move {
+            loop {
+                let waiting = queue.clone();
+                let messages = tokio::task::spawn_blocking(move || waiting.drain_timeout(DRAIN_WAIT))
+                    .await
+                    .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+                // The feed was closed while waiting
+                if Arc::strong_count(&worker) == 1 {
+                    return Err(PyStopAsyncIteration::new_err(()));
+                }
+                let mut state = state.lock().await;
+                if state.apply(messages, &worker) {
+                    return Ok(state.update(exchange, &symbol, depth, true));
+                }
+            }
+        })
+    }
+
+    // The book as it stands, without waiting for a change
+    fn book(&self) -> PyResult<BookUpdate> {
+        let mut state = self.state.try_lock().map_err(|_| PyRuntimeError::new_err("the feed is being read"))?;
+        Ok(state.update(self.exchange, &self.symbol, self.depth, false))
+    }
+
+    // Stops the feed; iteration ends
+    fn close(&mut self) {
+        self.worker = None;
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Feed({} {})", self.exchange.name(), self.symbol)
+    }
+}
+
+impl FeedState {
+    // Applies feed messages the way the frontends do; true if the book changed
+    fn apply(&mut self, messages: impl IntoIterator<Item = FeedMessage>, worker: &FeedWorker) -> bool {
+        let mut changed = false;
+        for message in messages {
+            match message {
+                FeedMessage::Snapshot(snapshot) => {
+                    self.book.apply_snapshot(&snapshot);
+                    changed = true;
+                }
+                FeedMessage::Update(update) => {
+                    self.book.queue_update(update);
+                    changed = true;
+                }
+                FeedMessage::FullState(state) => {
+                    self.book.apply_full_state(&state);
+                    changed = true;
+                }
+                FeedMessage::Trade(trade) => self.book.apply_trade(&trade),
+                FeedMessage::Status(status) => self.status = status,
+                FeedMessage::Error(e) => tracing::warn!("{e}"),
+                FeedMessage::Resync => self.book.resync(),
+                FeedMessage::Overflow(dropped) => {
+                    tracing::warn!("Python fell behind the feed, dropped {dropped} messages; resyncing");
+                    self.book.resync();
+                    worker.send(Control::Refetch);
+                }
+                FeedMessage::Precision(_, qty_prec) => self.book.set_qty_decimals(qty_prec),
+                FeedMessage::Order(event) => self.book.apply_order_event(&event),
+                FeedMessage::InstrumentStats(_) | FeedMessage::Instrument(_) | FeedMessage::Audit(_) => {}
+            }
+        }
+        self.book.flush_updates();
+        if self.book.needs_resync() {
+            self.book.resync();
+            worker.send(Control::Refetch);
+        }
+        changed && self.book.is_synced()
+    }
+
+    // With `drain`, the update takes the events inferred since the previous one
+    fn update(&mut self, exchange: ExchangeType, symbol: &str, depth: Option<usize>, drain: bool) -> BookUpdate {
+        let metrics = self.book.metrics();
+        BookUpdate {
+            exchange: exchange.name(),
+            symbol: symbol.to_string(),
+            status: self.status.label(),
+            last_update_id: self.book.last_update_id(),
+            synced: self.book.is_synced(),
+            bids: side_levels(&self.book, Side::Bid, depth),
+            asks: side_levels(&self.book, Side::Ask, depth),
+            mid: metrics.map(|m| m.mid),
+            microprice: metrics.map(|m| m.microprice),
+            imbalance: metrics.map(|m| m.imbalance),
+            events: if drain { self.book.drain_events().map(PyInferredEvent::from).collect() } else { Vec::new() },
+        }
+    }
+}
+
+#[pymodule]
+pub fn l3est(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyOrderBook>()?;
+    m.add_class::<PyInferredEvent>()?;
+    m.add_class::<Metrics>()?;
+    m.add_class::<PySnapshot>()?;
+    m.add_class::<BookUpdate>()?;
+    m.add_class::<Feed>()?;
+    m.add_function(wrap_pyfunction!(exchange_names, m)?)?;
+    m.add_function(wrap_pyfunction!(fetch_snapshot, m)?)?;
+    // Connectors run on the binding's tokio runtime; let them stop cleanly at interpreter exit
+    let atexit = m.py().import("atexit")?;
+    atexit.call_method1("register", (wrap_pyfunction!(shutdown, m)?,))?;
+    Ok(())
+}
+
+#[pyfunction]
+fn shutdown() {
+    pyo3_async_runtimes::tokio::get_runtime().block_on(exchanges::shutdown());
+}
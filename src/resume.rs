@@ -0,0 +1,136 @@
+// Keeps a feed's estimated book and session statistics across restarts. The state is saved to
+// `<dir>/<exchange>_<symbol>.json` at a fixed cadence and on exit; the next run for the same
+// instrument loads it and reconciles it against its first snapshot (`OrderBook::resume`), so
+// order ids and ages and the session totals carry on rather than starting over.
+use crate::archive::sanitize;
+use crate::exchanges::ExchangeType;
+use crate::orderbook::{OrderBook, SavedBook};
+use crate::session::SessionStats;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use web_time::Instant;
+
+// Bumped whenever a field changes meaning or goes away; states of another version are ignored
+pub const STATE_VERSION: u32 = 1;
+
+pub const DEFAULT_SAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResumeOptions {
+    pub dir: PathBuf,
+    // How often the state is saved while running, besides on exit
+    pub interval: Duration,
+}
+
+impl ResumeOptions {
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            interval: DEFAULT_SAVE_INTERVAL,
+        }
+    }
+
+    pub fn path(&self, exchange: ExchangeType, symbol: &str) -> PathBuf {
+        self.dir.join(format!("{}_{}.json", exchange.name().to_lowercase(), sanitize(symbol)))
+    }
+}
+
+// What one run leaves for the next
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SavedSession {
+    pub version: u32,
+    pub exchange: String,
+    pub symbol: String,
+    // Milliseconds since the Unix epoch
+    pub saved_at: u64,
+    pub book: SavedBook,
+    pub session: SessionStats,
+}
+
+impl SavedSession {
+    pub fn new(exchange: ExchangeType, symbol: &str, book: &OrderBook, session: SessionStats) -> Self {
+        Self {
+            version: STATE_VERSION,
+            exchange: exchange.name().to_string(),
+            symbol: symbol.to_string(),
+            saved_at: chrono::Utc::now().timestamp_millis() as u64,
+            book: book.state(),
+            session,
+        }
+    }
+
+    // None when there is no state to resume, or it was saved by another version
+    pub fn load(path: &Path) -> io::Result<Option<Self>> {
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let saved: Self = serde_json::from_slice(&bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if saved.version != STATE_VERSION {
+            tracing::warn!("Ignoring {}: state version {} is not {STATE_VERSION}", path.display(), saved.version);
+            return Ok(None);
+        }
+        Ok(Some(saved))
+    }
+
+    // Writes through a temporary name, so a crash mid-write leaves the previous state intact
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let partial = path.with_extension("partial");
+        fs::write(&partial, serde_json::to_vec(self).map_err(io::Error::other)?)?;
+        fs::rename(&partial, path)
+    }
+}
+
+// Saves a feed's state at the configured cadence while its book is synced
+pub struct StateSaver {
+    path: PathBuf,
+    interval: Duration,
+    last: Instant,
+    exchange: ExchangeType,
+    symbol: String,
+}
+
+impl StateSaver {
+    pub fn new(options: &ResumeOptions, exchange: ExchangeType, symbol: &str) -> Self {
+        Self {
+            path: options.path(exchange, symbol),
+            interval: options.interval,
+            last: Instant::now(),
+            exchange,
+            symbol: symbol.to_string(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // The state saved by the last run, if any
+    pub fn load(&self) -> io::Result<Option<SavedSession>> {
+        SavedSession::load(&self.path)
+    }
+
+    // Saves if the interval has passed since the last save
+    pub fn sample(&mut self, book: &OrderBook, session: SessionStats) -> io::Result<()> {
+        if self.last.elapsed() < self.interval {
+            return Ok(());
+        }
+        self.save(book, session)
+    }
+
+    // An unsynced book has nothing worth resuming, so the previous state is kept
+    pub fn save(&mut self, book: &OrderBook, session: SessionStats) -> io::Result<()> {
+        if !book.is_synced() {
+            return Ok(());
+        }
+        self.last = Instant::now();
+        SavedSession::new(self.exchange, &self.symbol, book, session).save(&self.path)
+    }
+}
@@ -0,0 +1,177 @@
+use crate::exchanges::ExchangeType;
+use crate::orderbook::{EstimatedOrder, LiquidityBand, OrderBook, OrderQueue};
+use crate::session::SessionStats;
+use futures_util::{SinkExt, StreamExt};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::net::SocketAddr;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+use tokio_tungstenite::{accept_async, tungstenite::protocol::Message as WsMessage};
+
+// Where and how much of the book to publish
+#[derive(Clone, Copy, Debug)]
+pub struct ServeOptions {
+    pub addr: SocketAddr,
+    // Price levels per side in each message
+    pub depth: usize,
+}
+
+// One published book state. Prices and quantities are decimal strings, as on the venues.
+#[derive(Serialize, Debug)]
+pub struct BookMessage<'a> {
+    pub exchange: &'static str,
+    pub symbol: &'a str,
+    // Milliseconds since the Unix epoch at which the state was published
+    pub timestamp: u64,
+    pub last_update_id: u64,
+    // False while the book waits for a snapshot after a reconnect
+    pub synced: bool,
+    pub mid: Option<Decimal>,
+    pub microprice: Option<Decimal>,
+    pub imbalance: Option<Decimal>,
+    // Resting size within each band around the mid, narrowest first
+    pub liquidity: Vec<BandMessage>,
+    // Average fill price of a market buy / sell worth `vwap_notional` in quote currency
+    pub vwap_notional: Decimal,
+    pub buy_vwap: Option<Decimal>,
+    pub sell_vwap: Option<Decimal>,
+    // Best first
+    pub bids: Vec<LevelMessage>,
+    pub asks: Vec<LevelMessage>,
+    // Uptime, reconnects, resyncs and update counts of the feed behind the book
+    pub session: SessionStats,
+}
+
+#[derive(Serialize, Debug)]
+pub struct BandMessage {
+    pub bps: u32,
+    pub bid: Decimal,
+    pub ask: Decimal,
+}
+
+impl From<&LiquidityBand> for BandMessage {
+    fn from(band: &LiquidityBand) -> Self {
+        Self {
+            bps: band.bps,
+            bid: band.bid,
+            ask: band.ask,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct LevelMessage {
+    pub price: Decimal,
+    pub quantity: Decimal,
+    // Estimated individual orders in queue order (oldest first)
+    pub orders: Vec<Decimal>,
+    // How sure the estimator is of each order's size, in (0, 1]; see `Inference::confidence`
+    pub confidence: Vec<f64>,
+}
+
+impl<'a> BookMessage<'a> {
+    pub fn from_book(
+        book: &OrderBook,
+        exchange: ExchangeType,
+        symbol: &'a str,
+        depth: usize,
+        session: SessionStats,
+    ) -> Self {
+        let level = |(&price, orders): (&Decimal, &OrderQueue)| LevelMessage {
+            price,
+            quantity: orders.total(),
+            orders: orders.sizes().collect(),
+            confidence: orders.iter().map(EstimatedOrder::confidence).collect(),
+        };
+        let metrics = book.metrics();
+        Self {
+            exchange: exchange.name(),
+            symbol,
+            timestamp: chrono::Utc::now().timestamp_millis() as u64,
+            last_update_id: book.last_update_id(),
+            synced: book.is_synced(),
+            mid: metrics.map(|m| m.mid),
+            microprice: metrics.map(|m| m.microprice),
+            imbalance: metrics.map(|m| m.imbalance),
+            liquidity: metrics.map_or(vec![], |m| m.liquidity.iter().map(BandMessage::from).collect()),
+            vwap_notional: book.vwap_notional(),
+            buy_vwap: metrics.and_then(|m| m.buy_vwap),
+            sell_vwap: metrics.and_then(|m| m.sell_vwap),
+            bids: book.bids.iter().rev().take(depth).map(level).collect(),
+            asks: book.asks.iter().take(depth).map(level).collect(),
+            session,
+        }
+    }
+}
+
+// Publishes the latest book state to every connected WebSocket client. Clients only ever
+// receive the newest state, so a slow reader skips intermediate ones instead of lagging.
+pub struct BookServer {
+    tx: watch::Sender<String>,
+}
+
+impl BookServer {
+    pub fn start(runtime: &tokio::runtime::Handle, addr: SocketAddr) -> std::io::Result<Self> {
+        let listener = runtime.block_on(TcpListener::bind(addr))?;
+        tracing::info!("Serving the estimated book on ws://{}", listener.local_addr()?);
+        let (tx, _) = watch::channel(String::new());
+        let clients = tx.clone();
+        runtime.spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, peer)) => {
+                        tokio::spawn(serve_client(stream, peer, clients.subscribe()));
+                    }
+                    Err(e) => tracing::warn!("Book server accept error: {e}"),
+                }
+            }
+        });
+        Ok(Self { tx })
+    }
+
+    pub fn publish(&self, message: &BookMessage) {
+        match serde_json::to_string(message) {
+            Ok(json) => {
+                self.tx.send_replace(json);
+            }
+            Err(e) => tracing::error!("Failed to serialize book: {e}"),
+        }
+    }
+}
+
+async fn serve_client(stream: TcpStream, peer: SocketAddr, mut rx: watch::Receiver<String>) {
+    let ws = match accept_async(stream).await {
+        Ok(ws) => ws,
+        Err(e) => {
+            tracing::warn!("WebSocket handshake with {peer} failed: {e}");
+            return;
+        }
+    };
+    tracing::info!("Book client connected: {peer}");
+    let (mut write, mut read) = ws.split();
+    // Send the current state right away instead of waiting for the next change
+    rx.mark_changed();
+    loop {
+        tokio::select! {
+            changed = rx.changed() => {
+                if changed.is_err() {
+                    break;
+                }
+                let json = rx.borrow_and_update().clone();
+                if json.is_empty() {
+                    continue;
+                }
+                if write.send(WsMessage::text(json)).await.is_err() {
+                    break;
+                }
+            }
+            // Clients are not expected to send anything; pings are answered by the library
+            incoming = read.next() => match incoming {
+                Some(Ok(WsMessage::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+    tracing::info!("Book client disconnected: {peer}");
+}
@@ -0,0 +1,153 @@
+// Session-level statistics of one feed: how long it has been connected, how often it had to
+// reconnect or resync, and how much of the stream the book applied.
+use crate::feed::{ConnectionStatus, FeedMessage};
+use crate::orderbook::BookCounters;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use web_time::Instant;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SessionStats {
+    // Seconds since the feed was started
+    pub session_secs: u64,
+    // Seconds the current connection has been up; 0 while not connected
+    pub uptime_secs: u64,
+    // Share of the session spent connected, in [0, 1]
+    pub connected_ratio: f64,
+    // Connections established; every one after the first is a reconnect
+    pub connects: u64,
+    // Times the book dropped its sync state and waited for a fresh snapshot
+    pub resyncs: u64,
+    // Feed messages handed to the frontend, status and error messages included
+    pub messages: u64,
+    // Depth updates (or merged bursts) applied to the book, and updates merged into them
+    pub updates_applied: u64,
+    pub updates_coalesced: u64,
+    // Updates older than the book plus messages the frontend's queue dropped to catch up
+    pub updates_dropped: u64,
+    // Mean time the book took to apply an update, estimation included
+    pub mean_apply_us: f64,
+}
+
+impl SessionStats {
+    pub fn reconnects(&self) -> u64 {
+        self.connects.saturating_sub(1)
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "up {} ({:.1}% of {}), {} reconnects, {} resyncs, {} messages, {} updates applied \
+             ({} coalesced), {} dropped, {:.1} µs/update",
+            duration_label(self.uptime_secs),
+            self.connected_ratio * 100.0,
+            duration_label(self.session_secs),
+            self.reconnects(),
+            self.resyncs,
+            self.messages,
+            self.updates_applied,
+            self.updates_coalesced,
+            self.updates_dropped,
+            self.mean_apply_us
+        )
+    }
+}
+
+// Counts what the feed delivered; the book counts what it did with it (see `BookCounters`)
+#[derive(Debug)]
+pub struct SessionTracker {
+    started: Instant,
+    // Start of the current connection
+    connected_at: Option<Instant>,
+    // Time spent connected in earlier connections
+    connected: Duration,
+    connects: u64,
+    messages: u64,
+    // Messages dropped by the frontend's queue
+    overflowed: u64,
+    // Totals of the runs this session resumed, added to this run's
+    previous: SessionStats,
+}
+
+impl Default for SessionTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SessionTracker {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            connected_at: None,
+            connected: Duration::ZERO,
+            connects: 0,
+            messages: 0,
+            overflowed: 0,
+            previous: SessionStats::default(),
+        }
+    }
+
+    // Continues the statistics saved by an earlier run; the restart counts as a reconnect
+    pub fn resumed(previous: SessionStats) -> Self {
+        Self {
+            previous,
+            ..Self::new()
+        }
+    }
+
+    pub fn record(&mut self, message: &FeedMessage) {
+        self.messages += 1;
+        match message {
+            FeedMessage::Status(ConnectionStatus::Connected) if self.connected_at.is_none() => {
+                self.connected_at = Some(Instant::now());
+                self.connects += 1;
+            }
+            FeedMessage::Status(ConnectionStatus::Connected) => {}
+            FeedMessage::Status(_) => {
+                if let Some(since) = self.connected_at.take() {
+                    self.connected += since.elapsed();
+                }
+            }
+            FeedMessage::Overflow(dropped) => self.overflowed += *dropped as u64,
+            _ => {}
+        }
+    }
+
+    pub fn stats(&self, book: &BookCounters) -> SessionStats {
+        let session = self.started.elapsed();
+        let uptime = self.connected_at.map_or(Duration::ZERO, |since| since.elapsed());
+        let connected = self.connected + uptime;
+        let previous = &self.previous;
+        let session_secs = previous.session_secs as f64 + session.as_secs_f64();
+        let connected_secs = previous.connected_ratio * previous.session_secs as f64 + connected.as_secs_f64();
+        let applied = previous.updates_applied + book.applied;
+        SessionStats {
+            session_secs: previous.session_secs + session.as_secs(),
+            uptime_secs: uptime.as_secs(),
+            connected_ratio: if session_secs == 0.0 { 0.0 } else { (connected_secs / session_secs).min(1.0) },
+            connects: previous.connects + self.connects,
+            resyncs: previous.resyncs + book.resyncs,
+            messages: previous.messages + self.messages,
+            updates_applied: applied,
+            updates_coalesced: previous.updates_coalesced + book.coalesced,
+            updates_dropped: previous.updates_dropped + book.skipped + self.overflowed,
+            // Weighted by the updates each run applied
+            mean_apply_us: if applied == 0 {
+                0.0
+            } else {
+                (previous.mean_apply_us * previous.updates_applied as f64
+                    + book.mean_apply_us() * book.applied as f64)
+                    / applied as f64
+            },
+        }
+    }
+}
+
+// e.g. "45s", "12m 03s", "3h 07m"
+pub fn duration_label(secs: u64) -> String {
+    match secs {
+        0..60 => format!("{secs}s"),
+        60..3600 => format!("{}m {:02}s", secs / 60, secs % 60),
+        _ => format!("{}h {:02}m", secs / 3600, secs % 3600 / 60),
+    }
+}
@@ -0,0 +1,419 @@
+use crate::exchanges::{DepthUpdate, OrderBookSnapshot, OrderEvent, OrderEventKind, PriceLevel, Side, Trade};
+use crate::orderbook::OrderBook;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rust_decimal::Decimal;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+
+// Tick the first orders are placed around
+const START_TICK: i64 = 10_000;
+// Order sizes are capped at this multiple of the minimum so the tail stays finite
+const MAX_SIZE_MULTIPLE: f64 = 1_000.0;
+
+// Parameters of the simulated order flow. Rates are per second of simulated time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimulatorConfig {
+    // The same seed replays the same flow
+    pub seed: u64,
+    // Limit order arrivals, both sides together
+    pub arrival_rate: f64,
+    // Cancellation rate of each resting order, so orders live 1 / cancel_rate seconds on average
+    pub cancel_rate: f64,
+    // Market order arrivals, both sides together
+    pub market_rate: f64,
+    // Pareto shape of order sizes; lower values give heavier tails
+    pub size_alpha: f64,
+    pub min_size: Decimal,
+    // Decimals order sizes are rounded to
+    pub size_decimals: u32,
+    pub tick_size: Decimal,
+    // Limit orders rest up to this many ticks behind the opposite touch, most of them close to it
+    pub depth: usize,
+    // Resting orders placed before the first event
+    pub initial_orders: usize,
+}
+
+impl Default for SimulatorConfig {
+    fn default() -> Self {
+        Self {
+            seed: 1,
+            arrival_rate: 50.0,
+            cancel_rate: 0.05,
+            market_rate: 5.0,
+            size_alpha: 1.5,
+            min_size: Decimal::ONE,
+            size_decimals: 3,
+            tick_size: Decimal::new(1, 2),
+            depth: 50,
+            initial_orders: 500,
+        }
+    }
+}
+
+struct SimOrder {
+    id: u64,
+    size: Decimal,
+}
+
+// One simulated event: what happened to individual orders, and the L2 diff a venue would
+// publish for it
+pub struct SimStep {
+    pub events: Vec<OrderEvent>,
+    // One per resting order a market order filled against
+    pub trades: Vec<Trade>,
+    pub update: DepthUpdate,
+}
+
+// How closely an estimated book matches the simulator's actual queues
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Accuracy {
+    // Price levels in the simulated book
+    pub levels: usize,
+    // Levels whose estimated queue holds exactly the true order sizes, in queue order
+    pub exact_levels: usize,
+    pub true_orders: usize,
+    pub estimated_orders: usize,
+    // Estimated orders with the size of a true order at the same level, each true order matched once
+    pub matched_orders: usize,
+}
+
+impl Accuracy {
+    pub fn exact_level_ratio(&self) -> f64 {
+        ratio(self.exact_levels, self.levels)
+    }
+
+    // Share of true orders the estimator found
+    pub fn recall(&self) -> f64 {
+        ratio(self.matched_orders, self.true_orders)
+    }
+
+    // Share of estimated orders that exist
+    pub fn precision(&self) -> f64 {
+        ratio(self.matched_orders, self.estimated_orders)
+    }
+}
+
+fn ratio(part: usize, whole: usize) -> f64 {
+    if whole == 0 {
+        1.0
+    } else {
+        part as f64 / whole as f64
+    }
+}
+
+// Deterministic L3 order flow: limit orders arrive as a Poisson process with power-law sizes,
+// each resting order is cancelled at a constant rate and market orders consume the queues from
+// the front. Every event comes with the L2 diff it produces, so the estimator can be run on the
+// diffs and scored against the true queues.
+pub struct OrderFlowSimulator {
+    config: SimulatorConfig,
+    rng: StdRng,
+    // Simulated seconds since the start
+    clock: f64,
+    seq: u64,
+    next_order_id: u64,
+    next_trade_id: u64,
+    bids: BTreeMap<i64, VecDeque<SimOrder>>,
+    asks: BTreeMap<i64, VecDeque<SimOrder>>,
+    // Resting order ids, so a cancellation can pick one uniformly; `location` maps each id to its
+    // slot here and its level
+    resting: Vec<u64>,
+    location: HashMap<u64, (usize, Side, i64)>,
+}
+
+impl OrderFlowSimulator {
+    pub fn new(config: SimulatorConfig) -> Self {
+        let mut sim = Self {
+            rng: StdRng::seed_from_u64(config.seed),
+            config,
+            clock: 0.0,
+            seq: 1,
+            next_order_id: 1,
+            next_trade_id: 1,
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+            resting: Vec::new(),
+            location: HashMap::new(),
+        };
+        let mut ignored = Changes::default();
+        for _ in 0..sim.config.initial_orders {
+            sim.place_limit(&mut ignored);
+        }
+        sim
+    }
+
+    // Simulated milliseconds since the start
+    pub fn time_ms(&self) -> u64 {
+        (self.clock * 1000.0) as u64
+    }
+
+    pub fn order_count(&self) -> usize {
+        self.resting.len()
+    }
+
+    // Aggregated book as a venue's REST snapshot would show it
+    pub fn snapshot(&self) -> OrderBookSnapshot {
+        let levels = |side: &BTreeMap<i64, VecDeque<SimOrder>>| {
+            side.iter()
+                .map(|(&tick, queue)| PriceLevel::new(self.price(tick), queue.iter().map(|o| o.size).sum()))
+                .collect()
+        };
+        OrderBookSnapshot {
+            last_update_id: self.seq,
+            event_time: self.time_ms(),
+            recv_time: self.time_ms(),
+            bids: levels(&self.bids),
+            asks: levels(&self.asks),
+        }
+    }
+
+    // True order sizes at a level, front of the queue first
+    pub fn queue(&self, side: Side, price: Decimal) -> Vec<Decimal> {
+        let tick = (price / self.config.tick_size).round().try_into().unwrap_or(0);
+        self.side(side).get(&tick).map(|queue| queue.iter().map(|o| o.size).collect()).unwrap_or_default()
+    }
+
+    // Advances to the next event
+    pub fn step(&mut self) -> SimStep {
+        let cancel_rate = self.config.cancel_rate * self.resting.len() as f64;
+        let total = self.config.arrival_rate + cancel_rate + self.config.market_rate;
+        if total > 0.0 {
+            let u: f64 = self.rng.random_range(f64::EPSILON..1.0);
+            self.clock += -u.ln() / total;
+        }
+        let mut changes = Changes::default();
+        let pick = self.rng.random::<f64>() * total;
+        if pick < self.config.arrival_rate {
+            self.place_limit(&mut changes);
+        } else if pick < self.config.arrival_rate + cancel_rate {
+            self.cancel_random(&mut changes);
+        } else {
+            self.market_order(&mut changes);
+        }
+
+        let pu = self.seq;
+        self.seq += 1;
+        let time = self.time_ms();
+        let levels = |changed: &BTreeSet<i64>, side: &BTreeMap<i64, VecDeque<SimOrder>>| {
+            changed
+                .iter()
+                .map(|tick| {
+                    let total = side.get(tick).map(|q| q.iter().map(|o| o.size).sum()).unwrap_or(Decimal::ZERO);
+                    PriceLevel::new(self.price(*tick), total)
+                })
+                .collect()
+        };
+        let update = DepthUpdate {
+            event_time: time,
+            transaction_time: time,
+            symbol: String::new(),
+            capital_u: self.seq,
+            small_u: self.seq,
+            pu: pu as i64,
+            bids: levels(&changes.bids, &self.bids),
+            asks: levels(&changes.asks, &self.asks),
+            recv_time: time,
+        };
+        SimStep {
+            events: changes.events,
+            trades: changes.trades,
+            update,
+        }
+    }
+
+    // Scores the estimator's queues against the true ones
+    pub fn accuracy(&self, book: &OrderBook) -> Accuracy {
+        let mut accuracy = Accuracy::default();
+        for (truth, estimate) in [(&self.bids, &book.bids), (&self.asks, &book.asks)] {
+            for (&tick, queue) in truth {
+                let price = self.price(tick);
+                let mut actual: Vec<Decimal> = queue.iter().map(|o| o.size).collect();
+                let mut estimated: Vec<Decimal> = estimate.get(&price).map(|q| q.sizes().collect()).unwrap_or_default();
+                accuracy.levels += 1;
+                if actual == estimated {
+                    accuracy.exact_levels += 1;
+                }
+                accuracy.true_orders += actual.len();
+                actual.sort();
+                estimated.sort();
+                accuracy.matched_orders += matching(&actual, &estimated);
+            }
+            // Including levels the simulator does not have
+            accuracy.estimated_orders += estimate.values().map(|q| q.len()).sum::<usize>();
+        }
+        accuracy
+    }
+
+    fn price(&self, tick: i64) -> Decimal {
+        Decimal::from(tick) * self.config.tick_size
+    }
+
+    fn side(&self, side: Side) -> &BTreeMap<i64, VecDeque<SimOrder>> {
+        match side {
+            Side::Bid => &self.bids,
+            Side::Ask => &self.asks,
+        }
+    }
+
+    fn random_size(&mut self) -> Decimal {
+        let u: f64 = self.rng.random_range(f64::EPSILON..1.0);
+        let multiple = u.powf(-1.0 / self.config.size_alpha).min(MAX_SIZE_MULTIPLE);
+        let size = self.config.min_size * Decimal::from_f64_retain(multiple).unwrap_or(Decimal::ONE);
+        size.round_dp(self.config.size_decimals).max(self.config.min_size)
+    }
+
+    fn place_limit(&mut self, changes: &mut Changes) {
+        let side = if self.rng.random_bool(0.5) { Side::Bid } else { Side::Ask };
+        let u: f64 = self.rng.random();
+        let offset = (u * u * self.config.depth as f64) as i64;
+        let best_bid = self.bids.keys().next_back().copied();
+        let best_ask = self.asks.keys().next().copied();
+        let tick = match side {
+            Side::Bid => best_ask.or(best_bid.map(|t| t + 1)).unwrap_or(START_TICK) - 1 - offset,
+            Side::Ask => best_bid.or(best_ask.map(|t| t - 1)).unwrap_or(START_TICK) + 1 + offset,
+        };
+        let size = self.random_size();
+        let id = self.next_order_id;
+        self.next_order_id += 1;
+        let levels = match side {
+            Side::Bid => &mut self.bids,
+            Side::Ask => &mut self.asks,
+        };
+        levels.entry(tick).or_default().push_back(SimOrder { id, size });
+        self.location.insert(id, (self.resting.len(), side, tick));
+        self.resting.push(id);
+        changes.record(side, tick, self.event(OrderEventKind::Created, id, side, tick, size));
+    }
+
+    fn cancel_random(&mut self, changes: &mut Changes) {
+        if self.resting.is_empty() {
+            return;
+        }
+        let id = self.resting[self.rng.random_range(0..self.resting.len())];
+        let (_, side, tick) = self.location[&id];
+        let levels = match side {
+            Side::Bid => &mut self.bids,
+            Side::Ask => &mut self.asks,
+        };
+        let Some(queue) = levels.get_mut(&tick) else {
+            return;
+        };
+        let Some(pos) = queue.iter().position(|o| o.id == id) else {
+            return;
+        };
+        let order = queue.remove(pos).expect("position is in the queue");
+        if queue.is_empty() {
+            levels.remove(&tick);
+        }
+        self.forget(id);
+        changes.record(side, tick, self.event(OrderEventKind::Deleted, id, side, tick, order.size));
+    }
+
+    fn market_order(&mut self, changes: &mut Changes) {
+        let aggressor = if self.rng.random_bool(0.5) { Side::Bid } else { Side::Ask };
+        let maker = match aggressor {
+            Side::Bid => Side::Ask,
+            Side::Ask => Side::Bid,
+        };
+        let mut remaining = self.random_size();
+        while remaining > Decimal::ZERO {
+            let levels = match maker {
+                Side::Bid => &mut self.bids,
+                Side::Ask => &mut self.asks,
+            };
+            let touch = match maker {
+                Side::Bid => levels.last_entry(),
+                Side::Ask => levels.first_entry(),
+            };
+            let Some(mut level) = touch else {
+                break;
+            };
+            let tick = *level.key();
+            let queue = level.get_mut();
+            let front = queue.front_mut().expect("levels are never empty");
+            let id = front.id;
+            let filled = remaining.min(front.size);
+            front.size -= filled;
+            remaining -= filled;
+            let left = front.size;
+            if left.is_zero() {
+                queue.pop_front();
+                if queue.is_empty() {
+                    level.remove();
+                }
+                self.forget(id);
+            }
+            let kind = if left.is_zero() { OrderEventKind::Deleted } else { OrderEventKind::Changed };
+            let event = self.event(kind, id, maker, tick, left);
+            changes.record(maker, tick, event);
+            changes.trades.push(Trade {
+                trade_id: self.next_trade_id,
+                price: self.price(tick),
+                qty: filled,
+                aggressor,
+                timestamp: self.time_ms(),
+            });
+            self.next_trade_id += 1;
+        }
+    }
+
+    // Drops a filled or cancelled order from the cancellation index
+    fn forget(&mut self, id: u64) {
+        let Some((slot, _, _)) = self.location.remove(&id) else {
+            return;
+        };
+        self.resting.swap_remove(slot);
+        if let Some(&moved) = self.resting.get(slot) {
+            if let Some(location) = self.location.get_mut(&moved) {
+                location.0 = slot;
+            }
+        }
+    }
+
+    fn event(&self, kind: OrderEventKind, order_id: u64, side: Side, tick: i64, qty: Decimal) -> OrderEvent {
+        OrderEvent {
+            kind,
+            order_id,
+            side,
+            price: self.price(tick),
+            qty,
+            timestamp: self.time_ms(),
+        }
+    }
+}
+
+// Orders and levels touched by one event
+#[derive(Default)]
+struct Changes {
+    events: Vec<OrderEvent>,
+    trades: Vec<Trade>,
+    bids: BTreeSet<i64>,
+    asks: BTreeSet<i64>,
+}
+
+impl Changes {
+    fn record(&mut self, side: Side, tick: i64, event: OrderEvent) {
+        match side {
+            Side::Bid => self.bids.insert(tick),
+            Side::Ask => self.asks.insert(tick),
+        };
+        self.events.push(event);
+    }
+}
+
+// Size of the multiset intersection of two sorted lists
+fn matching(a: &[Decimal], b: &[Decimal]) -> usize {
+    let (mut i, mut j, mut matched) = (0, 0, 0);
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                matched += 1;
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    matched
+}
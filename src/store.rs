@@ -0,0 +1,251 @@
+// Persists the estimator's inferred order lifecycles to a SQLite database (`sqlite` feature), so
+// order longevity and cancellation behavior can be studied after the session with plain SQL.
+//
+// `orders` holds one row per estimated order: when it was first seen and when it left the book,
+// and whether a trade explains its leaving. `events` keeps every inferred event as it happened.
+// Ids restart with each run, so both tables are keyed by the `sessions` row of the run.
+//
+// Orders loaded from a snapshot have no join event, so their `created_at` is NULL. A snapshot
+// rebuilds the book, and the orders still open are closed with outcome `reset`.
+use crate::exchanges::{ExchangeType, Side};
+use crate::orderbook::{InferredAction, InferredEvent};
+use rusqlite::{params, Connection, OptionalExtension};
+use rust_decimal::prelude::ToPrimitive;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+// Batches of events waiting for the writer before new ones are dropped
+const QUEUE_CAPACITY: usize = 1024;
+
+// Prices and sizes are REAL so they sort and aggregate in SQL; `lifetime_ms` is NULL while the
+// order rests or when its join was not seen
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS sessions (
+    id INTEGER PRIMARY KEY,
+    exchange TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    started_at INTEGER NOT NULL,
+    ended_at INTEGER
+);
+CREATE TABLE IF NOT EXISTS orders (
+    session INTEGER NOT NULL REFERENCES sessions(id),
+    order_id INTEGER NOT NULL,
+    side TEXT NOT NULL,
+    price REAL NOT NULL,
+    initial_size REAL NOT NULL,
+    size REAL NOT NULL,
+    filled REAL NOT NULL DEFAULT 0,
+    reductions INTEGER NOT NULL DEFAULT 0,
+    created_at INTEGER,
+    removed_at INTEGER,
+    outcome TEXT,
+    PRIMARY KEY (session, order_id)
+);
+CREATE INDEX IF NOT EXISTS orders_removed ON orders (session, removed_at);
+CREATE TABLE IF NOT EXISTS events (
+    session INTEGER NOT NULL REFERENCES sessions(id),
+    time INTEGER NOT NULL,
+    order_id INTEGER NOT NULL,
+    side TEXT NOT NULL,
+    price REAL NOT NULL,
+    action TEXT NOT NULL,
+    size REAL NOT NULL,
+    change REAL NOT NULL
+);
+CREATE INDEX IF NOT EXISTS events_order ON events (session, order_id);
+CREATE VIEW IF NOT EXISTS order_lifetimes AS
+    SELECT orders.*, removed_at - created_at AS lifetime_ms FROM orders;
+";
+
+// How an order left the book, in the `outcome` column
+pub const OUTCOME_CANCELLED: &str = "cancelled";
+pub const OUTCOME_FILLED: &str = "filled";
+pub const OUTCOME_RESET: &str = "reset";
+
+enum StoreMessage {
+    Events(Vec<InferredEvent>),
+    // A snapshot rebuilt the book at this time
+    Reset(u64),
+}
+
+// Hands the inferred events of one feed to a writer thread, one transaction per batch
+pub struct EventStore {
+    path: PathBuf,
+    session: i64,
+    tx: Option<SyncSender<StoreMessage>>,
+    thread: Option<JoinHandle<()>>,
+    written: Arc<AtomicU64>,
+    dropped: u64,
+}
+
+impl EventStore {
+    // Creates the database or adds to it, starting a new session
+    pub fn open(path: &Path, exchange: ExchangeType, symbol: &str) -> io::Result<Self> {
+        let mut db = Connection::open(path).map_err(io::Error::other)?;
+        db.execute_batch("PRAGMA journal_mode = WAL; PRAGMA synchronous = NORMAL;").map_err(io::Error::other)?;
+        db.execute_batch(SCHEMA).map_err(io::Error::other)?;
+        db.execute(
+            "INSERT INTO sessions (exchange, symbol, started_at) VALUES (?1, ?2, ?3)",
+            params![exchange.name(), symbol, now_millis() as i64],
+        )
+        .map_err(io::Error::other)?;
+        let session = db.last_insert_rowid();
+        let (tx, rx) = mpsc::sync_channel::<StoreMessage>(QUEUE_CAPACITY);
+        let written = Arc::new(AtomicU64::new(0));
+        let counter = written.clone();
+        let name = path.display().to_string();
+        let thread = std::thread::Builder::new().name("event store".to_string()).spawn(move || {
+            for message in rx {
+                let result = match message {
+                    StoreMessage::Events(events) => write_events(&mut db, session, &events),
+                    StoreMessage::Reset(time) => close_open(&db, session, time).map(|_| 0),
+                };
+                match result {
+                    Ok(rows) => {
+                        counter.fetch_add(rows, Ordering::Relaxed);
+                    }
+                    Err(e) => tracing::error!("Writing inferred events to {name} failed: {e}"),
+                }
+            }
+            if let Err(e) = db.execute(
+                "UPDATE sessions SET ended_at = ?2 WHERE id = ?1",
+                params![session, now_millis() as i64],
+            ) {
+                tracing::error!("Closing the session in {name} failed: {e}");
+            }
+        })?;
+        tracing::info!("Recording inferred orders of {} {} to {} (session {session})", exchange.name(), symbol, path.display());
+        Ok(Self {
+            path: path.to_path_buf(),
+            session,
+            tx: Some(tx),
+            thread: Some(thread),
+            written,
+            dropped: 0,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Id of this run's row in `sessions`
+    pub fn session(&self) -> i64 {
+        self.session
+    }
+
+    // Queues a batch of events, e.g. those drained from the book after an update
+    pub fn record(&mut self, events: Vec<InferredEvent>) {
+        if !events.is_empty() {
+            self.send(StoreMessage::Events(events));
+        }
+    }
+
+    // Closes the orders still open: a snapshot at `time` replaced the book they were in
+    pub fn reset(&mut self, time: u64) {
+        self.send(StoreMessage::Reset(time));
+    }
+
+    fn send(&mut self, message: StoreMessage) {
+        if let Some(tx) = &self.tx {
+            if let Err(TrySendError::Full(_)) = tx.try_send(message) {
+                self.dropped += 1;
+            }
+        }
+    }
+
+    // Events written so far
+    pub fn written(&self) -> u64 {
+        self.written.load(Ordering::Relaxed)
+    }
+
+    // Batches skipped because the database could not keep up
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    // Writes what is still queued and marks the session ended
+    pub fn close(&mut self) {
+        self.tx = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for EventStore {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+fn write_events(db: &mut Connection, session: i64, events: &[InferredEvent]) -> rusqlite::Result<u64> {
+    let tx = db.transaction()?;
+    {
+        let mut log = tx.prepare_cached(
+            "INSERT INTO events (session, time, order_id, side, price, action, size, change)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        )?;
+        let mut known = tx.prepare_cached("SELECT 1 FROM orders WHERE session = ?1 AND order_id = ?2")?;
+        let mut insert = tx.prepare_cached(
+            "INSERT INTO orders (session, order_id, side, price, initial_size, size, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?5, ?6)",
+        )?;
+        let mut update = tx.prepare_cached(
+            "UPDATE orders SET size = ?3,
+                 filled = filled + ?4,
+                 reductions = reductions + ?5,
+                 removed_at = CASE WHEN ?3 = 0 THEN ?6 ELSE removed_at END,
+                 outcome = CASE WHEN ?3 = 0 THEN ?7 ELSE outcome END
+             WHERE session = ?1 AND order_id = ?2",
+        )?;
+        for event in events {
+            let side = side_name(event.side);
+            let price = real(event.price);
+            let (size, change) = (real(event.size), real(event.change));
+            let time = event.time as i64;
+            let id = event.order_id as i64;
+            log.execute(params![session, time, id, side, price, event.action.label(), size, change])?;
+            if event.action == InferredAction::Joined {
+                insert.execute(params![session, id, side, price, size, time])?;
+                continue;
+            }
+            // An order from a snapshot, first seen as it changes
+            if known.query_row(params![session, id], |row| row.get::<_, i64>(0)).optional()?.is_none() {
+                insert.execute(params![session, id, side, price, size - change, None::<i64>])?;
+            }
+            let filled = if event.action == InferredAction::Filled { -change } else { 0.0 };
+            let reduced = i64::from(event.action == InferredAction::Reduced);
+            let outcome = if event.action == InferredAction::Filled { OUTCOME_FILLED } else { OUTCOME_CANCELLED };
+            update.execute(params![session, id, size, filled, reduced, time, outcome])?;
+        }
+    }
+    tx.commit()?;
+    Ok(events.len() as u64)
+}
+
+fn close_open(db: &Connection, session: i64, time: u64) -> rusqlite::Result<usize> {
+    db.execute(
+        "UPDATE orders SET removed_at = ?2, outcome = ?3 WHERE session = ?1 AND removed_at IS NULL",
+        params![session, time as i64, OUTCOME_RESET],
+    )
+}
+
+fn side_name(side: Side) -> &'static str {
+    match side {
+        Side::Bid => "bid",
+        Side::Ask => "ask",
+    }
+}
+
+fn real(value: rust_decimal::Decimal) -> f64 {
+    value.to_f64().unwrap_or(f64::NAN)
+}
+
+fn now_millis() -> u64 {
+    chrono::Utc::now().timestamp_millis() as u64
+}
@@ -0,0 +1,230 @@
+use crate::exchanges::{self, ExchangeSettings, ExchangeType};
+use crate::feed::{self, ConnectionStatus, Control, FeedMessage, FeedQueue, FeedWorker};
+use crate::orderbook::{OrderBook, OrderQueue};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Cell, Paragraph, Row, Table};
+use ratatui::{DefaultTerminal, Frame};
+use rust_decimal::Decimal;
+use std::sync::Arc;
+use std::time::Duration;
+
+// Messages the feed may queue ahead of the loop before it coalesces and then drops them
+const QUEUE_CAPACITY: usize = 10_000;
+
+// Longest wait for a key press before the book is redrawn
+const FRAME: Duration = Duration::from_millis(50);
+
+// Eighths of a cell, so bars grow smoothly rather than a whole character at a time
+const BAR_BLOCKS: [char; 8] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉', '█'];
+
+// Terminal frontend: the book grid with the spread between the sides and a bar per level,
+// drawn from the same feed and estimator as the GUI. q, Esc or Ctrl-C quits, r resyncs.
+pub fn run(symbol: String, exchange: ExchangeType, settings: ExchangeSettings) -> std::io::Result<()> {
+    let queue = FeedQueue::new(QUEUE_CAPACITY);
+    let feed_queue = queue.clone();
+    let sink: feed::FeedSink = Arc::new(move |msg| feed_queue.push(msg));
+    let runtime = tokio::runtime::Runtime::new()?;
+    let max_depth = settings.depth(exchange).estimation;
+    let vwap_notional = settings.vwap_notional;
+    let estimator = settings.estimator;
+    let worker = FeedWorker::spawn(runtime.handle(), sink, symbol.clone(), exchange, settings);
+
+    let mut view = View {
+        exchange,
+        symbol,
+        book: OrderBook::new(),
+        status: ConnectionStatus::Connecting,
+        error: None,
+    };
+    view.book.set_max_depth(max_depth);
+    view.book.set_vwap_notional(vwap_notional);
+    view.book.set_estimator(estimator);
+
+    // Restores the terminal on exit, and on panic through ratatui's hook
+    let result = ratatui::run(|terminal| view.run(terminal, &queue, &worker));
+    drop(worker);
+    runtime.block_on(exchanges::shutdown());
+    result
+}
+
+struct View {
+    exchange: ExchangeType,
+    symbol: String,
+    book: OrderBook,
+    status: ConnectionStatus,
+    error: Option<String>,
+}
+
+impl View {
+    fn run(&mut self, terminal: &mut DefaultTerminal, queue: &FeedQueue, worker: &FeedWorker) -> std::io::Result<()> {
+        loop {
+            for message in queue.drain() {
+                self.apply(message, worker);
+            }
+            self.book.flush_updates();
+            if self.book.needs_resync() {
+                self.error = Some(format!("book crossed {} times; resyncing", self.book.crossings()));
+                self.book.resync();
+                worker.send(Control::Refetch);
+            }
+            terminal.draw(|frame| self.draw(frame))?;
+
+            if !event::poll(FRAME)? {
+                continue;
+            }
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(()),
+                KeyCode::Char('r') => {
+                    self.book.resync();
+                    worker.send(Control::Refetch);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn apply(&mut self, message: FeedMessage, worker: &FeedWorker) {
+        match message {
+            FeedMessage::Snapshot(snap) => {
+                self.book.apply_snapshot(&snap);
+            }
+            FeedMessage::Update(update) => {
+                self.book.queue_update(update);
+            }
+            FeedMessage::FullState(state) => {
+                self.book.apply_full_state(&state);
+            }
+            FeedMessage::Status(status) => self.status = status,
+            FeedMessage::Error(e) => self.error = Some(e.to_string()),
+            FeedMessage::Resync => self.book.resync(),
+            FeedMessage::Overflow(dropped) => {
+                self.error = Some(format!("fell behind, dropped {dropped} messages; resyncing"));
+                self.book.resync();
+                worker.send(Control::Refetch);
+            }
+            FeedMessage::Precision(_, qty_prec) => self.book.set_qty_decimals(qty_prec),
+            FeedMessage::Trade(trade) => self.book.apply_trade(&trade),
+            FeedMessage::Order(event) => self.book.apply_order_event(&event),
+            FeedMessage::InstrumentStats(_) | FeedMessage::Instrument(_) | FeedMessage::Audit(_) => {}
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [header, body, footer] =
+            Layout::vertical([Constraint::Length(2), Constraint::Min(3), Constraint::Length(1)]).areas(frame.area());
+        frame.render_widget(Paragraph::new(self.header()), header);
+        self.draw_book(frame, body);
+        let footer_text = match &self.error {
+            Some(e) => Line::styled(format!("last error: {e}"), Style::new().fg(Color::Yellow)),
+            None => Line::styled("q quit  r resync", Style::new().fg(Color::DarkGray)),
+        };
+        frame.render_widget(Paragraph::new(footer_text), footer);
+    }
+
+    fn header(&self) -> Vec<Line<'static>> {
+        let status_color = match self.status {
+            ConnectionStatus::Connected => Color::Green,
+            ConnectionStatus::Connecting => Color::Yellow,
+            ConnectionStatus::Stale | ConnectionStatus::Disconnected => Color::Red,
+        };
+        let title = Line::from(vec![
+            Span::styled(
+                format!("{} {} ", self.exchange.name(), self.symbol.to_uppercase()),
+                Style::new().add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(self.status.label(), Style::new().fg(status_color)),
+            Span::raw(format!(
+                "  levels {}/{}  est. orders {}",
+                self.book.bids.len(),
+                self.book.asks.len(),
+                self.book.order_count()
+            )),
+        ]);
+        let quote = match (self.book.best_bid(), self.book.best_ask(), self.book.metrics()) {
+            (Some(bid), Some(ask), Some(m)) => format!(
+                "bid {bid}  ask {ask}  spread {}  mid {}  microprice {:.8}  imbalance {:+.3}",
+                ask - bid,
+                m.mid.normalize(),
+                m.microprice,
+                m.imbalance
+            ),
+            _ => "waiting for book...".to_string(),
+        };
+        vec![title, Line::raw(quote)]
+    }
+
+    // Asks above the spread row, best nearest to it, then bids; each side gets half the rows
+    fn draw_book(&self, frame: &mut Frame, area: Rect) {
+        let block = Block::new().borders(Borders::TOP);
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+        // Header and spread rows
+        let per_side = (inner.height.saturating_sub(2) / 2) as usize;
+        let asks: Vec<_> = self.book.asks.iter().take(per_side).collect();
+        let bids: Vec<_> = self.book.bids.iter().rev().take(per_side).collect();
+        let widest = asks.iter().chain(&bids).map(|(_, queue)| queue.total()).max().unwrap_or_default();
+
+        let [_, _, _, bar_area] = Self::columns(inner);
+        let bar_width = bar_area.width as usize;
+        let row = |(&price, queue): (&Decimal, &OrderQueue), color: Color| {
+            let orders = match queue.reported_count() {
+                Some(count) => format!("{} ({count})", queue.len()),
+                None => queue.len().to_string(),
+            };
+            Row::new(vec![
+                Cell::from(price.to_string()).style(Style::new().fg(color)),
+                Cell::from(queue.total().normalize().to_string()),
+                Cell::from(orders),
+                Cell::from(bar(queue.total(), widest, bar_width)).style(Style::new().fg(color)),
+            ])
+        };
+
+        let mut rows: Vec<Row> = Vec::with_capacity(per_side * 2 + 1);
+        // Pad the ask side so the spread row stays in the middle of a thin book
+        rows.extend((asks.len()..per_side).map(|_| Row::new(Vec::<Cell>::new())));
+        rows.extend(asks.into_iter().rev().map(|level| row(level, Color::Red)));
+        let spread = match (self.book.best_bid(), self.book.best_ask()) {
+            (Some(bid), Some(ask)) => format!("spread {}", ask - bid),
+            _ => String::new(),
+        };
+        rows.push(Row::new(vec![Cell::from(spread)]).style(Style::new().add_modifier(Modifier::DIM)));
+        rows.extend(bids.into_iter().map(|level| row(level, Color::Green)));
+
+        let table = Table::new(rows, Self::widths())
+            .header(Row::new(vec!["Price", "Size", "Orders", "Depth"]).style(Style::new().add_modifier(Modifier::BOLD)));
+        frame.render_widget(table, inner);
+    }
+
+    fn widths() -> [Constraint; 4] {
+        [Constraint::Length(16), Constraint::Length(16), Constraint::Length(12), Constraint::Fill(1)]
+    }
+
+    fn columns(area: Rect) -> [Rect; 4] {
+        // Tables put one cell of spacing between columns
+        Layout::horizontal(Self::widths()).spacing(1).areas(area)
+    }
+}
+
+// A bar of `size` relative to `widest`, `width` cells at most, in eighths of a cell
+fn bar(size: Decimal, widest: Decimal, width: usize) -> String {
+    if widest.is_zero() || width == 0 {
+        return String::new();
+    }
+    let eighths = ((size / widest) * Decimal::from(width * 8)).round();
+    let eighths = eighths.try_into().unwrap_or(0usize).min(width * 8);
+    let mut bar = BAR_BLOCKS[7].to_string().repeat(eighths / 8);
+    if eighths % 8 > 0 {
+        bar.push(BAR_BLOCKS[eighths % 8 - 1]);
+    }
+    bar
+}
@@ -0,0 +1,144 @@
+// Traded volume by price over a rolling window of the trade feed, split by the taker's side.
+// With the book grouped, prices are floored to its bucket as the book floors bids.
+use crate::exchanges::{Side, Trade};
+use rust_decimal::Decimal;
+use std::collections::{BTreeMap, VecDeque};
+
+// Seconds of trades the profile covers unless set otherwise
+pub const DEFAULT_PROFILE_WINDOW_SECS: u64 = 300;
+
+// Trades kept for the window; the oldest go first on very busy markets
+const TRADE_CAPACITY: usize = 100_000;
+
+// Volume traded at one price bucket
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ProfileLevel {
+    // Bought by takers lifting asks
+    pub buy: Decimal,
+    // Sold by takers hitting bids
+    pub sell: Decimal,
+}
+
+impl ProfileLevel {
+    pub fn total(&self) -> Decimal {
+        self.buy + self.sell
+    }
+
+    fn add(&mut self, side: Side, qty: Decimal) {
+        match side {
+            Side::Bid => self.buy += qty,
+            Side::Ask => self.sell += qty,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct VolumeProfile {
+    window_ms: u64,
+    bucket: Option<Decimal>,
+    trades: VecDeque<Trade>,
+    levels: BTreeMap<Decimal, ProfileLevel>,
+}
+
+impl Default for VolumeProfile {
+    fn default() -> Self {
+        Self::new(DEFAULT_PROFILE_WINDOW_SECS)
+    }
+}
+
+impl VolumeProfile {
+    pub fn new(window_secs: u64) -> Self {
+        Self {
+            window_ms: window_secs * 1000,
+            bucket: None,
+            trades: VecDeque::new(),
+            levels: BTreeMap::new(),
+        }
+    }
+
+    pub fn window_secs(&self) -> u64 {
+        self.window_ms / 1000
+    }
+
+    // A shorter window takes effect at the next `expire`; trades already dropped do not come back
+    pub fn set_window(&mut self, secs: u64) {
+        self.window_ms = secs * 1000;
+    }
+
+    pub fn bucket(&self) -> Option<Decimal> {
+        self.bucket
+    }
+
+    // Price bucket size, None for the traded prices as they are. Rebuilds the levels.
+    pub fn set_bucket(&mut self, bucket: Option<Decimal>) {
+        let bucket = bucket.filter(|b| *b > Decimal::ZERO);
+        if bucket == self.bucket {
+            return;
+        }
+        self.bucket = bucket;
+        self.levels.clear();
+        for trade in &self.trades {
+            let price = bucket_price(bucket, trade.price);
+            self.levels.entry(price).or_default().add(trade.aggressor, trade.qty);
+        }
+    }
+
+    pub fn record(&mut self, trade: &Trade) {
+        if trade.qty <= Decimal::ZERO {
+            return;
+        }
+        if self.trades.len() == TRADE_CAPACITY {
+            self.pop_oldest();
+        }
+        self.levels.entry(bucket_price(self.bucket, trade.price)).or_default().add(trade.aggressor, trade.qty);
+        self.trades.push_back(trade.clone());
+    }
+
+    // Drops trades older than the window before `now` (milliseconds since the Unix epoch)
+    pub fn expire(&mut self, now: u64) {
+        let cutoff = now.saturating_sub(self.window_ms);
+        while self.trades.front().is_some_and(|trade| trade.timestamp < cutoff) {
+            self.pop_oldest();
+        }
+    }
+
+    fn pop_oldest(&mut self) {
+        let Some(trade) = self.trades.pop_front() else {
+            return;
+        };
+        let price = bucket_price(self.bucket, trade.price);
+        if let Some(level) = self.levels.get_mut(&price) {
+            level.add(trade.aggressor, -trade.qty);
+            if level.total() <= Decimal::ZERO {
+                self.levels.remove(&price);
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.trades.clear();
+        self.levels.clear();
+    }
+
+    // Volume by bucket price, lowest first
+    pub fn levels(&self) -> &BTreeMap<Decimal, ProfileLevel> {
+        &self.levels
+    }
+
+    // Trades currently in the window
+    pub fn trades(&self) -> usize {
+        self.trades.len()
+    }
+
+    // The bucket with the most volume, the profile's point of control
+    pub fn point_of_control(&self) -> Option<(Decimal, ProfileLevel)> {
+        self.levels.iter().max_by_key(|(_, level)| level.total()).map(|(&price, &level)| (price, level))
+    }
+}
+
+fn bucket_price(bucket: Option<Decimal>, price: Decimal) -> Decimal {
+    match bucket {
+        Some(bucket) => (price / bucket).floor() * bucket,
+        None => price,
+    }
+}
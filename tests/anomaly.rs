@@ -0,0 +1,96 @@
+use multi_exchange_l3_est::anomaly::{AnomalyKind, AnomalyMonitor, AnomalySettings};
+use multi_exchange_l3_est::exchanges::{DepthUpdate, OrderBookSnapshot, PriceLevel};
+use multi_exchange_l3_est::feed::FeedMessage;
+use multi_exchange_l3_est::orderbook::OrderBook;
+use rust_decimal::dec;
+
+fn update(id: u64, bids: Vec<PriceLevel>, asks: Vec<PriceLevel>) -> DepthUpdate {
+    DepthUpdate {
+        event_time: 0,
+        transaction_time: 0,
+        symbol: String::new(),
+        capital_u: id,
+        small_u: id,
+        pu: id as i64 - 1,
+        bids,
+        asks,
+        recv_time: 0,
+    }
+}
+
+// Runs one second of `messages` messages and returns the kinds of anomaly it raised
+fn second(monitor: &mut AnomalyMonitor, book: &OrderBook, time: u64, messages: usize) -> Vec<AnomalyKind> {
+    for _ in 0..messages {
+        monitor.record(&FeedMessage::Resync);
+        monitor.record(&FeedMessage::Update(update(0, Vec::new(), Vec::new())));
+    }
+    monitor.sample(book, time * 1000).iter().map(|a| a.kind).collect()
+}
+
+#[test]
+fn rate_spread_and_depth_anomalies_stand_out_from_their_baselines() {
+    let settings = AnomalySettings {
+        enabled: true,
+        window_secs: 60,
+        warmup_secs: 10,
+        cooldown_secs: 5,
+        ..AnomalySettings::default()
+    };
+    let mut monitor = AnomalyMonitor::new(settings);
+    let mut book = OrderBook::new();
+    book.apply_snapshot(&OrderBookSnapshot {
+        last_update_id: 1,
+        event_time: 0,
+        recv_time: 0,
+        bids: vec![PriceLevel::new(dec!(99.99), dec!(50)), PriceLevel::new(dec!(99.98), dec!(50))],
+        asks: vec![PriceLevel::new(dec!(100.01), dec!(50)), PriceLevel::new(dec!(100.02), dec!(50))],
+    });
+    // The first sample only starts the clock; the baselines then warm up without alerting
+    assert!(second(&mut monitor, &book, 1000, 500).is_empty());
+    for t in 1001..1020 {
+        assert!(second(&mut monitor, &book, t, 100 + t as usize % 3).is_empty(), "second {t}");
+    }
+    // Two seconds without a message, then a return to normal
+    assert_eq!(second(&mut monitor, &book, 1021, 0), [AnomalyKind::RateDrop]);
+    assert!(second(&mut monitor, &book, 1022, 100).is_empty());
+    // Ten times the usual traffic, then the cooldown holds back a repeat
+    assert_eq!(second(&mut monitor, &book, 1023, 1000), [AnomalyKind::RateSpike]);
+    assert!(second(&mut monitor, &book, 1024, 1000).is_empty());
+
+    // The touch empties out: the spread goes from 2 bps to 10 and 30 of the 200 resting are left
+    book.handle_update(update(
+        2,
+        vec![
+            PriceLevel::new(dec!(99.99), dec!(0)),
+            PriceLevel::new(dec!(99.98), dec!(0)),
+            PriceLevel::new(dec!(99.97), dec!(10)),
+        ],
+        vec![
+            PriceLevel::new(dec!(100.01), dec!(0)),
+            PriceLevel::new(dec!(100.02), dec!(0)),
+            PriceLevel::new(dec!(100.07), dec!(20)),
+        ],
+    ));
+    let kinds = second(&mut monitor, &book, 1025, 100);
+    assert_eq!(kinds, [AnomalyKind::SpreadWidening, AnomalyKind::DepthEvaporation]);
+    let anomalies: Vec<_> = monitor.log().iter().map(|a| (a.kind, a.time)).collect();
+    assert_eq!(
+        anomalies,
+        [
+            (AnomalyKind::RateDrop, 1_021_000),
+            (AnomalyKind::RateSpike, 1_023_000),
+            (AnomalyKind::SpreadWidening, 1_025_000),
+            (AnomalyKind::DepthEvaporation, 1_025_000),
+        ]
+    );
+    let spread = monitor.log()[2];
+    assert!((spread.baseline - 2.0).abs() < 0.01 && (spread.value - 10.0).abs() < 0.01, "{spread:?}");
+    assert!(spread.describe().starts_with("Spread widened to 10.00 bps"));
+
+    // Turning the monitor off forgets the baselines, so it warms up again
+    monitor.settings.enabled = false;
+    assert!(second(&mut monitor, &book, 1026, 0).is_empty());
+    monitor.settings.enabled = true;
+    assert!(second(&mut monitor, &book, 1027, 0).is_empty());
+    assert!(second(&mut monitor, &book, 1028, 5000).is_empty());
+}
@@ -0,0 +1,85 @@
+use multi_exchange_l3_est::archive::{ArchiveOptions, ArchivedBook, Archiver};
+use multi_exchange_l3_est::exchanges::{DepthUpdate, ExchangeType, OrderBookSnapshot, PriceLevel};
+use multi_exchange_l3_est::orderbook::OrderBook;
+use rust_decimal::dec;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("l3_archive_{name}_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+// Archive files under `dir`, oldest first
+fn archived(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for instrument in std::fs::read_dir(dir).unwrap() {
+        for day in std::fs::read_dir(instrument.unwrap().path()).unwrap() {
+            files.extend(std::fs::read_dir(day.unwrap().path()).unwrap().map(|f| f.unwrap().path()));
+        }
+    }
+    files.sort();
+    files
+}
+
+fn update(id: u64, bids: Vec<PriceLevel>, asks: Vec<PriceLevel>) -> DepthUpdate {
+    DepthUpdate {
+        event_time: 0,
+        transaction_time: 0,
+        symbol: String::new(),
+        capital_u: id,
+        small_u: id,
+        pu: id as i64 - 1,
+        bids,
+        asks,
+        recv_time: 0,
+    }
+}
+
+#[test]
+fn archives_rotate_to_the_file_cap_across_runs() {
+    let dir = scratch("rotate");
+    let exchange = ExchangeType::ALL[0];
+    let options = ArchiveOptions { interval: Duration::ZERO, max_files: Some(2), ..ArchiveOptions::new(dir.clone()) };
+    let mut book = OrderBook::new();
+    let mut archiver = Archiver::start(options.clone(), exchange, "DOGE/USDT").unwrap();
+    // Nothing to archive before the book is synced
+    archiver.sample(&book);
+    book.apply_snapshot(&OrderBookSnapshot {
+        last_update_id: 1,
+        event_time: 0,
+        recv_time: 0,
+        bids: vec![PriceLevel::new(dec!(100), dec!(5))],
+        asks: vec![PriceLevel::new(dec!(101), dec!(4))],
+    });
+    book.handle_update(update(2, vec![PriceLevel::new(dec!(100), dec!(8))], Vec::new()));
+    archiver.sample(&book);
+    book.handle_update(update(3, vec![PriceLevel::new(dec!(99), dec!(1))], Vec::new()));
+    archiver.sample(&book);
+    book.handle_update(update(4, Vec::new(), vec![PriceLevel::new(dec!(101), dec!(6))]));
+    archiver.sample(&book);
+    archiver.close();
+    assert_eq!((archiver.written(), archiver.dropped()), (3, 0));
+
+    let files = archived(&dir);
+    assert_eq!(files.len(), 2);
+    assert!(files[0].starts_with(dir.join(format!("{}_doge_usdt", exchange.name().to_lowercase()))));
+    let latest = ArchivedBook::read(&files[1]).unwrap();
+    assert_eq!(latest.last_update_id, 4);
+    assert_eq!(latest.symbol, "DOGE/USDT");
+    let bids: Vec<_> = latest.bids.iter().map(|l| (l.price, l.quantity, l.orders.clone())).collect();
+    assert_eq!(bids, [(dec!(100), dec!(8), vec![dec!(5), dec!(3)]), (dec!(99), dec!(1), vec![dec!(1)])]);
+    assert_eq!(latest.asks[0].orders, [dec!(4), dec!(2)]);
+
+    // A later run counts the files already there
+    std::thread::sleep(Duration::from_millis(5));
+    let mut archiver =
+        Archiver::start(ArchiveOptions { max_files: Some(1), ..options }, exchange, "DOGE/USDT").unwrap();
+    archiver.sample(&book);
+    archiver.close();
+    let remaining = archived(&dir);
+    assert_eq!(remaining.len(), 1);
+    assert!(remaining[0] > files[1]);
+    let _ = std::fs::remove_dir_all(&dir);
+}
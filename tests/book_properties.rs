@@ -0,0 +1,537 @@
+use multi_exchange_l3_est::exchanges::{DepthUpdate, OrderBookSnapshot, PriceLevel, Side, Trade};
+use multi_exchange_l3_est::flow::{self, OrderFlow};
+use multi_exchange_l3_est::orderbook::{
+    CountSplit, Decomposition, EstimatorSettings, InferredAction, Inference, OrderBook, OrderQueue,
+};
+use multi_exchange_l3_est::simulator::{OrderFlowSimulator, SimulatorConfig};
+use proptest::prelude::*;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use std::collections::{BTreeMap, HashSet};
+
+// Ticks 1..=60 of 0.5, sizes in tenths; the generated feed never crosses
+const MAX_TICK: u32 = 60;
+
+// (is_bid, tick, qty in tenths); a zero quantity removes the level
+type Change = (bool, u32, u32);
+
+fn price(tick: u32) -> Decimal {
+    Decimal::new(tick as i64 * 5, 1)
+}
+
+fn change() -> impl Strategy<Value = Change> {
+    (any::<bool>(), 1..=MAX_TICK, prop_oneof![1 => Just(0u32), 4 => 1..200u32])
+}
+
+// Batches of level changes, each delivered as one depth update
+fn batches() -> impl Strategy<Value = Vec<Vec<Change>>> {
+    prop::collection::vec(prop::collection::vec(change(), 1..6), 1..60)
+}
+
+// The L2 book a venue would publish: what the reconstructed book must add up to
+#[derive(Clone, Default)]
+struct Feed {
+    bids: BTreeMap<Decimal, Decimal>,
+    asks: BTreeMap<Decimal, Decimal>,
+    id: u64,
+}
+
+impl Feed {
+    // Starts from resting levels below and above the middle tick
+    fn new(levels: &[(u32, u32)]) -> Self {
+        let mut feed = Feed::default();
+        for &(tick, qty) in levels.iter().filter(|(_, qty)| *qty > 0) {
+            let side = if tick <= MAX_TICK / 2 { &mut feed.bids } else { &mut feed.asks };
+            side.insert(price(tick), Decimal::new(qty as i64, 1));
+        }
+        feed.id = 1;
+        feed
+    }
+
+    fn snapshot(&self) -> OrderBookSnapshot {
+        let levels = |side: &BTreeMap<Decimal, Decimal>| side.iter().map(|(&p, &q)| PriceLevel::new(p, q)).collect();
+        OrderBookSnapshot {
+            last_update_id: self.id,
+            event_time: 0,
+            recv_time: 0,
+            bids: levels(&self.bids),
+            asks: levels(&self.asks),
+        }
+    }
+
+    // Applies the changes that keep the book uncrossed and returns them as the next update
+    fn update(&mut self, changes: &[Change]) -> DepthUpdate {
+        self.apply(changes, false)
+    }
+
+    // Like `update`, but also keeps the changes that cross, as a feed that missed removals would
+    fn apply(&mut self, changes: &[Change], allow_crossing: bool) -> DepthUpdate {
+        let (mut bids, mut asks) = (vec![], vec![]);
+        for &(is_bid, tick, qty) in changes {
+            let (price, qty) = (price(tick), Decimal::new(qty as i64, 1));
+            let crosses = if is_bid {
+                self.asks.keys().next().is_some_and(|&ask| price >= ask)
+            } else {
+                self.bids.keys().next_back().is_some_and(|&bid| price <= bid)
+            };
+            if crosses && !qty.is_zero() && !allow_crossing {
+                continue;
+            }
+            let (side, levels) = if is_bid { (&mut self.bids, &mut bids) } else { (&mut self.asks, &mut asks) };
+            if qty.is_zero() {
+                side.remove(&price);
+            } else {
+                side.insert(price, qty);
+            }
+            levels.push(PriceLevel::new(price, qty));
+        }
+        self.id += 1;
+        DepthUpdate {
+            event_time: 0,
+            transaction_time: 0,
+            symbol: String::new(),
+            capital_u: self.id,
+            small_u: self.id,
+            pu: self.id as i64 - 1,
+            bids,
+            asks,
+            recv_time: 0,
+        }
+    }
+}
+
+fn initial_levels() -> impl Strategy<Value = Vec<(u32, u32)>> {
+    prop::collection::vec((1..=MAX_TICK, 0..200u32), 0..40)
+}
+
+// Levels match the feed, queues add up to their level and the book is not crossed
+fn check_book(
+    book: &OrderBook,
+    bids: &BTreeMap<Decimal, Decimal>,
+    asks: &BTreeMap<Decimal, Decimal>,
+) -> Result<(), TestCaseError> {
+    if let (Some(bid), Some(ask)) = (book.best_bid(), book.best_ask()) {
+        prop_assert!(bid < ask, "crossed book: bid {} >= ask {}", bid, ask);
+    }
+    let mut ids = HashSet::new();
+    for (side, levels, expected) in [(Side::Bid, &book.bids, bids), (Side::Ask, &book.asks, asks)] {
+        let totals: BTreeMap<Decimal, Decimal> = levels.iter().map(|(&p, q)| (p, q.total())).collect();
+        prop_assert_eq!(&totals, expected);
+        for (&price, &qty) in expected {
+            prop_assert_eq!(book.level_total(side, price), qty);
+        }
+        for queue in levels.values() {
+            prop_assert!(!queue.is_empty());
+            prop_assert_eq!(queue.sizes().sum::<Decimal>(), queue.total());
+            for order in queue {
+                prop_assert!(order.size() > Decimal::ZERO, "order {} has size {}", order.id, order.size());
+                prop_assert!(ids.insert(order.id), "order id {} used twice", order.id);
+            }
+        }
+    }
+    Ok(())
+}
+
+// Grouped level totals: bids round down to the bucket, asks up
+fn grouped(levels: &BTreeMap<Decimal, Decimal>, bucket: Decimal, bids: bool) -> BTreeMap<Decimal, Decimal> {
+    let mut groups = BTreeMap::new();
+    for (&price, &qty) in levels {
+        let key = if bids { (price / bucket).floor() } else { (price / bucket).ceil() } * bucket;
+        *groups.entry(key).or_insert(Decimal::ZERO) += qty;
+    }
+    groups
+}
+
+fn totals(levels: &BTreeMap<Decimal, OrderQueue>) -> BTreeMap<Decimal, Decimal> {
+    levels.iter().map(|(&p, q)| (p, q.total())).collect()
+}
+
+proptest! {
+    #[test]
+    fn diffs_reconstruct_the_feed(levels in initial_levels(), batches in batches()) {
+        let mut feed = Feed::new(&levels);
+        let mut book = OrderBook::new();
+        book.apply_snapshot(&feed.snapshot());
+        check_book(&book, &feed.bids, &feed.asks)?;
+        for batch in &batches {
+            book.handle_update(feed.update(batch));
+            check_book(&book, &feed.bids, &feed.asks)?;
+        }
+    }
+
+    #[test]
+    fn coalesced_bursts_match_single_updates(
+        levels in initial_levels(),
+        batches in batches(),
+        burst in 1..8usize,
+    ) {
+        let mut feed = Feed::new(&levels);
+        let mut book = OrderBook::new();
+        book.apply_snapshot(&feed.snapshot());
+        for (i, batch) in batches.iter().enumerate() {
+            book.queue_update(feed.update(batch));
+            if (i + 1) % burst == 0 {
+                book.flush_updates();
+                check_book(&book, &feed.bids, &feed.asks)?;
+            }
+        }
+        book.flush_updates();
+        check_book(&book, &feed.bids, &feed.asks)?;
+    }
+
+    // After a resync, updates arriving before the snapshot are buffered; those the snapshot
+    // already contains are skipped and the rest replayed on top of it
+    #[test]
+    fn resync_bridges_buffered_updates_onto_the_snapshot(
+        levels in initial_levels(),
+        batches in batches(),
+        snapshot_at in any::<prop::sample::Index>(),
+        replay_from in any::<prop::sample::Index>(),
+    ) {
+        let mut feed = Feed::new(&levels);
+        let mut book = OrderBook::new();
+        book.apply_snapshot(&feed.snapshot());
+        let updates: Vec<(DepthUpdate, Feed)> = batches
+            .iter()
+            .map(|batch| (feed.update(batch), feed.clone()))
+            .collect();
+        let snapshot_at = snapshot_at.index(updates.len());
+        // The new stream starts at or before the update following the snapshot
+        let replay_from = replay_from.index(snapshot_at + 1);
+        book.resync();
+        for (update, _) in &updates[replay_from..] {
+            book.handle_update(update.clone());
+        }
+        prop_assert!(!book.is_synced());
+        book.apply_snapshot(&updates[snapshot_at].1.snapshot());
+        check_book(&book, &feed.bids, &feed.asks)?;
+    }
+
+    #[test]
+    fn grouped_view_adds_up_the_raw_levels(
+        levels in initial_levels(),
+        batches in batches(),
+        bucket in prop_oneof![Just(Decimal::new(5, 1)), Just(Decimal::ONE), Just(Decimal::new(25, 1))],
+    ) {
+        let mut feed = Feed::new(&levels);
+        let mut book = OrderBook::new();
+        book.set_grouping(Some(bucket));
+        book.apply_snapshot(&feed.snapshot());
+        for batch in &batches {
+            book.handle_update(feed.update(batch));
+            prop_assert_eq!(totals(book.view_bids()), grouped(&feed.bids, bucket, true));
+            prop_assert_eq!(totals(book.view_asks()), grouped(&feed.asks, bucket, false));
+        }
+    }
+
+    // Every change the estimator makes to a level shows up in the event log
+    #[test]
+    fn inferred_events_account_for_every_level_change(levels in initial_levels(), batches in batches()) {
+        let mut feed = Feed::new(&levels);
+        let mut book = OrderBook::new();
+        book.set_event_log(true);
+        book.apply_snapshot(&feed.snapshot());
+        for batch in &batches {
+            let (bids, asks) = (totals(&book.bids), totals(&book.asks));
+            book.handle_update(feed.update(batch));
+            let mut changes: BTreeMap<(bool, Decimal), Decimal> = BTreeMap::new();
+            for event in book.drain_events() {
+                prop_assert_eq!(event.size.is_zero(), event.action == InferredAction::Removed);
+                prop_assert_eq!(event.change > Decimal::ZERO, event.action == InferredAction::Joined);
+                *changes.entry((event.side == Side::Bid, event.price)).or_default() += event.change;
+            }
+            for (is_bid, before, after) in [(true, &bids, &feed.bids), (false, &asks, &feed.asks)] {
+                for price in before.keys().chain(after.keys()) {
+                    let level = |levels: &BTreeMap<Decimal, Decimal>| levels.get(price).copied().unwrap_or_default();
+                    let change = changes.get(&(is_bid, *price)).copied().unwrap_or_default();
+                    prop_assert_eq!(change, level(after) - level(before), "level {}", price);
+                }
+            }
+        }
+    }
+
+    // Order-flow statistics count every inferred event once, and without trades nothing fills
+    #[test]
+    fn flow_stats_count_every_inferred_event(levels in initial_levels(), batches in batches()) {
+        let mut feed = Feed::new(&levels);
+        let mut book = OrderBook::new();
+        let mut flow = OrderFlow::new(3600);
+        book.set_event_log(true);
+        book.apply_snapshot(&feed.snapshot());
+        let (mut joined, mut joined_size, mut left) = (0, Decimal::ZERO, 0);
+        for batch in &batches {
+            book.handle_update(feed.update(batch));
+            let (best_bid, best_ask) = (book.best_bid(), book.best_ask());
+            for event in book.drain_events() {
+                if event.action == InferredAction::Joined {
+                    joined += 1;
+                    joined_size += event.change;
+                } else {
+                    left += 1;
+                }
+                flow.record(&event, best_bid, best_ask);
+            }
+        }
+        let stats = flow.stats();
+        prop_assert_eq!((stats.adds, stats.cancels, stats.fills), (joined, left, 0));
+        prop_assert!(stats.touch_fill_rate.is_none_or(|rate| rate == 0.0));
+        prop_assert_eq!(stats.mean_bid_size.is_some() || stats.mean_ask_size.is_some(), joined > 0);
+        prop_assert!(stats.mean_bid_size.into_iter().chain(stats.mean_ask_size).all(|size| size <= joined_size));
+        // Nothing traded, so joining the touch has no chance of a fill
+        prop_assert_eq!((stats.bid_traded_per_sec, stats.ask_traded_per_sec), (0.0, 0.0));
+        if let Some(bid) = book.best_bid() {
+            let value = flow::queue_value(&book, Side::Bid, bid, &stats);
+            prop_assert!(value.is_none_or(|value| value.time_to_fill.is_none() && value.value_bps() == 0.0));
+        }
+    }
+
+    // Trades printed before a level shrinks fill the front of its queue; whatever they claim,
+    // the queues still add up to the feed
+    #[test]
+    fn trades_fill_from_the_front_of_the_queue(
+        levels in initial_levels(),
+        batches in batches(),
+        trades in prop::collection::vec(change(), 60),
+    ) {
+        let mut feed = Feed::new(&levels);
+        let mut book = OrderBook::new();
+        book.set_event_log(true);
+        book.apply_snapshot(&feed.snapshot());
+        for (batch, &(buy, tick, qty)) in batches.iter().zip(&trades) {
+            book.apply_trade(&Trade {
+                trade_id: 0,
+                price: price(tick),
+                qty: Decimal::new(qty as i64, 1),
+                aggressor: if buy { Side::Bid } else { Side::Ask },
+                timestamp: 0,
+            });
+            book.handle_update(feed.update(batch));
+            check_book(&book, &feed.bids, &feed.asks)?;
+            let filled: Vec<_> = book.drain_events().filter(|e| e.action == InferredAction::Filled).collect();
+            for event in filled {
+                let levels = if event.side == Side::Bid { &book.bids } else { &book.asks };
+                let order = levels.get(&event.price).and_then(|q| q.iter().find(|o| o.id == event.order_id));
+                prop_assert!(order.is_none_or(|o| o.inference >= Inference::TradeAttributed));
+            }
+        }
+    }
+
+    #[test]
+    fn full_states_are_tracked_exactly(states in prop::collection::vec(initial_levels(), 1..30)) {
+        let mut book = OrderBook::new();
+        for (i, levels) in states.iter().enumerate() {
+            let mut feed = Feed::new(levels);
+            feed.id = i as u64 + 1;
+            book.apply_full_state(&feed.snapshot());
+            check_book(&book, &feed.bids, &feed.asks)?;
+        }
+    }
+
+    // Levels with a reported order count hold that many estimated orders (as many as their lots
+    // allow) adding up to the level, through snapshots and later full states, whichever way they
+    // are split; the even split leaves snapshot levels within a lot of equal
+    #[test]
+    fn reported_counts_shape_the_queues(
+        states in prop::collection::vec((initial_levels(), prop::collection::vec(1..8u32, 60)), 1..20),
+        count_split in prop::sample::select(CountSplit::ALL.to_vec()),
+    ) {
+        let mut book = OrderBook::new();
+        book.set_estimator(EstimatorSettings {
+            count_split,
+            ..EstimatorSettings::default()
+        });
+        // Sizes step by a tenth, so a level has that many lots to split
+        book.set_qty_decimals(1);
+        for (i, (levels, counts)) in states.iter().enumerate() {
+            let mut state = Feed::new(levels).snapshot();
+            for (level, &count) in state.bids.iter_mut().chain(&mut state.asks).zip(counts) {
+                level.order_count = Some(count);
+            }
+            book.apply_full_state(&state);
+            for level in state.bids.iter().chain(&state.asks) {
+                let queue = book.bids.get(&level.price).or_else(|| book.asks.get(&level.price));
+                let queue = queue.expect("every state level is in the book");
+                let lots = (level.qty * Decimal::TEN).to_u32().unwrap_or(0);
+                let count = level.order_count.unwrap_or(1);
+                prop_assert_eq!(queue.total(), level.qty);
+                prop_assert_eq!(queue.reported_count(), Some(count));
+                prop_assert_eq!(queue.len() as u32, count.min(lots), "level {}", level.price);
+                if i == 0 && count_split == CountSplit::MaxEntropy {
+                    let sizes = || queue.sizes();
+                    let spread = sizes().max().unwrap_or_default() - sizes().min().unwrap_or_default();
+                    prop_assert!(spread <= Decimal::new(1, 1), "level {} split unevenly", level.price);
+                }
+            }
+        }
+    }
+
+    // The first snapshot seeds the size prior from its own levels: a counted level adds its count
+    // of samples, and the smaller half of the uncounted levels one each
+    #[test]
+    fn snapshot_bootstraps_the_size_prior(
+        levels in initial_levels(),
+        counts in prop::collection::vec(prop::option::of(1..8u32), 60),
+    ) {
+        let mut book = OrderBook::new();
+        book.set_estimator(EstimatorSettings {
+            decomposition: Decomposition::PowerLaw,
+            ..EstimatorSettings::default()
+        });
+        book.set_qty_decimals(1);
+        let feed = Feed::new(&levels);
+        let mut snapshot = feed.snapshot();
+        for (level, &count) in snapshot.bids.iter_mut().chain(&mut snapshot.asks).zip(&counts) {
+            level.order_count = count;
+        }
+        book.apply_snapshot(&snapshot);
+        check_book(&book, &feed.bids, &feed.asks)?;
+        let all = || snapshot.bids.iter().chain(&snapshot.asks);
+        let counted: u64 = all().filter_map(|level| level.order_count).map(u64::from).sum();
+        let uncounted = all().filter(|level| level.order_count.is_none()).count() as u64;
+        prop_assert_eq!(book.size_prior().samples(), counted + uncounted.div_ceil(2));
+        for level in all().filter(|level| level.order_count.is_none()) {
+            let queue = book.bids.get(&level.price).or_else(|| book.asks.get(&level.price)).unwrap();
+            let decomposed = queue.len() > 1;
+            prop_assert!(queue.iter().all(|order| (order.inference == Inference::Prior) == decomposed));
+        }
+    }
+
+    // Crossing updates never leave the book crossed: the stale side's crossing levels are
+    // dropped, and the rest still match the feed
+    #[test]
+    fn crossed_books_are_pruned(levels in initial_levels(), batches in batches()) {
+        let mut feed = Feed::new(&levels);
+        let mut book = OrderBook::new();
+        book.apply_snapshot(&feed.snapshot());
+        let mut crossings = 0;
+        for batch in &batches {
+            book.handle_update(feed.apply(batch, true));
+            if book.crossings() > crossings {
+                crossings = book.crossings();
+                // The feed forgets the pruned levels too, as a venue's removals would
+                feed.bids.retain(|price, _| book.bids.contains_key(price));
+                feed.asks.retain(|price, _| book.asks.contains_key(price));
+            }
+            check_book(&book, &feed.bids, &feed.asks)?;
+        }
+        prop_assert_eq!(book.needs_resync(), crossings >= 3);
+    }
+
+    // Diffs keep at most `depth` levels per side, each matching the feed (a trimmed level only
+    // returns with its next update); full states keep exactly the best `depth`
+    #[test]
+    fn depth_limit_keeps_levels_near_the_touch(
+        levels in initial_levels(),
+        batches in batches(),
+        depth in 1..10usize,
+    ) {
+        let mut feed = Feed::new(&levels);
+        let mut book = OrderBook::new();
+        book.set_max_depth(Some(depth));
+        book.apply_snapshot(&feed.snapshot());
+        for batch in &batches {
+            book.handle_update(feed.update(batch));
+            for (levels, expected) in [(&book.bids, &feed.bids), (&book.asks, &feed.asks)] {
+                prop_assert!(levels.len() <= depth);
+                for (price, queue) in levels {
+                    prop_assert_eq!(Some(&queue.total()), expected.get(price));
+                }
+            }
+        }
+        let mut full = OrderBook::new();
+        full.set_max_depth(Some(depth));
+        full.apply_full_state(&feed.snapshot());
+        full.apply_full_state(&feed.snapshot());
+        let best_bids: BTreeMap<_, _> = feed.bids.iter().rev().take(depth).map(|(&p, &q)| (p, q)).collect();
+        let best_asks: BTreeMap<_, _> = feed.asks.iter().take(depth).map(|(&p, &q)| (p, q)).collect();
+        check_book(&full, &best_bids, &best_asks)?;
+    }
+
+    // Liquidity bands add up the feed's levels within each distance of the mid, and the VWAPs
+    // fill the notional between the touch and the deepest level they reach
+    #[test]
+    fn liquidity_bands_and_vwaps_match_the_feed(
+        levels in initial_levels(),
+        batches in batches(),
+        notional in 1..400u32,
+    ) {
+        let mut feed = Feed::new(&levels);
+        let mut book = OrderBook::new();
+        book.set_vwap_notional(Decimal::from(notional));
+        book.apply_snapshot(&feed.snapshot());
+        for batch in &batches {
+            book.handle_update(feed.update(batch));
+            let Some(metrics) = book.metrics() else {
+                continue;
+            };
+            let mid = metrics.mid;
+            for band in &metrics.liquidity {
+                let offset = mid * Decimal::from(band.bps) / Decimal::from(10_000);
+                let bid: Decimal = feed.bids.range(mid - offset..).map(|(_, q)| q).sum();
+                let ask: Decimal = feed.asks.range(..=mid + offset).map(|(_, q)| q).sum();
+                prop_assert_eq!((band.bid, band.ask), (bid, ask));
+            }
+            let notional = Decimal::from(notional);
+            let depth = |side: &BTreeMap<Decimal, Decimal>| side.iter().map(|(p, q)| p * q).sum::<Decimal>();
+            prop_assert_eq!(metrics.buy_vwap.is_some(), depth(&feed.asks) >= notional);
+            prop_assert_eq!(metrics.sell_vwap.is_some(), depth(&feed.bids) >= notional);
+            if let Some(vwap) = metrics.buy_vwap {
+                prop_assert!(vwap >= *feed.asks.keys().next().unwrap() - Decimal::new(1, 20));
+            }
+            if let Some(vwap) = metrics.sell_vwap {
+                prop_assert!(vwap <= *feed.bids.keys().next_back().unwrap() + Decimal::new(1, 20));
+            }
+        }
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(24))]
+
+    // The simulator's diffs keep the book equal to its aggregated snapshot, whatever the flow
+    #[test]
+    fn simulated_order_flow_reconstructs_the_snapshot(seed in any::<u64>(), events in 1..3000usize) {
+        let mut sim = OrderFlowSimulator::new(SimulatorConfig {
+            seed,
+            initial_orders: 100,
+            ..SimulatorConfig::default()
+        });
+        let mut book = OrderBook::new();
+        book.apply_snapshot(&sim.snapshot());
+        for _ in 0..events {
+            book.handle_update(sim.step().update);
+        }
+        let snapshot = sim.snapshot();
+        let levels = |side: &[PriceLevel]| side.iter().map(|l| (l.price, l.qty)).collect::<BTreeMap<_, _>>();
+        check_book(&book, &levels(&snapshot.bids), &levels(&snapshot.asks))?;
+        prop_assert_eq!(book.last_update_id(), snapshot.last_update_id);
+    }
+    // Decomposing levels by the size prior, at the snapshot or once the prior is ready, keeps every
+    // level's total
+    #[test]
+    fn size_prior_decomposition_keeps_the_levels(seed in any::<u64>(), events in 1..3000usize) {
+        let mut sim = OrderFlowSimulator::new(SimulatorConfig {
+            seed,
+            initial_orders: 100,
+            ..SimulatorConfig::default()
+        });
+        let mut book = OrderBook::new();
+        book.set_estimator(EstimatorSettings {
+            decomposition: Decomposition::PowerLaw,
+            ..EstimatorSettings::default()
+        });
+        book.apply_snapshot(&sim.snapshot());
+        let levels = |side: &[PriceLevel]| side.iter().map(|l| (l.price, l.qty)).collect::<BTreeMap<_, _>>();
+        for i in 0..events {
+            book.handle_update(sim.step().update);
+            if i % 500 == 0 {
+                // A resync loads the snapshot with the prior already fitted
+                let snapshot = sim.snapshot();
+                book.apply_snapshot(&snapshot);
+                check_book(&book, &levels(&snapshot.bids), &levels(&snapshot.asks))?;
+            }
+        }
+        let snapshot = sim.snapshot();
+        check_book(&book, &levels(&snapshot.bids), &levels(&snapshot.asks))?;
+    }
+}
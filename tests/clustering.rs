@@ -0,0 +1,153 @@
+use multi_exchange_l3_est::clustering::{
+    ClusterFeatures, ClusterInput, ClusterSummary, Clusterer, KMeansInit, MiniBatchKMeans, QuantileBands,
+};
+use multi_exchange_l3_est::exchanges::{InstrumentInfo, SizeUnit};
+use rust_decimal::dec;
+use rust_decimal::Decimal;
+
+#[test]
+fn cluster_summaries_total_size_and_notional_per_label() {
+    let mut input = ClusterInput::new();
+    input.push_level(dec!(100), [dec!(1), dec!(0), dec!(10)]);
+    input.push_level(dec!(99), [dec!(3), dec!(12)]);
+    // One label per positive order, as a fit returns them; label 2 was left empty
+    let summaries = input.summarize(&[0, 1, 0, 1], 3);
+    assert_eq!(
+        summaries,
+        [
+            ClusterSummary { orders: 2, total_qty: dec!(4), notional: dec!(397) },
+            ClusterSummary { orders: 2, total_qty: dec!(22), notional: dec!(2188) },
+            ClusterSummary::default(),
+        ]
+    );
+    assert_eq!(summaries[0].centroid_size(), Some(dec!(2)));
+    assert_eq!(summaries[2].centroid_size(), None);
+}
+
+#[test]
+fn auto_k_picks_the_cluster_count_by_silhouette() {
+    // Four tight groups of sizes, decades apart
+    let mut input = ClusterInput::new();
+    for (i, base) in [1, 10, 100, 1000].into_iter().enumerate() {
+        let base = Decimal::from(base);
+        input.push_level(Decimal::from(100 - i), [base, base * dec!(1.05), base * dec!(1.1), base * dec!(0.95)]);
+    }
+    let mut kmeans = MiniBatchKMeans::new(10, 64, 64, ClusterFeatures::default(), true).auto_k(true);
+    let labels = kmeans.fit(&input).to_vec();
+    assert_eq!(kmeans.num_clusters(), 4);
+    // Each group is one cluster, ordered by size
+    assert_eq!(labels, [0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3]);
+
+    // Without it the given count stands
+    let mut kmeans = MiniBatchKMeans::new(10, 64, 64, ClusterFeatures::default(), true);
+    kmeans.fit(&input);
+    assert_eq!(kmeans.num_clusters(), 10);
+}
+
+#[test]
+fn quantile_bands_follow_the_window_of_observed_sizes() {
+    let mut input = ClusterInput::new();
+    input.push_level(dec!(100), (1..=8).map(Decimal::from));
+    let mut bands = QuantileBands::new(4);
+    assert_eq!(bands.fit(&input), [0, 0, 1, 1, 2, 2, 3, 3]);
+    assert_eq!(bands.edges(), [3.0, 5.0, 7.0]);
+    assert_eq!(bands.num_clusters(), 4);
+
+    // Refitting the same book changes nothing
+    assert_eq!(bands.fit(&input), [0, 0, 1, 1, 2, 2, 3, 3]);
+
+    // Larger orders shift the edges up, so the first book's orders fall into lower bands
+    let mut larger = ClusterInput::new();
+    larger.push_level(dec!(100), (9..=16).map(Decimal::from));
+    bands.fit(&larger);
+    bands.fit(&larger);
+    assert_eq!(bands.fit(&input), [0, 0, 0, 1, 1, 1, 2, 2]);
+    assert_eq!(bands.edges(), [4.0, 7.0, 12.0]);
+}
+
+#[test]
+fn seeded_kmeans_repeats_its_fits() {
+    // Sizes spread evenly, where the sampled mini-batches decide where the centroids settle
+    let mut input = ClusterInput::new();
+    for level in 0..20 {
+        input.push_level(Decimal::from(100 + level), (1..=25).map(|i| Decimal::from(i * (level + 1))));
+    }
+    let fits = |seed| {
+        let mut kmeans = MiniBatchKMeans::new(6, 16, 8, ClusterFeatures::default(), true).seed(seed);
+        (0..3).map(|_| kmeans.fit(&input).to_vec()).collect::<Vec<_>>()
+    };
+    assert_eq!(fits(7), fits(7));
+}
+
+#[test]
+fn kmeans_reseeds_centroids_that_capture_nothing() {
+    // The evenly spaced seeds land two centroids on the same small size, one of which never wins
+    // a point
+    let mut input = ClusterInput::new();
+    input.push_level(dec!(100), std::iter::repeat_n(dec!(1), 100));
+    input.push_level(dec!(101), [dec!(1000), dec!(2000)]);
+    let distinct = |labels: &[usize]| labels.iter().collect::<std::collections::BTreeSet<_>>().len();
+
+    let mut kmeans = MiniBatchKMeans::new(3, 64, 64, ClusterFeatures::default(), true).seed(1);
+    assert_eq!(distinct(kmeans.fit(&input)), 3);
+
+    let mut kmeans = MiniBatchKMeans::new(3, 64, 64, ClusterFeatures::default(), false).seed(1);
+    assert_eq!(distinct(kmeans.fit(&input)), 2);
+}
+
+#[test]
+fn plus_plus_init_spreads_centroids_over_a_skewed_tail() {
+    let mut input = ClusterInput::new();
+    input.push_level(dec!(100), std::iter::repeat_n(dec!(1), 200));
+    input.push_level(dec!(101), [dec!(1000), dec!(1000), dec!(100000), dec!(100000)]);
+    let distinct = |labels: &[usize]| labels.iter().collect::<std::collections::BTreeSet<_>>().len();
+    // No iterations, so the labels are those of the initial centroids
+    let kmeans = || MiniBatchKMeans::new(3, 64, 0, ClusterFeatures::default(), false).seed(3);
+
+    // Evenly spaced ranks put two of the three centroids on the small orders
+    assert_eq!(distinct(kmeans().fit(&input)), 2);
+    assert_eq!(distinct(kmeans().init(KMeansInit::PlusPlus).fit(&input)), 3);
+}
+
+#[test]
+fn sides_appended_into_one_input_measure_distance_from_their_own_touch() {
+    let mut asks = ClusterInput::new();
+    asks.push_level(dec!(101), [dec!(1), dec!(2)]);
+    asks.push_level(dec!(105), [dec!(50)]);
+    let mut bids = ClusterInput::new();
+    bids.push_level(dec!(100), [dec!(1), dec!(2)]);
+    bids.push_level(dec!(96), [dec!(50)]);
+    let mut both = ClusterInput::new();
+    both.append(&asks);
+    both.append(&bids);
+    assert_eq!(both.len(), 6);
+    assert_eq!(both.levels().map(|(price, _)| price).collect::<Vec<_>>(), [dec!(101), dec!(105), dec!(100), dec!(96)]);
+
+    // Mirror images of each other, so one fit labels both sides alike
+    let features = ClusterFeatures {
+        log_qty: true,
+        price_distance: true,
+    };
+    let mut kmeans = MiniBatchKMeans::new(2, 16, 16, features, true).seed(1);
+    let labels = kmeans.fit(&both);
+    assert_eq!(labels[..3], labels[3..]);
+    assert_eq!(labels[..3], [0, 0, 1]);
+}
+
+#[test]
+fn cluster_summaries_value_contracts_by_the_given_notional() {
+    // Inverse contracts of 100 USD each
+    let contract = InstrumentInfo::inverse(dec!(100));
+    let mut input = ClusterInput::new();
+    input.push_level(dec!(50000), [dec!(2), dec!(30)]);
+    input.push_level(dec!(49000), [dec!(5)]);
+    let notional = |price, qty| contract.convert(qty, price, SizeUnit::Native, SizeUnit::Notional);
+    let summaries = input.summarize_with(&[0, 1, 0], 2, notional);
+    assert_eq!(
+        summaries,
+        [
+            ClusterSummary { orders: 2, total_qty: dec!(7), notional: dec!(700) },
+            ClusterSummary { orders: 1, total_qty: dec!(30), notional: dec!(3000) },
+        ]
+    );
+}
@@ -0,0 +1,45 @@
+use multi_exchange_l3_est::ffi::*;
+use std::ffi::c_void;
+use std::ptr;
+
+unsafe extern "C" fn collect(event: *const L3Event, user_data: *mut c_void) {
+    let events = &mut *(user_data as *mut Vec<L3Event>);
+    events.push(*event);
+}
+
+#[test]
+fn c_abi_drives_the_book_and_reports_events() {
+    let mut events: Vec<L3Event> = Vec::new();
+    unsafe {
+        let book = l3_book_new();
+        l3_book_set_event_callback(book, Some(collect), &mut events as *mut _ as *mut c_void);
+
+        let bids = [L3Level { price: 100.0, quantity: 5.0 }];
+        let asks = [L3Level { price: 100.1, quantity: 4.0 }];
+        assert_eq!(
+            l3_book_apply_update(book, 11, 11, bids.as_ptr(), 1, ptr::null(), 0),
+            L3_BUFFERED
+        );
+        assert_eq!(l3_book_apply_snapshot(book, 10, bids.as_ptr(), 1, asks.as_ptr(), 1), L3_APPLIED);
+
+        let grown = [L3Level { price: 100.0, quantity: 8.0 }];
+        assert_eq!(l3_book_apply_update(book, 12, 12, grown.as_ptr(), 1, ptr::null(), 0), L3_APPLIED);
+        assert_eq!(l3_book_apply_update(book, 9, 9, grown.as_ptr(), 1, ptr::null(), 0), L3_SKIPPED);
+        assert_eq!(l3_book_apply_update(ptr::null_mut(), 13, 13, ptr::null(), 0, ptr::null(), 0), L3_INVALID);
+
+        let mut levels = [L3LevelInfo::default(); 4];
+        assert_eq!(l3_book_levels(book, L3_BID, levels.as_mut_ptr(), levels.len()), 1);
+        assert_eq!((levels[0].price, levels[0].quantity, levels[0].orders), (100.0, 8.0, 2));
+        assert_eq!(l3_book_levels(book, L3_ASK, levels.as_mut_ptr(), levels.len()), 1);
+        assert_eq!(levels[0].price, 100.1);
+
+        let mut sizes = [0.0; 4];
+        assert_eq!(l3_book_orders(book, L3_BID, 100.0, sizes.as_mut_ptr(), sizes.len()), 2);
+        assert_eq!(&sizes[..2], &[5.0, 3.0]);
+        assert_eq!(l3_book_orders(book, L3_BID, 99.0, sizes.as_mut_ptr(), sizes.len()), 0);
+
+        l3_book_free(book);
+    }
+    let joined: Vec<_> = events.iter().map(|e| (e.side, e.action, e.price, e.size, e.change)).collect();
+    assert_eq!(joined, [(L3_BID, L3_JOINED, 100.0, 3.0, 3.0)]);
+}
@@ -0,0 +1,78 @@
+use multi_exchange_l3_est::exchanges::{DepthUpdate, ExchangeType, OrderBookSnapshot, PriceLevel};
+use multi_exchange_l3_est::grpc::proto::l3_book_client::L3BookClient;
+use multi_exchange_l3_est::grpc::proto::{inferred_order, GetSnapshotRequest, Side, SubscribeInferredOrdersRequest};
+use multi_exchange_l3_est::grpc::GrpcServer;
+use multi_exchange_l3_est::orderbook::OrderBook;
+use multi_exchange_l3_est::server::BookMessage;
+use multi_exchange_l3_est::session::SessionStats;
+use rust_decimal::dec;
+use std::time::Duration;
+
+fn book() -> OrderBook {
+    let mut book = OrderBook::new();
+    book.set_event_log(true);
+    book.apply_snapshot(&OrderBookSnapshot {
+        last_update_id: 7,
+        event_time: 0,
+        recv_time: 0,
+        bids: vec![PriceLevel::new(dec!(99), dec!(2)), PriceLevel::new(dec!(98), dec!(1))],
+        asks: vec![PriceLevel::new(dec!(101), dec!(3))],
+    });
+    book
+}
+
+#[test]
+fn snapshot_and_inferred_orders_are_served() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let addr = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+    let server = GrpcServer::start(runtime.handle(), addr, ExchangeType::Mock, "mock").unwrap();
+    let mut book = book();
+    let mut client = runtime.block_on(async {
+        let url = format!("http://{addr}");
+        for _ in 0..50 {
+            if let Ok(client) = L3BookClient::connect(url.clone()).await {
+                return client;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("gRPC server did not come up on {addr}");
+    });
+
+    let empty = runtime.block_on(client.get_snapshot(GetSnapshotRequest { depth: 0 }));
+    assert_eq!(empty.unwrap_err().code(), tonic::Code::Unavailable);
+
+    server.publish(&BookMessage::from_book(&book, ExchangeType::Mock, "mock", 10, SessionStats::default()));
+    let state = runtime.block_on(client.get_snapshot(GetSnapshotRequest { depth: 1 })).unwrap().into_inner();
+    assert_eq!((state.exchange.as_str(), state.last_update_id, state.synced), ("Mock", 7, true));
+    assert_eq!((state.bids.len(), state.asks.len()), (1, 1));
+    assert_eq!((state.bids[0].price.as_str(), state.bids[0].orders.len()), ("99", 1));
+    assert_eq!(state.mid.as_deref(), Some("100"));
+
+    let mut orders = runtime
+        .block_on(client.subscribe_inferred_orders(SubscribeInferredOrdersRequest {}))
+        .unwrap()
+        .into_inner();
+    // A second order of 3 joins the best bid
+    book.handle_update(DepthUpdate {
+        event_time: 0,
+        transaction_time: 0,
+        symbol: String::new(),
+        capital_u: 8,
+        small_u: 8,
+        pu: 7,
+        bids: vec![PriceLevel::new(dec!(99), dec!(5))],
+        asks: vec![],
+        recv_time: 0,
+    });
+    for event in book.drain_events() {
+        server.publish_order(&event);
+    }
+    let order = runtime
+        .block_on(async { tokio::time::timeout(Duration::from_secs(5), orders.message()).await })
+        .expect("an inferred order")
+        .unwrap()
+        .unwrap();
+    assert_eq!(order.side(), Side::Bid);
+    assert_eq!(order.action(), inferred_order::Action::Joined);
+    assert_eq!((order.price.as_str(), order.size.as_str()), ("99", "3"));
+}
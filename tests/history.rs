@@ -0,0 +1,54 @@
+use multi_exchange_l3_est::exchanges::{PriceLevel, Side};
+use multi_exchange_l3_est::history::{BookFrame, BookHistory, HistorySettings, LevelDelta};
+use rust_decimal::dec;
+use rust_decimal::Decimal;
+use std::time::Duration;
+
+fn frame(timestamp: u64, bids: &[(Decimal, Decimal)], asks: &[(Decimal, Decimal)]) -> BookFrame {
+    let levels = |levels: &[(Decimal, Decimal)]| levels.iter().map(|&(p, q)| PriceLevel::new(p, q)).collect();
+    BookFrame {
+        timestamp,
+        mid: None,
+        bids: levels(bids),
+        asks: levels(asks),
+    }
+}
+
+fn delta(side: Side, price: Decimal, change: Decimal) -> LevelDelta {
+    LevelDelta { side, price, change }
+}
+
+#[test]
+fn level_deltas_compare_the_depth_both_samples_cover() {
+    let mut history = BookHistory::new(HistorySettings::default());
+    history.push(frame(
+        1_000,
+        &[(dec!(100), dec!(5)), (dec!(99), dec!(3)), (dec!(98), dec!(7))],
+        &[(dec!(101), dec!(4)), (dec!(102), dec!(6))],
+    ));
+    history.push(frame(5_000, &[(dec!(100), dec!(6))], &[(dec!(101), dec!(4))]));
+    // The price ticked up and 98 was pulled. 97 lies past the first sample's depth and 102 past
+    // the last one's, so neither is compared.
+    history.push(frame(
+        11_000,
+        &[(dec!(100.5), dec!(2)), (dec!(100), dec!(1)), (dec!(99), dec!(3)), (dec!(97), dec!(9))],
+        &[(dec!(101), dec!(1)), (dec!(101.5), dec!(8))],
+    ));
+
+    let (span, deltas) = history.deltas(None, Duration::from_secs(10)).unwrap();
+    assert_eq!(span, 10_000);
+    assert_eq!(
+        deltas,
+        [
+            delta(Side::Bid, dec!(100.5), dec!(2)),
+            delta(Side::Bid, dec!(100), dec!(-4)),
+            delta(Side::Bid, dec!(98), dec!(-7)),
+            delta(Side::Ask, dec!(101), dec!(-3)),
+            delta(Side::Ask, dec!(101.5), dec!(8)),
+        ]
+    );
+    // Up to a paused moment, and over more than the retained history
+    let (span, deltas) = history.deltas(Some(6_000), Duration::from_secs(60)).unwrap();
+    assert_eq!(span, 4_000);
+    assert_eq!(deltas, [delta(Side::Bid, dec!(100), dec!(1))]);
+}
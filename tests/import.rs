@@ -0,0 +1,176 @@
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use multi_exchange_l3_est::exchanges::{ExchangeMessage, ExchangeSettings, ExchangeType};
+use multi_exchange_l3_est::export::{ExportFormat, ExportOptions};
+use multi_exchange_l3_est::import::{self, HistoricalFeed, ImportOptions};
+use rust_decimal::dec;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+// A fresh directory per test under the system temp dir
+fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("l3_import_{name}_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+const T0: u64 = 1_700_000_000_000;
+
+// Tardis `incremental_book_L2`: a snapshot, then one update of two levels and one of a single
+// level, with a row of another instrument mixed in
+fn tardis_book() -> String {
+    let us = |ms: u64| ms * 1000;
+    let mut csv = String::from("exchange,symbol,timestamp,local_timestamp,is_snapshot,side,price,amount\n");
+    for (side, price, amount) in [("bid", "100", "5"), ("bid", "99.5", "2"), ("ask", "100.5", "4")] {
+        csv += &format!("binance-futures,BTCUSDT,{},{},true,{side},{price},{amount}\n", us(T0), us(T0));
+    }
+    csv += &format!("binance-futures,BTCUSDT,{},{},false,bid,100,8\n", us(T0 + 900), us(T0 + 1000));
+    csv += &format!("binance-futures,BTCUSDT,{},{},false,ask,100.5,1\n", us(T0 + 900), us(T0 + 1000));
+    csv += &format!("binance-futures,ETHUSDT,{},{},false,ask,2000,1\n", us(T0 + 1500), us(T0 + 1500));
+    csv += &format!("binance-futures,BTCUSDT,{},{},false,bid,99.5,0\n", us(T0 + 2900), us(T0 + 3000));
+    csv
+}
+
+#[test]
+fn tardis_csvs_replay_through_the_estimator_at_data_time() {
+    let dir = scratch("tardis");
+    let book = dir.join("binance-futures_incremental_book_L2_2023-11-14_BTCUSDT.csv.gz");
+    let mut gz = GzEncoder::new(std::fs::File::create(&book).unwrap(), Compression::default());
+    gz.write_all(tardis_book().as_bytes()).unwrap();
+    gz.finish().unwrap();
+    // A buy lifting 3 off the ask at 100.5, just before the update that shows it
+    let trades = dir.join("binance-futures_trades_2023-11-14_BTCUSDT.csv");
+    std::fs::write(
+        &trades,
+        format!(
+            "exchange,symbol,timestamp,local_timestamp,id,side,price,amount\n\
+             binance-futures,BTCUSDT,{},{},42,buy,100.5,3\n",
+            (T0 + 800) * 1000,
+            (T0 + 950) * 1000
+        ),
+    )
+    .unwrap();
+
+    let options = ImportOptions {
+        paths: vec![dir.clone()],
+        symbol: Some("btcusdt".to_string()),
+    };
+    let feed = HistoricalFeed::open(&options).unwrap();
+    assert_eq!(feed.venue(), Some("binance-futures"));
+    let messages: Vec<_> = feed.map(Result::unwrap).collect();
+    let kinds: Vec<_> = messages
+        .iter()
+        .map(|m| match &m.message {
+            ExchangeMessage::Snapshot(s) => format!("snapshot {} {}/{}", m.time, s.bids.len(), s.asks.len()),
+            ExchangeMessage::Update(u) => format!("update {} {}/{}", m.time, u.bids.len(), u.asks.len()),
+            ExchangeMessage::Trade(t) => format!("trade {} {}", m.time, t.trade_id),
+            _ => "other".to_string(),
+        })
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            format!("snapshot {T0} 2/1"),
+            format!("trade {} 42", T0 + 950),
+            format!("update {} 1/1", T0 + 1000),
+            format!("update {} 1/0", T0 + 3000),
+        ]
+    );
+
+    let export = dir.join("replay.csv");
+    let summary = import::run(
+        &options,
+        None,
+        &ExchangeSettings::default(),
+        Some(ExportOptions {
+            path: export.clone(),
+            format: ExportFormat::Csv,
+            interval: Duration::from_secs(1),
+            levels: 2,
+        }),
+    )
+    .unwrap();
+    assert_eq!((summary.snapshots, summary.updates, summary.trades), (1, 2, 1));
+    assert_eq!(summary.skipped_rows, 1);
+    assert_eq!((summary.first_time, summary.last_time), (Some(T0), Some(T0 + 3000)));
+    // Bid 100 grew by 3, the traded ask was filled, bid 99.5 went away
+    assert_eq!(summary.events, [1, 1, 0, 1]);
+    assert_eq!(summary.flow.fills, 1);
+
+    // Rows are stamped with the data's time, one per second of it
+    let rows = std::fs::read_to_string(&export).unwrap();
+    let stamps: Vec<&str> = rows.lines().skip(1).map(|row| row.split(',').next().unwrap()).collect();
+    assert_eq!(stamps, [T0.to_string(), (T0 + 1000).to_string(), (T0 + 3000).to_string()]);
+    assert!(rows.lines().nth(1).unwrap().contains(",Binance,BTCUSDT,"));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn tardis_ndjson_and_crypto_lake_csvs_are_read() {
+    let dir = scratch("formats");
+    let ndjson = dir.join("bitmex.ndjson");
+    std::fs::write(
+        &ndjson,
+        concat!(
+            r#"{"type":"book_change","symbol":"XBTUSD","exchange":"bitmex","isSnapshot":true,"bids":[{"price":0.1,"amount":10}],"asks":[{"price":0.2,"amount":5}],"timestamp":"2023-11-14T22:13:20.000Z","localTimestamp":"2023-11-14T22:13:20.005Z"}"#,
+            "\n",
+            r#"{"type":"quote","symbol":"XBTUSD"}"#,
+            "\n",
+            r#"{"type":"book_change","symbol":"XBTUSD","exchange":"bitmex","isSnapshot":false,"bids":[{"price":0.1,"amount":12}],"asks":[],"timestamp":"2023-11-14T22:13:21.000Z","localTimestamp":"2023-11-14T22:13:21.005Z"}"#,
+            "\n",
+            r#"{"type":"trade","symbol":"XBTUSD","exchange":"bitmex","id":"7","price":0.2,"amount":1,"side":"buy","timestamp":"2023-11-14T22:13:22Z","localTimestamp":"2023-11-14T22:13:22Z"}"#,
+            "\n"
+        ),
+    )
+    .unwrap();
+    let messages: Vec<_> = HistoricalFeed::open(&ImportOptions {
+        paths: vec![ndjson],
+        symbol: None,
+    })
+    .unwrap()
+    .map(Result::unwrap)
+    .collect();
+    assert_eq!(messages.len(), 3);
+    assert_eq!(messages[0].time, T0 + 5);
+    let ExchangeMessage::Update(update) = &messages[1].message else {
+        panic!("expected an update, got {:?}", messages[1].message);
+    };
+    assert_eq!((update.bids[0].price, update.bids[0].qty), (dec!(0.1), dec!(12)));
+    assert!(matches!(&messages[2].message, ExchangeMessage::Trade(t) if t.trade_id == 7));
+
+    // No snapshot flag: the deltas build the book from empty
+    let lake = dir.join("book_delta_v2.csv");
+    std::fs::write(
+        &lake,
+        "received_time,origin_time,sequence_number,side_is_bid,price,size\n\
+         2023-11-14 22:13:20.100,2023-11-14 22:13:20.000,1,true,100,5\n\
+         2023-11-14 22:13:20.100,2023-11-14 22:13:20.000,1,false,101,4\n\
+         2023-11-14 22:13:21.100,2023-11-14 22:13:21.000,2,True,100,7\n",
+    )
+    .unwrap();
+    let options = ImportOptions {
+        paths: vec![lake],
+        symbol: None,
+    };
+    let messages: Vec<_> = HistoricalFeed::open(&options).unwrap().map(Result::unwrap).collect();
+    assert!(matches!(&messages[0].message, ExchangeMessage::Snapshot(s) if s.bids.is_empty() && s.asks.is_empty()));
+    assert_eq!(messages.len(), 3);
+    // Without a venue in the file the output needs a label
+    assert!(import::run(&options, None, &ExchangeSettings::default(), None).is_err());
+    let summary = import::run(&options, Some(ExchangeType::ALL[0]), &ExchangeSettings::default(), None).unwrap();
+    // Two levels joined, then a second order behind the first at 100
+    assert_eq!(summary.events, [3, 0, 0, 0]);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn timestamps_are_read_in_any_unit() {
+    for value in ["1700000000", "1700000000000", "1700000000000000", "1700000000000000000", "2023-11-14T22:13:20Z"] {
+        assert_eq!(import::parse_time(value), Some(T0), "{value}");
+    }
+    assert_eq!(import::venue_exchange("gate-io-futures").map(|e| e.name()), Some("Gate.io"));
+    assert_eq!(import::venue_exchange("BINANCE_FUTURES"), Some(ExchangeType::Binance));
+    assert_eq!(import::venue_exchange("okex"), None);
+}
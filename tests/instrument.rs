@@ -0,0 +1,31 @@
+use multi_exchange_l3_est::exchanges::{InstrumentInfo, SizeUnit};
+use rust_decimal::dec;
+use rust_decimal::Decimal;
+
+#[test]
+fn linear_contracts_convert_by_their_multiplier() {
+    // Gate.io BTC_USDT: 0.0001 BTC per contract
+    let contract = InstrumentInfo::linear(dec!(0.0001));
+    assert_eq!(contract.size_in(SizeUnit::Native, dec!(500), dec!(60000)), dec!(500));
+    assert_eq!(contract.size_in(SizeUnit::Base, dec!(500), dec!(60000)), dec!(0.05));
+    assert_eq!(contract.size_in(SizeUnit::Notional, dec!(500), dec!(60000)), dec!(3000));
+    assert_eq!(contract.convert(dec!(3000), dec!(60000), SizeUnit::Notional, SizeUnit::Native), dec!(500));
+    assert_eq!(contract.decimals(SizeUnit::Base, 0), 4);
+
+    // Spot sizes are already in the base asset
+    let spot = InstrumentInfo::BASE;
+    assert_eq!(spot.size_in(SizeUnit::Base, dec!(1.5), dec!(2000)), dec!(1.5));
+    assert_eq!(spot.size_in(SizeUnit::Notional, dec!(1.5), dec!(2000)), dec!(3000));
+}
+
+#[test]
+fn inverse_contracts_are_worth_a_fixed_quote_amount() {
+    // Binance COIN-M BTCUSD_PERP: 100 USD per contract
+    let contract = InstrumentInfo::inverse(dec!(100));
+    assert_eq!(contract.size_in(SizeUnit::Notional, dec!(30), dec!(50000)), dec!(3000));
+    assert_eq!(contract.size_in(SizeUnit::Base, dec!(30), dec!(50000)), dec!(0.06));
+    assert_eq!(contract.convert(dec!(0.06), dec!(50000), SizeUnit::Base, SizeUnit::Notional), dec!(3000));
+    // Base is undefined without a price
+    assert_eq!(contract.size_in(SizeUnit::Base, dec!(30), Decimal::ZERO), Decimal::ZERO);
+    assert_eq!(contract.convert(dec!(1), Decimal::ZERO, SizeUnit::Base, SizeUnit::Native), Decimal::ZERO);
+}
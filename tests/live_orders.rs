@@ -0,0 +1,83 @@
+use multi_exchange_l3_est::exchanges::{OrderBookSnapshot, OrderEvent, OrderEventKind, PriceLevel, Side};
+use multi_exchange_l3_est::live_orders::{QueueCheck, QueueCheckStats};
+use multi_exchange_l3_est::orderbook::OrderBook;
+use rust_decimal::dec;
+use rust_decimal::Decimal;
+
+fn event(kind: OrderEventKind, order_id: u64, side: Side, price: Decimal, qty: Decimal) -> OrderEvent {
+    OrderEvent { kind, order_id, side, price, qty, timestamp: order_id }
+}
+
+fn queue(book: &OrderBook, side: Side, price: Decimal) -> Vec<(u64, Decimal)> {
+    book.live_orders().queue(side, price).iter().map(|order| (order.order_id, order.qty)).collect()
+}
+
+#[test]
+fn order_events_build_the_venue_queues_in_priority_order() {
+    let mut book = OrderBook::new();
+    for (id, qty) in [(1, dec!(2)), (2, dec!(5)), (3, dec!(1))] {
+        book.apply_order_event(&event(OrderEventKind::Created, id, Side::Bid, dec!(100), qty));
+    }
+    book.apply_order_event(&event(OrderEventKind::Created, 4, Side::Ask, dec!(101), dec!(3)));
+    // A partial fill keeps its place, a move goes to the back of the new level
+    book.apply_order_event(&event(OrderEventKind::Changed, 1, Side::Bid, dec!(100), dec!(1.5)));
+    book.apply_order_event(&event(OrderEventKind::Changed, 2, Side::Bid, dec!(99), dec!(5)));
+    book.apply_order_event(&event(OrderEventKind::Created, 5, Side::Bid, dec!(99), dec!(4)));
+    book.apply_order_event(&event(OrderEventKind::Deleted, 4, Side::Ask, dec!(101), dec!(3)));
+
+    assert_eq!(queue(&book, Side::Bid, dec!(100)), [(1, dec!(1.5)), (3, dec!(1))]);
+    assert_eq!(queue(&book, Side::Bid, dec!(99)), [(2, dec!(5)), (5, dec!(4))]);
+    assert!(queue(&book, Side::Ask, dec!(101)).is_empty());
+    assert_eq!(book.live_orders().len(), 4);
+    let prices: Vec<Decimal> = book.live_orders().levels(Side::Bid).map(|(price, _)| price).collect();
+    assert_eq!(prices, [dec!(100), dec!(99)]);
+
+    // A (first) full state leaves the orders already streamed in place
+    book.apply_snapshot(&OrderBookSnapshot {
+        last_update_id: 1,
+        event_time: 0,
+        recv_time: 0,
+        bids: vec![PriceLevel::new(dec!(100), dec!(2.5)), PriceLevel::new(dec!(99), dec!(9))],
+        asks: vec![],
+    });
+    assert_eq!(book.live_orders().len(), 4);
+
+    // Events were missed while the stream was down
+    book.resync();
+    assert!(book.live_orders().is_empty());
+}
+
+#[test]
+fn queue_checks_compare_the_estimate_on_fully_known_levels() {
+    let mut book = OrderBook::new();
+    book.apply_snapshot(&OrderBookSnapshot {
+        last_update_id: 1,
+        event_time: 0,
+        recv_time: 0,
+        bids: vec![PriceLevel::new(dec!(100), dec!(3)), PriceLevel::new(dec!(99), dec!(5))],
+        asks: vec![PriceLevel::new(dec!(101), dec!(4))],
+    });
+    // Each snapshot level is estimated as one order
+    book.apply_order_event(&event(OrderEventKind::Created, 1, Side::Bid, dec!(100), dec!(1)));
+    book.apply_order_event(&event(OrderEventKind::Created, 2, Side::Bid, dec!(100), dec!(2)));
+    book.apply_order_event(&event(OrderEventKind::Created, 3, Side::Bid, dec!(99), dec!(5)));
+    // Only part of the ask level is known
+    book.apply_order_event(&event(OrderEventKind::Created, 4, Side::Ask, dec!(101), dec!(1)));
+
+    let check = QueueCheck::compare(&book);
+    assert_eq!(check.unknown, 1);
+    assert_eq!(check.levels.len(), 2);
+    assert_eq!(check.exact(), 1);
+    let worst = check.worst(1)[0];
+    assert_eq!((worst.price, worst.estimated, worst.venue), (dec!(100), 1, 2));
+    // |3 - 1| at the front, and the venue's second order missing from the estimate
+    assert_eq!(worst.size_error, dec!(4));
+    assert_eq!(check.mean_count_error(), 0.5);
+    assert_eq!(check.size_error_ratio(), 0.5);
+
+    let mut stats = QueueCheckStats::default();
+    stats.record(check);
+    stats.record(QueueCheck::default());
+    assert_eq!(stats.checks, 1);
+    assert_eq!(stats.exact_ratio(), 0.5);
+}
@@ -0,0 +1,412 @@
+use multi_exchange_l3_est::audit::{AuditStats, BookDrift};
+use multi_exchange_l3_est::exchanges::mock::{MockConnection, MockExchange, MockScript};
+use multi_exchange_l3_est::exchanges::{
+    Exchange, ExchangeError, ExchangeMessage, ExchangeSettings, ExchangeType, InstrumentInfo, PriceLevel,
+};
+use multi_exchange_l3_est::feed::{FeedMessage, FeedWorker};
+use multi_exchange_l3_est::logging::{self, LogSettings};
+use multi_exchange_l3_est::orderbook::{OrderBook, UpdateOutcome};
+use multi_exchange_l3_est::publish::{BookEvent, EventBody, SCHEMA_VERSION};
+use multi_exchange_l3_est::session::SessionTracker;
+use rust_decimal::{dec, Decimal};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+const WAIT: Duration = Duration::from_secs(5);
+
+// Compact description of a feed message, so expected sequences read as one list
+fn describe(message: &FeedMessage) -> String {
+    match message {
+        FeedMessage::Snapshot(snapshot) => format!("snapshot {}", snapshot.last_update_id),
+        FeedMessage::Update(update) => format!("update {}", update.small_u),
+        FeedMessage::FullState(state) => format!("state {}", state.last_update_id),
+        FeedMessage::Order(_) => "order".to_string(),
+        FeedMessage::Trade(_) => "trade".to_string(),
+        FeedMessage::InstrumentStats(_) => "stats".to_string(),
+        FeedMessage::Precision(price, qty) => format!("precision {price} {qty}"),
+        FeedMessage::Instrument(instrument) => {
+            format!("instrument {}{}", instrument.multiplier, if instrument.inverse { " inverse" } else { "" })
+        }
+        FeedMessage::Status(status) => status.label().to_lowercase(),
+        FeedMessage::Error(e) => format!("error {}", e.kind()),
+        FeedMessage::Resync => "resync".to_string(),
+        FeedMessage::Overflow(dropped) => format!("overflow {dropped}"),
+        FeedMessage::Audit(snapshot) => format!("audit {}", snapshot.last_update_id),
+    }
+}
+
+fn settings(script: MockScript) -> ExchangeSettings {
+    ExchangeSettings {
+        stale_timeout: Duration::from_millis(300),
+        backoff_base: Duration::from_millis(10),
+        backoff_max: Duration::from_millis(20),
+        mock: script,
+        ..ExchangeSettings::default()
+    }
+}
+
+fn start(settings: ExchangeSettings) -> (FeedWorker, UnboundedReceiver<FeedMessage>) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let sink = Arc::new(move |message| {
+        let _ = tx.send(message);
+    });
+    let worker = FeedWorker::spawn(
+        &tokio::runtime::Handle::current(),
+        sink,
+        "mock".to_string(),
+        ExchangeType::Mock,
+        settings,
+    );
+    (worker, rx)
+}
+
+// Descriptions of the next `count` feed messages
+async fn next(rx: &mut UnboundedReceiver<FeedMessage>, count: usize) -> Vec<String> {
+    let mut seen = Vec::with_capacity(count);
+    for _ in 0..count {
+        match tokio::time::timeout(WAIT, rx.recv()).await {
+            Ok(Some(message)) => seen.push(describe(&message)),
+            _ => panic!("feed went quiet after {seen:?}"),
+        }
+    }
+    seen
+}
+
+// Every message of one connection, until its stream closes
+async fn replay(connection: MockConnection) -> Vec<ExchangeMessage> {
+    let exchange = MockExchange::new(MockScript::new().connection(connection));
+    let mut rx = exchange.connect("mock").await.expect("scripted connection");
+    let mut messages = vec![];
+    while let Some(message) = tokio::time::timeout(WAIT, rx.recv()).await.expect("stream did not close") {
+        messages.push(message);
+    }
+    messages
+}
+
+fn apply(book: &mut OrderBook, message: ExchangeMessage) -> Option<UpdateOutcome> {
+    match message {
+        ExchangeMessage::Snapshot(snapshot) => Some(book.apply_snapshot(&snapshot)),
+        ExchangeMessage::Update(update) => Some(book.handle_update(update)),
+        ExchangeMessage::FullState(state) => Some(book.apply_full_state(&state)),
+        _ => None,
+    }
+}
+
+fn sizes(book: &OrderBook, price: Decimal) -> Vec<Decimal> {
+    book.bids.get(&price).map(|queue| queue.sizes().collect()).unwrap_or_default()
+}
+
+#[tokio::test]
+async fn estimator_splits_level_changes_into_orders() {
+    let connection = MockConnection::new()
+        .snapshot(10, &[(dec!(100), dec!(5))], &[(dec!(101), dec!(4))])
+        .update(11, 11, &[(dec!(100), dec!(8))], &[])
+        .update(12, 12, &[(dec!(100), dec!(10))], &[])
+        // 3 leaves: exactly the order added by update 11
+        .update(13, 13, &[(dec!(100), dec!(7))], &[])
+        // No order of 4, so the largest shrinks and moves to the back
+        .update(14, 14, &[(dec!(100), dec!(3))], &[(dec!(101), dec!(0))]);
+    let mut book = OrderBook::new();
+    for message in replay(connection).await {
+        assert_eq!(apply(&mut book, message), Some(UpdateOutcome::Applied));
+    }
+    assert_eq!(sizes(&book, dec!(100)), vec![dec!(2), dec!(1)]);
+    assert_eq!(book.best_ask(), None);
+    assert_eq!(book.last_update_id(), 14);
+}
+
+#[tokio::test]
+async fn published_events_follow_the_schema() {
+    let connection = MockConnection::new()
+        .snapshot(10, &[(dec!(100), dec!(5))], &[])
+        .update(11, 11, &[(dec!(100), dec!(8))], &[]);
+    let mut book = OrderBook::new();
+    book.set_event_log(true);
+    let mut bodies = vec![];
+    for message in replay(connection).await {
+        match &message {
+            ExchangeMessage::Snapshot(snapshot) => bodies.push(EventBody::snapshot(snapshot)),
+            ExchangeMessage::Update(update) => bodies.push(EventBody::diff(update)),
+            _ => {}
+        }
+        apply(&mut book, message);
+    }
+    // The order joining at 100 on top of the snapshot's
+    bodies.extend(book.drain_events().map(|event| EventBody::order(&event)).last());
+    let json: Vec<serde_json::Value> = bodies
+        .into_iter()
+        .map(|body| {
+            let event = BookEvent { version: SCHEMA_VERSION, exchange: "Mock", symbol: "mock", time: 1, body };
+            serde_json::to_value(&event).unwrap()
+        })
+        .collect();
+    assert_eq!(
+        json[0],
+        serde_json::json!({
+            "version": 1, "exchange": "Mock", "symbol": "mock", "time": 1, "type": "snapshot",
+            "last_update_id": 10, "bids": [{ "price": "100", "quantity": "5" }], "asks": [],
+        })
+    );
+    assert_eq!(json[1]["type"], "diff");
+    assert_eq!((&json[1]["first_update_id"], &json[1]["last_update_id"]), (&11.into(), &11.into()));
+    assert_eq!(json[2]["type"], "order");
+    assert_eq!(
+        (&json[2]["side"], &json[2]["action"], &json[2]["size"], &json[2]["change"]),
+        (&"bid".into(), &"joined".into(), &"3".into(), &"3".into())
+    );
+}
+
+#[tokio::test]
+async fn updates_wait_for_the_snapshot_and_stale_ones_are_skipped() {
+    let connection = MockConnection::new()
+        .update(5, 5, &[(dec!(100), dec!(1))], &[])
+        .update(6, 7, &[(dec!(100), dec!(9))], &[])
+        .snapshot(6, &[(dec!(100), dec!(2))], &[])
+        .update(8, 8, &[(dec!(99), dec!(1))], &[])
+        // Reordered: older than what the book already holds
+        .update(7, 7, &[(dec!(99), dec!(5))], &[]);
+    let mut book = OrderBook::new();
+    let outcomes: Vec<_> = replay(connection).await.into_iter().map(|m| apply(&mut book, m)).collect();
+    let expected = [
+        UpdateOutcome::Buffered,
+        UpdateOutcome::Buffered,
+        UpdateOutcome::Applied,
+        UpdateOutcome::Applied,
+        UpdateOutcome::Skipped,
+    ];
+    assert_eq!(outcomes, expected.map(Some));
+    // Update 5 is already in the snapshot, 6..=7 straddles it
+    assert_eq!(sizes(&book, dec!(100)), vec![dec!(2), dec!(7)]);
+    assert_eq!(sizes(&book, dec!(99)), vec![dec!(1)]);
+}
+
+#[tokio::test]
+async fn full_states_are_diffed_into_level_changes() {
+    let connection = MockConnection::new()
+        .full_state(1, &[(dec!(100), dec!(5)), (dec!(99), dec!(2))], &[])
+        // 99 disappears from the state, 100 grows by 1
+        .full_state(2, &[(dec!(100), dec!(6))], &[]);
+    let mut book = OrderBook::new();
+    for message in replay(connection).await {
+        apply(&mut book, message);
+    }
+    assert_eq!(sizes(&book, dec!(100)), vec![dec!(5), dec!(1)]);
+    assert!(sizes(&book, dec!(99)).is_empty());
+}
+
+#[tokio::test]
+async fn malformed_frames_become_parse_errors_without_ending_the_stream() {
+    let connection = MockConnection::new()
+        .raw("{\"event_time\": 1, \"transaction_time\"")
+        .raw(
+            r#"{"event_time": 1, "transaction_time": 1, "symbol": "MOCK", "capital_u": 3, "small_u": 4, "pu": 2,
+                "bids": [["100.5", "2"]], "asks": []}"#,
+        );
+    let messages = replay(connection).await;
+    assert_eq!(messages.len(), 2);
+    assert!(matches!(&messages[0], ExchangeMessage::Error(e) if e.kind() == "parse"));
+    match &messages[1] {
+        ExchangeMessage::Update(update) => {
+            assert_eq!((update.capital_u, update.small_u, update.pu), (3, 4, 2));
+            assert_eq!(update.bids, vec![PriceLevel::new(dec!(100.5), dec!(2))]);
+        }
+        other => panic!("expected an update, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn scripted_delays_hold_back_later_messages() {
+    let exchange = MockExchange::new(
+        MockScript::new().connection(
+            MockConnection::new()
+                .snapshot(1, &[], &[])
+                .delay(Duration::from_millis(200))
+                .update(2, 2, &[], &[]),
+        ),
+    );
+    let mut rx = exchange.connect("mock").await.unwrap();
+    assert!(matches!(rx.recv().await, Some(ExchangeMessage::Snapshot(_))));
+    assert!(tokio::time::timeout(Duration::from_millis(100), rx.recv()).await.is_err());
+    assert!(matches!(rx.recv().await, Some(ExchangeMessage::Update(_))));
+}
+
+#[tokio::test]
+async fn feed_reconnects_and_resyncs_after_the_stream_closes() {
+    let script = MockScript::new()
+        .precision(3, 1)
+        .instrument(InstrumentInfo::inverse(dec!(10)))
+        .connection(MockConnection::new().snapshot(10, &[], &[]).update(11, 11, &[], &[]))
+        .connection(MockConnection::new().snapshot(20, &[], &[]).stall());
+    let (_worker, mut rx) = start(settings(script.clone()));
+    let expected = [
+        "connecting",
+        "connected",
+        "precision 3 1",
+        "instrument 10 inverse",
+        "snapshot 10",
+        "update 11",
+        "disconnected",
+        "connecting",
+        "connected",
+        "resync",
+        "precision 3 1",
+        "instrument 10 inverse",
+        "snapshot 20",
+    ];
+    assert_eq!(next(&mut rx, expected.len()).await, expected);
+    assert_eq!(script.remaining(), 0);
+}
+
+#[tokio::test]
+async fn session_stats_count_reconnects_resyncs_and_dropped_updates() {
+    let script = MockScript::new()
+        .connection(
+            MockConnection::new()
+                .snapshot(10, &[(dec!(100), dec!(1))], &[])
+                .update(11, 11, &[(dec!(100), dec!(2))], &[])
+                // Older than the book
+                .update(9, 9, &[(dec!(100), dec!(5))], &[]),
+        )
+        .connection(MockConnection::new().snapshot(20, &[(dec!(100), dec!(3))], &[]).stall());
+    let (_worker, mut rx) = start(settings(script));
+    let mut session = SessionTracker::new();
+    let mut book = OrderBook::new();
+    // connecting, connected, precision, instrument, snapshot, 2 updates, disconnected, connecting,
+    // connected, resync
+    for _ in 0..11 {
+        let message = tokio::time::timeout(WAIT, rx.recv()).await.unwrap().expect("feed ended");
+        session.record(&message);
+        match message {
+            FeedMessage::Snapshot(snapshot) => {
+                book.apply_snapshot(&snapshot);
+            }
+            FeedMessage::Update(update) => {
+                book.handle_update(update);
+            }
+            FeedMessage::Resync => book.resync(),
+            _ => {}
+        }
+    }
+    let stats = session.stats(&book.counters());
+    assert_eq!((stats.connects, stats.reconnects()), (2, 1));
+    assert_eq!(stats.resyncs, 1);
+    assert_eq!(stats.messages, 11);
+    assert_eq!((stats.updates_applied, stats.updates_dropped), (1, 1));
+    assert!(stats.connected_ratio > 0.0 && stats.connected_ratio <= 1.0);
+}
+
+#[tokio::test]
+async fn feed_logs_carry_the_exchange_and_symbol_span() {
+    logging::init(&LogSettings { console: false, ..LogSettings::default() }).unwrap();
+    let script = MockScript::new().connection(MockConnection::new().snapshot(10, &[], &[]).stall());
+    let (_worker, mut rx) = start(settings(script));
+    let expected = ["connecting", "connected", "precision 2 2", "instrument 1", "snapshot 10"];
+    assert_eq!(next(&mut rx, expected.len()).await, expected);
+    let connected = logging::recent()
+        .into_iter()
+        .find(|record| record.message.starts_with("Connected to Mock"))
+        .expect("connection was logged");
+    assert_eq!(connected.level, tracing::Level::INFO);
+    assert_eq!(connected.spans, "feed{exchange=Mock symbol=mock}");
+}
+
+#[tokio::test]
+async fn feed_fetches_a_rest_snapshot_when_the_stream_has_none() {
+    let script = MockScript::new().connection(
+        MockConnection::new()
+            .rest_snapshot(7, &[(dec!(1), dec!(1))], &[])
+            .update(8, 8, &[], &[])
+            .stall(),
+    );
+    let (_worker, mut rx) = start(settings(script));
+    let expected = ["connecting", "connected", "precision 2 2", "instrument 1", "snapshot 7", "update 8"];
+    assert_eq!(next(&mut rx, expected.len()).await, expected);
+}
+
+#[tokio::test]
+async fn audit_mode_compares_rest_snapshots_with_the_book() {
+    let script = MockScript::new().connection(
+        MockConnection::new()
+            .rest_snapshot(7, &[(dec!(1), dec!(2)), (dec!(0.5), dec!(1))], &[(dec!(2), dec!(3))])
+            .update(8, 8, &[(dec!(1), dec!(1))], &[])
+            .stall(),
+    );
+    let settings = ExchangeSettings {
+        audit_interval: Some(Duration::from_millis(50)),
+        ..settings(script)
+    };
+    let (_worker, mut rx) = start(settings);
+    let mut book = OrderBook::new();
+    let mut audits = AuditStats::default();
+    let mut seen = vec![];
+    while audits.audits < 2 {
+        let message = tokio::time::timeout(WAIT, rx.recv()).await.expect("no audit").expect("feed closed");
+        seen.push(describe(&message));
+        match message {
+            FeedMessage::Snapshot(snapshot) => {
+                book.apply_snapshot(&snapshot);
+            }
+            FeedMessage::Update(update) => {
+                book.handle_update(update);
+            }
+            FeedMessage::Audit(snapshot) => audits.record(BookDrift::compare(&book, &snapshot)),
+            _ => {}
+        }
+    }
+    assert_eq!(seen[..6], ["connecting", "connected", "precision 2 2", "instrument 1", "snapshot 7", "update 8"]);
+    // The update moved the best bid after the snapshot the audits keep returning
+    let drift = audits.last.unwrap();
+    assert_eq!((drift.levels, drift.mismatched, drift.missing, drift.extra), (3, 1, 0, 0));
+    assert_eq!(drift.size_error, dec!(1));
+    assert_eq!(audits.clean, 0);
+}
+
+#[tokio::test]
+async fn stalled_stream_trips_the_watchdog() {
+    let script = MockScript::new()
+        .connection(MockConnection::new().snapshot(1, &[], &[]).stall())
+        .connection(MockConnection::new().snapshot(2, &[], &[]).stall());
+    let (_worker, mut rx) = start(settings(script));
+    let seen = next(&mut rx, 13).await;
+    assert_eq!(seen[5..7], ["stale", "disconnected"]);
+    assert_eq!(seen[12], "snapshot 2");
+}
+
+#[tokio::test]
+async fn refused_connections_are_reported_and_retried() {
+    let script = MockScript::new()
+        .connection(MockConnection::refused())
+        .connection(MockConnection::new().error(ExchangeError::Desync("scripted gap".to_string())))
+        .connection(MockConnection::new().snapshot(3, &[], &[]).stall());
+    let (_worker, mut rx) = start(settings(script));
+    let expected = [
+        "connecting",
+        "error network",
+        "disconnected",
+        // The first connection that succeeds needs no resync
+        "connecting",
+        "connected",
+        "precision 2 2",
+        "instrument 1",
+        "error desync",
+        "disconnected",
+        "connecting",
+        "connected",
+        "resync",
+        "precision 2 2",
+        "instrument 1",
+        "snapshot 3",
+    ];
+    assert_eq!(next(&mut rx, expected.len()).await, expected);
+}
+
+#[tokio::test]
+async fn dropping_the_worker_closes_the_scripted_stream() {
+    let script = MockScript::new().connection(MockConnection::new().snapshot(1, &[], &[]).stall());
+    let (worker, mut rx) = start(settings(script));
+    assert_eq!(next(&mut rx, 5).await[4], "snapshot 1");
+    drop(worker);
+    // The sink is owned by the feed task, so the channel closes once the task is gone
+    assert!(tokio::time::timeout(WAIT, rx.recv()).await.expect("feed task kept running").is_none());
+}
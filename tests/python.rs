@@ -0,0 +1,47 @@
+use multi_exchange_l3_est::python::l3est;
+use pyo3::ffi::c_str;
+use pyo3::prelude::*;
+
+fn run(code: &std::ffi::CStr) {
+    pyo3::append_to_inittab!(l3est);
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| {
+        if let Err(e) = py.run(code, None, None) {
+            e.print(py);
+            panic!("Python code failed");
+        }
+    });
+}
+
+#[test]
+fn python_drives_the_book_and_a_live_feed() {
+    run(c_str!(
+        r#"
+import asyncio
+from decimal import Decimal
+import l3est
+
+book = l3est.OrderBook()
+assert book.apply_snapshot(10, [("100", "5")], [(101, 4)]) == "applied"
+assert book.apply_update(11, 11, [("100", "8")], []) == "applied"
+assert book.best_bid() == Decimal("100") and book.best_ask() == Decimal("101")
+assert book.orders("bid", "100") == [Decimal(5), Decimal(3)]
+assert book.bids(1) == [(Decimal(100), Decimal(8), [Decimal(5), Decimal(3)])]
+events = book.drain_events()
+assert [(e.action, e.side, e.size) for e in events] == [("joined", "bid", Decimal(3))]
+assert book.metrics().mid == Decimal("100.5")
+assert book.apply_update(9, 9, [("100", "1")], []) == "skipped"
+assert "Synthetic" in l3est.exchanges()
+
+async def first_update():
+    feed = l3est.Feed("synthetic", "SYN-USD", depth=5)
+    async for update in feed:
+        feed.close()
+        return update
+
+update = asyncio.run(asyncio.wait_for(first_update(), 10))
+assert update.synced and update.exchange == "Synthetic"
+assert 0 < len(update.bids) <= 5 and update.bids[0][0] < update.asks[0][0]
+"#
+    ));
+}
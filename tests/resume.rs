@@ -0,0 +1,108 @@
+use multi_exchange_l3_est::exchanges::{DepthUpdate, ExchangeType, OrderBookSnapshot, PriceLevel};
+use multi_exchange_l3_est::orderbook::{InferredAction, OrderBook};
+use multi_exchange_l3_est::resume::{ResumeOptions, SavedSession, StateSaver};
+use multi_exchange_l3_est::session::{SessionStats, SessionTracker};
+use rust_decimal::dec;
+
+fn update(id: u64, bids: Vec<PriceLevel>, asks: Vec<PriceLevel>) -> DepthUpdate {
+    DepthUpdate {
+        event_time: 0,
+        transaction_time: 0,
+        symbol: String::new(),
+        capital_u: id,
+        small_u: id,
+        pu: id as i64 - 1,
+        bids,
+        asks,
+        recv_time: 0,
+    }
+}
+
+fn snapshot(id: u64, bids: Vec<PriceLevel>, asks: Vec<PriceLevel>) -> OrderBookSnapshot {
+    OrderBookSnapshot {
+        last_update_id: id,
+        event_time: 0,
+        recv_time: 0,
+        bids,
+        asks,
+    }
+}
+
+#[test]
+fn a_restarted_book_keeps_the_orders_the_fresh_snapshot_still_shows() {
+    let dir = std::env::temp_dir().join(format!("l3_resume_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let exchange = ExchangeType::ALL[0];
+    let options = ResumeOptions::new(dir.clone());
+    let mut saver = StateSaver::new(&options, exchange, "BTC/USDT");
+    assert!(saver.path().ends_with(format!("{}_btc_usdt.json", exchange.name().to_lowercase())));
+
+    let mut book = OrderBook::new();
+    // Nothing is saved before the book is synced
+    saver.save(&book, SessionStats::default()).unwrap();
+    assert!(saver.load().unwrap().is_none());
+    book.set_clock(Some(1000));
+    book.apply_snapshot(&snapshot(
+        1,
+        vec![PriceLevel::new(dec!(100), dec!(5)), PriceLevel::new(dec!(99), dec!(2))],
+        vec![PriceLevel::new(dec!(101), dec!(4))],
+    ));
+    book.set_clock(Some(2000));
+    book.handle_update(update(2, vec![PriceLevel::new(dec!(100), dec!(8))], Vec::new()));
+    let stats = SessionStats {
+        session_secs: 100,
+        connected_ratio: 0.5,
+        connects: 2,
+        messages: 10,
+        updates_applied: 4,
+        mean_apply_us: 10.0,
+        ..SessionStats::default()
+    };
+    saver.save(&book, stats).unwrap();
+    let saved = saver.load().unwrap().expect("a saved state");
+    assert_eq!(saved, SavedSession { saved_at: saved.saved_at, ..SavedSession::new(exchange, "BTC/USDT", &book, stats) });
+    let before: Vec<_> = book.bids[&dec!(100)].iter().map(|o| (o.id, o.first_seen, o.size())).collect();
+
+    // The next run: an update arrives before the snapshot, which shows 99 shrunk and 98 new
+    let mut restarted = OrderBook::new();
+    restarted.set_event_log(true);
+    restarted.set_clock(Some(5000));
+    restarted.handle_update(update(12, Vec::new(), vec![PriceLevel::new(dec!(101), dec!(6))]));
+    restarted.resume(
+        &saved.book,
+        &snapshot(
+            11,
+            vec![
+                PriceLevel::new(dec!(100), dec!(8)),
+                PriceLevel::new(dec!(99), dec!(1)),
+                PriceLevel::new(dec!(98), dec!(2)),
+            ],
+            vec![PriceLevel::new(dec!(101), dec!(4))],
+        ),
+    );
+    assert!(restarted.is_synced());
+    assert_eq!(restarted.last_update_id(), 12);
+    let after: Vec<_> = restarted.bids[&dec!(100)].iter().map(|o| (o.id, o.first_seen, o.size())).collect();
+    assert_eq!(after, before);
+    assert_eq!(restarted.bids[&dec!(99)].sizes().collect::<Vec<_>>(), [dec!(1)]);
+    assert_eq!(restarted.asks[&dec!(101)].sizes().collect::<Vec<_>>(), [dec!(4), dec!(2)]);
+    let joined = restarted.bids[&dec!(98)].iter().next().unwrap();
+    assert!(joined.id > saved.book.last_order_id);
+    assert_eq!(joined.first_seen, 5000);
+    let actions: Vec<_> = restarted.drain_events().map(|e| (e.price, e.action)).collect();
+    assert_eq!(
+        actions,
+        [
+            (dec!(98), InferredAction::Joined),
+            (dec!(99), InferredAction::Reduced),
+            (dec!(101), InferredAction::Joined)
+        ]
+    );
+
+    // Session totals carry on from the saved ones; the restart counts as a reconnect
+    let resumed = SessionTracker::resumed(saved.session).stats(&restarted.counters());
+    assert_eq!((resumed.session_secs, resumed.connects, resumed.reconnects(), resumed.messages), (100, 2, 1, 10));
+    assert_eq!(resumed.updates_applied, 4 + restarted.counters().applied);
+    assert!((resumed.connected_ratio - 0.5).abs() < 0.01);
+    let _ = std::fs::remove_dir_all(&dir);
+}
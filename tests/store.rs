@@ -0,0 +1,104 @@
+use multi_exchange_l3_est::exchanges::{DepthUpdate, ExchangeType, OrderBookSnapshot, PriceLevel, Side, Trade};
+use multi_exchange_l3_est::orderbook::OrderBook;
+use multi_exchange_l3_est::store::EventStore;
+use rust_decimal::dec;
+use rusqlite::Connection;
+
+fn update(id: u64, bids: Vec<PriceLevel>, asks: Vec<PriceLevel>) -> DepthUpdate {
+    DepthUpdate {
+        event_time: 0,
+        transaction_time: 0,
+        symbol: String::new(),
+        capital_u: id,
+        small_u: id,
+        pu: id as i64 - 1,
+        bids,
+        asks,
+        recv_time: 0,
+    }
+}
+
+#[test]
+fn order_lifecycles_are_queryable_after_the_session() {
+    let path = std::env::temp_dir().join(format!("l3_store_{}.sqlite", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let mut store = EventStore::open(&path, ExchangeType::ALL[0], "DOGEUSDT").unwrap();
+    let mut book = OrderBook::new();
+    book.set_event_log(true);
+    book.apply_snapshot(&OrderBookSnapshot {
+        last_update_id: 1,
+        event_time: 0,
+        recv_time: 0,
+        bids: vec![PriceLevel::new(dec!(100), dec!(5))],
+        asks: vec![PriceLevel::new(dec!(101), dec!(4))],
+    });
+    let mut step = |book: &mut OrderBook, time: u64, update: DepthUpdate| {
+        book.set_clock(Some(time));
+        book.handle_update(update);
+        store.record(book.drain_events().collect());
+    };
+    // 3 join behind the snapshot's 5, a sell of 5 fills the front, then the 3 is cancelled
+    step(&mut book, 1000, update(2, vec![PriceLevel::new(dec!(100), dec!(8))], Vec::new()));
+    book.set_clock(Some(2000));
+    book.apply_trade(&Trade {
+        trade_id: 1,
+        price: dec!(100),
+        qty: dec!(5),
+        aggressor: Side::Ask,
+        timestamp: 2000,
+    });
+    step(&mut book, 2000, update(3, vec![PriceLevel::new(dec!(100), dec!(3))], Vec::new()));
+    step(&mut book, 3000, update(4, vec![PriceLevel::new(dec!(100), dec!(0))], Vec::new()));
+    // Still resting when a snapshot replaces the book
+    step(&mut book, 3500, update(5, Vec::new(), vec![PriceLevel::new(dec!(102), dec!(2))]));
+    store.reset(4000);
+    let session = store.session();
+    store.close();
+    assert_eq!((store.written(), store.dropped()), (4, 0));
+
+    let db = Connection::open(&path).unwrap();
+    let mut query = db
+        .prepare(
+            "SELECT side, price, initial_size, filled, created_at, removed_at, outcome, lifetime_ms
+             FROM order_lifetimes WHERE session = ?1 ORDER BY order_id",
+        )
+        .unwrap();
+    type Row = (String, f64, f64, f64, Option<i64>, Option<i64>, Option<String>, Option<i64>);
+    let orders: Vec<Row> = query
+        .query_map([session], |row| {
+            Ok((
+                row.get(0)?,
+                row.get(1)?,
+                row.get(2)?,
+                row.get(3)?,
+                row.get(4)?,
+                row.get(5)?,
+                row.get(6)?,
+                row.get(7)?,
+            ))
+        })
+        .unwrap()
+        .map(Result::unwrap)
+        .collect();
+    let outcome = |s: &str| Some(s.to_string());
+    assert_eq!(
+        orders,
+        [
+            // From the snapshot, so its join was not seen
+            ("bid".into(), 100.0, 5.0, 5.0, None, Some(2000), outcome("filled"), None),
+            ("bid".into(), 100.0, 3.0, 0.0, Some(1000), Some(3000), outcome("cancelled"), Some(2000)),
+            ("ask".into(), 102.0, 2.0, 0.0, Some(3500), Some(4000), outcome("reset"), Some(500)),
+        ]
+    );
+    let ended: Option<i64> = db.query_row("SELECT ended_at FROM sessions WHERE id = ?1", [session], |row| row.get(0)).unwrap();
+    assert!(ended.is_some());
+    let actions: Vec<String> = db
+        .prepare("SELECT action FROM events WHERE session = ?1 ORDER BY rowid")
+        .unwrap()
+        .query_map([session], |row| row.get(0))
+        .unwrap()
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(actions, ["joined", "filled", "removed", "joined"]);
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,48 @@
+use multi_exchange_l3_est::exchanges::{Side, Trade};
+use multi_exchange_l3_est::volume_profile::{ProfileLevel, VolumeProfile};
+use rust_decimal::dec;
+use rust_decimal::Decimal;
+
+fn trade(timestamp: u64, price: Decimal, qty: Decimal, aggressor: Side) -> Trade {
+    Trade {
+        trade_id: timestamp,
+        price,
+        qty,
+        aggressor,
+        timestamp,
+    }
+}
+
+fn level(buy: Decimal, sell: Decimal) -> ProfileLevel {
+    ProfileLevel { buy, sell }
+}
+
+#[test]
+fn volume_accumulates_by_bucket_and_leaves_with_the_window() {
+    let mut profile = VolumeProfile::new(60);
+    profile.record(&trade(1_000, dec!(100.03), dec!(2), Side::Bid));
+    profile.record(&trade(2_000, dec!(100.03), dec!(1), Side::Ask));
+    profile.record(&trade(30_000, dec!(100.07), dec!(5), Side::Ask));
+    profile.record(&trade(40_000, dec!(99.98), dec!(0), Side::Bid));
+    assert_eq!(profile.trades(), 3);
+    assert_eq!(
+        profile.levels().iter().map(|(&p, &l)| (p, l)).collect::<Vec<_>>(),
+        [(dec!(100.03), level(dec!(2), dec!(1))), (dec!(100.07), level(dec!(0), dec!(5)))]
+    );
+    assert_eq!(profile.point_of_control(), Some((dec!(100.07), level(dec!(0), dec!(5)))));
+
+    // Grouped like the book, prices floor to their bucket
+    profile.set_bucket(Some(dec!(0.05)));
+    assert_eq!(
+        profile.levels().iter().map(|(&p, &l)| (p, l)).collect::<Vec<_>>(),
+        [(dec!(100.00), level(dec!(2), dec!(1))), (dec!(100.05), level(dec!(0), dec!(5)))]
+    );
+
+    // A minute later the first two have left the window, and their bucket with them
+    profile.expire(62_500);
+    assert_eq!(profile.trades(), 1);
+    assert_eq!(profile.levels().keys().copied().collect::<Vec<_>>(), [dec!(100.05)]);
+    profile.set_window(10);
+    profile.expire(62_500);
+    assert!(profile.levels().is_empty() && profile.point_of_control().is_none());
+}